@@ -33,8 +33,9 @@ use clap::ValueEnum;
 /// );
 /// ```
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum Algorithm {
+	#[default]
 	UNSPECIFIED,
 	SHA1,
 	SHA2224,
@@ -54,6 +55,12 @@ pub enum Algorithm {
 	BLAKE2B,
 	BLAKE2S,
 	BLAKE3,
+	/// Amazon S3 ETag, the MD5 of a file or of the MD5s of its parts
+	#[value(name = "s3-etag")]
+	S3ETag,
+	/// BitTorrent v2 pieces root, the SHA-256 merkle root of 16 KiB blocks
+	#[value(name = "btv2")]
+	BTv2,
 }
 
 impl Algorithm {
@@ -62,8 +69,14 @@ impl Algorithm {
 		match *self {
 			Algorithm::CRC32 | Algorithm::XXH32 => 8,
 			Algorithm::XXH3 | Algorithm::XXH64 => 16,
-			Algorithm::MD5 => 32,
-			Algorithm::SHA3256 | Algorithm::SHA2256 | Algorithm::BLAKE2S | Algorithm::BLAKE3 | Algorithm::UNSPECIFIED => 64,
+			// Without the `-N` amount of parts of multipart ETags
+			Algorithm::MD5 | Algorithm::S3ETag => 32,
+			Algorithm::SHA3256
+			| Algorithm::SHA2256
+			| Algorithm::BLAKE2S
+			| Algorithm::BLAKE3
+			| Algorithm::BTv2
+			| Algorithm::UNSPECIFIED => 64,
 			Algorithm::SHA1 => 40,
 			Algorithm::SHA2224 | Algorithm::SHA3224 => 56,
 			Algorithm::SHA2384 | Algorithm::SHA3384 => 96,
@@ -104,6 +117,16 @@ impl Algorithm {
 			};
 		}
 
+		// Multipart S3 ETags are an MD5 followed by `-` and the amount of parts
+		if let Some((md5, parts)) = s.split_once('-')
+			&& md5.len() == 32
+			&& md5.chars().all(|c| c.is_ascii_hexdigit())
+			&& !parts.is_empty()
+			&& parts.chars().all(|c| c.is_ascii_digit())
+		{
+			return Algorithm::S3ETag;
+		}
+
 		// If the remaining characters are all hexadecimal, pick by length.
 		// When multiple algorithms share the same length prefer fast
 		// integrity-focused choices (e.g., `BLAKE3` for 64, `BLAKE2B` for
@@ -157,6 +180,8 @@ impl FromStr for Algorithm {
 			"blake2s" => Ok(Algorithm::BLAKE2S),
 			"blake3" => Ok(Algorithm::BLAKE3),
 			"whirlpool" => Ok(Algorithm::WhirlPool),
+			"s3-etag" | "s3etag" | "etag" => Ok(Algorithm::S3ETag),
+			"btv2" | "bt-v2" | "bittorrent-v2" => Ok(Algorithm::BTv2),
 			_ => Err(format!("\"{}\" is not a recognised hashing algorithm", s)),
 		}
 	}
@@ -0,0 +1,75 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Pieces roots of BitTorrent v2: the root of a merkle tree of the SHA-256
+//! hashes of the 16 KiB blocks of a file, the leaves past its end, up to a
+//! power of two, being zeros. Empty files have none, and get zeros.
+
+use sha2::{Digest, Sha256};
+
+use crate::hash_string;
+
+/// Bytes hashed into each leaf.
+pub(crate) const BLOCK_LEN: usize = 16 * 1024;
+
+hash_func!(
+	PiecesRoot { block: Sha256::new(), block_len: 0, leaves: Vec::new() },
+	|root: &mut PiecesRoot, buffer: &[u8]| root.update(buffer),
+	|root: PiecesRoot| hash_string(&root.finalize())
+);
+
+struct PiecesRoot {
+	/// SHA-256 of the block being read.
+	block: Sha256,
+	block_len: usize,
+	/// SHA-256s of the blocks read whole.
+	leaves: Vec<[u8; 32]>,
+}
+
+impl PiecesRoot {
+	fn update(&mut self, mut data: &[u8]) {
+		while !data.is_empty() {
+			let take = (BLOCK_LEN - self.block_len).min(data.len());
+			self.block.update(&data[..take]);
+			self.block_len += take;
+			data = &data[take..];
+			if self.block_len == BLOCK_LEN {
+				self.leaves.push(self.block.finalize_reset().into());
+				self.block_len = 0;
+			}
+		}
+	}
+
+	fn finalize(mut self) -> [u8; 32] {
+		if self.block_len > 0 {
+			self.leaves.push(self.block.finalize().into());
+		}
+		match self.leaves.is_empty() {
+			true => [0; 32],
+			false => merkle_root(&self.leaves, self.leaves.len().next_power_of_two(), [0; 32]),
+		}
+	}
+}
+
+/// Root of a merkle tree of SHA-256 hashes `width` leaves wide, the missing
+/// ones being `pad`.
+pub(crate) fn merkle_root(leaves: &[[u8; 32]], width: usize, pad: [u8; 32]) -> [u8; 32] {
+	let mut layer = leaves.to_vec();
+	layer.resize(width.max(layer.len()).max(1), pad);
+	while layer.len() > 1 {
+		layer = layer.chunks(2).map(|pair| Sha256::new().chain_update(pair[0]).chain_update(pair[1]).finalize().into()).collect();
+	}
+	layer[0]
+}
@@ -0,0 +1,91 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read-only memory maps of whole files.
+
+// `mmap()` has no safe wrapper in std
+#![allow(unsafe_code)]
+
+use std::{fs::File, io};
+
+/// A file mapped into memory, unmapped on drop.
+#[cfg(unix)]
+pub(super) struct Mmap {
+	ptr: *mut libc::c_void,
+	len: usize,
+}
+
+/// Map the whole file into memory. Fails for empty files.
+#[cfg(unix)]
+pub(super) fn map(file: &File) -> io::Result<Mmap> {
+	use std::{os::fd::AsRawFd, ptr};
+
+	let len = usize::try_from(file.metadata()?.len()).map_err(|_| io::Error::from(io::ErrorKind::FileTooLarge))?;
+	// SAFETY: a fresh private read-only mapping of a file descriptor we own,
+	// checked for failure before use
+	let ptr = unsafe { libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+	if ptr == libc::MAP_FAILED {
+		return Err(io::Error::last_os_error());
+	}
+	// SAFETY: `ptr` and `len` describe the mapping made above. It's only a
+	// hint, so failing is harmless
+	unsafe {
+		libc::madvise(ptr, len, libc::MADV_SEQUENTIAL);
+	}
+	Ok(Mmap { ptr, len })
+}
+
+#[cfg(unix)]
+impl std::ops::Deref for Mmap {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		// SAFETY: the mapping is readable, `len` bytes long and lives as long
+		// as `self`. Truncating the file while it's mapped is the same hazard
+		// every mmap user has
+		unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+	}
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+	fn drop(&mut self) {
+		// SAFETY: unmaps exactly the mapping made by `map()`, which no slice
+		// outlives
+		unsafe {
+			libc::munmap(self.ptr, self.len);
+		}
+	}
+}
+
+/// Memory maps aren't supported on this platform.
+#[cfg(not(unix))]
+pub(super) enum Mmap {}
+
+/// Memory maps aren't supported on this platform, so this always fails and
+/// files are read instead.
+#[cfg(not(unix))]
+pub(super) fn map(_file: &File) -> io::Result<Mmap> {
+	Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(not(unix))]
+impl std::ops::Deref for Mmap {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		match *self {}
+	}
+}
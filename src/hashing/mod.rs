@@ -15,14 +15,14 @@
 
 macro_rules! hash_func {
 	($ctx:expr, $update:expr, $convert:expr) => {
-		use std::io::Read;
+		use std::io::{self, Read};
 
-		pub fn hash<R: Read>(reader: &mut R) -> String {
-			let mut buffer = vec![0; 4096];
+		pub fn hash<R: Read>(reader: &mut R, buffer_len: usize) -> io::Result<String> {
+			let mut buffer = vec![0; buffer_len];
 
 			let mut ctx = $ctx;
 			loop {
-				let read = reader.read(&mut buffer[..]).unwrap();
+				let read = reader.read(&mut buffer[..])?;
 
 				if read == 0 {
 					break;
@@ -31,6 +31,12 @@ macro_rules! hash_func {
 				$update(&mut ctx, &buffer[..read]);
 			}
 
+			Ok($convert(ctx))
+		}
+
+		pub fn hash_bytes(bytes: &[u8]) -> String {
+			let mut ctx = $ctx;
+			$update(&mut ctx, bytes);
 			$convert(ctx)
 		}
 	};
@@ -38,25 +44,52 @@ macro_rules! hash_func {
 
 macro_rules! hash_func_write {
 	($ctx:expr, $convert:expr) => {
-		use std::io::{self, Read};
+		use std::io::{self, Read, Write};
+
+		pub fn hash<R: Read>(reader: &mut R, buffer_len: usize) -> io::Result<String> {
+			let mut buffer = vec![0; buffer_len];
+
+			let mut ctx = $ctx;
+			loop {
+				let read = reader.read(&mut buffer[..])?;
+
+				if read == 0 {
+					break;
+				}
+
+				ctx.write_all(&buffer[..read])?;
+			}
+
+			Ok($convert(ctx))
+		}
 
-		pub fn hash<R: Read>(reader: &mut R) -> String {
+		pub fn hash_bytes(bytes: &[u8]) -> String {
 			let mut ctx = $ctx;
-			io::copy(reader, &mut ctx).unwrap();
+			ctx.write_all(bytes).expect("Hashers don't fail to write");
 			$convert(ctx)
 		}
 	};
 }
 
-use std::{fmt::Write, fs::File, io::Read, path::Path};
+use std::{
+	fmt::Write,
+	fs::{File, Metadata},
+	io::{self, Read, Seek, SeekFrom},
+	path::Path,
+	time::SystemTime,
+};
 
 use super::Algorithm;
+use crate::utilities::long_path;
 
 mod blake2b;
 mod blake2s;
 mod blake3;
+mod bt_v2;
 mod crc32;
 mod md5;
+mod mmap;
+mod open;
 mod sha1;
 mod sha2_224;
 mod sha2_256;
@@ -65,38 +98,258 @@ mod sha2_512;
 mod sha3_224;
 mod sha3_256;
 mod sha3_384;
+mod s3_etag;
 mod sha3_512;
+mod sparse;
+mod ssdeep;
+mod text;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 mod whirlpool;
 mod xxh3;
 mod xxh32;
 mod xxh64;
 
+pub(crate) use bt_v2::{BLOCK_LEN, merkle_root};
+pub(crate) use ssdeep::{MIN_BLOCK_SIZE, SPAMSUM_LENGTH};
+
+/// How files are read for hashing.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct HashOptions {
+	/// Let reading a file update its access time. Otherwise it's opened with
+	/// `O_NOATIME` where the platform and permissions allow it.
+	pub update_atime: bool,
+	/// Evict each file from the page cache once it's hashed, where the
+	/// platform allows it.
+	pub drop_caches: bool,
+	/// Hash files by mapping them into memory instead of reading them, where
+	/// the platform allows it.
+	pub mmap: bool,
+	/// Read files through io_uring, keeping several reads in flight. Only
+	/// with the `io-uring` feature, on Linux.
+	#[cfg(feature = "io-uring")]
+	pub io_uring: bool,
+	/// Size of the parts of S3 multipart uploads, for S3 ETags. 8 MiB if 0.
+	pub part_size: u64,
+	/// Hash files as text, with CRLF line endings read as LF.
+	pub text_mode: bool,
+}
+
 /// Hash the specified file using the specified hashing algorithm.
+///
+/// Holes in sparse files are hashed as zeros without being read, where the
+/// platform can find them.
 pub fn hash_file(algo: Algorithm, path: &Path) -> String {
-	hash_reader(algo, &mut File::open(path).unwrap())
+	try_hash_file(algo, path, &HashOptions::default()).unwrap()
+}
+
+/// Like `hash_file()`, but reading the file as set by `options`, and
+/// returning an error if it can't be opened or read instead of panicking.
+pub fn try_hash_file(algo: Algorithm, path: &Path, options: &HashOptions) -> io::Result<String> {
+	hash_file_checked(algo, path, options).map(|(hash, _)| hash)
+}
+
+/// Like `try_hash_file()`, also telling whether the file's size and
+/// modification time stayed the same while it was read.
+pub(crate) fn hash_file_checked(algo: Algorithm, path: &Path, options: &HashOptions) -> io::Result<(String, bool)> {
+	let file = open::open_file(&long_path(path), options)?;
+	let before = file.metadata()?;
+	// Files that fail to map, like empty ones, are read instead, as is text
+	let hash = if options.mmap
+		&& !options.text_mode
+		&& let Ok(map) = mmap::map(&file)
+	{
+		hash_bytes(algo, &map, options.part_size)
+	} else {
+		open::advise_sequential(&file);
+		hash_buffered(algo, &mut reader(&file, options)?, buffer_len(before.len()), options.part_size)?
+	};
+	if options.drop_caches {
+		open::drop_cache(&file);
+	}
+	Ok((hash, stamp(&before) == stamp(&file.metadata()?)))
+}
+
+/// ssdeep digest of the specified file, read as set by `options`, which is
+/// alike for files with much of their content in common.
+///
+/// Short digests are worked out again at a smaller block size, which reads
+/// the file again.
+pub fn try_fuzzy_hash_file(path: &Path, options: &HashOptions) -> io::Result<String> {
+	let file = open::open_file(&long_path(path), options)?;
+	open::advise_sequential(&file);
+	let len = file.metadata()?.len();
+	let digest = ssdeep::hash(
+		len,
+		|| {
+			(&file).seek(SeekFrom::Start(0))?;
+			reader(&file, options)
+		},
+		buffer_len(len),
+	)?;
+	if options.drop_caches {
+		open::drop_cache(&file);
+	}
+	Ok(digest)
+}
+
+/// ssdeep digest of the specified bytes.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(quickdash::fuzzy_hash_bytes(b""), "3::");
+/// ```
+pub fn fuzzy_hash_bytes(bytes: &[u8]) -> String {
+	ssdeep::hash_bytes(bytes)
 }
 
 /// Hash the specified byte stream using the specified hashing algorithm.
 pub fn hash_reader<R: Read>(algo: Algorithm, data: &mut R) -> String {
+	try_hash_reader(algo, data).unwrap()
+}
+
+/// Like `hash_reader()`, but returns an error if the stream can't be read
+/// instead of panicking.
+pub fn try_hash_reader<R: Read>(algo: Algorithm, data: &mut R) -> io::Result<String> {
+	hash_buffered(algo, data, MIN_BUFFER_LEN, 0)
+}
+
+/// Like `try_hash_reader()`, but hashing as set by `options`.
+pub(crate) fn try_hash_reader_with<R: Read>(algo: Algorithm, data: &mut R, options: &HashOptions) -> io::Result<String> {
+	hash_buffered(algo, data, MIN_BUFFER_LEN, options.part_size)
+}
+
+/// Hash the specified byte stream, reading it `buffer_len` bytes at a time,
+/// in parts of `part_size` bytes for S3 ETags.
+fn hash_buffered<R: Read>(algo: Algorithm, data: &mut R, buffer_len: usize, part_size: u64) -> io::Result<String> {
+	match algo {
+		Algorithm::CRC32 => crc32::hash(data, buffer_len),
+		Algorithm::SHA1 => sha1::hash(data, buffer_len),
+		Algorithm::SHA2224 => sha2_224::hash(data, buffer_len),
+		Algorithm::SHA2256 => sha2_256::hash(data, buffer_len),
+		Algorithm::SHA2384 => sha2_384::hash(data, buffer_len),
+		Algorithm::SHA2512 => sha2_512::hash(data, buffer_len),
+		Algorithm::SHA3224 => sha3_224::hash(data, buffer_len),
+		Algorithm::SHA3256 => sha3_256::hash(data, buffer_len),
+		Algorithm::SHA3384 => sha3_384::hash(data, buffer_len),
+		Algorithm::SHA3512 => sha3_512::hash(data, buffer_len),
+		Algorithm::MD5 => md5::hash(data, buffer_len),
+		Algorithm::XXH64 => xxh64::hash(data, buffer_len),
+		Algorithm::XXH32 => xxh32::hash(data, buffer_len),
+		Algorithm::XXH3 => xxh3::hash(data, buffer_len),
+		Algorithm::BLAKE2B => blake2b::hash(data, buffer_len),
+		Algorithm::BLAKE2S => blake2s::hash(data, buffer_len),
+	 	Algorithm::UNSPECIFIED | Algorithm::BLAKE3 => blake3::hash(data, buffer_len),
+		Algorithm::WhirlPool => whirlpool::hash(data, buffer_len),
+		Algorithm::S3ETag => s3_etag::hash(data, buffer_len, part_size),
+		Algorithm::BTv2 => bt_v2::hash(data, buffer_len),
+	}
+}
+
+/// Read a file the way `try_hash_file()` does, mapped or through io_uring as
+/// set by `options`, handing it to `chunk` in pieces of up to `buffer_len()`
+/// bytes until it returns `false`.
+///
+/// Returns whether the file's size and modification time stayed the same
+/// while it was read.
+pub(crate) fn read_file_chunks<F: FnMut(Vec<u8>) -> bool>(
+	path: &Path,
+	options: &HashOptions,
+	mut chunk: F,
+) -> io::Result<bool> {
+	let file = open::open_file(&long_path(path), options)?;
+	open::advise_sequential(&file);
+	let before = file.metadata()?;
+	let chunk_len = buffer_len(before.len());
+	// Mapped files are handed over a chunk at a time all the same
+	if options.mmap
+		&& !options.text_mode
+		&& let Ok(map) = mmap::map(&file)
+	{
+		for part in map.chunks(chunk_len) {
+			if !chunk(part.to_vec()) {
+				break;
+			}
+		}
+	} else {
+		let mut reader = reader(&file, options)?;
+		loop {
+			let mut buffer = vec![0; chunk_len];
+			let read = match reader.read(&mut buffer) {
+				Ok(0) => break,
+				Ok(read) => read,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+				Err(err) => return Err(err),
+			};
+			buffer.truncate(read);
+			if !chunk(buffer) {
+				break;
+			}
+		}
+	}
+	if options.drop_caches {
+		open::drop_cache(&file);
+	}
+	Ok(stamp(&before) == stamp(&file.metadata()?))
+}
+
+/// Size and modification time of a file.
+fn stamp(metadata: &Metadata) -> (u64, Option<SystemTime>) {
+	(metadata.len(), metadata.modified().ok())
+}
+
+/// Reader of an opened file, as set by `options`.
+fn reader<'a>(file: &'a File, options: &HashOptions) -> io::Result<Box<dyn Read + 'a>> {
+	// Kernels without io_uring get regular reads
+	#[cfg(all(feature = "io-uring", target_os = "linux"))]
+	if options.io_uring
+		&& let Ok(reader) = uring::UringReader::new(file)
+	{
+		return Ok(text::text_reader(Box::new(reader), options));
+	}
+	Ok(text::text_reader(sparse::reader(file)?, options))
+}
+
+/// Smallest read buffer, also used for streams of unknown length.
+const MIN_BUFFER_LEN: usize = 4 << 10;
+
+/// Largest read buffer.
+const MAX_BUFFER_LEN: usize = 4 << 20;
+
+/// Size of the buffer to read a file of `len` bytes with: big enough to read
+/// small files in one go, so trees of tiny files don't cost extra syscalls,
+/// and a few MiB for large ones, for fewer round trips on network mounts.
+fn buffer_len(len: u64) -> usize {
+	(len.min(MAX_BUFFER_LEN as u64) as usize)
+		.next_power_of_two()
+		.clamp(MIN_BUFFER_LEN, MAX_BUFFER_LEN)
+}
+
+/// Hash the specified bytes in one go using the specified hashing algorithm,
+/// in parts of `part_size` bytes for S3 ETags.
+fn hash_bytes(algo: Algorithm, data: &[u8], part_size: u64) -> String {
 	match algo {
-		Algorithm::CRC32 => crc32::hash(data),
-		Algorithm::SHA1 => sha1::hash(data),
-		Algorithm::SHA2224 => sha2_224::hash(data),
-		Algorithm::SHA2256 => sha2_256::hash(data),
-		Algorithm::SHA2384 => sha2_384::hash(data),
-		Algorithm::SHA2512 => sha2_512::hash(data),
-		Algorithm::SHA3224 => sha3_224::hash(data),
-		Algorithm::SHA3256 => sha3_256::hash(data),
-		Algorithm::SHA3384 => sha3_384::hash(data),
-		Algorithm::SHA3512 => sha3_512::hash(data),
-		Algorithm::MD5 => md5::hash(data),
-		Algorithm::XXH64 => xxh64::hash(data),
-		Algorithm::XXH32 => xxh32::hash(data),
-		Algorithm::XXH3 => xxh3::hash(data),
-		Algorithm::BLAKE2B => blake2b::hash(data),
-		Algorithm::BLAKE2S => blake2s::hash(data),
-	 	Algorithm::UNSPECIFIED | Algorithm::BLAKE3 => blake3::hash(data),
-		Algorithm::WhirlPool => whirlpool::hash(data),
+		Algorithm::CRC32 => crc32::hash_bytes(data),
+		Algorithm::SHA1 => sha1::hash_bytes(data),
+		Algorithm::SHA2224 => sha2_224::hash_bytes(data),
+		Algorithm::SHA2256 => sha2_256::hash_bytes(data),
+		Algorithm::SHA2384 => sha2_384::hash_bytes(data),
+		Algorithm::SHA2512 => sha2_512::hash_bytes(data),
+		Algorithm::SHA3224 => sha3_224::hash_bytes(data),
+		Algorithm::SHA3256 => sha3_256::hash_bytes(data),
+		Algorithm::SHA3384 => sha3_384::hash_bytes(data),
+		Algorithm::SHA3512 => sha3_512::hash_bytes(data),
+		Algorithm::MD5 => md5::hash_bytes(data),
+		Algorithm::XXH64 => xxh64::hash_bytes(data),
+		Algorithm::XXH32 => xxh32::hash_bytes(data),
+		Algorithm::XXH3 => xxh3::hash_bytes(data),
+		Algorithm::BLAKE2B => blake2b::hash_bytes(data),
+		Algorithm::BLAKE2S => blake2s::hash_bytes(data),
+		Algorithm::UNSPECIFIED | Algorithm::BLAKE3 => blake3::hash_bytes(data),
+		Algorithm::WhirlPool => whirlpool::hash_bytes(data),
+		Algorithm::S3ETag => s3_etag::hash_bytes(data, part_size),
+		Algorithm::BTv2 => bt_v2::hash_bytes(data),
 	}
 }
 
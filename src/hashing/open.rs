@@ -0,0 +1,81 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Opening files for hashing, and hinting the kernel about how they're read.
+
+// `posix_fadvise()` has no safe wrapper in std
+#![allow(unsafe_code)]
+
+use std::{fs::File, io, path::Path};
+
+use super::HashOptions;
+
+/// Open a file for hashing, without updating its access time unless
+/// `options.update_atime` is set.
+///
+/// `O_NOATIME` is only allowed on files owned by the user, so other files are
+/// opened normally.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn open_file(path: &Path, options: &HashOptions) -> io::Result<File> {
+	use std::{fs::OpenOptions, os::unix::fs::OpenOptionsExt};
+
+	if options.update_atime {
+		return File::open(path);
+	}
+	match OpenOptions::new().read(true).custom_flags(libc::O_NOATIME).open(path) {
+		Err(err) if err.raw_os_error() == Some(libc::EPERM) => File::open(path),
+		result => result,
+	}
+}
+
+/// Open a file for hashing. Access times are left to the filesystem on this
+/// platform.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(super) fn open_file(path: &Path, _options: &HashOptions) -> io::Result<File> {
+	File::open(path)
+}
+
+/// Tell the kernel the file is about to be read from start to end, so it
+/// reads ahead more aggressively.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn advise_sequential(file: &File) {
+	advise(file, libc::POSIX_FADV_SEQUENTIAL);
+}
+
+/// Tell the kernel the file is done with, so its pages can be evicted from
+/// the page cache instead of pushing out those of other workloads.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn drop_cache(file: &File) {
+	advise(file, libc::POSIX_FADV_DONTNEED);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn advise(file: &File, advice: libc::c_int) {
+	use std::os::fd::AsRawFd;
+
+	// SAFETY: plain syscall on a file descriptor we own, no memory involved.
+	// It's only a hint, so failing is harmless.
+	unsafe {
+		libc::posix_fadvise(file.as_raw_fd(), 0, 0, advice);
+	}
+}
+
+/// Read-ahead hints aren't supported on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(super) fn advise_sequential(_file: &File) {}
+
+/// Page cache hints aren't supported on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(super) fn drop_cache(_file: &File) {}
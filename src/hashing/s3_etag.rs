@@ -0,0 +1,101 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! ETags of objects uploaded to Amazon S3 the way the AWS CLI does it: in
+//! parts of `part_size` bytes once they're at least that big, whose ETag is
+//! the MD5 of the MD5s of the parts followed by `-` and the amount of parts,
+//! and in one go otherwise, whose ETag is their MD5.
+
+use std::io::{self, Read};
+
+use md5::{Digest, Md5};
+
+use crate::hash_string;
+
+/// Part size of the AWS CLI, used when none is set.
+pub const DEFAULT_PART_SIZE: u64 = 8 << 20;
+
+pub fn hash<R: Read>(reader: &mut R, buffer_len: usize, part_size: u64) -> io::Result<String> {
+	let mut buffer = vec![0; buffer_len];
+
+	let mut etag = ETag::new(part_size);
+	loop {
+		let read = reader.read(&mut buffer[..])?;
+
+		if read == 0 {
+			break;
+		}
+
+		etag.update(&buffer[..read]);
+	}
+
+	Ok(etag.finalize())
+}
+
+pub fn hash_bytes(bytes: &[u8], part_size: u64) -> String {
+	let mut etag = ETag::new(part_size);
+	etag.update(bytes);
+	etag.finalize()
+}
+
+struct ETag {
+	part_size: u64,
+	/// MD5 of the part being read.
+	part: Md5,
+	part_len: u64,
+	/// MD5s of the parts read whole.
+	digests: Md5,
+	parts: usize,
+}
+
+impl ETag {
+	fn new(part_size: u64) -> Self {
+		ETag {
+			part_size: match part_size {
+				0 => DEFAULT_PART_SIZE,
+				part_size => part_size,
+			},
+			part: Md5::new(),
+			part_len: 0,
+			digests: Md5::new(),
+			parts: 0,
+		}
+	}
+
+	fn update(&mut self, mut data: &[u8]) {
+		while !data.is_empty() {
+			let take = (self.part_size - self.part_len).min(data.len() as u64) as usize;
+			self.part.update(&data[..take]);
+			self.part_len += take as u64;
+			data = &data[take..];
+			if self.part_len == self.part_size {
+				self.digests.update(self.part.finalize_reset());
+				self.part_len = 0;
+				self.parts += 1;
+			}
+		}
+	}
+
+	fn finalize(mut self) -> String {
+		if self.parts == 0 {
+			return hash_string(&self.part.finalize());
+		}
+		if self.part_len > 0 {
+			self.digests.update(self.part.finalize());
+			self.parts += 1;
+		}
+		format!("{}-{}", hash_string(&self.digests.finalize()), self.parts)
+	}
+}
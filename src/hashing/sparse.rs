@@ -0,0 +1,133 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Reading sparse files without reading their holes from disk.
+//!
+//! Holes are found with `SEEK_DATA`/`SEEK_HOLE` and fed to the hasher as
+//! zeros, so the hash is the same as that of a plain read. Where that isn't
+//! supported, files are read as-is; on Windows, where holes would be found
+//! with `FSCTL_QUERY_ALLOCATED_RANGES`, too.
+
+// `lseek()` with `SEEK_DATA`/`SEEK_HOLE` has no safe wrapper in std
+#![allow(unsafe_code)]
+
+use std::{
+	fs::File,
+	io::{self, Read},
+};
+
+/// Read an opened file for hashing, skipping over its holes if it's sparse.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd"))]
+pub(super) fn reader(file: &File) -> io::Result<Box<dyn Read + '_>> {
+	use std::os::unix::fs::MetadataExt;
+
+	let metadata = file.metadata()?;
+	// Fully allocated files have nothing to skip
+	if metadata.blocks() * 512 >= metadata.len() {
+		return Ok(Box::new(file));
+	}
+	Ok(Box::new(SparseReader {
+		file,
+		position: 0,
+		len: metadata.len(),
+		region_end: 0,
+		in_hole: false,
+	}))
+}
+
+/// Read an opened file for hashing. Sparse files are read as-is on this
+/// platform.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd")))]
+pub(super) fn reader(file: &File) -> io::Result<Box<dyn Read + '_>> {
+	Ok(Box::new(file))
+}
+
+/// Reader yielding zeros for the holes of a sparse file instead of reading
+/// them.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd"))]
+struct SparseReader<'a> {
+	file: &'a File,
+	position: u64,
+	len: u64,
+	/// End of the data or hole `position` is in.
+	region_end: u64,
+	in_hole: bool,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd"))]
+impl SparseReader<'_> {
+	/// Find the data or hole region starting at `position`.
+	fn next_region(&mut self) -> io::Result<()> {
+		use std::io::{Seek, SeekFrom};
+
+		match self.seek(libc::SEEK_DATA) {
+			Ok(data) if data > self.position => {
+				self.in_hole = true;
+				self.region_end = data.min(self.len);
+			}
+			Ok(_) => {
+				self.in_hole = false;
+				self.region_end = self.seek(libc::SEEK_HOLE)?.min(self.len);
+			}
+			// No data past `position`, the rest is a hole
+			Err(err) if err.raw_os_error() == Some(libc::ENXIO) => {
+				self.in_hole = true;
+				self.region_end = self.len;
+			}
+			Err(err) => return Err(err),
+		}
+		// Both seeks move the file offset
+		self.file.seek(SeekFrom::Start(self.position))?;
+		Ok(())
+	}
+
+	fn seek(&self, whence: libc::c_int) -> io::Result<u64> {
+		use std::os::fd::AsRawFd;
+
+		// SAFETY: plain syscall on a file descriptor we own, no memory involved
+		let offset = unsafe { libc::lseek(self.file.as_raw_fd(), self.position as libc::off_t, whence) };
+		if offset < 0 {
+			Err(io::Error::last_os_error())
+		} else {
+			Ok(offset as u64)
+		}
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd"))]
+impl Read for SparseReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.position >= self.len || buf.is_empty() {
+			return Ok(0);
+		}
+		if self.position >= self.region_end {
+			self.next_region()?;
+		}
+
+		let wanted = buf.len().min((self.region_end - self.position) as usize);
+		let read = if self.in_hole {
+			buf[..wanted].fill(0);
+			wanted
+		} else {
+			self.file.read(&mut buf[..wanted])?
+		};
+		// The file shrank under us
+		if read == 0 {
+			return Ok(0);
+		}
+		self.position += read as u64;
+		Ok(read)
+	}
+}
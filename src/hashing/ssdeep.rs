@@ -0,0 +1,188 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! ssdeep context triggered piecewise hashes, which are alike for files with
+//! much of their content in common, unlike cryptographic hashes.
+//!
+//! A rolling hash over the last few bytes splits the data wherever it hits a
+//! value depending on the block size, and each piece adds a base64 character
+//! to the digest. The block size is picked so the digest has between 32 and
+//! 64 characters, which may take reading the data again with a smaller one.
+//! Digests are `block size:digest:digest at twice the block size`.
+
+use std::io::{self, Read};
+
+const ROLLING_WINDOW: usize = 7;
+pub(crate) const MIN_BLOCK_SIZE: u64 = 3;
+pub(crate) const SPAMSUM_LENGTH: usize = 64;
+const HASH_PRIME: u32 = 0x0100_0193;
+const HASH_INIT: u32 = 0x2802_1967;
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Digest of data `len` bytes long, read from the readers `reread` opens at
+/// its start, `buffer_len` bytes at a time.
+pub fn hash<'a, F: FnMut() -> io::Result<Box<dyn Read + 'a>>>(len: u64, mut reread: F, buffer_len: usize) -> io::Result<String> {
+	let mut buffer = vec![0; buffer_len];
+	let mut block_size = start_block_size(len);
+	loop {
+		let mut digest = Digest::new(block_size);
+		let mut reader = reread()?;
+		loop {
+			let read = match reader.read(&mut buffer) {
+				Ok(0) => break,
+				Ok(read) => read,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+				Err(err) => return Err(err),
+			};
+			digest.update(&buffer[..read]);
+		}
+		if let Some(digest) = digest.finalize() {
+			return Ok(digest);
+		}
+		block_size /= 2;
+	}
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+	let mut block_size = start_block_size(bytes.len() as u64);
+	loop {
+		let mut digest = Digest::new(block_size);
+		digest.update(bytes);
+		if let Some(digest) = digest.finalize() {
+			return digest;
+		}
+		block_size /= 2;
+	}
+}
+
+/// Smallest block size giving a digest of at most `SPAMSUM_LENGTH`
+/// characters, going by the length of the data.
+fn start_block_size(len: u64) -> u64 {
+	let mut block_size = MIN_BLOCK_SIZE;
+	while block_size * (SPAMSUM_LENGTH as u64) < len {
+		block_size *= 2;
+	}
+	block_size
+}
+
+/// Digest of data at one block size.
+struct Digest {
+	block_size: u64,
+	roll: Roll,
+	first: Piecewise,
+	second: Piecewise,
+}
+
+impl Digest {
+	fn new(block_size: u64) -> Self {
+		Digest {
+			block_size,
+			roll: Roll::default(),
+			first: Piecewise::new(SPAMSUM_LENGTH - 1),
+			second: Piecewise::new(SPAMSUM_LENGTH / 2 - 1),
+		}
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		for &byte in data {
+			self.first.update(byte);
+			self.second.update(byte);
+			let sum = self.roll.update(byte) as u64;
+			if sum % self.block_size == self.block_size - 1 {
+				self.first.split();
+				if sum % (self.block_size * 2) == self.block_size * 2 - 1 {
+					self.second.split();
+				}
+			}
+		}
+	}
+
+	/// The digest, or `None` if it's too short to tell files apart by and a
+	/// smaller block size should be tried.
+	fn finalize(self) -> Option<String> {
+		if self.block_size > MIN_BLOCK_SIZE && self.first.digest.len() < SPAMSUM_LENGTH / 2 {
+			return None;
+		}
+		let ended = self.roll.sum() != 0;
+		Some(format!("{}:{}:{}", self.block_size, self.first.finalize(ended), self.second.finalize(ended)))
+	}
+}
+
+/// Characters of a digest, one for each piece of the data.
+struct Piecewise {
+	digest: String,
+	/// Longest the digest gets before its last character.
+	limit: usize,
+	hash: u32,
+	/// Character of the piece that would end the data, once at the limit.
+	pending: Option<char>,
+}
+
+impl Piecewise {
+	fn new(limit: usize) -> Self {
+		Piecewise { digest: String::new(), limit, hash: HASH_INIT, pending: None }
+	}
+
+	fn update(&mut self, byte: u8) {
+		self.hash = self.hash.wrapping_mul(HASH_PRIME) ^ byte as u32;
+	}
+
+	fn split(&mut self) {
+		let piece = B64[self.hash as usize % 64] as char;
+		match self.digest.len() < self.limit {
+			true => {
+				self.digest.push(piece);
+				self.hash = HASH_INIT;
+			}
+			// The rest of the data goes into the last character
+			false => self.pending = Some(piece),
+		}
+	}
+
+	/// The digest, with the last piece if the data didn't end on a split.
+	fn finalize(mut self, ended: bool) -> String {
+		match ended {
+			true => self.digest.push(B64[self.hash as usize % 64] as char),
+			false => self.digest.extend(self.pending),
+		}
+		self.digest
+	}
+}
+
+/// Rolling hash of the last `ROLLING_WINDOW` bytes.
+#[derive(Default)]
+struct Roll {
+	window: [u8; ROLLING_WINDOW],
+	h1: u32,
+	h2: u32,
+	h3: u32,
+	n: usize,
+}
+
+impl Roll {
+	fn update(&mut self, byte: u8) -> u32 {
+		let byte32 = byte as u32;
+		self.h2 = self.h2.wrapping_sub(self.h1).wrapping_add(ROLLING_WINDOW as u32 * byte32);
+		self.h1 = self.h1.wrapping_add(byte32).wrapping_sub(self.window[self.n] as u32);
+		self.window[self.n] = byte;
+		self.n = (self.n + 1) % ROLLING_WINDOW;
+		self.h3 = (self.h3 << 5) ^ byte32;
+		self.sum()
+	}
+
+	fn sum(&self) -> u32 {
+		self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+	}
+}
@@ -0,0 +1,99 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Reading files as text, with CRLF line endings turned into LF, so a file
+//! hashes the same whether it was last saved on Windows or on Unix.
+
+use std::{
+	io::{self, Read},
+	mem,
+};
+
+use super::HashOptions;
+
+/// `reader` read as set by `options`: as text with `text_mode`, or as-is.
+pub(super) fn text_reader<'a>(reader: Box<dyn Read + 'a>, options: &HashOptions) -> Box<dyn Read + 'a> {
+	match options.text_mode {
+		true => Box::new(TextReader::new(reader)),
+		false => reader,
+	}
+}
+
+/// Reader turning the CRLF line endings of another into LF. Lone CRs are
+/// left as they are.
+pub(super) struct TextReader<R> {
+	inner: R,
+	buffer: Vec<u8>,
+	/// What was read, converted, and how much of it was handed out.
+	converted: Vec<u8>,
+	position: usize,
+	/// Whether the last byte read was a CR, held back until it's known
+	/// whether an LF follows.
+	cr: bool,
+}
+
+impl<R: Read> TextReader<R> {
+	pub(super) fn new(inner: R) -> Self {
+		TextReader {
+			inner,
+			buffer: vec![0; super::MIN_BUFFER_LEN],
+			converted: Vec::with_capacity(super::MIN_BUFFER_LEN + 1),
+			position: 0,
+			cr: false,
+		}
+	}
+
+	/// Read and convert more. Returns `false` at the end.
+	fn fill(&mut self) -> io::Result<bool> {
+		self.converted.clear();
+		self.position = 0;
+		let read = self.inner.read(&mut self.buffer)?;
+		if read == 0 {
+			if mem::take(&mut self.cr) {
+				self.converted.push(b'\r');
+				return Ok(true);
+			}
+			return Ok(false);
+		}
+		for &byte in &self.buffer[..read] {
+			if self.cr && byte != b'\n' {
+				self.converted.push(b'\r');
+			}
+			self.cr = byte == b'\r';
+			if !self.cr {
+				self.converted.push(byte);
+			}
+		}
+		Ok(true)
+	}
+}
+
+impl<R: Read> Read for TextReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		// A read of a lone CR converts to nothing yet
+		while self.position == self.converted.len() {
+			if !self.fill()? {
+				return Ok(0);
+			}
+		}
+		let len = buf.len().min(self.converted.len() - self.position);
+		buf[..len].copy_from_slice(&self.converted[self.position..self.position + len]);
+		self.position += len;
+		Ok(len)
+	}
+}
@@ -0,0 +1,187 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Reading files through io_uring, with several reads of a file in flight
+//! ahead of the hasher so that I/O latency overlaps with hashing. Reads don't
+//! run ahead into the next file; each thread reuses one ring and its buffers
+//! from file to file.
+
+// Submitting reads hands buffers over to the kernel
+#![allow(unsafe_code)]
+
+use std::{
+	cell::Cell,
+	fs::File,
+	io::{self, Read},
+	os::{fd::AsRawFd, unix::fs::FileExt},
+};
+
+use io_uring::{IoUring, opcode, types};
+
+/// Amount of reads kept in flight.
+const QUEUE_DEPTH: usize = 8;
+
+/// Size of each read.
+const CHUNK_LEN: usize = 256 << 10;
+
+thread_local! {
+	/// Ring and buffers left by the last reader on this thread, for the next
+	/// one to take instead of setting up its own.
+	static SPARE: Cell<Option<(IoUring, Vec<Vec<u8>>)>> = const { Cell::new(None) };
+}
+
+/// Reader submitting reads of the next `QUEUE_DEPTH` chunks of a file while
+/// the current one is being hashed.
+///
+/// Chunk `n` is read into buffer `n % QUEUE_DEPTH`, which is only reused once
+/// chunk `n` has been handed out.
+pub(super) struct UringReader<'a> {
+	file: &'a File,
+	/// Only taken when dropped.
+	ring: Option<IoUring>,
+	buffers: Vec<Vec<u8>>,
+	/// Length read into each buffer, once its read completed.
+	done: Vec<Option<usize>>,
+	len: u64,
+	/// Chunk being handed out, and how much of it was.
+	current: u64,
+	consumed: usize,
+	/// Chunks whose read was submitted.
+	submitted: u64,
+	in_flight: usize,
+}
+
+impl<'a> UringReader<'a> {
+	pub(super) fn new(file: &'a File) -> io::Result<Self> {
+		let (ring, buffers) = match SPARE.take() {
+			Some(spare) => spare,
+			None => (IoUring::new(QUEUE_DEPTH as u32)?, vec![vec![0; CHUNK_LEN]; QUEUE_DEPTH]),
+		};
+		let mut reader = UringReader {
+			file,
+			ring: Some(ring),
+			buffers,
+			done: vec![None; QUEUE_DEPTH],
+			len: file.metadata()?.len(),
+			current: 0,
+			consumed: 0,
+			submitted: 0,
+			in_flight: 0,
+		};
+		reader.submit_ahead()?;
+		Ok(reader)
+	}
+
+	fn ring(&mut self) -> &mut IoUring {
+		self.ring.as_mut().expect("the ring is only taken when dropped")
+	}
+
+	/// Length of chunk `n`, as long as the file doesn't change size.
+	fn chunk_len(&self, n: u64) -> usize {
+		(self.len - n * CHUNK_LEN as u64).min(CHUNK_LEN as u64) as usize
+	}
+
+	/// Submit reads for the chunks after the current one whose buffers are
+	/// free.
+	fn submit_ahead(&mut self) -> io::Result<()> {
+		while self.submitted < self.current + QUEUE_DEPTH as u64 && self.submitted * (CHUNK_LEN as u64) < self.len {
+			let slot = (self.submitted % QUEUE_DEPTH as u64) as usize;
+			self.done[slot] = None;
+			let entry = opcode::Read::new(
+				types::Fd(self.file.as_raw_fd()),
+				self.buffers[slot].as_mut_ptr(),
+				CHUNK_LEN as u32,
+			)
+			.offset(self.submitted * CHUNK_LEN as u64)
+			.build()
+			.user_data(self.submitted);
+			// SAFETY: the buffer is neither touched nor freed until the read's
+			// completion is reaped, see `Drop`
+			unsafe { self.ring().submission().push(&entry) }.map_err(|_| io::Error::other("io_uring queue is full"))?;
+			self.submitted += 1;
+			self.in_flight += 1;
+		}
+		self.ring().submit()?;
+		Ok(())
+	}
+
+	/// Wait for at least one read to complete.
+	fn reap(&mut self) -> io::Result<()> {
+		self.ring().submit_and_wait(1)?;
+		let completed: Vec<(u64, i32)> = self.ring().completion().map(|cqe| (cqe.user_data(), cqe.result())).collect();
+		self.in_flight -= completed.len();
+		for (chunk, result) in completed {
+			if result < 0 {
+				return Err(io::Error::from_raw_os_error(-result));
+			}
+			self.done[(chunk % QUEUE_DEPTH as u64) as usize] = Some(result as usize);
+		}
+		Ok(())
+	}
+}
+
+impl Read for UringReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.current * (CHUNK_LEN as u64) >= self.len || buf.is_empty() {
+			return Ok(0);
+		}
+		let slot = (self.current % QUEUE_DEPTH as u64) as usize;
+		while self.done[slot].is_none() {
+			self.reap()?;
+		}
+
+		// Reads may come up short, read the rest of the chunk directly
+		let expected = self.chunk_len(self.current);
+		let mut available = self.done[slot].unwrap_or_default();
+		if available < expected {
+			let offset = self.current * CHUNK_LEN as u64 + available as u64;
+			self.file.read_exact_at(&mut self.buffers[slot][available..expected], offset)?;
+			available = expected;
+			self.done[slot] = Some(available);
+		}
+
+		let read = buf.len().min(available - self.consumed);
+		buf[..read].copy_from_slice(&self.buffers[slot][self.consumed..self.consumed + read]);
+		self.consumed += read;
+		if self.consumed == available {
+			self.current += 1;
+			self.consumed = 0;
+			self.submit_ahead()?;
+		}
+		Ok(read)
+	}
+}
+
+impl Drop for UringReader<'_> {
+	fn drop(&mut self) {
+		// The kernel may still be writing into the buffers
+		while self.in_flight > 0 {
+			match self.ring().submit_and_wait(1) {
+				Ok(_) => {
+					let completed = self.ring().completion().count();
+					self.in_flight -= completed;
+				}
+				// Leak the buffers rather than free them under the kernel
+				Err(_) => {
+					std::mem::forget(std::mem::take(&mut self.buffers));
+					return;
+				}
+			}
+		}
+		if let Some(ring) = self.ring.take() {
+			SPARE.set(Some((ring, std::mem::take(&mut self.buffers))));
+		}
+	}
+}
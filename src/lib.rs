@@ -61,6 +61,10 @@
 //! most well-known hash functions are supported, like MD5, SHA1, SHA2 etc. It's
 //! licensed under Apache-2.0 License.
 //!
+//! The holes of sparse files are hashed as the zeros they read as without
+//! being read from disk, on Linux, Android, macOS and FreeBSD. Elsewhere,
+//! Windows included, sparse files are read whole; their hashes are the same.
+//!
 //! ## OPTIONS
 //!
 //! -a --algorithm &lt;algorithm&gt;
@@ -68,7 +72,21 @@
 //! ```text
 //! Quite simple, select the hash you want. Case-insensitive.
 //!
-//! Supported algorithms: SHA{1,2-,3-{224,256,384,512}, CRC32, MD5, BLAKE{2B,2S,3}, XXH3, XXHASH64
+//! Supported algorithms: SHA{1,2-,3-{224,256,384,512}, CRC32, MD5, BLAKE{2B,2S,3}, XXH3, XXHASH64, S3-ETAG, BTV2
+//!
+//! BTV2 is the pieces root of BitTorrent v2, the SHA-256 merkle root of the
+//! 16 KiB blocks of a file, as listed in v2 .torrent files.
+//! ```
+//!
+//! --part-size &lt;size&gt;
+//!
+//! ```text
+//! With `-a s3-etag`, hash files into the ETag Amazon S3 gives them when
+//! uploaded in parts of this size, like the AWS CLI does: the MD5 of the MD5s
+//! of the parts followed by `-` and the amount of parts for files of at
+//! least one part, the MD5 of the file otherwise. Lets files be verified
+//! against the ETags listed by S3 and compatible object stores without
+//! downloading them back. S3 ETags aren't cached. Default: 8M.
 //! ```
 //!
 //! -c --create
@@ -79,6 +97,9 @@
 //!
 //! And will also fail if the output file exists already and the command `--force` is not presented.
 //!
+//! The output file is left out of the hashes when it's inside the directory, as
+//! are the shards of a sharded one it replaces.
+//!
 //! Only with `--verify`. Overrides `--verify`.
 //! ```
 //!
@@ -110,6 +131,9 @@
 //!
 //! ```text
 //! Recurse down symlinks. Default.
+//!
+//! Symlinks leading back to a directory being walked are skipped, and listed
+//! under "Warnings:" at the end.
 //! ```
 //!
 //! --no-follow-symlinks
@@ -118,6 +142,178 @@
 //! Don't recurse down symlinks.
 //! ```
 //!
+//! --record-symlinks
+//!
+//! ```text
+//! Record symlinks instead of skipping them, without following them. Their
+//! hash is that of the link target, written after a `symlink -> target`
+//! comment, so verifying catches a re-pointed `current -> v1.2` and reports
+//! it as retargeted, with the old and new targets.
+//! ```
+//!
+//! --names-only [--with-sizes]
+//!
+//! ```text
+//! Hash the layout of the tree instead of its contents, without reading any
+//! file: each entry gets a placeholder hash, or with `--with-sizes`, the hash
+//! of its size. `create` also prints a single layout digest over all entries,
+//! and writes it as a `layout digest:` header comment, so two copies can be
+//! compared in seconds before a full verification. `verify` with the same
+//! options lists added, removed and, with sizes, resized files. Not available
+//! with `--low-memory` or `check`.
+//! ```
+//!
+//! --record-empty-dirs
+//!
+//! ```text
+//! Record directories with nothing in them, with a placeholder hash of dashes
+//! after an `empty directory` comment, so verifying notices when one goes
+//! missing. Directories with files in them are implied by their files.
+//! ```
+//!
+//! --tree-hash
+//!
+//! ```text
+//! With `create`, also print a single root hash of the tree and write it as a
+//! `tree hash:` header comment. Not available with `--low-memory`.
+//! ```
+//!
+//! --piece-size &lt;size&gt;
+//!
+//! ```text
+//! With `create`, also record the hash of each piece of this size of files
+//! larger than it, e.g. `64M`, as `piece N of SIZE bytes:` comments before
+//! their entry. When such a file doesn't match, `verify` reads it again to
+//! report which byte ranges differ, so only those need transferring again.
+//! Files are read a second time to record them. Default: none.
+//! ```
+//!
+//! --bao-outboard
+//!
+//! ```text
+//! With `create` and BLAKE3, also write a bao outboard of each file over
+//! 16 KiB to `<hash file>.bao/<file>.obao`, for `verify-range`. Outboards are
+//! 1/256th of the size of their file, and are kept while their file's hash
+//! stays the same. Files are read a second time to write them.
+//! ```
+//!
+//! --rsync-signatures [--signature-block-len &lt;size&gt;]
+//!
+//! ```text
+//! With `create`, also write a librsync signature of each file bigger than a
+//! block to `<hash file>.rsig/<file>.sig`, with BLAKE2 strong sums in blocks
+//! of 2048 bytes unless set, so `rdiff delta` and other librsync users can
+//! work out deltas against the hashed files without reading them. Signatures
+//! newer than their file are kept. Files are read a second time to write
+//! them.
+//!
+//! Example:
+//!   quickdash create /srv/images --rsync-signatures
+//!   rdiff delta /srv/images/images.hash.rsig/disk.img.sig new/disk.img disk.delta
+//! ```
+//!
+//! --par2 &lt;percent&gt;
+//!
+//! ```text
+//! With `create`, also make PAR2 recovery files of the files and of the hash
+//! file with `par2` (par2cmdline), able to restore this percentage of them,
+//! like `5%`, in `<hash file>.par2/`, replacing any made before. `repair`
+//! uses them. Needs `par2` on the PATH, and is given each file as an
+//! argument, so very many files may be too many.
+//! ```
+//!
+//! --torrent &lt;file&gt; [--torrent-piece-length &lt;size&gt;]
+//!
+//! ```text
+//! With `create` and `-a btv2`, also write a BitTorrent v2 .torrent of the
+//! files, without trackers, so one pass yields both the hash file and the
+//! torrent metadata. Pieces are 256K unless set, a power of two of at least
+//! 16K. Files larger than a piece are read a second time for their piece
+//! layer.
+//! ```
+//!
+//! --in-toto &lt;file&gt; [--sign-with &lt;command&gt;]
+//!
+//! ```text
+//! With `create`, also write an in-toto statement of the files as JSON, each
+//! file a subject with its digest, and a SLSA provenance predicate naming the
+//! directory and algorithm, so build pipelines can publish the results as a
+//! supply-chain attestation. Files that weren't hashed are left out.
+//!
+//! With `--sign-with`, the statement is signed into a DSSE envelope instead.
+//! The shell command gets the DSSE pre-authentication encoding of the
+//! statement on its standard input, and prints a raw signature.
+//!
+//! Example:
+//!   quickdash -a sha2256 create dist --in-toto dist.intoto.json \
+//!     --sign-with 'cat > msg && openssl pkeyutl -sign -inkey key.pem -rawin -in msg'
+//! ```
+//!
+//! --timestamp-url &lt;url&gt;
+//!
+//! ```text
+//! With `create`, have the hash file timestamped by the RFC 3161
+//! time-stamping authority at this http:// URL, proving it existed as it is
+//! at that time, and keep the token as `<file>.tsr` next to it. Tokens are
+//! signed, so TSAs take plain HTTP. Not available with `--shard-by`.
+//!
+//! `verify` and `check` report when a hash file with a token was timestamped,
+//! and fail if it changed since. That the TSA signed the token is checked
+//! with its certificate and OpenSSL:
+//!   openssl ts -verify -in photos.hash.tsr -data photos.hash -CAfile tsa.pem
+//!
+//! Example:
+//!   quickdash create photos --timestamp-url http://timestamp.digicert.com
+//! ```
+//!
+//! --keep-generation
+//!
+//! ```text
+//! With `create`, also keep a copy of the hash file as a new generation in
+//! `<file>.history` next to it, named by the UTC time it was made, like
+//! `20250314T092653Z.hash`. Run on a schedule, the generations tell when
+//! files changed, see `generations`. Not available with `--shard-by`.
+//! ```
+//!
+//! --resume
+//!
+//! ```text
+//! `create` appends the hash of each file it reads to `<file>.checkpoint`
+//! next to the hash file, with the file's size and modification time, and
+//! removes it once the hash file is written. `verify` does the same with
+//! `--resume`, until it has compared. With `--resume`, a run interrupted by
+//! a crash, a reboot or `--service` being stopped picks up where it was:
+//! files whose size and modification time are the same as in the checkpoint
+//! get its hash without being read. A resumed `verify` reports on every
+//! file, those read before the interruption included, so long scrubs are
+//! best run with `--resume` from the start. The checkpoint is started over
+//! if it was made with another algorithm, or without `--resume`. Can't be
+//! used with labelled directories, `--low-memory`, `--names-only` or fetched
+//! hash files.
+//!
+//! Example:
+//!   quickdash create /srv/archive --resume
+//!   quickdash verify /srv/archive --resume
+//! ```
+//!
+//! --publish &lt;url&gt;...
+//!
+//! ```text
+//! With `create`, upload the hash file once written, and its shards first
+//! with `--shard-by`, under each of these URLs by its file name, so a
+//! manifest isn't only kept next to the data it protects:
+//!   s3://bucket/prefix           with the AWS CLI and its credentials
+//!   https://host/dav/collection  WebDAV, with curl and ~/.netrc
+//!   sftp://user@host/path        with curl and SSH keys
+//! `davs://` and `dav://` stand for WebDAV over https and http. Missing
+//! directories are made on SFTP servers, not WebDAV ones. A failed upload
+//! makes the run fail, the hash file being kept.
+//!
+//! Example:
+//!   quickdash create photos --publish s3://backups/manifests \
+//!     --publish sftp://me@offsite/srv/manifests
+//! ```
+//!
 //! -i --ignore &lt;filename[,filename2][,filename3][,filenameN]...&gt;...
 //!
 //! ```text
@@ -125,31 +321,686 @@
 //!
 //! The program marks the files that will be ignored. The specified ignored files will not come to the output file.
 //!
+//! Paths are relative to the directory. Globs like `*.log` are allowed, and a
+//! trailing `/` ignores only directories, e.g. `cache/`. These match at any
+//! depth, as in `--exclude-from`, unless they start with `/`, e.g. `/*.log`.
+//!
 //! Can be used multiple times.
 //! ```
 //!
+//! --special-files &lt;skip|record|error&gt;
+//!
+//! ```text
+//! What to do with FIFOs, sockets and device nodes: leave them out, record them
+//! with a placeholder hash without opening them, or fail the run, naming
+//! them. Default: skip.
+//! ```
+//!
+//! --skip-larger-than &lt;size&gt;
+//!
+//! ```text
+//! Don't hash files larger than `size`, e.g. `500M` or `2T`. They're recorded
+//! with a placeholder hash of dashes, after a `skipped: size` comment, so a
+//! quick hash file can be made of a tree holding a few huge VM images.
+//! `check` doesn't read the files of entries with a placeholder hash, and
+//! reports them as ignored.
+//! ```
+//!
+//! --hash-each-hard-link
+//!
+//! ```text
+//! Read every hard link to a file. By default a file with several hard links is
+//! hashed once and its hash reused for the other links, which saves re-reading
+//! backup trees made with `rsync --link-dest`. Only done on Unix.
+//! ```
+//!
+//! --skip-hidden
+//!
+//! ```text
+//! Skip dotfiles and dot-directories, and on Windows files/directories with the
+//! hidden attribute, e.g. `.Spotlight-V100` and `.Trashes` on camera cards.
+//! By default hidden files are hashed like any other.
+//! ```
+//!
+//! --text-mode
+//!
+//! ```text
+//! Hash files as text, reading CRLF line endings as LF like `md5sum --text`
+//! does on Windows, so text files only converted between Windows and Unix
+//! line endings still verify. Lone CRs are left as they are. The hash file
+//! notes it in its header, and `verify` then always reads the files as text,
+//! refusing --text-mode for hash files made without it. The hash cache isn't
+//! used.
+//! ```
+//!
+//! --git-tracked
+//!
+//! ```text
+//! Only hash the files git tracks, as listed by `git ls-files` in the hashed
+//! directory, including those of submodules, so the hash file of a source
+//! checkout leaves out untracked build output. The hash file notes it in its
+//! header, and `verify` then only looks at the files git tracks too.
+//! ```
+//!
+//! --look-inside &lt;zip,tar,7z&gt;
+//!
+//! ```text
+//! Also hash the regular files inside archives of these kinds without
+//! extracting them, storing each as `archive.zip!member/path` after the
+//! archive's own entry, so `verify` with the same option tells which members
+//! changed. Nested archives aren't looked into.
+//!
+//! zip archives may be Zip64, with stored or deflated members; tar archives
+//! ustar, GNU or pax, compressed as `.tar.gz`, `.tar.bz2`, `.tar.xz` or
+//! `.tar.zst` and their short forms, uncompressed with gzip, bzip2, xz or
+//! zstd. 7z archives are read with bsdtar. Archives that can't be read are
+//! warned about, and so are members, which get a placeholder hash. Not
+//! available with `check` or `--low-memory`.
+//!
+//! Example:
+//!   quickdash --look-inside zip,tar create datasets
+//! ```
+//!
+//! --mac-metadata
+//!
+//! ```text
+//! On macOS, also hash what a copy to another filesystem may lose: the
+//! resource fork of each file, stored as `file/..namedfork/rsrc`, and its
+//! Finder info (type, creator and Finder flags) and quarantine extended
+//! attributes, stored as `file/..xattr/com.apple.FinderInfo` and
+//! `file/..xattr/com.apple.quarantine`. Files without them get no such
+//! entries, so `verify` with the same option tells which were lost or
+//! changed. Does nothing on other platforms. Not available with `check` or
+//! `--low-memory`.
+//!
+//! Example:
+//!   quickdash --mac-metadata create Applications
+//! ```
+//!
+//! --snapshot &lt;vss|btrfs|zfs|lvm&gt;
+//!
+//! ```text
+//! Hash files from a snapshot of their volume rather than the live files, so
+//! files kept open or locked, like mailboxes and databases, are read whole and
+//! as they were at one instant. The snapshot is taken when hashing starts and
+//! removed when it's done; files are named as they are outside it.
+//!
+//!   vss: a Volume Shadow Copy of the drive, on Windows, made and removed
+//!        with PowerShell. Needs administrator rights.
+//!   btrfs: a read-only snapshot of the subvolume, made in its root.
+//!   zfs: a snapshot of the dataset, read under its `.zfs/snapshot`.
+//!   lvm: a snapshot of the logical volume, taking a tenth of its size from
+//!        the free space of the volume group, mounted read-only in the
+//!        temporary directory.
+//!
+//! btrfs, ZFS and LVM snapshots are taken on Linux with the `btrfs`, `zfs`
+//! and `lvcreate` commands, which need root.
+//!
+//! Only available with `create` and `verify`, and not with labelled
+//! directories, `--absolute-paths` or `--relative-to`.
+//!
+//! Example:
+//!   quickdash --snapshot vss create C:\Users\me\Documents\Outlook
+//!   quickdash --snapshot zfs verify /srv/mail
+//! ```
+//!
+//! --update-atime
+//!
+//! ```text
+//! Let reading files update their access time. By default files are opened
+//! with `O_NOATIME` on Linux, where permitted, so scrubbing an archive doesn't
+//! rewrite the access time of every file in it. Files owned by other users are
+//! opened normally unless running as root.
+//! ```
+//!
+//! --drop-caches
+//!
+//! ```text
+//! Evict each file from the page cache once it's hashed, so scrubbing
+//! terabytes doesn't push out the cache of other workloads on the same
+//! machine. Only done on Linux. Files are always read with a sequential
+//! read-ahead hint there.
+//! ```
+//!
+//! --mmap
+//!
+//! ```text
+//! Hash files by mapping them into memory instead of reading them in small
+//! chunks, which is faster for medium-sized files on fast SSDs, especially
+//! with BLAKE3. Files that fail to map are read as usual. Only done on Unix.
+//! Files mustn't be truncated while being hashed this way. Applies whether
+//! files are read on one thread or several.
+//! ```
+//!
+//! --io-uring
+//!
+//! ```text
+//! Read files through io_uring, keeping several reads of each file in flight
+//! while earlier parts of it are hashed. This is per-file read-ahead: reads
+//! of the next file only start once the current one is done, so it helps most
+//! with large files on NVMe and network storage with high latency. Falls back
+//! to regular reads on kernels without io_uring. Only on Linux, in builds with
+//! the `io-uring` feature. Applies whether files are read on one thread or
+//! several.
+//! ```
+//!
+//! --retries &lt;n&gt; [--retry-delay &lt;delay&gt;]
+//!
+//! ```text
+//! Read a file up to `n` more times when reading it fails, waiting `delay`
+//! (e.g. `500ms`, `5s`, default 1s) in between, for network filesystems that
+//! occasionally time out. A file that still can't be read is recorded with a
+//! placeholder hash of dashes, after an `error:` comment, and listed under
+//! "Warnings:". Default: 0.
+//! ```
+//!
+//! --no-cache
+//!
+//! ```text
+//! Read every file when creating. By default, create reuses the hashes of
+//! files whose device, inode, size and modification time haven't changed
+//! since an earlier run, from a cache in the user's cache directory
+//! (`$XDG_CACHE_HOME` or `~/.cache`, `%LOCALAPPDATA%` on Windows). Verify
+//! always reads every file, so bit rot is still caught. Entries of files
+//! found changed are dropped, and runs saving the cache at once merge their
+//! entries in turn. Runs with `--low-memory` don't use it, as it's held in
+//! memory whole.
+//! ```
+//!
+//! --cache-path &lt;file&gt;
+//!
+//! ```text
+//! Keep the hash cache in `file` instead.
+//! ```
+//!
+//! --exclude-from &lt;file&gt;
+//!
+//! ```text
+//! Ignore files/directories matching the patterns in `file`, one per line, like
+//! rsync's option of the same name. Lines starting with `#` or `;` are comments.
+//!
+//! `*` matches anything but `/`, `**` matches anything, `?` one character.
+//! A leading `/` anchors the pattern to the directory, otherwise it matches at
+//! any depth. A trailing `/` matches directories only, e.g. `/cache/`.
+//! ```
+//!
+//! --relative-to &lt;dir&gt;
+//!
+//! ```text
+//! Store and look up paths relative to `dir` instead of the hashed directory.
+//! `dir` has to contain the hashed directory. Lets a hash file created from a
+//! subdirectory be verified from the project root.
+//! ```
+//!
+//! --absolute-paths
+//!
+//! ```text
+//! Make `create` store absolute canonical paths instead of relative ones.
+//! `verify` picks up either form from each entry of the hash file, so hash
+//! files merged from both kinds verify too.
+//! ```
+//!
+//! --path-style &lt;native|unix|windows&gt;
+//!
+//! ```text
+//! Path separator used in written hash files. Default: native.
+//!
+//! Use `unix` for hash files that move between Windows and Linux/macOS.
+//! ```
+//!
+//! --normalize-unicode[=nfc|nfd]
+//!
+//! ```text
+//! Bring walked and loaded paths to the same Unicode normalization form before
+//! comparing them, so names stored decomposed by macOS match names stored
+//! composed elsewhere. Default: off, `nfc` if given without a value.
+//!
+//! The value has to follow an `=`, as in `--normalize-unicode=nfd`, so the
+//! flag doesn't take the next argument, e.g. a path, as its value.
+//! ```
+//!
+//! --manifest-encoding &lt;auto|utf8|cp1252|cp850&gt;
+//!
+//! ```text
+//! Text encoding of hash files being read. Default: auto, which reads UTF-8
+//! and switches to whichever of CP1252/CP850 fits better at the first line
+//! that isn't valid UTF-8. UTF-16 hash files are recognised by their BOM
+//! regardless of this option.
+//! ```
+//!
+//! --comment-style &lt;semicolon|hash&gt; [--comment &lt;text&gt;]...
+//!
+//! ```text
+//! Character starting comment lines in written hash files. Default: semicolon.
+//! Lines starting with either `;` or `# ` are skipped when reading. A `#`
+//! directly followed by a name, as in `#notes.txt  <hash>` of filename-first
+//! hash files, starts an entry.
+//!
+//! `--comment` adds a comment at the top of the file written by `create` or
+//! `merge`. Can be used multiple times.
+//! ```
+//!
+//! --hash-encoding &lt;hex|multihash|cid|oci&gt;
+//!
+//! ```text
+//! How hashes are written in hash files, for archives built on IPFS or
+//! tools speaking OCI digests. Default: hex.
+//!
+//! multihash - The digest after the multihash code of its algorithm and its
+//!             length, in base58btc: `Qm...` for SHA-256.
+//! cid       - A CIDv1 of the file as a single raw block, in base32:
+//!             `bafkrei...` for SHA-256, `bafkr4i...` for BLAKE3. Matches
+//!             `ipfs add --raw-leaves` for files that fit in one block.
+//! oci       - The name of the algorithm, a colon and the lowercase digest,
+//!             as OCI/Docker and many supply-chain tools write them:
+//!             `sha256:e3b0...`, `blake3:af13...`.
+//!
+//! All are accepted when reading, whatever this is set to, and name the
+//! algorithm to verify with when `-a` isn't given. Whirlpool, S3-ETAG and
+//! BTV2 have no multihash code. `tree-hash` prints its root hash this way
+//! too.
+//! ```
+//!
+//! --rclone
+//!
+//! ```text
+//! Write hash files exactly as `rclone hashsum` and `rclone md5sum` print
+//! them, so they can be diffed against the hashes rclone reports for a cloud
+//! copy of the tree: lowercase hashes, two spaces, and `/` separated
+//! filenames as they are, without escaping, comments or entries of files
+//! without a hash.
+//!
+//! Hash files are read by the same rules, keeping the whitespace around
+//! filenames. Files rclone couldn't hash, `UNSUPPORTED` or `ERROR`, are
+//! skipped.
+//!
+//! Example:
+//!   rclone md5sum remote:photos > photos.md5
+//!   quickdash -a md5 --rclone verify photos --file photos.md5
+//! ```
+//!
+//! --mtree
+//!
+//! ```text
+//! Write hash files as BSD mtree specs, one line per file with its full
+//! path, `type`, `size`, `time`, `mode`, `uid`, `gid` and checksum keyword:
+//! md5digest, sha1digest, sha256digest, sha384digest or sha512digest.
+//! Checksums are SHA-256 unless `-a` says otherwise. Empty directories and
+//! symlinks are listed as `type=dir` and `type=link`, with `--record-empty-
+//! dirs` and `--record-symlinks`.
+//!
+//! Specs are read in the full path form of `mtree -C` and bsdtar, or the
+//! hierarchical one of `mtree -c`, with `/set` and `/unset`. The checksum
+//! used is the strongest one of the first file listing any, and names the
+//! algorithm to verify with when `-a` isn't given. Files with a size and
+//! time are reported as changed rather than corrupted when those differ,
+//! and their mode and owner are checked with `--check-metadata`.
+//!
+//! Example:
+//!   mtree -c -K sha256digest -p firmware > firmware.mtree
+//!   quickdash --mtree verify firmware --file firmware.mtree
+//! ```
+//!
+//! --encrypt-to &lt;recipient&gt;
+//!
+//! ```text
+//! Encrypt written hash files to this recipient, ASCII-armored, so the names
+//! they list can't be read where they're stored. Recipients starting with
+//! `age1` or `ssh-` are age public keys and encrypted to with `age`, others
+//! are gpg keys and encrypted to with `gpg`. May be repeated, with keys of
+//! one tool only. Can't be used with `--low-memory`.
+//!
+//! Encrypted hash files are recognised and decrypted in memory on read,
+//! whether or not `--encrypt-to` is given: gpg finds its secret keys itself,
+//! age is given the files of `--identity`.
+//!
+//! Example:
+//!   quickdash --encrypt-to age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p create photos
+//!   quickdash --identity ~/.config/age/key.txt verify photos
+//! ```
+//!
+//! --audit-log &lt;file&gt;
+//!
+//! ```text
+//! Append a record of each `verify`, `check`, `remote` against a hash file,
+//! `check-torrent`, `bagit validate` and `release verify` run to this file,
+//! one JSON object per line: when it ran, the hash file and its SHA-256, how
+//! many files matched, were assumed unchanged, added, removed or ignored,
+//! which ones failed, and why the run couldn't compare if it couldn't. Each
+//! record holds the SHA-256 of the one before it and its own, so records
+//! can't be edited, dropped or reordered without `verify-audit-log`
+//! noticing, short of rewriting every record after them.
+//!
+//! Example:
+//!   quickdash --audit-log /var/log/quickdash-scrubs.jsonl verify /srv/archive
+//! ```
+//!
+//! --metrics-textfile &lt;file&gt;
+//!
+//! ```text
+//! Write the metrics of each run `--audit-log` records to this file, in the
+//! format of node_exporter's textfile collector, labelled with the command
+//! and hash file: files scanned, bytes hashed, files that failed, whether
+//! the run succeeded, when it ran and when a run last succeeded. A run
+//! succeeds when it compares and no file fails. Metrics of other hash files
+//! written to the same file are kept. The file is replaced whole, so the
+//! collector never reads half of it.
+//!
+//! Example:
+//!   quickdash --metrics-textfile /var/lib/node_exporter/quickdash.prom verify /srv/archive
+//! ```
+//!
+//! --notify-webhook &lt;url&gt; --notify-email &lt;address&gt;... --smtp-url &lt;url&gt; [--smtp-from &lt;address&gt;]
+//!
+//! ```text
+//! Notify someone when a run `--audit-log` records fails: when it can't
+//! compare, or a file fails. `--notify-webhook` POSTs a JSON summary to the
+//! URL: when, the host, command and hash file, the files that failed, how
+//! many files are gone and why the run couldn't compare if it couldn't.
+//! `--notify-email` emails the same to each address, through the SMTP
+//! server of `--smtp-url`, from `--smtp-from` or quickdash@ the host name.
+//! Both are sent with curl, which logs into the SMTP server with
+//! `~/.netrc`. Failing to notify is reported, and doesn't change the exit
+//! code.
+//!
+//! Example:
+//!   quickdash --notify-webhook https://hooks.example.com/scrubs verify /srv/archive
+//!   quickdash --notify-email ops@example.com --smtp-url smtps://mail.example.com verify /srv/archive
+//! ```
+//!
+//! --log-to &lt;syslog|journald&gt;
+//!
+//! ```text
+//! Log a record of each run `--audit-log` records, and one of each file that
+//! failed, to the local syslog daemon or the systemd journal. Records have
+//! structured fields: COMMAND and MANIFEST, and MESSAGE_ID telling runs
+//! from failures. Runs add MATCHED, FAILED, ADDED, REMOVED and ERROR if
+//! they couldn't compare, failures FILE, EXPECTED and ACTUAL. Runs that
+//! passed are logged at info, others and failures at err. The journal gets
+//! the fields as they are, syslog as an RFC 5424 structured data element
+//! `[quickdash@32473 ...]` at the end of the message.
+//!   runs      MESSAGE_ID=3f1d6c0a9b2e4e57a4c8d1e26f0b9a53
+//!   failures  MESSAGE_ID=b8e4a2f17c6d4d0f9e35a0c2d7f14e68
+//!
+//! Example:
+//!   quickdash --log-to journald verify /srv/archive
+//!   journalctl MESSAGE_ID=b8e4a2f17c6d4d0f9e35a0c2d7f14e68
+//! ```
+//!
+//! --service
+//!
+//! ```text
+//! Run `create` or `verify` as a systemd service of Type=notify: tell
+//! systemd the run is ready, ping its watchdog at twice the rate of
+//! WatchdogSec= while hashing, and on SIGTERM stop reading more files.
+//! Files being read are finished. A stopped `create` writes what was hashed
+//! to `directory_name.hash.partial`, leaving out the others, and a stopped
+//! `verify` reports on the files it verified, not counting the others as
+//! removed. Both then exit with 143, as if killed by SIGTERM. Can't be used
+//! with `--low-memory`.
+//!
+//! Example unit, run by a timer:
+//!   [Service]
+//!   Type=notify
+//!   ExecStart=quickdash --service verify --scrub-percent 10 /srv/archive
+//!   WatchdogSec=5min
+//! ```
+//!
+//! --lock-wait &lt;duration&gt;
+//!
+//! ```text
+//! `create`, `verify`, `check` and `update` lock the hash file for the run,
+//! through `directory_name.hash.lock` next to it, so overlapping runs, like
+//! cron jobs running late, can't interleave their writes to it. `verify`
+//! and `check` share the lock, unless they write next to the hash file with
+//! `--fix`, `--record-verified`, scrubbing or `--resume`. A run that finds it
+//! locked waits this long for the other one to finish, then exits with 75 if
+//! it hasn't. Hash files where the lock can't be made, like on read-only
+//! media, are used without one. Default: 0s, not waiting.
+//!
+//! Example:
+//!   quickdash --lock-wait 30m verify /srv/archive
+//! ```
+//!
+//! --inventory &lt;command&gt; [--host &lt;name&gt;]
+//!
+//! ```text
+//! Note each `create` and `verify` run in a central inventory of the
+//! directories of many hosts, by piping SQL that SQLite and PostgreSQL both
+//! take to this shell command. Tables are created if missing:
+//!   runs (id, host, root, manifest, command, algorithm, time)
+//!   files (run, name, hash, status)
+//! with a row in `files` for each file of the run, and its status: created,
+//! matches, assumed, differs, changed, retargeted, metadata changed, added,
+//! removed or ignored. Hosts are named by `--host`, or their host name.
+//! Can't be used with labelled directories or `--low-memory`.
+//!
+//! Example:
+//!   quickdash --inventory 'sqlite3 /srv/inventory.db' verify /srv/datasets/census
+//!   sqlite3 /srv/inventory.db "SELECT host, root, MAX(time) FROM runs
+//!     JOIN files ON files.run = runs.id WHERE hash = '81C4...' AND
+//!     status = 'matches' GROUP BY host, root"
+//! ```
+//!
+//! --known-good &lt;file&gt;... --known-bad &lt;file&gt;...
+//!
+//! ```text
+//! Triage the files of a `create` or `verify` run by sets of known hashes,
+//! like the NSRL Reference Data Set. Files in a known bad set are listed as
+//! `Known bad`, and with `--known-good`, those in no known good set as
+//! `Unknown`, followed by how many of each there were. Exits 1 if any were
+//! known bad. A set lists one hash per line, optionally followed by a name as
+//! `sha256sum` writes them, or is a CSV file with a header, like
+//! `NSRLFile.txt`, whose column named for the algorithm is read. Both may be
+//! repeated. Can't be used with `--low-memory`.
+//!
+//! Example:
+//!   quickdash -a sha1 --known-good NSRLFile.txt --known-bad malware.txt create /mnt/evidence
+//! ```
+//!
 //! --force
 //!
 //! ```text
 //! Rewrite the output file in `--create` mode.
 //! ```
 //!
-//! -j --jobs [jobs]
+//! -j[=jobs] --jobs[=jobs]
 //!
 //! ```text
-//! Amount of threads used for hashing. Default: # of CPU threads
+//! Amount of threads used for reading and hashing. Default: # of CPU threads,
+//! but files on spinning disks are read with 1 thread, as concurrent reads
+//! make the heads seek back and forth. Only detected on Linux.
 //!
 //! One thread can hash one file at a time, potentially speeding up hashing
 //! up to `jobs` times.
 //!
 //! No/empty value: # of CPU threads. value = 0: maximum, of u8 (255)
 //!
+//! The value has to follow an `=`, as in `--jobs=4` or `-j=4`,
+//! so the flag doesn't take the next argument, e.g. a path, as its value.
+//!
 //! ```
 //!
-//! [DIRECTORY]
+//! --io-threads &lt;n&gt; --hash-threads &lt;n&gt;
+//!
+//! ```text
+//! Read files on `--io-threads` threads and hash them on `--hash-threads`
+//! others, connected by bounded queues, so slow storage and slow algorithms
+//! don't hold each other up. More reading threads help on a NAS, more hashing
+//! threads on local NVMe. Each reading thread feeds one file at a time, so
+//! there's no use in more hashing threads than reading ones. The reading
+//! threads also list directories while files are being found. Both default to
+//! `--jobs`. With both at 1, files are read and hashed one after another. Not
+//! used with `--low-memory`.
+//! ```
+//!
+//! --order &lt;inode|size-desc&gt;
+//!
+//! ```text
+//! Order files are hashed in. Default: inode, which reads files in on-disk
+//! order where the platform exposes it, for fewer seeks on hard drives.
+//! `size-desc` hashes the biggest files first, so with several threads one
+//! huge file doesn't start last and leave the others idle at the end.
+//! ```
+//!
+//! --shard-by &lt;top-level-dir|hash-prefix&gt;
+//!
+//! ```text
+//! Split the output of `create` into several hash files, one per top-level
+//! directory or per first two hash digits. The output file becomes an index
+//! listing the shards. `verify` and `check` load shards one at a time.
+//!
+//! Shards are named after the output file and their directory or digits,
+//! `photos.docs.hash` for `docs/` in `photos.hash`, with top-level files in
+//! `photos..hash`. Shards of the index being replaced that are no longer
+//! needed are removed.
+//! ```
+//!
+//! --low-memory [--unsorted]
+//!
+//! ```text
+//! Make `create` write hashes as it goes instead of collecting them all first.
+//! Sorted output is kept by spilling sorted runs to the temporary directory and
+//! merging them at the end, unless `--unsorted` is given.
+//!
+//! Memory still grows with the number of files that have several hard links,
+//! whose hashes are kept to be reused for their other links; give
+//! `--hash-each-hard-link` as well to keep it flat.
+//! ```
+//!
+//! [DIRECTORY]...
 //!
 //! ```text
 //! Directory to create/verify hash for. By default is current workdir.
+//!
+//! Several directories can be covered by one hash file, given in the same
+//! order every time. Each one's entries are stored under its name, e.g.
+//! `photos/IMG_0001.JPG`, and `--file` has to be given.
+//! ```
+//!
+//! --label &lt;label&gt;...
+//!
+//! ```text
+//! Store the entries of each directory under `label` instead of its name.
+//! Given once per directory, in the same order.
+//! ```
+//!
+//! --record-metadata
+//!
+//! ```text
+//! Make `create` record the size and modification time of each file, as a
+//! `metadata:` comment line before its entry. Other tools skip it like any
+//! other comment.
+//!
+//! `verify` then reports files whose size or modification time changed as
+//! "changed since hashed", apart from files that don't match with unchanged
+//! metadata, which likely rotted. Files whose size changed aren't read.
+//! Files whose content still matches after only their modification time
+//! changed don't count as differing.
+//! ```
+//!
+//! --check-metadata
+//!
+//! ```text
+//! Make `create` record the owner, group and mode of each file along with its
+//! size and modification time, and `verify` and `check` report changes to
+//! them as "Metadata changed", apart from content mismatches. On Windows, the
+//! readonly, hidden and system attributes and the creation time are recorded
+//! instead, and changes to them reported as "Attributes changed". Each such
+//! file counts as differing.
+//! ```
+//!
+//! --quick [--sample &lt;percent&gt;]
+//!
+//! ```text
+//! Make `verify` only read files whose size or modification time differ from
+//! the ones recorded with `--record-metadata`, reporting the others as
+//! "assumed OK (metadata match)". Files recorded without metadata are read as
+//! usual. With `--sample`, that percentage of the assumed files is picked at
+//! random and read anyway, so repeated quick runs still catch bit rot over
+//! time. A sanity check for archives that take days to fully verify.
+//! ```
+//!
+//! --record-verified [--stale-after &lt;days&gt;]
+//!
+//! ```text
+//! Make `verify` and `check` keep when each file last verified in
+//! `<file>.verified` next to the hash file, and list the files that never
+//! did after the results. With `--stale-after`, also list the files that
+//! haven't verified in that many days, with how long ago they last did.
+//! Files assumed OK by `--quick` keep the time they were last read.
+//! ```
+//!
+//! --scrub-percent &lt;percent&gt; | --scrub-bytes &lt;size&gt;
+//!
+//! ```text
+//! Make `verify` read only a slice of the files each run: that percentage of
+//! the bytes of the files recorded with `--record-metadata`, or that many
+//! bytes, taking the files that never verified first, then those verified
+//! least recently. The others are assumed OK if their metadata matches, as
+//! with `--quick`. Implies `--record-verified`, so scheduled runs rotate
+//! through the archive, reading all of it every 100 / percent runs.
+//!
+//! Example:
+//!   quickdash verify /srv/archive --scrub-bytes 500G --stale-after 60
+//! ```
+//!
+//! --quarantine &lt;dir&gt; [--quarantine-link]
+//!
+//! ```text
+//! Make `verify` move the files whose content doesn't match to this
+//! directory, under the same relative paths, so later sync jobs don't spread
+//! them, or hard-link them there with `--quarantine-link`. Each is appended
+//! to `quarantine.log` in it, tab-separated: the time, `moved` or `linked`,
+//! the recorded and current hash, and the name. Files whose name is taken
+//! in the quarantine already are left in place, as are those whose recorded
+//! name goes up with `..`. Absolute names are put under the directory as if
+//! relative. The directory isn't verified when inside the verified one.
+//!
+//! Example:
+//!   quickdash verify /srv/archive --quarantine /srv/quarantine
+//! ```
+//!
+//! --fix [--yes]
+//!
+//! ```text
+//! Make `verify` ask, for each mismatch after the results, whether to update
+//! the entry of a changed file to its new hash, keep it or delete it, to
+//! delete the entry of a missing file, and to add one for a new file, then
+//! rewrite the hash file with the chosen changes. Recorded pieces, notes and
+//! metadata of the remaining entries are kept, while the tree hash and layout
+//! digest are dropped, as they no longer hold. `--yes` accepts every change
+//! without asking. Encrypted hash files need `--encrypt-to` to be rewritten.
+//!
+//! Example:
+//!   quickdash verify /srv/archive --fix
+//! ```
+//!
+//! --file https://&lt;url&gt; [--signature &lt;signature&gt;]
+//!
+//! ```text
+//! Make `verify` and `check` fetch the hash file over HTTP(S) with curl to a
+//! temporary directory, then compare the local files against it, as with a
+//! release's published checksums. Unless `--algorithm` is given, the
+//! algorithm of checksum files like `SHA256SUMS` is told by their name.
+//! `--signature` names a detached gpg signature of the hash file, a path or
+//! URL, checked first; if it doesn't verify nothing is compared. Fetched hash
+//! files can't be used with `--fix` or `--record-verified`, which write next
+//! to them.
+//!
+//! Example:
+//!   quickdash verify release/ -f https://example.com/1.0/SHA256SUMS \
+//!     --signature https://example.com/1.0/SHA256SUMS.asc
+//! ```
+//!
+//! ## WARNINGS
+//!
+//! ```text
+//! Problems that don't stop a run are listed under "Warnings:" at the end:
+//! skipped symlink loops, files that couldn't be read, and files whose size or
+//! modification time changed while they were being hashed. The latter are
+//! also marked with an `unstable: changed while hashing` comment in created
+//! hash files, as their hash likely won't verify.
 //! ```
 //!
 //! ## EXAMPLES
@@ -187,6 +1038,363 @@
 //!   F013BF0B163785CBB3BE52DE981E069E2B64E1CAC863815AC7BEED63E1734BAE  Cargo.toml
 //!   E84E380AEBDA3D98E96267201D61784C3D6FFB128C4D669E6C1D994C7D7BF32B  Cross.toml
 //! ```
+//!
+//! `quickdash merge` *infile*... `-o` *outfile* [`--policy` *policy*]
+//!
+//! ```text
+//! Consolidate several hash files (e.g. one per disc) into a single one.
+//!
+//! *policy* decides what happens when two files disagree about a hash:
+//!   error                  - refuse to merge (default)
+//!   prefer-newest          - keep the hash from the most recently modified file
+//!   keep-both-with-suffix  - keep both, the later one as "name~N"
+//! ```
+//!
+//! `quickdash update` [*directory*] [`-f` *hash file*]
+//!
+//! ```text
+//! Write a hash file again, reading only the files that changed since it was
+//! last updated; other files keep the hash they had. The hash file records
+//! in its header how far changes were read, and a hash file without that,
+//! or whose changes weren't all kept since, is updated by reading all files.
+//! Use the same options it was created with.
+//!
+//! Changes are those recorded by `record-changes` next to the hash file, if
+//! it's been running: only the changed paths are walked, and the rest of the
+//! hash file kept as it is.
+//!
+//! Otherwise, on Windows, changes are read from the NTFS change journal (the
+//! USN journal) with `fsutil`, which needs administrator rights. The journal
+//! names changed files but not their directories, so every file of a changed
+//! name is read again, as are files that weren't in the hash file. The
+//! directory is still walked for added, removed and renamed files, but
+//! without reading them.
+//!
+//! Example:
+//!   quickdash create D:\Archive
+//!   quickdash update D:\Archive
+//! ```
+//!
+//! `quickdash record-changes` [*directory*] [`-f` *hash file*]
+//!
+//! ```text
+//! Record which paths change under a directory with inotify, on Linux, until
+//! stopped, so that `update` walks only those on very large filesystems. The
+//! changes are appended to `hash file.changes` (default:
+//! `directory_name.hash.changes`), which is locked while recording; run it
+//! as a service from when the hash file is created. inotify watches each
+//! directory, within the limit of `fs.inotify.max_user_watches`; when it
+//! drops changes, or a directory can't be watched, the next update reads all
+//! files.
+//!
+//! Example:
+//!   quickdash record-changes /srv/data &
+//!   quickdash update /srv/data
+//! ```
+//!
+//! `quickdash tree-hash` [*path*] [`--expect` *digest*]
+//!
+//! ```text
+//! Print a single Merkle-style root hash of the tree: each directory's hash
+//! covers the sorted names and hashes of its files and subdirectories, so two
+//! trees with the same root hash have the same contents, on any platform.
+//! Honors the same walk options as `create`.
+//!
+//! With `--expect`, also fail unless the root hash is *digest*: hex, or an
+//! OCI digest like `sha256:...`, a multihash or a CID, whose algorithm is
+//! used when `-a` isn't given.
+//! ```
+//!
+//! `quickdash remote` *[user@]host:path* [`-f` *hash file*] [`--output` *file*] [`--agent` *command* | `--upload`]
+//!
+//! ```text
+//! Hash a directory on another host over SSH, with quickdash run there, so
+//! only its hash file crosses the network, then verify it against the local
+//! hash file given with -f, as after copying it to a replica. Without -f,
+//! print the remote directory's hash file; --output keeps it either way.
+//!
+//! The remote side hashes with -a, or the algorithm of the hash file, and
+//! ignores the -i patterns and copies of the hash file. It runs `quickdash`,
+//! or --agent; --upload copies this binary over for the run instead, for
+//! hosts of the same platform without quickdash. ssh is run as is, so its
+//! configuration and keys are used.
+//!
+//! Example:
+//!   quickdash remote backup@nas:/volume1/photos -f photos/photos.hash
+//! ```
+//!
+//! `quickdash cmp` *a* *b* [`--first-difference`]
+//!
+//! ```text
+//! Compare two directories directly, as after copying one to the other,
+//! without writing a hash file: list the files only in either and those that
+//! differ, like `diff -r`, then how many of each. Files in both are only read
+//! if their sizes match, those of both trees through the same workers. Honors
+//! the same walk options as `create`; -a picks the algorithm.
+//!
+//! With `--first-difference`, both files of each differing pair are read
+//! again to show the offset of the first byte that differs and the bytes
+//! around it in hex: a copy that ends early differs where it ends, bad RAM or
+//! a bad disk anywhere.
+//!
+//! Example output:
+//!   Only in /mnt/old: notes.txt
+//!   Files /mnt/old/photos/1.jpg and /mnt/new/photos/1.jpg differ
+//!     first difference at byte 1048576, where B ends
+//!     A 000ffff8: 3c 91 0a 7e 55 d2 00 13 [8f] 40 2e 61 09 c4 77 1b
+//!     B 000ffff8: 3c 91 0a 7e 55 d2 00 13
+//!   2410 identical, 1 only in /mnt/old, 0 only in /mnt/new, 1 differing, 0 unreadable
+//! ```
+//!
+//! `quickdash sum` *url* [`--expect` *digest*]
+//!
+//! ```text
+//! Hash the file at an http:// or https:// URL as curl downloads it, without
+//! saving it, and print its hash and URL, to validate mirrors or check a
+//! download is worth making. Redirects are followed; error statuses, and
+//! stalling for 5 minutes, fail.
+//!
+//! With `--expect`, also fail unless the hash is *digest*, given as with
+//! `tree-hash`.
+//!
+//! Example:
+//!   quickdash sum https://mirror.example.org/debian.iso \
+//!     --expect sha256:5f6e...
+//! ```
+//!
+//! `quickdash verify-range` *file* [`--offset` *size*] [`--length` *size*] [`--path` *directory*] [`-f` *infile*]
+//!
+//! ```text
+//! Verify a byte range of a file against its BLAKE3 hash, reading only that
+//! range, rounded out to 16 KiB, and its outboard written by `--bao-outboard`,
+//! to spot-check huge files on slow storage. *file* is named as in *infile*.
+//!
+//! Example output:
+//!   Bytes 1048576..1048676 of "disk.img" match
+//!   Bytes 2998272..3014656 of "disk.img" don't match
+//! ```
+//!
+//! `quickdash check-torrent` *file.torrent* [*path*]
+//!
+//! ```text
+//! Verify data downloaded to *path*, the current directory by default,
+//! against the piece hashes of a .torrent file: the SHA-1 pieces of v1
+//! torrents, or the SHA-256 merkle roots of the files of v2 and hybrid ones.
+//! Files whose pieces fail are listed along with them, and missing files as
+//! removed.
+//!
+//! Example output:
+//!   File "ubuntu/ubuntu.iso" doesn't match
+//!     Pieces 412-414 fail
+//! ```
+//!
+//! `quickdash bagit create` [*directory*] [`--info` *"Label: value"*]...
+//!
+//! `quickdash bagit validate` [*directory*] [`--fast`]
+//!
+//! ```text
+//! Create and validate BagIt bags (RFC 8493). `create` turns a directory
+//! into a bag in place: what it holds moves into its `data` directory, and
+//! bagit.txt, bag-info.txt with the Payload-Oxum and the `--info` lines,
+//! manifest-<algorithm>.txt and tagmanifest-<algorithm>.txt are written
+//! next to it. Checksums are SHA-512 unless `-a` picks MD5, SHA-1 or another
+//! SHA-2.
+//!
+//! `validate` checks that every payload file is listed in every manifest
+//! and matches it, that tag files match the tag manifests, and that the
+//! payload matches its Payload-Oxum. With `--fast`, only the Payload-Oxum
+//! is checked, without reading files.
+//! ```
+//!
+//! `quickdash release create` [*directory*] [`--file` *sums*] [`--clearsign`] [`-u` *key*]
+//!
+//! `quickdash release verify` *signature* [`--path` *directory*] [`--file` *sums*]
+//!
+//! ```text
+//! Publish and check releases the usual way, with gpg. `create` writes the
+//! checksums of the directory's files as coreutils does, to SHA256SUMS in it
+//! unless `--file` says otherwise, and signs them into SHA256SUMS.asc:
+//! detached, or clearsigned with `--clearsign`. Checksums are SHA-256 unless
+//! `-a` says otherwise, naming the file after it, like SHA512SUMS.
+//!
+//! `verify` checks the signature, then the files it lists against their
+//! checksums, in the directory of the signature unless `--path` is given. A
+//! detached signature is of the file named like it without `.asc` or `.sig`
+//! unless `--file` is given. The algorithm is told by that name.
+//!
+//! Example:
+//!   quickdash release create dist -u release@example.org
+//!   quickdash release verify downloads/SHA256SUMS.asc
+//! ```
+//!
+//! `quickdash query` (`--hash` *hash* | `--path` *name* | `--same-as` *file*) [`--file` *hash file*...]
+//!
+//! ```text
+//! Look up files by hash in the hash files given with `--file`, or, with
+//! none, in the inventory given with `--inventory`. `--hash` finds the files
+//! with that hash, `--path` the hash of the file of that name, as stored,
+//! and the other files with it, and `--same-as` the copies of a file, hashed
+//! the way each hash file was. In the inventory, each match is printed with
+//! the host and root it was seen under and when it was last seen matching.
+//! Exits 1 if nothing was found in the hash files.
+//!
+//! Example:
+//!   quickdash query --same-as ~/Downloads/report.pdf -f /srv/archive.hash
+//!   quickdash --inventory 'sqlite3 inventory.db' query --hash 81C4B7F7...
+//! ```
+//!
+//! `quickdash repair` [*directory*] [`--file` *hash file*]
+//!
+//! ```text
+//! Check the files listed in a hash file, and if any are corrupt or missing,
+//! or the hash file is, repair them from the recovery files made with
+//! `create --par2`, with `par2`, and check them again. Exits like `check`.
+//!
+//! Example:
+//!   quickdash create /srv/archive --par2 10%
+//!   quickdash repair /srv/archive
+//! ```
+//!
+//! `quickdash similar` [*directory*] [`--threshold` *score*] [`--digests`]
+//!
+//! ```text
+//! Find near-duplicate files, like re-encodes or edited documents, which
+//! exact hashes tell apart. Each non-empty file gets an ssdeep digest, and
+//! files whose digests are at least `--threshold` alike (default: 50, out of
+//! 100), directly or through another file, are listed together, each with
+//! its best score. `--digests` prints the digests instead, the way ssdeep
+//! does. Digests of all files are compared with each other, which takes long
+//! for many files.
+//!
+//! Example:
+//!   quickdash similar ~/Documents --threshold 70
+//! ```
+//!
+//! `quickdash dedupe` [*directory*] [`--action` *action* [`--apply`]] [`--keep` *which*] [`--log` *file*] [`--undo`]
+//!
+//! ```text
+//! Find files with the same content: non-empty files of the same size are
+//! hashed, and those with the same hash listed together, the one to keep
+//! first. `--keep` picks it: `first` by name (default), or `newest`. Hard
+//! links to the same file aren't duplicates, and reflinks of it, already
+//! sharing its blocks, are listed as `shared` and free no space.
+//!
+//! `--action` tells what to do with the others: `hardlink`, `reflink` (on
+//! copy-on-write filesystems like Btrfs, XFS and APFS) or `delete` them.
+//! Nothing is done without `--apply`, and then each duplicate is read again
+//! along with the file kept, and left alone if they no longer match or, for
+//! links, are on different filesystems. What's done is logged to `--log`
+//! (default: `directory_name.dedupe.log`); `--undo` copies the kept files
+//! back where the log says their duplicates were, as reflinks where it can.
+//!
+//! Example:
+//!   quickdash dedupe ~/Photos --action hardlink --keep newest --apply
+//! ```
+//!
+//! `quickdash tag` [*directory*] [`--strip`] [`--dry-run`]
+//!
+//! ```text
+//! Rename files to add their hash in brackets before the extension, as files
+//! are often distributed with, like `Episode 01 [89ABCDEF].mkv`: the first 8
+//! hex digits, of their CRC32 unless `--algorithm` is given. Files with one
+//! already are left alone. `--strip` removes it from those with one instead.
+//! Files aren't renamed onto one that exists or where another would go.
+//!
+//! Example:
+//!   quickdash tag ~/Downloads/Series --dry-run
+//! ```
+//!
+//! `quickdash tui` [*directory*] [`--file` *hash file*] [`--quarantine` *dir* [`--quarantine-link`]]
+//!
+//! ```text
+//! Verify interactively. The screen shows the tree of directories with how
+//! many of their files were hashed and how many are to look into, what each
+//! worker is reading, the throughput of the last seconds, and, under Tab,
+//! the failures, which `/` filters by name or kind. The selected failure can
+//! be hashed again with `r`, accepted with `a`, updating, deleting or adding
+//! its entry in the hash file when leaving, or quarantined with `x`, as with
+//! `verify --quarantine`. `q` leaves, printing the results like `verify`
+//! and exiting the same way.
+//!
+//! Example:
+//!   quickdash tui /srv/archive --quarantine /srv/quarantine
+//! ```
+//!
+//! `quickdash daemon` [`--socket` *path*]
+//!
+//! ```text
+//! Take jobs over a Unix socket only the user may connect to, by default
+//! `quickdash.sock` in `$XDG_RUNTIME_DIR`, or else in `quickdash-<uid>` in the
+//! temporary directory, which only the user may use, as JSON-RPC 2.0 requests
+//! and responses, one per line. Jobs run with the global options the daemon
+//! was started with. Clients should check that the socket is the user's
+//! before trusting its answers.
+//!
+//!   create {path, file?, algorithm?, force?}  start creating a hash file
+//!   verify {path, file?, algorithm?}          start verifying one
+//!       both return the job number; `file` defaults as for `create`
+//!   status {job}     how far the job got: hashed, files and bytes read
+//!   jobs             the status of every job
+//!   result {job}     what it found, or why it failed
+//!   subscribe {job}  `progress` notifications with its status until it's
+//!                    done, then its result
+//!   shutdown         stop taking jobs and leave
+//!
+//! Example:
+//!   quickdash daemon &
+//!   echo '{"jsonrpc":"2.0","id":1,"method":"verify","params":{"path":"/srv/archive"}}' \
+//!     | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/quickdash.sock
+//! ```
+//!
+//! `quickdash serve` *directory*... [`--listen` *address*] [`--token-file` *file*]
+//!
+//! ```text
+//! Serve an HTTP API over the given directories, each verified against its
+//! `directory_name.hash` and found by its name, listening on 127.0.0.1:7878
+//! unless told otherwise. With `--token-file`, requests must carry the token
+//! in it as `Authorization: Bearer <token>`. Responses are JSON, but for the
+//! hash files. Only the latest finished run of each directory is kept, and
+//! past 64 requests at once, clients get a 503 and should retry.
+//!
+//!   GET  /roots                 the directories, with their latest run
+//!   POST /roots/<name>/verify   start verifying one, unless it's running
+//!   GET  /roots/<name>/status   how far its latest run got
+//!   GET  /roots/<name>/results  what its latest finished run found
+//!   GET  /roots/<name>/manifest its hash file
+//!
+//! Example:
+//!   quickdash serve /srv/photos /srv/music --token-file ~/.config/quickdash-token
+//!   curl -X POST -H "Authorization: Bearer $TOKEN" localhost:7878/roots/photos/verify
+//! ```
+//!
+//! `quickdash generations list` [`--path` *directory*] [`--file` *hash file*]
+//!
+//! `quickdash generations diff` *from* [*to*] [`--path` *directory*] [`--file` *hash file*]
+//!
+//! `quickdash generations log` *name* [`--path` *directory*] [`--file` *hash file*]
+//!
+//! `quickdash generations prune` `--keep` *n* [`--path` *directory*] [`--file` *hash file*]
+//!
+//! ```text
+//! Look through the generations of a hash file kept with `--keep-generation`.
+//! `list` numbers them from 1, oldest first. `diff` lists the files added,
+//! removed or changed between two of them, named by number or name, up to
+//! the newest one unless `to` is given. `log` lists the generations in which
+//! a file changed, with its hash from then on, answering when it changed.
+//! `prune` removes all but the newest `--keep` generations.
+//!
+//! Example:
+//!   quickdash generations log --path photos 2019/wedding/IMG_0412.CR2
+//!   quickdash generations diff 1 --path photos
+//! ```
+//!
+//! `quickdash verify-audit-log` *log*
+//!
+//! ```text
+//! Check that each record of an audit log written with `--audit-log` holds
+//! the hash of the one before it, and that its own hash is right, reporting
+//! the first one that isn't.
+//! ```
 
 #![deny(unsafe_code)]
 #![allow(clippy::tabs_in_doc_comments)]
@@ -203,5 +1411,5 @@ pub use crate::{
 	algorithms::Algorithm,
 	error::Error,
 	hashing::*,
-	options::{Commands, Mode},
+	options::{BagitAction, Commands, CreateArgs, GenerationsAction, GlobalOptions, Mode, ReleaseAction, VerifyArgs},
 };
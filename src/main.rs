@@ -14,12 +14,48 @@
  */
 
 use std::{
-	collections::BTreeMap, fs::remove_file, io::{stderr, stdout}, path::{Path, PathBuf}, process::exit, str::FromStr
+	collections::BTreeMap, fs::{File, metadata, read_to_string, remove_file}, io::{BufRead, Read, stderr, stdin, stdout}, path::{Component, Path, PathBuf}, process::exit, time::{Duration, SystemTime}
 };
 
 use clap::Parser;
-use quickdash::{Algorithm, Commands, Mode};
+use quickdash::{
+	Algorithm, BagitAction, Commands, CreateArgs, Error, GenerationsAction, GlobalOptions, HashOptions, Mode, ReleaseAction, VerifyArgs,
+	operations::{
+		CompareFileResult, CompareOutcome, CompareResult, DedupeAction, Keep, Encryption, FetchedFile, Fix, GIT_TRACKED_HEADER, Generation, GenerationChange, HashEncoding, HashingReport, InventoryRun, KnownHashes, LiveVerification, LogTarget, Query, RunLock, ServedRoot, LastVerified, Mismatch, TimestampCheck, LAYOUT_DIGEST_PREFIX, MergeError, MergePolicy, PublishTarget, ReadOptions, RangeCheck, RecordedMetadata, RunMetrics, ScrubBudget, Smtp, Snapshot, TEXT_MODE_HEADER, TREE_HASH_PREFIX, USN_HEADER_PREFIX, Changed, ChangesPosition, CHANGES_HEADER_PREFIX, Unchanged, UsnPosition, WalkOptions, WriteOptions, cpu_threads, default_cache_path, default_io_threads, is_url,
+		outboard_dir, outboard_path,
+	},
+};
+
+
+/// What the commands are run with, set up from the global options.
+struct Context {
+	opts: GlobalOptions,
+	walk_options: WalkOptions,
+	read_options: ReadOptions,
+	write_options: WriteOptions,
+	reporting: Reporting,
+}
 
+/// Where the outcome of verifying runs is recorded besides the terminal.
+struct Reporting {
+	audit_log: Option<PathBuf>,
+	metrics_textfile: Option<PathBuf>,
+	notify_webhook: Option<String>,
+	smtp: Option<Smtp>,
+	host: String,
+	log_to: Option<LogTarget>,
+}
+
+impl Reporting {
+	/// Append the outcome of a `command` run on `manifest` to the audit log,
+	/// write its metrics, notify of its failure and log it, as set up.
+	fn finish_run(&self, command: &str, manifest: &Path, run: &RunMetrics, result: Result<&CompareOutcome, &Error>) {
+		audit(self.audit_log.as_deref(), command, manifest, result);
+		metrics(self.metrics_textfile.as_deref(), command, manifest, run, result);
+		notify(self.notify_webhook.as_deref(), self.smtp.as_ref(), &self.host, command, manifest, result);
+		log_run(self.log_to, command, manifest, result);
+	}
+}
 
 fn main() {
 	let result = actual_main();
@@ -27,102 +63,2570 @@ fn main() {
 }
 
 fn actual_main() -> i32 {
-	let opts = Commands::parse();
-
-	match opts.command {
-		Mode::Create { path, file, force } => {
-			let file = file.unwrap_or_else(|| default_file(&path));
-			match (force, file.exists()) {
-				(true, _) | (_, false) => {
-					// if this fails, it probably didn't exist
-					let _ = remove_file(&file);
-					let ignored_files: Vec<PathBuf> = opts.ignored_files
-						.into_iter()
-						.map(|f|PathBuf::from_str(&f).unwrap())
-						.collect();
-					let hashes: BTreeMap<PathBuf, String> = quickdash::operations::create_hashes(
+	let Commands { options: mut opts, command } = Commands::parse();
+
+	// Spinning disks are read with one thread unless told otherwise
+	let jobs = match opts.jobs {
+		Some(Some(0)) => Some(u8::MAX as usize),
+		Some(Some(jobs)) => Some(jobs),
+		Some(None) => Some(cpu_threads()),
+		None => None,
+	};
+	let io_threads = opts.io_threads.or(jobs).unwrap_or_else(|| match command.paths().first() {
+		Some(path) => default_io_threads(path),
+		None => 1,
+	});
+	let hash_threads = opts.hash_threads.or(jobs).unwrap_or_else(cpu_threads);
+	let mut walk_options = WalkOptions {
+		depth: opts.depth,
+		follow_symlinks: opts.follow_symlinks,
+		record_symlinks: opts.record_symlinks,
+		record_empty_dirs: opts.record_empty_dirs,
+		names_only: opts.names_only,
+		names_with_sizes: opts.with_sizes,
+		skip_hidden: opts.skip_hidden,
+		io_threads,
+		hash_threads,
+		order: opts.order,
+		retries: opts.retries,
+		retry_delay: opts.retry_delay,
+		hash_options: HashOptions {
+			update_atime: opts.update_atime,
+			drop_caches: opts.drop_caches,
+			mmap: opts.mmap,
+			#[cfg(feature = "io-uring")]
+			io_uring: opts.io_uring,
+			part_size: opts.part_size,
+			text_mode: opts.text_mode,
+		},
+		hash_each_hard_link: opts.hash_each_hard_link,
+		skip_larger_than: opts.skip_larger_than,
+		special_files: opts.special_files,
+		normalize_unicode: opts.normalize_unicode,
+		check_metadata: opts.check_metadata,
+		look_inside: opts.look_inside.clone(),
+		mac_metadata: opts.mac_metadata,
+		..Default::default()
+	};
+	if let Some(exclude_from) = &opts.exclude_from {
+		let patterns = read_to_string(exclude_from)
+			.map_err(|err| format!("Failed to read {:?}: {}", exclude_from, err))
+			.and_then(|text| quickdash::operations::parse_ignore_patterns(&text));
+		match patterns {
+			Ok(patterns) => walk_options.ignored_patterns = patterns,
+			Err(err) => {
+				eprintln!("{}", err);
+				return 1;
+			}
+		}
+	}
+	for ignored in &opts.ignored_files {
+		if let Err(err) = walk_options.ignore(ignored) {
+			eprintln!("Invalid pattern {:?}: {}", ignored, err);
+			return 1;
+		}
+	}
+	if opts.git_tracked {
+		for path in command.paths() {
+			if let Err(err) = walk_options.track_git(path) {
+				eprintln!("Failed to list the files git tracks in {:?}: {}", path, err);
+				return 1;
+			}
+		}
+	}
+	if opts.snapshot.is_some() && !matches!(command, Mode::Create(_) | Mode::Verify(_)) {
+		eprintln!("--snapshot can only be used with create and verify.");
+		return 1;
+	}
+	if opts.service && !matches!(command, Mode::Create(_) | Mode::Verify(_)) {
+		eprintln!("--service can only be used with create and verify.");
+		return 1;
+	}
+	// Pings until the run is over
+	let _watchdog = match opts.service {
+		true => {
+			quickdash::operations::stop_on_sigterm();
+			if let Err(err) = quickdash::operations::sd_notify("READY=1") {
+				eprintln!("Failed to notify the service manager: {}", err);
+			}
+			quickdash::operations::Watchdog::start()
+		}
+		false => None,
+	};
+	let host = opts.host.clone().unwrap_or_else(quickdash::operations::host_name);
+	let smtp = opts.smtp_url.clone().map(|url| Smtp {
+		url,
+		from: opts.smtp_from.clone().unwrap_or_else(|| format!("quickdash@{}", host)),
+		to: opts.notify_email.clone(),
+	});
+	let read_options = ReadOptions {
+		encoding: opts.manifest_encoding,
+		normalize_unicode: opts.normalize_unicode,
+		rclone: opts.rclone,
+		mtree: opts.mtree,
+		identities: opts.identity.clone(),
+	};
+	if opts.mtree && opts.algorithm == Algorithm::UNSPECIFIED && matches!(command, Mode::Create(_)) {
+		opts.algorithm = Algorithm::SHA2256;
+	}
+	let write_options = WriteOptions {
+		path_style: opts.path_style,
+		comment_style: opts.comment_style,
+		header: Vec::new(),
+		notes: BTreeMap::new(),
+		metadata: BTreeMap::new(),
+		pieces: BTreeMap::new(),
+		hash_encoding: opts.hash_encoding,
+		algorithm: opts.algorithm,
+		rclone: opts.rclone,
+		mtree: opts.mtree,
+		encrypt_to: opts.encrypt_to.clone(),
+	};
+	if opts.rclone && opts.hash_encoding != HashEncoding::Hex {
+		eprintln!("--rclone can't be used with --hash-encoding, rclone prints hexadecimal hashes.");
+		return 1;
+	}
+	if opts.mtree && opts.hash_encoding != HashEncoding::Hex {
+		eprintln!("--mtree can't be used with --hash-encoding, mtree specs hold hexadecimal digests.");
+		return 1;
+	}
+	if opts.mtree
+		&& opts.algorithm != Algorithm::UNSPECIFIED
+		&& quickdash::operations::mtree_keyword(opts.algorithm).is_none()
+	{
+		eprintln!("mtree specs hold MD5, SHA-1, SHA-256, SHA-384 or SHA-512 digests only.");
+		return 1;
+	}
+	if matches!(opts.hash_encoding, HashEncoding::Multihash | HashEncoding::Cid)
+		&& quickdash::operations::multihash_code(opts.algorithm).is_none()
+	{
+		eprintln!("Whirlpool, S3 ETags and btv2 have no multihash code, use --hash-encoding hex.");
+		return 1;
+	}
+
+	let reporting = Reporting {
+		audit_log: opts.audit_log.clone(),
+		metrics_textfile: opts.metrics_textfile.clone(),
+		notify_webhook: opts.notify_webhook.clone(),
+		smtp,
+		host,
+		log_to: opts.log_to,
+	};
+	let ctx = Context { opts, walk_options, read_options, write_options, reporting };
+	match command {
+		Mode::Create(args) => create(ctx, args),
+		Mode::Verify(args) => verify(ctx, args),
+		Mode::Check { paths, label, file, signature, record_verified, stale_after } => check(ctx, paths, label, file, signature, record_verified, stale_after),
+		Mode::Update { path, file } => update(ctx, path, file),
+		Mode::RecordChanges { path, file } => record_changes(ctx, path, file),
+		Mode::TreeHash { path, expect } => tree_hash(ctx, path, expect),
+		Mode::Remote { target, file, output, agent, upload } => remote(ctx, target, file, output, agent, upload),
+		Mode::Cmp { paths, first_difference } => cmp(ctx, paths, first_difference),
+		Mode::Sum { url, expect } => sum(ctx, url, expect),
+		Mode::VerifyRange { name, offset, length, path, file } => verify_range(ctx, name, offset, length, path, file),
+		Mode::CheckTorrent { torrent: torrent_file, path } => check_torrent(ctx, torrent_file, path),
+		Mode::Bagit { action: BagitAction::Create { path, info } } => bagit_create(ctx, path, info),
+		Mode::Bagit { action: BagitAction::Validate { path, fast } } => bagit_validate(ctx, path, fast),
+		Mode::Release { action: ReleaseAction::Create { path, file, clearsign, local_user, force } } => release_create(ctx, path, file, clearsign, local_user, force),
+		Mode::Release { action: ReleaseAction::Verify { signature, path, file } } => release_verify(ctx, signature, path, file),
+		Mode::Merge { files, output, policy, force, comment } => merge(ctx, files, output, policy, force, comment),
+		Mode::Query { hash, name, same_as, file } => query(ctx, hash, name, same_as, file),
+		Mode::Repair { path, file } => repair(ctx, path, file),
+		Mode::Similar { path, threshold, digests } => similar(ctx, path, threshold, digests),
+		Mode::Dedupe { path, action, keep, apply, log, undo } => dedupe(ctx, path, action, keep, apply, log, undo),
+		Mode::Tag { path, strip, dry_run } => tag(ctx, path, strip, dry_run),
+		Mode::Tui { path, file, quarantine, quarantine_link } => tui(ctx, path, file, quarantine, quarantine_link),
+		#[cfg(unix)]
+		Mode::Daemon { socket } => daemon(ctx, socket),
+		#[cfg(not(unix))]
+		Mode::Daemon { .. } => {
+			eprintln!("The daemon listens on a Unix socket, which this platform lacks.");
+			1
+		}
+		Mode::Serve { paths, listen, token_file } => serve(ctx, paths, listen, token_file),
+		Mode::Generations { action: GenerationsAction::List { path, file } } => generations_list(path, file),
+		Mode::Generations { action: GenerationsAction::Diff { from, to, path, file } } => generations_diff(ctx, from, to, path, file),
+		Mode::Generations { action: GenerationsAction::Log { name, path, file } } => generations_log(ctx, name, path, file),
+		Mode::Generations { action: GenerationsAction::Prune { keep, path, file } } => generations_prune(keep, path, file),
+		Mode::VerifyAuditLog { log } => verify_audit_log(log),
+	}
+}
+
+/// Run `create`, creating a hash file.
+fn create(ctx: Context, args: CreateArgs) -> i32 {
+	let CreateArgs {
+		paths,
+		label,
+		file,
+		force,
+		shard_by,
+		low_memory,
+		unsorted,
+		resume,
+		absolute_paths,
+		comment,
+		record_metadata,
+		tree_hash,
+		bao_outboard,
+		rsync_signatures,
+		signature_block_len,
+		par2,
+		piece_size,
+		torrent,
+		torrent_piece_length,
+		in_toto,
+		sign_with,
+		timestamp_url,
+		keep_generation,
+		publish,
+	} = args;
+	let Context { opts, mut walk_options, mut write_options, reporting, .. } = ctx;
+	write_options.header = comment;
+	// Verify walks the same files, and reads them the same way
+	if opts.git_tracked {
+		write_options.header.push(GIT_TRACKED_HEADER.to_owned());
+	}
+	if opts.text_mode {
+		write_options.header.push(TEXT_MODE_HEADER.to_owned());
+	}
+	walk_options.record_metadata = record_metadata || opts.check_metadata || opts.mtree;
+	// mtree specs list the mode and owner of files
+	walk_options.check_metadata |= opts.mtree;
+	// Verification always reads files, to catch them rotting unchanged
+	// Cached hashes are of the files as-is
+	walk_options.cache = match opts.no_cache || opts.text_mode {
+		true => None,
+		false => opts.cache_path.or_else(default_cache_path),
+	};
+	let roots = match label_roots(&paths, label) {
+		Ok(roots) => roots,
+		Err(rval) => return rval,
+	};
+	if roots.is_some() && (low_memory || absolute_paths || opts.relative_to.is_some()) {
+		eprintln!("Labelled directories can't be used with --low-memory, --absolute-paths or --relative-to.");
+		return 1;
+	}
+	if opts.snapshot.is_some() && (roots.is_some() || absolute_paths || opts.relative_to.is_some()) {
+		eprintln!("--snapshot can't be used with labelled directories, --absolute-paths or --relative-to.");
+		return 1;
+	}
+	if resume && (roots.is_some() || opts.names_only) {
+		eprintln!("--resume can't be used with labelled directories or --names-only.");
+		return 1;
+	}
+	if low_memory && (opts.names_only || tree_hash) {
+		eprintln!("--names-only and --tree-hash can't be used with --low-memory.");
+		return 1;
+	}
+	if (bao_outboard || rsync_signatures || par2.is_some() || piece_size.is_some() || torrent.is_some())
+		&& (roots.is_some() || low_memory || absolute_paths || opts.names_only)
+	{
+		eprintln!("--bao-outboard, --rsync-signatures, --par2, --piece-size and --torrent can't be used with labelled directories, --low-memory, --absolute-paths or --names-only.");
+		return 1;
+	}
+	let Ok(signature_block_len @ 1..) = u32::try_from(signature_block_len) else {
+		eprintln!("--signature-block-len must be from 1 byte to 4G.");
+		return 1;
+	};
+	if in_toto.is_some() && (low_memory || opts.names_only) {
+		eprintln!("--in-toto can't be used with --low-memory or --names-only.");
+		return 1;
+	}
+	if opts.inventory.is_some() && (roots.is_some() || low_memory) {
+		eprintln!("--inventory can't be used with labelled directories or --low-memory.");
+		return 1;
+	}
+	if low_memory && !(opts.known_good.is_empty() && opts.known_bad.is_empty()) {
+		eprintln!("--known-good and --known-bad can't be used with --low-memory.");
+		return 1;
+	}
+	if low_memory && !opts.encrypt_to.is_empty() {
+		eprintln!("--encrypt-to can't be used with --low-memory, hash files are encrypted whole.");
+		return 1;
+	}
+	if low_memory && !opts.look_inside.is_empty() {
+		eprintln!("--look-inside can't be used with --low-memory.");
+		return 1;
+	}
+	if low_memory && opts.mac_metadata {
+		eprintln!("--mac-metadata can't be used with --low-memory.");
+		return 1;
+	}
+	if low_memory && opts.service {
+		eprintln!("--service can't be used with --low-memory.");
+		return 1;
+	}
+	if piece_size == Some(0) {
+		eprintln!("--piece-size must be at least 1 byte.");
+		return 1;
+	}
+	if bao_outboard && !matches!(opts.algorithm, Algorithm::UNSPECIFIED | Algorithm::BLAKE3) {
+		eprintln!("--bao-outboard needs BLAKE3 hashes.");
+		return 1;
+	}
+	if torrent.is_some() && opts.algorithm != Algorithm::BTv2 {
+		eprintln!("--torrent needs btv2 hashes.");
+		return 1;
+	}
+	if !torrent_piece_length.is_power_of_two() || torrent_piece_length < 16 * 1024 {
+		eprintln!("--torrent-piece-length must be a power of two of at least 16K.");
+		return 1;
+	}
+	let Some(file) = file.or_else(|| roots.is_none().then(|| default_file(&paths[0]))) else {
+		eprintln!("Use --file to name the hash file of several directories.");
+		return 1;
+	};
+	let _lock = match lock_run(&file, opts.lock_wait, false) {
+		Ok(lock) => lock,
+		Err(rval) => return rval,
+	};
+	let known = match load_known(&opts.known_good, &opts.known_bad, opts.algorithm) {
+		Ok(known) => known,
+		Err(rval) => return rval,
+	};
+	let path = paths.into_iter().next().unwrap();
+	walk_options.absolute_paths = absolute_paths;
+	let path = match resolve_relative_to(path, opts.relative_to, &mut walk_options) {
+		Ok(path) => path,
+		Err(rval) => return rval,
+	};
+	match (force, file.exists()) {
+		(true, _) | (_, false) => {
+			// Don't hash the hash file, the shards of a previous one, or outboards
+			let mut shards = match file.exists() {
+				true => quickdash::operations::read_shard_index(&file).ok().flatten().unwrap_or_default(),
+				false => Vec::new(),
+			};
+			shards.push(outboard_dir(&file));
+			shards.push(quickdash::operations::signature_dir(&file));
+			shards.push(quickdash::operations::par2_dir(&file));
+			shards.push(quickdash::operations::timestamp_path(&file));
+			shards.push(quickdash::operations::history_dir(&file));
+			shards.push(quickdash::operations::verified_path(&file));
+			shards.push(quickdash::operations::partial_path(&file));
+			shards.push(quickdash::operations::lock_path(&file));
+			shards.push(quickdash::operations::checkpoint_path(&file));
+			shards.extend(torrent.clone());
+			shards.extend(in_toto.clone());
+			let walked: Vec<&Path> = match &roots {
+				Some(roots) => roots.iter().map(|(root, _)| root.as_path()).collect(),
+				None => vec![&path],
+			};
+			for root in walked {
+				for hash_file in shards.iter().chain([&file]) {
+					walk_options.ignore_file(root, hash_file);
+				}
+			}
+			// if this fails, it probably didn't exist
+			let _ = remove_file(&file);
+			// The token of the previous one would only vouch for that
+			let _ = remove_file(quickdash::operations::timestamp_path(&file));
+			let mut report = HashingReport::default();
+			if let Some(roots) = roots {
+				let hashes = quickdash::operations::create_hashes_for_roots(
+					&roots,
+					opts.algorithm,
+					&walk_options,
+					&mut report,
+				);
+				if !report.unhashed.is_empty() {
+					return write_partial(&file, hashes, report, write_options);
+				}
+				if print_errors(&report.errors) {
+					return 1;
+				}
+				if let Some(in_toto) = &in_toto
+					&& let Err(err) = quickdash::operations::write_in_toto(
 						&path,
-						ignored_files,
+						&hashes,
+						&report.notes,
 						opts.algorithm,
-						opts.depth,
-						opts.follow_symlinks,
-					);
-					quickdash::operations::write_hashes(&file, hashes)
+						in_toto,
+						sign_with.as_deref(),
+					) {
+					report.warnings.push(format!("Failed to write {:?}: {}", in_toto, err));
+				}
+				print_warnings(&report.warnings);
+				if opts.names_only {
+					add_layout_digest(&hashes, opts.algorithm, &mut write_options);
+				}
+				if tree_hash {
+					add_tree_hash(&hashes, opts.algorithm, &mut write_options);
+				}
+				let triaged = known.as_ref().is_none_or(|known| print_known(known, &hashes, !opts.known_good.is_empty()));
+				write_options.notes = report.notes;
+				write_options.metadata = report.metadata;
+				let rval = match shard_by {
+					Some(shard_by) => quickdash::operations::write_sharded_hashes(&file, hashes, shard_by, &write_options),
+					None => quickdash::operations::write_hashes(&file, hashes, &write_options),
+				};
+				let rval = if triaged || rval != 0 { rval } else { 1 };
+				return publish_to(&file, &publish, generation(&file, keep_generation, timestamp(&file, timestamp_url.as_deref(), rval)));
+			}
+			// Files are read from the snapshot, and named as they are outside it
+			let snapshot = match opts.snapshot.map(|kind| quickdash::operations::snapshot(kind, &path)).transpose() {
+				Ok(snapshot) => snapshot,
+				Err(err) => {
+					eprintln!("Failed to snapshot {:?}: {}", path, err);
+					return 1;
+				}
+			};
+			let hashed = snapshot.as_ref().map_or(path.as_path(), Snapshot::path);
+			if low_memory {
+				let rval = quickdash::operations::create_hashes_bounded(
+					hashed,
+					opts.algorithm,
+					&walk_options,
+					&file,
+					!unsorted,
+					&write_options,
+					&mut report,
+				);
+				print_warnings(&report.warnings);
+				if print_errors(&report.errors) {
+					return 1;
+				}
+				return publish_to(&file, &publish, generation(&file, keep_generation, timestamp(&file, timestamp_url.as_deref(), rval)));
+			}
+			// Hashes are checkpointed as they're made, for resuming
+			walk_options.checkpoint = Some(quickdash::operations::checkpoint_path(&file));
+			walk_options.resume = resume;
+			let hashes: BTreeMap<PathBuf, String> = quickdash::operations::create_hashes(
+				hashed,
+				opts.algorithm,
+				&walk_options,
+				&mut report,
+			);
+			if !report.unhashed.is_empty() {
+				return write_partial(&file, hashes, report, write_options);
+			}
+			if print_errors(&report.errors) {
+				return 1;
+			}
+			if let Some(piece_size) = piece_size {
+				write_options.pieces = quickdash::operations::record_pieces(
+					walk_options.base(hashed),
+					&hashes,
+					&report.notes,
+					opts.algorithm,
+					piece_size,
+					&mut report.warnings,
+				);
+			}
+			if bao_outboard {
+				quickdash::operations::write_outboards(
+					walk_options.base(hashed),
+					&hashes,
+					&report.notes,
+					&outboard_dir(&file),
+					&mut report.warnings,
+				);
+			}
+			if rsync_signatures {
+				quickdash::operations::write_signatures(
+					walk_options.base(hashed),
+					&hashes,
+					&report.notes,
+					&quickdash::operations::signature_dir(&file),
+					signature_block_len,
+					&walk_options.hash_options,
+					&mut report.warnings,
+				);
+			}
+			if let Some(torrent) = &torrent
+				&& let Err(err) = quickdash::operations::write_torrent(
+					walk_options.base(hashed),
+					&hashes,
+					&report.notes,
+					torrent_piece_length,
+					torrent,
+					&mut report.warnings,
+				) {
+				report.warnings.push(format!("Failed to write {:?}: {}", torrent, err));
+			}
+			if let Some(in_toto) = &in_toto
+				&& let Err(err) = quickdash::operations::write_in_toto(
+					walk_options.base(hashed),
+					&hashes,
+					&report.notes,
+					opts.algorithm,
+					in_toto,
+					sign_with.as_deref(),
+				) {
+				report.warnings.push(format!("Failed to write {:?}: {}", in_toto, err));
+			}
+			print_warnings(&report.warnings);
+			if opts.names_only {
+				add_layout_digest(&hashes, opts.algorithm, &mut write_options);
+			}
+			if tree_hash {
+				add_tree_hash(&hashes, opts.algorithm, &mut write_options);
+			}
+			let inventoried = match &opts.inventory {
+				Some(command) => {
+					let root = walk_options.base(&path);
+					let run = InventoryRun { host: &reporting.host, root: &root, manifest: &file, command: "create", algorithm: opts.algorithm };
+					inventory(command, &run, &hashes, None)
+				}
+				None => true,
+			};
+			let triaged = known.as_ref().is_none_or(|known| print_known(known, &hashes, !opts.known_good.is_empty()));
+			// Files without a note are regular files
+			let recovered: Vec<PathBuf> = match par2 {
+				Some(_) => hashes.keys().filter(|name| !report.notes.contains_key(*name)).cloned().collect(),
+				None => Vec::new(),
+			};
+			write_options.notes = report.notes;
+			write_options.metadata = report.metadata;
+			let rval = match shard_by {
+				Some(shard_by) => quickdash::operations::write_sharded_hashes(&file, hashes, shard_by, &write_options),
+				None => quickdash::operations::write_hashes(&file, hashes, &write_options),
+			};
+			// The hash file holds them all now
+			if rval == 0 {
+				let _ = remove_file(quickdash::operations::checkpoint_path(&file));
+			}
+			let rval = if inventoried && triaged || rval != 0 { rval } else { 1 };
+			let rval = match par2 {
+				Some(redundancy) if rval == 0 => recover(&file, walk_options.base(hashed), &recovered, redundancy),
+				_ => rval,
+			};
+			publish_to(&file, &publish, generation(&file, keep_generation, timestamp(&file, timestamp_url.as_deref(), rval)))
+		}
+		(false, true) => {
+			eprintln!("File already exists. Use --force to overwrite.");
+			1
+		}
+	}
+}
+
+/// Run `verify`, verifying a hash file.
+fn verify(ctx: Context, args: VerifyArgs) -> i32 {
+	let VerifyArgs {
+		paths,
+		label,
+		file,
+		signature,
+		quick,
+		sample,
+		record_verified,
+		stale_after,
+		scrub_percent,
+		scrub_bytes,
+		quarantine,
+		quarantine_link,
+		fix,
+		yes,
+		resume,
+	} = args;
+	let Context { opts, mut walk_options, read_options, mut write_options, reporting } = ctx;
+	let scrub = match (scrub_percent, scrub_bytes) {
+		(Some(percent), _) if !(percent > 0.0 && percent <= 100.0) => {
+			eprintln!("--scrub-percent must be more than 0 and at most 100.");
+			return 1;
+		}
+		(Some(percent), _) => Some(ScrubBudget::Percent(percent)),
+		(None, Some(bytes)) => Some(ScrubBudget::Bytes(bytes)),
+		(None, None) => None,
+	};
+	let record_verified = record_verified || scrub.is_some();
+	let roots = match label_roots(&paths, label) {
+		Ok(roots) => roots,
+		Err(rval) => return rval,
+	};
+	if roots.is_some() && (opts.relative_to.is_some() || opts.inventory.is_some()) {
+		eprintln!("Labelled directories can't be used with --relative-to or --inventory.");
+		return 1;
+	}
+	if opts.snapshot.is_some() && (roots.is_some() || opts.relative_to.is_some()) {
+		eprintln!("--snapshot can't be used with labelled directories or --relative-to.");
+		return 1;
+	}
+	if resume && (roots.is_some() || opts.names_only) {
+		eprintln!("--resume can't be used with labelled directories or --names-only.");
+		return 1;
+	}
+	let Some(file) = file.or_else(|| roots.is_none().then(|| default_file(&paths[0]))) else {
+		eprintln!("Use --file to name the hash file of several directories.");
+		return 1;
+	};
+	let fetched = match fetch_hash_file(&file, signature.as_deref()) {
+		Ok(fetched) => fetched,
+		Err(rval) => return rval,
+	};
+	if fetched.is_some() && (fix || record_verified || resume) {
+		eprintln!("--fix, --record-verified, --resume and scrubbing write next to the hash file, which can't be a fetched one.");
+		return 1;
+	}
+	let file = fetched.as_ref().map_or(file, |fetched| fetched.path().to_owned());
+	// Runs writing next to the hash file are on their own
+	let _lock = match lock_run(&file, opts.lock_wait, !(fix || record_verified || resume)) {
+		Ok(lock) => lock,
+		Err(rval) => return rval,
+	};
+	// Multihashes and CIDs name the algorithm they were made with
+	let loaded = quickdash::operations::read_shard_index(&file).and_then(|shards| match opts.algorithm {
+		Algorithm::UNSPECIFIED => {
+			let named = quickdash::operations::read_named_algorithm(&file, &read_options)?;
+			// Fetched checksum files like `SHA256SUMS` are named after theirs
+			let algo = named
+				.or_else(|| fetched.as_ref().and_then(|_| quickdash::operations::sums_algorithm(&file)))
+				.unwrap_or(Algorithm::UNSPECIFIED);
+			Ok((shards, algo))
+		}
+		algo => Ok((shards, algo)),
+	});
+	let (shards, algo) = match loaded {
+		Ok(loaded) => loaded,
+		Err(rval) => {
+			reporting.finish_run("verify", &file, &RunMetrics::default(), Err(&rval));
+			return rval.exit_value();
+		}
+	};
+	let header = quickdash::operations::read_header(&file, &read_options).unwrap_or_default();
+	// Text is verified as text, and binary as binary
+	match (opts.text_mode, header.iter().any(|line| line == TEXT_MODE_HEADER)) {
+		(true, false) => {
+			eprintln!("{:?} wasn't made with --text-mode.", file);
+			return 1;
+		}
+		(_, text_mode) => walk_options.hash_options.text_mode = text_mode,
+	}
+	// Hash files of the files git tracks are verified against those only
+	if !opts.git_tracked && header.iter().any(|line| line == GIT_TRACKED_HEADER) {
+		for path in &paths {
+			if let Err(err) = walk_options.track_git(path) {
+				eprintln!("{:?} is one of the files git tracks, but listing them in {:?} failed: {}", file, path, err);
+				return 1;
+			}
+		}
+	}
+	if fix && shards.is_some() {
+		eprintln!("--fix can't be used with sharded hash files.");
+		return 1;
+	}
+	if fix && opts.encrypt_to.is_empty() && is_encrypted(&file) {
+		eprintln!("Use --encrypt-to to fix an encrypted hash file, it's written again.");
+		return 1;
+	}
+	// Updated entries get their current metadata
+	walk_options.record_metadata |= fix;
+	if let Err(rval) = check_timestamp(&file) {
+		return rval;
+	}
+	let known = match load_known(&opts.known_good, &opts.known_bad, algo) {
+		Ok(known) => known,
+		Err(rval) => return rval,
+	};
+	let (outboards, token) = (outboard_dir(&file), quickdash::operations::timestamp_path(&file));
+	let (signatures, recovery) = (quickdash::operations::signature_dir(&file), quickdash::operations::par2_dir(&file));
+	let (history, verified) = (quickdash::operations::history_dir(&file), quickdash::operations::verified_path(&file));
+	let (partial, lock) = (quickdash::operations::partial_path(&file), quickdash::operations::lock_path(&file));
+	let checkpoint = quickdash::operations::checkpoint_path(&file);
+	let hash_files: Vec<&Path> = shards
+		.iter()
+		.flatten()
+		.map(PathBuf::as_path)
+		.chain([file.as_path(), outboards.as_path(), signatures.as_path(), recovery.as_path(), token.as_path(), history.as_path(), verified.as_path()])
+		.chain([partial.as_path(), lock.as_path(), checkpoint.as_path()])
+		.chain(quarantine.as_deref())
+		.collect();
+	walk_options.recorded = match RecordedMetadata::load(&file, &read_options) {
+		Ok(recorded) if quick => Some(recorded.quick(sample)),
+		Ok(recorded) => match scrub {
+			Some(budget) => match LastVerified::load(&file) {
+				Ok(verified) => Some(recorded.scrub(&verified, budget)),
+				Err(err) => {
+					eprintln!("Failed to read when files last verified: {}", err);
+					return 1;
 				}
-				(false, true) => {
-					eprintln!("File already exists. Use --force to overwrite.");
-					1
+			},
+			None => Some(recorded),
+		},
+		Err(rval) => return rval.exit_value(),
+	};
+	let mut report = HashingReport::default();
+	let (hashes, base) = match &roots {
+		Some(roots) => {
+			// Don't hash the hash file
+			for (root, _) in roots {
+				for hash_file in &hash_files {
+					walk_options.ignore_file(root, hash_file);
 				}
 			}
+			(quickdash::operations::create_hashes_for_roots(roots, algo, &walk_options, &mut report), None)
 		}
-		Mode::Verify { path, file } => {
-			let ignored_files = opts.ignored_files
-				.into_iter()
-				.map(|f| PathBuf::from_str(&f).unwrap())
-				.collect();
+		None => {
+			// Name each file the same way its entry in the hash file is
+			match quickdash::operations::relative_entries(&file, &read_options) {
+				Ok(Some(relative_names)) => {
+					walk_options.absolute_paths = true;
+					walk_options.relative_names = relative_names;
+				}
+				Ok(None) => walk_options.absolute_paths = false,
+				Err(rval) => return rval.exit_value(),
+			}
+			let path = paths.into_iter().next().unwrap();
+			let path = match resolve_relative_to(path, opts.relative_to, &mut walk_options) {
+				Ok(path) => path,
+				Err(rval) => return rval,
+			};
+			for hash_file in &hash_files {
+				walk_options.ignore_file(&path, hash_file);
+			}
+			if opts.snapshot.is_some() && walk_options.absolute_paths {
+				eprintln!("--snapshot can't be used with hash files of absolute paths.");
+				return 1;
+			}
+			let snapshot = match opts.snapshot.map(|kind| quickdash::operations::snapshot(kind, &path)).transpose() {
+				Ok(snapshot) => snapshot,
+				Err(err) => {
+					eprintln!("Failed to snapshot {:?}: {}", path, err);
+					return 1;
+				}
+			};
+			let hashed = snapshot.as_ref().map_or(path.as_path(), Snapshot::path);
+			// What was read is checkpointed, for resuming
+			if resume {
+				walk_options.checkpoint = Some(checkpoint.clone());
+				walk_options.resume = true;
+			}
 			let hashes = quickdash::operations::create_hashes(
-				&path,
-				ignored_files,
-				opts.algorithm,
-				opts.depth,
-				opts.follow_symlinks,
+				hashed,
+				algo,
+				&walk_options,
+				&mut report,
 			);
-			let file = file.unwrap_or_else(|| default_file(&path));
-			match quickdash::operations::read_hashes(&file) {
-				Ok(loaded_hashes) => {
-					let compare_result =
-						quickdash::operations::compare_hashes(hashes, loaded_hashes);
-					quickdash::operations::write_hash_comparison_results(
-						&mut stdout(),
-						&mut stderr(),
-						compare_result,
-					)
-				}
-				Err(rval) => rval,
-			}
-			.exit_value()
-		}
-		Mode::Check { path, file } => {
-			// Read hash file
-			// Check for files mentioned in hashfile
-			// Hash all existing files mentioned in hashfile
-			let mut file = file.unwrap_or_else(|| default_file(&path));
-			if file.is_relative(){
-				let cwd = std::env::current_dir().unwrap();
-				file = cwd.join(file);
-			}
-			assert!(file.exists(), "file did not exist {:?}", file);
-			match quickdash::operations::read_hashes(&file) {
-				Ok(loaded_hashes) => {
-					let mut algo = opts.algorithm;
-					if opts.algorithm == Algorithm::UNSPECIFIED {
-						// try to autodetect hash algorithm from hashes read, ignore the "------..."
-						let example_hash = loaded_hashes.values()
-							.filter(|s| !s.starts_with("----"))
-							.next().unwrap();
-						algo = Algorithm::autodetect_from_hash(&example_hash);
+			(hashes, Some(walk_options.base(&path).to_owned()))
+		}
+	};
+	let walk_failed = print_errors(&report.errors);
+	let run_hashes = (opts.inventory.is_some() || known.is_some() || fix).then(|| hashes.clone());
+	let compare_result = match shards {
+		Some(shards) => quickdash::operations::compare_sharded_hashes(hashes, &shards, &read_options),
+		// Sorted hash files are compared while reading, unsorted ones are loaded whole
+		None => match quickdash::operations::stream_hashes(&file, &read_options)
+			.and_then(|loaded_hashes| quickdash::operations::compare_sorted_hashes(&hashes, loaded_hashes))
+		{
+			Ok(Some(compare_result)) => Ok(compare_result),
+			Ok(None) => quickdash::operations::read_hashes(&file, &read_options)
+				.map(|loaded_hashes| quickdash::operations::compare_hashes(hashes, loaded_hashes)),
+			Err(rval) => Err(rval),
+		},
+	};
+	let compare_result = compare_result.map(|compare_result| {
+		let mut compare_result = quickdash::operations::apply_recorded_metadata(compare_result, &report);
+		// Files left unread when stopped aren't gone
+		if let Ok((compare_results, _)) = &mut compare_result {
+			compare_results.retain(|res| !matches!(res, CompareResult::FileRemoved(file) if report.unhashed.contains(file)));
+		}
+		if let Some(recorded) = &walk_options.recorded {
+			compare_result = quickdash::operations::apply_recorded_pieces(compare_result, recorded, algo, |file| {
+				match (&roots, &base) {
+					(Some(roots), _) => roots.iter().find_map(|(path, label)| Some(path.join(file.strip_prefix(label).ok()?))),
+					(None, Some(base)) => Some(base.join(file)),
+					(None, None) => None,
+				}
+			});
+		}
+		compare_result
+	});
+	// A run that got to compare reports on the files of those it resumed too
+	if resume && report.unhashed.is_empty() && compare_result.is_ok() {
+		let _ = remove_file(&checkpoint);
+	}
+	let run = RunMetrics { files_scanned: report.files_scanned, bytes_hashed: report.bytes_hashed };
+	reporting.finish_run("verify", &file, &run, compare_result.as_ref());
+	let unverified = match &compare_result {
+		Ok(Ok((compare_results, file_compare_results))) if record_verified => {
+			track_verified(&file, compare_results, file_compare_results, stale_after)
+		}
+		_ => Vec::new(),
+	};
+	let quarantined = match (&quarantine, &compare_result) {
+		(Some(dir), Ok(Ok((_, file_compare_results)))) => {
+			quickdash::operations::quarantine(dir, file_compare_results, quarantine_link, |file| match (&roots, &base) {
+				(Some(roots), _) => roots.iter().find_map(|(path, label)| Some(path.join(file.strip_prefix(label).ok()?))),
+				(None, Some(base)) => Some(base.join(file)),
+				(None, None) => None,
+			})
+		}
+		_ => Vec::new(),
+	};
+	let inventoried = match (&opts.inventory, &run_hashes, &base, &compare_result) {
+		(Some(command), Some(hashes), Some(root), Ok(Ok((compare_results, file_compare_results)))) => {
+			let run = InventoryRun { host: &reporting.host, root, manifest: &file, command: "verify", algorithm: algo };
+			inventory(command, &run, hashes, Some((compare_results, file_compare_results)))
+		}
+		_ => true,
+	};
+	let mismatches = match (&run_hashes, &compare_result) {
+		(Some(hashes), Ok(Ok((compare_results, file_compare_results)))) if fix => {
+			// Files whose size changed aren't read when their metadata is recorded
+			Mismatch::of(compare_results, file_compare_results, |file| match hashes.get(file)? {
+				hash if hash.starts_with('-') => {
+					let path = match (&roots, &base) {
+						(Some(roots), _) => roots.iter().find_map(|(path, label)| Some(path.join(file.strip_prefix(label).ok()?)))?,
+						(None, Some(base)) => base.join(file),
+						(None, None) => return None,
+					};
+					quickdash::try_hash_file(algo, &path, &walk_options.hash_options).ok()
+				}
+				hash => Some(hash.clone()),
+			})
+		}
+		_ => Vec::new(),
+	};
+	let rval = match compare_result {
+		Ok(compare_result) => quickdash::operations::write_hash_comparison_results(
+			&mut stdout(),
+			&mut stderr(),
+			compare_result,
+			&report.warnings,
+		),
+		Err(rval) => rval,
+	};
+	if !mismatches.is_empty() {
+		let fixes = choose_fixes(&mismatches, yes);
+		write_options.algorithm = algo;
+		if !fixes.is_empty() {
+			match quickdash::operations::fix_hashes(&file, &fixes, &report.metadata, &read_options, write_options) {
+				Ok(0) => println!("Fixed {} entries of {:?}", fixes.len(), file),
+				Ok(_) => return 1,
+				Err(rval) => return rval.exit_value(),
+			}
+		}
+	}
+	print_unverified(&unverified);
+	for line in &quarantined {
+		println!("{}", line);
+	}
+	let triaged = match (&known, &run_hashes) {
+		(Some(known), Some(hashes)) => print_known(known, hashes, !opts.known_good.is_empty()),
+		_ => true,
+	};
+	if !report.unhashed.is_empty() {
+		eprintln!("Stopped with {} files left unverified.", report.unhashed.len());
+		return quickdash::operations::STOPPED_EXIT_CODE;
+	}
+	match rval.exit_value() {
+		0 if !inventoried || !triaged || walk_failed => 1,
+		rval => rval,
+	}
+}
+
+/// Run `check`, checking the files a hash file lists.
+fn check(
+	ctx: Context,
+	paths: Vec<PathBuf>,
+	label: Vec<String>,
+	file: Option<PathBuf>,
+	signature: Option<PathBuf>,
+	record_verified: bool,
+	stale_after: Option<u64>,
+) -> i32 {
+	let Context { opts, walk_options, read_options, reporting, .. } = ctx;
+	if opts.names_only {
+		eprintln!("--names-only can't be used with check, use verify.");
+		return 1;
+	}
+	if !opts.look_inside.is_empty() {
+		eprintln!("--look-inside can't be used with check, use verify.");
+		return 1;
+	}
+	if opts.mac_metadata {
+		eprintln!("--mac-metadata can't be used with check, use verify.");
+		return 1;
+	}
+	// Read hash file
+	// Check for files mentioned in hashfile
+	// Hash all existing files mentioned in hashfile
+	let roots = match label_roots(&paths, label) {
+		Ok(roots) => roots,
+		Err(rval) => return rval,
+	};
+	let Some(mut file) = file.or_else(|| roots.is_none().then(|| default_file(&paths[0]))) else {
+		eprintln!("Use --file to name the hash file of several directories.");
+		return 1;
+	};
+	let fetched = match fetch_hash_file(&file, signature.as_deref()) {
+		Ok(fetched) => fetched,
+		Err(rval) => return rval,
+	};
+	if fetched.is_some() && record_verified {
+		eprintln!("--record-verified writes next to the hash file, which can't be a fetched one.");
+		return 1;
+	}
+	if let Some(fetched) = &fetched {
+		file = fetched.path().to_owned();
+	}
+	if file.is_relative(){
+		let cwd = std::env::current_dir().unwrap();
+		file = cwd.join(file);
+	}
+	assert!(file.exists(), "file did not exist {:?}", file);
+	let _lock = match lock_run(&file, opts.lock_wait, !record_verified) {
+		Ok(lock) => lock,
+		Err(rval) => return rval,
+	};
+	if let Err(rval) = check_timestamp(&file) {
+		return rval;
+	}
+	// Sharded hash files are checked one shard at a time
+	let shards = match quickdash::operations::read_shard_index(&file) {
+		Ok(Some(shards)) => shards,
+		Ok(None) => vec![file.clone()],
+		Err(rval) => return rval.exit_value(),
+	};
+
+	let base = opts.relative_to.as_deref().unwrap_or(&paths[0]);
+	let mut algo = opts.algorithm;
+	if algo == Algorithm::UNSPECIFIED && fetched.is_some() {
+		// Fetched checksum files like `SHA256SUMS` are named after theirs
+		algo = quickdash::operations::sums_algorithm(&file).unwrap_or(algo);
+	}
+	// Shards that can't be read fail the run
+	let mut outcome = Ok(Ok((Vec::new(), Vec::new())));
+	let mut run = RunMetrics::default();
+	let mut report = HashingReport::default();
+	for shard in shards {
+		let mut loaded_hashes = match quickdash::operations::read_hashes(&shard, &read_options) {
+			Ok(loaded_hashes) => loaded_hashes,
+			Err(rval) => {
+				outcome = Err(rval);
+				break;
+			}
+		};
+		if algo == Algorithm::UNSPECIFIED {
+			// Multihashes and CIDs name the algorithm they were made with
+			match quickdash::operations::read_named_algorithm(&shard, &read_options) {
+				Ok(named) => algo = named.unwrap_or(algo),
+				Err(rval) => {
+					outcome = Err(rval);
+					break;
+				}
+			}
+		}
+		if algo == Algorithm::UNSPECIFIED {
+			// try to autodetect hash algorithm from hashes read, ignore the "------..."
+			if let Some(example_hash) = loaded_hashes.values().find(|s| !s.starts_with("----")) {
+				algo = Algorithm::autodetect_from_hash(example_hash);
+			}
+		}
+
+		// Files recorded without being hashed aren't read
+		let skipped = quickdash::operations::take_placeholders(&mut loaded_hashes);
+		let files: Vec<PathBuf> = loaded_hashes
+			.keys()
+			.map(|f|f.to_owned())
+			.collect();
+		if opts.metrics_textfile.is_some() {
+			run.files_scanned += files.len();
+			run.bytes_hashed += files
+				.iter()
+				.filter_map(|file| match &roots {
+					Some(roots) => roots.iter().find_map(|(path, label)| Some(path.join(file.strip_prefix(label).ok()?))),
+					None => Some(base.join(file)),
+				})
+				.filter_map(|path| metadata(path).ok())
+				.filter(|metadata| metadata.is_file())
+				.map(|metadata| metadata.len())
+				.sum::<u64>();
+		}
+		let hashes: BTreeMap<PathBuf, String> = match &roots {
+			Some(roots) => quickdash::operations::create_hashes_for_labelled_files(roots, files, algo, &walk_options, &mut report),
+			None => quickdash::operations::create_hashes_for_files(base, files, algo, &walk_options, &mut report),
+		};
+		if opts.check_metadata {
+			let recorded = match RecordedMetadata::load(&shard, &read_options) {
+				Ok(recorded) => recorded,
+				Err(rval) => {
+					outcome = Err(rval);
+					break;
+				}
+			};
+			if let Ok(Ok((compare_results, _))) = &mut outcome {
+				compare_results.extend(recorded.metadata_changes(|file| match &roots {
+					Some(roots) => roots.iter().find_map(|(path, label)| Some(path.join(file.strip_prefix(label).ok()?))),
+					None => Some(base.join(file)),
+				}));
+			}
+		}
+
+		match (&mut outcome, quickdash::operations::compare_hashes(hashes, loaded_hashes)) {
+			(Ok(Ok((compare_results, file_compare_results))), Ok((results, file_results))) => {
+				compare_results.extend(results);
+				compare_results.extend(skipped);
+				file_compare_results.extend(file_results);
+			}
+			(_, Err(err)) => {
+				outcome = Ok(Err(err));
+				break;
+			}
+			_ => unreachable!(),
+		}
+	}
+
+	reporting.finish_run("check", &file, &run, outcome.as_ref());
+	let compare_result = match outcome {
+		Ok(compare_result) => compare_result,
+		Err(rval) => return rval.exit_value(),
+	};
+	let unverified = match &compare_result {
+		Ok((compare_results, file_compare_results)) if record_verified => {
+			track_verified(&file, compare_results, file_compare_results, stale_after)
+		}
+		_ => Vec::new(),
+	};
+	let err = quickdash::operations::write_hash_comparison_results(
+		&mut stdout(),
+		&mut stderr(),
+		compare_result,
+		&report.warnings,
+	);
+	print_unverified(&unverified);
+	println!("{:#?}", err);
+	err.exit_value()
+}
+
+/// Run `update`, reading only the files changed since the hash file was last updated.
+fn update(ctx: Context, path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let Context { opts, mut walk_options, read_options, mut write_options, .. } = ctx;
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let _lock = match lock_run(&file, opts.lock_wait, false) {
+		Ok(lock) => lock,
+		Err(rval) => return rval,
+	};
+	match quickdash::operations::read_shard_index(&file) {
+		Ok(None) => {}
+		Ok(Some(_)) => {
+			eprintln!("update can't be used with sharded hash files.");
+			return 1;
+		}
+		Err(rval) => return rval.exit_value(),
+	}
+	let header = match quickdash::operations::read_header(&file, &read_options) {
+		Ok(header) => header,
+		Err(rval) => return rval.exit_value(),
+	};
+	let journal = quickdash::operations::changes_path(&file);
+	// Changes recorded by record-changes are used over the USN journal
+	let changes = match journal.exists() {
+		true => recorded_changes(&journal, &file, &header),
+		false => usn_changes(&path, &file, &header),
+	};
+	let (position, changed) = match changes {
+		Ok(changes) => changes,
+		Err(rval) => return rval,
+	};
+	// Files are walked and read the way they were
+	walk_options.hash_options.text_mode = header.iter().any(|line| line == TEXT_MODE_HEADER);
+	if !opts.git_tracked
+		&& header.iter().any(|line| line == GIT_TRACKED_HEADER)
+		&& let Err(err) = walk_options.track_git(&path)
+	{
+		eprintln!("{:?} is one of the files git tracks, but listing them in {:?} failed: {}", file, path, err);
+		return 1;
+	}
+	let algo = match opts.algorithm {
+		Algorithm::UNSPECIFIED => match quickdash::operations::read_named_algorithm(&file, &read_options) {
+			Ok(named) => named.unwrap_or_default(),
+			Err(rval) => return rval.exit_value(),
+		},
+		algo => algo,
+	};
+	if let Some(changed) = changed {
+		match Unchanged::load(&file, &read_options, changed) {
+			Ok(unchanged) => walk_options.unchanged = Some(unchanged),
+			Err(rval) => return rval.exit_value(),
+		}
+	}
+	walk_options.ignore_file(&path, &file);
+	walk_options.ignore_file(&path, &journal);
+	walk_options.ignore_file(&path, &quickdash::operations::lock_path(&file));
+	let mut report = HashingReport::default();
+	let hashes = quickdash::operations::create_hashes(&path, algo, &walk_options, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	write_options.header = header
+		.into_iter()
+		.filter(|line| {
+			![USN_HEADER_PREFIX, CHANGES_HEADER_PREFIX, TREE_HASH_PREFIX, LAYOUT_DIGEST_PREFIX].iter().any(|prefix| line.starts_with(prefix))
+		})
+		.chain(position)
+		.collect();
+	write_options.algorithm = algo;
+	write_options.notes = report.notes;
+	write_options.metadata = report.metadata;
+	quickdash::operations::write_hashes(&file, hashes, &write_options)
+}
+
+/// Run `record-changes`, recording which paths change under a directory until stopped.
+fn record_changes(ctx: Context, path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let Context { mut walk_options, .. } = ctx;
+	let file = file.unwrap_or_else(|| default_file(&path));
+	walk_options.ignore_file(&path, &file);
+	walk_options.ignore_file(&path, &quickdash::operations::lock_path(&file));
+	match quickdash::operations::record_changes(&path, &quickdash::operations::changes_path(&file), &walk_options) {
+		Ok(()) => 0,
+		Err(err) => {
+			eprintln!("Failed to record the changes under {:?}: {}", path, err);
+			1
+		}
+	}
+}
+
+/// Run `tree-hash`, printing a single Merkle-style root hash of a directory.
+fn tree_hash(ctx: Context, path: PathBuf, expect: Option<String>) -> i32 {
+	let Context { opts, walk_options, .. } = ctx;
+	let (algo, expected) = match expected_hash(expect.as_deref(), opts.algorithm) {
+		Ok(expected) => expected,
+		Err(rval) => return rval,
+	};
+	let mut report = HashingReport::default();
+	let hashes = quickdash::operations::create_hashes(&path, algo, &walk_options, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	let root = quickdash::operations::tree_hash(&hashes, algo);
+	println!("{}", opts.hash_encoding.encode(&root, algo));
+	match expected {
+		Some(expected) if expected != root => {
+			eprintln!("Tree hash doesn't match the expected {}", expect.unwrap_or_default());
+			Error::NFilesDiffer(1).exit_value()
+		}
+		_ => 0,
+	}
+}
+
+/// Run `remote`, hashing a remote directory over SSH and verifying it against a local hash file.
+fn remote(
+	ctx: Context,
+	target: String,
+	file: Option<PathBuf>,
+	output: Option<PathBuf>,
+	agent: String,
+	upload: bool,
+) -> i32 {
+	let Context { opts, read_options, reporting, .. } = ctx;
+	let (host, path) = match quickdash::operations::parse_remote_target(&target) {
+		Ok(target) => target,
+		Err(err) => {
+			eprintln!("{}", err);
+			return 1;
+		}
+	};
+	let loaded = match file.as_deref().map(|file| quickdash::operations::read_hashes(file, &read_options)).transpose() {
+		Ok(loaded) => loaded,
+		Err(rval) => return rval.exit_value(),
+	};
+	// The remote side hashes with the algorithm of the local hash file
+	let algo = match (&file, &loaded, opts.algorithm) {
+		(Some(file), Some(loaded), Algorithm::UNSPECIFIED) => match quickdash::operations::read_named_algorithm(file, &read_options) {
+			Ok(named) => named
+				.or_else(|| loaded.values().find(|hash| !hash.starts_with("----")).map(|hash| Algorithm::autodetect_from_hash(hash)))
+				.unwrap_or(Algorithm::UNSPECIFIED),
+			Err(rval) => return rval.exit_value(),
+		},
+		(_, _, algo) => algo,
+	};
+	let temporary = output.is_none().then(|| std::env::temp_dir().join(format!("quickdash-remote-{}.hash", std::process::id())));
+	let manifest = output.as_deref().or(temporary.as_deref()).unwrap();
+	// A copy of the hash file in the remote directory isn't one of its files
+	let ignored: Vec<String> =
+		opts.ignored_files.iter().cloned().chain(file.as_deref().and_then(Path::file_name).map(|name| name.to_string_lossy().into_owned())).collect();
+	let made = quickdash::operations::remote_manifest(&host, path, &agent, upload, algo, &ignored, manifest);
+	let remote = (made.is_ok() && loaded.is_some()).then(|| quickdash::operations::read_hashes(manifest, &read_options));
+	if made.is_ok()
+		&& loaded.is_none()
+		&& let Some(temporary) = &temporary
+	{
+		// Not verifying, so the hash file is what's asked for
+		match read_to_string(temporary) {
+			Ok(manifest) => print!("{}", manifest),
+			Err(err) => eprintln!("Failed to read {:?}: {}", temporary, err),
+		}
+	}
+	if let Some(temporary) = &temporary {
+		let _ = remove_file(temporary);
+	}
+	if let Err(err) = made {
+		eprintln!("Failed to hash {}: {}", target, err);
+		return 1;
+	}
+	let (Some(remote), Some(loaded)) = (remote, loaded) else {
+		return 0;
+	};
+	let manifest = file.as_deref().unwrap_or(Path::new(&target));
+	let run = RunMetrics { files_scanned: loaded.len(), ..Default::default() };
+	let remote = match remote {
+		Ok(remote) => remote,
+		Err(rval) => {
+			reporting.finish_run("remote", manifest, &run, Err(&rval));
+			return rval.exit_value();
+		}
+	};
+	let outcome = quickdash::operations::compare_hashes(remote, loaded);
+	reporting.finish_run("remote", manifest, &run, Ok(&outcome));
+	quickdash::operations::write_hash_comparison_results(&mut stdout(), &mut stderr(), outcome, &[]).exit_value()
+}
+
+/// Run `cmp`, comparing two directories without a hash file.
+fn cmp(ctx: Context, paths: Vec<PathBuf>, first_difference: bool) -> i32 {
+	let Context { opts, walk_options, .. } = ctx;
+	let (a, b) = (&paths[0], &paths[1]);
+	for path in [a, b] {
+		if !path.is_dir() {
+			eprintln!("{:?} isn't a directory.", path);
+			return 1;
+		}
+	}
+	let mut report = HashingReport::default();
+	let comparison = quickdash::operations::compare_trees(a, b, opts.algorithm, &walk_options, &mut report);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	for name in &comparison.only_in_a {
+		println!("Only in {}: {}", a.display(), name.display());
+	}
+	for name in &comparison.only_in_b {
+		println!("Only in {}: {}", b.display(), name.display());
+	}
+	for name in &comparison.differing {
+		println!("Files {} and {} differ", a.join(name).display(), b.join(name).display());
+		if first_difference {
+			match quickdash::operations::first_difference(&a.join(name), &b.join(name)) {
+				Ok(Some(difference)) => print_difference(&difference),
+				Ok(None) => println!("  no longer differ"),
+				Err(err) => println!("  couldn't re-read: {}", err),
+			}
+		}
+	}
+	println!(
+		"{} identical, {} only in {}, {} only in {}, {} differing, {} unreadable",
+		comparison.identical,
+		comparison.only_in_a.len(),
+		a.display(),
+		comparison.only_in_b.len(),
+		b.display(),
+		comparison.differing.len(),
+		comparison.unreadable.len()
+	);
+	print_warnings(&report.warnings);
+	match comparison.is_identical() {
+		true => 0,
+		false => Error::NFilesDiffer(
+			(comparison.only_in_a.len() + comparison.only_in_b.len() + comparison.differing.len() + comparison.unreadable.len()) as i32,
+		)
+		.exit_value(),
+	}
+}
+
+/// Run `sum`, hashing a file at a URL as it's downloaded.
+fn sum(ctx: Context, url: String, expect: Option<String>) -> i32 {
+	let Context { opts, walk_options, .. } = ctx;
+	if !is_url(Path::new(&url)) {
+		eprintln!("{:?} isn't an http:// or https:// URL.", url);
+		return 1;
+	}
+	let (algo, expected) = match expected_hash(expect.as_deref(), opts.algorithm) {
+		Ok(expected) => expected,
+		Err(rval) => return rval,
+	};
+	let hash = match quickdash::operations::hash_url(&url, algo, &walk_options.hash_options) {
+		Ok(hash) => hash,
+		Err(err) => {
+			eprintln!("Failed to hash {}: {}", url, err);
+			return 1;
+		}
+	};
+	println!("{}  {}", opts.hash_encoding.encode(&hash, algo), url);
+	match expected {
+		Some(expected) if expected != hash => {
+			eprintln!("Hash doesn't match the expected {}", expect.unwrap_or_default());
+			Error::NFilesDiffer(1).exit_value()
+		}
+		_ => 0,
+	}
+}
+
+/// Run `verify-range`, verifying a byte range of a file using its bao outboard.
+fn verify_range(
+	ctx: Context,
+	name: PathBuf,
+	offset: u64,
+	length: Option<u64>,
+	path: PathBuf,
+	file: Option<PathBuf>,
+) -> i32 {
+	let Context { opts, read_options, .. } = ctx;
+	if !matches!(opts.algorithm, Algorithm::UNSPECIFIED | Algorithm::BLAKE3) {
+		eprintln!("verify-range needs BLAKE3 hashes.");
+		return 1;
+	}
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let hashes = match quickdash::operations::read_hashes(&file, &read_options) {
+		Ok(hashes) => hashes,
+		Err(rval) => return rval.exit_value(),
+	};
+	let Some(hash) = hashes.get(&name) else {
+		eprintln!("{:?} isn't in {:?}.", name, file);
+		return 1;
+	};
+	let data = opts.relative_to.as_deref().unwrap_or(&path).join(&name);
+	let end = length.map_or(u64::MAX, |length| offset.saturating_add(length));
+	match quickdash::operations::verify_range(&data, &outboard_path(&outboard_dir(&file), &name), hash, offset, end) {
+		Ok(RangeCheck::Matches) => {
+			match length {
+				Some(length) => println!("Bytes {}..{} of {:?} match", offset, offset + length, name),
+				None => println!("Bytes {}.. of {:?} match", offset, name),
+			}
+			0
+		}
+		Ok(RangeCheck::Differs { start, end }) => {
+			println!("Bytes {}..{} of {:?} don't match", start, end, name);
+			Error::NFilesDiffer(1).exit_value()
+		}
+		Ok(RangeCheck::Resized { was, is }) => {
+			println!("File {:?} is {} bytes, was {}", name, is, was);
+			Error::NFilesDiffer(1).exit_value()
+		}
+		Err(err) => {
+			eprintln!("Failed to verify {:?}: {}", name, err);
+			1
+		}
+	}
+}
+
+/// Run `check-torrent`, verifying downloaded data against the piece hashes of a .torrent file.
+fn check_torrent(ctx: Context, torrent_file: PathBuf, path: PathBuf) -> i32 {
+	let Context { reporting, .. } = ctx;
+	let torrent = match quickdash::operations::Torrent::load(&torrent_file) {
+		Ok(torrent) => torrent,
+		Err(err) => {
+			eprintln!("Failed to read {:?}: {:?}", torrent_file, err);
+			reporting.finish_run("check-torrent", &torrent_file, &RunMetrics::default(), Err(&err));
+			return err.exit_value();
+		}
+	};
+	let outcome = quickdash::operations::check_torrent(&torrent, &path);
+	let run = RunMetrics { files_scanned: torrent.file_count(), bytes_hashed: torrent.total_length() };
+	reporting.finish_run("check-torrent", &torrent_file, &run, Ok(&outcome));
+	quickdash::operations::write_hash_comparison_results(&mut stdout(), &mut stderr(), outcome, &[]).exit_value()
+}
+
+/// Run `bagit create`, turning a directory into a bag in place.
+fn bagit_create(ctx: Context, path: PathBuf, info: Vec<String>) -> i32 {
+	let Context { opts, walk_options, .. } = ctx;
+	let algo = match opts.algorithm {
+		Algorithm::UNSPECIFIED => Algorithm::SHA2512,
+		algo => algo,
+	};
+	if quickdash::operations::bagit_algorithm_name(algo).is_none() {
+		eprintln!("Bags can only use MD5, SHA-1 and SHA-2 checksums.");
+		return 1;
+	}
+	let mut report = HashingReport::default();
+	let bagged = quickdash::operations::create_bag(&path, algo, &info, &walk_options, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	match bagged {
+		Ok(()) => 0,
+		Err(err) => {
+			eprintln!("Failed to bag {:?}: {}", path, err);
+			1
+		}
+	}
+}
+
+/// Run `bagit validate`, checking a bag's payload against its manifests.
+fn bagit_validate(ctx: Context, path: PathBuf, fast: bool) -> i32 {
+	let Context { walk_options, reporting, .. } = ctx;
+	let mut report = HashingReport::default();
+	let validated = quickdash::operations::validate_bag(&path, fast, &walk_options, &mut report);
+	let run = RunMetrics { files_scanned: report.files_scanned, bytes_hashed: report.bytes_hashed };
+	reporting.finish_run("bagit validate", &path, &run, validated.as_ref());
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	match validated {
+		Ok(Ok(_)) if fast => {
+			println!("Payload-Oxum matches");
+			0
+		}
+		Ok(outcome) => {
+			quickdash::operations::write_hash_comparison_results(&mut stdout(), &mut stderr(), outcome, &report.warnings)
+				.exit_value()
+		}
+		Err(err) => {
+			eprintln!("Not a valid bag: {:?}", err);
+			err.exit_value()
+		}
+	}
+}
+
+/// Run `release create`, writing the checksums of a directory's files and signing them.
+fn release_create(
+	ctx: Context,
+	path: PathBuf,
+	file: Option<PathBuf>,
+	clearsign: bool,
+	local_user: Option<String>,
+	force: bool,
+) -> i32 {
+	let Context { opts, mut walk_options, .. } = ctx;
+	let algo = match opts.algorithm {
+		Algorithm::UNSPECIFIED => Algorithm::SHA2256,
+		algo => algo,
+	};
+	let file = file.unwrap_or_else(|| path.join(quickdash::operations::sums_name(algo)));
+	if !force && file.exists() {
+		eprintln!("File already exists. Use --force to overwrite.");
+		return 1;
+	}
+	// Don't hash the checksums or their signature
+	let mut signature = file.clone().into_os_string();
+	signature.push(".asc");
+	for hash_file in [file.as_path(), Path::new(&signature)] {
+		walk_options.ignore_file(&path, hash_file);
+	}
+	let mut report = HashingReport::default();
+	let created = quickdash::operations::create_sums(&path, algo, &file, &walk_options, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	if let Err(err) = created {
+		eprintln!("Failed to write {:?}: {}", file, err);
+		return 1;
+	}
+	match quickdash::operations::sign_sums(&file, clearsign, local_user.as_deref()) {
+		Ok(signature) => {
+			println!("Signed {:?} into {:?}", file, signature);
+			0
+		}
+		Err(err) => {
+			eprintln!("Failed to sign {:?}: {}", file, err);
+			1
+		}
+	}
+}
+
+/// Run `release verify`, checking the signature of a checksum file, then the files it lists.
+fn release_verify(ctx: Context, signature: PathBuf, path: Option<PathBuf>, file: Option<PathBuf>) -> i32 {
+	let Context { opts, walk_options, read_options, reporting, .. } = ctx;
+	let path = path
+		.or_else(|| signature.parent().filter(|parent| !parent.as_os_str().is_empty()).map(Path::to_owned))
+		.unwrap_or_else(|| PathBuf::from("."));
+	let mut report = HashingReport::default();
+	let verified =
+		quickdash::operations::verify_sums(&signature, file.as_deref(), &path, opts.algorithm, &walk_options, &read_options, &mut report);
+	let run = RunMetrics { files_scanned: report.files_scanned, bytes_hashed: report.bytes_hashed };
+	reporting.finish_run("release verify", &signature, &run, verified.as_ref());
+	match verified {
+		Ok(outcome) => {
+			quickdash::operations::write_hash_comparison_results(&mut stdout(), &mut stderr(), outcome, &report.warnings)
+				.exit_value()
+		}
+		Err(err) => {
+			eprintln!("Failed to verify {:?}: {:?}", signature, err);
+			err.exit_value()
+		}
+	}
+}
+
+/// Run `merge`, merging several hash files into one.
+fn merge(
+	ctx: Context,
+	files: Vec<PathBuf>,
+	output: PathBuf,
+	policy: MergePolicy,
+	force: bool,
+	comment: Vec<String>,
+) -> i32 {
+	let Context { read_options, mut write_options, .. } = ctx;
+	write_options.header = comment;
+	if !force && output.exists() {
+		eprintln!("File already exists. Use --force to overwrite.");
+		return 1;
+	}
+
+	let mut files = files;
+	if policy == MergePolicy::PreferNewest {
+		// oldest first, so that newer manifests override older ones
+		files.sort_by_key(|f| metadata(f).and_then(|m| m.modified()).ok());
+	}
+
+	let mut manifests = Vec::with_capacity(files.len());
+	for file in &files {
+		match quickdash::operations::read_hashes(file, &read_options) {
+			Ok(hashes) => manifests.push(hashes),
+			Err(rval) => return rval.exit_value(),
+		}
+		if write_options.algorithm == Algorithm::UNSPECIFIED
+			&& let Ok(Some(named)) = quickdash::operations::read_named_algorithm(file, &read_options)
+		{
+			write_options.algorithm = named;
+		}
+	}
+
+	match quickdash::operations::merge_hashes(manifests, policy) {
+		Ok(merged) => quickdash::operations::write_hashes(&output, merged, &write_options),
+		Err(MergeError::Conflict { file, first_hash, second_hash }) => {
+			eprintln!("Conflicting hashes for {:?}: {} and {}", file, first_hash, second_hash);
+			eprintln!("Use --policy to choose how conflicts are resolved.");
+			1
+		}
+	}
+}
+
+/// Run `query`, looking files up by hash or by name.
+fn query(
+	ctx: Context,
+	hash: Option<String>,
+	name: Option<PathBuf>,
+	same_as: Option<PathBuf>,
+	file: Vec<PathBuf>,
+) -> i32 {
+	let Context { opts, walk_options, read_options, .. } = ctx;
+	if file.is_empty() && opts.inventory.is_none() {
+		eprintln!("Use --file to name the hash files to look in, or --inventory.");
+		return 1;
+	}
+	let mut found = false;
+	for manifest in &file {
+		let hashes = match quickdash::operations::read_hashes(manifest, &read_options) {
+			Ok(hashes) => hashes,
+			Err(rval) => return rval.exit_value(),
+		};
+		let query = match (&hash, &name, &same_as) {
+			(Some(hash), _, _) => Query::Hash(hash.clone()),
+			(_, Some(name), _) => Query::Name(name.clone()),
+			(_, _, Some(same_as)) => {
+				// Hashed the way the hash file was
+				let algo = match opts.algorithm {
+					Algorithm::UNSPECIFIED => match quickdash::operations::read_named_algorithm(manifest, &read_options) {
+						Ok(Some(named)) => named,
+						Ok(None) => hashes
+							.values()
+							.find(|hash| !hash.starts_with('-'))
+							.map_or(Algorithm::UNSPECIFIED, |hash| Algorithm::autodetect_from_hash(hash)),
+						Err(rval) => return rval.exit_value(),
+					},
+					algo => algo,
+				};
+				match quickdash::try_hash_file(algo, same_as, &walk_options.hash_options) {
+					Ok(hash) => Query::Hash(hash),
+					Err(err) => {
+						eprintln!("Failed to hash {:?}: {}", same_as, err);
+						return 1;
 					}
+				}
+			}
+			(None, None, None) => unreachable!("clap requires one"),
+		};
+		for (entry, entry_hash) in quickdash::operations::query_hashes(&hashes, &query) {
+			println!("{}  {:?} in {:?}", entry_hash, entry, manifest);
+			found = true;
+		}
+	}
+	if let Some(command) = &opts.inventory {
+		let (query, algorithm) = match (hash, name, same_as) {
+			(Some(hash), _, _) => (Query::Hash(hash), None),
+			(_, Some(name), _) => (Query::Name(name), None),
+			(_, _, Some(same_as)) => match quickdash::try_hash_file(opts.algorithm, &same_as, &walk_options.hash_options) {
+				Ok(hash) => (Query::Hash(hash), Some(opts.algorithm)),
+				Err(err) => {
+					eprintln!("Failed to hash {:?}: {}", same_as, err);
+					return 1;
+				}
+			},
+			(None, None, None) => unreachable!("clap requires one"),
+		};
+		return match quickdash::operations::write_inventory(command, &quickdash::operations::query_sql(&query, algorithm)) {
+			Ok(()) => 0,
+			Err(err) => {
+				eprintln!("Failed to query the inventory: {}", err);
+				1
+			}
+		};
+	}
+	match found {
+		true => 0,
+		false => {
+			println!("No such file found");
+			1
+		}
+	}
+}
+
+/// Run `repair`, repairing corrupt or missing files from their PAR2 recovery files.
+fn repair(ctx: Context, path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let Context { opts, walk_options, read_options, .. } = ctx;
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let base = opts.relative_to.as_deref().unwrap_or(&path);
+	// A damaged hash file is repaired too
+	let intact = match check_listed(&file, base, opts.algorithm, &read_options, &walk_options, &mut HashingReport::default()) {
+		Ok(Ok((compare_results, file_compare_results))) => {
+			compare_results.is_empty()
+				&& file_compare_results.iter().all(|result| matches!(result, CompareFileResult::FileMatches(_)))
+		}
+		_ => false,
+	};
+	if intact {
+		println!("Nothing to repair");
+		return 0;
+	}
+	if let Err(err) = quickdash::operations::repair(&file, base) {
+		eprintln!("Failed to repair the files of {:?}: {}", file, err);
+		return 1;
+	}
+	let mut report = HashingReport::default();
+	match check_listed(&file, base, opts.algorithm, &read_options, &walk_options, &mut report) {
+		Ok(compare_result) => {
+			quickdash::operations::write_hash_comparison_results(&mut stdout(), &mut stderr(), compare_result, &report.warnings)
+				.exit_value()
+		}
+		Err(rval) => rval.exit_value(),
+	}
+}
+
+/// Run `similar`, finding near-duplicate files by their ssdeep digests.
+fn similar(ctx: Context, path: PathBuf, threshold: u32, digests: bool) -> i32 {
+	let Context { walk_options, .. } = ctx;
+	let mut report = HashingReport::default();
+	let hashes = quickdash::operations::fuzzy_hashes(&path, &walk_options, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	if digests {
+		for (file, digest) in &hashes {
+			println!("{},{:?}", digest, file);
+		}
+		return 0;
+	}
+	let clusters = quickdash::operations::similar_files(&hashes, threshold);
+	if clusters.is_empty() {
+		println!("No similar files");
+	}
+	for (i, cluster) in clusters.iter().enumerate() {
+		if i > 0 {
+			println!();
+		}
+		for (file, score) in cluster {
+			println!("{:>3}  {:?}", score, file);
+		}
+	}
+	0
+}
+
+/// Run `dedupe`, finding files with the same content and consolidating them.
+fn dedupe(
+	ctx: Context,
+	path: PathBuf,
+	action: Option<DedupeAction>,
+	keep: Keep,
+	apply: bool,
+	log: Option<PathBuf>,
+	undo: bool,
+) -> i32 {
+	let Context { opts, mut walk_options, .. } = ctx;
+	let log = log.unwrap_or_else(|| default_file(&path).with_extension("dedupe.log"));
+	if undo {
+		return match quickdash::operations::undo_consolidations(&log) {
+			Ok(lines) => {
+				for line in lines {
+					println!("{}", line);
+				}
+				0
+			}
+			Err(err) => {
+				eprintln!("Failed to undo the deduplication logged in {:?}: {}", log, err);
+				1
+			}
+		};
+	}
+	walk_options.ignore_file(&path, &log);
+	let mut report = HashingReport::default();
+	let sets = quickdash::operations::find_duplicates(&path, opts.algorithm, &walk_options, keep, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	let (mut freed, mut failed) = (0, 0);
+	for (i, set) in sets.iter().enumerate() {
+		if i > 0 {
+			println!();
+		}
+		println!("keep  {:?}", set.kept);
+		for duplicate in &set.duplicates {
+			// Reflinks already free what they can
+			let shared = set.shared.contains(duplicate);
+			let len = match shared {
+				true => 0,
+				false => set.len,
+			};
+			if shared && action == Some(DedupeAction::Reflink) {
+				println!("shared  {:?}", duplicate);
+				continue;
+			}
+			let Some(action) = action.filter(|_| apply) else {
+				match action {
+					Some(action) => println!("would {}  {:?}", action.name(), duplicate),
+					None if shared => println!("shared  {:?}", duplicate),
+					None => println!("same  {:?}", duplicate),
+				}
+				freed += len;
+				continue;
+			};
+			let done = quickdash::operations::consolidate(&set.kept, duplicate, action)
+				.and_then(|()| quickdash::operations::log_consolidation(&log, action, &set.kept, duplicate));
+			match done {
+				Ok(()) => {
+					println!("{}  {:?}", action.name(), duplicate);
+					freed += len;
+				}
+				Err(err) => {
+					println!("failed to {} {:?}: {}", action.name(), duplicate, err);
+					failed += 1;
+				}
+			}
+		}
+	}
+	let duplicates = sets.iter().map(|set| set.duplicates.len()).sum::<usize>();
+	match (action, apply) {
+		(Some(action), false) => {
+			println!("{} duplicates of {} files, {} bytes; use --apply to {} them", duplicates, sets.len(), freed, action.name())
+		}
+		(Some(_), true) => println!("{} of {} duplicates of {} files done, {} bytes freed", duplicates - failed, duplicates, sets.len(), freed),
+		(None, _) => println!("{} duplicates of {} files, {} bytes", duplicates, sets.len(), freed),
+	}
+	match failed {
+		0 => 0,
+		_ => 1,
+	}
+}
+
+/// Run `tag`, adding the CRC32 of files to their names, or removing it.
+fn tag(ctx: Context, path: PathBuf, strip: bool, dry_run: bool) -> i32 {
+	let Context { opts, walk_options, .. } = ctx;
+	let mut report = HashingReport::default();
+	let renames = quickdash::operations::tag_renames(&path, opts.algorithm, &walk_options, strip, &mut report);
+	print_warnings(&report.warnings);
+	if print_errors(&report.errors) {
+		return 1;
+	}
+	let mut failed = 0;
+	for rename in &renames {
+		if dry_run {
+			println!("would rename {:?} -> {:?}", rename.from, rename.to);
+			continue;
+		}
+		match quickdash::operations::apply_rename(rename) {
+			Ok(()) => println!("renamed {:?} -> {:?}", rename.from, rename.to),
+			Err(err) => {
+				println!("failed to rename {:?}: {}", rename.from, err);
+				failed += 1;
+			}
+		}
+	}
+	match failed {
+		0 => 0,
+		_ => 1,
+	}
+}
+
+/// Run `tui`, verifying interactively.
+fn tui(ctx: Context, path: PathBuf, file: Option<PathBuf>, quarantine: Option<PathBuf>, quarantine_link: bool) -> i32 {
+	let Context { opts, mut walk_options, read_options, mut write_options, .. } = ctx;
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let algo = match opts.algorithm {
+		Algorithm::UNSPECIFIED => match quickdash::operations::read_named_algorithm(&file, &read_options) {
+			Ok(named) => named.unwrap_or(Algorithm::UNSPECIFIED),
+			Err(rval) => return rval.exit_value(),
+		},
+		algo => algo,
+	};
+	let (outboards, token) = (outboard_dir(&file), quickdash::operations::timestamp_path(&file));
+	let (signatures, recovery) = (quickdash::operations::signature_dir(&file), quickdash::operations::par2_dir(&file));
+	let (history, verified) = (quickdash::operations::history_dir(&file), quickdash::operations::verified_path(&file));
+	for hash_file in [&file, &outboards, &signatures, &recovery, &token, &history, &verified].into_iter().chain(&quarantine) {
+		walk_options.ignore_file(&path, hash_file);
+	}
+	let verification = match LiveVerification::start(&path, &file, algo, &walk_options, &read_options) {
+		Ok(verification) => verification,
+		Err(rval) => return rval.exit_value(),
+	};
+	let quarantine = quarantine.as_deref().map(|dir| (dir, quarantine_link));
+	if let Err(err) = quickdash::operations::run_tui(&verification, &format!("{:?}", file), quarantine) {
+		eprintln!("Failed to run the TUI: {}", err);
+		return 1;
+	}
+	let fixes = verification.fixes();
+	if !fixes.is_empty() {
+		write_options.algorithm = algo;
+		match quickdash::operations::fix_hashes(&file, &fixes, &BTreeMap::new(), &read_options, write_options) {
+			Ok(0) => println!("Fixed {} entries of {:?}", fixes.len(), file),
+			Ok(_) => return 1,
+			Err(rval) => return rval.exit_value(),
+		}
+	}
+	quickdash::operations::write_hash_comparison_results(
+		&mut stdout(),
+		&mut stderr(),
+		verification.outcome(),
+		&verification.warnings(),
+	)
+	.exit_value()
+}
+
+/// Run `daemon`, taking create and verify jobs on a Unix socket.
+#[cfg(unix)]
+fn daemon(ctx: Context, socket: Option<PathBuf>) -> i32 {
+	let Context { opts, walk_options, read_options, write_options, .. } = ctx;
+	let socket = socket.unwrap_or_else(quickdash::operations::default_socket_path);
+	let options = quickdash::operations::JobOptions { algorithm: opts.algorithm, walk_options, read_options, write_options };
+	eprintln!("Listening on {:?}", socket);
+	match quickdash::operations::serve(&socket, options) {
+		Ok(()) => 0,
+		Err(err) => {
+			eprintln!("Failed to serve on {:?}: {}", socket, err);
+			1
+		}
+	}
+}
+
+/// Run `serve`, serving an HTTP API to verify directories.
+fn serve(ctx: Context, paths: Vec<PathBuf>, listen: String, token_file: Option<PathBuf>) -> i32 {
+	let Context { opts, walk_options, read_options, write_options, .. } = ctx;
+	let mut roots: Vec<ServedRoot> = Vec::new();
+	for path in paths {
+		let Some(name) = path.canonicalize().ok().and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned())) else {
+			eprintln!("Can't name {:?} after its directory.", path);
+			return 1;
+		};
+		if roots.iter().any(|root| root.name == name) {
+			eprintln!("Two directories are named {:?}.", name);
+			return 1;
+		}
+		roots.push(ServedRoot { name, file: default_file(&path), path });
+	}
+	let token = match token_file.map(read_to_string).transpose() {
+		Ok(token) => token.map(|token| token.trim().to_owned()),
+		Err(err) => {
+			eprintln!("Failed to read the token: {}", err);
+			return 1;
+		}
+	};
+	let options = quickdash::operations::JobOptions { algorithm: opts.algorithm, walk_options, read_options, write_options };
+	eprintln!("Listening on http://{}", listen);
+	match quickdash::operations::serve_http(listen.as_str(), roots, token, options) {
+		Ok(()) => 0,
+		Err(err) => {
+			eprintln!("Failed to serve on {}: {}", listen, err);
+			1
+		}
+	}
+}
+
+/// Run `generations list`, listing the generations of a hash file.
+fn generations_list(path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let generations = match generations_of(&file) {
+		Ok(generations) => generations,
+		Err(rval) => return rval,
+	};
+	if generations.is_empty() {
+		println!("No generations of {:?} kept, use create --keep-generation.", file);
+	}
+	for (i, generation) in generations.iter().enumerate() {
+		println!("{:>4}  {}", i + 1, generation.id);
+	}
+	0
+}
+
+/// Run `generations diff`, listing the files that differ between two generations.
+fn generations_diff(ctx: Context, from: String, to: Option<String>, path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let Context { read_options, .. } = ctx;
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let generations = match generations_of(&file) {
+		Ok(generations) => generations,
+		Err(rval) => return rval,
+	};
+	let to = to.unwrap_or_else(|| generations.len().to_string());
+	let (Some(old), Some(new)) = (
+		quickdash::operations::find_generation(&generations, &from),
+		quickdash::operations::find_generation(&generations, &to),
+	) else {
+		eprintln!("No such generation of {:?}, see generations list.", file);
+		return 1;
+	};
+	match quickdash::operations::diff_generations(old, new, &read_options) {
+		Ok(changes) => {
+			if changes.is_empty() {
+				println!("No files changed from {} to {}", old.id, new.id);
+			}
+			for (name, change) in changes {
+				match change {
+					GenerationChange::Added(_) => println!("File added: {:?}", name),
+					GenerationChange::Removed(_) => println!("File removed: {:?}", name),
+					GenerationChange::Changed { was, new } => {
+						println!("File changed: {:?}", name);
+						println!("  Was: {}", was);
+						println!("  Is : {}", new);
+					}
+				}
+			}
+			0
+		}
+		Err(rval) => rval.exit_value(),
+	}
+}
+
+/// Run `generations log`, listing the generations in which a file changed.
+fn generations_log(ctx: Context, name: PathBuf, path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let Context { read_options, .. } = ctx;
+	let file = file.unwrap_or_else(|| default_file(&path));
+	let generations = match generations_of(&file) {
+		Ok(generations) => generations,
+		Err(rval) => return rval,
+	};
+	match quickdash::operations::entry_history(&generations, &name, &read_options) {
+		Ok(history) if history.is_empty() => {
+			eprintln!("{:?} isn't in any generation of {:?}.", name, file);
+			1
+		}
+		Ok(history) => {
+			for (generation, hash) in history {
+				println!("{}  {}", generation.id, hash.as_deref().unwrap_or("removed"));
+			}
+			0
+		}
+		Err(rval) => rval.exit_value(),
+	}
+}
+
+/// Run `generations prune`, removing all but the newest generations.
+fn generations_prune(keep: usize, path: PathBuf, file: Option<PathBuf>) -> i32 {
+	let file = file.unwrap_or_else(|| default_file(&path));
+	match quickdash::operations::prune_generations(&file, keep) {
+		Ok(removed) => {
+			for generation in removed {
+				println!("Removed generation {}", generation.id);
+			}
+			0
+		}
+		Err(err) => {
+			eprintln!("Failed to prune the generations of {:?}: {}", file, err);
+			1
+		}
+	}
+}
+
+/// Run `verify-audit-log`, checking that the records of an audit log still chain.
+fn verify_audit_log(log: PathBuf) -> i32 {
+	match quickdash::operations::verify_audit_log(&log) {
+		Ok(Ok(records)) => {
+			println!("All {} records of {:?} chain", records, log);
+			0
+		}
+		Ok(Err(line)) => {
+			println!("Record on line {} of {:?} doesn't chain, the log was tampered with", line, log);
+			Error::NFilesDiffer(1).exit_value()
+		}
+		Err(err) => {
+			eprintln!("Failed to read {:?}: {}", log, err);
+			1
+		}
+	}
+}
+
+/// Pair each directory with the label its entries are stored under, or get
+/// `None` for a single unlabelled directory whose entries are stored as-is.
+fn label_roots(paths: &[PathBuf], labels: Vec<String>) -> Result<Option<Vec<(PathBuf, String)>>, i32> {
+	if paths.len() == 1 && labels.is_empty() {
+		return Ok(None);
+	}
+	if !labels.is_empty() && labels.len() != paths.len() {
+		eprintln!("Got {} labels for {} directories.", labels.len(), paths.len());
+		return Err(1);
+	}
+
+	let mut roots: Vec<(PathBuf, String)> = Vec::with_capacity(paths.len());
+	for (i, path) in paths.iter().enumerate() {
+		let label = match labels.get(i) {
+			Some(label) => label.clone(),
+			None => match path.canonicalize().ok().and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned())) {
+				Some(name) => name,
+				None => {
+					eprintln!("Can't name {:?}, use --label.", path);
+					return Err(1);
+				}
+			},
+		};
+		if !matches!(Path::new(&label).components().collect::<Vec<_>>()[..], [Component::Normal(_)]) {
+			eprintln!("Invalid label {:?}.", label);
+			return Err(1);
+		}
+		if roots.iter().any(|(_, other)| *other == label) {
+			eprintln!("Several directories are labelled {:?}, use --label.", label);
+			return Err(1);
+		}
+		roots.push((path.clone(), label));
+	}
+
+	Ok(Some(roots))
+}
+
+/// Show the layout digest of names-only hashes, and note it in the header.
+fn add_layout_digest(hashes: &BTreeMap<PathBuf, String>, algo: Algorithm, write_options: &mut WriteOptions) {
+	let digest = quickdash::operations::layout_digest(hashes, algo);
+	println!("Layout digest: {}", digest);
+	write_options.header.push(format!("{}{}", LAYOUT_DIGEST_PREFIX, digest));
+}
+
+/// Note the tree hash of the hashes in the header.
+fn add_tree_hash(hashes: &BTreeMap<PathBuf, String>, algo: Algorithm, write_options: &mut WriteOptions) {
+	let root = quickdash::operations::tree_hash(hashes, algo);
+	write_options.header.push(format!("{}{}", TREE_HASH_PREFIX, root));
+}
+
+/// Have the hash file just written timestamped by the authority at `url`, if
+/// given and writing it succeeded.
+fn timestamp(file: &Path, url: Option<&str>, rval: i32) -> i32 {
+	match url {
+		Some(url) if rval == 0 => match quickdash::operations::request_timestamp(file, url) {
+			Ok(time) => {
+				println!("Timestamped {:?} at {}", file, time);
+				0
+			}
+			Err(err) => {
+				eprintln!("Failed to timestamp {:?}: {}", file, err);
+				1
+			}
+		},
+		_ => rval,
+	}
+}
 
-					let files: Vec<PathBuf> = loaded_hashes
-						.keys()
-						.map(|f|f.to_owned())
-						.collect();
-					let hashes: BTreeMap<PathBuf, String> = quickdash::operations::create_hashes_for_files(&path, files, algo);
+/// Algorithm to hash with and the hash expected, from `--expect` and `-a`.
+/// OCI digests, multihashes and CIDs name the algorithm they were made with.
+fn expected_hash(expect: Option<&str>, algorithm: Algorithm) -> Result<(Algorithm, Option<String>), i32> {
+	match expect.map(|digest| (digest, quickdash::operations::decode_named_hash(digest))) {
+		None => Ok((algorithm, None)),
+		Some((_, Some((named, hash)))) if algorithm == Algorithm::UNSPECIFIED || algorithm == named => Ok((named, Some(hash))),
+		Some((_, Some((named, _)))) => {
+			eprintln!("--expect names {}, not the algorithm given with -a.", quickdash::operations::oci_name(named));
+			Err(1)
+		}
+		Some((digest, None)) if !digest.is_empty() && digest.chars().all(|c| c.is_ascii_hexdigit()) => {
+			Ok((algorithm, Some(digest.to_uppercase())))
+		}
+		Some((digest, None)) => {
+			eprintln!("Invalid digest {:?}, expected hex, `algorithm:hex`, a multihash or a CID.", digest);
+			Err(1)
+		}
+	}
+}
 
-					let compare_result =
-						quickdash::operations::compare_hashes(hashes, loaded_hashes);
-					let err = quickdash::operations::write_hash_comparison_results(
-						&mut stdout(),
-						&mut stderr(),
-						compare_result,
-					);
-					println!("{:#?}", err);
-					err.exit_value()
+/// Upload the hash file just written, and its shards, to each target, if
+/// writing it succeeded.
+fn publish_to(file: &Path, targets: &[PublishTarget], rval: i32) -> i32 {
+	if targets.is_empty() || rval != 0 {
+		return rval;
+	}
+	let files = match quickdash::operations::read_shard_index(file) {
+		Ok(shards) => shards.into_iter().flatten().chain([file.to_owned()]).collect::<Vec<_>>(),
+		Err(err) => return err.exit_value(),
+	};
+	let mut rval = 0;
+	for target in targets {
+		for file in &files {
+			match quickdash::operations::publish(file, target) {
+				Ok(url) => println!("Published {:?} to {}", file, url),
+				Err(err) => {
+					eprintln!("Failed to publish {:?}: {}", file, err);
+					rval = 1;
 				}
-				Err(rval) => rval.exit_value(),
 			}
 		}
 	}
+	rval
+}
+
+/// Generations kept of the hash file, oldest first.
+fn generations_of(file: &Path) -> Result<Vec<Generation>, i32> {
+	quickdash::operations::list_generations(file).map_err(|err| {
+		eprintln!("Failed to list the generations of {:?}: {}", file, err);
+		1
+	})
+}
+
+/// Keep a copy of the hash file just written as a new generation, if asked
+/// to and writing it succeeded.
+fn generation(file: &Path, keep: bool, rval: i32) -> i32 {
+	if !keep || rval != 0 {
+		return rval;
+	}
+	match quickdash::operations::store_generation(file) {
+		Ok(generation) => {
+			println!("Kept generation {} of {:?}", generation.id, file);
+			0
+		}
+		Err(err) => {
+			eprintln!("Failed to keep a generation of {:?}: {}", file, err);
+			1
+		}
+	}
+}
+
+/// Check the hash file against its timestamp token, if it has one, failing
+/// with the exit value if it changed since.
+fn check_timestamp(file: &Path) -> Result<(), i32> {
+	match quickdash::operations::check_timestamp(file) {
+		Ok(None) => Ok(()),
+		Ok(Some(TimestampCheck::Matches { time })) => {
+			println!("Hash file unchanged since timestamped at {}", time);
+			Ok(())
+		}
+		Ok(Some(TimestampCheck::Differs { time })) => {
+			println!("Hash file changed since timestamped at {}", time);
+			Err(Error::NFilesDiffer(1).exit_value())
+		}
+		Err(err) => {
+			eprintln!("Failed to read the timestamp of {:?}: {}", file, err);
+			Err(1)
+		}
+	}
+}
+
+/// Fetch the hash file `file` if it's a URL, then check its detached
+/// `signature`, fetched too if it's a URL.
+fn fetch_hash_file(file: &Path, signature: Option<&Path>) -> Result<Option<FetchedFile>, i32> {
+	let fetch = |url: &Path| {
+		quickdash::operations::fetch(&url.to_string_lossy()).map_err(|err| {
+			eprintln!("Failed to fetch {}: {}", url.display(), err);
+			1
+		})
+	};
+	let fetched = match is_url(file) {
+		true => Some(fetch(file)?),
+		false => None,
+	};
+	if let Some(signature) = signature {
+		let fetched_signature = match is_url(signature) {
+			true => Some(fetch(signature)?),
+			false => None,
+		};
+		let local = fetched.as_ref().map_or(file, FetchedFile::path);
+		match quickdash::operations::check_signature(fetched_signature.as_ref().map_or(signature, FetchedFile::path), local) {
+			Ok(true) => {}
+			Ok(false) => {
+				eprintln!("Signature doesn't verify");
+				return Err(Error::NFilesDiffer(1).exit_value());
+			}
+			Err(err) => {
+				eprintln!("Failed to check the signature {}: {}", signature.display(), err);
+				return Err(1);
+			}
+		}
+	}
+	Ok(fetched)
+}
+
+/// Append a record of verifying `manifest` to the audit log, if one is kept.
+fn audit(log: Option<&Path>, command: &str, manifest: &Path, result: Result<&CompareOutcome, &Error>) {
+	if let Some(log) = log
+		&& let Err(err) = quickdash::operations::append_audit_record(log, command, manifest, result)
+	{
+		eprintln!("Failed to append to the audit log {:?}: {}", log, err);
+	}
+}
+
+/// Lock the hash file for the run, waiting for the run holding it as long as
+/// `--lock-wait` says.
+fn lock_run(file: &Path, wait: Duration, shared: bool) -> Result<RunLock, i32> {
+	match quickdash::operations::lock_run(file, wait, shared) {
+		Ok(Some(lock)) => {
+			if !lock.held() {
+				eprintln!("Can't lock {:?} where it is, running without a lock.", file);
+			}
+			Ok(lock)
+		}
+		Ok(None) => {
+			eprintln!("Another run is using {:?}, use --lock-wait to wait for it.", file);
+			Err(quickdash::operations::LOCKED_EXIT_CODE)
+		}
+		Err(err) => {
+			eprintln!("Failed to lock {:?}: {}", file, err);
+			Err(1)
+		}
+	}
+}
+
+/// Write the hashes made before stopping on SIGTERM next to the hash file.
+fn write_partial(file: &Path, hashes: BTreeMap<PathBuf, String>, report: HashingReport, mut write_options: WriteOptions) -> i32 {
+	let partial = quickdash::operations::partial_path(file);
+	print_warnings(&report.warnings);
+	let hashed = hashes.len();
+	write_options.notes = report.notes;
+	write_options.metadata = report.metadata;
+	if quickdash::operations::write_hashes(&partial, hashes, &write_options) == 0 {
+		eprintln!("Stopped with {} files left, wrote the {} hashed to {:?}.", report.unhashed.len(), hashed, partial);
+	}
+	quickdash::operations::STOPPED_EXIT_CODE
+}
+
+/// Write the metrics of a run to the textfile, if asked to.
+fn metrics(textfile: Option<&Path>, command: &str, manifest: &Path, run: &RunMetrics, result: Result<&CompareOutcome, &Error>) {
+	if let Some(textfile) = textfile
+		&& let Err(err) = quickdash::operations::write_metrics_textfile(textfile, command, manifest, run, result)
+	{
+		eprintln!("Failed to write the metrics to {:?}: {}", textfile, err);
+	}
+}
+
+/// Notify the webhook and email addresses of a failed run, if any are given.
+fn notify(webhook: Option<&str>, smtp: Option<&Smtp>, host: &str, command: &str, manifest: &Path, result: Result<&CompareOutcome, &Error>) {
+	if webhook.is_none() && smtp.is_none() {
+		return;
+	}
+	let Some(summary) = quickdash::operations::FailureSummary::of(host, command, manifest, result) else {
+		return;
+	};
+	if let Some(url) = webhook
+		&& let Err(err) = quickdash::operations::send_webhook(url, &summary)
+	{
+		eprintln!("Failed to notify the webhook: {}", err);
+	}
+	if let Some(smtp) = smtp
+		&& let Err(err) = quickdash::operations::send_email(smtp, &summary)
+	{
+		eprintln!("Failed to email {}: {}", smtp.to.join(", "), err);
+	}
+}
+
+/// Log records of a run to syslog or the journal, if asked to.
+fn log_run(target: Option<LogTarget>, command: &str, manifest: &Path, result: Result<&CompareOutcome, &Error>) {
+	if let Some(target) = target
+		&& let Err(err) = quickdash::operations::log_to(target, &quickdash::operations::log_records(command, manifest, result))
+	{
+		eprintln!("Failed to log the run: {}", err);
+	}
+}
+
+/// Note a run in the central inventory, telling whether that worked.
+fn inventory(command: &str, run: &InventoryRun, hashes: &BTreeMap<PathBuf, String>, outcome: Option<(&[CompareResult], &[CompareFileResult])>) -> bool {
+	let sql = quickdash::operations::inventory_sql(run, hashes, outcome);
+	match quickdash::operations::write_inventory(command, &sql) {
+		Ok(()) => true,
+		Err(err) => {
+			eprintln!("Failed to note the run in the inventory: {}", err);
+			false
+		}
+	}
+}
+
+/// Make the PAR2 recovery files of the files stored as `names` under `base`
+/// and of the hash file just written.
+fn recover(file: &Path, base: &Path, names: &[PathBuf], redundancy: u32) -> i32 {
+	match quickdash::operations::create_recovery(file, base, names, redundancy) {
+		Ok(()) => 0,
+		Err(err) => {
+			eprintln!("Failed to make the PAR2 recovery files of {:?}: {}", file, err);
+			1
+		}
+	}
+}
+
+/// Hash the files listed in a hash file and compare them with it, the way
+/// `check` does, noting the files that couldn't be read in `report`.
+fn check_listed(
+	file: &Path,
+	base: &Path,
+	algo: Algorithm,
+	read_options: &ReadOptions,
+	walk_options: &WalkOptions,
+	report: &mut HashingReport,
+) -> Result<CompareOutcome, Error> {
+	let mut loaded_hashes = quickdash::operations::read_hashes(file, read_options)?;
+	let algo = match algo {
+		// Multihashes and CIDs name the algorithm they were made with
+		Algorithm::UNSPECIFIED => quickdash::operations::read_named_algorithm(file, read_options)?
+			.or_else(|| loaded_hashes.values().find(|hash| !hash.starts_with("----")).map(|hash| Algorithm::autodetect_from_hash(hash)))
+			.unwrap_or(algo),
+		algo => algo,
+	};
+	let skipped = quickdash::operations::take_placeholders(&mut loaded_hashes);
+	let hashes = quickdash::operations::create_hashes_for_files(base, loaded_hashes.keys().cloned().collect(), algo, walk_options, report);
+	Ok(quickdash::operations::compare_hashes(hashes, loaded_hashes).map(|(mut compare_results, file_compare_results)| {
+		compare_results.extend(skipped);
+		(compare_results, file_compare_results)
+	}))
+}
+
+/// Whether a hash file is encrypted, going by its start.
+fn is_encrypted(file: &Path) -> bool {
+	let mut start = [0; 64];
+	let read = File::open(file).and_then(|mut file| file.read(&mut start));
+	read.is_ok_and(|read| Encryption::detect(&start[..read]).is_some())
+}
+
+/// Ask what to do about each mismatch, or accept them all with `yes`,
+/// getting the fixes to make. Mismatches left unanswered are kept as is.
+fn choose_fixes(mismatches: &[Mismatch], yes: bool) -> BTreeMap<PathBuf, Fix> {
+	if yes {
+		return mismatches.iter().map(|mismatch| (mismatch.file().to_owned(), mismatch.accept())).collect();
+	}
+	let mut fixes = BTreeMap::new();
+	let mut lines = stdin().lock().lines();
+	for mismatch in mismatches {
+		let (question, choices) = match mismatch {
+			Mismatch::Differs { file, .. } => (format!("Update the entry of {:?} to its new hash, keep it, or delete it? [u/k/d] ", file), "ukd"),
+			Mismatch::Removed(file) => (format!("Delete the entry of missing {:?}, or keep it? [d/k] ", file), "dk"),
+			Mismatch::Added { file, .. } => (format!("Add an entry for new {:?}, or skip it? [a/s] ", file), "as"),
+		};
+		let answer = loop {
+			eprint!("{}", question);
+			match lines.next() {
+				Some(Ok(line)) => match line.trim().to_lowercase().chars().next() {
+					Some(answer) if choices.contains(answer) => break answer,
+					_ => continue,
+				},
+				// Nobody to answer
+				_ => return fixes,
+			}
+		};
+		match answer {
+			'u' | 'a' => fixes.insert(mismatch.file().to_owned(), mismatch.accept()),
+			'd' => fixes.insert(mismatch.file().to_owned(), Fix::Delete),
+			_ => None,
+		};
+	}
+	fixes
+}
+
+/// Load the hashes of known good and known bad files, if any sets were
+/// given.
+fn load_known(good: &[PathBuf], bad: &[PathBuf], algorithm: Algorithm) -> Result<Option<KnownHashes>, i32> {
+	if good.is_empty() && bad.is_empty() {
+		return Ok(None);
+	}
+	match KnownHashes::load(good, bad, algorithm) {
+		Ok(known) => Ok(Some(known)),
+		Err(err) => {
+			eprintln!("Failed to read the known hashes: {}", err);
+			Err(1)
+		}
+	}
+}
+
+/// Print the files of a run flagged as known bad, and those not known good
+/// if `list_unknown`, telling whether none were flagged.
+fn print_known(known: &KnownHashes, hashes: &BTreeMap<PathBuf, String>, list_unknown: bool) -> bool {
+	let files = known.classify_files(hashes);
+	for file in &files.flagged {
+		println!("Known bad: {:?}", file);
+	}
+	if list_unknown {
+		for file in &files.unknown {
+			println!("Unknown: {:?}", file);
+		}
+	}
+	eprintln!("{} known good, {} unknown, {} known bad", files.known, files.unknown.len(), files.flagged.len());
+	files.flagged.is_empty()
+}
+
+/// Note when the files that matched verified, next to the hash file, and
+/// get lines listing the files that never did, and with `stale_after`, those
+/// that haven't in that many days.
+fn track_verified(
+	file: &Path,
+	compare_results: &[CompareResult],
+	file_compare_results: &[CompareFileResult],
+	stale_after: Option<u64>,
+) -> Vec<String> {
+	let mut verified = match LastVerified::load(file) {
+		Ok(verified) => verified,
+		Err(err) => return vec![format!("Failed to read when files last verified: {}", err)],
+	};
+	let now = SystemTime::now();
+	verified.record(compare_results, file_compare_results, now);
+	let mut lines = Vec::new();
+	if let Err(err) = verified.save(file) {
+		lines.push(format!("Failed to keep when files last verified: {}", err));
+	}
+	let never = verified.never_verified();
+	if !never.is_empty() {
+		lines.push("Never verified:".to_string());
+		lines.extend(never.iter().map(|name| format!("  {:?}", name)));
+	}
+	if let Some(days) = stale_after {
+		let stale = verified.verified_before(now - Duration::from_secs(days * 86400));
+		if !stale.is_empty() {
+			lines.push(format!("Not verified in {} days:", days));
+			lines.extend(stale.iter().map(|(name, time)| {
+				let ago = now.duration_since(*time).unwrap_or_default().as_secs() / 86400;
+				format!("  {:?}, {} days ago", name, ago)
+			}));
+		}
+	}
+	lines
+}
+
+/// List the files that haven't verified lately, if any.
+fn print_unverified(lines: &[String]) {
+	if lines.is_empty() {
+		return;
+	}
+	println!();
+	for line in lines {
+		println!("{}", line);
+	}
+}
+
+/// Show where two files first differ, the differing byte in brackets.
+fn print_difference(difference: &quickdash::operations::Difference) {
+	let at = (difference.offset - difference.start) as usize;
+	let ends = match (difference.context_a.len() <= at, difference.context_b.len() <= at) {
+		(true, _) => ", where A ends",
+		(_, true) => ", where B ends",
+		_ => "",
+	};
+	println!("  first difference at byte {}{}", difference.offset, ends);
+	for (side, context) in [("A", &difference.context_a), ("B", &difference.context_b)] {
+		let bytes = context
+			.iter()
+			.enumerate()
+			.map(|(i, byte)| match i == at {
+				true => format!("[{:02x}]", byte),
+				false => format!("{:02x}", byte),
+			})
+			.collect::<Vec<_>>();
+		println!("  {} {:08x}: {}", side, difference.start, bytes.join(" "));
+	}
+}
+
+/// List the warnings raised while hashing, if any.
+fn print_warnings(warnings: &[String]) {
+	if warnings.is_empty() {
+		return;
+	}
+	eprintln!("Warnings:");
+	for warning in warnings {
+		eprintln!("  {}", warning);
+	}
+}
+
+/// Print the problems that fail the run, like special files found when
+/// they're an error. Whether there were any.
+fn print_errors(errors: &[String]) -> bool {
+	for error in errors {
+		eprintln!("{}", error);
+	}
+	!errors.is_empty()
+}
+
+/// Point `walk_options` at `relative_to`, returning the path to walk so that
+/// `relative_to` is a prefix of it, or so that it is absolute with
+/// `absolute_paths`.
+fn resolve_relative_to(
+	path: PathBuf,
+	relative_to: Option<PathBuf>,
+	walk_options: &mut WalkOptions,
+) -> Result<PathBuf, i32> {
+	let Some(relative_to) = relative_to else {
+		if walk_options.absolute_paths {
+			return path.canonicalize().map_err(|err| {
+				eprintln!("Failed to resolve {:?}: {}", path, err);
+				1
+			});
+		}
+		return Ok(path);
+	};
+
+	match (path.canonicalize(), relative_to.canonicalize()) {
+		(Ok(path), Ok(relative_to)) if path.starts_with(&relative_to) => {
+			walk_options.relative_to = Some(relative_to);
+			Ok(path)
+		}
+		(Ok(_), Ok(_)) => {
+			eprintln!("{:?} is not inside {:?}", path, relative_to);
+			Err(1)
+		}
+		(Err(err), _) | (_, Err(err)) => {
+			eprintln!("Failed to resolve --relative-to: {}", err);
+			Err(1)
+		}
+	}
+}
+
+/// The USN journal position of the volume of `path` to record in the
+/// updated hash `file`, and the names of the files changed since the
+/// position its `header` records, if the journal still reaches back to it.
+fn usn_changes(path: &Path, file: &Path, header: &[String]) -> Result<(Option<String>, Option<Changed>), i32> {
+	// Taken before hashing, so files changed while hashing are read next time
+	let position = match quickdash::operations::usn_position(path) {
+		Ok(position) => position.header(),
+		Err(err) => {
+			eprintln!("Failed to read the USN journal of {:?}: {}", path, err);
+			return Err(1);
+		}
+	};
+	let Some(since) = header.iter().find_map(|line| UsnPosition::from_header(line)) else {
+		eprintln!("{:?} wasn't updated from the USN journal before, reading all files.", file);
+		return Ok((Some(position), None));
+	};
+	match quickdash::operations::usn_changed_names(path, since) {
+		Ok(Some(names)) => Ok((Some(position), Some(Changed::Names(names)))),
+		Ok(None) => {
+			eprintln!("The USN journal no longer reaches back to when {:?} was last updated, reading all files.", file);
+			Ok((Some(position), None))
+		}
+		Err(err) => {
+			eprintln!("Failed to read the USN journal of {:?}: {}", path, err);
+			Err(1)
+		}
+	}
+}
+
+/// The position in the `journal` of changes to record in the updated hash
+/// `file`, and the paths changed since the position its `header` records, if
+/// the recorder has been running since.
+fn recorded_changes(journal: &Path, file: &Path, header: &[String]) -> Result<(Option<String>, Option<Changed>), i32> {
+	// Taken before hashing, so files changed while hashing are read next time
+	let now = match quickdash::operations::changes_position(journal) {
+		Ok(Some(now)) => now,
+		Ok(None) => {
+			eprintln!("No record-changes is writing {:?}, reading all files.", journal);
+			return Ok((None, None));
+		}
+		Err(err) => {
+			eprintln!("Failed to read {:?}: {}", journal, err);
+			return Err(1);
+		}
+	};
+	let Some(since) = header.iter().find_map(|line| ChangesPosition::from_header(line)) else {
+		eprintln!("{:?} wasn't updated from recorded changes before, reading all files.", file);
+		return Ok((Some(now.header()), None));
+	};
+	match quickdash::operations::changed_paths(journal, since, now) {
+		Ok(Some(paths)) => Ok((Some(now.header()), Some(Changed::Paths(paths)))),
+		Ok(None) => {
+			eprintln!("Changes weren't all recorded since {:?} was last updated, reading all files.", file);
+			Ok((Some(now.header()), None))
+		}
+		Err(err) => {
+			eprintln!("Failed to read {:?}: {}", journal, err);
+			Err(1)
+		}
+	}
 }
 
 fn default_file(path: &Path) -> PathBuf {
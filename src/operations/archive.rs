@@ -0,0 +1,355 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Members of zip, tar and 7z archives, hashed without extracting them and
+//! stored as `archive.zip!member/path`, for per-member integrity of datasets
+//! kept as archives.
+//!
+//! zip and tar are read here, compressed tarballs through `gzip`, `bzip2`,
+//! `xz` or `zstd`, and 7z archives through `bsdtar`, turning them into tar.
+
+use std::{
+	collections::BTreeMap,
+	fs::File,
+	io::{self, BufReader, Read, Seek, SeekFrom},
+	path::{Path, PathBuf},
+	process::{Child, Command, Stdio},
+};
+
+use clap::ValueEnum;
+
+use super::{ERROR_NOTE_PREFIX, HashingReport, WalkOptions, inflate::Inflate, note_warning, placeholder_hash};
+use crate::{Algorithm, hashing::try_hash_reader_with};
+
+/// Separator of an archive's name and its members' in stored names.
+pub static MEMBER_SEPARATOR: char = '!';
+
+/// Kinds of archives looked inside.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum ArchiveKind {
+	/// `.zip`, with stored or deflated members.
+	Zip,
+	/// `.tar`, and `.tar.gz`, `.tar.bz2`, `.tar.xz` and `.tar.zst` or their
+	/// short forms, like `.tgz`.
+	Tar,
+	/// `.7z`, read with bsdtar.
+	#[value(name = "7z")]
+	SevenZ,
+}
+
+/// How an archive is read.
+enum Format {
+	Zip,
+	/// A tarball, uncompressed with this command if compressed.
+	Tar(Option<&'static str>),
+	SevenZ,
+}
+
+/// How `file` is read if it's an archive of one of `kinds`, told by its
+/// extension.
+fn format_of(file: &Path, kinds: &[ArchiveKind]) -> Option<Format> {
+	let name = file.file_name()?.to_string_lossy().to_lowercase();
+	let tar = [
+		(".tar", None),
+		(".tar.gz", Some("gzip")),
+		(".tgz", Some("gzip")),
+		(".tar.bz2", Some("bzip2")),
+		(".tbz2", Some("bzip2")),
+		(".tar.xz", Some("xz")),
+		(".txz", Some("xz")),
+		(".tar.zst", Some("zstd")),
+		(".tzst", Some("zstd")),
+	];
+	if kinds.contains(&ArchiveKind::Zip) && name.ends_with(".zip") {
+		return Some(Format::Zip);
+	}
+	if kinds.contains(&ArchiveKind::SevenZ) && name.ends_with(".7z") {
+		return Some(Format::SevenZ);
+	}
+	match tar.iter().find(|(extension, _)| name.ends_with(extension)) {
+		Some((_, uncompress)) if kinds.contains(&ArchiveKind::Tar) => Some(Format::Tar(*uncompress)),
+		_ => None,
+	}
+}
+
+/// Whether `file` is an archive looked inside with `options`.
+pub(super) fn is_archive(file: &Path, options: &WalkOptions) -> bool {
+	format_of(file, &options.look_inside).is_some()
+}
+
+/// Hash the members of the archive `file`, stored as `name`, adding them to
+/// `hashes` as `name!member`. Members that can't be read get a placeholder
+/// hash and a note in `report`, and archives that can't be a warning.
+pub(super) fn hash_members(
+	file: &Path,
+	name: &Path,
+	algo: Algorithm,
+	options: &WalkOptions,
+	hashes: &mut BTreeMap<PathBuf, String>,
+	report: &mut HashingReport,
+) {
+	let mut members = Vec::new();
+	let mut hash = |member: String, data: &mut dyn Read| {
+		members.push((member, try_hash_reader_with(algo, &mut { data }, &options.hash_options)));
+		Ok(())
+	};
+	let read = match format_of(file, &options.look_inside) {
+		Some(Format::Zip) => File::open(file).and_then(|mut archive| zip_members(&mut archive, &mut hash)),
+		Some(Format::Tar(None)) => File::open(file).and_then(|archive| tar_members(BufReader::new(archive), &mut hash)),
+		Some(Format::Tar(Some(uncompress))) => {
+			File::open(file).and_then(|archive| piped(Command::new(uncompress).arg("-dc").stdin(archive), &mut hash))
+		}
+		Some(Format::SevenZ) => piped(Command::new("bsdtar").args(["-cf", "-", "--format", "pax"]).arg(format!("@{}", file.display())), &mut hash),
+		None => return,
+	};
+	if let Err(err) = read {
+		report.warnings.push(format!("Failed to look inside {:?}: {}", name, err));
+	}
+	for (member, hash) in members {
+		let member = PathBuf::from(format!("{}{}{}", name.to_string_lossy(), MEMBER_SEPARATOR, member));
+		let hash = match hash {
+			Ok(hash) => hash,
+			Err(err) => {
+				let note = format!("{}{}", ERROR_NOTE_PREFIX, err);
+				report.warnings.extend(note_warning(&member, &Some(note.clone())));
+				report.notes.insert(member.clone(), note);
+				placeholder_hash(algo)
+			}
+		};
+		hashes.insert(member, hash);
+	}
+}
+
+/// Member names as stored: relative, with `/` separators.
+fn member_name(name: &str) -> String {
+	name.trim_start_matches("./").trim_start_matches('/').to_owned()
+}
+
+/// Read the tar stream `command` writes, failing if it does.
+fn piped(command: &mut Command, each: &mut dyn FnMut(String, &mut dyn Read) -> io::Result<()>) -> io::Result<()> {
+	let program = command.get_program().to_string_lossy().into_owned();
+	let mut child: Child = command.stdout(Stdio::piped()).spawn().map_err(|err| match err.kind() {
+		io::ErrorKind::NotFound => io::Error::new(err.kind(), format!("reading it needs {}, which isn't installed", program)),
+		_ => err,
+	})?;
+	let mut stdout = BufReader::new(child.stdout.take().expect("the output is piped"));
+	// What's after the end of the archive is read too, so the command ends
+	let read = tar_members(&mut stdout, each).and_then(|_| io::copy(&mut stdout, &mut io::sink()).map(|_| ()));
+	if read.is_err() {
+		let _ = child.kill();
+	}
+	let status = child.wait()?;
+	match (read, status.success()) {
+		(Err(err), _) => Err(err),
+		(Ok(()), true) => Ok(()),
+		(Ok(()), false) => Err(io::Error::other(format!("{} failed ({})", program, status))),
+	}
+}
+
+fn invalid(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Number in a tar header field: octal text, or big-endian binary if its
+/// first byte has its high bit set.
+fn tar_number(field: &[u8]) -> io::Result<u64> {
+	if field[0] & 0x80 != 0 {
+		return Ok(field[1..].iter().fold(u64::from(field[0] & 0x7f), |n, &byte| (n << 8) | u64::from(byte)));
+	}
+	let text = String::from_utf8_lossy(field);
+	let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+	match text.is_empty() {
+		true => Ok(0),
+		false => u64::from_str_radix(text, 8).map_err(|_| invalid(format!("invalid number {:?} in a tar header", text))),
+	}
+}
+
+/// Text of a NUL-terminated tar header field.
+fn tar_text(field: &[u8]) -> String {
+	let end = field.iter().position(|&byte| byte == 0).unwrap_or(field.len());
+	String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Call `each` with the name and data of each regular file in the tar
+/// stream `reader`, in ustar, GNU or pax format.
+fn tar_members<R: Read>(mut reader: R, each: &mut dyn FnMut(String, &mut dyn Read) -> io::Result<()>) -> io::Result<()> {
+	// Long names and sizes given by GNU and pax headers, for the next member
+	let (mut long_name, mut long_size) = (None, None);
+	loop {
+		let mut header = [0u8; 512];
+		if let Err(err) = reader.read_exact(&mut header) {
+			// Some writers leave out the closing blocks
+			return match err.kind() {
+				io::ErrorKind::UnexpectedEof if long_name.is_none() => Ok(()),
+				_ => Err(err),
+			};
+		}
+		if header.iter().all(|&byte| byte == 0) {
+			return Ok(());
+		}
+		let sum: u64 = header.iter().enumerate().map(|(i, &byte)| if (148..156).contains(&i) { 32 } else { u64::from(byte) }).sum();
+		if tar_number(&header[148..156])? != sum {
+			return Err(invalid("tar header checksum doesn't match".to_owned()));
+		}
+		let size = match long_size.take() {
+			Some(size) => size,
+			None => tar_number(&header[124..136])?,
+		};
+		let mut data = (&mut reader).take(size);
+		match header[156] {
+			b'L' => {
+				let mut name = Vec::new();
+				data.read_to_end(&mut name)?;
+				long_name = Some(tar_text(&name));
+			}
+			b'x' => {
+				let mut records = Vec::new();
+				data.read_to_end(&mut records)?;
+				for record in String::from_utf8_lossy(&records).lines() {
+					// Records are `<length> <key>=<value>`
+					match record.split_once(' ').and_then(|(_, pair)| pair.split_once('=')) {
+						Some(("path", path)) => long_name = Some(path.to_owned()),
+						Some(("size", size)) => long_size = size.parse().ok(),
+						_ => {}
+					}
+				}
+			}
+			kind @ (b'0' | b'\0' | b'7') => {
+				let name = long_name.take().unwrap_or_else(|| {
+					let name = tar_text(&header[0..100]);
+					match (&header[257..262], tar_text(&header[345..500])) {
+						(b"ustar", prefix) if !prefix.is_empty() => format!("{}/{}", prefix, name),
+						_ => name,
+					}
+				});
+				// Old archives mark directories by a trailing `/` only
+				if !(kind == b'\0' && name.ends_with('/')) {
+					each(member_name(&name), &mut data)?;
+				}
+			}
+			_ => long_name = None,
+		}
+		// What the member's data wasn't read of, then its padding
+		io::copy(&mut data, &mut io::sink())?;
+		io::copy(&mut (&mut reader).take((512 - size % 512) % 512), &mut io::sink())?;
+	}
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+	u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+	u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+	u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+/// Call `each` with the name and data of each file in the zip archive,
+/// reading stored and deflated members, Zip64 included. Members it can't
+/// read get an error as their data.
+fn zip_members(archive: &mut File, each: &mut dyn FnMut(String, &mut dyn Read) -> io::Result<()>) -> io::Result<()> {
+	let malformed = |what: &str| invalid(format!("not a zip archive, or a damaged one: {}", what));
+	// The end of central directory record is followed by a comment of up to
+	// 64 KiB
+	let len = archive.metadata()?.len();
+	let tail_len = len.min(22 + 65535);
+	let mut tail = vec![0; tail_len as usize];
+	archive.seek(SeekFrom::Start(len - tail_len))?;
+	archive.read_exact(&mut tail)?;
+	let end = (0..tail.len().saturating_sub(21)).rev().find(|&at| u32_at(&tail, at) == 0x0605_4b50).ok_or_else(|| malformed("no end of central directory"))?;
+	let (mut entries, mut directory_len, mut directory_at) = (u64::from(u16_at(&tail, end + 10)), u64::from(u32_at(&tail, end + 12)), u64::from(u32_at(&tail, end + 16)));
+	if (entries == 0xffff || directory_len == 0xffff_ffff || directory_at == 0xffff_ffff) && end >= 20 && u32_at(&tail, end - 20) == 0x0706_4b50 {
+		let mut zip64_end = [0; 56];
+		archive.seek(SeekFrom::Start(u64_at(&tail, end - 20 + 8)))?;
+		archive.read_exact(&mut zip64_end)?;
+		if u32_at(&zip64_end, 0) != 0x0606_4b50 {
+			return Err(malformed("no Zip64 end of central directory"));
+		}
+		(entries, directory_len, directory_at) = (u64_at(&zip64_end, 32), u64_at(&zip64_end, 40), u64_at(&zip64_end, 48));
+	}
+	if directory_at.saturating_add(directory_len) > len {
+		return Err(malformed("central directory past the end"));
+	}
+	let mut directory = vec![0; directory_len as usize];
+	archive.seek(SeekFrom::Start(directory_at))?;
+	archive.read_exact(&mut directory)?;
+
+	let mut at = 0;
+	for _ in 0..entries {
+		if at + 46 > directory.len() || u32_at(&directory, at) != 0x0201_4b50 {
+			return Err(malformed("truncated central directory"));
+		}
+		let entry = &directory[at..];
+		let (flags, method) = (u16_at(entry, 8), u16_at(entry, 10));
+		let (mut compressed, mut local_at) = (u64::from(u32_at(entry, 20)), u64::from(u32_at(entry, 42)));
+		let uncompressed = u32_at(entry, 24);
+		let (name_len, extra_len, comment_len) = (usize::from(u16_at(entry, 28)), usize::from(u16_at(entry, 30)), usize::from(u16_at(entry, 32)));
+		if at + 46 + name_len + extra_len + comment_len > directory.len() {
+			return Err(malformed("truncated central directory"));
+		}
+		let name = String::from_utf8_lossy(&entry[46..46 + name_len]).into_owned();
+		// Zip64 sizes and offsets are in an extra field, for those that overflow
+		let mut extra = &entry[46 + name_len..46 + name_len + extra_len];
+		while extra.len() >= 4 {
+			let (id, len) = (u16_at(extra, 0), usize::from(u16_at(extra, 2)));
+			let field = &extra[4..(4 + len).min(extra.len())];
+			if id == 0x0001 {
+				let mut values = field.chunks_exact(8).map(|value| u64_at(value, 0));
+				if uncompressed == 0xffff_ffff {
+					values.next();
+				}
+				if compressed == 0xffff_ffff {
+					compressed = values.next().unwrap_or(compressed);
+				}
+				if local_at == 0xffff_ffff {
+					local_at = values.next().unwrap_or(local_at);
+				}
+			}
+			extra = &extra[(4 + len).min(extra.len())..];
+		}
+		at += 46 + name_len + extra_len + comment_len;
+		if name.ends_with('/') {
+			continue;
+		}
+
+		let mut local = [0; 30];
+		archive.seek(SeekFrom::Start(local_at))?;
+		archive.read_exact(&mut local)?;
+		if u32_at(&local, 0) != 0x0403_4b50 {
+			return Err(malformed("no local header where the central directory says"));
+		}
+		archive.seek(SeekFrom::Current(i64::from(u16_at(&local, 26)) + i64::from(u16_at(&local, 28))))?;
+		let mut data = (&mut *archive).take(compressed);
+		let unreadable = |message: String| io::Error::new(io::ErrorKind::Unsupported, message);
+		match (flags & 1 != 0, method) {
+			(true, _) => each(member_name(&name), &mut ErrorReader(Some(unreadable("encrypted".to_owned()))))?,
+			(false, 0) => each(member_name(&name), &mut data)?,
+			(false, 8) => each(member_name(&name), &mut Inflate::new(BufReader::new(data)))?,
+			(false, method) => each(member_name(&name), &mut ErrorReader(Some(unreadable(format!("compression method {} isn't supported", method)))))?,
+		}
+	}
+	Ok(())
+}
+
+/// Reader failing with its error.
+struct ErrorReader(Option<io::Error>);
+
+impl Read for ErrorReader {
+	fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+		Err(self.0.take().unwrap_or_else(|| io::Error::other("already failed")))
+	}
+}
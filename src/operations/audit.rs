@@ -0,0 +1,190 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Append-only audit log of verification runs, one JSON record per line,
+//! each holding the SHA-256 of the one before it, so records can't be
+//! edited, dropped or reordered without breaking the chain.
+
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{CompareError, CompareFileResult, CompareOutcome, CompareResult, PathStyle, in_toto::json_string};
+use crate::{Error, hash_string};
+
+/// Previous hash of the first record.
+static GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Start of the last field of records, their hash.
+static HASH_FIELD: &str = ",\"hash\":\"";
+
+/// Start of the field before it, the hash of the previous record.
+static PREVIOUS_FIELD: &str = ",\"previous\":\"";
+
+/// Append a record of `command` verifying `manifest` to the audit log at
+/// `log`, creating it if needed: when, the SHA-256 of the manifest, how many
+/// files matched, were added, removed or ignored, which failed, and why the
+/// run couldn't compare if it couldn't.
+pub fn append_audit_record(log: &Path, command: &str, manifest: &Path, result: Result<&CompareOutcome, &Error>) -> io::Result<()> {
+	let previous = match fs::read_to_string(log) {
+		Ok(text) => match text.lines().last() {
+			Some(line) => split_record(line)
+				.map(|(_, _, hash)| hash.to_owned())
+				.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "last record is malformed"))?,
+			None => GENESIS.to_owned(),
+		},
+		Err(err) if err.kind() == io::ErrorKind::NotFound => GENESIS.to_owned(),
+		Err(err) => return Err(err),
+	};
+
+	let digest = match fs::read(manifest) {
+		Ok(contents) => format!("\"{}\"", hash_string(&Sha256::digest(contents)).to_lowercase()),
+		// Bags are directories
+		Err(_) => "null".to_owned(),
+	};
+	let (mut matched, mut assumed_ok, mut added, mut removed, mut ignored) = (0, 0, 0, 0, 0);
+	let mut failures = Vec::new();
+	if let Ok(Ok((compare_results, file_compare_results))) = result {
+		for res in compare_results {
+			match res {
+				CompareResult::FileAdded(_) => added += 1,
+				CompareResult::FileRemoved(_) => removed += 1,
+				CompareResult::FileIgnored(_) => ignored += 1,
+				CompareResult::MetadataChanged { .. } | CompareResult::AttributesChanged { .. } => {}
+			}
+		}
+		for fres in file_compare_results {
+			match fres {
+				CompareFileResult::FileMatches(_) | CompareFileResult::FileDrifted { matches: Some(true), .. } => matched += 1,
+				CompareFileResult::FileAssumedOk(_) => assumed_ok += 1,
+				_ => {}
+			}
+		}
+		failures = failed_files(compare_results, file_compare_results);
+	}
+	let error = run_error(result).map_or_else(|| "null".to_owned(), |error| json_string(&error));
+	let failures: Vec<String> = failures.iter().map(|file| json_string(&PathStyle::Unix.format(file))).collect();
+
+	let body = format!(
+		"{{\"time\":\"{}\",\"command\":{},\"manifest\":{},\"manifest_sha256\":{},\"matched\":{},\"assumed_ok\":{},\"added\":{},\
+		\"removed\":{},\"ignored\":{},\"failed\":{},\"failures\":[{}],\"error\":{}{}{}\"",
+		utc_time(SystemTime::now()),
+		json_string(command),
+		json_string(&manifest.to_string_lossy()),
+		digest,
+		matched,
+		assumed_ok,
+		added,
+		removed,
+		ignored,
+		failures.len(),
+		failures.join(","),
+		error,
+		PREVIOUS_FIELD,
+		previous
+	);
+	let hash = record_hash(&body);
+	let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+	writeln!(file, "{}{}{}\"}}", body, HASH_FIELD, hash)?;
+	file.sync_data()
+}
+
+/// Why a run couldn't compare, if it couldn't.
+pub(super) fn run_error(result: Result<&CompareOutcome, &Error>) -> Option<String> {
+	match result {
+		Ok(Ok(_)) => None,
+		Ok(Err(err)) => Some(
+			match err {
+				CompareError::HashLengthDiffers { .. } => "hash lengths differ",
+				CompareError::PayloadOxumDiffers { .. } => "Payload-Oxum differs",
+				CompareError::BadSignature => "signature doesn't verify",
+			}
+			.to_owned(),
+		),
+		Err(Error::HashesFileParsingFailure(reason)) => Some(reason.clone()),
+		Err(err) => Some(format!("{:?}", err)),
+	}
+}
+
+/// The files that failed verification in a comparison, sorted, each once.
+pub(super) fn failed_files<'a>(compare_results: &'a [CompareResult], file_compare_results: &'a [CompareFileResult]) -> Vec<&'a PathBuf> {
+	let mut failures = Vec::new();
+	for res in compare_results {
+		if let CompareResult::MetadataChanged { file, .. } | CompareResult::AttributesChanged { file, .. } = res {
+			failures.push(file);
+		}
+	}
+	for fres in file_compare_results {
+		match fres {
+			CompareFileResult::FileMatches(_) | CompareFileResult::FileAssumedOk(_) | CompareFileResult::FileDrifted { matches: Some(true), .. } => {}
+			CompareFileResult::FileDiffers { file, .. }
+			| CompareFileResult::FilePiecesDiffer { file, .. }
+			| CompareFileResult::PiecesFail { file, .. }
+			| CompareFileResult::FileDrifted { file, .. }
+			| CompareFileResult::SymlinkRetargeted { file, .. } => failures.push(file),
+		}
+	}
+	failures.sort();
+	failures.dedup();
+	failures
+}
+
+/// Check the chain of the audit log at `log`, getting how many records it
+/// holds, or the line number of the first one that's been tampered with.
+pub fn verify_audit_log(log: &Path) -> io::Result<Result<usize, usize>> {
+	let text = fs::read_to_string(log)?;
+	let mut previous = GENESIS;
+	for (index, line) in text.lines().enumerate() {
+		match split_record(line) {
+			Some((body, claimed_previous, hash)) if claimed_previous == previous && record_hash(body) == hash => previous = hash,
+			_ => return Ok(Err(index + 1)),
+		}
+	}
+	Ok(Ok(text.lines().count()))
+}
+
+/// Split a record into the part its hash is of, the hash of the previous
+/// record, and its own hash.
+fn split_record(line: &str) -> Option<(&str, &str, &str)> {
+	let (body, hash) = line.strip_suffix("\"}")?.rsplit_once(HASH_FIELD)?;
+	let previous = body.rsplit_once(PREVIOUS_FIELD)?.1.strip_suffix('"')?;
+	Some((body, previous, hash))
+}
+
+/// SHA-256 of a record without its hash, closed as JSON.
+fn record_hash(body: &str) -> String {
+	hash_string(&Sha256::digest(format!("{}}}", body))).to_lowercase()
+}
+
+/// `time` in UTC, as RFC 3339 to the second.
+pub(super) fn utc_time(time: SystemTime) -> String {
+	let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+	let (days, seconds) = (seconds / 86400, seconds % 86400);
+	// Civil date of days since 1970-01-01, counted in 400 year eras from
+	// 0000-03-01
+	let days = days + 719468;
+	let (era, day_of_era) = (days / 146097, days % 146097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let shifted_month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+	let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+	let year = era * 400 + year_of_era + u64::from(month <= 2);
+	format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
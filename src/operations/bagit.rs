@@ -0,0 +1,277 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! BagIt bags (RFC 8493): a `data` directory of payload files, next to tag
+//! files declaring the bag in `bagit.txt`, describing it in `bag-info.txt`,
+//! and listing the checksums of the payload in `manifest-<algorithm>.txt`
+//! and of the other tag files in `tagmanifest-<algorithm>.txt`.
+
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use walkdir::WalkDir;
+
+use super::{CompareError, CompareOutcome, HashingReport, PathStyle, WalkOptions, compare_hashes, create_hashes};
+use crate::{Algorithm, Error, HashOptions, try_hash_file, utilities::long_path};
+
+/// Directory of the payload files of a bag.
+static PAYLOAD_DIR: &str = "data";
+
+/// Name of `algo` in the names of manifests, if bags can use it.
+pub fn bagit_algorithm_name(algo: Algorithm) -> Option<&'static str> {
+	match algo {
+		Algorithm::MD5 => Some("md5"),
+		Algorithm::SHA1 => Some("sha1"),
+		Algorithm::SHA2224 => Some("sha224"),
+		Algorithm::SHA2256 => Some("sha256"),
+		Algorithm::SHA2384 => Some("sha384"),
+		Algorithm::SHA2512 => Some("sha512"),
+		_ => None,
+	}
+}
+
+/// Algorithm of the manifest or tag manifest named `name`, given the prefix
+/// of its kind.
+fn manifest_algorithm(name: &str, prefix: &str) -> Option<Algorithm> {
+	let algo = name.strip_prefix(prefix)?.strip_suffix(".txt")?;
+	[Algorithm::MD5, Algorithm::SHA1, Algorithm::SHA2224, Algorithm::SHA2256, Algorithm::SHA2384, Algorithm::SHA2512]
+		.into_iter()
+		.find(|&known| bagit_algorithm_name(known) == Some(algo))
+}
+
+/// Turn the directory at `path` into a bag, moving what it holds into its
+/// payload directory, and write its tag files with `algo` checksums.
+/// `info` lines, like `Source-Organization: Library`, are added to
+/// `bag-info.txt`.
+///
+/// Payload entries that aren't regular files are left out of the manifest,
+/// with a warning.
+pub fn create_bag(
+	path: &Path,
+	algo: Algorithm,
+	info: &[String],
+	options: &WalkOptions,
+	report: &mut HashingReport,
+) -> io::Result<()> {
+	let name = bagit_algorithm_name(algo)
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "bags can only use MD5, SHA-1 and SHA-2 checksums"))?;
+	if path.join("bagit.txt").exists() {
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists, "already a bag"));
+	}
+
+	// The payload may hold a `data` directory of its own
+	let staging = path.join(".quickdash-bagit");
+	fs::create_dir(&staging)?;
+	for entry in fs::read_dir(path)? {
+		let entry = entry?;
+		if entry.path() != staging {
+			fs::rename(entry.path(), staging.join(entry.file_name()))?;
+		}
+	}
+	let payload = path.join(PAYLOAD_DIR);
+	fs::rename(&staging, &payload)?;
+
+	let hashes = create_hashes(&payload, algo, options, report);
+	let (mut octets, mut streams) = (0, 0);
+	let mut manifest = Vec::new();
+	for (file, hash) in &hashes {
+		if report.notes.contains_key(file) {
+			report.warnings.push(format!("Not a regular file, left out of the manifest: {:?}", file));
+			continue;
+		}
+		octets += fs::metadata(long_path(&payload.join(file)))?.len();
+		streams += 1;
+		let file = PathStyle::Unix.format(&Path::new(PAYLOAD_DIR).join(file));
+		writeln!(manifest, "{}  {}", hash.to_lowercase(), encode_filepath(&file))?;
+	}
+
+	let manifest_name = format!("manifest-{}.txt", name);
+	fs::write(path.join(&manifest_name), manifest)?;
+	fs::write(path.join("bagit.txt"), "BagIt-Version: 1.0\nTag-File-Character-Encoding: UTF-8\n")?;
+	let mut bag_info = File::create(path.join("bag-info.txt"))?;
+	writeln!(bag_info, "Bag-Software-Agent: quickdash {}", env!("CARGO_PKG_VERSION"))?;
+	writeln!(bag_info, "Bagging-Date: {}", today())?;
+	writeln!(bag_info, "Payload-Oxum: {}.{}", octets, streams)?;
+	for line in info {
+		writeln!(bag_info, "{}", line)?;
+	}
+	drop(bag_info);
+
+	let mut tag_manifest = Vec::new();
+	for tag_file in ["bagit.txt", "bag-info.txt", &manifest_name] {
+		let hash = try_hash_file(algo, &path.join(tag_file), &HashOptions::default())?;
+		writeln!(tag_manifest, "{}  {}", hash.to_lowercase(), tag_file)?;
+	}
+	fs::write(path.join(format!("tagmanifest-{}.txt", name)), tag_manifest)
+}
+
+/// Validate the bag at `path`: that every payload file is listed in every
+/// manifest and matches its checksums, that the tag files listed in tag
+/// manifests match theirs, and that the payload is as large as its
+/// `Payload-Oxum` says. With `fast`, only the last is checked.
+///
+/// Bags without `bagit.txt` or a manifest fail to load. Manifests of
+/// algorithms bags can't use are skipped with a warning.
+pub fn validate_bag(path: &Path, fast: bool, options: &WalkOptions, report: &mut HashingReport) -> Result<CompareOutcome, Error> {
+	let invalid = |message: &str| Error::HashesFileParsingFailure(format!("{:?}: {}", path, message));
+	let declaration = fs::read_to_string(path.join("bagit.txt")).map_err(|_| invalid("no bagit.txt"))?;
+	if !declaration.lines().any(|line| line.starts_with("BagIt-Version:")) {
+		return Err(invalid("bagit.txt doesn't declare a BagIt version"));
+	}
+
+	let recorded_oxum = fs::read_to_string(path.join("bag-info.txt")).ok().and_then(|bag_info| {
+		let oxum = bag_info.lines().find_map(|line| line.strip_prefix("Payload-Oxum:"))?;
+		let (octets, streams) = oxum.trim().split_once('.')?;
+		Some((octets.parse().ok()?, streams.parse().ok()?))
+	});
+	let payload = path.join(PAYLOAD_DIR);
+	if fast {
+		let recorded = recorded_oxum.ok_or_else(|| invalid("no Payload-Oxum in bag-info.txt"))?;
+		return Ok(check_oxum(recorded, &payload).map(|()| (Vec::new(), Vec::new())));
+	}
+
+	let (mut manifests, mut tag_manifests) = (Vec::new(), Vec::new());
+	for entry in fs::read_dir(path).map_err(|err| invalid(&err.to_string()))? {
+		let name = entry.map_err(|err| invalid(&err.to_string()))?.file_name().to_string_lossy().into_owned();
+		let kind = match name.starts_with("tagmanifest-") {
+			true => &mut tag_manifests,
+			false if name.starts_with("manifest-") => &mut manifests,
+			false => continue,
+		};
+		match manifest_algorithm(&name, "manifest-").or_else(|| manifest_algorithm(&name, "tagmanifest-")) {
+			Some(algo) => kind.push((algo, path.join(name))),
+			None => report.warnings.push(format!("Unknown algorithm, not checked: {}", name)),
+		}
+	}
+	if manifests.is_empty() {
+		return Err(invalid("no payload manifest"));
+	}
+
+	let (mut compare_results, mut file_compare_results) = (Vec::new(), Vec::new());
+	for (algo, manifest) in manifests {
+		let loaded = read_manifest(&manifest)?;
+		let current = create_hashes(&payload, algo, options, report)
+			.into_iter()
+			.map(|(file, hash)| (Path::new(PAYLOAD_DIR).join(file), hash))
+			.collect();
+		let (results, file_results) = match compare_hashes(current, loaded) {
+			Ok(outcome) => outcome,
+			Err(err) => return Ok(Err(err)),
+		};
+		compare_results.extend(results);
+		file_compare_results.extend(file_results);
+	}
+	for (algo, tag_manifest) in tag_manifests {
+		let loaded = read_manifest(&tag_manifest)?;
+		let current = loaded
+			.keys()
+			.filter_map(|file| Some((file.clone(), try_hash_file(algo, &path.join(file), &options.hash_options).ok()?)))
+			.collect();
+		let (results, file_results) = match compare_hashes(current, loaded) {
+			Ok(outcome) => outcome,
+			Err(err) => return Ok(Err(err)),
+		};
+		compare_results.extend(results);
+		file_compare_results.extend(file_results);
+	}
+	compare_results.sort();
+	compare_results.dedup();
+	file_compare_results.sort();
+	file_compare_results.dedup();
+
+	// A payload matching its manifests can still disagree with its oxum
+	if compare_results.is_empty()
+		&& let Some(recorded) = recorded_oxum
+		&& let Err(err) = check_oxum(recorded, &payload)
+	{
+		return Ok(Err(err));
+	}
+	Ok(Ok((compare_results, file_compare_results)))
+}
+
+/// Compare the octets and streams of the payload in `payload` to
+/// `recorded`.
+fn check_oxum(recorded: (u64, u64), payload: &Path) -> Result<(), CompareError> {
+	let found = WalkDir::new(payload)
+		.into_iter()
+		.filter_map(Result::ok)
+		.filter(|entry| entry.file_type().is_file())
+		.fold((0, 0), |(octets, streams), entry| (octets + entry.metadata().map_or(0, |metadata| metadata.len()), streams + 1));
+	match found == recorded {
+		true => Ok(()),
+		false => Err(CompareError::PayloadOxumDiffers { recorded, found }),
+	}
+}
+
+/// Checksums of a manifest or tag manifest, by filepath.
+fn read_manifest(manifest: &Path) -> Result<BTreeMap<PathBuf, String>, Error> {
+	let text = fs::read_to_string(manifest).map_err(|err| Error::HashesFileParsingFailure(format!("{:?}: {}", manifest, err)))?;
+	let mut hashes = BTreeMap::new();
+	for line in text.lines().filter(|line| !line.trim().is_empty()) {
+		let entry = line
+			.split_once([' ', '\t'])
+			.map(|(hash, file)| (hash, file.trim_start_matches([' ', '\t'])))
+			.filter(|(hash, file)| hash.chars().all(|c| c.is_ascii_hexdigit()) && !file.is_empty());
+		let Some((hash, file)) = entry else {
+			return Err(Error::HashesFileParsingFailure(line.to_owned()));
+		};
+		hashes.insert(PathBuf::from(decode_filepath(file)), hash.to_uppercase());
+	}
+	Ok(hashes)
+}
+
+/// Percent-encode the characters of a filepath that would break its line.
+fn encode_filepath(file: &str) -> String {
+	file.replace('%', "%25").replace('\n', "%0A").replace('\r', "%0D")
+}
+
+fn decode_filepath(file: &str) -> String {
+	let mut decoded = String::with_capacity(file.len());
+	let mut rest = file;
+	while let Some(at) = rest.find('%') {
+		decoded.push_str(&rest[..at]);
+		let (c, len) = match rest.get(at + 1..at + 3).map(str::to_ascii_uppercase).as_deref() {
+			Some("25") => ('%', 3),
+			Some("0A") => ('\n', 3),
+			Some("0D") => ('\r', 3),
+			_ => ('%', 1),
+		};
+		decoded.push(c);
+		rest = &rest[at + len..];
+	}
+	decoded.push_str(rest);
+	decoded
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+fn today() -> String {
+	let days = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_secs() / 86400) as i64;
+	// Civil date from days since the epoch, after Howard Hinnant
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+	format!("{:04}-{:02}-{:02}", year, month, day)
+}
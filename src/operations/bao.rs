@@ -0,0 +1,318 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Bao outboard encodings of BLAKE3 hashed files, to verify byte ranges of
+//! them without reading the rest.
+//!
+//! An outboard holds the length of its file as 8 little endian bytes,
+//! followed by the parent nodes of the file's BLAKE3 tree in pre-order, each
+//! the 32-byte chaining values of its left and right subtree. Leaves are
+//! groups of 16 chunks, so an outboard is 1/256th of the size of its file,
+//! and ranges are verified 16 KiB at a time.
+
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+};
+
+use blake3::hazmat::{ChainingValue, HasherExt, Mode, left_subtree_len, merge_subtrees_non_root, merge_subtrees_root};
+
+use crate::{hash_string, utilities::long_path};
+
+/// Bytes hashed into each leaf of the outboard tree.
+const GROUP_LEN: u64 = 16 * 1024;
+
+/// Bytes of the length at the start of an outboard.
+const HEADER_LEN: u64 = 8;
+
+/// Bytes of each parent node.
+const PARENT_LEN: u64 = 64;
+
+/// Directory the outboards of the files listed in `hash_file` are kept in,
+/// next to it.
+pub fn outboard_dir(hash_file: &Path) -> PathBuf {
+	let mut dir = hash_file.as_os_str().to_owned();
+	dir.push(".bao");
+	PathBuf::from(dir)
+}
+
+/// Outboard of the file stored as `name`, in `dir`.
+pub fn outboard_path(dir: &Path, name: &Path) -> PathBuf {
+	let mut path = dir.join(name).into_os_string();
+	path.push(".obao");
+	PathBuf::from(path)
+}
+
+/// Write the outboards of the regular files in `hashes`, found under `base`,
+/// to `dir`, given their BLAKE3 hashes.
+///
+/// Files of up to 16 KiB don't need one, and outboards already matching
+/// their file's size and hash are kept. Files that can't be read, or whose
+/// hash changed since they were hashed, get a warning instead.
+pub fn write_outboards(
+	base: &Path,
+	hashes: &BTreeMap<PathBuf, String>,
+	notes: &BTreeMap<PathBuf, String>,
+	dir: &Path,
+	warnings: &mut Vec<String>,
+) {
+	for (name, hash) in hashes {
+		if notes.contains_key(name) {
+			continue;
+		}
+		let path = base.join(name);
+		let outboard = outboard_path(dir, name);
+		let written = fs::metadata(long_path(&path)).and_then(|metadata| match metadata.len() <= GROUP_LEN {
+			true => Ok(true),
+			false if is_current(&outboard, metadata.len(), hash) => Ok(true),
+			false => write_outboard(&path, &outboard, hash),
+		});
+		match written {
+			Ok(true) => {}
+			Ok(false) => warnings.push(format!("Changed since hashed, no outboard written: {:?}", name)),
+			Err(err) => warnings.push(format!("Failed to write the outboard of {:?}: {}", name, err)),
+		}
+	}
+}
+
+/// Write the outboard of the file at `path` to `outboard`, if the file's
+/// hash is still `hash`. Returns whether it was.
+fn write_outboard(path: &Path, outboard: &Path, hash: &str) -> io::Result<bool> {
+	let data = File::open(long_path(path))?;
+	let len = data.metadata()?.len();
+	if let Some(parent) = outboard.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	let mut encoder = Encoder {
+		data: BufReader::with_capacity(1 << 20, data),
+		outboard: File::create(outboard)?,
+		buffer: vec![0; GROUP_LEN as usize],
+		position: HEADER_LEN,
+	};
+	encoder.outboard.write_all(&len.to_le_bytes())?;
+	let root = encoder.encode(0, len, true)?;
+	if !hash_string(&root).eq_ignore_ascii_case(hash) {
+		drop(encoder);
+		let _ = fs::remove_file(outboard);
+		return Ok(false);
+	}
+	Ok(true)
+}
+
+/// Whether `outboard` is that of a file of `len` bytes hashing to `hash`,
+/// going by its header and root node.
+fn is_current(outboard: &Path, len: u64, hash: &str) -> bool {
+	let mut head = [0; (HEADER_LEN + PARENT_LEN) as usize];
+	let read = File::open(outboard).and_then(|mut file| {
+		file.read_exact(&mut head)?;
+		file.metadata()
+	});
+	let Ok(metadata) = read else {
+		return false;
+	};
+	let (header, parent) = head.split_at(HEADER_LEN as usize);
+	metadata.len() == HEADER_LEN + parents(len) * PARENT_LEN
+		&& header == len.to_le_bytes()
+		&& hash_string(&merge(parent, true)).eq_ignore_ascii_case(hash)
+}
+
+/// Outcome of verifying a byte range of a file.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum RangeCheck {
+	/// The range matches the recorded hash.
+	Matches,
+	/// The file's size differs from that in its outboard.
+	Resized { was: u64, is: u64 },
+	/// The bytes from `start` to `end` don't match the recorded hash, or
+	/// their part of the outboard is damaged.
+	Differs { start: u64, end: u64 },
+}
+
+/// Verify the bytes from `start` to `end` of the file at `path` against its
+/// BLAKE3 `hash`, reading only them and the nodes of its `outboard` above
+/// them, rounded out to 16 KiB.
+///
+/// Files of up to 16 KiB are read whole, without an outboard.
+pub fn verify_range(path: &Path, outboard: &Path, hash: &str, start: u64, end: u64) -> io::Result<RangeCheck> {
+	let data = File::open(long_path(path))?;
+	let len = data.metadata()?.len();
+	if start >= len.max(1) || start >= end {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("no bytes to verify from {} in a file of {} bytes", start, len),
+		));
+	}
+	let Some(expected) = decode_hash(hash) else {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "not a BLAKE3 hash"));
+	};
+
+	let mut verifier = Verifier {
+		data,
+		outboard: None,
+		buffer: vec![0; GROUP_LEN as usize],
+		start,
+		end: end.min(len),
+	};
+	if len > GROUP_LEN {
+		let mut outboard = File::open(outboard)?;
+		let mut header = [0; HEADER_LEN as usize];
+		outboard.read_exact(&mut header)?;
+		let was = u64::from_le_bytes(header);
+		if was != len {
+			return Ok(RangeCheck::Resized { was, is: len });
+		}
+		verifier.outboard = Some(outboard);
+	}
+	Ok(match verifier.check(0, len, &expected, true, HEADER_LEN)? {
+		Some((start, end)) => RangeCheck::Differs { start, end },
+		None => RangeCheck::Matches,
+	})
+}
+
+/// Writer of an outboard, reading its file from start to end.
+struct Encoder<R: Read> {
+	data: R,
+	outboard: File,
+	buffer: Vec<u8>,
+	/// Position of the next parent node in the outboard.
+	position: u64,
+}
+
+impl<R: Read> Encoder<R> {
+	/// Write the parent nodes of the subtree of `len` bytes at `offset`, and
+	/// get its chaining value, or the root hash if it's the `root`.
+	fn encode(&mut self, offset: u64, len: u64, root: bool) -> io::Result<ChainingValue> {
+		if len <= GROUP_LEN {
+			let group = &mut self.buffer[..len as usize];
+			self.data.read_exact(group)?;
+			return Ok(leaf(group, offset, root));
+		}
+		// Parents come before their children, but are known after them
+		let position = self.position;
+		self.position += PARENT_LEN;
+		let left_len = left_subtree_len(len);
+		let left = self.encode(offset, left_len, false)?;
+		let right = self.encode(offset + left_len, len - left_len, false)?;
+		let parent = [left, right].concat();
+		self.outboard.seek(SeekFrom::Start(position))?;
+		self.outboard.write_all(&parent)?;
+		Ok(merge(&parent, root))
+	}
+}
+
+/// Reader of the parts of a file, and of its outboard, covering a range.
+struct Verifier {
+	data: File,
+	/// Outboard, unless the file is a single group.
+	outboard: Option<File>,
+	buffer: Vec<u8>,
+	start: u64,
+	end: u64,
+}
+
+impl Verifier {
+	/// Check the subtree of `len` bytes at `offset`, whose parent node is at
+	/// `position` in the outboard, against its chaining value `expected`, or
+	/// the root hash if it's the `root`, descending only into the children
+	/// overlapping the range.
+	///
+	/// Returns the bytes of the first subtree that doesn't match.
+	fn check(
+		&mut self,
+		offset: u64,
+		len: u64,
+		expected: &ChainingValue,
+		root: bool,
+		position: u64,
+	) -> io::Result<Option<(u64, u64)>> {
+		let parent = match &mut self.outboard {
+			Some(outboard) if len > GROUP_LEN => {
+				let mut parent = [0; PARENT_LEN as usize];
+				outboard.seek(SeekFrom::Start(position))?;
+				outboard.read_exact(&mut parent)?;
+				parent
+			}
+			_ => {
+				let group = &mut self.buffer[..len as usize];
+				self.data.seek(SeekFrom::Start(offset))?;
+				self.data.read_exact(group)?;
+				let matches = leaf(group, offset, root) == *expected;
+				return Ok((!matches).then_some((offset, offset + len)));
+			}
+		};
+		if merge(&parent, root) != *expected {
+			return Ok(Some((offset, offset + len)));
+		}
+
+		let left_len = left_subtree_len(len);
+		let (left, right) = parent.split_at(32);
+		let children = [
+			(offset, left_len, left, position + PARENT_LEN),
+			(
+				offset + left_len,
+				len - left_len,
+				right,
+				position + PARENT_LEN * (1 + parents(left_len)),
+			),
+		];
+		for (offset, len, expected, position) in children {
+			if offset < self.end && self.start < offset + len {
+				let expected = expected.try_into().expect("Chaining values are 32 bytes");
+				if let Some(differs) = self.check(offset, len, &expected, false, position)? {
+					return Ok(Some(differs));
+				}
+			}
+		}
+		Ok(None)
+	}
+}
+
+/// Chaining value of the group of bytes at `offset`, or the root hash if
+/// it's the whole file.
+fn leaf(group: &[u8], offset: u64, root: bool) -> ChainingValue {
+	match root {
+		true => *blake3::hash(group).as_bytes(),
+		false => blake3::Hasher::new().set_input_offset(offset).update(group).finalize_non_root(),
+	}
+}
+
+/// Chaining value of a parent node, or the root hash if it's the `root`.
+fn merge(parent: &[u8], root: bool) -> ChainingValue {
+	let left = parent[..32].try_into().expect("Chaining values are 32 bytes");
+	let right = parent[32..].try_into().expect("Chaining values are 32 bytes");
+	match root {
+		true => *merge_subtrees_root(&left, &right, Mode::Hash).as_bytes(),
+		false => merge_subtrees_non_root(&left, &right, Mode::Hash),
+	}
+}
+
+/// Amount of parent nodes in the tree of `len` bytes.
+fn parents(len: u64) -> u64 {
+	len.div_ceil(GROUP_LEN).max(1) - 1
+}
+
+/// Bytes of a hexadecimal 32-byte hash, such as BLAKE3's.
+pub(super) fn decode_hash(hash: &str) -> Option<ChainingValue> {
+	let mut bytes = [0; 32];
+	if hash.len() != 64 {
+		return None;
+	}
+	for (i, byte) in bytes.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(hash.get(2 * i..2 * i + 2)?, 16).ok()?;
+	}
+	Some(bytes)
+}
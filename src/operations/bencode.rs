@@ -0,0 +1,170 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Bencoding, the serialization of `.torrent` files.
+
+use std::collections::BTreeMap;
+
+/// A bencoded value. Dictionaries are sorted by key, as bencoding requires.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub(super) enum Value {
+	Int(i64),
+	Bytes(Vec<u8>),
+	List(Vec<Value>),
+	Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+	/// Parse a whole bencoded document.
+	pub(super) fn parse(data: &[u8]) -> Result<Value, String> {
+		let mut parser = Parser { data, position: 0, depth: 0 };
+		let value = parser.value()?;
+		match parser.position == data.len() {
+			true => Ok(value),
+			false => Err(format!("trailing data at byte {}", parser.position)),
+		}
+	}
+
+	/// Value of `key`, if this is a dictionary holding it.
+	pub(super) fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Value::Dict(dict) => dict.get(key.as_bytes()),
+			_ => None,
+		}
+	}
+
+	pub(super) fn as_int(&self) -> Option<i64> {
+		match self {
+			Value::Int(int) => Some(*int),
+			_ => None,
+		}
+	}
+
+	pub(super) fn as_bytes(&self) -> Option<&[u8]> {
+		match self {
+			Value::Bytes(bytes) => Some(bytes),
+			_ => None,
+		}
+	}
+
+	pub(super) fn as_list(&self) -> Option<&[Value]> {
+		match self {
+			Value::List(list) => Some(list),
+			_ => None,
+		}
+	}
+
+	pub(super) fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, Value>> {
+		match self {
+			Value::Dict(dict) => Some(dict),
+			_ => None,
+		}
+	}
+
+	/// Bencode the value.
+	pub(super) fn encode(&self, out: &mut Vec<u8>) {
+		match self {
+			Value::Int(int) => out.extend_from_slice(format!("i{}e", int).as_bytes()),
+			Value::Bytes(bytes) => {
+				out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+				out.extend_from_slice(bytes);
+			}
+			Value::List(list) => {
+				out.push(b'l');
+				list.iter().for_each(|value| value.encode(out));
+				out.push(b'e');
+			}
+			Value::Dict(dict) => {
+				out.push(b'd');
+				for (key, value) in dict {
+					Value::Bytes(key.clone()).encode(out);
+					value.encode(out);
+				}
+				out.push(b'e');
+			}
+		}
+	}
+}
+
+/// Nesting deeper than this is refused, so hostile files can't overflow the
+/// stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+	data: &'a [u8],
+	position: usize,
+	depth: usize,
+}
+
+impl Parser<'_> {
+	fn value(&mut self) -> Result<Value, String> {
+		let start = self.position;
+		match self.data.get(self.position) {
+			Some(b'i') => {
+				self.position += 1;
+				let int = self.until(b'e')?;
+				int.parse().map(Value::Int).map_err(|_| format!("invalid integer at byte {}", start))
+			}
+			Some(b'l' | b'd') => {
+				self.depth += 1;
+				if self.depth > MAX_DEPTH {
+					return Err(format!("nested too deep at byte {}", start));
+				}
+				let dict = self.data[self.position] == b'd';
+				self.position += 1;
+				let mut list = Vec::new();
+				while self.data.get(self.position) != Some(&b'e') {
+					list.push(self.value()?);
+				}
+				self.position += 1;
+				self.depth -= 1;
+				if !dict {
+					return Ok(Value::List(list));
+				}
+				if list.len() % 2 != 0 {
+					return Err(format!("key without a value in the dictionary at byte {}", start));
+				}
+				let mut entries = BTreeMap::new();
+				let mut pairs = list.into_iter();
+				while let (Some(key), Some(value)) = (pairs.next(), pairs.next()) {
+					let Value::Bytes(key) = key else {
+						return Err(format!("non-string key in the dictionary at byte {}", start));
+					};
+					entries.insert(key, value);
+				}
+				Ok(Value::Dict(entries))
+			}
+			Some(b'0'..=b'9') => {
+				let len: usize = self.until(b':')?.parse().map_err(|_| format!("invalid length at byte {}", start))?;
+				let bytes = self
+					.data
+					.get(self.position..self.position.saturating_add(len))
+					.ok_or_else(|| format!("string at byte {} runs past the end", start))?;
+				self.position += len;
+				Ok(Value::Bytes(bytes.to_vec()))
+			}
+			Some(_) => Err(format!("unexpected byte at {}", start)),
+			None => Err("unexpected end of data".to_owned()),
+		}
+	}
+
+	/// Text up to `end`, skipping past it.
+	fn until(&mut self, end: u8) -> Result<&str, String> {
+		let rest = &self.data[self.position..];
+		let len = rest.iter().position(|&b| b == end).ok_or("unexpected end of data")?;
+		self.position += len + 1;
+		std::str::from_utf8(&rest[..len]).map_err(|err| err.to_string())
+	}
+}
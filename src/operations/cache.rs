@@ -0,0 +1,197 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::{HashMap, HashSet},
+	env,
+	fs::{File, create_dir_all, read_to_string, remove_file, rename},
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+	process,
+	sync::atomic::{AtomicU64, Ordering},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{FileRecord, lock::lock_run};
+use crate::Algorithm;
+
+/// Comment on the first line of a cache file.
+static CACHE_HEADER: &str = "# quickdash hash cache 1";
+
+/// Files modified this recently aren't cached, as a change within the same
+/// modification time tick would go unnoticed.
+pub(super) const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// How long saving waits for other runs saving the same cache.
+const LOCK_WAIT: Duration = Duration::from_secs(60);
+
+/// Hashes of files from earlier runs, by device and inode, kept valid by
+/// their size and modification time.
+///
+/// Stored as a tab-separated text file, one file per line:
+/// `algorithm dev ino size mtime hash`.
+#[derive(Debug)]
+pub(super) struct HashCache {
+	path: PathBuf,
+	entries: HashMap<(Algorithm, u64, u64), (u64, Duration, String)>,
+	/// Hashes remembered by this run.
+	fresh: HashMap<(Algorithm, u64, u64), (u64, Duration, String)>,
+	/// Entries looked up whose file changed or whose inode now holds another
+	/// file.
+	stale: HashSet<(Algorithm, u64, u64)>,
+	started: SystemTime,
+}
+
+impl HashCache {
+	/// Load the cache file at `path`. A missing or unreadable one is an
+	/// empty cache.
+	pub(super) fn load(path: &Path) -> Self {
+		HashCache {
+			path: path.to_owned(),
+			entries: read_entries(path),
+			fresh: HashMap::new(),
+			stale: HashSet::new(),
+			started: SystemTime::now(),
+		}
+	}
+
+	/// Cached hash of the file, if it hasn't changed since it was hashed.
+	/// Entries of files that changed are dropped when saving.
+	pub(super) fn get(&mut self, record: &FileRecord, algo: Algorithm) -> Option<String> {
+		let ((dev, ino), mtime) = (record.ino?, since_epoch(record.mtime?)?);
+		let key = (cached_algorithm(algo)?, dev, ino);
+		match self.entries.get(&key) {
+			Some((len, cached_mtime, hash)) if *len == record.len && *cached_mtime == mtime => Some(hash.clone()),
+			Some(_) => {
+				self.stale.insert(key);
+				None
+			}
+			None => None,
+		}
+	}
+
+	/// Remember the hash of a file, unless it was modified too recently to
+	/// be sure it won't change unnoticed.
+	pub(super) fn insert(&mut self, record: &FileRecord, algo: Algorithm, hash: &str) {
+		let (Some(algo), Some((dev, ino)), Some(mtime)) = (cached_algorithm(algo), record.ino, record.mtime) else {
+			return;
+		};
+		if mtime + SETTLE_TIME > self.started {
+			return;
+		}
+		if let Some(mtime) = since_epoch(mtime) {
+			self.fresh.insert((algo, dev, ino), (record.len, mtime, hash.to_owned()));
+		}
+	}
+
+	/// Merge the hashes remembered by this run into the cache file, without
+	/// its stale entries, replacing it in one go.
+	///
+	/// Runs saving the same cache at once take turns, each merging into what
+	/// the one before it saved.
+	pub(super) fn save(&self) -> io::Result<()> {
+		if self.fresh.is_empty() && self.stale.is_empty() {
+			return Ok(());
+		}
+		if let Some(parent) = self.path.parent() {
+			create_dir_all(parent)?;
+		}
+		let Some(_lock) = lock_run(&self.path, LOCK_WAIT, false)? else {
+			return Err(io::Error::new(io::ErrorKind::WouldBlock, "another run kept the cache locked"));
+		};
+		let mut entries = read_entries(&self.path);
+		entries.retain(|key, _| !self.stale.contains(key));
+		entries.extend(self.fresh.iter().map(|(key, value)| (*key, value.clone())));
+
+		let temporary = temporary_path(&self.path);
+		let written = write_entries(&temporary, &entries).and_then(|()| rename(&temporary, &self.path));
+		if written.is_err() {
+			let _ = remove_file(&temporary);
+		}
+		written
+	}
+}
+
+#[allow(clippy::type_complexity)]
+fn read_entries(path: &Path) -> HashMap<(Algorithm, u64, u64), (u64, Duration, String)> {
+	read_to_string(path).unwrap_or_default().lines().skip(1).filter_map(parse_line).collect()
+}
+
+#[allow(clippy::type_complexity)]
+fn write_entries(path: &Path, entries: &HashMap<(Algorithm, u64, u64), (u64, Duration, String)>) -> io::Result<()> {
+	let mut out = BufWriter::new(File::create(path)?);
+	writeln!(out, "{}", CACHE_HEADER)?;
+	for ((algo, dev, ino), (len, mtime, hash)) in entries {
+		writeln!(
+			out,
+			"{:?}\t{}\t{}\t{}\t{}.{:09}\t{}",
+			algo,
+			dev,
+			ino,
+			len,
+			mtime.as_secs(),
+			mtime.subsec_nanos(),
+			hash
+		)?;
+	}
+	out.into_inner()?.sync_all()
+}
+
+/// A temporary file next to `path` no other run or thread writes to.
+fn temporary_path(path: &Path) -> PathBuf {
+	static COUNTER: AtomicU64 = AtomicU64::new(0);
+	let mut temporary = path.as_os_str().to_owned();
+	temporary.push(format!(".{}.{}.tmp", process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+	PathBuf::from(temporary)
+}
+
+/// Where the hash cache is kept when not told: the user's cache directory.
+pub fn default_cache_path() -> Option<PathBuf> {
+	let cache_dir = if cfg!(windows) {
+		env::var_os("LOCALAPPDATA").map(PathBuf::from)
+	} else {
+		env::var_os("XDG_CACHE_HOME")
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+	};
+	cache_dir.map(|dir| dir.join("quickdash").join("hashes.cache"))
+}
+
+/// Files hashed with the default algorithm share entries with it. S3 ETags
+/// aren't cached, as they depend on the part size.
+fn cached_algorithm(algo: Algorithm) -> Option<Algorithm> {
+	match algo {
+		Algorithm::UNSPECIFIED => Some(Algorithm::BLAKE3),
+		Algorithm::S3ETag => None,
+		algo => Some(algo),
+	}
+}
+
+fn since_epoch(time: SystemTime) -> Option<Duration> {
+	time.duration_since(UNIX_EPOCH).ok()
+}
+
+#[allow(clippy::type_complexity)]
+fn parse_line(line: &str) -> Option<((Algorithm, u64, u64), (u64, Duration, String))> {
+	let mut fields = line.split('\t');
+	let algo: Algorithm = fields.next()?.parse().ok()?;
+	let dev = fields.next()?.parse().ok()?;
+	let ino = fields.next()?.parse().ok()?;
+	let len = fields.next()?.parse().ok()?;
+	let (secs, nanos) = fields.next()?.split_once('.')?;
+	let mtime = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+	let hash = fields.next()?.to_owned();
+	Some(((algo, dev, ino), (len, mtime, hash)))
+}
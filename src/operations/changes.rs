@@ -0,0 +1,316 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Recording which paths change under a directory with inotify, on Linux,
+//! so that updating its hash file walks only those.
+//!
+//! The recorder appends the changed paths, relative to the directory, to a
+//! journal next to the hash file, one per line and escaped like hash file
+//! names, after a first line telling when it started. `*` stands for
+//! everything, when changes were lost. It holds a lock on the journal while
+//! it runs, so journals it stopped writing to, which may have missed
+//! changes, aren't trusted.
+
+// inotify and flock have no safe wrapper in std
+#![cfg_attr(target_os = "linux", allow(unsafe_code))]
+
+use std::{
+	collections::BTreeSet,
+	fs::File,
+	io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+	path::{Path, PathBuf},
+};
+
+use super::WalkOptions;
+use crate::utilities::unescape_filename;
+
+/// Start of the hash file header line recording the journal position it was
+/// last updated at.
+pub static CHANGES_HEADER_PREFIX: &str = "changes journal: ";
+
+/// First line of journals, before when the recorder started.
+static JOURNAL_HEADER: &str = "# quickdash changes ";
+
+/// Line standing for every path.
+static EVERYTHING: &str = "*";
+
+/// Journal the changes under the directory of `hash_file` are recorded in,
+/// next to it.
+pub fn changes_path(hash_file: &Path) -> PathBuf {
+	let mut path = hash_file.as_os_str().to_owned();
+	path.push(".changes");
+	PathBuf::from(path)
+}
+
+/// A position in a journal of changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangesPosition {
+	/// When the recorder writing it started, in nanoseconds since the epoch.
+	pub started: u64,
+	/// Length of the journal.
+	pub offset: u64,
+}
+
+impl ChangesPosition {
+	/// The position recorded by a hash file header line, if it is one.
+	pub fn from_header(line: &str) -> Option<Self> {
+		let (started, offset) = line.strip_prefix(CHANGES_HEADER_PREFIX)?.split_once(' ')?;
+		Some(ChangesPosition { started: started.parse().ok()?, offset: offset.parse().ok()? })
+	}
+
+	/// Hash file header line recording the position.
+	pub fn header(&self) -> String {
+		format!("{}{} {}", CHANGES_HEADER_PREFIX, self.started, self.offset)
+	}
+}
+
+/// The current position in `journal`, if a recorder is still writing it.
+pub fn changes_position(journal: &Path) -> io::Result<Option<ChangesPosition>> {
+	let mut file = File::open(journal)?;
+	if !imp::is_locked(&file)? {
+		return Ok(None);
+	}
+	let mut header = String::new();
+	BufReader::new(&mut file).read_line(&mut header)?;
+	let Some(started) = header.trim_end().strip_prefix(JOURNAL_HEADER).and_then(|started| started.parse().ok()) else {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{:?} isn't a journal of changes", journal)));
+	};
+	Ok(Some(ChangesPosition { started, offset: file.metadata()?.len() }))
+}
+
+/// The paths recorded in `journal` as changed from `since` to `now`, or none
+/// if that's not known, for the recorder having started over or lost
+/// changes.
+pub fn changed_paths(journal: &Path, since: ChangesPosition, now: ChangesPosition) -> io::Result<Option<BTreeSet<PathBuf>>> {
+	if since.started != now.started || since.offset > now.offset {
+		return Ok(None);
+	}
+	let mut file = File::open(journal)?;
+	file.seek(SeekFrom::Start(since.offset))?;
+	let mut recorded = String::new();
+	file.take(now.offset - since.offset).read_to_string(&mut recorded)?;
+	let mut paths = BTreeSet::new();
+	for line in recorded.lines() {
+		match unescape_filename(line) {
+			Some(path) if path != EVERYTHING => {
+				paths.insert(PathBuf::from(path));
+			}
+			_ => return Ok(None),
+		}
+	}
+	Ok(Some(paths))
+}
+
+/// Record the paths that change under `path` in `journal` until stopped,
+/// leaving out those `options` ignore. Fails if another recorder is writing
+/// it.
+pub fn record_changes(path: &Path, journal: &Path, options: &WalkOptions) -> io::Result<()> {
+	imp::record(path, journal, options)
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+	use std::{
+		collections::{BTreeSet, HashMap},
+		ffi::CString,
+		fs::{File, OpenOptions},
+		io::{self, Write},
+		mem,
+		os::{fd::AsRawFd, unix::ffi::OsStrExt},
+		path::{Path, PathBuf},
+		time::{SystemTime, UNIX_EPOCH},
+	};
+
+	use walkdir::WalkDir;
+
+	use super::{EVERYTHING, JOURNAL_HEADER};
+	use crate::{
+		operations::{WalkOptions, is_ignored},
+		utilities::{escape_filename, relative_name},
+	};
+
+	/// Changes watched for in each directory.
+	const WATCHED: u32 = libc::IN_CREATE
+		| libc::IN_DELETE
+		| libc::IN_MODIFY
+		| libc::IN_ATTRIB
+		| libc::IN_MOVED_FROM
+		| libc::IN_MOVED_TO
+		| libc::IN_ONLYDIR
+		| libc::IN_DONT_FOLLOW;
+
+	/// Whether a recorder holds the lock on `file`.
+	pub(super) fn is_locked(file: &File) -> io::Result<bool> {
+		// SAFETY: the file descriptor is open for the duration of the call
+		if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_SH | libc::LOCK_NB) } == 0 {
+			// SAFETY: as above
+			unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) };
+			return Ok(false);
+		}
+		let err = io::Error::last_os_error();
+		match err.raw_os_error() {
+			Some(libc::EWOULDBLOCK) => Ok(true),
+			_ => Err(err),
+		}
+	}
+
+	/// Directories watched by an inotify instance, by watch descriptor.
+	struct Watches {
+		fd: i32,
+		root: PathBuf,
+		dirs: HashMap<i32, PathBuf>,
+	}
+
+	impl Watches {
+		/// Watch the directory `dir` and those under it, leaving out those
+		/// `options` ignore. Returns false, having warned, if one that's still
+		/// there couldn't be watched, e.g. with the inotify watch limit reached,
+		/// as changes under it would then go unrecorded.
+		fn add(&mut self, dir: &Path, options: &WalkOptions) -> bool {
+			let root = self.root.clone();
+			let walker = WalkDir::new(dir)
+				.follow_links(false)
+				.into_iter()
+				.filter_entry(|entry| entry.file_type().is_dir() && !is_ignored(&root, entry, entry.path().components().count() - root.components().count(), options));
+			for entry in walker.flatten() {
+				let Ok(path) = CString::new(entry.path().as_os_str().as_bytes()) else {
+					continue;
+				};
+				// SAFETY: `path` is NUL-terminated and outlives the call
+				let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCHED) };
+				if wd >= 0 {
+					self.dirs.insert(wd, entry.path().to_owned());
+					continue;
+				}
+				let err = io::Error::last_os_error();
+				// Directories gone since have nothing to watch
+				if !matches!(err.raw_os_error(), Some(libc::ENOENT | libc::ENOTDIR)) {
+					eprintln!("Failed to watch {:?}: {}, so everything is recorded as changed", entry.path(), err);
+					return false;
+				}
+			}
+			true
+		}
+	}
+
+	impl Drop for Watches {
+		fn drop(&mut self) {
+			// SAFETY: the inotify instance is only closed here
+			unsafe { libc::close(self.fd) };
+		}
+	}
+
+	pub(super) fn record(path: &Path, journal: &Path, options: &WalkOptions) -> io::Result<()> {
+		let root = path.canonicalize()?;
+		let mut file = OpenOptions::new().create(true).write(true).truncate(false).open(journal)?;
+		// SAFETY: the file descriptor is open for the duration of the call,
+		// and the lock is released when it's closed
+		if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+			return Err(io::Error::new(io::ErrorKind::WouldBlock, format!("another recorder is writing {:?}", journal)));
+		}
+		file.set_len(0)?;
+		let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+		writeln!(file, "{}{}", JOURNAL_HEADER, started)?;
+		// Writing the journal is a change too
+		let journal = journal.canonicalize()?;
+
+		// SAFETY: no pointers are passed
+		let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+		if fd < 0 {
+			return Err(io::Error::last_os_error());
+		}
+		let mut watches = Watches { fd, root: root.clone(), dirs: HashMap::new() };
+		if !watches.add(&root, options) {
+			writeln!(file, "{}", EVERYTHING)?;
+		}
+
+		// Aligned for `inotify_event`
+		let mut buffer = vec![0u64; 8192];
+		loop {
+			// SAFETY: `buffer` has room for the bytes asked for
+			let read = unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len() * mem::size_of::<u64>()) };
+			if read < 0 {
+				let err = io::Error::last_os_error();
+				if err.kind() == io::ErrorKind::Interrupted {
+					continue;
+				}
+				return Err(err);
+			}
+			// SAFETY: the kernel wrote `read` bytes of `buffer`
+			let bytes = unsafe { std::slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), read as usize) };
+			// Paths changed again after an update are recorded again
+			let (mut lines, mut recorded) = (String::new(), BTreeSet::new());
+			let mut offset = 0;
+			while offset + mem::size_of::<libc::inotify_event>() <= bytes.len() {
+				// SAFETY: the kernel writes whole events, each followed by
+				// its `len` bytes of name
+				let event = unsafe { bytes.as_ptr().add(offset).cast::<libc::inotify_event>().read_unaligned() };
+				let name_start = offset + mem::size_of::<libc::inotify_event>();
+				let name = &bytes[name_start..name_start + event.len as usize];
+				let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+				offset = name_start + event.len as usize;
+
+				if event.mask & libc::IN_Q_OVERFLOW != 0 {
+					lines.push_str(EVERYTHING);
+					lines.push('\n');
+					continue;
+				}
+				if event.mask & libc::IN_IGNORED != 0 {
+					watches.dirs.remove(&event.wd);
+					continue;
+				}
+				let Some(dir) = watches.dirs.get(&event.wd) else {
+					continue;
+				};
+				let changed = dir.join(std::ffi::OsStr::from_bytes(name));
+				if event.mask & libc::IN_ISDIR != 0
+					&& event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0
+					&& !watches.add(&changed, options)
+				{
+					lines.push_str(EVERYTHING);
+					lines.push('\n');
+				}
+				let relative = relative_name(&root, &changed).to_owned();
+				if changed == journal || options.ignored_files.contains(&relative) {
+					continue;
+				}
+				if recorded.insert(relative.clone()) {
+					match relative.to_str() {
+						Some(name) => lines.push_str(escape_filename(name, false).as_deref().unwrap_or(name)),
+						// Paths that can't be written down stand for everything
+						None => lines.push_str(EVERYTHING),
+					}
+					lines.push('\n');
+				}
+			}
+			file.write_all(lines.as_bytes())?;
+		}
+	}
+}
+
+/// Changes are only recorded on Linux.
+#[cfg(not(target_os = "linux"))]
+mod imp {
+	use std::{fs::File, io, path::Path};
+
+	use crate::operations::WalkOptions;
+
+	pub(super) fn is_locked(_: &File) -> io::Result<bool> {
+		Ok(false)
+	}
+
+	pub(super) fn record(_: &Path, _: &Path, _: &WalkOptions) -> io::Result<()> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "changes are only recorded on Linux"))
+	}
+}
@@ -0,0 +1,168 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Checkpoints of `create` runs: the hashes of the files read so far,
+//! appended to a file next to the hash file as they're made, so a run that's
+//! interrupted can resume, reading only the files it hadn't, or that changed
+//! since.
+//!
+//! Stored as a tab-separated text file after a header naming how hashes were
+//! made, one file per line: `size mtime hash name`, the name escaped like in
+//! hash files.
+
+use std::{
+	collections::HashMap,
+	fs::{File, OpenOptions, read_to_string},
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::{FileRecord, cache::SETTLE_TIME};
+use crate::{
+	Algorithm, HashOptions,
+	utilities::{escape_filename, unescape_filename},
+};
+
+/// Start of the first line of checkpoint files.
+static CHECKPOINT_HEADER: &str = "# quickdash checkpoint 1";
+
+/// How often what was hashed is written out.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Checkpoint of the run hashing the files of `hash_file`, next to it.
+pub fn checkpoint_path(hash_file: &Path) -> PathBuf {
+	let mut path = hash_file.as_os_str().to_owned();
+	path.push(".checkpoint");
+	PathBuf::from(path)
+}
+
+/// Hashes checkpointed by an interrupted run, kept valid by the size and
+/// modification time of their files, and the checkpoint of this one.
+#[derive(Debug)]
+pub(super) struct Checkpoint {
+	entries: HashMap<PathBuf, (u64, Duration, String)>,
+	out: BufWriter<File>,
+	flushed: Instant,
+	started: SystemTime,
+	/// First failure to write, after which nothing more is.
+	failed: Option<io::Error>,
+}
+
+impl Checkpoint {
+	/// Open the checkpoint at `path` for a run hashing files with `algo` as
+	/// `hash_options` say, resuming from the hashes it holds with `resume`, if
+	/// they were made the same way, starting it over otherwise.
+	pub(super) fn open(path: &Path, algo: Algorithm, hash_options: &HashOptions, resume: bool) -> io::Result<Self> {
+		let header = format!(
+			"{} {:?} {} {}",
+			CHECKPOINT_HEADER,
+			algo,
+			if hash_options.text_mode { "text" } else { "binary" },
+			hash_options.part_size
+		);
+		let mut entries = HashMap::new();
+		let (mut resumed, mut cut) = (false, false);
+		if resume {
+			match read_to_string(path) {
+				Ok(text) => {
+					// A line cut short by the interruption is left out
+					let complete = &text[..text.rfind('\n').map_or(0, |end| end + 1)];
+					let mut lines = complete.lines();
+					if lines.next() == Some(header.as_str()) {
+						(resumed, cut) = (true, complete.len() < text.len());
+						entries.extend(lines.filter_map(parse_line));
+					}
+				}
+				Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+				Err(err) => return Err(err),
+			}
+		}
+		let mut out = match resumed {
+			true => BufWriter::new(OpenOptions::new().append(true).open(path)?),
+			false => {
+				let mut out = BufWriter::new(File::create(path)?);
+				writeln!(out, "{}", header)?;
+				out
+			}
+		};
+		if cut {
+			writeln!(out)?;
+		}
+		Ok(Checkpoint { entries, out, flushed: Instant::now(), started: SystemTime::now(), failed: None })
+	}
+
+	/// Checkpointed hash of the file stored as `name`, if it hasn't changed
+	/// since it was hashed.
+	pub(super) fn get(&self, name: &Path, record: &FileRecord) -> Option<String> {
+		let mtime = record.mtime?.duration_since(UNIX_EPOCH).ok()?;
+		match self.entries.get(name) {
+			Some((len, checkpointed_mtime, hash)) if *len == record.len && *checkpointed_mtime == mtime => Some(hash.clone()),
+			_ => None,
+		}
+	}
+
+	/// Checkpoint the hash of the file stored as `name`, unless it was
+	/// modified too recently to be sure it won't change unnoticed, or its
+	/// name can't be written down.
+	pub(super) fn record(&mut self, name: &Path, record: &FileRecord, hash: &str) {
+		let (Some(mtime), Some(name)) = (record.mtime, name.to_str()) else {
+			return;
+		};
+		if self.failed.is_some() || mtime + SETTLE_TIME > self.started {
+			return;
+		}
+		let Ok(since_epoch) = mtime.duration_since(UNIX_EPOCH) else {
+			return;
+		};
+		let escaped = escape_filename(name, false);
+		let written = writeln!(
+			self.out,
+			"{}\t{}.{:09}\t{}\t{}",
+			record.len,
+			since_epoch.as_secs(),
+			since_epoch.subsec_nanos(),
+			hash,
+			escaped.as_deref().unwrap_or(name)
+		)
+		.and_then(|()| match self.flushed.elapsed() >= FLUSH_INTERVAL {
+			true => {
+				self.flushed = Instant::now();
+				self.out.flush()
+			}
+			false => Ok(()),
+		});
+		if let Err(err) = written {
+			self.failed = Some(err);
+		}
+	}
+
+	/// Write out what's left, getting the first failure to write if any.
+	pub(super) fn finish(mut self) -> io::Result<()> {
+		match self.failed.take() {
+			Some(err) => Err(err),
+			None => self.out.flush(),
+		}
+	}
+}
+
+fn parse_line(line: &str) -> Option<(PathBuf, (u64, Duration, String))> {
+	let mut fields = line.splitn(4, '\t');
+	let len = fields.next()?.parse().ok()?;
+	let (secs, nanos) = fields.next()?.split_once('.')?;
+	let mtime = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+	let hash = fields.next()?.to_owned();
+	let name = unescape_filename(fields.next()?)?;
+	Some((PathBuf::from(name), (len, mtime, hash)))
+}
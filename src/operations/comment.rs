@@ -0,0 +1,68 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::Write;
+
+use clap::ValueEnum;
+
+/// Character starting comment lines in written hash files. Both are accepted
+/// when reading.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum CommentStyle {
+	/// `; comment`, like QuickSFV.
+	#[default]
+	Semicolon,
+	/// `# comment`, like GNU coreutils.
+	Hash,
+}
+
+impl CommentStyle {
+	pub fn prefix(&self) -> char {
+		match *self {
+			CommentStyle::Semicolon => ';',
+			CommentStyle::Hash => '#',
+		}
+	}
+
+	/// Write `text` as a comment line.
+	pub(super) fn write<W: Write>(&self, out: &mut W, text: &str) {
+		writeln!(out, "{} {}", self.prefix(), text).unwrap();
+	}
+}
+
+/// Text of a comment line, or `None` if it isn't one.
+///
+/// `#` has to be followed by whitespace, another `#` or nothing, since names
+/// start with it too, first on the line in filename-first hash files.
+///
+/// # Examples
+///
+/// ```
+/// # use quickdash::operations::comment_text;
+/// assert_eq!(comment_text("; generated by QuickSFV"), Some("generated by QuickSFV"));
+/// assert_eq!(comment_text("  # sha256"), Some("sha256"));
+/// assert_eq!(comment_text("#"), Some(""));
+/// assert_eq!(comment_text("#notes.txt  AABBCCDD"), None);
+/// assert_eq!(comment_text("AABBCCDD  file"), None);
+/// ```
+pub fn comment_text(line: &str) -> Option<&str> {
+	let line = line.trim_start();
+	if let Some(text) = line.strip_prefix(';') {
+		return Some(text.trim());
+	}
+	line.strip_prefix('#')
+		.filter(|text| text.is_empty() || text.starts_with(|c: char| c == '#' || c.is_whitespace()))
+		.map(str::trim)
+}
@@ -15,22 +15,68 @@
 
 use std::{collections::BTreeMap, path::{PathBuf}};
 
+use super::{EntryMetadata, HashingReport, Ownership, WindowsAttributes};
+use crate::Error;
+
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CompareResult {
 	FileAdded(PathBuf),
 	FileRemoved(PathBuf),
 	FileIgnored(PathBuf),
+	/// Owner, group or mode differ from the recorded ones.
+	MetadataChanged {
+		file: PathBuf,
+		was: Ownership,
+		new: Ownership,
+	},
+	/// Windows attributes or creation time differ from the recorded ones.
+	AttributesChanged {
+		file: PathBuf,
+		was: WindowsAttributes,
+		new: WindowsAttributes,
+	},
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CompareFileResult {
 	FileMatches(PathBuf),
+	/// Not read, as its size and modification time match the stored ones.
+	FileAssumedOk(PathBuf),
 	FileDiffers {
 		file: PathBuf,
 		was_hash: String,
 		new_hash: String,
 	},
+	/// Differs in the byte ranges of the pieces that differ from the
+	/// recorded ones.
+	FilePiecesDiffer {
+		file: PathBuf,
+		was_hash: String,
+		new_hash: String,
+		piece_size: u64,
+		ranges: Vec<(u64, u64)>,
+	},
+	/// Pieces of a torrent fail.
+	PiecesFail {
+		file: PathBuf,
+		pieces: Vec<usize>,
+	},
+	/// Size or modification time differ from the recorded ones.
+	FileDrifted {
+		file: PathBuf,
+		was: EntryMetadata,
+		new: EntryMetadata,
+		/// Whether the content still matches, `None` if the file wasn't read
+		/// as its size differs.
+		matches: Option<bool>,
+	},
+	/// A recorded symlink points somewhere else.
+	SymlinkRetargeted {
+		file: PathBuf,
+		was_target: String,
+		new_target: String,
+	},
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
@@ -39,19 +85,46 @@ pub enum CompareError {
 		previous_len: usize,
 		current_len: usize,
 	},
+	/// The octets and streams of a bag's payload differ from its
+	/// `Payload-Oxum`.
+	PayloadOxumDiffers {
+		recorded: (u64, u64),
+		found: (u64, u64),
+	},
+	/// The signature of a checksum file doesn't verify.
+	BadSignature,
+}
+
+/// Result of comparing current hashes against loaded ones.
+pub type CompareOutcome = Result<(Vec<CompareResult>, Vec<CompareFileResult>), CompareError>;
+
+/// Take the entries with a placeholder hash out of `loaded_hashes`, getting
+/// them as ignored. Their files weren't hashed, like those skipped for their
+/// size, so there's nothing to check them against.
+pub fn take_placeholders(loaded_hashes: &mut BTreeMap<PathBuf, String>) -> Vec<CompareResult> {
+	let mut ignored = Vec::new();
+	loaded_hashes.retain(|file, hash| {
+		let placeholder = !hash.is_empty() && hash.bytes().all(|b| b == b'-');
+		if placeholder {
+			ignored.push(CompareResult::FileIgnored(file.clone()));
+		}
+		!placeholder
+	});
+	ignored
 }
 
 /// Compare two provided hashes
 pub fn compare_hashes(
 	mut current_hashes: BTreeMap<PathBuf, String>,
 	mut loaded_hashes: BTreeMap<PathBuf, String>,
-) -> Result<(Vec<CompareResult>, Vec<CompareFileResult>), CompareError> {
-	let current_hashes_value_len = current_hashes.iter().next().unwrap().1.len();
-	let loaded_hashes_value_len = loaded_hashes.iter().next().unwrap().1.len();
-	if current_hashes_value_len != loaded_hashes_value_len {
+) -> CompareOutcome {
+	if let (Some(current_hash), Some(loaded_hash)) =
+		(current_hashes.values().next(), loaded_hashes.values().next())
+		&& current_hash.len() != loaded_hash.len()
+	{
 		return Err(CompareError::HashLengthDiffers {
-			previous_len: loaded_hashes_value_len,
-			current_len: current_hashes_value_len,
+			previous_len: loaded_hash.len(),
+			current_len: current_hash.len(),
 		});
 	}
 	let mut file_compare_results: Vec<CompareFileResult> = Vec::new();
@@ -88,6 +161,113 @@ pub fn compare_hashes(
 	))
 }
 
+/// Compare current hashes against a stream of loaded ones by walking both in
+/// path order, so that the loaded hashes never have to be held in memory.
+///
+/// Returns `Ok(None)` as soon as the loaded entries turn out not to be sorted
+/// by path, in which case the caller should fall back to `compare_hashes()`.
+pub fn compare_sorted_hashes<I>(
+	current_hashes: &BTreeMap<PathBuf, String>,
+	loaded_hashes: I,
+) -> Result<Option<CompareOutcome>, Error>
+where
+	I: IntoIterator<Item = Result<(PathBuf, String), Error>>,
+{
+	let mut compare_results = Vec::new();
+	let mut file_compare_results = Vec::new();
+
+	let mut current = current_hashes.iter().peekable();
+	let mut previous: Option<PathBuf> = None;
+	for entry in loaded_hashes {
+		let (file, loaded_hash) = entry?;
+		match previous {
+			Some(ref previous) if *previous >= file => return Ok(None),
+			Some(_) => {}
+			None => {
+				if let Some(current_hash) = current_hashes.values().next()
+					&& current_hash.len() != loaded_hash.len()
+				{
+					return Ok(Some(Err(CompareError::HashLengthDiffers {
+						previous_len: loaded_hash.len(),
+						current_len: current_hash.len(),
+					})));
+				}
+			}
+		}
+
+		while let Some((added, _)) = current.next_if(|(key, _)| **key < file) {
+			compare_results.push(CompareResult::FileAdded(added.clone()));
+		}
+		match current.next_if(|(key, _)| **key == file) {
+			Some((_, current_hash)) if *current_hash == loaded_hash => {
+				file_compare_results.push(CompareFileResult::FileMatches(file.clone()));
+			}
+			Some((_, current_hash)) => {
+				file_compare_results.push(CompareFileResult::FileDiffers {
+					file: file.clone(),
+					was_hash: loaded_hash,
+					new_hash: current_hash.clone(),
+				});
+			}
+			None => compare_results.push(CompareResult::FileRemoved(file.clone())),
+		}
+
+		previous = Some(file);
+	}
+	compare_results.extend(current.map(|(added, _)| CompareResult::FileAdded(added.clone())));
+
+	Ok(Some(Ok((compare_results, file_compare_results))))
+}
+
+/// Report the files `report` has the metadata of as assumed OK or drifted,
+/// and its retargeted symlinks as such, instead of as matching or
+/// differing, and add its ownership and attribute changes.
+pub fn apply_recorded_metadata(outcome: CompareOutcome, report: &HashingReport) -> CompareOutcome {
+	let (mut compare_results, file_compare_results) = outcome?;
+	compare_results.extend(
+		report
+			.metadata_changed
+			.iter()
+			.map(|(file, &(was, new))| CompareResult::MetadataChanged { file: file.clone(), was, new }),
+	);
+	compare_results.extend(
+		report
+			.attributes_changed
+			.iter()
+			.map(|(file, &(was, new))| CompareResult::AttributesChanged { file: file.clone(), was, new }),
+	);
+	let file_compare_results = file_compare_results
+		.into_iter()
+		.map(|result| {
+			let (file, matches) = match &result {
+				CompareFileResult::FileMatches(file) => (file, true),
+				CompareFileResult::FileDiffers { file, .. } => (file, false),
+				_ => return result,
+			};
+			if matches && report.assumed.contains(file) {
+				return CompareFileResult::FileAssumedOk(file.clone());
+			}
+			if let Some((was_target, new_target)) = report.retargeted.get(file) {
+				return CompareFileResult::SymlinkRetargeted {
+					file: file.clone(),
+					was_target: was_target.clone(),
+					new_target: new_target.clone(),
+				};
+			}
+			match report.drifted.get(file) {
+				Some(&(was, new)) => CompareFileResult::FileDrifted {
+					file: file.clone(),
+					was,
+					new,
+					matches: (was.size == new.size).then_some(matches),
+				},
+				None => result,
+			}
+		})
+		.collect();
+	Ok((compare_results, file_compare_results))
+}
+
 fn process_ignores<F, Rc, Rl>(
 	f: F,
 	cres: Rc,
@@ -0,0 +1,258 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Daemon taking jobs over a local Unix socket, with JSON-RPC 2.0 requests
+//! and responses one per line, so front ends and schedulers can create and
+//! verify hash files and follow their progress without starting a process
+//! for each.
+
+// `umask()` and `getuid()` have no wrapper in std
+#![allow(unsafe_code)]
+
+use std::{
+	collections::BTreeMap,
+	env, fs,
+	io::{self, BufRead, BufReader, Write},
+	os::unix::{
+		fs::{DirBuilderExt, MetadataExt},
+		net::{UnixListener, UnixStream},
+	},
+	path::{Path, PathBuf},
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	thread,
+	time::Duration,
+};
+
+use super::{
+	Json, JobOptions,
+	jobs::{Job, JobKind, default_hash_file},
+};
+
+/// How often subscribers are told how far a job got.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const NO_SUCH_JOB: i32 = -32001;
+const JOB_RUNNING: i32 = -32002;
+const JOB_FAILED: i32 = -32003;
+
+/// Where the daemon listens when not told: the user's runtime directory,
+/// or a directory of the user's own in the temporary directory.
+pub fn default_socket_path() -> PathBuf {
+	match env::var_os("XDG_RUNTIME_DIR") {
+		Some(dir) => Path::new(&dir).join("quickdash.sock"),
+		None => private_dir().join("quickdash.sock"),
+	}
+}
+
+/// Directory of the user's own for the socket, when there's no runtime
+/// directory.
+fn private_dir() -> PathBuf {
+	env::temp_dir().join(format!("quickdash-{}", uid()))
+}
+
+fn uid() -> u32 {
+	// SAFETY: no pointers are passed, and it can't fail
+	unsafe { libc::getuid() }
+}
+
+/// Create `dir` for the user alone, or make sure it already is only theirs,
+/// so that nobody else can put a socket of their own in it.
+fn make_private_dir(dir: &Path) -> io::Result<()> {
+	match fs::DirBuilder::new().mode(0o700).create(dir) {
+		Ok(()) => return Ok(()),
+		Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+		Err(err) => return Err(err),
+	}
+	let metadata = fs::symlink_metadata(dir)?;
+	match metadata.is_dir() && metadata.uid() == uid() && metadata.mode() & 0o077 == 0 {
+		true => Ok(()),
+		false => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{:?} isn't a directory only the user may use", dir))),
+	}
+}
+
+/// Connect to the daemon listening on `socket`, failing unless the socket
+/// belongs to the user, as one someone else made could answer anything.
+pub fn connect_daemon(socket: &Path) -> io::Result<UnixStream> {
+	if fs::symlink_metadata(socket)?.uid() != uid() {
+		return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{:?} belongs to another user", socket)));
+	}
+	UnixStream::connect(socket)
+}
+
+/// Take jobs on the Unix socket `socket` until asked to shut down, then
+/// remove it. A socket left behind by a daemon that's gone is taken over.
+///
+/// Only the user running the daemon may connect. The socket is made
+/// unreachable by others before it's bound, and the directory of the default
+/// one in the temporary directory is only the user's.
+pub fn serve(socket: &Path, options: JobOptions) -> io::Result<()> {
+	if socket.parent() == Some(private_dir().as_path()) {
+		make_private_dir(&private_dir())?;
+	}
+	if socket.exists() {
+		if connect_daemon(socket).is_ok() {
+			return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("a daemon already listens on {:?}", socket)));
+		}
+		fs::remove_file(socket)?;
+	}
+	// Owner-only from the moment it exists, rather than once bound
+	// SAFETY: no pointers are passed
+	let umask = unsafe { libc::umask(0o177) };
+	let listener = UnixListener::bind(socket);
+	// SAFETY: as above
+	unsafe { libc::umask(umask) };
+	let listener = listener?;
+	let daemon = Arc::new(Daemon { options, socket: socket.to_owned(), jobs: Mutex::new(Vec::new()), stop: AtomicBool::new(false) });
+	for stream in listener.incoming() {
+		if daemon.stop.load(Ordering::Acquire) {
+			break;
+		}
+		if let Ok(stream) = stream {
+			let daemon = Arc::clone(&daemon);
+			thread::spawn(move || daemon.converse(stream));
+		}
+	}
+	fs::remove_file(socket)
+}
+
+struct Daemon {
+	options: JobOptions,
+	socket: PathBuf,
+	jobs: Mutex<Vec<Arc<Job>>>,
+	stop: AtomicBool,
+}
+
+/// Error answering a request.
+struct RpcError(i32, String);
+
+impl Job {
+	fn result(&self) -> Result<Json, RpcError> {
+		match self.outcome() {
+			None => Err(RpcError(JOB_RUNNING, format!("job {} is still running", self.id))),
+			Some(Ok(result)) => Ok(result),
+			Some(Err(reason)) => Err(RpcError(JOB_FAILED, reason)),
+		}
+	}
+}
+
+impl Daemon {
+	/// Answer the requests coming on `stream`, one per line, until it's
+	/// closed.
+	fn converse(&self, stream: UnixStream) {
+		let Ok(mut out) = stream.try_clone() else {
+			return;
+		};
+		for line in BufReader::new(stream).lines() {
+			let Ok(line) = line else {
+				return;
+			};
+			if line.trim().is_empty() {
+				continue;
+			}
+			let (id, result) = match line.parse::<Json>() {
+				Ok(request) => (request.get("id").cloned(), self.answer(&request, &mut out)),
+				Err(err) => (Some(Json::Null), Err(RpcError(PARSE_ERROR, err))),
+			};
+			// Notifications get no response
+			let Some(id) = id else {
+				continue;
+			};
+			let mut response = Json::object([("jsonrpc", "2.0".into()), ("id", id)]);
+			if let Json::Object(members) = &mut response {
+				match result {
+					Ok(result) => members.insert("result".to_owned(), result),
+					Err(RpcError(code, message)) => members
+						.insert("error".to_owned(), Json::object([("code", code.into()), ("message", message.into())])),
+				};
+			}
+			if writeln!(out, "{}", response).is_err() {
+				return;
+			}
+			if self.stop.load(Ordering::Acquire) {
+				// Wake the listener up, to see it's to stop
+				let _ = UnixStream::connect(&self.socket);
+				return;
+			}
+		}
+	}
+
+	fn answer(&self, request: &Json, out: &mut impl Write) -> Result<Json, RpcError> {
+		let method = request
+			.get("method")
+			.and_then(Json::as_str)
+			.ok_or_else(|| RpcError(INVALID_REQUEST, "no method".to_owned()))?;
+		let params = request.get("params").cloned().unwrap_or(Json::Object(BTreeMap::new()));
+		match method {
+			"create" => self.submit(JobKind::Create, &params),
+			"verify" => self.submit(JobKind::Verify, &params),
+			"jobs" => Ok(Json::Array(self.jobs.lock().unwrap().iter().map(|job| job.status()).collect())),
+			"status" => Ok(self.job(&params)?.status()),
+			"result" => self.job(&params)?.result(),
+			"subscribe" => {
+				let job = self.job(&params)?;
+				loop {
+					let notification = Json::object([("jsonrpc", "2.0".into()), ("method", "progress".into()), ("params", job.status())]);
+					writeln!(out, "{}", notification).map_err(|err| RpcError(JOB_FAILED, err.to_string()))?;
+					if job.outcome().is_some() {
+						return job.result();
+					}
+					thread::sleep(PROGRESS_INTERVAL);
+				}
+			}
+			"shutdown" => {
+				self.stop.store(true, Ordering::Release);
+				Ok(Json::Bool(true))
+			}
+			method => Err(RpcError(METHOD_NOT_FOUND, format!("no method {:?}", method))),
+		}
+	}
+
+	/// Start a job of `kind` with `params`, returning its number.
+	fn submit(&self, kind: JobKind, params: &Json) -> Result<Json, RpcError> {
+		let invalid = |message: &str| RpcError(INVALID_PARAMS, message.to_owned());
+		let path = PathBuf::from(params.get("path").and_then(Json::as_str).ok_or_else(|| invalid("no path"))?);
+		let file = match params.get("file").and_then(Json::as_str) {
+			Some(file) => PathBuf::from(file),
+			None => default_hash_file(&path).ok_or_else(|| invalid("no file, and the path has no name to name it after"))?,
+		};
+		let algorithm = match params.get("algorithm").and_then(Json::as_str) {
+			Some(algorithm) => algorithm.parse().map_err(|err: String| invalid(&err))?,
+			None => self.options.algorithm,
+		};
+		let force = params.get("force").and_then(Json::as_bool).unwrap_or(false);
+		let mut jobs = self.jobs.lock().unwrap();
+		let id = jobs.len() + 1;
+		jobs.push(Job::start(id, kind, path, file, algorithm, force, &self.options));
+		Ok(Json::from(id))
+	}
+
+	/// The job numbered by the `job` parameter.
+	fn job(&self, params: &Json) -> Result<Arc<Job>, RpcError> {
+		let id = params.get("job").and_then(Json::as_u64).ok_or_else(|| RpcError(INVALID_PARAMS, "no job".to_owned()))?;
+		let jobs = self.jobs.lock().unwrap();
+		match (id as usize).checked_sub(1).and_then(|i| jobs.get(i)) {
+			Some(job) => Ok(Arc::clone(job)),
+			None => Err(RpcError(NO_SUCH_JOB, format!("no job {}", id))),
+		}
+	}
+}
+
@@ -0,0 +1,309 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Files with the same content, found by hashing those of the same size, and
+//! consolidated by hard-linking, reflinking or deleting all but one of each
+//! set. What's done is logged, to be undone by copying the kept files back.
+
+use std::{
+	collections::BTreeMap,
+	ffi::OsString,
+	fs::{self, OpenOptions},
+	io::{self, Write},
+	path::{self, Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+
+use super::{
+	ERROR_NOTE_PREFIX, HashingReport, SPINNER_STRINGS, WalkOptions, audit::utc_time, hard_links::HardLinks, hash_entry, note_warning,
+	parallel, record::FileRecord, reflink, trees::first_difference, walk_files,
+};
+use crate::{
+	Algorithm,
+	utilities::{escape_filename, long_path, unescape_filename},
+};
+
+/// What's done with the duplicates of a kept file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupeAction {
+	/// Replace them by hard links to it.
+	Hardlink,
+	/// Replace them by copies sharing its blocks, on filesystems with
+	/// copy-on-write like Btrfs, XFS and APFS.
+	Reflink,
+	/// Delete them.
+	Delete,
+}
+
+impl DedupeAction {
+	pub fn name(self) -> &'static str {
+		match self {
+			DedupeAction::Hardlink => "hardlink",
+			DedupeAction::Reflink => "reflink",
+			DedupeAction::Delete => "delete",
+		}
+	}
+}
+
+/// Which file of a set of duplicates is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+	/// The last modified.
+	Newest,
+	/// The first by name.
+	First,
+}
+
+/// Files with the same content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSet {
+	/// Size of each, in bytes.
+	pub len: u64,
+	pub kept: PathBuf,
+	pub duplicates: Vec<PathBuf>,
+	/// Those of `duplicates` already sharing their blocks with `kept`, as
+	/// reflinks of it, so that consolidating them frees no space.
+	pub shared: Vec<PathBuf>,
+}
+
+/// The sets of files under `path` with the same content, walked and read as
+/// set by `options`, hashing with `algo` those of the same size. Empty files
+/// and hard links to the same file aren't duplicates, but reflinks are, and
+/// are told apart as `shared`. Warnings go to `report`.
+pub fn find_duplicates(path: &Path, algo: Algorithm, options: &WalkOptions, keep: Keep, report: &mut HashingReport) -> Vec<DuplicateSet> {
+	let pb_style = ProgressStyle::default_bar()
+		.template("{prefix:.bold.dim} {spinner} {wide_bar} {pos:>7}/{len:7} ETA: {eta} - {msg}")
+		.unwrap()
+		.tick_strings(&SPINNER_STRINGS);
+	let pb = ProgressBar::new_spinner();
+	pb.set_style(pb_style);
+	pb.enable_steady_tick(Duration::from_millis(80));
+	pb.set_message("Finding files of the same size...");
+
+	let mut by_len = BTreeMap::<u64, Vec<FileRecord>>::new();
+	for record in walk_files(path, options, report) {
+		if record.entry.file_type().is_file() && record.len > 0 {
+			let same_len = by_len.entry(record.len).or_default();
+			// Hard links to a file already share its content
+			if record.ino.is_none() || same_len.iter().all(|other| other.ino != record.ino) {
+				same_len.push(record);
+			}
+		}
+	}
+	let files = by_len.into_values().filter(|same_len| same_len.len() > 1).flatten().collect::<Vec<_>>();
+
+	pb.reset();
+	pb.set_length(files.len() as u64);
+	pb.set_message("Hashing files...");
+	let results = match options.io_threads.max(options.hash_threads) > 1 {
+		true => parallel::hash_entries(&files, vec![None; files.len()], algo, options, &pb),
+		false => {
+			let mut hard_links = HardLinks::default();
+			files.iter().progress_with(pb).map(|record| hash_entry(record, algo, options, &mut hard_links)).collect()
+		}
+	};
+	let mut by_content = BTreeMap::<(u64, String), Vec<&FileRecord>>::new();
+	for (record, (hash, note)) in files.iter().zip(results) {
+		match note {
+			// Unstable or skipped files can't be told to be the same
+			Some(note) => {
+				if note.starts_with(ERROR_NOTE_PREFIX) {
+					report.warnings.extend(note_warning(record.path(), &Some(note)));
+				}
+			}
+			None => by_content.entry((record.len, hash)).or_default().push(record),
+		}
+	}
+
+	let mut sets = Vec::new();
+	for ((len, _), mut same) in by_content {
+		if same.len() < 2 {
+			continue;
+		}
+		same.sort_by(|a, b| a.path().cmp(b.path()));
+		let kept = match keep {
+			Keep::First => 0,
+			// The first of the newest
+			Keep::Newest => same.iter().enumerate().rev().max_by_key(|(_, record)| record.mtime).map_or(0, |(i, _)| i),
+		};
+		let kept = same.remove(kept).path().to_path_buf();
+		let duplicates = same.iter().map(|record| record.path().to_path_buf()).collect::<Vec<_>>();
+		let shared = duplicates.iter().filter(|duplicate| reflink::shares_blocks(&kept, duplicate).unwrap_or(false)).cloned().collect();
+		sets.push(DuplicateSet { len, kept, duplicates, shared });
+	}
+	sets.sort_by(|a, b| a.kept.cmp(&b.kept));
+	sets
+}
+
+/// Do `action` with `duplicate`, after reading it again along with `kept` to
+/// be sure they still hold the same. Links are only made on the same
+/// filesystem, replacing `duplicate` in one go. Reflinks keep the
+/// permissions, modification time and, where allowed, owner of `duplicate`.
+pub fn consolidate(kept: &Path, duplicate: &Path, action: DedupeAction) -> io::Result<()> {
+	if first_difference(kept, duplicate)?.is_some() {
+		return Err(io::Error::other("no longer the same as the kept file"));
+	}
+	if action == DedupeAction::Delete {
+		return fs::remove_file(long_path(duplicate));
+	}
+	if !same_device(kept, duplicate)? {
+		return Err(io::Error::other("not on the same filesystem as the kept file"));
+	}
+	let metadata = fs::metadata(long_path(duplicate))?;
+	let temporary = temporary_path(duplicate);
+	// Hard links are the kept file, attributes and all
+	let replaced = match action {
+		DedupeAction::Hardlink => fs::hard_link(long_path(kept), &temporary),
+		_ => reflink::reflink(kept, &temporary).and_then(|()| take_attributes(&temporary, &metadata)),
+	};
+	replaced.and_then(|()| fs::rename(&temporary, long_path(duplicate))).inspect_err(|_| {
+		let _ = fs::remove_file(&temporary);
+	})
+}
+
+/// Give the new file at `temporary` the permissions, modification time and,
+/// where allowed, the owner of the file with `metadata` it replaces.
+fn take_attributes(temporary: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+	let file = fs::File::options().write(true).open(temporary)?;
+	// Changing the owner may clear the setuid and setgid bits, so it's first
+	set_owner(&file, metadata);
+	file.set_modified(metadata.modified()?)?;
+	file.set_permissions(metadata.permissions())
+}
+
+/// Only root can give files away, so the owner is kept where allowed.
+#[cfg(unix)]
+fn set_owner(file: &fs::File, metadata: &fs::Metadata) {
+	use std::os::unix::fs::{MetadataExt, fchown};
+
+	let _ = fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn set_owner(_file: &fs::File, _metadata: &fs::Metadata) {}
+
+/// Path next to `path` to make its replacement at.
+fn temporary_path(path: &Path) -> PathBuf {
+	let mut temporary = OsString::from(long_path(path).as_os_str());
+	temporary.push(".dedupe.tmp");
+	PathBuf::from(temporary)
+}
+
+#[cfg(unix)]
+fn same_device(a: &Path, b: &Path) -> io::Result<bool> {
+	use std::os::unix::fs::MetadataExt;
+
+	Ok(fs::metadata(a)?.dev() == fs::metadata(b)?.dev())
+}
+
+/// Linking across volumes fails anyway.
+#[cfg(not(unix))]
+fn same_device(_: &Path, _: &Path) -> io::Result<bool> {
+	Ok(true)
+}
+
+/// Append that `action` was done with `duplicate` of `kept` to `log`, by
+/// their absolute paths.
+pub fn log_consolidation(log: &Path, action: DedupeAction, kept: &Path, duplicate: &Path) -> io::Result<()> {
+	let escape = |path: &Path| -> io::Result<String> {
+		let path = path::absolute(path)?;
+		let name = path.to_string_lossy();
+		Ok(escape_filename(&name, false).unwrap_or_else(|| name.into_owned()))
+	};
+	let line = format!("{}\t{}\t{}\t{}\n", utc_time(SystemTime::now()), action.name(), escape(kept)?, escape(duplicate)?);
+	OpenOptions::new().create(true).append(true).open(log)?.write_all(line.as_bytes())
+}
+
+/// Undo what `log` says was done, last first, copying each kept file back
+/// to where its duplicate was, as a reflink where the filesystem can, unless
+/// a file is there that isn't linked to it. The log is removed once everything is undone.
+///
+/// Returns lines telling what was done with each file, or why it couldn't
+/// be.
+pub fn undo_consolidations(log: &Path) -> io::Result<Vec<String>> {
+	let mut lines = Vec::new();
+	let mut undone = true;
+	for line in fs::read_to_string(log)?.lines().rev() {
+		let mut fields = line.split('\t').skip(1);
+		let (Some(action), Some(kept), Some(duplicate)) = (
+			fields.next().and_then(|action| DedupeAction::from_str(action, false).ok()),
+			fields.next().and_then(unescape_filename),
+			fields.next().and_then(unescape_filename),
+		) else {
+			lines.push(format!("Malformed line in the log: {:?}", line));
+			undone = false;
+			continue;
+		};
+		let (kept, duplicate) = (Path::new(&kept), Path::new(&duplicate));
+		match undo_consolidation(kept, duplicate, action) {
+			Ok(true) => lines.push(format!("Restored {:?} from {:?}", duplicate, kept)),
+			Ok(false) => lines.push(format!("Left {:?}, no longer a {} of {:?}", duplicate, action.name(), kept)),
+			Err(err) => {
+				lines.push(format!("Failed to restore {:?}: {}", duplicate, err));
+				undone = false;
+			}
+		}
+	}
+	if undone {
+		fs::remove_file(log)?;
+	}
+	Ok(lines)
+}
+
+/// Put a copy of `kept` where `duplicate` was, replacing the link to it
+/// and keeping its attributes. Returns whether that was done.
+fn undo_consolidation(kept: &Path, duplicate: &Path, action: DedupeAction) -> io::Result<bool> {
+	let current = match fs::symlink_metadata(long_path(duplicate)) {
+		Ok(metadata) => Some(metadata),
+		Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+		Err(err) => return Err(err),
+	};
+	let metadata = match &current {
+		Some(current) => current.clone(),
+		None => fs::metadata(long_path(kept))?,
+	};
+	let replaced = match (action, current) {
+		(DedupeAction::Delete, current) => current.is_none(),
+		(_, None) => false,
+		(DedupeAction::Hardlink, Some(current)) => same_file(&fs::metadata(long_path(kept))?, &current),
+		// Shared blocks can't be told apart from copied ones
+		(DedupeAction::Reflink, Some(_)) => first_difference(kept, duplicate)?.is_none(),
+	};
+	if !replaced {
+		return Ok(false);
+	}
+	let temporary = temporary_path(duplicate);
+	reflink::copy(kept, &temporary)?;
+	take_attributes(&temporary, &metadata)
+		.and_then(|()| fs::rename(&temporary, long_path(duplicate)))
+		.inspect_err(|_| {
+			let _ = fs::remove_file(&temporary);
+		})?;
+	Ok(true)
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+	use std::os::unix::fs::MetadataExt;
+
+	(a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+	a.len() == b.len() && a.modified().ok() == b.modified().ok()
+}
@@ -0,0 +1,168 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	path::{Path, PathBuf},
+	sync::{
+		Condvar, Mutex,
+		atomic::{AtomicUsize, Ordering},
+	},
+	thread,
+};
+
+use indicatif::ProgressBar;
+use walkdir::WalkDir;
+
+use super::{FileRecord, HashingReport, WalkOptions, is_ignored, is_walked_file, loop_warning};
+use crate::utilities::long_path;
+
+/// Directories waiting to be listed, and how many are being listed.
+#[derive(Default)]
+struct Queue {
+	directories: Vec<(PathBuf, usize)>,
+	listing: usize,
+}
+
+/// Walk the specified directory like `walk_files()` does, listing
+/// directories on `threads` threads at once, which helps most on trees with
+/// millions of entries and on network filesystems.
+///
+/// Files are yielded in no particular order, with their metadata queried by
+/// the threads that found them. The amount found so far is shown on `pb`.
+pub(super) fn walk_files_parallel(
+	path: &Path,
+	options: &WalkOptions,
+	report: &mut HashingReport,
+	threads: usize,
+	pb: &ProgressBar,
+) -> Vec<FileRecord> {
+	let root = long_path(path).into_owned();
+	let queue = Mutex::new(Queue {
+		directories: vec![(root.clone(), 0)],
+		listing: 0,
+	});
+	let changed = Condvar::new();
+	let found = AtomicUsize::new(0);
+
+	let results: Vec<(Vec<FileRecord>, HashingReport)> = thread::scope(|scope| {
+		let workers: Vec<_> = (0..threads)
+			.map(|_| {
+				scope.spawn(|| {
+					let mut files = Vec::new();
+					let mut problems = HashingReport::default();
+					while let Some((directory, depth)) = next_directory(&queue, &changed) {
+						let subdirectories = list(&root, &directory, depth, options, &mut files, &mut problems);
+						let mut queue = queue.lock().unwrap();
+						queue.directories.extend(subdirectories);
+						queue.listing -= 1;
+						changed.notify_all();
+						drop(queue);
+						pb.set_message(format!(
+							"Finding files to hash... {} found",
+							found.fetch_add(files.len(), Ordering::Relaxed) + files.len()
+						));
+					}
+					(files, problems)
+				})
+			})
+			.collect();
+		workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+	});
+
+	let mut files = Vec::new();
+	for (mut worker_files, mut problems) in results {
+		files.append(&mut worker_files);
+		report.warnings.append(&mut problems.warnings);
+		report.errors.append(&mut problems.errors);
+	}
+	files
+}
+
+/// Take a directory off the queue, waiting while others are being listed.
+/// `None` once there's nothing left to list.
+fn next_directory(queue: &Mutex<Queue>, changed: &Condvar) -> Option<(PathBuf, usize)> {
+	let mut queue = queue.lock().unwrap();
+	loop {
+		if let Some(directory) = queue.directories.pop() {
+			queue.listing += 1;
+			return Some(directory);
+		}
+		if queue.listing == 0 {
+			return None;
+		}
+		queue = changed.wait(queue).unwrap();
+	}
+}
+
+/// List a directory `depth` levels below `root`, adding its files to `files`
+/// and its problems to `report`, and returning the subdirectories to walk.
+fn list(
+	root: &Path,
+	directory: &Path,
+	depth: usize,
+	options: &WalkOptions,
+	files: &mut Vec<FileRecord>,
+	report: &mut HashingReport,
+) -> Vec<(PathBuf, usize)> {
+	let mut subdirectories = Vec::new();
+	let entries = WalkDir::new(directory)
+		.follow_links(options.follow_symlinks)
+		.min_depth(1)
+		.max_depth(1);
+	for entry in entries {
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(err) => {
+				if let (Some(link), Some(ancestor)) = (err.path(), err.loop_ancestor()) {
+					report.warnings.push(loop_warning(root, link, ancestor));
+				}
+				continue;
+			}
+		};
+		if is_ignored(root, &entry, depth + 1, options) {
+			continue;
+		}
+		if !entry.file_type().is_dir() {
+			if is_walked_file(&entry, options, &mut report.errors) {
+				files.push(FileRecord::new(entry));
+			}
+			continue;
+		}
+		if is_walked_file(&entry, options, &mut report.errors) {
+			files.push(FileRecord::new(entry.clone()));
+		}
+		// The walker only sees one directory at a time, so look for loops here
+		if entry.path_is_symlink()
+			&& let Some(ancestor) = loop_ancestor(root, entry.path())
+		{
+			report.warnings.push(loop_warning(root, entry.path(), &ancestor));
+			continue;
+		}
+		if options.depth.is_none_or(|max_depth| depth < max_depth) {
+			subdirectories.push((entry.into_path(), depth + 1));
+		}
+	}
+	subdirectories
+}
+
+/// Directory between `root` and the specified symlink that it leads back to.
+fn loop_ancestor(root: &Path, link: &Path) -> Option<PathBuf> {
+	let target = link.canonicalize().ok()?;
+	link.ancestors()
+		.skip(1)
+		.take_while(|ancestor| ancestor.starts_with(root))
+		.find(|ancestor| ancestor.canonicalize().is_ok_and(|ancestor| ancestor == target))
+		.map(Path::to_owned)
+}
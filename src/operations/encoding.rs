@@ -0,0 +1,200 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	io::{self, Read},
+	str,
+};
+
+use clap::ValueEnum;
+
+/// Text encoding of a hash file.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum ManifestEncoding {
+	/// UTF-8, switching to the likelier of CP1252 and CP850 at the first line
+	/// that isn't valid UTF-8.
+	#[default]
+	Auto,
+	Utf8,
+	/// Windows Western European.
+	Cp1252,
+	/// DOS Western European.
+	Cp850,
+}
+
+impl ManifestEncoding {
+	/// Decode a line of the hash file. Returns `None` if it isn't valid in
+	/// this encoding.
+	///
+	/// `Auto` settles on an encoding at the first non-UTF-8 line, so the same
+	/// instance should be used for a whole file.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use quickdash::operations::ManifestEncoding;
+	/// let mut encoding = ManifestEncoding::Auto;
+	/// assert_eq!(encoding.decode("caf\u{e9}".as_bytes()).unwrap(), "caf\u{e9}");
+	/// assert_eq!(encoding, ManifestEncoding::Auto);
+	/// assert_eq!(encoding.decode(b"caf\xE9").unwrap(), "caf\u{e9}");
+	/// assert_eq!(encoding, ManifestEncoding::Cp1252);
+	/// ```
+	pub fn decode(&mut self, bytes: &[u8]) -> Option<String> {
+		match *self {
+			ManifestEncoding::Auto => match str::from_utf8(bytes) {
+				Ok(line) => Some(line.to_owned()),
+				Err(_) => {
+					*self = detect_single_byte(bytes);
+					self.decode(bytes)
+				}
+			},
+			ManifestEncoding::Utf8 => str::from_utf8(bytes).ok().map(str::to_owned),
+			ManifestEncoding::Cp1252 => Some(decode_single_byte(bytes, &CP1252)),
+			ManifestEncoding::Cp850 => Some(decode_single_byte(bytes, &CP850)),
+		}
+	}
+}
+
+/// Detect a UTF-16 byte order mark, returning whether it's big-endian.
+pub(super) fn utf16_bom(start: &[u8]) -> Option<bool> {
+	match start.get(..2) {
+		Some([0xFF, 0xFE]) => Some(false),
+		Some([0xFE, 0xFF]) => Some(true),
+		_ => None,
+	}
+}
+
+/// Reader transcoding a UTF-16 stream, without its BOM, to UTF-8.
+pub(super) struct Utf16Reader<R> {
+	inner: R,
+	big_endian: bool,
+	/// Input bytes not decoded yet: an odd byte or a lone high surrogate.
+	pending: Vec<u8>,
+	decoded: Vec<u8>,
+	position: usize,
+}
+
+impl<R: Read> Utf16Reader<R> {
+	pub(super) fn new(inner: R, big_endian: bool) -> Self {
+		Utf16Reader {
+			inner,
+			big_endian,
+			pending: Vec::new(),
+			decoded: Vec::new(),
+			position: 0,
+		}
+	}
+
+	/// Decode the next chunk of input. Returns `false` at the end of input.
+	fn fill(&mut self) -> io::Result<bool> {
+		let mut chunk = [0; 4096];
+		let read = self.inner.read(&mut chunk)?;
+		if read == 0 {
+			if !self.pending.is_empty() {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated UTF-16"));
+			}
+			return Ok(false);
+		}
+		self.pending.extend_from_slice(&chunk[..read]);
+
+		let mut units: Vec<u16> = self
+			.pending
+			.chunks_exact(2)
+			.map(|pair| {
+				let pair = [pair[0], pair[1]];
+				if self.big_endian { u16::from_be_bytes(pair) } else { u16::from_le_bytes(pair) }
+			})
+			.collect();
+		let mut keep = self.pending.len() % 2;
+		// The low half of a surrogate pair may still be on its way
+		if units.last().is_some_and(|unit| (0xD800..0xDC00).contains(unit)) {
+			units.pop();
+			keep += 2;
+		}
+
+		self.decoded.clear();
+		self.position = 0;
+		for c in char::decode_utf16(units) {
+			let c = c.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+			self.decoded.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+		}
+		self.pending.drain(..self.pending.len() - keep);
+		Ok(true)
+	}
+}
+
+impl<R: Read> Read for Utf16Reader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		while self.position == self.decoded.len() {
+			if !self.fill()? {
+				return Ok(0);
+			}
+		}
+
+		let read = buf.len().min(self.decoded.len() - self.position);
+		buf[..read].copy_from_slice(&self.decoded[self.position..self.position + read]);
+		self.position += read;
+		Ok(read)
+	}
+}
+
+/// Pick whichever single-byte encoding turns more of the non-ASCII bytes into
+/// letters, preferring CP1252 on a tie.
+fn detect_single_byte(bytes: &[u8]) -> ManifestEncoding {
+	let letters = |table: &[char; 128]| {
+		bytes
+			.iter()
+			.filter(|b| !b.is_ascii() && table[(**b - 0x80) as usize].is_alphabetic())
+			.count()
+	};
+
+	if letters(&CP850) > letters(&CP1252) {
+		ManifestEncoding::Cp850
+	} else {
+		ManifestEncoding::Cp1252
+	}
+}
+
+fn decode_single_byte(bytes: &[u8], high_half: &[char; 128]) -> String {
+	bytes
+		.iter()
+		.map(|&b| if b.is_ascii() { b as char } else { high_half[(b - 0x80) as usize] })
+		.collect()
+}
+
+/// Characters for bytes `0x80..=0xFF` in CP1252. Bytes Windows leaves
+/// undefined map to the matching C1 control, as Windows itself does.
+static CP1252: [char; 128] = [
+	'€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+	'\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+	'\u{a0}', '¡', '¢', '£', '¤', '¥', '¦', '§', '¨', '©', 'ª', '«', '¬', '\u{ad}', '®', '¯',
+	'°', '±', '²', '³', '´', 'µ', '¶', '·', '¸', '¹', 'º', '»', '¼', '½', '¾', '¿',
+	'À', 'Á', 'Â', 'Ã', 'Ä', 'Å', 'Æ', 'Ç', 'È', 'É', 'Ê', 'Ë', 'Ì', 'Í', 'Î', 'Ï',
+	'Ð', 'Ñ', 'Ò', 'Ó', 'Ô', 'Õ', 'Ö', '×', 'Ø', 'Ù', 'Ú', 'Û', 'Ü', 'Ý', 'Þ', 'ß',
+	'à', 'á', 'â', 'ã', 'ä', 'å', 'æ', 'ç', 'è', 'é', 'ê', 'ë', 'ì', 'í', 'î', 'ï',
+	'ð', 'ñ', 'ò', 'ó', 'ô', 'õ', 'ö', '÷', 'ø', 'ù', 'ú', 'û', 'ü', 'ý', 'þ', 'ÿ',
+];
+
+/// Characters for bytes `0x80..=0xFF` in CP850.
+static CP850: [char; 128] = [
+	'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+	'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ',
+	'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»',
+	'░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐',
+	'└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤',
+	'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀',
+	'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´',
+	'\u{ad}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{a0}',
+];
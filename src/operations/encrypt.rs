@@ -0,0 +1,125 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Hash files encrypted at rest, with age or gpg, so their names don't give
+//! away what's stored next to them.
+//!
+//! Hash files are encrypted ASCII-armored on write, and decrypted in memory
+//! on read, never touching the disk in the clear.
+
+use std::{
+	io::{self, Write},
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+};
+
+
+/// Start of binary age files.
+static AGE_HEADER: &[u8] = b"age-encryption.org/v1\n";
+/// Start of ASCII-armored age files.
+static AGE_ARMOR: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+/// Start of ASCII-armored OpenPGP messages.
+static PGP_ARMOR: &[u8] = b"-----BEGIN PGP MESSAGE-----";
+
+/// Tool a hash file is encrypted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encryption {
+	Age,
+	Gpg,
+}
+
+impl Encryption {
+	/// Tool `recipient` is a key of: age for `age1` and `ssh-` public keys,
+	/// gpg for anything else.
+	pub fn of_recipient(recipient: &str) -> Self {
+		match recipient.starts_with("age1") || recipient.starts_with("ssh-") {
+			true => Encryption::Age,
+			false => Encryption::Gpg,
+		}
+	}
+
+	/// Tool a file starting with `start` was encrypted with, if it was.
+	///
+	/// Binary OpenPGP messages are told by their first packet, an encrypted
+	/// session key in the old packet format gpg writes, or a public-key one
+	/// in the new.
+	pub fn detect(start: &[u8]) -> Option<Self> {
+		if start.starts_with(AGE_HEADER) || start.starts_with(AGE_ARMOR) {
+			return Some(Encryption::Age);
+		}
+		match start.first() {
+			_ if start.starts_with(PGP_ARMOR) => Some(Encryption::Gpg),
+			Some(0x84..=0x87 | 0x8c..=0x8f | 0xc1) => Some(Encryption::Gpg),
+			_ => None,
+		}
+	}
+
+	fn program(self) -> &'static str {
+		match self {
+			Encryption::Age => "age",
+			Encryption::Gpg => "gpg",
+		}
+	}
+}
+
+/// Encrypt `data` to every one of `recipients` into `out_file`,
+/// ASCII-armored.
+///
+/// Recipients must all be age keys, or all gpg ones.
+pub fn encrypt(data: &[u8], recipients: &[String], out_file: &Path) -> io::Result<()> {
+	let encryption = Encryption::of_recipient(&recipients[0]);
+	if recipients.iter().any(|recipient| Encryption::of_recipient(recipient) != encryption) {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't encrypt to both age and gpg recipients"));
+	}
+
+	let mut command = Command::new(encryption.program());
+	match encryption {
+		Encryption::Age => command.args(["--encrypt", "--armor"]),
+		// Recipients are named explicitly, so don't ask whether to trust them
+		Encryption::Gpg => command.args(["--batch", "--yes", "--armor", "--trust-model", "always", "--encrypt"]),
+	};
+	for recipient in recipients {
+		command.args(["--recipient", recipient]);
+	}
+	let mut child = command.arg("--output").arg(out_file).stdin(Stdio::piped()).spawn()?;
+	let written = child.stdin.take().unwrap().write_all(data);
+	let status = child.wait()?;
+	written?;
+	match status.success() {
+		true => Ok(()),
+		false => Err(io::Error::other(format!("{} failed to encrypt ({})", encryption.program(), status))),
+	}
+}
+
+/// Decrypt `file`, encrypted with `encryption`, into memory. age is given
+/// the `identities` files, gpg finds its keys itself.
+pub fn decrypt(file: &Path, encryption: Encryption, identities: &[PathBuf]) -> io::Result<Vec<u8>> {
+	let mut command = Command::new(encryption.program());
+	match encryption {
+		Encryption::Age => {
+			command.arg("--decrypt");
+			for identity in identities {
+				command.arg("--identity").arg(identity);
+			}
+		}
+		Encryption::Gpg => {
+			command.args(["--batch", "--quiet", "--decrypt"]);
+		}
+	}
+	let output = command.arg(file).stderr(Stdio::inherit()).output()?;
+	match output.status.success() {
+		true => Ok(output.stdout),
+		false => Err(io::Error::other(format!("{} failed to decrypt ({})", encryption.program(), output.status))),
+	}
+}
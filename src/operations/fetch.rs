@@ -0,0 +1,100 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Hash files fetched over HTTP(S) to verify against, like the `SHA256SUMS`
+//! of a release, downloaded with curl to a temporary directory, and remote
+//! files hashed as curl streams them, without being saved.
+
+use std::{
+	env::temp_dir,
+	fs, io,
+	path::{Path, PathBuf},
+	process::{self, Command, Stdio},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{Algorithm, HashOptions, hashing::try_hash_reader_with};
+
+/// How long a download of a hash file may take, or one streamed may stall,
+/// in seconds.
+const TIMEOUT: &str = "300";
+
+/// Downloads made by this process, to name their directories.
+static FETCHED: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether `file` is an `http://` or `https://` URL rather than a path.
+pub fn is_url(file: &Path) -> bool {
+	file.to_str().is_some_and(|file| file.starts_with("http://") || file.starts_with("https://"))
+}
+
+/// A downloaded file, removed when dropped.
+#[derive(Debug)]
+pub struct FetchedFile {
+	dir: PathBuf,
+	path: PathBuf,
+}
+
+impl FetchedFile {
+	/// Where it was downloaded, under the last segment of its URL, so the
+	/// name still tells the algorithm of files like `SHA256SUMS`.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for FetchedFile {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.dir);
+	}
+}
+
+/// Download `url`, following redirects, failing on error statuses.
+pub fn fetch(url: &str) -> io::Result<FetchedFile> {
+	let name = url.split(['?', '#']).next().unwrap_or_default().rsplit('/').next().filter(|name| !name.is_empty());
+	let dir = temp_dir().join(format!("quickdash-fetch-{}-{}", process::id(), FETCHED.fetch_add(1, Ordering::Relaxed)));
+	fs::create_dir_all(&dir)?;
+	let fetched = FetchedFile { path: dir.join(name.unwrap_or("manifest")), dir };
+	let status = Command::new("curl")
+		.args(["--fail", "--silent", "--show-error", "--location", "--proto", "=http,https", "--max-time", TIMEOUT])
+		.arg("--output")
+		.arg(&fetched.path)
+		.arg("--")
+		.arg(url)
+		.status()?;
+	match status.success() {
+		true => Ok(fetched),
+		false => Err(io::Error::other(format!("curl failed to fetch {} ({})", url, status))),
+	}
+}
+
+/// Hash what's at `url` with `algo` as it's downloaded, following redirects,
+/// failing on error statuses or if it stalls.
+pub fn hash_url(url: &str, algo: Algorithm, options: &HashOptions) -> io::Result<String> {
+	let mut curl = Command::new("curl")
+		.args(["--fail", "--silent", "--show-error", "--location", "--proto", "=http,https", "--speed-limit", "1", "--speed-time", TIMEOUT])
+		.arg("--")
+		.arg(url)
+		.stdout(Stdio::piped())
+		.spawn()?;
+	let hash = try_hash_reader_with(algo, curl.stdout.as_mut().expect("curl's output is piped"), options);
+	if hash.is_err() {
+		let _ = curl.kill();
+	}
+	let status = curl.wait()?;
+	match (hash, status.success()) {
+		(Ok(hash), true) => Ok(hash),
+		(Err(err), _) => Err(err),
+		(Ok(_), false) => Err(io::Error::other(format!("curl failed to fetch {} ({})", url, status))),
+	}
+}
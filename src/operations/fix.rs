@@ -0,0 +1,145 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Fixing single entries of a hash file after verification, to accept
+//! legitimate changes without creating it again.
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use super::{
+	CompareFileResult, CompareResult, EntryMetadata, LAYOUT_DIGEST_PREFIX, ReadOptions, SYMLINK_NOTE_PREFIX, TREE_HASH_PREFIX,
+	WriteOptions, read_named_algorithm, stream_hashes, write_hashes,
+};
+use crate::{Algorithm, Error};
+
+/// An entry of a hash file that verification found out of date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+	/// The file's content differs, now hashing to `new_hash`.
+	Differs { file: PathBuf, new_hash: String },
+	/// The file is gone.
+	Removed(PathBuf),
+	/// A file without an entry, hashing to `hash`.
+	Added { file: PathBuf, hash: String },
+}
+
+/// A change to make to an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+	/// Set its hash, adding it if missing.
+	Update(String),
+	Delete,
+}
+
+impl Mismatch {
+	/// Mismatches of a verification. Current hashes of files that changed
+	/// without it telling them, like those whose size changed, and of added
+	/// files are got from `hash_of`, leaving out those it has none for.
+	pub fn of<F: Fn(&Path) -> Option<String>>(
+		compare_results: &[CompareResult],
+		file_compare_results: &[CompareFileResult],
+		hash_of: F,
+	) -> Vec<Mismatch> {
+		let changed = file_compare_results.iter().filter_map(|result| match result {
+			CompareFileResult::FileDiffers { file, new_hash, .. } | CompareFileResult::FilePiecesDiffer { file, new_hash, .. } => {
+				Some(Mismatch::Differs { file: file.clone(), new_hash: new_hash.clone() })
+			}
+			CompareFileResult::FileDrifted { file, matches: Some(false) | None, .. } => {
+				Some(Mismatch::Differs { file: file.clone(), new_hash: hash_of(file)? })
+			}
+			_ => None,
+		});
+		let listed = compare_results.iter().filter_map(|result| match result {
+			CompareResult::FileRemoved(file) => Some(Mismatch::Removed(file.clone())),
+			CompareResult::FileAdded(file) => Some(Mismatch::Added { file: file.clone(), hash: hash_of(file)? }),
+			_ => None,
+		});
+		changed.chain(listed).collect()
+	}
+
+	pub fn file(&self) -> &Path {
+		match self {
+			Mismatch::Differs { file, .. } | Mismatch::Removed(file) | Mismatch::Added { file, .. } => file,
+		}
+	}
+
+	/// The fix accepting the file as it is now.
+	pub fn accept(&self) -> Fix {
+		match self {
+			Mismatch::Differs { new_hash: hash, .. } | Mismatch::Added { hash, .. } => Fix::Update(hash.clone()),
+			Mismatch::Removed(_) => Fix::Delete,
+		}
+	}
+}
+
+/// Rewrite the hash file `file` with `fixes` made to its entries, written as
+/// set by `options`, keeping its header comments and the metadata, pieces
+/// and symlink targets of the other entries. Updated entries get their
+/// metadata from `metadata`, the current one, if there.
+///
+/// Tree hashes and layout digests in the header, which would no longer
+/// match, are dropped. Sharded hash files can't be fixed, and encrypted
+/// ones are only encrypted again with `WriteOptions::encrypt_to`.
+pub fn fix_hashes(
+	file: &Path,
+	fixes: &BTreeMap<PathBuf, Fix>,
+	metadata: &BTreeMap<PathBuf, EntryMetadata>,
+	read_options: &ReadOptions,
+	mut options: WriteOptions,
+) -> Result<i32, Error> {
+	if options.algorithm == Algorithm::UNSPECIFIED {
+		options.algorithm = read_named_algorithm(file, read_options)?.unwrap_or_default();
+	}
+	let mut hashes = BTreeMap::new();
+	let mut reader = stream_hashes(file, read_options)?;
+	while let Some(entry) = reader.next() {
+		let (name, hash) = entry?;
+		if let Some(pieces) = reader.pieces() {
+			options.pieces.insert(name.clone(), pieces.clone());
+		}
+		if let Some(target) = reader.symlink_target() {
+			options.notes.insert(name.clone(), format!("{}{}", SYMLINK_NOTE_PREFIX, target));
+		} else if let Some(metadata) = reader.metadata() {
+			options.metadata.insert(name.clone(), metadata);
+		}
+		hashes.insert(name, hash);
+	}
+	options.header = reader
+		.header()
+		.iter()
+		.filter(|line| !line.starts_with(TREE_HASH_PREFIX) && !line.starts_with(LAYOUT_DIGEST_PREFIX))
+		.cloned()
+		.collect();
+	drop(reader);
+	for (name, fix) in fixes {
+		options.pieces.remove(name);
+		options.notes.remove(name);
+		options.metadata.remove(name);
+		match fix {
+			Fix::Update(hash) => {
+				hashes.insert(name.clone(), hash.clone());
+				if let Some(metadata) = metadata.get(name) {
+					options.metadata.insert(name.clone(), *metadata);
+				}
+			}
+			Fix::Delete => {
+				hashes.remove(name);
+			}
+		}
+	}
+	Ok(write_hashes(file, hashes, &options))
+}
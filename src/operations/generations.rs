@@ -0,0 +1,146 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Generations of a hash file: timestamped copies kept next to it each time
+//! it's created, to tell when files changed without version control.
+
+use std::{
+	collections::BTreeMap,
+	fs,
+	io,
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use super::{ReadOptions, audit::utc_time, read_hashes};
+use crate::Error;
+
+/// A stored copy of a hash file, named by when it was made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generation {
+	/// UTC time it was stored at, like `20250314T092653Z`, with a `-2`,
+	/// `-3`, ... suffix if several were stored the same second.
+	pub id: String,
+	pub path: PathBuf,
+}
+
+/// How an entry differs between two generations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationChange {
+	Added(String),
+	Removed(String),
+	Changed { was: String, new: String },
+}
+
+/// Directory the generations of `hash_file` are kept in, next to it.
+pub fn history_dir(hash_file: &Path) -> PathBuf {
+	let mut dir = hash_file.as_os_str().to_owned();
+	dir.push(".history");
+	PathBuf::from(dir)
+}
+
+/// Generations of `hash_file`, oldest first, none if it has no history.
+pub fn list_generations(hash_file: &Path) -> io::Result<Vec<Generation>> {
+	let entries = match fs::read_dir(history_dir(hash_file)) {
+		Ok(entries) => entries,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => return Err(err),
+	};
+	let mut generations = Vec::new();
+	for entry in entries {
+		let path = entry?.path();
+		if path.extension().is_some_and(|extension| extension == "hash")
+			&& let Some(id) = path.file_stem().and_then(|stem| stem.to_str())
+		{
+			generations.push(Generation { id: id.to_owned(), path });
+		}
+	}
+	generations.sort_by(|a, b| a.id.cmp(&b.id));
+	Ok(generations)
+}
+
+/// Store a copy of `hash_file` as its newest generation.
+pub fn store_generation(hash_file: &Path) -> io::Result<Generation> {
+	let dir = history_dir(hash_file);
+	fs::create_dir_all(&dir)?;
+	let stamp: String = utc_time(SystemTime::now()).chars().filter(|c| !matches!(c, '-' | ':')).collect();
+	let mut id = stamp.clone();
+	for n in 2.. {
+		if !dir.join(format!("{}.hash", id)).exists() {
+			break;
+		}
+		id = format!("{}-{}", stamp, n);
+	}
+	let path = dir.join(format!("{}.hash", id));
+	fs::copy(hash_file, &path)?;
+	Ok(Generation { id, path })
+}
+
+/// Generation named by `id`, or by its number in `generations`, counting
+/// from 1 for the oldest.
+pub fn find_generation<'a>(generations: &'a [Generation], id: &str) -> Option<&'a Generation> {
+	match id.parse::<usize>() {
+		Ok(n) => generations.get(n.checked_sub(1)?),
+		Err(_) => generations.iter().find(|generation| generation.id == id),
+	}
+}
+
+/// Remove all but the newest `keep` generations of `hash_file`, getting the
+/// ones removed.
+pub fn prune_generations(hash_file: &Path, keep: usize) -> io::Result<Vec<Generation>> {
+	let mut generations = list_generations(hash_file)?;
+	generations.truncate(generations.len().saturating_sub(keep));
+	for generation in &generations {
+		fs::remove_file(&generation.path)?;
+	}
+	Ok(generations)
+}
+
+/// Entries that differ between the `old` and `new` generations, by name.
+pub fn diff_generations(old: &Generation, new: &Generation, options: &ReadOptions) -> Result<BTreeMap<PathBuf, GenerationChange>, Error> {
+	let mut old = read_hashes(&old.path, options)?;
+	let mut changes = BTreeMap::new();
+	for (file, hash) in read_hashes(&new.path, options)? {
+		match old.remove(&file) {
+			None => {
+				changes.insert(file, GenerationChange::Added(hash));
+			}
+			Some(was) if was != hash => {
+				changes.insert(file, GenerationChange::Changed { was, new: hash });
+			}
+			Some(_) => {}
+		}
+	}
+	changes.extend(old.into_iter().map(|(file, hash)| (file, GenerationChange::Removed(hash))));
+	Ok(changes)
+}
+
+/// Generations in which the entry of `name` changed, with its hash from
+/// then on, `None` once it's gone.
+pub fn entry_history<'a>(
+	generations: &'a [Generation],
+	name: &Path,
+	options: &ReadOptions,
+) -> Result<Vec<(&'a Generation, Option<String>)>, Error> {
+	let mut history = Vec::new();
+	let mut last = None;
+	for generation in generations {
+		let hash = read_hashes(&generation.path, options)?.remove(name);
+		if hash != last {
+			history.push((generation, hash.clone()));
+			last = hash;
+		}
+	}
+	Ok(history)
+}
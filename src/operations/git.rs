@@ -0,0 +1,58 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! The files git tracks in a checkout, so that manifests of a source tree
+//! leave out untracked build output.
+
+use std::{
+	io,
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+/// Header line of hash files of only the files git tracks, telling verify to
+/// walk only those too.
+pub static GIT_TRACKED_HEADER: &str = "files: tracked by git";
+
+/// The files git tracks under `path`, including those of submodules,
+/// relative to it, as listed by `git ls-files`.
+pub fn git_tracked_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+	let output = Command::new("git").arg("-C").arg(path).args(["ls-files", "-z", "--recurse-submodules"]).output()?;
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(io::Error::other(format!("git ls-files failed ({}): {}", output.status, stderr.trim())));
+	}
+	output
+		.stdout
+		.split(|&b| b == 0)
+		.filter(|name| !name.is_empty())
+		.map(name_path)
+		.collect()
+}
+
+#[cfg(unix)]
+fn name_path(name: &[u8]) -> io::Result<PathBuf> {
+	use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+	Ok(PathBuf::from(OsStr::from_bytes(name)))
+}
+
+/// Names are UTF-8 where they aren't bytes.
+#[cfg(not(unix))]
+fn name_path(name: &[u8]) -> io::Result<PathBuf> {
+	match std::str::from_utf8(name) {
+		Ok(name) => Ok(PathBuf::from(name)),
+		Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("git lists a non-UTF-8 name: {:?}", String::from_utf8_lossy(name)))),
+	}
+}
@@ -0,0 +1,46 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{collections::HashMap, io};
+
+use super::FileRecord;
+
+/// Hashes of files with several hard links, by inode, so that each one is
+/// only read once.
+#[derive(Debug, Default)]
+pub(super) struct HardLinks {
+	hashes: HashMap<(u64, u64), String>,
+}
+
+impl HardLinks {
+	/// Hash of the file, and whether it stayed the same while hashed,
+	/// reusing the hash of an earlier link to the same inode instead of
+	/// calling `hash` again. Failed hashes aren't reused.
+	pub(super) fn hash<F: FnOnce() -> io::Result<(String, bool)>>(
+		&mut self,
+		record: &FileRecord,
+		hash: F,
+	) -> io::Result<(String, bool)> {
+		let Some(inode) = record.hard_link_id() else {
+			return hash();
+		};
+		if let Some(hash) = self.hashes.get(&inode) {
+			return Ok((hash.clone(), true));
+		}
+		let (hash, stable) = hash()?;
+		self.hashes.insert(inode, hash.clone());
+		Ok((hash, stable))
+	}
+}
@@ -0,0 +1,283 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! HTTP API over a set of directories and their hash files, for dashboards:
+//! starting verifications, fetching the hash files, and getting the latest
+//! results as JSON.
+
+use std::{
+	fs,
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{TcpListener, TcpStream, ToSocketAddrs},
+	path::PathBuf,
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicUsize, Ordering},
+	},
+	thread,
+	time::Duration,
+};
+
+use super::{
+	Json, JobOptions,
+	jobs::{Job, JobKind},
+};
+
+/// Most header lines read from a request.
+const MAX_HEADERS: usize = 100;
+
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most requests answered at once; clients connecting past that are told to
+/// come back later.
+const MAX_CONNECTIONS: usize = 64;
+
+/// A directory served, with the hash file it's verified against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServedRoot {
+	/// Name it's found by in URLs.
+	pub name: String,
+	pub path: PathBuf,
+	pub file: PathBuf,
+}
+
+/// Serve the HTTP API on `address` until killed, over `roots`, verified with
+/// `options`. With `token`, requests must carry it as a bearer token.
+///
+/// - `GET /roots`: the roots, each with its latest verification.
+/// - `POST /roots/<name>/verify`: start verifying a root.
+/// - `GET /roots/<name>/status`: how far its latest verification got.
+/// - `GET /roots/<name>/results`: the results of its latest finished one.
+/// - `GET /roots/<name>/manifest`: its hash file.
+///
+/// Only the latest finished verification of each root is kept, besides the
+/// one running.
+pub fn serve_http<A: ToSocketAddrs>(address: A, roots: Vec<ServedRoot>, token: Option<String>, options: JobOptions) -> io::Result<()> {
+	let listener = TcpListener::bind(address)?;
+	let server = Arc::new(Server {
+		roots,
+		token,
+		options,
+		jobs: Mutex::new(Vec::new()),
+		started: AtomicUsize::new(0),
+		connections: AtomicUsize::new(0),
+	});
+	for mut stream in listener.incoming().flatten() {
+		if server.connections.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+			server.connections.fetch_sub(1, Ordering::AcqRel);
+			// A fresh connection has room for the response, so this won't block
+			respond(&mut stream, Response::error(503, "too many connections"));
+			continue;
+		}
+		let server = Arc::clone(&server);
+		thread::spawn(move || {
+			server.converse(stream);
+			server.connections.fetch_sub(1, Ordering::AcqRel);
+		});
+	}
+	Ok(())
+}
+
+struct Server {
+	roots: Vec<ServedRoot>,
+	token: Option<String>,
+	options: JobOptions,
+	/// Verifications kept, oldest first, with the root each is of.
+	jobs: Mutex<Vec<(usize, Arc<Job>)>>,
+	/// Verifications started so far, to number them.
+	started: AtomicUsize,
+	/// Requests being answered.
+	connections: AtomicUsize,
+}
+
+/// A response: its status, content type and body.
+struct Response(u16, &'static str, Vec<u8>);
+
+impl Response {
+	fn json(status: u16, body: Json) -> Response {
+		Response(status, "application/json", format!("{}\n", body).into_bytes())
+	}
+
+	fn error(status: u16, message: &str) -> Response {
+		Response::json(status, Json::object([("error", message.into())]))
+	}
+}
+
+impl Server {
+	/// Answer the one request coming on `stream`.
+	fn converse(&self, stream: TcpStream) {
+		let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+		let Ok(mut out) = stream.try_clone() else {
+			return;
+		};
+		let response = match read_request(&mut BufReader::new(stream)) {
+			Ok((method, target, authorization)) => match &self.token {
+				Some(token) if authorization.as_deref().and_then(|value| value.strip_prefix("Bearer ")) != Some(token) => {
+					Response::error(401, "a bearer token is needed")
+				}
+				_ => self.answer(&method, &target),
+			},
+			Err(err) => Response::error(400, &err.to_string()),
+		};
+		respond(&mut out, response);
+	}
+
+	fn answer(&self, method: &str, target: &str) -> Response {
+		// Queries are ignored
+		let path = target.split('?').next().unwrap_or_default();
+		let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+		match (method, segments.as_slice()) {
+			("GET", [] | ["roots"]) => Response::json(
+				200,
+				Json::Array(
+					(0..self.roots.len())
+						.map(|root| {
+							let mut listing = self.root_json(root);
+							if let Json::Object(members) = &mut listing {
+								members.insert("latest".to_owned(), self.latest(root, false).map_or(Json::Null, |job| job.status()));
+							}
+							listing
+						})
+						.collect(),
+				),
+			),
+			(method, ["roots", name, action]) => {
+				let Some(root) = self.roots.iter().position(|root| root.name == *name) else {
+					return Response::error(404, &format!("no root {:?}", name));
+				};
+				match (method, *action) {
+					("POST", "verify") => self.verify(root),
+					("GET", "status") => match self.latest(root, false) {
+						Some(job) => Response::json(200, job.status()),
+						None => Response::error(404, "not verified yet"),
+					},
+					("GET", "results") => match self.latest(root, true) {
+						Some(job) => {
+							let (key, value) = match job.outcome() {
+								Some(Ok(result)) => ("result", result),
+								Some(Err(reason)) => ("error", reason.into()),
+								None => unreachable!("only finished jobs are looked at"),
+							};
+							Response::json(200, Json::object([("job", job.status()), (key, value)]))
+						}
+						None => Response::error(404, "no verification finished yet"),
+					},
+					("GET", "manifest") => match fs::read(&self.roots[root].file) {
+						Ok(contents) => Response(200, "text/plain; charset=utf-8", contents),
+						Err(err) if err.kind() == io::ErrorKind::NotFound => Response::error(404, "no hash file"),
+						Err(err) => Response::error(500, &err.to_string()),
+					},
+					(_, "verify" | "status" | "results" | "manifest") => Response::error(405, "method not allowed"),
+					_ => Response::error(404, "not found"),
+				}
+			}
+			_ => Response::error(404, "not found"),
+		}
+	}
+
+	/// Start verifying root number `root`, unless it's being verified,
+	/// forgetting its verifications older than the latest.
+	fn verify(&self, root: usize) -> Response {
+		let mut jobs = self.jobs.lock().unwrap();
+		if let Some((_, job)) = jobs.iter().rev().find(|(of, _)| *of == root) {
+			if job.outcome().is_none() {
+				return Response::json(409, Json::object([("error", "already being verified".into()), ("job", job.status())]));
+			}
+			// Its results stay until the new one finishes
+			let latest = Arc::clone(job);
+			jobs.retain(|(of, job)| *of != root || Arc::ptr_eq(job, &latest));
+		}
+		let ServedRoot { path, file, .. } = self.roots[root].clone();
+		let id = self.started.fetch_add(1, Ordering::Relaxed) + 1;
+		let job = Job::start(id, JobKind::Verify, path, file, self.options.algorithm, false, &self.options);
+		let status = job.status();
+		jobs.push((root, job));
+		Response::json(202, status)
+	}
+
+	/// Latest verification of root number `root`, only looking at finished
+	/// ones with `finished`.
+	fn latest(&self, root: usize, finished: bool) -> Option<Arc<Job>> {
+		let jobs = self.jobs.lock().unwrap();
+		jobs.iter()
+			.rev()
+			.find(|(of, job)| *of == root && (!finished || job.outcome().is_some()))
+			.map(|(_, job)| Arc::clone(job))
+	}
+
+	fn root_json(&self, root: usize) -> Json {
+		let ServedRoot { name, path, file } = &self.roots[root];
+		Json::object([
+			("name", name.as_str().into()),
+			("path", path.to_string_lossy().as_ref().into()),
+			("file", file.to_string_lossy().as_ref().into()),
+		])
+	}
+}
+
+/// Write `response` to `out`, closing the connection after it.
+fn respond(out: &mut TcpStream, response: Response) {
+	let Response(status, content_type, body) = response;
+	let head = format!(
+		"HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		status,
+		reason(status),
+		content_type,
+		body.len()
+	);
+	let _ = out.write_all(head.as_bytes()).and_then(|_| out.write_all(&body));
+}
+
+/// Method, target and `Authorization` header of a request, whose body, if
+/// any, is left unread.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<(String, String, Option<String>)> {
+	let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+	let mut line = String::new();
+	reader.by_ref().take(8192).read_line(&mut line)?;
+	let mut parts = line.split_whitespace();
+	let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+		return Err(invalid("malformed request line"));
+	};
+	let (method, target) = (method.to_owned(), target.to_owned());
+	let mut authorization = None;
+	for _ in 0..MAX_HEADERS {
+		line.clear();
+		reader.by_ref().take(8192).read_line(&mut line)?;
+		let header = line.trim_end();
+		if header.is_empty() {
+			return Ok((method, target, authorization));
+		}
+		if let Some((name, value)) = header.split_once(':')
+			&& name.eq_ignore_ascii_case("authorization")
+		{
+			authorization = Some(value.trim().to_owned());
+		}
+	}
+	Err(invalid("too many headers"))
+}
+
+fn reason(status: u16) -> &'static str {
+	match status {
+		200 => "OK",
+		202 => "Accepted",
+		400 => "Bad Request",
+		401 => "Unauthorized",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		409 => "Conflict",
+		503 => "Service Unavailable",
+		_ => "Internal Server Error",
+	}
+}
@@ -0,0 +1,161 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::{Component, Path};
+
+use regex::Regex;
+
+/// A glob pattern of files/directories to skip, following rsync's exclude
+/// rules:
+///
+/// - `*` matches anything but `/`, `**` matches anything, `?` matches one
+///   character but `/`, and `[...]` matches a character class.
+/// - A leading `/` anchors the pattern to the walked directory, otherwise it
+///   matches the end of the path, so `*.tmp` matches at any depth.
+/// - A trailing `/` makes it match only directories.
+#[derive(Debug, Clone)]
+pub struct IgnorePattern {
+	pattern: String,
+	regex: Regex,
+	directories_only: bool,
+}
+
+impl IgnorePattern {
+	pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+		let (glob, directories_only) = match pattern.strip_suffix('/') {
+			Some(glob) => (glob, true),
+			None => (pattern, false),
+		};
+		let regex = match glob.strip_prefix('/') {
+			Some(glob) => format!("^{}$", glob_to_regex(glob)),
+			None => format!("(?:^|/){}$", glob_to_regex(glob)),
+		};
+
+		Ok(IgnorePattern {
+			pattern: pattern.to_owned(),
+			regex: Regex::new(&regex)?,
+			directories_only,
+		})
+	}
+
+	/// Whether the pattern matches `path`, relative to the walked directory.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use std::path::Path;
+	/// # use quickdash::operations::IgnorePattern;
+	/// let tmp = IgnorePattern::new("*.tmp").unwrap();
+	/// assert!(tmp.matches(Path::new("a/b.tmp"), false));
+	/// assert!(!tmp.matches(Path::new("b.tmp/c"), false));
+	///
+	/// let cache = IgnorePattern::new("/cache/").unwrap();
+	/// assert!(cache.matches(Path::new("cache"), true));
+	/// assert!(!cache.matches(Path::new("cache"), false));
+	/// assert!(!cache.matches(Path::new("a/cache"), true));
+	///
+	/// let logs = IgnorePattern::new("logs/**/*.gz").unwrap();
+	/// assert!(logs.matches(Path::new("x/logs/2024/01/a.gz"), false));
+	/// ```
+	pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+		if self.directories_only && !is_dir {
+			return false;
+		}
+		let path = path
+			.components()
+			.filter_map(|c| match c {
+				Component::Normal(name) => Some(name.to_string_lossy()),
+				_ => None,
+			})
+			.collect::<Vec<_>>()
+			.join("/");
+		self.regex.is_match(&path)
+	}
+}
+
+impl PartialEq for IgnorePattern {
+	fn eq(&self, other: &Self) -> bool {
+		self.pattern == other.pattern
+	}
+}
+
+impl Eq for IgnorePattern {}
+
+/// Parse an exclude file of one pattern per line, like rsync's
+/// `--exclude-from`.
+///
+/// Empty lines and lines starting with `#` or `;` are skipped, and a leading
+/// `- ` is allowed.
+pub fn parse_ignore_patterns(text: &str) -> Result<Vec<IgnorePattern>, String> {
+	text.lines()
+		.map(|line| line.trim_end())
+		.filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+		.map(|line| {
+			if line.starts_with("+ ") {
+				return Err(format!("Include rules aren't supported: {:?}", line));
+			}
+			let pattern = line.strip_prefix("- ").unwrap_or(line);
+			IgnorePattern::new(pattern).map_err(|err| format!("Invalid pattern {:?}: {}", line, err))
+		})
+		.collect()
+}
+
+fn glob_to_regex(glob: &str) -> String {
+	let mut regex = String::new();
+	let mut chars = glob.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'*' if chars.peek() == Some(&'*') => {
+				chars.next();
+				// `**/` also matches no directories at all
+				if chars.next_if_eq(&'/').is_some() {
+					regex.push_str("(?:.*/)?");
+				} else {
+					regex.push_str(".*");
+				}
+			}
+			'*' => regex.push_str("[^/]*"),
+			'?' => regex.push_str("[^/]"),
+			'[' => {
+				regex.push('[');
+				if chars.next_if(|c| matches!(c, '!' | '^')).is_some() {
+					regex.push('^');
+				}
+				// A `]` right after the opening bracket is part of the class
+				if chars.next_if_eq(&']').is_some() {
+					regex.push_str(r"\]");
+				}
+				for c in chars.by_ref() {
+					match c {
+						']' => break,
+						'\\' | '[' | '&' | '~' => {
+							regex.push('\\');
+							regex.push(c);
+						}
+						c => regex.push(c),
+					}
+				}
+				regex.push(']');
+			}
+			'\\' => {
+				if let Some(c) = chars.next() {
+					regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+				}
+			}
+			c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+		}
+	}
+	regex
+}
@@ -0,0 +1,156 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! in-toto statements of the hashed files, as SLSA provenance, for build
+//! pipelines to publish as supply-chain attestations, optionally signed into
+//! a DSSE envelope.
+
+use std::{
+	collections::BTreeMap,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use super::{PathStyle, oci_name};
+use crate::{Algorithm, utilities::run_shell};
+
+static STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+static PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+
+/// Media type of statements in DSSE envelopes.
+static PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Builder and build type of the provenance.
+static BUILDER_ID: &str = "https://github.com/iamtakingithard/QuickDash";
+
+static BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Write an in-toto statement to `out` with a subject for each hashed file,
+/// named with `/` separators, and a SLSA provenance predicate naming the
+/// hashed directory. Files with a note, like symlinks and files that
+/// couldn't be read, are left out.
+///
+/// With `sign_with`, the statement is signed by running it through the
+/// shell, with the DSSE pre-authentication encoding of the statement on its
+/// standard input, and a DSSE envelope of the statement and the raw
+/// signature it prints is written instead.
+pub fn write_in_toto(
+	base: &Path,
+	hashes: &BTreeMap<PathBuf, String>,
+	notes: &BTreeMap<PathBuf, String>,
+	algo: Algorithm,
+	out: &Path,
+	sign_with: Option<&str>,
+) -> io::Result<()> {
+	let subjects: Vec<String> = hashes
+		.iter()
+		.filter(|(file, hash)| !notes.contains_key(*file) && !hash.starts_with('-'))
+		.map(|(file, hash)| {
+			format!(
+				"{{\"name\":{},\"digest\":{{\"{}\":\"{}\"}}}}",
+				json_string(&PathStyle::Unix.format(file)),
+				digest_name(algo),
+				hash.to_lowercase()
+			)
+		})
+		.collect();
+	let statement = format!(
+		"{{\"_type\":\"{}\",\"subject\":[{}],\"predicateType\":\"{}\",\"predicate\":{{\
+		\"buildDefinition\":{{\"buildType\":\"{}/create@v1\",\"externalParameters\":{{\"path\":{},\"algorithm\":\"{}\"}}}},\
+		\"runDetails\":{{\"builder\":{{\"id\":\"{}\",\"version\":{{\"quickdash\":\"{}\"}}}}}}}}}}",
+		STATEMENT_TYPE,
+		subjects.join(","),
+		PREDICATE_TYPE,
+		BUILDER_ID,
+		json_string(&base.to_string_lossy()),
+		digest_name(algo),
+		BUILDER_ID,
+		env!("CARGO_PKG_VERSION")
+	);
+	let contents = match sign_with {
+		Some(command) => {
+			let signature = sign(command, &pae(PAYLOAD_TYPE, statement.as_bytes()))?;
+			format!(
+				"{{\"payloadType\":\"{}\",\"payload\":\"{}\",\"signatures\":[{{\"sig\":\"{}\"}}]}}",
+				PAYLOAD_TYPE,
+				base64_encode(statement.as_bytes()),
+				base64_encode(&signature)
+			)
+		}
+		None => statement,
+	};
+	fs::write(out, contents + "\n")
+}
+
+/// Name of `algo` in in-toto digest sets.
+fn digest_name(algo: Algorithm) -> &'static str {
+	match algo {
+		Algorithm::SHA3224 => "sha3_224",
+		Algorithm::SHA3256 => "sha3_256",
+		Algorithm::SHA3384 => "sha3_384",
+		Algorithm::SHA3512 => "sha3_512",
+		algo => oci_name(algo),
+	}
+}
+
+/// DSSE pre-authentication encoding of a payload, what's actually signed.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+	let mut encoded = format!("DSSEv1 {} {} {} ", payload_type.len(), payload_type, payload.len()).into_bytes();
+	encoded.extend_from_slice(payload);
+	encoded
+}
+
+/// Run `command` through the shell with `message` on its standard input,
+/// and get what it prints, failing if it exits unsuccessfully.
+fn sign(command: &str, message: &[u8]) -> io::Result<Vec<u8>> {
+	let output = run_shell(command, message.to_vec(), true)?;
+	match output.status.success() && !output.stdout.is_empty() {
+		true => Ok(output.stdout),
+		false => Err(io::Error::other(format!("{:?} didn't sign the statement ({})", command, output.status))),
+	}
+}
+
+/// `text` as a JSON string, quoted.
+pub(super) fn json_string(text: &str) -> String {
+	let mut quoted = String::with_capacity(text.len() + 2);
+	quoted.push('"');
+	for c in text.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\t' => quoted.push_str("\\t"),
+			c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+/// Standard base64, with padding.
+pub(super) fn base64_encode(bytes: &[u8]) -> String {
+	let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	for chunk in bytes.chunks(3) {
+		let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (i, &byte)| buffer | u32::from(byte) << (16 - 8 * i));
+		for i in 0..4 {
+			match i <= chunk.len() {
+				true => text.push(char::from(BASE64_ALPHABET[(buffer >> (18 - 6 * i) & 63) as usize])),
+				false => text.push('='),
+			}
+		}
+	}
+	text
+}
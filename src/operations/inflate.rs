@@ -0,0 +1,276 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Decoder of DEFLATE streams (RFC 1951), the compression of most zip
+//! members, read as it decodes.
+
+use std::{
+	io::{self, Read},
+	mem,
+};
+
+/// Bytes of history back-references may reach into.
+const WINDOW_LEN: usize = 32768;
+
+/// Bytes decoded at a time.
+const CHUNK_LEN: usize = 32768;
+
+/// Base lengths and extra bits of length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Base distances and extra bits of distance codes 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+	1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order code length code lengths come in, in dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn invalid(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("invalid deflate stream: {}", message))
+}
+
+/// Canonical Huffman code, as the amount of codes of each length and the
+/// symbols by code.
+struct Huffman {
+	counts: [u16; 16],
+	symbols: Vec<u16>,
+}
+
+impl Huffman {
+	/// Code of symbols with the given code lengths, 0 for unused ones.
+	fn new(lengths: &[u8]) -> io::Result<Huffman> {
+		let mut counts = [0u16; 16];
+		for &length in lengths {
+			counts[usize::from(length)] += 1;
+		}
+		counts[0] = 0;
+		let mut left = 1i32;
+		for &count in &counts[1..] {
+			left = (left << 1) - i32::from(count);
+			if left < 0 {
+				return Err(invalid("over-subscribed code"));
+			}
+		}
+		let mut offsets = [0u16; 16];
+		for length in 1..15 {
+			offsets[length + 1] = offsets[length] + counts[length];
+		}
+		let mut symbols = vec![0; lengths.len()];
+		for (symbol, &length) in lengths.iter().enumerate() {
+			if length != 0 {
+				symbols[usize::from(offsets[usize::from(length)])] = symbol as u16;
+				offsets[usize::from(length)] += 1;
+			}
+		}
+		Ok(Huffman { counts, symbols })
+	}
+}
+
+/// Where decoding is between blocks and symbols.
+enum State {
+	/// At a block header.
+	Header,
+	/// In a stored block, with this many bytes left.
+	Stored(usize),
+	/// In a compressed block, with its literal/length and distance codes.
+	Codes(Box<(Huffman, Huffman)>),
+	Done,
+}
+
+/// Reader of what the DEFLATE stream read from `input` decodes to.
+pub(super) struct Inflate<R> {
+	input: R,
+	/// Bits read but not used yet, lowest first.
+	bits: u32,
+	bit_count: u32,
+	/// The last `WINDOW_LEN` bytes decoded, written at `written` modulo it.
+	window: Vec<u8>,
+	written: usize,
+	state: State,
+	last_block: bool,
+	/// Bytes decoded and not read yet, from `start`.
+	decoded: Vec<u8>,
+	start: usize,
+}
+
+impl<R: Read> Inflate<R> {
+	pub(super) fn new(input: R) -> Inflate<R> {
+		Inflate {
+			input,
+			bits: 0,
+			bit_count: 0,
+			window: vec![0; WINDOW_LEN],
+			written: 0,
+			state: State::Header,
+			last_block: false,
+			decoded: Vec::with_capacity(CHUNK_LEN + 258),
+			start: 0,
+		}
+	}
+
+	fn byte(&mut self) -> io::Result<u8> {
+		let mut byte = [0];
+		match self.input.read(&mut byte)? {
+			0 => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "deflate stream cut short")),
+			_ => Ok(byte[0]),
+		}
+	}
+
+	fn take_bits(&mut self, count: u32) -> io::Result<u32> {
+		while self.bit_count < count {
+			self.bits |= u32::from(self.byte()?) << self.bit_count;
+			self.bit_count += 8;
+		}
+		let value = self.bits & ((1u32 << count) - 1);
+		self.bits >>= count;
+		self.bit_count -= count;
+		Ok(value)
+	}
+
+	fn symbol(&mut self, code: &Huffman) -> io::Result<u16> {
+		let (mut bits, mut first, mut index) = (0i32, 0i32, 0i32);
+		for &count in &code.counts[1..] {
+			bits |= self.take_bits(1)? as i32;
+			let count = i32::from(count);
+			if bits - count < first {
+				return Ok(code.symbols[(index + bits - first) as usize]);
+			}
+			index += count;
+			first = (first + count) << 1;
+			bits <<= 1;
+		}
+		Err(invalid("unused code"))
+	}
+
+	fn emit(&mut self, byte: u8) {
+		self.decoded.push(byte);
+		self.window[self.written % WINDOW_LEN] = byte;
+		self.written += 1;
+	}
+
+	/// Codes of a dynamic block, read from its header.
+	fn dynamic_codes(&mut self) -> io::Result<(Huffman, Huffman)> {
+		let literals = self.take_bits(5)? as usize + 257;
+		let distances = self.take_bits(5)? as usize + 1;
+		let code_lengths = self.take_bits(4)? as usize + 4;
+		let mut lengths = [0u8; 19];
+		for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+			lengths[symbol] = self.take_bits(3)? as u8;
+		}
+		let code = Huffman::new(&lengths)?;
+		let mut lengths = Vec::with_capacity(literals + distances);
+		while lengths.len() < literals + distances {
+			let (length, repeat) = match self.symbol(&code)? {
+				length @ 0..16 => (length as u8, 1),
+				16 => (*lengths.last().ok_or_else(|| invalid("repeat with no length before"))?, 3 + self.take_bits(2)?),
+				17 => (0, 3 + self.take_bits(3)?),
+				_ => (0, 11 + self.take_bits(7)?),
+			};
+			lengths.extend((0..repeat).map(|_| length));
+		}
+		if lengths.len() > literals + distances {
+			return Err(invalid("too many code lengths"));
+		}
+		if lengths[256] == 0 {
+			return Err(invalid("no end of block code"));
+		}
+		Ok((Huffman::new(&lengths[..literals])?, Huffman::new(&lengths[literals..])?))
+	}
+
+	/// Decode up to about `CHUNK_LEN` bytes into `decoded`.
+	fn decode(&mut self) -> io::Result<()> {
+		while self.decoded.len() < CHUNK_LEN {
+			match mem::replace(&mut self.state, State::Done) {
+				State::Done => return Ok(()),
+				State::Header if self.last_block => return Ok(()),
+				State::Header => {
+					self.last_block = self.take_bits(1)? == 1;
+					self.state = match self.take_bits(2)? {
+						0 => {
+							// Stored blocks start at a byte boundary
+							(self.bits, self.bit_count) = (0, 0);
+							let header = [self.byte()?, self.byte()?, self.byte()?, self.byte()?];
+							let (len, complement) = (u16::from_le_bytes([header[0], header[1]]), u16::from_le_bytes([header[2], header[3]]));
+							if len != !complement {
+								return Err(invalid("stored block length doesn't match its complement"));
+							}
+							State::Stored(usize::from(len))
+						}
+						1 => {
+							let mut lengths = [8u8; 288];
+							lengths[144..256].fill(9);
+							lengths[256..280].fill(7);
+							State::Codes(Box::new((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?)))
+						}
+						2 => State::Codes(Box::new(self.dynamic_codes()?)),
+						_ => return Err(invalid("reserved block type")),
+					};
+				}
+				State::Stored(0) => self.state = State::Header,
+				State::Stored(left) => {
+					let byte = self.byte()?;
+					self.emit(byte);
+					self.state = State::Stored(left - 1);
+				}
+				State::Codes(codes) => {
+					let symbol = self.symbol(&codes.0)?;
+					match symbol {
+						0..256 => self.emit(symbol as u8),
+						256 => {
+							self.state = State::Header;
+							continue;
+						}
+						_ => {
+							let code = usize::from(symbol - 257);
+							let Some(&base) = LENGTH_BASE.get(code) else {
+								return Err(invalid("invalid length code"));
+							};
+							let length = usize::from(base) + self.take_bits(u32::from(LENGTH_EXTRA[code]))? as usize;
+							let code = usize::from(self.symbol(&codes.1)?);
+							let Some(&base) = DISTANCE_BASE.get(code) else {
+								return Err(invalid("invalid distance code"));
+							};
+							let distance = usize::from(base) + self.take_bits(u32::from(DISTANCE_EXTRA[code]))? as usize;
+							if distance > self.written {
+								return Err(invalid("distance too far back"));
+							}
+							for _ in 0..length {
+								self.emit(self.window[(self.written - distance) % WINDOW_LEN]);
+							}
+						}
+					}
+					self.state = State::Codes(codes);
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+impl<R: Read> Read for Inflate<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.start == self.decoded.len() {
+			self.decoded.clear();
+			self.start = 0;
+			self.decode()?;
+		}
+		let len = buf.len().min(self.decoded.len() - self.start);
+		buf[..len].copy_from_slice(&self.decoded[self.start..self.start + len]);
+		self.start += len;
+		Ok(len)
+	}
+}
@@ -0,0 +1,160 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Central inventory of the files of many directories on many hosts, kept in
+//! a SQL database, one run at a time, with the status and hash of each file
+//! in each run.
+//!
+//! Runs are written as SQL that SQLite and PostgreSQL both take, piped to
+//! their command line clients, so neither needs linking in.
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	env, fs,
+	hash::{BuildHasher, RandomState},
+	io,
+	path::{self, Path, PathBuf},
+	time::SystemTime,
+};
+
+use super::{CompareFileResult, CompareResult, PathStyle, audit::utc_time, oci_name};
+use crate::{Algorithm, utilities::run_shell};
+
+/// Tables of the inventory, created if missing.
+static SCHEMA: &str = "CREATE TABLE IF NOT EXISTS runs (\
+	id TEXT PRIMARY KEY, host TEXT NOT NULL, root TEXT NOT NULL, manifest TEXT NOT NULL, \
+	command TEXT NOT NULL, algorithm TEXT NOT NULL, time TEXT NOT NULL);\n\
+	CREATE TABLE IF NOT EXISTS files (\
+	run TEXT NOT NULL REFERENCES runs (id), name TEXT NOT NULL, hash TEXT, status TEXT NOT NULL, \
+	PRIMARY KEY (run, name));\n";
+
+/// A run of `create` or `verify` on one directory, as noted in the
+/// inventory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryRun<'a> {
+	/// Name of the host the directory is on.
+	pub host: &'a str,
+	/// The directory, noted as an absolute path.
+	pub root: &'a Path,
+	/// Its hash file, noted as an absolute path.
+	pub manifest: &'a Path,
+	/// `create` or `verify`.
+	pub command: &'a str,
+	pub algorithm: Algorithm,
+}
+
+/// Name of this host, for telling the same directories on different hosts
+/// apart. `localhost` if it can't be told.
+pub fn host_name() -> String {
+	env::var("COMPUTERNAME")
+		.or_else(|_| env::var("HOSTNAME"))
+		.ok()
+		.or_else(|| fs::read_to_string("/etc/hostname").ok())
+		.map(|name| name.trim().to_owned())
+		.filter(|name| !name.is_empty())
+		.unwrap_or_else(|| "localhost".to_owned())
+}
+
+/// SQL noting `run` in the inventory, in one transaction, with a row for
+/// each file it hashed or compared.
+///
+/// Created files are `created`. Verified ones are `matches`, `assumed`,
+/// `differs`, `changed` when their size or modification time differ,
+/// `retargeted` symlinks, `metadata changed`, `added`, `removed` or
+/// `ignored`. Files without a hash, like removed ones or those that couldn't
+/// be read, have a NULL one.
+pub fn inventory_sql(
+	run: &InventoryRun,
+	hashes: &BTreeMap<PathBuf, String>,
+	outcome: Option<(&[CompareResult], &[CompareFileResult])>,
+) -> String {
+	let time = utc_time(SystemTime::now());
+	let id = format!("{}-{}-{:016x}", run.host, time, RandomState::new().hash_one(run.root));
+
+	let mut statuses: BTreeMap<&Path, &str> = BTreeMap::new();
+	if let Some((compare_results, file_compare_results)) = outcome {
+		for fres in file_compare_results {
+			let (file, status) = match fres {
+				CompareFileResult::FileMatches(file) => (file, "matches"),
+				CompareFileResult::FileAssumedOk(file) => (file, "assumed"),
+				CompareFileResult::FileDrifted { file, matches: Some(true), .. } => (file, "matches"),
+				CompareFileResult::FileDrifted { file, .. } => (file, "changed"),
+				CompareFileResult::FileDiffers { file, .. }
+				| CompareFileResult::FilePiecesDiffer { file, .. }
+				| CompareFileResult::PiecesFail { file, .. } => (file, "differs"),
+				CompareFileResult::SymlinkRetargeted { file, .. } => (file, "retargeted"),
+			};
+			statuses.insert(file, status);
+		}
+		// Files with changed metadata are compared too, but that's what's
+		// wrong with them
+		for res in compare_results {
+			let (file, status) = match res {
+				CompareResult::FileAdded(file) => (file, "added"),
+				CompareResult::FileRemoved(file) => (file, "removed"),
+				CompareResult::FileIgnored(file) => (file, "ignored"),
+				CompareResult::MetadataChanged { file, .. } => (file, "metadata changed"),
+				CompareResult::AttributesChanged { file, .. } => (file, "attributes changed"),
+			};
+			statuses.insert(file, status);
+		}
+	}
+	let absolute = |path: &Path| path::absolute(path).unwrap_or_else(|_| path.to_owned()).to_string_lossy().into_owned();
+	let names: BTreeSet<&Path> = hashes.keys().map(PathBuf::as_path).chain(statuses.keys().copied()).collect();
+
+	let mut sql = String::from(SCHEMA);
+	sql.push_str("BEGIN;\n");
+	sql.push_str(&format!(
+		"INSERT INTO runs (id, host, root, manifest, command, algorithm, time) VALUES ({}, {}, {}, {}, {}, {}, {});\n",
+		sql_string(&id),
+		sql_string(run.host),
+		sql_string(&absolute(run.root)),
+		sql_string(&absolute(run.manifest)),
+		sql_string(run.command),
+		sql_string(oci_name(run.algorithm)),
+		sql_string(&time)
+	));
+	for name in names {
+		let hash = match hashes.get(name) {
+			Some(hash) if !hash.starts_with('-') => sql_string(hash),
+			_ => "NULL".to_owned(),
+		};
+		let status = statuses.get(name).copied().unwrap_or("created");
+		sql.push_str(&format!(
+			"INSERT INTO files (run, name, hash, status) VALUES ({}, {}, {}, {});\n",
+			sql_string(&id),
+			sql_string(&PathStyle::Unix.format(name)),
+			hash,
+			sql_string(status)
+		));
+	}
+	sql.push_str("COMMIT;\n");
+	sql
+}
+
+/// Run `command` through the shell with `sql` on its standard input, like
+/// `sqlite3 inventory.db` or `psql inventory`, failing if it exits
+/// unsuccessfully.
+pub fn write_inventory(command: &str, sql: &str) -> io::Result<()> {
+	let status = run_shell(command, sql.as_bytes().to_vec(), false)?.status;
+	match status.success() {
+		true => Ok(()),
+		false => Err(io::Error::other(format!("{:?} failed ({})", command, status))),
+	}
+}
+
+/// `text` as a SQL string literal.
+pub(super) fn sql_string(text: &str) -> String {
+	format!("'{}'", text.replace('\'', "''"))
+}
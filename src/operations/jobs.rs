@@ -0,0 +1,227 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Jobs creating or verifying hash files in the background, as taken by
+//! the daemon and the HTTP API, with their progress and results as JSON.
+
+use std::{
+	collections::BTreeMap,
+	io,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	thread,
+};
+
+use super::{
+	ERROR_NOTE_PREFIX, Json, LiveVerification, Progress, ReadOptions, Verdict, WalkOptions, WriteOptions, placeholder_hash,
+	read_hashes, read_named_algorithm, write_hash_comparison_results, write_hashes,
+};
+use crate::{Algorithm, Error};
+
+/// Settings jobs are run with, besides those given with each.
+#[derive(Debug, Clone, Default)]
+pub struct JobOptions {
+	/// Algorithm of created hash files, and of verified ones that don't name
+	/// theirs.
+	pub algorithm: Algorithm,
+	pub walk_options: WalkOptions,
+	pub read_options: ReadOptions,
+	pub write_options: WriteOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum JobKind {
+	Create,
+	Verify,
+}
+
+pub(super) struct Job {
+	pub(super) id: usize,
+	pub(super) kind: JobKind,
+	pub(super) path: PathBuf,
+	pub(super) file: PathBuf,
+	algorithm: Algorithm,
+	/// Overwrite the hash file, when creating it.
+	force: bool,
+	/// The hashing under way, if still running.
+	live: Mutex<Option<Arc<LiveVerification>>>,
+	/// How far it got, once it's no longer running.
+	progress: Mutex<Option<Progress>>,
+	/// The result, or why it failed, once done.
+	outcome: Mutex<Option<Result<Json, String>>>,
+}
+
+/// Hash file of the directory `path` when not told, named after it the way
+/// `create` names it.
+pub(super) fn default_hash_file(path: &Path) -> Option<PathBuf> {
+	Some(path.join(path.file_stem()?).with_extension("hash"))
+}
+
+impl Job {
+	/// Start job number `id`, of `kind`, on the files under `path` and the
+	/// hash file `file`.
+	pub(super) fn start(
+		id: usize,
+		kind: JobKind,
+		path: PathBuf,
+		file: PathBuf,
+		algorithm: Algorithm,
+		force: bool,
+		options: &JobOptions,
+	) -> Arc<Job> {
+		let job = Arc::new(Job {
+			id,
+			kind,
+			path,
+			file,
+			algorithm,
+			force,
+			live: Mutex::new(None),
+			progress: Mutex::new(None),
+			outcome: Mutex::new(None),
+		});
+		let (started, options) = (Arc::clone(&job), options.clone());
+		thread::spawn(move || {
+			let outcome = started.run(&options);
+			*started.outcome.lock().unwrap() = Some(outcome);
+		});
+		job
+	}
+
+	/// Where the job stands, and how far it got.
+	pub(super) fn status(&self) -> Json {
+		let progress = match &*self.live.lock().unwrap() {
+			Some(live) => Some(live.progress()),
+			None => *self.progress.lock().unwrap(),
+		};
+		let state = match &*self.outcome.lock().unwrap() {
+			None => "running",
+			Some(Ok(_)) => "done",
+			Some(Err(_)) => "failed",
+		};
+		let (hashed, files, read) = progress.map_or((0, 0, 0), |progress| (progress.hashed, progress.files, progress.read));
+		Json::object([
+			("job", self.id.into()),
+			("kind", self.kind.name().into()),
+			("path", self.path.to_string_lossy().as_ref().into()),
+			("file", self.file.to_string_lossy().as_ref().into()),
+			("state", state.into()),
+			("hashed", hashed.into()),
+			("files", files.into()),
+			("read", read.into()),
+		])
+	}
+
+	/// The result, or why it failed, once done.
+	pub(super) fn outcome(&self) -> Option<Result<Json, String>> {
+		self.outcome.lock().unwrap().clone()
+	}
+
+	/// Do the job, returning its result or why it failed.
+	fn run(&self, options: &JobOptions) -> Result<Json, String> {
+		if self.kind == JobKind::Create && !self.force && self.file.exists() {
+			return Err(format!("{:?} exists, pass force to overwrite it", self.file));
+		}
+		let (loaded, algorithm) = match self.kind {
+			JobKind::Create => (BTreeMap::new(), self.algorithm),
+			JobKind::Verify => {
+				let loaded = read_hashes(&self.file, &options.read_options).map_err(describe)?;
+				let algorithm = match self.algorithm {
+					// Multihashes and CIDs name the algorithm they were made with
+					Algorithm::UNSPECIFIED => {
+						read_named_algorithm(&self.file, &options.read_options).map_err(describe)?.unwrap_or_default()
+					}
+					algorithm => algorithm,
+				};
+				(loaded, algorithm)
+			}
+		};
+		let mut walk_options = options.walk_options.clone();
+		walk_options.ignore_file(&self.path, &self.file);
+		let live = Arc::new(LiveVerification::start_against(&self.path, loaded, algorithm, &walk_options));
+		*self.live.lock().unwrap() = Some(Arc::clone(&live));
+		live.wait();
+		let verdicts = live.verdicts();
+		let warnings = live.warnings();
+		let result = match self.kind {
+			JobKind::Create => {
+				let mut write_options = WriteOptions { algorithm, ..options.write_options.clone() };
+				let mut hashes = BTreeMap::new();
+				for (file, verdict) in verdicts {
+					match verdict {
+						Verdict::Added(hash) => {
+							hashes.insert(file, hash);
+						}
+						Verdict::Unreadable(err) => {
+							write_options.notes.insert(file.clone(), format!("{}{}", ERROR_NOTE_PREFIX, err));
+							hashes.insert(file, placeholder_hash(algorithm));
+						}
+						_ => {}
+					}
+				}
+				let files = hashes.len();
+				match write_hashes(&self.file, hashes, &write_options) {
+					0 => Ok(Json::object([
+						("file", self.file.to_string_lossy().as_ref().into()),
+						("files", files.into()),
+						("warnings", warnings.into()),
+					])),
+					_ => Err(format!("failed to write {:?}", self.file)),
+				}
+			}
+			JobKind::Verify => {
+				let mut lists: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+				let mut matched = 0;
+				for (file, verdict) in &verdicts {
+					match verdict {
+						Verdict::Matches => matched += 1,
+						verdict => lists.entry(verdict.label()).or_default().push(file.to_string_lossy().into_owned()),
+					}
+				}
+				let exit = write_hash_comparison_results(&mut io::sink(), &mut io::sink(), live.outcome(), &warnings).exit_value();
+				let mut result = Json::object([
+					("matched", matched.into()),
+					("exit", exit.into()),
+					("warnings", warnings.into()),
+				]);
+				if let Json::Object(members) = &mut result {
+					for label in ["differs", "missing", "added", "unreadable"] {
+						members.insert(label.to_owned(), lists.remove(label).unwrap_or_default().into());
+					}
+				}
+				Ok(result)
+			}
+		};
+		*self.progress.lock().unwrap() = Some(live.progress());
+		*self.live.lock().unwrap() = None;
+		result
+	}
+}
+
+impl JobKind {
+	pub(super) fn name(self) -> &'static str {
+		match self {
+			JobKind::Create => "create",
+			JobKind::Verify => "verify",
+		}
+	}
+}
+
+/// Why reading a hash file failed.
+fn describe(err: Error) -> String {
+	match err {
+		Error::HashesFileParsingFailure(reason) => reason,
+		err => format!("{:?}", err),
+	}
+}
@@ -0,0 +1,268 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! JSON values, as read and written by the daemon's API.
+
+use std::{collections::BTreeMap, fmt, iter::Peekable, str::Chars, str::FromStr};
+
+use super::in_toto::json_string;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Json>),
+	Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+	/// Object of the given members.
+	pub fn object<const N: usize>(members: [(&str, Json); N]) -> Json {
+		Json::Object(members.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+	}
+
+	/// Member `key` of an object.
+	pub fn get(&self, key: &str) -> Option<&Json> {
+		match self {
+			Json::Object(members) => members.get(key),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Json::String(text) => Some(text),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Json::Bool(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	/// The number, if it's a whole one that fits.
+	pub fn as_u64(&self) -> Option<u64> {
+		match self {
+			Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= u64::MAX as f64 => Some(*n as u64),
+			_ => None,
+		}
+	}
+}
+
+impl From<&str> for Json {
+	fn from(text: &str) -> Self {
+		Json::String(text.to_owned())
+	}
+}
+
+impl From<String> for Json {
+	fn from(text: String) -> Self {
+		Json::String(text)
+	}
+}
+
+impl From<bool> for Json {
+	fn from(value: bool) -> Self {
+		Json::Bool(value)
+	}
+}
+
+impl From<u64> for Json {
+	fn from(n: u64) -> Self {
+		Json::Number(n as f64)
+	}
+}
+
+impl From<usize> for Json {
+	fn from(n: usize) -> Self {
+		Json::Number(n as f64)
+	}
+}
+
+impl From<i32> for Json {
+	fn from(n: i32) -> Self {
+		Json::Number(f64::from(n))
+	}
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+	fn from(values: Vec<T>) -> Self {
+		Json::Array(values.into_iter().map(Into::into).collect())
+	}
+}
+
+/// Written compactly, on one line.
+impl fmt::Display for Json {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Json::Null => f.write_str("null"),
+			Json::Bool(value) => write!(f, "{}", value),
+			// Whole numbers are written without a fraction
+			Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+			Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+			Json::Number(_) => f.write_str("null"),
+			Json::String(text) => f.write_str(&json_string(text)),
+			Json::Array(values) => {
+				f.write_str("[")?;
+				for (i, value) in values.iter().enumerate() {
+					if i > 0 {
+						f.write_str(",")?;
+					}
+					write!(f, "{}", value)?;
+				}
+				f.write_str("]")
+			}
+			Json::Object(members) => {
+				f.write_str("{")?;
+				for (i, (key, value)) in members.iter().enumerate() {
+					if i > 0 {
+						f.write_str(",")?;
+					}
+					write!(f, "{}:{}", json_string(key), value)?;
+				}
+				f.write_str("}")
+			}
+		}
+	}
+}
+
+impl FromStr for Json {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut chars = s.chars().peekable();
+		let value = parse_value(&mut chars)?;
+		skip_whitespace(&mut chars);
+		match chars.next() {
+			None => Ok(value),
+			Some(c) => Err(format!("unexpected {:?} after the value", c)),
+		}
+	}
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+	while chars.next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r')).is_some() {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+	match word.chars().all(|c| chars.next() == Some(c)) {
+		true => Ok(()),
+		false => Err(format!("expected {:?}", word)),
+	}
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+	skip_whitespace(chars);
+	match chars.peek() {
+		Some('n') => expect(chars, "null").map(|_| Json::Null),
+		Some('t') => expect(chars, "true").map(|_| Json::Bool(true)),
+		Some('f') => expect(chars, "false").map(|_| Json::Bool(false)),
+		Some('"') => parse_string(chars).map(Json::String),
+		Some('[') => {
+			chars.next();
+			let mut values = Vec::new();
+			skip_whitespace(chars);
+			if chars.next_if_eq(&']').is_some() {
+				return Ok(Json::Array(values));
+			}
+			loop {
+				values.push(parse_value(chars)?);
+				skip_whitespace(chars);
+				match chars.next() {
+					Some(',') => {}
+					Some(']') => return Ok(Json::Array(values)),
+					_ => return Err("expected ',' or ']' in an array".to_owned()),
+				}
+			}
+		}
+		Some('{') => {
+			chars.next();
+			let mut members = BTreeMap::new();
+			skip_whitespace(chars);
+			if chars.next_if_eq(&'}').is_some() {
+				return Ok(Json::Object(members));
+			}
+			loop {
+				skip_whitespace(chars);
+				let key = parse_string(chars)?;
+				skip_whitespace(chars);
+				expect(chars, ":")?;
+				members.insert(key, parse_value(chars)?);
+				skip_whitespace(chars);
+				match chars.next() {
+					Some(',') => {}
+					Some('}') => return Ok(Json::Object(members)),
+					_ => return Err("expected ',' or '}' in an object".to_owned()),
+				}
+			}
+		}
+		Some(c) if *c == '-' || c.is_ascii_digit() => {
+			let mut number = String::new();
+			while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+				number.push(c);
+			}
+			number.parse().map(Json::Number).map_err(|_| format!("invalid number {:?}", number))
+		}
+		Some(c) => Err(format!("unexpected {:?}", c)),
+		None => Err("unexpected end".to_owned()),
+	}
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+	expect(chars, "\"")?;
+	let mut text = String::new();
+	loop {
+		match chars.next().ok_or("unterminated string")? {
+			'"' => return Ok(text),
+			'\\' => match chars.next().ok_or("unterminated string")? {
+				'"' => text.push('"'),
+				'\\' => text.push('\\'),
+				'/' => text.push('/'),
+				'b' => text.push('\u{8}'),
+				'f' => text.push('\u{c}'),
+				'n' => text.push('\n'),
+				'r' => text.push('\r'),
+				't' => text.push('\t'),
+				'u' => {
+					let unit = parse_hex4(chars)?;
+					// Characters beyond the BMP come as surrogate pairs
+					let c = match unit {
+						0xd800..0xdc00 => {
+							expect(chars, "\\u")?;
+							let low = parse_hex4(chars)?;
+							char::from_u32(0x10000 + ((unit - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff))
+						}
+						unit => char::from_u32(unit),
+					};
+					text.push(c.ok_or("invalid \\u escape")?);
+				}
+				c => return Err(format!("invalid escape \\{}", c)),
+			},
+			c => text.push(c),
+		}
+	}
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+	let hex: String = chars.take(4).collect();
+	match hex.len() == 4 {
+		true => u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid \\u escape {:?}", hex)),
+		false => Err("unterminated \\u escape".to_owned()),
+	}
+}
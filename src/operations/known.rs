@@ -0,0 +1,152 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Sets of known hashes, like the NSRL Reference Data Set, to triage files
+//! by: known good ones can be passed over, known bad ones are flagged.
+
+use std::{
+	collections::{BTreeMap, HashSet},
+	fs::File,
+	io::{self, BufRead, BufReader},
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use crate::Algorithm;
+
+/// Hashes of known good and known bad files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KnownHashes {
+	good: HashSet<String>,
+	bad: HashSet<String>,
+}
+
+/// How a file was classified by its hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Known {
+	/// In a known good set, and no known bad one.
+	Good,
+	/// In a known bad set.
+	Flagged,
+	Unknown,
+}
+
+/// Files of a run, by how they were classified.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KnownFiles {
+	/// How many were known good.
+	pub known: usize,
+	pub unknown: Vec<PathBuf>,
+	pub flagged: Vec<PathBuf>,
+}
+
+impl KnownHashes {
+	/// Load the sets `good` and `bad` list, with hashes made with
+	/// `algorithm`.
+	///
+	/// A set lists one hash per line, optionally followed by a name, as in
+	/// `sha256sum` output, or is a CSV file with a header naming its columns,
+	/// like the NSRL's `NSRLFile.txt`, whose column named after `algorithm`
+	/// is read. Lines starting with `#` and fields that aren't hexadecimal
+	/// are skipped.
+	pub fn load(good: &[PathBuf], bad: &[PathBuf], algorithm: Algorithm) -> io::Result<Self> {
+		let mut known = KnownHashes::default();
+		for set in good {
+			read_set(set, algorithm, &mut known.good)?;
+		}
+		for set in bad {
+			read_set(set, algorithm, &mut known.bad)?;
+		}
+		Ok(known)
+	}
+
+	/// Whether no sets were loaded, or they were all empty.
+	pub fn is_empty(&self) -> bool {
+		self.good.is_empty() && self.bad.is_empty()
+	}
+
+	/// Classify a file by its hash. Known bad beats known good.
+	pub fn classify(&self, hash: &str) -> Known {
+		let hash = hash.to_ascii_uppercase();
+		if self.bad.contains(&hash) {
+			Known::Flagged
+		} else if self.good.contains(&hash) {
+			Known::Good
+		} else {
+			Known::Unknown
+		}
+	}
+
+	/// Classify each file of `hashes`, skipping those that couldn't be read.
+	pub fn classify_files(&self, hashes: &BTreeMap<PathBuf, String>) -> KnownFiles {
+		let mut files = KnownFiles::default();
+		for (file, hash) in hashes.iter().filter(|(_, hash)| !hash.starts_with('-')) {
+			match self.classify(hash) {
+				Known::Good => files.known += 1,
+				Known::Flagged => files.flagged.push(file.clone()),
+				Known::Unknown => files.unknown.push(file.clone()),
+			}
+		}
+		files
+	}
+}
+
+fn read_set(set: &Path, algorithm: Algorithm, hashes: &mut HashSet<String>) -> io::Result<()> {
+	let algorithm = match algorithm {
+		Algorithm::UNSPECIFIED => Algorithm::BLAKE3,
+		algorithm => algorithm,
+	};
+	let mut lines = BufReader::new(File::open(set)?).lines();
+	// CSV column of the hashes, if it is CSV
+	let mut column = None;
+	for line in lines.by_ref() {
+		let line = line?;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		if line.starts_with('"') && line.contains(',') {
+			column = line.split(',').map(|name| name.trim().trim_matches('"')).position(|name| Algorithm::from_str(name) == Ok(algorithm));
+			if column.is_none() {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("{:?} has no column of {:?} hashes", set, algorithm),
+				));
+			}
+		} else {
+			insert_hash(hashes, line.split_whitespace().next());
+		}
+		break;
+	}
+	for line in lines {
+		let line = line?;
+		let field = match column {
+			Some(column) => line.split(',').nth(column).map(|field| field.trim().trim_matches('"')),
+			None if line.starts_with('#') => None,
+			None => line.split_whitespace().next(),
+		};
+		insert_hash(hashes, field);
+	}
+	Ok(())
+}
+
+fn insert_hash(hashes: &mut HashSet<String>, field: Option<&str>) {
+	// sha256sum escapes the lines of names with backslashes
+	if let Some(hash) = field.map(|field| field.trim_start_matches('\\'))
+		&& !hash.is_empty()
+		&& hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+	{
+		hashes.insert(hash.to_ascii_uppercase());
+	}
+}
@@ -0,0 +1,47 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use super::{FileRecord, PathStyle, placeholder_hash};
+use crate::{Algorithm, hash_reader};
+
+/// Start of the header comment holding the layout digest of a names-only
+/// hash file.
+pub static LAYOUT_DIGEST_PREFIX: &str = "layout digest: ";
+
+/// Hash standing for a file's content when only the layout of a tree is
+/// hashed: that of its size in bytes with `with_sizes`, a placeholder
+/// otherwise.
+pub(super) fn names_only_hash(record: &FileRecord, algo: Algorithm, with_sizes: bool) -> String {
+	match with_sizes {
+		true => hash_reader(algo, &mut record.len.to_string().as_bytes()),
+		false => placeholder_hash(algo),
+	}
+}
+
+/// Single digest of a tree's layout, over the names and hashes of its
+/// entries in path order, with `/` separators so that it's the same on every
+/// platform.
+///
+/// With hashes made by `--names-only`, two trees with the same digest have
+/// the same files, and with `--with-sizes`, of the same sizes.
+pub fn layout_digest(hashes: &BTreeMap<PathBuf, String>, algo: Algorithm) -> String {
+	let mut listing = Vec::new();
+	for (file, hash) in hashes {
+		listing.extend_from_slice(format!("{}  {}\n", hash, PathStyle::Unix.format(file)).as_bytes());
+	}
+	hash_reader(algo, &mut listing.as_slice())
+}
@@ -0,0 +1,98 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Locks on hash files, so that a run started while another one uses the
+//! same hash file, like overlapping cron jobs, waits or stops instead of
+//! interleaving their writes.
+//!
+//! The lock is taken on a file next to the hash file, as the hash file
+//! itself is replaced when written. It's left there after the run, removing
+//! it would let a waiting run and a new one both lock it.
+//!
+//! Runs only reading the hash file share the lock, so they don't hold each
+//! other up. Hash files in places that can't be written to, like read-only
+//! media, are left unlocked: nothing can write to them either.
+
+use std::{
+	fs::{File, OpenOptions, TryLockError},
+	io,
+	path::{Path, PathBuf},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// Exit code of runs that didn't get the lock, EX_TEMPFAIL of sysexits.h.
+pub const LOCKED_EXIT_CODE: i32 = 75;
+
+/// How often the lock is tried while waiting for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// File locked by runs using `hash_file`, next to it.
+pub fn lock_path(hash_file: &Path) -> PathBuf {
+	let mut path = hash_file.as_os_str().to_owned();
+	path.push(".lock");
+	PathBuf::from(path)
+}
+
+/// Lock on a hash file, held until dropped.
+#[derive(Debug)]
+pub struct RunLock {
+	/// None if the hash file is somewhere that can't be written to.
+	file: Option<File>,
+}
+
+impl RunLock {
+	/// Whether the lock is held, the hash file being somewhere it could be
+	/// taken.
+	pub fn held(&self) -> bool {
+		self.file.is_some()
+	}
+}
+
+/// Lock `hash_file` for a run, waiting up to `wait` for the run holding it
+/// to finish. Gets none if it's still held then. With `shared`, it's held
+/// along with other runs taking it `shared`, for runs that only read the hash
+/// file.
+pub fn lock_run(hash_file: &Path, wait: Duration, shared: bool) -> io::Result<Option<RunLock>> {
+	let path = lock_path(hash_file);
+	let file = match OpenOptions::new().create(true).write(true).truncate(false).open(&path) {
+		Ok(file) => file,
+		// Shared locks are taken on files opened for reading too
+		Err(err) if unwritable(&err) => match File::open(&path) {
+			Ok(file) if shared => file,
+			_ => return Ok(Some(RunLock { file: None })),
+		},
+		Err(err) => return Err(err),
+	};
+	let deadline = Instant::now() + wait;
+	loop {
+		let locked = match shared {
+			true => file.try_lock_shared(),
+			false => file.try_lock(),
+		};
+		match locked {
+			Ok(()) => return Ok(Some(RunLock { file: Some(file) })),
+			Err(TryLockError::WouldBlock) => match deadline.checked_duration_since(Instant::now()) {
+				Some(left) if !left.is_zero() => thread::sleep(left.min(POLL_INTERVAL)),
+				_ => return Ok(None),
+			},
+			Err(TryLockError::Error(err)) => return Err(err),
+		}
+	}
+}
+
+/// Whether `err` is of a place that can't be written to.
+fn unwritable(err: &io::Error) -> bool {
+	matches!(err.kind(), io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem)
+}
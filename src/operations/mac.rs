@@ -0,0 +1,121 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Data macOS keeps alongside a file's content: its resource fork, stored as
+//! `file/..namedfork/rsrc` like macOS names it, and its Finder info (type,
+//! creator and Finder flags) and quarantine extended attributes, stored as
+//! `file/..xattr/com.apple.FinderInfo` and `file/..xattr/com.apple.quarantine`.
+//! Files without them get no such entries.
+
+// `getxattr()` has no safe wrapper in std
+#![cfg_attr(target_os = "macos", allow(unsafe_code))]
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use super::{HashingReport, WalkOptions};
+use crate::Algorithm;
+
+/// Hash the resource fork and extended attributes of `file`, stored as
+/// `name`, adding them to `hashes`. Those that can't be read get a warning in
+/// `report`.
+#[cfg(target_os = "macos")]
+pub(super) fn hash_mac_metadata(
+	file: &Path,
+	name: &Path,
+	algo: Algorithm,
+	options: &WalkOptions,
+	hashes: &mut BTreeMap<PathBuf, String>,
+	report: &mut HashingReport,
+) {
+	use std::fs;
+
+	use crate::{
+		hashing::{try_hash_file, try_hash_reader_with},
+		utilities::long_path,
+	};
+
+	/// Extended attributes hashed along with files.
+	const XATTRS: [&str; 2] = ["com.apple.FinderInfo", "com.apple.quarantine"];
+
+	let fork = file.join("..namedfork").join("rsrc");
+	if fs::metadata(long_path(&fork)).is_ok_and(|metadata| metadata.len() > 0) {
+		// Read as-is whatever the content is read as
+		let hash_options = crate::HashOptions { text_mode: false, ..options.hash_options.clone() };
+		match try_hash_file(algo, &fork, &hash_options) {
+			Ok(hash) => {
+				hashes.insert(name.join("..namedfork").join("rsrc"), hash);
+			}
+			Err(err) => report.warnings.push(format!("Failed to read the resource fork of {:?}: {}", name, err)),
+		}
+	}
+	for xattr in XATTRS {
+		match getxattr(file, xattr) {
+			Ok(Some(value)) => {
+				let hash = try_hash_reader_with(algo, &mut value.as_slice(), &options.hash_options).expect("Reading bytes doesn't fail");
+				hashes.insert(name.join("..xattr").join(xattr), hash);
+			}
+			Ok(None) => {}
+			Err(err) => report.warnings.push(format!("Failed to read {} of {:?}: {}", xattr, name, err)),
+		}
+	}
+}
+
+/// Files have no resource forks or Finder info on this platform.
+#[cfg(not(target_os = "macos"))]
+pub(super) fn hash_mac_metadata(
+	_file: &Path,
+	_name: &Path,
+	_algo: Algorithm,
+	_options: &WalkOptions,
+	_hashes: &mut BTreeMap<PathBuf, String>,
+	_report: &mut HashingReport,
+) {
+}
+
+/// Value of the extended attribute `name` of `file`, if it has it.
+#[cfg(target_os = "macos")]
+fn getxattr(file: &Path, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+	use std::{ffi::CString, io, os::unix::ffi::OsStrExt, ptr};
+
+	let path = CString::new(file.as_os_str().as_bytes()).map_err(io::Error::other)?;
+	let name = CString::new(name).map_err(io::Error::other)?;
+	loop {
+		// SAFETY: both strings are NUL-terminated, and no buffer is passed
+		let len = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), ptr::null_mut(), 0, 0, libc::XATTR_NOFOLLOW) };
+		if len < 0 {
+			let err = io::Error::last_os_error();
+			return match err.raw_os_error() {
+				Some(libc::ENOATTR) => Ok(None),
+				_ => Err(err),
+			};
+		}
+		let mut value = vec![0u8; len as usize];
+		// SAFETY: `value` has room for the `len` bytes asked for
+		let read = unsafe {
+			libc::getxattr(path.as_ptr(), name.as_ptr(), value.as_mut_ptr().cast(), value.len(), 0, libc::XATTR_NOFOLLOW)
+		};
+		if read >= 0 {
+			value.truncate(read as usize);
+			return Ok(Some(value));
+		}
+		let err = io::Error::last_os_error();
+		// It grew in between
+		if err.raw_os_error() != Some(libc::ERANGE) {
+			return Err(err);
+		}
+	}
+}
@@ -0,0 +1,109 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::{BTreeMap, btree_map::Entry},
+	ffi::OsString,
+	path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+
+/// What to do when two manifests disagree about the hash of the same file.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum MergePolicy {
+	/// Keep the entry from the manifest that comes last (newest).
+	PreferNewest,
+	/// Refuse to merge.
+	Error,
+	/// Keep both entries, renaming the later one with a `~N` suffix.
+	KeepBothWithSuffix,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum MergeError {
+	Conflict {
+		file: PathBuf,
+		first_hash: String,
+		second_hash: String,
+	},
+}
+
+/// Merge the specified manifests into one.
+///
+/// Manifests are applied in order, so with `MergePolicy::PreferNewest` the
+/// caller is expected to pass them oldest first. Entries with equal hashes
+/// are never considered conflicting.
+///
+/// # Examples
+///
+/// ```
+/// # use std::{collections::BTreeMap, path::PathBuf};
+/// # use quickdash::operations::{MergePolicy, merge_hashes};
+/// let a = BTreeMap::from([(PathBuf::from("a"), "00".to_string())]);
+/// let b = BTreeMap::from([(PathBuf::from("a"), "FF".to_string())]);
+///
+/// let merged = merge_hashes(vec![a, b], MergePolicy::KeepBothWithSuffix).unwrap();
+/// assert_eq!(merged[&PathBuf::from("a")], "00");
+/// assert_eq!(merged[&PathBuf::from("a~1")], "FF");
+/// ```
+pub fn merge_hashes(
+	manifests: Vec<BTreeMap<PathBuf, String>>,
+	policy: MergePolicy,
+) -> Result<BTreeMap<PathBuf, String>, MergeError> {
+	let mut merged = BTreeMap::new();
+
+	for manifest in manifests {
+		for (file, hash) in manifest {
+			match merged.entry(file) {
+				Entry::Vacant(entry) => {
+					entry.insert(hash);
+				}
+				Entry::Occupied(entry) if *entry.get() == hash => {}
+				Entry::Occupied(mut entry) => match policy {
+					MergePolicy::PreferNewest => {
+						entry.insert(hash);
+					}
+					MergePolicy::Error => {
+						return Err(MergeError::Conflict {
+							file: entry.key().clone(),
+							first_hash: entry.get().clone(),
+							second_hash: hash,
+						});
+					}
+					MergePolicy::KeepBothWithSuffix => {
+						let file = entry.key().clone();
+						let renamed = suffixed_name(&merged, &file, &hash);
+						merged.insert(renamed, hash);
+					}
+				},
+			}
+		}
+	}
+
+	Ok(merged)
+}
+
+/// Find the first `file~N` that is either free or already holds `hash`.
+fn suffixed_name(merged: &BTreeMap<PathBuf, String>, file: &Path, hash: &str) -> PathBuf {
+	(1..)
+		.map(|n| {
+			let mut name = OsString::from(file.as_os_str());
+			name.push(format!("~{}", n));
+			PathBuf::from(name)
+		})
+		.find(|candidate| merged.get(candidate).is_none_or(|h| h == hash))
+		.unwrap()
+}
@@ -0,0 +1,94 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::BTreeMap,
+	ffi::OsString,
+	path::{Component, PathBuf},
+};
+
+use crate::{Algorithm, hash_reader};
+
+/// Start of the header comment holding the tree hash of a hash file.
+pub static TREE_HASH_PREFIX: &str = "tree hash: ";
+
+/// A directory of the tree, by entry name.
+#[derive(Debug, Default)]
+struct Directory {
+	files: BTreeMap<OsString, String>,
+	directories: BTreeMap<OsString, Directory>,
+}
+
+impl Directory {
+	/// Hash of the listing of the directory, one `kind hash name` record per
+	/// entry in name order, where `kind` is `f` for files and `d` for
+	/// directories, whose hash is that of their own listing.
+	fn digest(&self, algo: Algorithm) -> String {
+		let mut entries: Vec<(&OsString, char, String)> = self
+			.files
+			.iter()
+			.map(|(name, hash)| (name, 'f', hash.clone()))
+			.chain(self.directories.iter().map(|(name, directory)| (name, 'd', directory.digest(algo))))
+			.collect();
+		entries.sort();
+
+		let mut listing = Vec::new();
+		for (name, kind, hash) in entries {
+			// Filenames can't hold NUL, so records can't run into each other
+			listing.extend_from_slice(format!("{} {} {}\0", kind, hash, name.to_string_lossy()).as_bytes());
+		}
+		hash_reader(algo, &mut listing.as_slice())
+	}
+}
+
+/// Merkle-style root hash of a directory, given the hashes of its files by
+/// relative path: each directory is hashed from the names and hashes of its
+/// files and subdirectories, bottom-up, so the root changes with any file's
+/// content, name or place, and the same tree gives the same root on every
+/// platform. File hashes are lowercased first, so hash files written in
+/// either case give the same root.
+///
+/// # Examples
+///
+/// ```
+/// # use std::{collections::BTreeMap, path::PathBuf};
+/// # use quickdash::{Algorithm, operations::tree_hash};
+/// let hashes = BTreeMap::from([(PathBuf::from("a/b"), "AA".to_string()), (PathBuf::from("c"), "BB".to_string())]);
+/// let moved = BTreeMap::from([(PathBuf::from("b"), "AA".to_string()), (PathBuf::from("c"), "BB".to_string())]);
+/// assert_eq!(tree_hash(&hashes, Algorithm::SHA2256), tree_hash(&hashes.clone(), Algorithm::SHA2256));
+/// assert_ne!(tree_hash(&hashes, Algorithm::SHA2256), tree_hash(&moved, Algorithm::SHA2256));
+/// let lowercase = BTreeMap::from([(PathBuf::from("a/b"), "aa".to_string()), (PathBuf::from("c"), "bb".to_string())]);
+/// assert_eq!(tree_hash(&hashes, Algorithm::SHA2256), tree_hash(&lowercase, Algorithm::SHA2256));
+/// ```
+pub fn tree_hash(hashes: &BTreeMap<PathBuf, String>, algo: Algorithm) -> String {
+	let mut root = Directory::default();
+	for (file, hash) in hashes {
+		let mut names: Vec<OsString> = file
+			.components()
+			.filter_map(|component| match component {
+				Component::Normal(name) => Some(name.to_owned()),
+				_ => None,
+			})
+			.collect();
+		let Some(name) = names.pop() else {
+			continue;
+		};
+		let directory = names
+			.into_iter()
+			.fold(&mut root, |directory, name| directory.directories.entry(name).or_default());
+		directory.files.insert(name, hash.to_ascii_lowercase());
+	}
+	root.digest(algo)
+}
@@ -0,0 +1,223 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	fmt,
+	fs::Metadata,
+	time::{Duration, UNIX_EPOCH},
+};
+
+use super::FileRecord;
+
+/// Start of the comment line holding the metadata of the entry after it.
+pub(super) static METADATA_PREFIX: &str = "metadata: ";
+
+/// Metadata of a file recorded alongside its hash, as a comment line right
+/// before its entry: `metadata: size 1234, mtime 1700000000.000000000`,
+/// followed by `, owner 0, group 0, mode 644` with its ownership, and by
+/// `, attributes RHS, created 1700000000.000000000` with its Windows
+/// attributes.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EntryMetadata {
+	/// Size in bytes.
+	pub size: u64,
+	/// Modification time, since the Unix epoch.
+	pub mtime: Duration,
+	pub ownership: Option<Ownership>,
+	pub attributes: Option<WindowsAttributes>,
+}
+
+/// Owner, group and permission bits of a file.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ownership {
+	pub uid: u32,
+	pub gid: u32,
+	/// Permission bits, including setuid, setgid and sticky.
+	pub mode: u32,
+}
+
+impl Ownership {
+	#[cfg(unix)]
+	pub(super) fn of(metadata: &Metadata) -> Option<Self> {
+		use std::os::unix::fs::MetadataExt;
+
+		Some(Ownership {
+			uid: metadata.uid(),
+			gid: metadata.gid(),
+			mode: metadata.mode() & 0o7777,
+		})
+	}
+
+	/// Files have no Unix owner or mode on this platform.
+	#[cfg(not(unix))]
+	pub(super) fn of(_metadata: &Metadata) -> Option<Self> {
+		None
+	}
+}
+
+/// Attributes and creation time of a file on Windows.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WindowsAttributes {
+	pub readonly: bool,
+	pub hidden: bool,
+	pub system: bool,
+	/// Creation time, since the Unix epoch.
+	pub created: Duration,
+}
+
+impl WindowsAttributes {
+	#[cfg(windows)]
+	pub(super) fn of(metadata: &Metadata) -> Option<Self> {
+		use std::os::windows::fs::MetadataExt;
+
+		const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+		const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+		const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+		let attributes = metadata.file_attributes();
+		Some(WindowsAttributes {
+			readonly: attributes & FILE_ATTRIBUTE_READONLY != 0,
+			hidden: attributes & FILE_ATTRIBUTE_HIDDEN != 0,
+			system: attributes & FILE_ATTRIBUTE_SYSTEM != 0,
+			created: metadata.created().ok()?.duration_since(UNIX_EPOCH).ok()?,
+		})
+	}
+
+	/// Files have no Windows attributes on this platform.
+	#[cfg(not(windows))]
+	pub(super) fn of(_metadata: &Metadata) -> Option<Self> {
+		None
+	}
+
+	/// The attributes that are set, as `RHS`, or `-` for none.
+	pub fn flags(&self) -> String {
+		let flags: String = [(self.readonly, 'R'), (self.hidden, 'H'), (self.system, 'S')]
+			.into_iter()
+			.filter_map(|(set, flag)| set.then_some(flag))
+			.collect();
+		match flags.is_empty() {
+			true => "-".to_owned(),
+			false => flags,
+		}
+	}
+}
+
+impl EntryMetadata {
+	/// Metadata of a walked file, if its modification time is known, with
+	/// its ownership and Windows attributes if `ownership`.
+	pub(super) fn of(record: &FileRecord, ownership: bool) -> Option<Self> {
+		Some(EntryMetadata {
+			size: record.len,
+			mtime: record.mtime?.duration_since(UNIX_EPOCH).ok()?,
+			ownership: record.ownership.filter(|_| ownership),
+			attributes: record.attributes.filter(|_| ownership),
+		})
+	}
+
+	/// Whether the size or modification time differ, so the content likely
+	/// changed.
+	pub(super) fn drifted(&self, new: &EntryMetadata) -> bool {
+		self.size != new.size || self.mtime != new.mtime
+	}
+
+	/// Parse the text of a metadata comment, or get `None` if it isn't one.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use std::time::Duration;
+	/// # use quickdash::operations::EntryMetadata;
+	/// let metadata = EntryMetadata::parse("metadata: size 5, mtime 1700000000.5").unwrap();
+	/// assert_eq!(metadata.size, 5);
+	/// assert_eq!(metadata.mtime, Duration::new(1_700_000_000, 500_000_000));
+	/// assert_eq!(metadata.ownership, None);
+	/// assert_eq!(EntryMetadata::parse(&metadata.to_string()), Some(metadata));
+	///
+	/// let metadata = EntryMetadata::parse("metadata: size 5, mtime 1, owner 0, group 10, mode 4755").unwrap();
+	/// assert_eq!(metadata.ownership.unwrap().mode, 0o4755);
+	/// assert_eq!(EntryMetadata::parse(&metadata.to_string()), Some(metadata));
+	/// assert_eq!(EntryMetadata::parse("generated by QuickSFV"), None);
+	///
+	/// let metadata = EntryMetadata::parse("metadata: size 5, mtime 1, attributes RS, created 1.5").unwrap();
+	/// assert_eq!(metadata.attributes.unwrap().flags(), "RS");
+	/// assert_eq!(EntryMetadata::parse(&metadata.to_string()), Some(metadata));
+	/// ```
+	pub fn parse(comment: &str) -> Option<Self> {
+		let (mut size, mut mtime, mut uid, mut gid, mut mode) = (None, None, None, None, None);
+		let (mut flags, mut created) = (None, None);
+		for field in comment.strip_prefix(METADATA_PREFIX)?.split(',') {
+			match field.trim().split_once(' ')? {
+				("size", value) => size = Some(value.parse().ok()?),
+				("mtime", value) => mtime = Some(parse_time(value)?),
+				("owner", value) => uid = Some(value.parse().ok()?),
+				("group", value) => gid = Some(value.parse().ok()?),
+				("mode", value) => mode = Some(u32::from_str_radix(value, 8).ok()?),
+				("attributes", value) if value.chars().all(|c| "RHS-".contains(c)) => flags = Some(value),
+				("created", value) => created = Some(parse_time(value)?),
+				// Fields from later versions
+				_ => {}
+			}
+		}
+		let ownership = match (uid, gid, mode) {
+			(Some(uid), Some(gid), Some(mode)) => Some(Ownership { uid, gid, mode }),
+			_ => None,
+		};
+		let attributes = match (flags, created) {
+			(Some(flags), Some(created)) => Some(WindowsAttributes {
+				readonly: flags.contains('R'),
+				hidden: flags.contains('H'),
+				system: flags.contains('S'),
+				created,
+			}),
+			_ => None,
+		};
+		Some(EntryMetadata { size: size?, mtime: mtime?, ownership, attributes })
+	}
+}
+
+impl fmt::Display for EntryMetadata {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}size {}, mtime {}.{:09}",
+			METADATA_PREFIX,
+			self.size,
+			self.mtime.as_secs(),
+			self.mtime.subsec_nanos()
+		)?;
+		if let Some(Ownership { uid, gid, mode }) = self.ownership {
+			write!(f, ", owner {}, group {}, mode {:o}", uid, gid, mode)?;
+		}
+		match self.attributes {
+			Some(attributes) => write!(
+				f,
+				", attributes {}, created {}.{:09}",
+				attributes.flags(),
+				attributes.created.as_secs(),
+				attributes.created.subsec_nanos()
+			),
+			None => Ok(()),
+		}
+	}
+}
+
+/// Parse `seconds[.fraction]`, keeping up to nanoseconds.
+pub(super) fn parse_time(value: &str) -> Option<Duration> {
+	let (secs, fraction) = value.split_once('.').unwrap_or((value, ""));
+	if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+	let nanos = format!("{:0<9}", fraction).parse().ok()?;
+	Some(Duration::new(secs.parse().ok()?, nanos))
+}
@@ -0,0 +1,127 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Metrics of verification runs in the text format of node_exporter's
+//! textfile collector, so Prometheus can alert when scrubs fail or stop
+//! running.
+//!
+//! Each run replaces the samples labelled with its command and hash file,
+//! keeping those of other runs written to the same file.
+
+use std::{
+	collections::BTreeMap,
+	fs,
+	io,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{CompareOutcome, audit::failed_files};
+use crate::Error;
+
+/// Gauges written, with their help text.
+static GAUGES: [(&str, &str); 6] = [
+	("quickdash_files_scanned", "Files walked by the last run, read or not."),
+	("quickdash_bytes_hashed", "Bytes read to hash files in the last run."),
+	("quickdash_failures", "Files that failed verification in the last run."),
+	("quickdash_last_run_success", "Whether the last run compared and no file failed."),
+	("quickdash_last_run_timestamp_seconds", "When the last run finished, in seconds since the epoch."),
+	("quickdash_last_success_timestamp_seconds", "When a run last compared and no file failed, in seconds since the epoch."),
+];
+
+/// What a run hashed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RunMetrics {
+	/// Files walked, whether read or not.
+	pub files_scanned: usize,
+	/// Bytes read to hash them.
+	pub bytes_hashed: u64,
+}
+
+/// Write the metrics of `command` verifying `manifest` to `textfile`,
+/// creating it if needed, replacing those of the last such run.
+///
+/// The file is written next to it and renamed over it, so the collector
+/// never reads half of it.
+pub fn write_metrics_textfile(
+	textfile: &Path,
+	command: &str,
+	manifest: &Path,
+	run: &RunMetrics,
+	result: Result<&CompareOutcome, &Error>,
+) -> io::Result<()> {
+	let labels = format!("{{command=\"{}\",manifest=\"{}\"}}", label_value(command), label_value(&manifest.to_string_lossy()));
+	let existing = match fs::read_to_string(textfile) {
+		Ok(text) => text,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+		Err(err) => return Err(err),
+	};
+	let mut samples: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+	let mut last_success = None;
+	for line in existing.lines().filter(|line| !line.starts_with('#')) {
+		let Some((name, _)) = GAUGES.iter().find(|(name, _)| line.strip_prefix(name).is_some_and(|rest| rest.starts_with('{'))) else {
+			continue;
+		};
+		match line[name.len()..].strip_prefix(labels.as_str()) {
+			Some(value) if *name == GAUGES[5].0 => last_success = Some(value.trim().to_owned()),
+			Some(_) => {}
+			None => samples.entry(*name).or_default().push(line.to_owned()),
+		}
+	}
+
+	let (failures, compared) = match result {
+		Ok(Ok((compare_results, file_compare_results))) => (failed_files(compare_results, file_compare_results).len(), true),
+		_ => (0, false),
+	};
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()).to_string();
+	let succeeded = compared && failures == 0;
+	if succeeded {
+		last_success = Some(now.clone());
+	}
+	let values = [
+		Some(run.files_scanned.to_string()),
+		Some(run.bytes_hashed.to_string()),
+		Some(failures.to_string()),
+		Some(u8::from(succeeded).to_string()),
+		Some(now),
+		last_success,
+	];
+	for ((name, _), value) in GAUGES.iter().zip(values) {
+		if let Some(value) = value {
+			samples.entry(*name).or_default().push(format!("{}{} {}", name, labels, value));
+		}
+	}
+
+	let mut text = String::new();
+	for (name, help) in GAUGES {
+		let Some(lines) = samples.get(name) else {
+			continue;
+		};
+		text.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+		for line in lines {
+			text.push_str(line);
+			text.push('\n');
+		}
+	}
+	let mut partial = textfile.as_os_str().to_owned();
+	partial.push(".tmp");
+	let partial = PathBuf::from(partial);
+	fs::write(&partial, text)?;
+	fs::rename(&partial, textfile)
+}
+
+/// `value` escaped as a label value.
+fn label_value(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
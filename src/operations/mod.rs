@@ -22,16 +22,86 @@
 //! saved hashes, them with `compare_hashes()` and print them with
 //! `write_hash_comparison_results()`.
 
+mod archive;
+mod audit;
+mod bagit;
+mod bao;
+mod bencode;
+mod cache;
+mod changes;
+mod checkpoint;
+mod comment;
 mod compare;
+#[cfg(unix)]
+mod daemon;
+mod dedupe;
+mod discover;
+mod encoding;
+mod encrypt;
+mod fetch;
+mod fix;
+mod generations;
+mod git;
+mod hard_links;
+mod http;
+mod ignore;
+mod in_toto;
+mod inflate;
+mod inventory;
+mod jobs;
+mod json;
+mod known;
+mod layout;
+mod lock;
+mod mac;
+mod merge;
+mod merkle;
+mod metadata;
+mod metrics;
+mod mtree;
+mod multihash;
+mod normalize;
+mod notify;
+mod par2;
+mod path_style;
+mod pieces;
+mod pipeline;
+mod publish;
+mod quarantine;
+mod query;
+mod recorded;
+mod record;
+mod reflink;
+mod release;
+mod remote;
+mod roots;
+mod service;
+mod shard;
+mod signature;
+mod similar;
+mod snapshot;
+mod special;
+mod storage;
+mod syslog;
+mod tag;
+mod timestamp;
+mod torrent;
+mod trees;
+mod tui;
+mod update;
+mod usn;
+mod verified;
 mod write;
 mod optimize_file_order;
+mod parallel;
 
 use std::{
-	collections::BTreeMap,
-	fs::File,
-	io::{BufRead, BufReader, Write},
+	collections::{BTreeMap, BTreeSet},
+	fs::{File, read_dir, read_link},
+	io::{self, BufRead, BufReader, Read, Write},
 	path::{Path, PathBuf},
 	sync::LazyLock,
+	thread,
 	time::Duration,
 };
 
@@ -40,28 +110,290 @@ use regex::Regex;
 use tabwriter::TabWriter;
 use walkdir::{DirEntry, WalkDir};
 
-pub use self::{compare::*, write::*};
+use self::{
+	cache::HashCache,
+	checkpoint::Checkpoint,
+	encoding::{Utf16Reader, utf16_bom},
+	hard_links::HardLinks,
+	layout::names_only_hash,
+	metadata::METADATA_PREFIX,
+	record::FileRecord,
+	special::special_kind,
+};
+pub use self::{archive::{ArchiveKind, MEMBER_SEPARATOR}, audit::{append_audit_record, verify_audit_log}, bagit::{bagit_algorithm_name, create_bag, validate_bag}, bao::{RangeCheck, outboard_dir, outboard_path, verify_range, write_outboards}, cache::default_cache_path, changes::{CHANGES_HEADER_PREFIX, ChangesPosition, changed_paths, changes_path, changes_position, record_changes}, checkpoint::checkpoint_path, comment::*, compare::*, dedupe::{DedupeAction, DuplicateSet, Keep, consolidate, find_duplicates, log_consolidation, undo_consolidations}, encoding::ManifestEncoding, encrypt::{Encryption, decrypt, encrypt}, fetch::{FetchedFile, fetch, hash_url, is_url}, fix::{Fix, Mismatch, fix_hashes}, generations::*, git::{GIT_TRACKED_HEADER, git_tracked_files}, http::{ServedRoot, serve_http}, ignore::*, in_toto::write_in_toto, inventory::{InventoryRun, host_name, inventory_sql, write_inventory}, jobs::JobOptions, json::Json, known::{Known, KnownFiles, KnownHashes}, layout::{LAYOUT_DIGEST_PREFIX, layout_digest}, lock::{LOCKED_EXIT_CODE, RunLock, lock_path, lock_run}, merge::*, merkle::{TREE_HASH_PREFIX, tree_hash}, metadata::{EntryMetadata, Ownership, WindowsAttributes}, metrics::{RunMetrics, write_metrics_textfile}, mtree::mtree_keyword, multihash::{HashEncoding, decode_named_hash, multihash_code, oci_name}, normalize::*, notify::{FailureSummary, Smtp, send_email, send_webhook}, optimize_file_order::FileOrder, par2::{create_recovery, par2_dir, recovery_file, repair}, path_style::*, pieces::{Pieces, apply_recorded_pieces, record_pieces}, pipeline::*, publish::{PublishTarget, publish}, quarantine::{quarantine, quarantine_log}, query::{Query, query_hashes, query_sql}, recorded::{RecordedMetadata, ScrubBudget}, release::{check_signature, create_sums, sign_sums, sums_algorithm, sums_name, verify_sums}, remote::{parse_remote_target, remote_manifest}, roots::*, service::{STOPPED_EXIT_CODE, Watchdog, partial_path, sd_notify, stop_on_sigterm, stopping}, shard::*, signature::{signature_dir, signature_path, write_signatures}, similar::{FuzzyHash, fuzzy_hashes, similar_files}, snapshot::{Snapshot, SnapshotKind, snapshot}, special::SpecialFiles, storage::*, syslog::{FAILURE_MESSAGE_ID, LogRecord, LogTarget, RUN_MESSAGE_ID, log_records, log_to}, tag::{Rename, TAG_LEN, apply_rename, strip_tag, tag_name, tag_renames}, timestamp::{TimestampCheck, check_timestamp, request_timestamp, timestamp_path}, torrent::{Torrent, check_torrent, write_torrent}, trees::{Difference, TreeComparison, compare_trees, first_difference}, tui::{LiveVerification, Progress, Verdict, run_tui}, update::{Changed, Unchanged}, usn::{USN_HEADER_PREFIX, UsnPosition, usn_changed_names, usn_position}, verified::{LastVerified, verified_path}, write::*};
+#[cfg(unix)]
+pub use self::daemon::{connect_daemon, default_socket_path, serve};
 use crate::{
-	Algorithm, Error, hash_file,
-	utilities::relative_name,
+	Algorithm, Error, HashOptions, hash_reader,
+	hashing::hash_file_checked,
+	utilities::{escape_filename, long_path, mul_str, relative_name, short_path, unescape_filename},
 };
 
+/// Byte order mark some Windows tools start text files with.
+static BOM: char = '\u{feff}';
+
 static SPINNER_STRINGS: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
-/// Create subpath->hash mappings for a given path using a given algorithm up to
-/// a given depth.
+/// Options controlling which files under a path get hashed and how they're
+/// named.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalkOptions {
+	/// Files/directories to skip, relative to the walked path.
+	pub ignored_files: Vec<PathBuf>,
+	/// Patterns of files/directories to skip, matched against paths relative
+	/// to the walked path.
+	pub ignored_patterns: Vec<IgnorePattern>,
+	/// Max recursion depth. Infinite if `None`.
+	pub depth: Option<usize>,
+	/// Whether to recurse down symlinks.
+	pub follow_symlinks: bool,
+	/// Without `follow_symlinks`, record symlinks by their target instead of
+	/// skipping them.
+	pub record_symlinks: bool,
+	/// Record directories with nothing in them with a placeholder hash, so
+	/// their removal is noticed.
+	pub record_empty_dirs: bool,
+	/// Don't read files, giving each one a placeholder hash, or with
+	/// `names_with_sizes`, the hash of its size, so that only the layout of
+	/// the tree is compared.
+	pub names_only: bool,
+	pub names_with_sizes: bool,
+	/// What to do with FIFOs, sockets and device nodes.
+	pub special_files: SpecialFiles,
+	/// Record files larger than this many bytes with a placeholder instead of
+	/// hashing them.
+	pub skip_larger_than: Option<u64>,
+	/// Hash every hard link to a file, instead of hashing it once and
+	/// reusing the hash for its other links.
+	pub hash_each_hard_link: bool,
+	/// Skip dotfiles, dot-directories and, on Windows, files/directories
+	/// with the hidden attribute.
+	pub skip_hidden: bool,
+	/// How many more times to read a file that failed to be read before
+	/// recording it as errored.
+	pub retries: u32,
+	/// How long to wait before reading a file again.
+	pub retry_delay: Duration,
+	/// How files are read.
+	pub hash_options: HashOptions,
+	/// Threads reading files, ahead of the threads hashing them. Files are
+	/// read and hashed on the calling thread if neither is more than 1.
+	pub io_threads: usize,
+	/// Threads hashing what the reading threads read.
+	pub hash_threads: usize,
+	/// Order files are hashed in.
+	pub order: FileOrder,
+	/// Hash cache file to reuse the hashes of unchanged files from and
+	/// remember new ones in. Files are always read if `None`.
+	pub cache: Option<PathBuf>,
+	/// Checkpoint file the hashes are appended to as they're made, so an
+	/// interrupted run can resume. Not written if `None`.
+	pub checkpoint: Option<PathBuf>,
+	/// Reuse the hashes in `checkpoint` of the files that haven't changed
+	/// since, instead of starting it over.
+	pub resume: bool,
+	/// Note the size and modification time of hashed files in the report.
+	pub record_metadata: bool,
+	/// Include the owner, group and mode with `record_metadata`, and note
+	/// changes to them against `recorded` in the report.
+	pub check_metadata: bool,
+	/// Metadata of the manifest entries being verified, to note the files
+	/// whose metadata changed in the report, not reading those whose size
+	/// changed, and with `quick`, not reading those whose metadata matches.
+	pub recorded: Option<RecordedMetadata>,
+	/// Directory the stored names are relative to. Must be a prefix of the
+	/// walked path. The walked path itself if `None`.
+	pub relative_to: Option<PathBuf>,
+	/// Store full paths instead of relative ones. The walked path should be
+	/// absolute.
+	pub absolute_paths: bool,
+	/// With `absolute_paths`, names still stored relative to the walked path,
+	/// as for the relative entries of a hash file mixing both.
+	pub relative_names: BTreeSet<PathBuf>,
+	/// Unicode normalization form stored names are brought to. Left as-is if
+	/// `None`.
+	pub normalize_unicode: Option<UnicodeForm>,
+	/// Kinds of archives whose members are hashed too, as
+	/// `archive!member`.
+	pub look_inside: Vec<ArchiveKind>,
+	/// Also hash the resource forks, Finder info and quarantine attributes
+	/// of files, on macOS.
+	pub mac_metadata: bool,
+	/// Hashes of an earlier run, kept for the files that haven't changed
+	/// since instead of reading them.
+	pub unchanged: Option<Unchanged>,
+	/// Only walk the files/directories named here, relative to the walked
+	/// path, like those git tracks and the directories leading to them. All
+	/// are walked if `None`.
+	pub tracked: Option<BTreeSet<PathBuf>>,
+}
+
+impl WalkOptions {
+	/// Directory the names of files under `path` are relative to.
+	pub fn base<'a>(&'a self, path: &'a Path) -> &'a Path {
+		self.relative_to.as_deref().unwrap_or(path)
+	}
+
+	/// Ignore files/directories matching `pattern`, relative to the walked
+	/// path.
+	///
+	/// Plain paths are compared as-is. Globs and paths ending in `/`, which
+	/// match directories only, become `IgnorePattern`s, matching at any depth
+	/// unless they start with `/`, the same as patterns of an exclude file.
+	pub fn ignore(&mut self, pattern: &str) -> Result<(), regex::Error> {
+		if pattern.ends_with('/') || pattern.contains(['*', '?', '[']) {
+			self.ignored_patterns.push(IgnorePattern::new(pattern)?);
+		} else {
+			self.ignored_files.push(PathBuf::from(pattern));
+		}
+		Ok(())
+	}
+
+	/// Ignore `file` when walking `path`, if it's under it. Meant for hash
+	/// files kept inside the hashed directory, which needn't exist yet.
+	pub fn ignore_file(&mut self, path: &Path, file: &Path) {
+		let parent = match file.parent() {
+			Some(parent) if !parent.as_os_str().is_empty() => parent,
+			_ => Path::new("."),
+		};
+		if let (Ok(path), Ok(parent), Some(name)) = (path.canonicalize(), parent.canonicalize(), file.file_name())
+			&& let Ok(relative) = parent.join(name).strip_prefix(&path)
+		{
+			self.ignored_files.push(relative.to_owned());
+		}
+	}
+
+	/// Only walk the files git tracks under `path`, along with those of any
+	/// other path tracked this way.
+	pub fn track_git(&mut self, path: &Path) -> io::Result<()> {
+		let tracked = self.tracked.get_or_insert_default();
+		for file in git_tracked_files(path)? {
+			tracked.extend(file.ancestors().filter(|ancestor| !ancestor.as_os_str().is_empty()).map(Path::to_path_buf));
+		}
+		Ok(())
+	}
+
+	/// Name to store for `file` found under `path`.
+	pub fn name(&self, path: &Path, file: &Path) -> PathBuf {
+		let relative = || self.normalized(relative_name(&long_path(self.base(path)), file));
+		if !self.absolute_paths {
+			return relative();
+		}
+		if !self.relative_names.is_empty() {
+			let name = relative();
+			if self.relative_names.contains(&name) {
+				return name;
+			}
+		}
+		self.normalized(&short_path(file))
+	}
+
+	/// `name` brought to `normalize_unicode`.
+	fn normalized(&self, name: &Path) -> PathBuf {
+		match self.normalize_unicode {
+			Some(form) => form.normalize(name),
+			None => name.to_owned(),
+		}
+	}
+}
+
+/// Options controlling how hash files are read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+	/// Text encoding of the hash file.
+	pub encoding: ManifestEncoding,
+	/// Unicode normalization form loaded paths are brought to. Left as-is if
+	/// `None`.
+	pub normalize_unicode: Option<UnicodeForm>,
+	/// Read entries by the rules of `rclone hashsum`, taking filenames as they
+	/// are.
+	pub rclone: bool,
+	/// Read hash files as BSD mtree specs.
+	pub mtree: bool,
+	/// age identity files to decrypt age-encrypted hash files with.
+	pub identities: Vec<PathBuf>,
+}
+
+/// Options controlling how hash files are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+	/// Path separator used for stored names.
+	pub path_style: PathStyle,
+	/// Character starting comment lines.
+	pub comment_style: CommentStyle,
+	/// Comment lines written before the entries, without the comment prefix.
+	pub header: Vec<String>,
+	/// Comments written right before the entries of the named files, e.g.
+	/// why they have a placeholder hash.
+	pub notes: BTreeMap<PathBuf, String>,
+	/// Metadata written right before the entries of the named files.
+	pub metadata: BTreeMap<PathBuf, EntryMetadata>,
+	/// Hashes of the pieces of the named files, written right before their
+	/// entries.
+	pub pieces: BTreeMap<PathBuf, Pieces>,
+	/// How hashes are written.
+	pub hash_encoding: HashEncoding,
+	/// Algorithm the hashes were made with, named by multihashes and CIDs.
+	pub algorithm: Algorithm,
+	/// Write entries exactly as `rclone hashsum` prints them: lowercase
+	/// hashes, `/` separators and filenames as they are, and nothing else.
+	pub rclone: bool,
+	/// Write BSD mtree specs, with the type, size, time, mode, owner and
+	/// checksum of each file.
+	pub mtree: bool,
+	/// Encrypt hash files to these age or gpg recipients. Left in the clear
+	/// if empty.
+	pub encrypt_to: Vec<String>,
+}
+
+/// What happened while creating hashes, besides the hashes themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HashingReport {
+	/// Notes on files that weren't hashed like regular files, e.g. why they
+	/// have a placeholder hash.
+	pub notes: BTreeMap<PathBuf, String>,
+	/// Problems found along the way, like skipped symlink loops.
+	pub warnings: Vec<String>,
+	/// Metadata of the hashed files, with `WalkOptions::record_metadata`.
+	pub metadata: BTreeMap<PathBuf, EntryMetadata>,
+	/// Files whose hash was taken from `WalkOptions::recorded` instead of
+	/// reading them.
+	pub assumed: BTreeSet<PathBuf>,
+	/// Recorded and current metadata of the files whose metadata differs
+	/// from `WalkOptions::recorded`. Those whose size differs weren't read,
+	/// and have a placeholder hash.
+	pub drifted: BTreeMap<PathBuf, (EntryMetadata, EntryMetadata)>,
+	/// Recorded and current ownership of the files whose owner, group or
+	/// mode differ from `WalkOptions::recorded`, with
+	/// `WalkOptions::check_metadata`.
+	pub metadata_changed: BTreeMap<PathBuf, (Ownership, Ownership)>,
+	/// Recorded and current Windows attributes of the files whose
+	/// attributes or creation time differ from `WalkOptions::recorded`, with
+	/// `WalkOptions::check_metadata`.
+	pub attributes_changed: BTreeMap<PathBuf, (WindowsAttributes, WindowsAttributes)>,
+	/// Recorded and current targets of the symlinks whose target differs
+	/// from `WalkOptions::recorded`.
+	pub retargeted: BTreeMap<PathBuf, (String, String)>,
+	/// Amount of files walked, whether they were read or not.
+	pub files_scanned: usize,
+	/// Bytes of the files read to hash them.
+	pub bytes_hashed: u64,
+	/// Files left unread, as hashing stopped on SIGTERM.
+	pub unhashed: BTreeSet<PathBuf>,
+	/// Problems that fail the run, like special files found when they're an
+	/// error. The files are left out.
+	pub errors: Vec<String>,
+}
+
+/// Create subpath->hash mappings for a given path using a given algorithm.
+///
+/// Files that aren't hashed like regular files get a note in `report`.
 pub fn create_hashes(
 	path: &Path,
-	ignored_files: Vec<PathBuf>,
 	algo: Algorithm,
-	depth: Option<usize>,
-	follow_symlinks: bool,
+	options: &WalkOptions,
+	report: &mut HashingReport,
 ) -> BTreeMap<PathBuf, String> {
-	let mut walkdir = WalkDir::new(path).follow_links(follow_symlinks);
-	if let Some(depth) = depth {
-		walkdir = walkdir.max_depth(depth + 1);
-	}
-
 	let pb_style = ProgressStyle::default_bar()
 		.template("{prefix:.bold.dim} {spinner} {wide_bar} {pos:>7}/{len:7} ETA: {eta} - {msg}")
 		.unwrap()
@@ -72,47 +404,412 @@ pub fn create_hashes(
 
 	pb.enable_steady_tick(Duration::from_millis(80));
 	pb.set_message("Finding files to hash...");
-	let mut files: Vec<DirEntry> = walkdir
-		.into_iter()
-		.filter_entry(|e: &walkdir::DirEntry| {
-			let filename = relative_name(path, e.path());
-			match (ignored_files.iter().any(|f| f.as_path().eq(filename)), e.file_type().is_file()) {
-				(true, true) => {
-					// hashes.insert(mul_str("-", algo.hexlen()), filename);
-					false
-				}
-				(true, false) => false,
-				_ => true,
-			}
-		})
-		.flatten()
-		.filter(|e| e.file_type().is_file())
-		.collect();
+	// Listing directories is I/O too, so it's spread over the reading threads
+	let mut files: Vec<FileRecord> = match options.io_threads > 1 && path.is_dir() {
+		true => discover::walk_files_parallel(path, options, report, options.io_threads, &pb),
+		false => walk_files(path, options, report).collect(),
+	};
 
-	optimize_file_order::optimize_file_order(&mut files);
+	optimize_file_order::order_files(&mut files, options.order);
 
 	pb.reset();
 	pb.set_length(files.len() as u64);
 	pb.set_message("Hashing files...");
 
-	let hashes: BTreeMap<PathBuf, String> = files
+	// Names-only hashes aren't content hashes
+	let mut cache = options.cache.as_deref().filter(|_| !options.names_only).map(HashCache::load);
+	let mut checkpoint = options.checkpoint.as_deref().filter(|_| !options.names_only).and_then(|checkpoint| {
+		Checkpoint::open(checkpoint, algo, &options.hash_options, options.resume)
+			.map_err(|err| report.warnings.push(format!("Failed to open the checkpoint {:?}: {}", checkpoint, err)))
+			.ok()
+	});
+	// Hashes of files that needn't be read
+	let known: Vec<Option<String>> = files
+		.iter()
+		.map(|record| {
+			if options.names_only {
+				return Some(names_only_hash(record, algo, options.names_with_sizes));
+			}
+			let recorded = options
+				.recorded
+				.as_ref()
+				.and_then(|recorded| recorded_hash(recorded, options.name(path, record.path()), record, algo, options, report));
+			recorded
+				.or_else(|| options.unchanged.as_ref().and_then(|unchanged| unchanged.get(&options.name(path, record.path()), record)))
+				.or_else(|| checkpoint.as_ref().and_then(|checkpoint| checkpoint.get(&options.name(path, record.path()), record)))
+				.or_else(|| cache.as_mut().and_then(|cache| cache.get(record, algo)))
+		})
+		.collect();
+	report.files_scanned += files.len();
+	report.bytes_hashed += files
+		.iter()
+		.zip(&known)
+		.filter(|(record, known)| known.is_none() && record.entry.file_type().is_file())
+		.map(|(record, _)| record.len)
+		.sum::<u64>();
+	let archives: Vec<PathBuf> =
+		files.iter().filter(|record| archive::is_archive(record.path(), options)).map(|record| record.path().to_owned()).collect();
+	let mac_files: Vec<PathBuf> = match options.mac_metadata && !options.names_only {
+		true => files.iter().filter(|record| record.entry.file_type().is_file()).map(|record| record.path().to_owned()).collect(),
+		false => Vec::new(),
+	};
+	let mut hard_links = HardLinks::default();
+	// Files with a note weren't hashed like regular files, or not reliably
+	let mut on_hashed = |record: &FileRecord, (hash, note): &(String, Option<String>)| {
+		if let Some(checkpoint) = &mut checkpoint
+			&& note.is_none()
+		{
+			checkpoint.record(&options.name(path, record.path()), record, hash);
+		}
+	};
+	let results: Vec<Option<(String, Option<String>)>> = match options.io_threads.max(options.hash_threads) > 1 {
+		true => parallel::hash_entries_until(&files, known, algo, options, &pb, service::stopping, &mut on_hashed),
+		false => files
+			.iter()
+			.zip(known)
+			.progress_with(pb)
+			.map(|(e, known)| match known {
+				Some(hash) => Some((hash, None)),
+				None if service::stopping() => None,
+				None => {
+					let result = hash_entry(e, algo, options, &mut hard_links);
+					on_hashed(e, &result);
+					Some(result)
+				}
+			})
+			.collect(),
+	};
+	if let Some(checkpoint) = checkpoint
+		&& let Err(err) = checkpoint.finish()
+	{
+		report.warnings.push(format!("Failed to write the checkpoint: {}", err));
+	}
+	// Files left unread when stopped are left out
+	let (files, results): (Vec<FileRecord>, Vec<(String, Option<String>)>) = files
 		.into_iter()
-		.progress_with(pb)
-		.map(|e| {
-			let value = hash_file(algo, e.path());
-			let filename = relative_name(path, e.path());
-			(filename.to_owned(), value)
+		.zip(results)
+		.filter_map(|(record, result)| match result {
+			Some(result) => Some((record, result)),
+			None => {
+				report.unhashed.insert(options.name(path, record.path()));
+				None
+			}
+		})
+		.unzip();
+	if let Some(cache) = &mut cache {
+		// Files with a note weren't hashed like regular files, or not reliably
+		for (record, (hash, note)) in files.iter().zip(&results) {
+			if note.is_none() {
+				cache.insert(record, algo, hash);
+			}
+		}
+		if let Err(err) = cache.save() {
+			report.warnings.push(format!("Failed to save the hash cache: {}", err));
+		}
+	}
+	let mut hashes: BTreeMap<PathBuf, String> = files
+		.into_iter()
+		.zip(results)
+		.map(|(e, (value, note))| {
+			let filename = options.name(path, e.path());
+			report.warnings.extend(note_warning(&filename, &note));
+			if let Some(recorded) = &options.recorded
+				&& let Some(new) = note.as_deref().and_then(|note| note.strip_prefix(SYMLINK_NOTE_PREFIX))
+				&& let Some(was) = recorded.symlink_target(&filename)
+				&& was != new
+			{
+				report.retargeted.insert(filename.clone(), (was.to_owned(), new.to_owned()));
+			}
+			match note {
+				Some(note) => {
+					report.notes.insert(filename.clone(), note);
+				}
+				None if options.record_metadata => {
+					if let Some(metadata) = EntryMetadata::of(&e, options.check_metadata) {
+						report.metadata.insert(filename.clone(), metadata);
+					}
+				}
+				None => {}
+			}
+			(filename, value)
 		})
 		.collect();
+	for archive in archives {
+		let name = options.name(path, &archive);
+		// Archives that weren't read, or not reliably, aren't looked inside
+		if !report.notes.contains_key(&name) && !report.unhashed.contains(&name) {
+			archive::hash_members(&archive, &name, algo, options, &mut hashes, report);
+		}
+	}
+	for file in mac_files {
+		let name = options.name(path, &file);
+		if report.unhashed.contains(&name) {
+			continue;
+		}
+		mac::hash_mac_metadata(&file, &name, algo, options, &mut hashes, report);
+	}
+	// Files left unwalked for not having changed are kept as they were
+	if let Some(unchanged) = &options.unchanged {
+		unchanged.keep(&mut hashes, report);
+	}
 	hashes
 }
 
+/// Hash of the file stored as `name` known from its recorded metadata
+/// without reading it, if any, noting how its metadata changed in `report`.
+fn recorded_hash(
+	recorded: &RecordedMetadata,
+	name: PathBuf,
+	record: &FileRecord,
+	algo: Algorithm,
+	options: &WalkOptions,
+	report: &mut HashingReport,
+) -> Option<String> {
+	let ((was, hash), new) = (recorded.get(&name)?, EntryMetadata::of(record, true)?);
+	if options.check_metadata
+		&& let (Some(was), Some(new)) = (was.ownership, new.ownership)
+		&& was != new
+	{
+		report.metadata_changed.insert(name.clone(), (was, new));
+	}
+	if options.check_metadata
+		&& let (Some(was), Some(new)) = (was.attributes, new.attributes)
+		&& was != new
+	{
+		report.attributes_changed.insert(name.clone(), (was, new));
+	}
+	if was.drifted(&new) {
+		let size_changed = was.size != new.size;
+		report.drifted.insert(name, (*was, new));
+		// It differs whatever it holds
+		return size_changed.then(|| placeholder_hash(algo));
+	}
+	if recorded.assumes(&name) {
+		report.assumed.insert(name);
+		return Some(hash.clone());
+	}
+	None
+}
+
+/// Walk the specified path, yielding the files that are not ignored, and
+/// special files unless they're skipped, with their metadata.
+///
+/// Symlink loops are skipped with a warning in `report`, and special files
+/// that are an error with an error.
+fn walk_files<'a>(
+	path: &'a Path,
+	options: &'a WalkOptions,
+	report: &'a mut HashingReport,
+) -> impl Iterator<Item = FileRecord> + 'a {
+	let root = long_path(path).into_owned();
+	let loop_root = root.clone();
+	let mut walkdir = WalkDir::new(&root).follow_links(options.follow_symlinks);
+	if let Some(depth) = options.depth {
+		walkdir = walkdir.max_depth(depth + 1);
+	}
+
+	walkdir
+		.into_iter()
+		.filter_entry(move |e: &walkdir::DirEntry| !is_ignored(&root, e, e.depth(), options))
+		.filter_map(move |entry| match entry {
+			Ok(entry) => is_walked_file(&entry, options, &mut report.errors).then(|| FileRecord::new(entry)),
+			Err(err) => {
+				if let (Some(link), Some(ancestor)) = (err.path(), err.loop_ancestor()) {
+					report.warnings.push(loop_warning(&loop_root, link, ancestor));
+				}
+				None
+			}
+		})
+}
+
+/// Whether a walked entry `depth` levels below `root` is left out by the
+/// ignored files and patterns, for being hidden, for not being tracked, or
+/// for not having changed.
+fn is_ignored(root: &Path, e: &DirEntry, depth: usize, options: &WalkOptions) -> bool {
+	let filename = relative_name(root, e.path());
+	options.ignored_files.iter().any(|f| f.as_path().eq(filename))
+		|| (depth > 0 && options.ignored_patterns.iter().any(|p| p.matches(filename, e.file_type().is_dir())))
+		|| (depth > 0 && options.skip_hidden && is_hidden(e))
+		|| (depth > 0 && options.tracked.as_ref().is_some_and(|tracked| !tracked.contains(filename)))
+		|| (depth > 0 && options.unchanged.as_ref().is_some_and(|unchanged| unchanged.skips(filename, e.file_type().is_dir())))
+}
+
+/// Whether a walked entry that isn't ignored gets hashed: regular files,
+/// recorded symlinks, recorded empty directories, and special files unless
+/// they're skipped. Special files that are an error are noted in `errors`,
+/// and left out.
+fn is_walked_file(e: &DirEntry, options: &WalkOptions, errors: &mut Vec<String>) -> bool {
+	match special_kind(e.file_type()) {
+		Some(kind) => match options.special_files {
+			SpecialFiles::Skip => false,
+			SpecialFiles::Record => true,
+			SpecialFiles::Error => {
+				errors.push(format!("Found {} {:?}, use --special-files to skip or record it", kind, e.path()));
+				false
+			}
+		},
+		None if e.file_type().is_dir() => options.record_empty_dirs && e.depth() > 0 && is_empty_dir(e.path()),
+		None => e.file_type().is_file() || (options.record_symlinks && e.file_type().is_symlink()),
+	}
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+	read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// Warning for a symlink under `root` leading back to one of its ancestors.
+fn loop_warning(root: &Path, link: &Path, ancestor: &Path) -> String {
+	let relative = |path: &'_ Path| match path.strip_prefix(root) {
+		Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+		Ok(relative) => relative.to_owned(),
+		Err(_) => path.to_owned(),
+	};
+	format!("Symlink loop skipped: {:?} leads back to {:?}", relative(link), relative(ancestor))
+}
+
+/// Header line of hash files made with `HashOptions::text_mode`, telling
+/// verify to read the files as text too.
+pub static TEXT_MODE_HEADER: &str = "line endings: CRLF read as LF";
+
+/// Hash recorded for files that weren't hashed.
+pub fn placeholder_hash(algo: Algorithm) -> String {
+	mul_str("-", algo.hexlen())
+}
+
+/// Hash a walked entry, with a note if it isn't hashed like a regular file.
+///
+/// Symlinks are hashed by their target, so re-pointing them is detected.
+fn hash_entry(
+	record: &FileRecord,
+	algo: Algorithm,
+	options: &WalkOptions,
+	hard_links: &mut HardLinks,
+) -> (String, Option<String>) {
+	if record.entry.file_type().is_symlink() {
+		return match read_link(record.path()) {
+			Ok(target) => (
+				hash_reader(algo, &mut target.as_os_str().as_encoded_bytes()),
+				Some(format!("{}{}", SYMLINK_NOTE_PREFIX, target.display())),
+			),
+			Err(err) => (placeholder_hash(algo), Some(format!("skipped: {}", err))),
+		};
+	}
+
+	if let Some(note) = skip_note(record, options) {
+		return (placeholder_hash(algo), Some(note));
+	}
+
+	let hash = match options.hash_each_hard_link {
+		true => hash_with_retries(algo, record.path(), options),
+		false => hard_links.hash(record, || hash_with_retries(algo, record.path(), options)),
+	};
+	hashed(hash, algo)
+}
+
+/// Hash and note of a regular file, given its hash and whether its size and
+/// modification time stayed the same while it was hashed.
+fn hashed(hash: io::Result<(String, bool)>, algo: Algorithm) -> (String, Option<String>) {
+	match hash {
+		Ok((hash, true)) => (hash, None),
+		Ok((hash, false)) => (hash, Some(UNSTABLE_NOTE.to_owned())),
+		Err(err) => (placeholder_hash(algo), Some(format!("{}{}", ERROR_NOTE_PREFIX, err))),
+	}
+}
+
+/// Hash a file, and tell whether it stayed the same while hashed, reading it
+/// again up to `options.retries` times if reading fails, e.g. on a network
+/// filesystem timing out.
+fn hash_with_retries(algo: Algorithm, path: &Path, options: &WalkOptions) -> io::Result<(String, bool)> {
+	retry_hash(hash_file_checked(algo, path, &options.hash_options), algo, path, options)
+}
+
+/// Hash a file again up to `options.retries` times while `result`, that of
+/// the first attempt, is a failure. Missing files aren't retried.
+fn retry_hash(
+	mut result: io::Result<(String, bool)>,
+	algo: Algorithm,
+	path: &Path,
+	options: &WalkOptions,
+) -> io::Result<(String, bool)> {
+	for _ in 0..options.retries {
+		match &result {
+			Err(err) if err.kind() != io::ErrorKind::NotFound => {
+				thread::sleep(options.retry_delay);
+				result = hash_file_checked(algo, path, &options.hash_options);
+			}
+			_ => break,
+		}
+	}
+	result
+}
+
+/// Note on files whose size or modification time changed while they were
+/// being hashed, so their hash likely won't verify.
+static UNSTABLE_NOTE: &str = "unstable: changed while hashing";
+
+/// Start of the note on files that couldn't be read, followed by the error.
+static ERROR_NOTE_PREFIX: &str = "error: ";
+
+/// Note on recorded empty directories.
+static EMPTY_DIR_NOTE: &str = "empty directory";
 
-/// Create hash mappings for given files using a given algorithm
+/// Start of the note on recorded symlinks, followed by their target.
+static SYMLINK_NOTE_PREFIX: &str = "symlink -> ";
+
+/// Warning for a file that was noted as unstable or failed to be read.
+fn note_warning(filename: &Path, note: &Option<String>) -> Option<String> {
+	let note = note.as_deref()?;
+	if note == UNSTABLE_NOTE {
+		return Some(format!("Unstable, changed while being hashed: {:?}", filename));
+	}
+	note.strip_prefix(ERROR_NOTE_PREFIX)
+		.map(|err| format!("Failed to read {:?}: {}", filename, err))
+}
+
+/// Why the file shouldn't be hashed, if it shouldn't.
+fn skip_note(record: &FileRecord, options: &WalkOptions) -> Option<String> {
+	if record.entry.file_type().is_dir() {
+		return Some(EMPTY_DIR_NOTE.to_owned());
+	}
+	if let Some(kind) = special_kind(record.entry.file_type()) {
+		return Some(format!("skipped: {}", kind));
+	}
+	if let Some(limit) = options.skip_larger_than
+		&& record.len > limit
+	{
+		return Some(format!("skipped: size, {} bytes", record.len));
+	}
+	None
+}
+
+/// Whether the entry is a dotfile or has the hidden attribute.
+#[cfg(windows)]
+fn is_hidden(entry: &DirEntry) -> bool {
+	use std::os::windows::fs::MetadataExt;
+
+	const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+	entry.file_name().to_string_lossy().starts_with('.')
+		|| entry.metadata().is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+/// Whether the entry is a dotfile.
+#[cfg(not(windows))]
+fn is_hidden(entry: &DirEntry) -> bool {
+	entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Create hash mappings for given files using a given algorithm, reading
+/// them as set by `options`
+///
+/// Relative files are looked up in `path`. Files keep the names they were
+/// given. Those that can't be read, even after `options.retries` more tries,
+/// get a placeholder hash, and a note and warning in `report`.
 pub fn create_hashes_for_files(
 	path: &Path,
 	files: Vec<PathBuf>,
 	algo: Algorithm,
+	options: &WalkOptions,
+	report: &mut HashingReport,
 ) -> BTreeMap<PathBuf, String> {
 
 	let pb_style = ProgressStyle::default_bar()
@@ -124,11 +821,13 @@ pub fn create_hashes_for_files(
 	pb.enable_steady_tick(Duration::from_millis(80));
 	pb.set_message("Finding files to hash...");
 
-	let files: Vec<PathBuf> = files
+	// Absolute files keep their name, relative ones are looked up in `path`
+	let files: Vec<(PathBuf, PathBuf)> = files
 		.into_iter()
 		.filter_map(|f| {
-			let p = if f.is_relative() { path.join(f) } else { f };
-			if p.is_file() {Some(p)} else {None}
+			let p = if f.is_relative() { path.join(&f) } else { f.clone() };
+			let p_long = long_path(&p);
+			if p_long.is_file() || (p_long.is_dir() && is_empty_dir(&p_long)) {Some((f, p))} else {None}
 		})
 		.collect();
 
@@ -139,55 +838,355 @@ pub fn create_hashes_for_files(
 	files
 		.into_iter()
 		.progress_with(pb)
-		.map(|e| {
-			let value = hash_file(algo, e.as_path());
-			let filename = relative_name(path, e.as_path());
-			(filename.to_owned(), value)
+		.map(|(filename, e)| {
+			// Recorded empty directories
+			let (value, note) = match long_path(&e).is_dir() {
+				true => (placeholder_hash(algo), None),
+				false => hashed(hash_with_retries(algo, &e, options), algo),
+			};
+			report.warnings.extend(note_warning(&filename, &note));
+			if let Some(note) = note {
+				report.notes.insert(filename.clone(), note);
+			}
+			(filename, value)
 		})
 		.collect::<BTreeMap<PathBuf, String>>()
 }
 
 
 /// Serialise the specified hashes to the specified output file.
-pub fn write_hashes(out_file: &Path, hashes: BTreeMap<PathBuf, String>) -> i32 {
-	let file = File::create(&out_file).unwrap();
-	let mut out = TabWriter::new(file);
-
-	// hashes.insert(
-	// 	out_file.to_string_lossy().to_string(),
-	// 	mul_str("-", algo.hexlen()),
-	// );
+///
+/// With `encrypt_to` recipients, the hash file is put together in memory and
+/// only its encryption written.
+pub fn write_hashes(out_file: &Path, hashes: BTreeMap<PathBuf, String>, options: &WriteOptions) -> i32 {
+	let mut clear = Vec::new();
+	let file: Box<dyn Write> = match options.encrypt_to.is_empty() {
+		true => Box::new(File::create(long_path(out_file)).unwrap()),
+		false => Box::new(&mut clear),
+	};
+	// rclone keeps tabs in filenames, and mtree lines have none
+	let mut out: Box<dyn Write> = match options.rclone || options.mtree {
+		true => Box::new(io::BufWriter::new(file)),
+		false => Box::new(TabWriter::new(file)),
+	};
+
+	write_header(&mut out, options);
 	for (fname, hash) in hashes {
-		writeln!(&mut out, "{}  {}", hash, fname.to_string_lossy()).unwrap();
+		write_entry(&mut out, &hash, &fname, options);
 	}
 
 	out.flush().expect("Failed to flush output file");
+	drop(out);
+	if !options.encrypt_to.is_empty()
+		&& let Err(err) = encrypt(&clear, &options.encrypt_to, out_file)
+	{
+		eprintln!("Failed to encrypt {:?}: {}", out_file, err);
+		return 1;
+	}
 	0
 }
 
+/// Write the header comments of a hash file.
+fn write_header<W: Write>(out: &mut W, options: &WriteOptions) {
+	if options.mtree {
+		return mtree::write_header(out, options);
+	}
+	if options.rclone {
+		return;
+	}
+	for line in &options.header {
+		options.comment_style.write(out, line);
+	}
+}
+
+/// Write a single `HASH  FILENAME` line, preceded by its metadata and note
+/// if any. Filenames that would break the line are escaped and the line is
+/// prefixed with `\`, like coreutils does.
+fn write_entry<W: Write>(out: &mut W, hash: &str, filename: &Path, options: &WriteOptions) {
+	if options.mtree {
+		return mtree::write_entry(out, hash, filename, options);
+	}
+	// rclone lists files with a hash only, and nothing about them
+	if options.rclone {
+		if !hash.starts_with('-') {
+			writeln!(out, "{}  {}", hash.to_lowercase(), PathStyle::Unix.format(filename)).unwrap();
+		}
+		return;
+	}
+	if let Some(metadata) = options.metadata.get(filename) {
+		options.comment_style.write(out, &metadata.to_string());
+	}
+	for piece in options.pieces.get(filename).into_iter().flat_map(Pieces::comments) {
+		options.comment_style.write(out, &piece);
+	}
+	if let Some(note) = options.notes.get(filename) {
+		options.comment_style.write(out, note);
+	}
+	let hash = options.hash_encoding.encode(hash, options.algorithm);
+	let fname = options.path_style.format(filename);
+	match escape_filename(&fname, options.path_style.backslash_is_separator()) {
+		Some(escaped) => writeln!(out, "\\{}  {}", hash, escaped).unwrap(),
+		None => writeln!(out, "{}  {}", hash, fname).unwrap(),
+	}
+}
+
 /// Read uppercased hashes with `write_hashes()` from the specified path or fail
 /// with line numbers not matching pattern.
-pub fn read_hashes(file: &Path) -> Result<BTreeMap<PathBuf, String>, Error> {
+///
+/// Shard indices written by `write_sharded_hashes()` are expanded, loading
+/// every shard.
+pub fn read_hashes(file: &Path, options: &ReadOptions) -> Result<BTreeMap<PathBuf, String>, Error> {
 	let mut hashes = BTreeMap::new();
 
-	let reader = BufReader::new(File::open(&file).unwrap());
-	for line in reader.lines() {
-		match line {
-			Ok(line) => {
-				if line.is_empty() {
-					continue;
-				}
-				// Skip comment lines
-				if line.trim_start().starts_with(";"){
-					continue;
+	if let Some(shards) = read_shard_index(file)? {
+		for shard in shards {
+			hashes.extend(read_hashes(&shard, options)?);
+		}
+		return Ok(hashes);
+	}
+
+	for entry in stream_hashes(file, options)? {
+		let (file, hash) = entry?;
+		hashes.insert(file, hash);
+	}
+
+	Ok(hashes)
+}
+
+/// Names of the entries of the specified hashes file stored as relative
+/// paths, if any entry is stored as an absolute one, so that walked files
+/// can be named the way their own entry is, or `None` if none are.
+///
+/// Shard indices are followed to all their shards.
+pub fn relative_entries(file: &Path, options: &ReadOptions) -> Result<Option<BTreeSet<PathBuf>>, Error> {
+	let files = read_shard_index(file)?.unwrap_or_else(|| vec![file.to_owned()]);
+	let (mut relative, mut any_absolute) = (BTreeSet::new(), false);
+	for file in files {
+		for entry in stream_hashes(&file, options)? {
+			let (name, _) = entry?;
+			match name.is_absolute() {
+				true => any_absolute = true,
+				false => {
+					relative.insert(name);
 				}
-				try_contains(&line, &mut hashes)?;
 			}
-			Err(err) => return Err(Error::HashesFileParsingFailure(err.to_string())),
 		}
 	}
+	Ok(any_absolute.then_some(relative))
+}
 
-	Ok(hashes)
+/// Get the comment lines before the first entry of the specified hashes file,
+/// without their comment prefix.
+pub fn read_header(file: &Path, options: &ReadOptions) -> Result<Vec<String>, Error> {
+	let mut reader = stream_hashes(file, options)?;
+	if let Some(Err(err)) = reader.next() {
+		return Err(err);
+	}
+	Ok(reader.header)
+}
+
+/// Get the algorithm named by the first entry of the specified hashes file
+/// with a hash, if it's a multihash, CID or OCI digest.
+///
+/// Shard indices are followed to their first shard.
+pub fn read_named_algorithm(file: &Path, options: &ReadOptions) -> Result<Option<Algorithm>, Error> {
+	if let Some(shards) = read_shard_index(file)? {
+		return match shards.first() {
+			Some(shard) => read_named_algorithm(shard, options),
+			None => Ok(None),
+		};
+	}
+	let mut reader = stream_hashes(file, options)?;
+	while let Some(entry) = reader.next() {
+		// Placeholder hashes don't name one
+		if !entry?.1.starts_with('-') {
+			return Ok(reader.algorithm);
+		}
+	}
+	Ok(None)
+}
+
+/// Open the specified hashes file for reading one entry at a time, without
+/// loading it whole.
+///
+/// Shard indices are not expanded, use `read_shard_index()` for those.
+/// UTF-16 files are recognised by their BOM and decoded transparently, and
+/// age or gpg encrypted ones are decrypted in memory.
+/// mtree specs are loaded whole, as their entries depend on the lines before
+/// them.
+pub fn stream_hashes(file: &Path, options: &ReadOptions) -> Result<HashesReader<Box<dyn BufRead>>, Error> {
+	let to_error = |err: io::Error| Error::HashesFileParsingFailure(err.to_string());
+	let mut reader: BufReader<Box<dyn Read>> = BufReader::new(Box::new(File::open(long_path(file)).map_err(to_error)?));
+	if let Some(encryption) = Encryption::detect(reader.fill_buf().map_err(to_error)?) {
+		let clear = decrypt(file, encryption, &options.identities).map_err(to_error)?;
+		reader = BufReader::new(Box::new(io::Cursor::new(clear)));
+	}
+
+	let (mut reader, mut encoding): (Box<dyn BufRead>, _) = match utf16_bom(reader.fill_buf().map_err(to_error)?) {
+		Some(big_endian) => {
+			reader.consume(2);
+			let reader = BufReader::new(Utf16Reader::new(reader, big_endian));
+			(Box::new(reader), ManifestEncoding::Utf8)
+		}
+		None => (Box::new(reader), options.encoding),
+	};
+
+	let mut header = Vec::new();
+	let mut mtree = None;
+	let mut algorithm = None;
+	if options.mtree {
+		let mut text = Vec::new();
+		reader.read_to_end(&mut text).map_err(to_error)?;
+		let text = encoding.decode(&text).ok_or_else(|| Error::HashesFileParsingFailure(file.display().to_string()))?;
+		let spec = mtree::parse_spec(text.trim_start_matches(BOM))?;
+		(header, algorithm) = (spec.header, spec.algorithm);
+		mtree = Some(spec.entries.into_iter());
+	}
+
+	Ok(HashesReader {
+		reader,
+		buffer: Vec::new(),
+		header,
+		entries: 0,
+		pending_metadata: None,
+		metadata: None,
+		pending_target: None,
+		symlink_target: None,
+		pending_pieces: None,
+		pieces: None,
+		algorithm,
+		rclone: options.rclone,
+		mtree,
+		encoding,
+		normalize_unicode: options.normalize_unicode,
+	})
+}
+
+/// Iterator over the entries of a hashes file, in file order.
+pub struct HashesReader<R> {
+	reader: R,
+	buffer: Vec<u8>,
+	header: Vec<String>,
+	entries: usize,
+	/// Metadata read for the next entry.
+	pending_metadata: Option<EntryMetadata>,
+	/// Metadata of the last entry read.
+	metadata: Option<EntryMetadata>,
+	/// Symlink target noted for the next entry.
+	pending_target: Option<String>,
+	/// Symlink target noted for the last entry read.
+	symlink_target: Option<String>,
+	/// Pieces read for the next entry.
+	pending_pieces: Option<Pieces>,
+	/// Pieces of the last entry read.
+	pieces: Option<Pieces>,
+	/// Algorithm named by the last entry read, if it's a multihash, CID or
+	/// OCI digest.
+	algorithm: Option<Algorithm>,
+	rclone: bool,
+	/// Entries of an mtree spec, loaded whole.
+	mtree: Option<std::vec::IntoIter<mtree::MtreeEntry>>,
+	encoding: ManifestEncoding,
+	normalize_unicode: Option<UnicodeForm>,
+}
+
+impl<R> HashesReader<R> {
+	/// Comment lines read before the first entry, without their comment
+	/// prefix.
+	pub fn header(&self) -> &[String] {
+		&self.header
+	}
+
+	/// Metadata recorded for the last entry read, if any.
+	pub fn metadata(&self) -> Option<EntryMetadata> {
+		self.metadata
+	}
+
+	/// Target noted for the last entry read, if it's a recorded symlink.
+	pub fn symlink_target(&self) -> Option<&str> {
+		self.symlink_target.as_deref()
+	}
+
+	/// Hashes of the pieces of the last entry read, if recorded.
+	pub fn pieces(&self) -> Option<&Pieces> {
+		self.pieces.as_ref()
+	}
+
+	/// Algorithm named by the last entry read, if it's a multihash, CID or
+	/// OCI digest. Its hash is the hexadecimal digest.
+	pub fn algorithm(&self) -> Option<Algorithm> {
+		self.algorithm
+	}
+}
+
+impl<R: BufRead> Iterator for HashesReader<R> {
+	type Item = Result<(PathBuf, String), Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(entries) = &mut self.mtree {
+			let entry = entries.next()?;
+			self.entries += 1;
+			self.metadata = entry.metadata;
+			self.symlink_target = entry.target;
+			let file = match self.normalize_unicode {
+				Some(form) => form.normalize(&entry.file),
+				None => entry.file,
+			};
+			return Some(Ok((file, entry.hash)));
+		}
+		loop {
+			self.buffer.clear();
+			match self.reader.read_until(b'\n', &mut self.buffer) {
+				Ok(0) => return None,
+				Ok(_) => {}
+				Err(err) => return Some(Err(Error::HashesFileParsingFailure(err.to_string()))),
+			}
+			let Some(line) = self.encoding.decode(&self.buffer) else {
+				let line = String::from_utf8_lossy(&self.buffer).trim_end().to_owned();
+				return Some(Err(Error::HashesFileParsingFailure(line)));
+			};
+			// Tolerate a UTF-8 BOM, CRLF line endings and trailing whitespace,
+			// but for rclone's, which keeps the whitespace of filenames
+			let line = line.trim_start_matches(BOM);
+			let line = match self.rclone {
+				true => line.trim_end_matches(['\r', '\n']),
+				false => line.trim_end(),
+			};
+			if line.is_empty() {
+				continue;
+			}
+			// Skip comment lines, keeping the ones heading the file
+			if let Some(comment) = comment_text(line) {
+				if comment.starts_with(METADATA_PREFIX) {
+					self.pending_metadata = EntryMetadata::parse(comment);
+				} else if let Some(target) = comment.strip_prefix(SYMLINK_NOTE_PREFIX) {
+					self.pending_target = Some(target.to_owned());
+				} else if !Pieces::parse_into(comment, &mut self.pending_pieces) && self.entries == 0 {
+					self.header.push(comment.to_owned());
+				}
+				continue;
+			}
+			let entry = match self.rclone {
+				true => match parse_rclone_line(line).transpose() {
+					Some(entry) => entry.map(|(file, hash)| (file, hash, None)),
+					// Files rclone couldn't hash have nothing to compare
+					None => continue,
+				},
+				false => parse_line(line),
+			};
+			self.entries += 1;
+			self.metadata = self.pending_metadata.take();
+			self.symlink_target = self.pending_target.take();
+			self.pieces = self.pending_pieces.take();
+			return Some(entry.map(|(file, hash, algorithm)| {
+				self.algorithm = algorithm;
+				match self.normalize_unicode {
+					Some(form) => (form.normalize(&file), hash),
+					None => (file, hash),
+				}
+			}));
+		}
+	}
 }
 
 
@@ -215,23 +1214,74 @@ static LINE_RGX1: LazyLock<Regex> = LazyLock::new(||
 static LINE_RGX2: LazyLock<Regex> = LazyLock::new(|| 
 	Regex::new(r"(?i)^(.+?)\t{0,}\s{1,}([[:xdigit:]-]+)$").unwrap());
 
+/// Regex matching lines where a multihash, CID or OCI digest appears first,
+/// followed by the filename, as written with `HashEncoding::Multihash`,
+/// `Cid` or `Oci`.
+///
+/// - Capture group 1: the hash (letters, digits, and `:`, `-`, `+`, `.` or
+///   `_` of OCI digests).
+/// - Capture group 2: the filename/path (non-greedy to the line end).
+/// - Example matches: `bafkreih...  path/to/file.txt` or
+///   `sha256:e3b0...  path/to/file.txt`.
+static MULTIHASH_RGX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([[:alnum:]:+._-]+)\s+(.+?)$").unwrap());
+
+/// Filename, algorithm and hexadecimal digest of a line starting with a
+/// multihash, CID or OCI digest.
+fn parse_multihash_line(line: &str) -> Option<(&str, Algorithm, String)> {
+	let captures = MULTIHASH_RGX.captures(line)?;
+	let (algo, hash) = multihash::decode_named_hash(captures.get(1)?.as_str())?;
+	Some((captures.get(2)?.as_str(), algo, hash))
+}
 
-fn try_contains(line: &str, hashes: &mut BTreeMap<PathBuf, String>) -> Result<(), Error> {
+/// Filename and uppercased hash of an entry line, along with the algorithm
+/// named by its hash if it's a multihash, CID or OCI digest.
+fn parse_line(line: &str) -> Result<(PathBuf, String, Option<Algorithm>), Error> {
+	// Lines written with an escaped filename
+	if let Some(escaped_line) = line.strip_prefix('\\') {
+		if let Some((escaped, algo, hash)) = parse_multihash_line(escaped_line) {
+			let escaped = escaped.strip_prefix('*').unwrap_or(escaped);
+			let file = unescape_filename(escaped).ok_or_else(|| Error::HashesFileParsingFailure(line.to_owned()))?;
+			return Ok((PathBuf::from(file), hash, Some(algo)));
+		}
+		let captures = LINE_RGX1
+			.captures(escaped_line)
+			.ok_or_else(|| Error::HashesFileParsingFailure(line.to_owned()))?;
+		let escaped = captures[2].strip_prefix('*').unwrap_or(&captures[2]);
+		let file = unescape_filename(escaped).ok_or_else(|| Error::HashesFileParsingFailure(line.to_owned()))?;
+		let hash = captures[1].to_uppercase();
+		return Ok((PathBuf::from(file), hash, None));
+	}
+	if let Some((file, algo, hash)) = parse_multihash_line(line) {
+		return Ok((filepath_parser(file), hash, Some(algo)));
+	}
 	if let Some(captures) = LINE_RGX1.captures(line) {
 		let file = filepath_parser(&captures[2]);
 		let hash = captures[1].to_uppercase();
-		hashes.insert(file, hash);
-		return Ok(());
+		return Ok((file, hash, None));
 	}
 	if let Some(captures) = LINE_RGX2.captures(line) {
 		let file = filepath_parser(&captures[1]);
 		let hash = captures[2].to_uppercase();
-		hashes.insert(file, hash);
-		return Ok(());
+		return Ok((file, hash, None));
 	}
 	Err(Error::HashesFileParsingFailure(line.to_owned()))
 }
 
+/// Filename and uppercased hash of a line as printed by `rclone hashsum`: the
+/// hash, right-aligned, then two spaces, or a space and `*`, and the filename
+/// as it is. `None` for files rclone couldn't hash, with `UNSUPPORTED` or
+/// `ERROR` for a hash.
+fn parse_rclone_line(line: &str) -> Result<Option<(PathBuf, String)>, Error> {
+	let failure = || Error::HashesFileParsingFailure(line.to_owned());
+	let (hash, rest) = line.trim_start_matches(' ').split_once(' ').ok_or_else(failure)?;
+	let file = rest.strip_prefix([' ', '*']).filter(|file| !file.is_empty()).ok_or_else(failure)?;
+	match hash {
+		"UNSUPPORTED" | "ERROR" => Ok(None),
+		_ if hash.chars().all(|c| c.is_ascii_hexdigit()) => Ok(Some((PathBuf::from(file), hash.to_uppercase()))),
+		_ => Err(failure()),
+	}
+}
+
 fn filepath_parser(raw: &str) -> PathBuf {
 	// Basic cleanup
 	let mut s = raw.trim().replace('*', "");
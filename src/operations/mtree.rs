@@ -0,0 +1,293 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! BSD mtree specifications: one line per file naming it, then
+//! `keyword=value` pairs for its type, size, mode and checksums, with `/set`
+//! lines giving defaults for the lines after them.
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+use super::{
+	EMPTY_DIR_NOTE, EntryMetadata, Ownership, PathStyle, SYMLINK_NOTE_PREFIX, WriteOptions, metadata::parse_time,
+	placeholder_hash,
+};
+use crate::{Algorithm, Error, hash_reader};
+
+/// First line of the specs written, as libarchive does.
+static SIGNATURE: &str = "#mtree";
+
+/// Checksum keywords of the algorithms mtree knows, strongest first.
+static DIGEST_KEYWORDS: [(Algorithm, &str, &str); 5] = [
+	(Algorithm::SHA2512, "sha512digest", "sha512"),
+	(Algorithm::SHA2384, "sha384digest", "sha384"),
+	(Algorithm::SHA2256, "sha256digest", "sha256"),
+	(Algorithm::SHA1, "sha1digest", "sha1"),
+	(Algorithm::MD5, "md5digest", "md5"),
+];
+
+/// The mtree keyword holding checksums made with `algo`, if any.
+pub fn mtree_keyword(algo: Algorithm) -> Option<&'static str> {
+	DIGEST_KEYWORDS.iter().find(|(known, _, _)| *known == algo).map(|(_, keyword, _)| *keyword)
+}
+
+/// An entry of a spec, as a hash file entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct MtreeEntry {
+	pub(super) file: PathBuf,
+	/// Uppercased hash, a placeholder if it has no checksum, or the hash of
+	/// the target of symlinks.
+	pub(super) hash: String,
+	/// Size, time, owner, group and mode of regular files listing them.
+	pub(super) metadata: Option<EntryMetadata>,
+	/// Target of symlinks.
+	pub(super) target: Option<String>,
+}
+
+/// A parsed spec.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct MtreeSpec {
+	/// Comment lines before the first entry, without their `#`.
+	pub(super) header: Vec<String>,
+	pub(super) entries: Vec<MtreeEntry>,
+	/// Algorithm of the checksums used, the strongest one of the first file
+	/// with any.
+	pub(super) algorithm: Option<Algorithm>,
+}
+
+/// Parse the text of a spec, in the full path form `mtree -C` and libarchive
+/// write or the hierarchical one of `mtree -c`, where entries are relative to
+/// the last directory entered and `..` leaves it.
+///
+/// Directories holding entries are left out, as hash files only list empty
+/// ones. Other file types than regular files, directories and symlinks get a
+/// placeholder hash.
+pub(super) fn parse_spec(text: &str) -> Result<MtreeSpec, Error> {
+	let mut header = Vec::new();
+	let mut defaults = BTreeMap::new();
+	let mut cwd = PathBuf::new();
+	let mut listed: Vec<(PathBuf, BTreeMap<String, String>, String)> = Vec::new();
+
+	let mut lines = text.lines();
+	while let Some(line) = lines.next() {
+		// Long lines are continued with a trailing backslash
+		let mut line = line.trim().to_owned();
+		while let Some(start) = line.strip_suffix('\\') {
+			line = format!("{} {}", start, lines.next().unwrap_or_default().trim());
+		}
+		if line.is_empty() {
+			continue;
+		}
+		if let Some(comment) = line.strip_prefix('#') {
+			if listed.is_empty() && !line.starts_with(SIGNATURE) {
+				header.push(comment.strip_prefix(' ').unwrap_or(comment).to_owned());
+			}
+			continue;
+		}
+		let mut words = line.split_whitespace();
+		let name = words.next().unwrap_or_default();
+		match name {
+			"/set" => defaults.extend(words.filter_map(keyword)),
+			"/unset" => {
+				for word in words {
+					match word {
+						"all" => defaults.clear(),
+						_ => {
+							defaults.remove(word);
+						}
+					}
+				}
+			}
+			".." => drop(cwd.pop()),
+			_ => {
+				let name = decode_name(name).ok_or_else(|| Error::HashesFileParsingFailure(line.clone()))?;
+				let mut keywords = defaults.clone();
+				keywords.extend(words.filter_map(keyword));
+				let is_dir = keywords.get("type").is_some_and(|kind| kind == "dir");
+				// Names with a slash are full paths, and don't enter directories
+				let file = match name.contains('/') {
+					true => PathBuf::from(name.strip_prefix("./").unwrap_or(&name)),
+					false if name == "." => continue,
+					false => cwd.join(&name),
+				};
+				if is_dir && !name.contains('/') {
+					cwd.push(&name);
+				}
+				listed.push((file, keywords, line.clone()));
+			}
+		}
+	}
+
+	let algorithm = listed.iter().find_map(|(_, keywords, _)| {
+		DIGEST_KEYWORDS
+			.iter()
+			.find(|(_, keyword, alias)| keywords.contains_key(*keyword) || keywords.contains_key(*alias))
+			.map(|(algo, _, _)| *algo)
+	});
+	let algo = algorithm.unwrap_or_default();
+	let parents: BTreeSet<&Path> = listed.iter().flat_map(|(file, _, _)| file.ancestors().skip(1)).collect();
+
+	let mut entries = Vec::new();
+	for (file, keywords, line) in &listed {
+		let failure = || Error::HashesFileParsingFailure(line.clone());
+		let (hash, metadata, target) = match keywords.get("type").map_or("file", String::as_str) {
+			"file" => {
+				let hash = DIGEST_KEYWORDS
+					.iter()
+					.filter(|(known, _, _)| *known == algo)
+					.find_map(|(_, keyword, alias)| keywords.get(*keyword).or_else(|| keywords.get(*alias)));
+				let hash = match hash {
+					Some(hash) if hash.len() == algo.hexlen() && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+						hash.to_uppercase()
+					}
+					Some(_) => return Err(failure()),
+					None => placeholder_hash(algo),
+				};
+				(hash, entry_metadata(keywords), None)
+			}
+			"link" => {
+				let target = keywords.get("link").and_then(|link| decode_name(link)).ok_or_else(failure)?;
+				(hash_reader(algo, &mut target.as_bytes()), None, Some(target))
+			}
+			"dir" if parents.contains(file.as_path()) => continue,
+			_ => (placeholder_hash(algo), None, None),
+		};
+		entries.push(MtreeEntry {
+			file: file.clone(),
+			hash,
+			metadata,
+			target,
+		});
+	}
+
+	Ok(MtreeSpec { header, entries, algorithm })
+}
+
+/// Write the first line of a spec, and the header comments.
+pub(super) fn write_header<W: Write>(out: &mut W, options: &WriteOptions) {
+	writeln!(out, "{}", SIGNATURE).unwrap();
+	for line in &options.header {
+		writeln!(out, "# {}", line).unwrap();
+	}
+}
+
+/// Write the entry of a file with its full path, its type, and its size,
+/// time, mode, owner, group and checksum if known. Files that weren't hashed
+/// have no checksum, and their note as a comment before them.
+pub(super) fn write_entry<W: Write>(out: &mut W, hash: &str, filename: &Path, options: &WriteOptions) {
+	let name = PathStyle::Unix.format(filename);
+	let name = match filename.is_absolute() {
+		true => encode_name(&name),
+		false => format!("./{}", encode_name(&name)),
+	};
+	let mut line = match options.notes.get(filename).map(String::as_str) {
+		Some(note) if note == EMPTY_DIR_NOTE => format!("{} type=dir", name),
+		Some(note) if note.starts_with(SYMLINK_NOTE_PREFIX) => {
+			format!("{} type=link link={}", name, encode_name(&note[SYMLINK_NOTE_PREFIX.len()..]))
+		}
+		note => {
+			if let Some(note) = note {
+				writeln!(out, "# {}", note).unwrap();
+			}
+			format!("{} type=file", name)
+		}
+	};
+	let is_file = line.ends_with("type=file");
+	if let Some(metadata) = options.metadata.get(filename) {
+		line += &format!(
+			" size={} time={}.{:09}",
+			metadata.size,
+			metadata.mtime.as_secs(),
+			metadata.mtime.subsec_nanos()
+		);
+		if let Some(Ownership { uid, gid, mode }) = metadata.ownership {
+			line += &format!(" mode={:04o} uid={} gid={}", mode, uid, gid);
+		}
+	}
+	if is_file
+		&& !hash.starts_with('-')
+		&& let Some(keyword) = mtree_keyword(options.algorithm)
+	{
+		line += &format!(" {}={}", keyword, hash.to_lowercase());
+	}
+	writeln!(out, "{}", line).unwrap();
+}
+
+/// Metadata of an entry listing its size and time, with its ownership if it
+/// lists its owner, group and numeric mode too.
+fn entry_metadata(keywords: &BTreeMap<String, String>) -> Option<EntryMetadata> {
+	let ownership = || {
+		Some(Ownership {
+			uid: keywords.get("uid")?.parse().ok()?,
+			gid: keywords.get("gid")?.parse().ok()?,
+			mode: u32::from_str_radix(keywords.get("mode")?, 8).ok()?,
+		})
+	};
+	Some(EntryMetadata {
+		size: keywords.get("size")?.parse().ok()?,
+		mtime: parse_time(keywords.get("time")?)?,
+		ownership: ownership(),
+		attributes: None,
+	})
+}
+
+/// A `keyword=value` pair. Keywords without a value, like `optional`, are
+/// left out.
+fn keyword(word: &str) -> Option<(String, String)> {
+	let (keyword, value) = word.split_once('=')?;
+	Some((keyword.to_owned(), value.to_owned()))
+}
+
+/// Escape the bytes of a name that mtree doesn't take as they are as `\`
+/// and three octal digits, like vis(3) does: whitespace, anything outside of
+/// printable ASCII, and `\`, `#`, `=` and glob characters.
+fn encode_name(name: &str) -> String {
+	let mut encoded = String::with_capacity(name.len());
+	for byte in name.bytes() {
+		match byte {
+			b'\\' | b'#' | b'=' | b'*' | b'?' | b'[' => encoded += &format!("\\{:03o}", byte),
+			byte if byte.is_ascii_graphic() => encoded.push(byte as char),
+			byte => encoded += &format!("\\{:03o}", byte),
+		}
+	}
+	encoded
+}
+
+/// Undo `encode_name()`, also taking the `\s`, `\t` and `\n` escapes of
+/// vis(3). `None` if it doesn't make UTF-8.
+fn decode_name(name: &str) -> Option<String> {
+	let mut decoded = Vec::with_capacity(name.len());
+	let mut bytes = name.bytes();
+	while let Some(byte) = bytes.next() {
+		if byte != b'\\' {
+			decoded.push(byte);
+			continue;
+		}
+		match bytes.next()? {
+			digit @ b'0'..=b'7' => {
+				let rest = [bytes.next()?, bytes.next()?];
+				let octal = [digit, rest[0], rest[1]];
+				decoded.push(u8::from_str_radix(str::from_utf8(&octal).ok()?, 8).ok()?);
+			}
+			b's' => decoded.push(b' '),
+			b't' => decoded.push(b'\t'),
+			b'n' => decoded.push(b'\n'),
+			other => decoded.push(other),
+		}
+	}
+	String::from_utf8(decoded).ok()
+}
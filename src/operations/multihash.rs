@@ -0,0 +1,303 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Multihashes and CIDs, the self-describing hashes of IPFS: a digest
+//! preceded by the varint code of its algorithm and its length, and for
+//! CIDv1s, by the CID version and the codec of the hashed data. And the
+//! `algorithm:hex` digests of OCI images.
+
+use clap::ValueEnum;
+
+use crate::{Algorithm, hash_string};
+
+/// Multicodec of raw bytes, that CIDs of whole files are made with.
+const RAW_CODEC: u64 = 0x55;
+
+static BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+static BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// How hashes are written in hash files. All are accepted when reading.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum HashEncoding {
+	/// Hexadecimal digests.
+	#[default]
+	Hex,
+	/// Multihashes in base58btc, like the `Qm...` of SHA-256.
+	Multihash,
+	/// CIDv1s of raw blocks in base32, like the `bafkrei...` of SHA-256.
+	Cid,
+	/// OCI digests, the lowercase digest after the name of its algorithm and
+	/// a colon, like `sha256:e3b0...`.
+	Oci,
+}
+
+/// Names of the algorithms in OCI digests.
+static OCI_NAMES: [(Algorithm, &str); 20] = [
+	(Algorithm::SHA1, "sha1"),
+	(Algorithm::SHA2224, "sha224"),
+	(Algorithm::SHA2256, "sha256"),
+	(Algorithm::SHA2384, "sha384"),
+	(Algorithm::SHA2512, "sha512"),
+	(Algorithm::SHA3224, "sha3-224"),
+	(Algorithm::SHA3256, "sha3-256"),
+	(Algorithm::SHA3384, "sha3-384"),
+	(Algorithm::SHA3512, "sha3-512"),
+	(Algorithm::XXH32, "xxh32"),
+	(Algorithm::XXH64, "xxh64"),
+	(Algorithm::XXH3, "xxh3"),
+	(Algorithm::CRC32, "crc32"),
+	(Algorithm::MD5, "md5"),
+	(Algorithm::WhirlPool, "whirlpool"),
+	(Algorithm::BLAKE2B, "blake2b"),
+	(Algorithm::BLAKE2S, "blake2s"),
+	(Algorithm::BLAKE3, "blake3"),
+	(Algorithm::S3ETag, "s3-etag"),
+	(Algorithm::BTv2, "btv2"),
+];
+
+impl HashEncoding {
+	/// Encode the hexadecimal `hash`, made with `algo`. Placeholder hashes,
+	/// and the multihashes and CIDs of algorithms without a multihash code,
+	/// are left as is.
+	pub fn encode(&self, hash: &str, algo: Algorithm) -> String {
+		if *self == HashEncoding::Oci && !hash.starts_with('-') {
+			return format!("{}:{}", oci_name(algo), hash.to_lowercase());
+		}
+		let (Some(code), Some(digest)) = (multihash_code(algo), decode_hex(hash)) else {
+			return hash.to_owned();
+		};
+		let mut multihash = Vec::with_capacity(digest.len() + 4);
+		push_varint(&mut multihash, code);
+		push_varint(&mut multihash, digest.len() as u64);
+		multihash.extend_from_slice(&digest);
+		match self {
+			HashEncoding::Hex | HashEncoding::Oci => hash.to_owned(),
+			HashEncoding::Multihash => base58_encode(&multihash),
+			HashEncoding::Cid => {
+				let mut cid = vec![1];
+				push_varint(&mut cid, RAW_CODEC);
+				cid.extend_from_slice(&multihash);
+				format!("b{}", base32_encode(&cid))
+			}
+		}
+	}
+}
+
+/// Multicodec code of the multihashes of `algo`, if it has one.
+pub fn multihash_code(algo: Algorithm) -> Option<u64> {
+	Some(match algo {
+		Algorithm::SHA1 => 0x11,
+		Algorithm::SHA2224 => 0x1013,
+		Algorithm::SHA2256 => 0x12,
+		Algorithm::SHA2384 => 0x20,
+		Algorithm::SHA2512 => 0x13,
+		Algorithm::SHA3224 => 0x17,
+		Algorithm::SHA3256 => 0x16,
+		Algorithm::SHA3384 => 0x15,
+		Algorithm::SHA3512 => 0x14,
+		Algorithm::XXH32 => 0xb3e1,
+		Algorithm::XXH64 => 0xb3e2,
+		Algorithm::XXH3 => 0xb3e3,
+		Algorithm::CRC32 => 0x0132,
+		Algorithm::MD5 => 0xd5,
+		Algorithm::BLAKE2B => 0xb240,
+		Algorithm::BLAKE2S => 0xb260,
+		Algorithm::UNSPECIFIED | Algorithm::BLAKE3 => 0x1e,
+		Algorithm::WhirlPool | Algorithm::S3ETag | Algorithm::BTv2 => return None,
+	})
+}
+
+/// Name of `algo` in OCI digests.
+pub fn oci_name(algo: Algorithm) -> &'static str {
+	let algo = match algo {
+		Algorithm::UNSPECIFIED => Algorithm::BLAKE3,
+		algo => algo,
+	};
+	OCI_NAMES.iter().find(|(known, _)| *known == algo).map_or("blake3", |(_, name)| name)
+}
+
+/// Algorithm and uppercase hexadecimal digest of an OCI digest, of a
+/// multihash in base58btc, or of a CIDv1 in base32 or base58btc, or `None`
+/// if `hash` isn't one. Hexadecimal hashes never are.
+///
+/// # Examples
+///
+/// ```
+/// # use quickdash::{Algorithm, operations::decode_named_hash};
+/// assert_eq!(decode_named_hash("md5:d41d8cd98f00b204e9800998ecf8427e").unwrap().0, Algorithm::MD5);
+/// assert_eq!(decode_named_hash("sha256:d41d8cd98f00b204e9800998ecf8427e"), None);
+/// assert_eq!(decode_named_hash("D41D8CD98F00B204E9800998ECF8427E"), None);
+/// ```
+pub fn decode_named_hash(hash: &str) -> Option<(Algorithm, String)> {
+	if let Some((name, digest)) = hash.split_once(':') {
+		let (algo, _) = OCI_NAMES.iter().find(|(_, known)| *known == name)?;
+		// Multipart S3 ETags end with `-` and their amount of parts
+		let hex = digest.split_once('-').filter(|_| *algo == Algorithm::S3ETag).map_or(digest, |(hex, _)| hex);
+		return match hex.len() == algo.hexlen() && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+			true => Some((*algo, digest.to_uppercase())),
+			false => None,
+		};
+	}
+	if hash.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+		return None;
+	}
+	let prefixed = match hash.split_at_checked(1)? {
+		("b", rest) => base32_decode(rest),
+		("B", rest) => base32_decode(&rest.to_lowercase()),
+		("z", rest) => base58_decode(rest),
+		_ => None,
+	};
+	prefixed
+		.and_then(|bytes| decode_multihash(&bytes, true))
+		.or_else(|| decode_multihash(&base58_decode(hash)?, false))
+}
+
+/// Algorithm and digest of the multihash `bytes` hold whole, after a CIDv1
+/// header if `cid`.
+fn decode_multihash(mut bytes: &[u8], cid: bool) -> Option<(Algorithm, String)> {
+	if cid && read_varint(&mut bytes)? != 1 {
+		return None;
+	}
+	if cid {
+		read_varint(&mut bytes)?;
+	}
+	let code = read_varint(&mut bytes)?;
+	let len = read_varint(&mut bytes)?;
+	let algo = [
+		Algorithm::SHA1,
+		Algorithm::SHA2224,
+		Algorithm::SHA2256,
+		Algorithm::SHA2384,
+		Algorithm::SHA2512,
+		Algorithm::SHA3224,
+		Algorithm::SHA3256,
+		Algorithm::SHA3384,
+		Algorithm::SHA3512,
+		Algorithm::XXH32,
+		Algorithm::XXH64,
+		Algorithm::XXH3,
+		Algorithm::CRC32,
+		Algorithm::MD5,
+		Algorithm::BLAKE2B,
+		Algorithm::BLAKE2S,
+		Algorithm::BLAKE3,
+	]
+	.into_iter()
+	.find(|&algo| multihash_code(algo) == Some(code))?;
+	match bytes.len() as u64 == len && algo.hexlen() == bytes.len() * 2 {
+		true => Some((algo, hash_string(bytes))),
+		false => None,
+	}
+}
+
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		out.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+	out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+	let mut value = 0;
+	for shift in (0..63).step_by(7) {
+		let (&byte, rest) = bytes.split_first()?;
+		*bytes = rest;
+		value |= u64::from(byte & 0x7f) << shift;
+		if byte < 0x80 {
+			return Some(value);
+		}
+	}
+	None
+}
+
+fn decode_hex(hash: &str) -> Option<Vec<u8>> {
+	if !hash.len().is_multiple_of(2) {
+		return None;
+	}
+	(0..hash.len()).step_by(2).map(|i| u8::from_str_radix(hash.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+	let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+	// Base 58 digits, least significant first
+	let mut digits: Vec<u8> = Vec::new();
+	for &byte in &bytes[zeros..] {
+		let mut carry = u32::from(byte);
+		for digit in digits.iter_mut() {
+			carry += u32::from(*digit) << 8;
+			*digit = (carry % 58) as u8;
+			carry /= 58;
+		}
+		while carry > 0 {
+			digits.push((carry % 58) as u8);
+			carry /= 58;
+		}
+	}
+	let digits = digits.iter().rev().map(|&digit| BASE58_ALPHABET[digit as usize]);
+	std::iter::repeat_n(b'1', zeros).chain(digits).map(char::from).collect()
+}
+
+fn base58_decode(text: &str) -> Option<Vec<u8>> {
+	let zeros = text.bytes().take_while(|&c| c == b'1').count();
+	// Bytes, least significant first
+	let mut bytes: Vec<u8> = Vec::new();
+	for c in text.bytes().skip(zeros) {
+		let mut carry = BASE58_ALPHABET.iter().position(|&digit| digit == c)? as u32;
+		for byte in bytes.iter_mut() {
+			carry += u32::from(*byte) * 58;
+			*byte = carry as u8;
+			carry >>= 8;
+		}
+		while carry > 0 {
+			bytes.push(carry as u8);
+			carry >>= 8;
+		}
+	}
+	Some(std::iter::repeat_n(0, zeros).chain(bytes.into_iter().rev()).collect())
+}
+
+/// Lowercase base32 without padding.
+fn base32_encode(bytes: &[u8]) -> String {
+	let mut text = String::with_capacity(bytes.len().div_ceil(5) * 8);
+	let (mut buffer, mut bits) = (0u32, 0);
+	for &byte in bytes {
+		buffer = buffer << 8 | u32::from(byte);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			text.push(char::from(BASE32_ALPHABET[(buffer >> bits & 31) as usize]));
+		}
+	}
+	if bits > 0 {
+		text.push(char::from(BASE32_ALPHABET[(buffer << (5 - bits) & 31) as usize]));
+	}
+	text
+}
+
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+	let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+	let (mut buffer, mut bits) = (0u32, 0);
+	for c in text.bytes() {
+		buffer = buffer << 5 | BASE32_ALPHABET.iter().position(|&digit| digit == c)? as u32;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			bytes.push((buffer >> bits) as u8);
+		}
+	}
+	Some(bytes)
+}
@@ -0,0 +1,44 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization form paths are brought to before comparison.
+///
+/// macOS stores filenames decomposed (NFD), while most other systems keep them
+/// composed (NFC), so the same name can be spelled with different bytes.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum UnicodeForm {
+	/// Canonical composition.
+	Nfc,
+	/// Canonical decomposition.
+	Nfd,
+}
+
+impl UnicodeForm {
+	/// Normalize a path. Paths that aren't valid UTF-8 are left alone.
+	pub fn normalize(&self, path: &Path) -> PathBuf {
+		match path.to_str() {
+			Some(path) => match *self {
+				UnicodeForm::Nfc => path.nfc().collect::<String>().into(),
+				UnicodeForm::Nfd => path.nfd().collect::<String>().into(),
+			},
+			None => path.to_owned(),
+		}
+	}
+}
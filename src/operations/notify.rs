@@ -0,0 +1,165 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Notifications of failed verification runs, POSTed to a webhook as JSON or
+//! emailed over SMTP, so unattended scrubs can page someone. Both are sent
+//! with curl, logging into SMTP servers with `~/.netrc`.
+
+use std::{
+	io::{self, Write},
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	time::SystemTime,
+};
+
+use super::{
+	CompareOutcome, CompareResult, PathStyle,
+	audit::{failed_files, run_error, utc_time},
+	in_toto::{base64_encode, json_string},
+};
+use crate::Error;
+
+/// SMTP server emails are sent through, and who they're from and to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Smtp {
+	/// `smtp://` or `smtps://` URL of the server.
+	pub url: String,
+	pub from: String,
+	pub to: Vec<String>,
+}
+
+/// What went wrong in a failed run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureSummary {
+	pub host: String,
+	pub command: String,
+	pub manifest: PathBuf,
+	pub time: SystemTime,
+	/// Files that failed verification.
+	pub failures: Vec<PathBuf>,
+	/// Files in the hash file that are gone.
+	pub removed: usize,
+	/// Why the run couldn't compare, if it couldn't.
+	pub error: Option<String>,
+}
+
+impl FailureSummary {
+	/// Summary of `command` verifying `manifest` on `host`, if it failed:
+	/// couldn't compare, or a file failed.
+	pub fn of(host: &str, command: &str, manifest: &Path, result: Result<&CompareOutcome, &Error>) -> Option<Self> {
+		let (failures, removed) = match result {
+			Ok(Ok((compare_results, file_compare_results))) => (
+				failed_files(compare_results, file_compare_results).into_iter().cloned().collect(),
+				compare_results.iter().filter(|res| matches!(res, CompareResult::FileRemoved(_))).count(),
+			),
+			_ => (Vec::new(), 0),
+		};
+		let error = run_error(result);
+		if failures.is_empty() && error.is_none() {
+			return None;
+		}
+		Some(FailureSummary {
+			host: host.to_owned(),
+			command: command.to_owned(),
+			manifest: manifest.to_owned(),
+			time: SystemTime::now(),
+			failures,
+			removed,
+			error,
+		})
+	}
+
+	/// The summary as a JSON object.
+	pub fn json(&self) -> String {
+		let failures: Vec<String> = self.failures.iter().map(|file| json_string(&PathStyle::Unix.format(file))).collect();
+		format!(
+			"{{\"time\":\"{}\",\"host\":{},\"command\":{},\"manifest\":{},\"failed\":{},\"failures\":[{}],\"removed\":{},\"error\":{}}}",
+			utc_time(self.time),
+			json_string(&self.host),
+			json_string(&self.command),
+			json_string(&self.manifest.to_string_lossy()),
+			self.failures.len(),
+			failures.join(","),
+			self.removed,
+			self.error.as_deref().map_or_else(|| "null".to_owned(), json_string)
+		)
+	}
+
+	/// The summary as an email from `from` to `to`.
+	pub fn email(&self, from: &str, to: &[String]) -> String {
+		// Quoted, so names can't break out of the header
+		let subject = format!("quickdash {} of {:?} failed on {}", self.command, self.manifest, self.host);
+		let mut body = format!("{} of {:?} on {} failed at {}.\r\n", self.command, self.manifest, self.host, utc_time(self.time));
+		if let Some(error) = &self.error {
+			body.push_str(&format!("\r\nIt couldn't compare: {}\r\n", error));
+		}
+		if !self.failures.is_empty() {
+			body.push_str(&format!("\r\n{} files failed:\r\n", self.failures.len()));
+			for file in &self.failures {
+				body.push_str(&format!("  {}\r\n", PathStyle::Unix.format(file)));
+			}
+		}
+		if self.removed > 0 {
+			body.push_str(&format!("\r\n{} files are gone.\r\n", self.removed));
+		}
+		format!(
+			"From: {}\r\nTo: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\
+			Content-Transfer-Encoding: 8bit\r\n\r\n{}",
+			from,
+			to.join(", "),
+			header_text(&subject),
+			body
+		)
+	}
+}
+
+/// POST `summary` as JSON to `url`.
+pub fn send_webhook(url: &str, summary: &FailureSummary) -> io::Result<()> {
+	let mut command = Command::new("curl");
+	command
+		.args(["--fail", "--silent", "--show-error", "--header", "Content-Type: application/json", "--data-binary", "@-", "--"])
+		.arg(url);
+	curl(command, summary.json().as_bytes(), url)
+}
+
+/// Email `summary` through `smtp`.
+pub fn send_email(smtp: &Smtp, summary: &FailureSummary) -> io::Result<()> {
+	let mut command = Command::new("curl");
+	command.args(["--fail", "--silent", "--show-error", "--netrc-optional", "--mail-from", smtp.from.as_str()]);
+	for to in &smtp.to {
+		command.args(["--mail-rcpt", to.as_str()]);
+	}
+	command.args(["--upload-file", "-", "--url", smtp.url.as_str()]);
+	curl(command, summary.email(&smtp.from, &smtp.to).as_bytes(), &smtp.url)
+}
+
+/// Run curl, giving it `input`.
+fn curl(mut command: Command, input: &[u8], url: &str) -> io::Result<()> {
+	let mut child = command.stdin(Stdio::piped()).spawn()?;
+	let written = child.stdin.take().expect("stdin is piped").write_all(input);
+	let status = child.wait()?;
+	written?;
+	match status.success() {
+		true => Ok(()),
+		false => Err(io::Error::other(format!("failed to send to {} ({})", url, status))),
+	}
+}
+
+/// `text` as it can be put in an email header, encoded if it isn't ASCII.
+fn header_text(text: &str) -> String {
+	match text.is_ascii() {
+		true => text.to_owned(),
+		false => format!("=?utf-8?b?{}?=", base64_encode(text.as_bytes())),
+	}
+}
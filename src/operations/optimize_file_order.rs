@@ -1,42 +1,125 @@
-use walkdir::DirEntry;
+use std::cmp::Reverse;
 
+use clap::ValueEnum;
+
+use super::FileRecord;
+
+/// Order files are hashed in.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum FileOrder {
+	/// By on-disk location where the platform exposes it, for fewer seeks.
+	#[default]
+	Inode,
+	/// Biggest first, so a huge file doesn't start last and leave the other
+	/// threads idle at the end of a parallel run.
+	SizeDesc,
+}
+
+// Put the files in the specified order.
+pub(super) fn order_files(dirs: &mut [FileRecord], order: FileOrder) {
+	match order {
+		FileOrder::Inode => optimize_file_order(dirs),
+		FileOrder::SizeDesc => dirs.sort_by_key(|e| Reverse(e.len)),
+	}
+}
 
 // Linux: sort by inode to keep files with nearby disk locations together
 // (optimises access patterns for many files on ext-filesystems).
 #[cfg(target_os = "linux")]
-pub fn optimize_file_order(dirs: &mut [DirEntry]) {
+pub(super) fn optimize_file_order(dirs: &mut [FileRecord]) {
 	use walkdir::DirEntryExt;
 	dirs.sort_by(|a, b| {
-		let a_inode = a.ino();
-		let b_inode = b.ino();
+		let a_inode = a.entry.ino();
+		let b_inode = b.entry.ino();
 		a_inode.cmp(&b_inode)
 	});
 }
 
-// macOS: use the device and inode from `Metadata` for a similar effect to
-// Linux. `std::os::unix::fs::MetadataExt` is available on Unix-like
-// platforms including macOS and exposes `dev()` and `ino()`.
+// macOS: use the device and inode queried while walking for a similar
+// effect to Linux.
 #[cfg(target_os = "macos")]
-pub fn optimize_file_order(dirs: &mut [DirEntry]) {
-	use std::os::unix::fs::MetadataExt;
-	dirs.sort_by(|a, b| {
-		let a_meta = a.metadata();
-		let b_meta = b.metadata();
-		match (a_meta, b_meta) {
-			(Ok(am), Ok(bm)) => (am.dev(), am.ino()).cmp(&(bm.dev(), bm.ino())),
-			_ => a.path().cmp(&b.path()),
-		}
+pub(super) fn optimize_file_order(dirs: &mut [FileRecord]) {
+	dirs.sort_by(|a, b| match (a.ino, b.ino) {
+		(Some(a_ino), Some(b_ino)) => a_ino.cmp(&b_ino),
+		_ => a.path().cmp(&b.path()),
 	});
 }
 
-// Windows: metadata does not expose POSIX-style inode semantics portably
-// in the standard library in the same way. Fall back to sorting by
-// path to provide deterministic ordering on Windows.
+// Windows: sort by volume and NTFS file reference number, whose low 48 bits
+// are the file's MFT record, so files are read in roughly the order they were
+// laid out. Files whose ID can't be had go last, by path.
+#[cfg(target_family = "windows")]
+pub(super) fn optimize_file_order(dirs: &mut [FileRecord]) {
+	dirs.sort_by_cached_key(|e| (file_id::file_id(e.path()).unwrap_or((u32::MAX, u64::MAX)), e.path().to_path_buf()));
+}
+
 #[cfg(target_family = "windows")]
-pub fn optimize_file_order(dirs: &mut [DirEntry]) {
-	dirs.sort_by(|a, b| a.path().cmp(&b.path()));
+mod file_id {
+	// `GetFileInformationByHandle()` has no stable wrapper in std
+	#![allow(unsafe_code)]
+
+	use std::{
+		ffi::c_void,
+		fs::OpenOptions,
+		os::windows::{fs::OpenOptionsExt, io::AsRawHandle},
+		path::Path,
+	};
+
+	use crate::utilities::long_path;
+
+	// Laid out for the system to fill in, not all of it is read
+	#[allow(dead_code)]
+	#[repr(C)]
+	#[derive(Default)]
+	struct FileTime {
+		low: u32,
+		high: u32,
+	}
+
+	#[allow(dead_code)]
+	#[repr(C)]
+	#[derive(Default)]
+	struct ByHandleFileInformation {
+		file_attributes: u32,
+		creation_time: FileTime,
+		last_access_time: FileTime,
+		last_write_time: FileTime,
+		volume_serial_number: u32,
+		file_size_high: u32,
+		file_size_low: u32,
+		number_of_links: u32,
+		file_index_high: u32,
+		file_index_low: u32,
+	}
+
+	#[link(name = "kernel32")]
+	unsafe extern "system" {
+		fn GetFileInformationByHandle(file: *mut c_void, information: *mut ByHandleFileInformation) -> i32;
+	}
+
+	/// Flag needed to open directories, harmless for files.
+	const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+	// Volume serial number and MFT record number of a file.
+	pub(super) fn file_id(path: &Path) -> Option<(u32, u64)> {
+		// No access rights needed to query the file's information
+		let file = OpenOptions::new()
+			.access_mode(0)
+			.custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+			.open(long_path(path))
+			.ok()?;
+		let mut information = ByHandleFileInformation::default();
+		// SAFETY: a valid handle owned by `file`, and a pointer to a properly
+		// laid out struct for the call to fill in
+		let ok = unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut information) };
+		if ok == 0 {
+			return None;
+		}
+		let index = ((information.file_index_high as u64) << 32) | information.file_index_low as u64;
+		Some((information.volume_serial_number, index & 0xFFFF_FFFF_FFFF))
+	}
 }
 
 // Other platforms: no-op (preserve original order).
 #[cfg(not(any(target_os = "linux", target_os = "macos", target_family = "windows")))]
-pub fn optimize_file_order(_dirs: &mut [DirEntry]) {}
+pub(super) fn optimize_file_order(_dirs: &mut [FileRecord]) {}
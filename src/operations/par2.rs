@@ -0,0 +1,94 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! PAR2 recovery files of hashed files, made and used by `par2`
+//! (par2cmdline), to repair files that verification finds corrupt.
+//!
+//! The recovery set covers the hash file too, and is kept next to it, in a
+//! directory named after it.
+
+use std::{
+	fs, io,
+	path::{self, Path, PathBuf},
+	process::{Command, Stdio},
+};
+
+use crate::utilities::long_path;
+
+/// Directory the recovery files of `hash_file` are kept in, next to it.
+pub fn par2_dir(hash_file: &Path) -> PathBuf {
+	let mut dir = hash_file.as_os_str().to_owned();
+	dir.push(".par2");
+	PathBuf::from(dir)
+}
+
+/// Index file of the recovery set of `hash_file`, which the volumes are
+/// named after.
+pub fn recovery_file(hash_file: &Path) -> PathBuf {
+	let mut name = hash_file.file_name().unwrap_or_default().to_owned();
+	name.push(".par2");
+	par2_dir(hash_file).join(name)
+}
+
+/// Make recovery files able to restore `redundancy` percent of the files
+/// stored as `names` under `base` and of `hash_file`, replacing any made
+/// before.
+pub fn create_recovery(hash_file: &Path, base: &Path, names: &[PathBuf], redundancy: u32) -> io::Result<()> {
+	let dir = par2_dir(hash_file);
+	match fs::remove_dir_all(&dir) {
+		Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+		_ => fs::create_dir_all(&dir)?,
+	}
+	let files = names.iter().map(|name| base.join(name)).filter(|path| long_path(path).is_file());
+	run_par2(
+		Command::new("par2")
+			.arg("create")
+			.arg("-q")
+			.arg(format!("-r{}", redundancy))
+			.arg(basepath(base, hash_file))
+			.arg(recovery_file(hash_file))
+			.arg("--")
+			.arg(hash_file)
+			.args(files),
+	)
+}
+
+/// Repair the files of `hash_file` under `base`, and the hash file, from its
+/// recovery files. Files that are fine are left alone.
+pub fn repair(hash_file: &Path, base: &Path) -> io::Result<()> {
+	let recovery = recovery_file(hash_file);
+	if !recovery.is_file() {
+		return Err(io::Error::new(io::ErrorKind::NotFound, format!("no recovery files, {:?} is missing", recovery)));
+	}
+	run_par2(Command::new("par2").arg("repair").arg("-q").arg(basepath(base, hash_file)).arg(recovery))
+}
+
+/// `-B` option setting the directory files are named relative to in the
+/// recovery set, the one holding both `base` and `hash_file`.
+fn basepath(base: &Path, hash_file: &Path) -> String {
+	let (base, hash_file) = (path::absolute(base), path::absolute(hash_file));
+	let common = match (&base, &hash_file) {
+		(Ok(base), Ok(hash_file)) => hash_file.ancestors().find(|dir| base.starts_with(dir)).map(Path::to_path_buf),
+		_ => None,
+	};
+	format!("-B{}", common.unwrap_or_default().display())
+}
+
+fn run_par2(command: &mut Command) -> io::Result<()> {
+	let status = command.stdin(Stdio::null()).status()?;
+	match status.success() {
+		true => Ok(()),
+		false => Err(io::Error::other(format!("par2 failed ({})", status))),
+	}
+}
@@ -0,0 +1,223 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::{HashMap, hash_map::Entry},
+	io::{self, Read},
+	sync::{
+		Mutex,
+		atomic::{AtomicUsize, Ordering},
+		mpsc::{Receiver, channel, sync_channel},
+	},
+	thread,
+};
+
+use indicatif::ProgressBar;
+use super::{FileRecord, HardLinks, WalkOptions, hash_entry, hashed, retry_hash, skip_note};
+use crate::{
+	Algorithm,
+	hashing::{read_file_chunks, try_hash_reader_with},
+};
+
+/// Amount of files read ahead of the hashing threads.
+const QUEUE_LEN: usize = 64;
+
+/// Amount of chunks of a file read ahead of the thread hashing it.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+/// Hash walked entries with `options.io_threads` threads reading files and
+/// `options.hash_threads` threads hashing them, connected by bounded queues,
+/// so that slow storage and slow algorithms don't hold each other up.
+///
+/// Returns the same as `hash_entry()` for each entry, in order. Entries
+/// whose hash is `known` aren't read.
+pub(super) fn hash_entries(
+	entries: &[FileRecord],
+	known: Vec<Option<String>>,
+	algo: Algorithm,
+	options: &WalkOptions,
+	pb: &ProgressBar,
+) -> Vec<(String, Option<String>)> {
+	hash_entries_until(entries, known, algo, options, pb, || false, &mut |_, _| {})
+		.into_iter()
+		.map(|result| result.expect("Every entry is hashed"))
+		.collect()
+}
+
+/// Hash walked entries like `hash_entries()`, reading no more files once
+/// `stop()`, getting none for those left unread. Each file read is given to
+/// `on_hashed` as soon as it's hashed.
+pub(super) fn hash_entries_until(
+	entries: &[FileRecord],
+	mut known: Vec<Option<String>>,
+	algo: Algorithm,
+	options: &WalkOptions,
+	pb: &ProgressBar,
+	stop: fn() -> bool,
+	on_hashed: &mut dyn FnMut(&FileRecord, &(String, Option<String>)),
+) -> Vec<Option<(String, Option<String>)>> {
+	let mut results = vec![None; entries.len()];
+	// Regular files to read, and the hard links reusing the hash of another
+	let mut to_read = Vec::new();
+	let mut links = Vec::new();
+	let mut first_links = HashMap::new();
+	for (i, entry) in entries.iter().enumerate() {
+		if let Some(hash) = known[i].take() {
+			results[i] = Some((hash, None));
+			pb.inc(1);
+			continue;
+		}
+		if entry.entry.file_type().is_symlink() || skip_note(entry, options).is_some() {
+			results[i] = Some(hash_entry(entry, algo, options, &mut HardLinks::default()));
+			pb.inc(1);
+			continue;
+		}
+		if !options.hash_each_hard_link
+			&& let Some(inode) = entry.hard_link_id()
+		{
+			match first_links.entry(inode) {
+				Entry::Occupied(first) => {
+					links.push((i, *first.get()));
+					continue;
+				}
+				Entry::Vacant(first) => {
+					first.insert(i);
+				}
+			}
+		}
+		to_read.push(i);
+	}
+
+	let next = AtomicUsize::new(0);
+	let (file_sender, file_receiver) = sync_channel(QUEUE_LEN);
+	let file_receiver = Mutex::new(file_receiver);
+	let (result_sender, result_receiver) = channel();
+	thread::scope(|scope| {
+		let (to_read, next, file_receiver) = (&to_read, &next, &file_receiver);
+		for _ in 0..options.io_threads.max(1) {
+			let file_sender = file_sender.clone();
+			scope.spawn(move || {
+				while !stop()
+					&& let Some(&i) = to_read.get(next.fetch_add(1, Ordering::Relaxed))
+				{
+					let path = entries[i].path();
+					let (chunk_sender, chunk_receiver) = sync_channel(CHUNKS_IN_FLIGHT);
+					// Queued before reading, so files are hashed in the order they're read
+					if file_sender.send((i, chunk_receiver)).is_err() {
+						break;
+					}
+					let read = read_file_chunks(path, &options.hash_options, |chunk| {
+						chunk_sender.send(Chunk::Data(chunk)).is_ok()
+					});
+					let _ = chunk_sender.send(match read {
+						Ok(stable) => Chunk::End(stable),
+						Err(err) => Chunk::Failed(err),
+					});
+				}
+			});
+		}
+		drop(file_sender);
+
+		for _ in 0..options.hash_threads.max(1) {
+			let result_sender = result_sender.clone();
+			scope.spawn(move || {
+				loop {
+					let file = file_receiver.lock().unwrap().recv();
+					let Ok((i, chunks)) = file else {
+						break;
+					};
+					let mut reader = ChunkReader::new(chunks);
+					let hash = try_hash_reader_with(algo, &mut reader, &options.hash_options).map(|hash| (hash, reader.stable));
+					if result_sender.send((i, hash)).is_err() {
+						break;
+					}
+				}
+			});
+		}
+		drop(result_sender);
+
+		for (i, hash) in result_receiver {
+			// Failed reads are retried here, one file at a time
+			let hash = retry_hash(hash, algo, entries[i].path(), options);
+			let result = hashed(hash, algo);
+			on_hashed(&entries[i], &result);
+			results[i] = Some(result);
+			pb.inc(1);
+		}
+	});
+
+	for (i, first) in links {
+		results[i] = results[first].clone();
+		if let Some(result) = &results[i] {
+			on_hashed(&entries[i], result);
+		}
+		pb.inc(1);
+	}
+	results
+}
+
+/// What the thread reading a file sends the thread hashing it.
+enum Chunk {
+	Data(Vec<u8>),
+	/// End of the file, and whether its size and modification time stayed
+	/// the same while it was read.
+	End(bool),
+	Failed(io::Error),
+}
+
+/// Reader of the chunks of a file sent by the thread reading it.
+struct ChunkReader {
+	chunks: Receiver<Chunk>,
+	chunk: Vec<u8>,
+	position: usize,
+	/// Whether the file stayed the same, once it's been read to the end.
+	stable: bool,
+}
+
+impl ChunkReader {
+	fn new(chunks: Receiver<Chunk>) -> Self {
+		ChunkReader {
+			chunks,
+			chunk: Vec::new(),
+			position: 0,
+			stable: false,
+		}
+	}
+}
+
+impl Read for ChunkReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		while self.position == self.chunk.len() {
+			match self.chunks.recv() {
+				Ok(Chunk::Data(chunk)) => {
+					self.chunk = chunk;
+					self.position = 0;
+				}
+				Ok(Chunk::End(stable)) => {
+					self.stable = stable;
+					return Ok(0);
+				}
+				Ok(Chunk::Failed(err)) => return Err(err),
+				// The reading thread is gone without saying why
+				Err(_) => return Err(io::Error::other("file reader stopped")),
+			}
+		}
+
+		let read = buf.len().min(self.chunk.len() - self.position);
+		buf[..read].copy_from_slice(&self.chunk[self.position..self.position + read]);
+		self.position += read;
+		Ok(read)
+	}
+}
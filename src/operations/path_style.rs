@@ -0,0 +1,63 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::Path;
+
+use clap::ValueEnum;
+
+/// Path separator used when writing hash files.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum PathStyle {
+	/// Whatever the current platform uses.
+	#[default]
+	Native,
+	/// Always `/`.
+	Unix,
+	/// Always `\`.
+	Windows,
+}
+
+impl PathStyle {
+	/// Format a path for writing, using this style's separator.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// # use std::path::Path;
+	/// # use quickdash::operations::PathStyle;
+	/// assert_eq!(PathStyle::Windows.format(Path::new("a/b")), "a\\b");
+	/// assert_eq!(PathStyle::Unix.format(Path::new("a/b")), "a/b");
+	/// ```
+	pub fn format(&self, path: &Path) -> String {
+		let path = path.to_string_lossy();
+		match *self {
+			PathStyle::Native => path.into_owned(),
+			// `\` is a valid filename character on Unix-likes, only touch it
+			// where it's a separator
+			PathStyle::Unix if cfg!(windows) => path.replace('\\', "/"),
+			PathStyle::Unix => path.into_owned(),
+			PathStyle::Windows => path.replace('/', "\\"),
+		}
+	}
+
+	/// Whether `\` separates path components in this style.
+	pub fn backslash_is_separator(&self) -> bool {
+		match *self {
+			PathStyle::Native => cfg!(windows),
+			PathStyle::Unix => false,
+			PathStyle::Windows => true,
+		}
+	}
+}
@@ -0,0 +1,168 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::BTreeMap,
+	fs::File,
+	io::{self, BufReader, Read},
+	path::{Path, PathBuf},
+};
+
+use super::{CompareFileResult, CompareOutcome, RecordedMetadata};
+use crate::{Algorithm, try_hash_reader, utilities::long_path};
+
+/// Start of the comment lines holding the hashes of the pieces of the entry
+/// after them.
+pub(super) static PIECE_PREFIX: &str = "piece ";
+
+/// Hashes of the consecutive pieces of a file, recorded alongside its hash
+/// as one comment line per piece right before its entry:
+/// `piece 412 of 67108864 bytes: HASH`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Pieces {
+	/// Bytes in each piece but the last.
+	pub size: u64,
+	pub hashes: Vec<String>,
+}
+
+impl Pieces {
+	/// Hash the file at `path` `size` bytes at a time.
+	pub fn of(path: &Path, algo: Algorithm, size: u64) -> io::Result<Self> {
+		let file = File::open(long_path(path))?;
+		let len = file.metadata()?.len();
+		let mut reader = BufReader::with_capacity(4 << 20, file);
+		let mut hashes = Vec::new();
+		for _ in 0..len.div_ceil(size) {
+			hashes.push(try_hash_reader(algo, &mut (&mut reader).take(size))?);
+		}
+		Ok(Pieces { size, hashes })
+	}
+
+	/// Byte ranges of the pieces that differ in `new`, with consecutive ones
+	/// merged, given the size in bytes of the file `new` was made of.
+	pub fn differing(&self, new: &Pieces, len: u64) -> Vec<(u64, u64)> {
+		let mut ranges: Vec<(u64, u64)> = Vec::new();
+		for i in 0..self.hashes.len().max(new.hashes.len()) {
+			if self.hashes.get(i) == new.hashes.get(i) {
+				continue;
+			}
+			let start = i as u64 * self.size;
+			let end = match i < new.hashes.len() {
+				true => (start + self.size).min(len),
+				false => start + self.size,
+			};
+			match ranges.last_mut() {
+				Some(last) if last.1 == start => last.1 = end,
+				_ => ranges.push((start, end)),
+			}
+		}
+		ranges
+	}
+
+	/// Comment lines holding the hashes, in order.
+	pub(super) fn comments(&self) -> impl Iterator<Item = String> + '_ {
+		self.hashes
+			.iter()
+			.enumerate()
+			.map(|(i, hash)| format!("{}{} of {} bytes: {}", PIECE_PREFIX, i, self.size, hash))
+	}
+
+	/// Add the piece held by the text of a piece comment, if it's the next
+	/// one. Returns whether it's a piece comment.
+	pub(super) fn parse_into(comment: &str, pieces: &mut Option<Pieces>) -> bool {
+		let piece = comment.strip_prefix(PIECE_PREFIX).and_then(|piece| {
+			let (index, rest) = piece.split_once(" of ")?;
+			let (size, hash) = rest.split_once(" bytes: ")?;
+			Some((index.parse::<usize>().ok()?, size.parse::<u64>().ok()?, hash.trim().to_uppercase()))
+		});
+		let Some((index, size, hash)) = piece else {
+			return false;
+		};
+		let pieces = pieces.get_or_insert_with(|| Pieces { size, hashes: Vec::new() });
+		if pieces.size == size && pieces.hashes.len() == index {
+			pieces.hashes.push(hash);
+		}
+		true
+	}
+}
+
+/// Hash the pieces of the regular files in `hashes` larger than `size`,
+/// found under `base`. Files that can't be read get a warning instead.
+pub fn record_pieces(
+	base: &Path,
+	hashes: &BTreeMap<PathBuf, String>,
+	notes: &BTreeMap<PathBuf, String>,
+	algo: Algorithm,
+	size: u64,
+	warnings: &mut Vec<String>,
+) -> BTreeMap<PathBuf, Pieces> {
+	let mut pieces = BTreeMap::new();
+	for name in hashes.keys().filter(|name| !notes.contains_key(*name)) {
+		let path = base.join(name);
+		match std::fs::metadata(long_path(&path)) {
+			Ok(metadata) if metadata.len() <= size => continue,
+			Ok(_) => {}
+			Err(err) => {
+				warnings.push(format!("Failed to hash the pieces of {:?}: {}", name, err));
+				continue;
+			}
+		}
+		match Pieces::of(&path, algo, size) {
+			Ok(file_pieces) => {
+				pieces.insert(name.clone(), file_pieces);
+			}
+			Err(err) => warnings.push(format!("Failed to hash the pieces of {:?}: {}", name, err)),
+		}
+	}
+	pieces
+}
+
+/// Report the byte ranges that differ of the files that don't match and
+/// have pieces in `recorded`, looking each one up with `locate` and hashing
+/// its pieces again.
+pub fn apply_recorded_pieces<F: Fn(&Path) -> Option<PathBuf>>(
+	outcome: CompareOutcome,
+	recorded: &RecordedMetadata,
+	algo: Algorithm,
+	locate: F,
+) -> CompareOutcome {
+	let (compare_results, file_compare_results) = outcome?;
+	let file_compare_results = file_compare_results
+		.into_iter()
+		.map(|result| {
+			let CompareFileResult::FileDiffers { file, was_hash, new_hash } = &result else {
+				return result;
+			};
+			let Some(was) = recorded.pieces(file) else {
+				return result;
+			};
+			let new = locate(file).and_then(|path| {
+				let len = std::fs::metadata(long_path(&path)).ok()?.len();
+				Some((Pieces::of(&path, algo, was.size).ok()?, len))
+			});
+			match new {
+				Some((new, len)) => CompareFileResult::FilePiecesDiffer {
+					file: file.clone(),
+					was_hash: was_hash.clone(),
+					new_hash: new_hash.clone(),
+					piece_size: was.size,
+					ranges: was.differing(&new, len),
+				},
+				None => result,
+			}
+		})
+		.collect();
+	Ok((compare_results, file_compare_results))
+}
@@ -0,0 +1,251 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	cmp::Reverse,
+	collections::{BTreeMap, BinaryHeap},
+	env::temp_dir,
+	fs::{File, remove_file},
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+	process,
+	sync::mpsc::sync_channel,
+	thread,
+	time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use super::{
+	EntryMetadata, HardLinks, HashesReader, HashingReport, ReadOptions, SPINNER_STRINGS, WalkOptions, WriteOptions, hash_entry, stream_hashes, note_warning, walk_files,
+	write_entry, write_header,
+};
+use crate::{Algorithm, Error, utilities::long_path};
+
+/// Amount of discovered files that may wait to be hashed.
+const QUEUE_LEN: usize = 1024;
+
+/// Amount of hashes kept in memory before a sorted run is spilled to disk.
+const RUN_LEN: usize = 100_000;
+
+/// Create hashes for a given path and write them straight to `out_file`,
+/// keeping memory use flat regardless of how many files there are.
+///
+/// Files are discovered on a separate thread and handed over through a bounded
+/// queue. With `sorted`, hashes are written in the same order as
+/// `write_hashes()` would, spilling sorted runs to a temporary directory and
+/// merging them at the end; otherwise they're written as soon as they're
+/// computed.
+///
+/// `out_file` is left out of the hashes when it's under `path`. `report` is
+/// filled like `create_hashes()` does. `WalkOptions::cache` isn't used, as
+/// the whole cache would be held in memory. Hashes of files with several hard
+/// links are still kept for reuse, so memory grows with the number of those
+/// unless `WalkOptions::hash_each_hard_link` is set.
+///
+/// Returns 1 if `out_file` or a sorted run can't be written or read back.
+pub fn create_hashes_bounded(
+	path: &Path,
+	algo: Algorithm,
+	options: &WalkOptions,
+	out_file: &Path,
+	sorted: bool,
+	write_options: &WriteOptions,
+	report: &mut HashingReport,
+) -> i32 {
+	let out = match File::create(long_path(out_file)) {
+		Ok(out) => out,
+		Err(err) => {
+			eprintln!("Failed to create {:?}: {}", out_file, err);
+			return 1;
+		}
+	};
+	// Not to hash what's written so far
+	let mut options = options.clone();
+	options.ignore_file(path, out_file);
+	let options = &options;
+	let mut write_options = write_options.clone();
+	let pb_style = ProgressStyle::default_spinner()
+		.template("{prefix:.bold.dim} {spinner} {pos:>7} files - {msg}")
+		.unwrap()
+		.tick_strings(&SPINNER_STRINGS);
+	let pb = ProgressBar::new_spinner();
+	pb.set_style(pb_style);
+	pb.enable_steady_tick(Duration::from_millis(80));
+	pb.set_message("Hashing files...");
+
+	let mut out = BufWriter::new(out);
+	write_header(&mut out, &write_options);
+	let mut run = BTreeMap::new();
+	let mut spilled_runs = Vec::new();
+	let mut hard_links = HardLinks::default();
+	let mut noted = Vec::new();
+	let mut spill_failure = None;
+
+	thread::scope(|scope| {
+		let (sender, receiver) = sync_channel(QUEUE_LEN);
+		let walked = &mut *report;
+		scope.spawn(move || {
+			for record in walk_files(path, options, walked) {
+				if sender.send(record).is_err() {
+					break;
+				}
+			}
+		});
+
+		for record in receiver {
+			let filename = options.name(path, record.path());
+			let (hash, note) = hash_entry(&record, algo, options, &mut hard_links);
+			noted.extend(note_warning(&filename, &note));
+			let metadata = match note {
+				Some(note) => {
+					write_options.notes.insert(filename.clone(), note);
+					None
+				}
+				None if options.record_metadata => EntryMetadata::of(&record, options.check_metadata),
+				None => None,
+			};
+			pb.inc(1);
+
+			if !sorted {
+				write_run_entry(&mut out, &hash, &filename, metadata, &mut write_options);
+				continue;
+			}
+
+			run.insert(filename, (hash, metadata));
+			if run.len() >= RUN_LEN {
+				// Dropping the queue stops the walk
+				match spill_run(std::mem::take(&mut run), spilled_runs.len()) {
+					Ok(spilled_run) => spilled_runs.push(spilled_run),
+					Err(err) => {
+						spill_failure = Some(err);
+						break;
+					}
+				}
+			}
+		}
+	});
+
+	if spill_failure.is_none() && !spilled_runs.is_empty() && !run.is_empty() {
+		match spill_run(std::mem::take(&mut run), spilled_runs.len()) {
+			Ok(spilled_run) => spilled_runs.push(spilled_run),
+			Err(err) => spill_failure = Some(err),
+		}
+	}
+	if let Some(err) = spill_failure {
+		pb.finish_and_clear();
+		eprintln!("Failed to spill sorted hashes to {:?}: {}", temp_dir(), err);
+		for spilled_run in &spilled_runs {
+			let _ = remove_file(spilled_run);
+		}
+		drop(out);
+		let _ = remove_file(long_path(out_file));
+		return 1;
+	}
+
+	if spilled_runs.is_empty() {
+		for (filename, (hash, metadata)) in &run {
+			write_run_entry(&mut out, hash, filename, *metadata, &mut write_options);
+		}
+	} else {
+		pb.set_message("Merging sorted runs...");
+		let merged = merge_runs(&mut out, &spilled_runs, &mut write_options);
+		for spilled_run in &spilled_runs {
+			let _ = remove_file(spilled_run);
+		}
+		if let Err(err) = merged {
+			pb.finish_and_clear();
+			eprintln!("Failed to read back sorted hashes from {:?}: {}", temp_dir(), err);
+			drop(out);
+			let _ = remove_file(long_path(out_file));
+			return 1;
+		}
+	}
+
+	pb.finish_and_clear();
+	if let Err(err) = out.flush() {
+		eprintln!("Failed to write {:?}: {}", out_file, err);
+		return 1;
+	}
+	report.notes.append(&mut write_options.notes);
+	report.warnings.append(&mut noted);
+	0
+}
+
+/// Hashes and metadata of a sorted run, by filename.
+type Run = BTreeMap<PathBuf, (String, Option<EntryMetadata>)>;
+
+/// Write an entry with its metadata, without keeping the metadata in
+/// `write_options` afterwards.
+fn write_run_entry<W: Write>(
+	out: &mut W,
+	hash: &str,
+	filename: &Path,
+	metadata: Option<EntryMetadata>,
+	write_options: &mut WriteOptions,
+) {
+	if let Some(metadata) = metadata {
+		write_options.metadata.insert(filename.to_owned(), metadata);
+	}
+	write_entry(out, hash, filename, write_options);
+	write_options.metadata.remove(filename);
+}
+
+/// Write a sorted run to a temporary file.
+fn spill_run(run: Run, n: usize) -> io::Result<PathBuf> {
+	let run_file = temp_dir().join(format!("quickdash-{}-{}.run", process::id(), n));
+	let mut out = BufWriter::new(File::create(&run_file)?);
+	let mut write_options = WriteOptions::default();
+	for (filename, (hash, metadata)) in &run {
+		write_run_entry(&mut out, hash, filename, *metadata, &mut write_options);
+	}
+	if let Err(err) = out.flush() {
+		let _ = remove_file(&run_file);
+		return Err(err);
+	}
+	Ok(run_file)
+}
+
+/// K-way merge of sorted runs into the output, failing if a run can't be
+/// read back.
+fn merge_runs<W: Write>(
+	out: &mut W,
+	run_files: &[PathBuf],
+	write_options: &mut WriteOptions,
+) -> io::Result<()> {
+	let to_error = |err: Error| io::Error::other(format!("{:?}", err));
+	let mut runs = run_files
+		.iter()
+		.map(|run_file| stream_hashes(run_file, &ReadOptions::default()).map_err(to_error))
+		.collect::<io::Result<Vec<_>>>()?;
+	let next = |run: &mut HashesReader<_>, i| -> io::Result<Option<_>> {
+		let Some(entry) = run.next() else {
+			return Ok(None);
+		};
+		let (filename, hash) = entry.map_err(to_error)?;
+		Ok(Some(Reverse((filename, hash, run.metadata(), i))))
+	};
+
+	let mut heap = BinaryHeap::new();
+	for (i, run) in runs.iter_mut().enumerate() {
+		heap.extend(next(run, i)?);
+	}
+
+	while let Some(Reverse((filename, hash, metadata, i))) = heap.pop() {
+		write_run_entry(out, &hash, &filename, metadata, write_options);
+		heap.extend(next(&mut runs[i], i)?);
+	}
+	Ok(())
+}
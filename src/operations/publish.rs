@@ -0,0 +1,95 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Copies of hash files uploaded off-box, to S3, WebDAV or SFTP, so a
+//! manifest isn't lost in the same disaster as the data it protects. Uploads
+//! are made with the AWS CLI for S3 and with curl otherwise, using their own
+//! credentials: the AWS profile and environment, `~/.netrc`, or SSH keys.
+
+use std::{fmt, io, path::Path, process::Command, str::FromStr};
+
+/// Where hash files are published, each under its file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishTarget {
+	/// `s3://bucket/prefix`
+	S3(String),
+	/// `https://`, `http://`, `davs://` or `dav://` collection URL.
+	WebDav(String),
+	/// `sftp://[user@]host/path`
+	Sftp(String),
+}
+
+impl PublishTarget {
+	/// URL of `name` under the target.
+	pub fn url(&self, name: &str) -> String {
+		let base = match self {
+			PublishTarget::S3(base) | PublishTarget::WebDav(base) | PublishTarget::Sftp(base) => base,
+		};
+		format!("{}/{}", base.trim_end_matches('/'), name)
+	}
+}
+
+impl FromStr for PublishTarget {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (scheme, rest) = s.split_once("://").ok_or_else(|| format!("{:?} isn't a URL", s))?;
+		if rest.is_empty() {
+			return Err(format!("{:?} names no host or bucket", s));
+		}
+		match scheme {
+			"s3" => Ok(PublishTarget::S3(s.to_owned())),
+			"http" | "https" => Ok(PublishTarget::WebDav(s.to_owned())),
+			// WebDAV is spoken over HTTP
+			"dav" => Ok(PublishTarget::WebDav(format!("http://{}", rest))),
+			"davs" => Ok(PublishTarget::WebDav(format!("https://{}", rest))),
+			"sftp" => Ok(PublishTarget::Sftp(s.to_owned())),
+			_ => Err(format!("can't publish to {}:// URLs, only s3, https, http, davs, dav and sftp", scheme)),
+		}
+	}
+}
+
+impl fmt::Display for PublishTarget {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PublishTarget::S3(url) | PublishTarget::WebDav(url) | PublishTarget::Sftp(url) => f.write_str(url),
+		}
+	}
+}
+
+/// Upload `file` to `target` under its file name, returning where it went.
+pub fn publish(file: &Path, target: &PublishTarget) -> io::Result<String> {
+	let name = file.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the hash file has no name"))?;
+	let url = target.url(&name.to_string_lossy());
+	let mut command = match target {
+		PublishTarget::S3(_) => {
+			let mut aws = Command::new("aws");
+			aws.args(["s3", "cp", "--only-show-errors"]).arg(file).arg(&url);
+			aws
+		}
+		PublishTarget::WebDav(_) | PublishTarget::Sftp(_) => {
+			let mut curl = Command::new("curl");
+			curl.args(["--fail", "--silent", "--show-error", "--netrc-optional", "--ftp-create-dirs", "--upload-file"])
+				.arg(file)
+				.arg("--")
+				.arg(&url);
+			curl
+		}
+	};
+	let status = command.status()?;
+	match status.success() {
+		true => Ok(url),
+		false => Err(io::Error::other(format!("failed to upload to {} ({})", url, status))),
+	}
+}
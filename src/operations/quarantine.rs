@@ -0,0 +1,125 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Quarantine of the files verification finds corrupt, moved or hard-linked
+//! out of the way under the same relative paths, so sync jobs don't spread
+//! them, with a log of what was quarantined.
+
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, Write},
+	path::{Component, Path, PathBuf},
+	time::SystemTime,
+};
+
+use super::{CompareFileResult, audit::utc_time};
+use crate::utilities::long_path;
+
+/// Log of the files quarantined in a quarantine directory, kept in it.
+pub fn quarantine_log(dir: &Path) -> PathBuf {
+	dir.join("quarantine.log")
+}
+
+/// Move the files whose content differs in `file_compare_results` to
+/// `dir`, or hard-link them there with `link`, found with `locate`, under
+/// the names they're stored as. Absolute names are put under `dir` as if
+/// relative, and names going up with `..` are refused. Each is appended to the log in `dir`, with
+/// the time, what was done and its recorded and current hash.
+///
+/// Returns lines telling what was done with each file, or why it couldn't
+/// be.
+pub fn quarantine<F: Fn(&Path) -> Option<PathBuf>>(
+	dir: &Path,
+	file_compare_results: &[CompareFileResult],
+	link: bool,
+	locate: F,
+) -> Vec<String> {
+	let mut lines = Vec::new();
+	let mut log = Vec::new();
+	let time = utc_time(SystemTime::now());
+	let action = match link {
+		true => "linked",
+		false => "moved",
+	};
+	for result in file_compare_results {
+		let (file, was_hash, new_hash) = match result {
+			CompareFileResult::FileDiffers { file, was_hash, new_hash }
+			| CompareFileResult::FilePiecesDiffer { file, was_hash, new_hash, .. } => (file, was_hash.as_str(), new_hash.as_str()),
+			CompareFileResult::PiecesFail { file, .. } => (file, "-", "-"),
+			_ => continue,
+		};
+		let Some(path) = locate(file) else {
+			continue;
+		};
+		let Some(relative) = contained(file) else {
+			lines.push(format!("Failed to quarantine {:?}: its name leads out of {:?}", file, dir));
+			continue;
+		};
+		let target = dir.join(relative);
+		match quarantine_file(&path, &target, link) {
+			Ok(()) => {
+				lines.push(format!("Quarantined {:?} in {:?}", file, target));
+				log.push(format!("{}\t{}\t{}\t{}\t{:?}\n", time, action, was_hash, new_hash, file));
+			}
+			Err(err) => lines.push(format!("Failed to quarantine {:?}: {}", file, err)),
+		}
+	}
+	if !log.is_empty() {
+		let logged = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(quarantine_log(dir))
+			.and_then(|mut file| file.write_all(log.concat().as_bytes()));
+		if let Err(err) = logged {
+			lines.push(format!("Failed to write the quarantine log: {}", err));
+		}
+	}
+	lines
+}
+
+/// `file` made relative by dropping its root or drive, or `None` if it goes
+/// up with `..` or names nothing.
+fn contained(file: &Path) -> Option<PathBuf> {
+	let mut relative = PathBuf::new();
+	for component in file.components() {
+		match component {
+			Component::Normal(name) => relative.push(name),
+			Component::ParentDir => return None,
+			Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+		}
+	}
+	Some(relative).filter(|relative| !relative.as_os_str().is_empty())
+}
+
+/// Move `path` to `target`, copying it across filesystems, or hard-link it
+/// there with `link`, failing if there's a file there already.
+fn quarantine_file(path: &Path, target: &Path, link: bool) -> io::Result<()> {
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(long_path(parent))?;
+	}
+	let (path, target) = (long_path(path), long_path(target));
+	if target.exists() {
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} already exists", target)));
+	}
+	if link {
+		return fs::hard_link(&path, &target);
+	}
+	match fs::rename(&path, &target) {
+		Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+			fs::copy(&path, &target)?;
+			fs::remove_file(&path)
+		}
+		renamed => renamed,
+	}
+}
@@ -0,0 +1,79 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Looking files up by hash or by name, in hash files or the central
+//! inventory, to tell whether a file is already kept somewhere.
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use super::{PathStyle, inventory::sql_string, oci_name};
+use crate::Algorithm;
+
+/// What to look files up by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+	/// Files with this hash, in hexadecimal of any case.
+	Hash(String),
+	/// The file stored under this name, and the others with its hash.
+	Name(PathBuf),
+}
+
+/// Entries of `hashes` matching `query`, with their hash. The named file
+/// comes first when looking up by name.
+pub fn query_hashes<'a>(hashes: &'a BTreeMap<PathBuf, String>, query: &Query) -> Vec<(&'a Path, &'a str)> {
+	let (first, hash) = match query {
+		Query::Hash(hash) => (None, hash.as_str()),
+		Query::Name(name) => match hashes.get_key_value(name) {
+			Some((name, hash)) if !hash.starts_with('-') => (Some((name.as_path(), hash.as_str())), hash.as_str()),
+			_ => return Vec::new(),
+		},
+	};
+	first
+		.into_iter()
+		.chain(
+			hashes
+				.iter()
+				.filter(|(file, stored)| Some(file.as_path()) != first.map(|(name, _)| name) && stored.eq_ignore_ascii_case(hash))
+				.map(|(file, stored)| (file.as_path(), stored.as_str())),
+		)
+		.collect()
+}
+
+/// SQL listing the files of the inventory matching `query`, by host,
+/// directory, name and hash, with when they were last seen intact. Only
+/// runs with `algorithm` are looked through, if given.
+pub fn query_sql(query: &Query, algorithm: Option<Algorithm>) -> String {
+	let matching = match query {
+		Query::Hash(hash) => format!("files.hash = {}", sql_string(&hash.to_uppercase())),
+		Query::Name(name) => format!(
+			"files.hash IN (SELECT hash FROM files WHERE name = {})",
+			sql_string(&PathStyle::Unix.format(name))
+		),
+	};
+	let algorithm = match algorithm {
+		Some(algorithm) => format!(" AND runs.algorithm = {}", sql_string(oci_name(algorithm))),
+		None => String::new(),
+	};
+	format!(
+		"SELECT runs.host, runs.root, files.name, files.hash, MAX(runs.time) AS last_seen \
+		FROM files JOIN runs ON runs.id = files.run \
+		WHERE {}{} AND files.status IN ('created', 'matches', 'assumed') \
+		GROUP BY runs.host, runs.root, files.name, files.hash \
+		ORDER BY runs.host, runs.root, files.name;\n",
+		matching, algorithm
+	)
+}
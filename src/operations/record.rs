@@ -0,0 +1,88 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{fs::Metadata, path::Path, time::SystemTime};
+
+use walkdir::DirEntry;
+
+use super::metadata::{Ownership, WindowsAttributes};
+
+/// A walked file with the metadata later stages need, queried once when it's
+/// found, as every query is a round trip on network filesystems.
+#[derive(Debug, Clone)]
+pub(crate) struct FileRecord {
+	pub(crate) entry: DirEntry,
+	/// Size in bytes, 0 if unknown.
+	pub(crate) len: u64,
+	pub(crate) mtime: Option<SystemTime>,
+	/// Device and inode, where the platform has them.
+	pub(crate) ino: Option<(u64, u64)>,
+	/// Amount of hard links to the file, 1 if unknown.
+	pub(crate) links: u64,
+	/// Owner, group and mode, where the platform has them.
+	pub(crate) ownership: Option<Ownership>,
+	/// Attributes and creation time, on Windows.
+	pub(crate) attributes: Option<WindowsAttributes>,
+}
+
+impl FileRecord {
+	pub(crate) fn new(entry: DirEntry) -> Self {
+		let metadata = entry.metadata().ok();
+		FileRecord {
+			len: metadata.as_ref().map_or(0, Metadata::len),
+			mtime: metadata.as_ref().and_then(|m| m.modified().ok()),
+			ino: metadata.as_ref().and_then(inode),
+			links: metadata.as_ref().map_or(1, links),
+			ownership: metadata.as_ref().and_then(Ownership::of),
+			attributes: metadata.as_ref().and_then(WindowsAttributes::of),
+			entry,
+		}
+	}
+
+	pub(crate) fn path(&self) -> &Path {
+		self.entry.path()
+	}
+
+	/// Device and inode of a file with more than one hard link.
+	pub(crate) fn hard_link_id(&self) -> Option<(u64, u64)> {
+		self.ino.filter(|_| self.links > 1)
+	}
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> Option<(u64, u64)> {
+	use std::os::unix::fs::MetadataExt;
+
+	Some((metadata.dev(), metadata.ino()))
+}
+
+/// Inodes aren't exposed on this platform.
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> Option<(u64, u64)> {
+	None
+}
+
+#[cfg(unix)]
+fn links(metadata: &Metadata) -> u64 {
+	use std::os::unix::fs::MetadataExt;
+
+	metadata.nlink()
+}
+
+/// Hard links aren't counted on this platform.
+#[cfg(not(unix))]
+fn links(_metadata: &Metadata) -> u64 {
+	1
+}
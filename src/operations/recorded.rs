@@ -0,0 +1,186 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fs::symlink_metadata,
+	hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState},
+	path::{Path, PathBuf},
+};
+
+use super::{CompareResult, EntryMetadata, LastVerified, Ownership, Pieces, ReadOptions, WindowsAttributes, read_shard_index, stream_hashes};
+use crate::{Error, utilities::long_path};
+
+/// Hashes and metadata of the manifest entries recorded with metadata, for
+/// telling files that changed from files that rotted while verifying,
+/// checking their ownership, and optionally for not reading files whose
+/// metadata still matches, and the pieces of those recorded with them, for
+/// finding which parts of a file differ.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedMetadata {
+	entries: BTreeMap<PathBuf, (EntryMetadata, String)>,
+	/// Targets of the recorded symlinks.
+	targets: BTreeMap<PathBuf, String>,
+	pieces: BTreeMap<PathBuf, Pieces>,
+	/// Whether files whose metadata matches are assumed unchanged.
+	quick: bool,
+	/// Share of those files read anyway, in millionths.
+	sample: u64,
+	seed: u64,
+	/// Files read anyway when scrubbing.
+	scrubbed: BTreeSet<PathBuf>,
+}
+
+/// How much of the recorded files to read when scrubbing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrubBudget {
+	/// Share of the bytes of the recorded files.
+	Percent(f64),
+	Bytes(u64),
+}
+
+impl RecordedMetadata {
+	/// Load the entries with metadata of the specified hashes file,
+	/// expanding shard indices.
+	pub fn load(file: &Path, options: &ReadOptions) -> Result<Self, Error> {
+		let files = read_shard_index(file)?.unwrap_or_else(|| vec![file.to_owned()]);
+		let (mut entries, mut targets, mut pieces) = (BTreeMap::new(), BTreeMap::new(), BTreeMap::new());
+		for file in files {
+			let mut reader = stream_hashes(&file, options)?;
+			while let Some(entry) = reader.next() {
+				let (file, hash) = entry?;
+				if let Some(file_pieces) = reader.pieces() {
+					pieces.insert(file.clone(), file_pieces.clone());
+				}
+				if let Some(target) = reader.symlink_target() {
+					targets.insert(file, target.to_owned());
+				} else if let Some(metadata) = reader.metadata() {
+					entries.insert(file, (metadata, hash));
+				}
+			}
+		}
+		Ok(RecordedMetadata {
+			entries,
+			targets,
+			pieces,
+			..Default::default()
+		})
+	}
+
+	/// Assume files whose metadata matches are unchanged, except for
+	/// `sample_percent` of them picked at random.
+	pub fn quick(self, sample_percent: f64) -> Self {
+		RecordedMetadata {
+			quick: true,
+			sample: (sample_percent.clamp(0.0, 100.0) * 10_000.0) as u64,
+			seed: RandomState::new().hash_one(()),
+			..self
+		}
+	}
+
+	/// Assume files whose metadata matches are unchanged, except for the
+	/// ones verified least recently, never verified first, up to `budget`
+	/// bytes of them. Scrubbing on each run reads every file in turn.
+	pub fn scrub(self, verified: &LastVerified, budget: ScrubBudget) -> Self {
+		let total: u64 = self.entries.values().map(|(metadata, _)| metadata.size).sum();
+		let budget = match budget {
+			ScrubBudget::Percent(percent) => (total as f64 * percent.clamp(0.0, 100.0) / 100.0).ceil() as u64,
+			ScrubBudget::Bytes(bytes) => bytes,
+		};
+		let mut oldest: Vec<(&PathBuf, u64)> = self.entries.iter().map(|(file, (metadata, _))| (file, metadata.size)).collect();
+		oldest.sort_by_key(|(file, _)| verified.get(file));
+		let mut scrubbed = BTreeSet::new();
+		let mut read = 0;
+		for (file, size) in oldest {
+			if read >= budget {
+				break;
+			}
+			scrubbed.insert(file.clone());
+			read += size;
+		}
+		RecordedMetadata {
+			quick: true,
+			scrubbed,
+			..self
+		}
+	}
+
+	/// The entries stored under `label`, named without it.
+	pub(super) fn under(&self, label: &Path) -> Self {
+		RecordedMetadata {
+			entries: strip_label(&self.entries, label),
+			targets: strip_label(&self.targets, label),
+			pieces: strip_label(&self.pieces, label),
+			scrubbed: self.scrubbed.iter().filter_map(|file| Some(file.strip_prefix(label).ok()?.to_owned())).collect(),
+			..*self
+		}
+	}
+
+	/// Recorded metadata and hash of the file named `name`.
+	pub(super) fn get(&self, name: &Path) -> Option<&(EntryMetadata, String)> {
+		self.entries.get(name)
+	}
+
+	/// Recorded pieces of the file named `name`.
+	pub fn pieces(&self, name: &Path) -> Option<&Pieces> {
+		self.pieces.get(name)
+	}
+
+	/// Recorded target of the symlink stored as `name`.
+	pub(super) fn symlink_target(&self, name: &Path) -> Option<&str> {
+		self.targets.get(name).map(String::as_str)
+	}
+
+	/// Owner, group and mode changes, and Windows attribute changes, of the
+	/// recorded files, looking each one up with `locate`. Files that can't be
+	/// found are left out.
+	pub fn metadata_changes<F: Fn(&Path) -> Option<PathBuf>>(&self, locate: F) -> Vec<CompareResult> {
+		let mut changes = Vec::new();
+		for (file, (metadata, _)) in &self.entries {
+			if metadata.ownership.is_none() && metadata.attributes.is_none() {
+				continue;
+			}
+			let Some(current) = locate(file).and_then(|path| symlink_metadata(long_path(&path)).ok()) else {
+				continue;
+			};
+			if let (Some(was), Some(new)) = (metadata.ownership, Ownership::of(&current))
+				&& was != new
+			{
+				changes.push(CompareResult::MetadataChanged { file: file.clone(), was, new });
+			}
+			if let (Some(was), Some(new)) = (metadata.attributes, WindowsAttributes::of(&current))
+				&& was != new
+			{
+				changes.push(CompareResult::AttributesChanged { file: file.clone(), was, new });
+			}
+		}
+		changes
+	}
+
+	/// Whether the file named `name` is assumed unchanged if its metadata
+	/// matches.
+	pub(super) fn assumes(&self, name: &Path) -> bool {
+		let mut hasher = DefaultHasher::new();
+		(self.seed, name).hash(&mut hasher);
+		self.quick && !self.scrubbed.contains(name) && hasher.finish() % 1_000_000 >= self.sample
+	}
+}
+
+fn strip_label<T: Clone>(entries: &BTreeMap<PathBuf, T>, label: &Path) -> BTreeMap<PathBuf, T> {
+	entries
+		.iter()
+		.filter_map(|(file, entry)| Some((file.strip_prefix(label).ok()?.to_owned(), entry.clone())))
+		.collect()
+}
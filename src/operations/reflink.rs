@@ -0,0 +1,184 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Copies sharing the blocks of the original on copy-on-write filesystems,
+//! and telling whether two files already share theirs.
+//!
+//! Reflinks are made with the `FICLONE` ioctl on Linux (Btrfs, XFS) and
+//! `clonefile()` on macOS (APFS). Shared blocks are found with the
+//! `FS_IOC_FIEMAP` ioctl on Linux; elsewhere files are never taken to share
+//! them.
+
+// None of these have a safe wrapper in std
+#![allow(unsafe_code)]
+
+use std::{fs, io, path::Path};
+
+use crate::utilities::long_path;
+
+/// Make `to` a copy of `from` sharing its blocks, failing if there's a file
+/// there already or the filesystem can't.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+	use std::{fs::File, os::fd::AsRawFd};
+
+	let source = File::open(long_path(from))?;
+	let target = fs::OpenOptions::new().write(true).create_new(true).open(long_path(to))?;
+	// SAFETY: both file descriptors are open for the duration of the call
+	let cloned = unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+	if cloned < 0 {
+		let err = io::Error::last_os_error();
+		drop(target);
+		let _ = fs::remove_file(long_path(to));
+		return Err(err);
+	}
+	Ok(())
+}
+
+/// Make `to` a copy of `from` sharing its blocks, failing if there's a file
+/// there already or the filesystem can't.
+#[cfg(target_os = "macos")]
+pub(super) fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let from = CString::new(from.as_os_str().as_bytes()).map_err(io::Error::other)?;
+	let to = CString::new(to.as_os_str().as_bytes()).map_err(io::Error::other)?;
+	// SAFETY: both paths are NUL-terminated and outlive the call
+	match unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } {
+		0 => Ok(()),
+		_ => Err(io::Error::last_os_error()),
+	}
+}
+
+/// Reflinks aren't supported on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+pub(super) fn reflink(_: &Path, _: &Path) -> io::Result<()> {
+	Err(io::Error::new(io::ErrorKind::Unsupported, "reflinks aren't supported on this platform"))
+}
+
+/// Copy `from` to `to`, sharing its blocks where the filesystem can, and
+/// otherwise in full. Returns whether the blocks are shared.
+pub(super) fn copy(from: &Path, to: &Path) -> io::Result<bool> {
+	if reflink(from, to).is_ok() {
+		return Ok(true);
+	}
+	// `fs::copy()` uses `copy_file_range()` on Linux, which may still share
+	// blocks on its own
+	fs::copy(long_path(from), long_path(to))?;
+	Ok(false)
+}
+
+/// Whether `a` and `b` already share all their blocks, as after a reflink,
+/// so that consolidating them frees no space. Files whose blocks can't be
+/// told, like those not yet written to disk, aren't taken to share them.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn shares_blocks(a: &Path, b: &Path) -> io::Result<bool> {
+	use std::os::unix::fs::MetadataExt;
+
+	let (file_a, file_b) = (fs::File::open(long_path(a))?, fs::File::open(long_path(b))?);
+	if file_a.metadata()?.dev() != file_b.metadata()?.dev() {
+		return Ok(false);
+	}
+	let (extents_a, extents_b) = (fiemap::extents(&file_a)?, fiemap::extents(&file_b)?);
+	Ok(!extents_a.is_empty() && extents_a == extents_b)
+}
+
+/// Shared blocks can't be told on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(super) fn shares_blocks(_: &Path, _: &Path) -> io::Result<bool> {
+	Ok(false)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod fiemap {
+	use std::{fs::File, io, mem, os::fd::AsRawFd};
+
+	/// `struct fiemap` from `linux/fiemap.h`, without its extents.
+	#[repr(C)]
+	#[derive(Default)]
+	struct Header {
+		start: u64,
+		length: u64,
+		flags: u32,
+		mapped_extents: u32,
+		extent_count: u32,
+		reserved: u32,
+	}
+
+	/// `struct fiemap_extent` from `linux/fiemap.h`.
+	#[repr(C)]
+	#[derive(Default, Clone, Copy)]
+	struct Extent {
+		logical: u64,
+		physical: u64,
+		length: u64,
+		reserved64: [u64; 2],
+		flags: u32,
+		reserved: [u32; 3],
+	}
+
+	/// How many extents are asked for at a time.
+	const BATCH: usize = 32;
+
+	#[repr(C)]
+	#[derive(Default)]
+	struct Request {
+		header: Header,
+		extents: [Extent; BATCH],
+	}
+
+	const FS_IOC_FIEMAP: libc::Ioctl = libc::_IOWR::<Header>('f' as u32, 11);
+	const FIEMAP_FLAG_SYNC: u32 = 0x1;
+	const FIEMAP_EXTENT_LAST: u32 = 0x1;
+	/// Extents whose physical location isn't known, or isn't theirs alone:
+	/// unknown, delayed allocation, encoded, not aligned, inline and tail.
+	const FIEMAP_EXTENT_UNPLACED: u32 = 0x2 | 0x4 | 0x8 | 0x100 | 0x200 | 0x400;
+
+	/// The `(logical, physical, length)` of the extents of `file`, or none if
+	/// any of them isn't placed on disk.
+	pub(super) fn extents(file: &File) -> io::Result<Vec<(u64, u64, u64)>> {
+		let mut extents = Vec::new();
+		let mut start = 0;
+		loop {
+			let mut request = Request::default();
+			request.header = Header {
+				start,
+				length: u64::MAX - start,
+				flags: FIEMAP_FLAG_SYNC,
+				extent_count: BATCH as u32,
+				..Header::default()
+			};
+			// SAFETY: `request` holds room for `extent_count` extents past
+			// its header, as the kernel expects, and outlives the call
+			let mapped = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP, &mut request as *mut Request) };
+			if mapped < 0 {
+				return Err(io::Error::last_os_error());
+			}
+			let count = (request.header.mapped_extents as usize).min(BATCH);
+			for extent in &request.extents[..count] {
+				if extent.flags & FIEMAP_EXTENT_UNPLACED != 0 {
+					return Ok(Vec::new());
+				}
+				extents.push((extent.logical, extent.physical, extent.length));
+			}
+			match request.extents[..count].last() {
+				Some(last) if last.flags & FIEMAP_EXTENT_LAST == 0 => start = last.logical + last.length,
+				_ => return Ok(extents),
+			}
+		}
+	}
+
+	const _: () = assert!(mem::size_of::<Header>() == 32 && mem::size_of::<Extent>() == 56);
+}
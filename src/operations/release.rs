@@ -0,0 +1,159 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Signed checksum files of releases, as `SHA256SUMS` in the format of
+//! coreutils, signed with gpg into `SHA256SUMS.asc`, detached or clearsigned.
+
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{self, remove_file},
+	io,
+	path::{Path, PathBuf},
+	process::{self, Command},
+};
+
+use clap::ValueEnum;
+
+use super::{
+	CompareError, CompareOutcome, HashingReport, PathStyle, ReadOptions, WalkOptions, WriteOptions, compare_hashes,
+	create_hashes, create_hashes_for_files, oci_name, read_hashes, write_hashes,
+};
+use crate::{Algorithm, Error};
+
+/// First line of clearsigned files.
+static CLEARSIGNED_HEADER: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+
+/// Conventional name of checksum files of `algo`, like `SHA256SUMS`.
+pub fn sums_name(algo: Algorithm) -> String {
+	format!("{}SUMS", oci_name(algo).replace('-', "").to_uppercase())
+}
+
+/// Algorithm of the checksum file named `name`, or of the signature of one.
+pub fn sums_algorithm(name: &Path) -> Option<Algorithm> {
+	let name = name.file_name()?.to_str()?;
+	Algorithm::value_variants()
+		.iter()
+		.copied()
+		.filter(|&algo| algo != Algorithm::UNSPECIFIED)
+		.find(|&algo| name.strip_prefix(&sums_name(algo)).is_some_and(|rest| rest.is_empty() || rest.starts_with('.')))
+}
+
+/// Write the checksums of the files of `path` to `file`, as coreutils does:
+/// lowercase hashes, two spaces, and `/` separated names, escaped with a
+/// leading `\` if they need to be. Files that couldn't be hashed are left
+/// out, with a warning.
+pub fn create_sums(
+	path: &Path,
+	algo: Algorithm,
+	file: &Path,
+	options: &WalkOptions,
+	report: &mut HashingReport,
+) -> io::Result<()> {
+	let hashes: BTreeMap<PathBuf, String> = create_hashes(path, algo, options, report)
+		.into_iter()
+		.filter(|(_, hash)| !hash.starts_with('-'))
+		.map(|(name, hash)| (name, hash.to_lowercase()))
+		.collect();
+	let options = WriteOptions { path_style: PathStyle::Unix, ..Default::default() };
+	match write_hashes(file, hashes, &options) {
+		0 => Ok(()),
+		_ => Err(io::Error::other("failed to write the checksums")),
+	}
+}
+
+/// Sign `file` with gpg, with the key of `local_user` or the default one,
+/// into `file.asc`, detached or clearsigned, and get the path of the
+/// signature.
+pub fn sign_sums(file: &Path, clearsign: bool, local_user: Option<&str>) -> io::Result<PathBuf> {
+	let mut signature = file.as_os_str().to_owned();
+	signature.push(".asc");
+	let signature = PathBuf::from(signature);
+
+	let mut gpg = Command::new("gpg");
+	gpg.args(["--batch", "--yes", "--armor"]);
+	if let Some(local_user) = local_user {
+		gpg.args(["--local-user", local_user]);
+	}
+	gpg.arg(if clearsign { "--clearsign" } else { "--detach-sign" });
+	let status = gpg.arg("--output").arg(&signature).arg(file).status()?;
+	match status.success() {
+		true => Ok(signature),
+		false => Err(io::Error::other(format!("gpg failed to sign ({})", status))),
+	}
+}
+
+/// Whether the detached gpg `signature` of `file` verifies.
+pub fn check_signature(signature: &Path, file: &Path) -> io::Result<bool> {
+	Ok(Command::new("gpg").arg("--batch").arg("--verify").arg(signature).arg(file).status()?.success())
+}
+
+/// Check the signature of a checksum file with gpg, then the files it lists
+/// in `path` against it.
+///
+/// A clearsigned `signature` holds the checksums. A detached one is of
+/// `file`, by default the signature without its `.asc` or `.sig`. The
+/// algorithm is told by the name of the checksum file unless given, and is
+/// SHA-256 if it doesn't tell.
+pub fn verify_sums(
+	signature: &Path,
+	file: Option<&Path>,
+	path: &Path,
+	algo: Algorithm,
+	options: &WalkOptions,
+	read_options: &ReadOptions,
+	report: &mut HashingReport,
+) -> Result<CompareOutcome, Error> {
+	let failure = |err: io::Error| Error::HashesFileParsingFailure(format!("{:?}: {}", signature, err));
+	let clearsigned = fs::read(signature).map_err(failure)?.starts_with(CLEARSIGNED_HEADER.as_bytes());
+	let sums = match (clearsigned, file) {
+		(true, _) => temp_dir().join(format!("quickdash-sums-{}", process::id())),
+		(false, Some(file)) => file.to_owned(),
+		(false, None) => match signature.extension().and_then(|extension| extension.to_str()) {
+			Some("asc" | "sig") => signature.with_extension(""),
+			_ => return Err(Error::HashesFileParsingFailure(format!("{:?} doesn't name what it signs", signature))),
+		},
+	};
+
+	let signed = match clearsigned {
+		// Decrypting a signed message checks it, and writes what's signed
+		true => Command::new("gpg")
+			.args(["--batch", "--yes", "--output"])
+			.arg(&sums)
+			.arg("--decrypt")
+			.arg(signature)
+			.status()
+			.map_err(failure)?
+			.success(),
+		false => check_signature(signature, &sums).map_err(failure)?,
+	};
+	if !signed {
+		if clearsigned {
+			let _ = remove_file(&sums);
+		}
+		return Ok(Err(CompareError::BadSignature));
+	}
+
+	let loaded = read_hashes(&sums, read_options);
+	if clearsigned {
+		let _ = remove_file(&sums);
+	}
+	let loaded = loaded?;
+	let algo = match algo {
+		Algorithm::UNSPECIFIED => sums_algorithm(file.unwrap_or(signature)).unwrap_or(Algorithm::SHA2256),
+		algo => algo,
+	};
+	let hashes = create_hashes_for_files(path, loaded.keys().cloned().collect(), algo, options, report);
+	Ok(compare_hashes(hashes, loaded))
+}
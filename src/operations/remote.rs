@@ -0,0 +1,109 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Hash files of remote replicas made over SSH, by a quickdash agent run on
+//! the remote host, so only the hash file comes back over the network. The
+//! agent is the `quickdash` installed there, or this very binary streamed
+//! over and removed after.
+
+use std::{
+	env,
+	fs::{self, File},
+	io::{self, Write},
+	path::Path,
+	process::{Command, Stdio},
+	thread,
+};
+
+use clap::ValueEnum;
+
+use crate::Algorithm;
+
+/// Host and path of a `[user@]host:path` target, like scp's.
+pub fn parse_remote_target(target: &str) -> Result<(String, &str), String> {
+	// IPv6 addresses are bracketed, as they hold colons
+	let split = match target.find('[') {
+		Some(start) if !target[..start].contains(':') => target[start..].find(']').map(|end| start + end + 1),
+		_ => target.find(':'),
+	};
+	match split.map(|at| (&target[..at], target[at..].strip_prefix(':'))) {
+		Some((host, Some(path))) if !host.is_empty() && !path.is_empty() => Ok((host.replace(['[', ']'], ""), path)),
+		_ => Err(format!("{:?} isn't like user@host:/path", target)),
+	}
+}
+
+/// Quote `text` for a POSIX shell.
+fn quote(text: &str) -> String {
+	format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Have `agent` on `host` hash the directory `path` there with `algo`,
+/// ignoring the `ignored` patterns, writing the hash file it makes to `out`. With `upload`, the agent is this
+/// binary, copied to a temporary file on the host for the run, which must
+/// then be of the same platform.
+pub fn remote_manifest(
+	host: &str,
+	path: &str,
+	agent: &str,
+	upload: bool,
+	algo: Algorithm,
+	ignored: &[String],
+	out: &Path,
+) -> io::Result<()> {
+	let mut options = String::new();
+	if algo != Algorithm::UNSPECIFIED
+		&& let Some(name) = algo.to_possible_value()
+	{
+		options += &format!("-a {} ", name.get_name());
+	}
+	for pattern in ignored {
+		options += &format!("-i {} ", quote(pattern));
+	}
+	// Hashing a directory that isn't there would make an empty hash file
+	let create = format!(
+		"[ -d {path} ] || {{ echo {missing} >&2; exit 1; }}; \"$agent\" {}create {path} --file \"$t\" --force",
+		options,
+		path = quote(path),
+		missing = quote(&format!("{} isn't a directory", path)),
+	);
+	let agent = match upload {
+		true => "agent=$(mktemp) && cat > \"$agent\" && chmod +x \"$agent\" || exit 1".to_owned(),
+		false => format!("agent={}", quote(agent)),
+	};
+	// The agent's own output goes to stderr, leaving stdout to the hash file
+	let script = format!(
+		"{}; t=$(mktemp) || exit 1; {} >&2; rc=$?; [ $rc -eq 0 ] && cat \"$t\"; rm -f \"$t\"{}; exit $rc",
+		agent,
+		create,
+		if upload { " \"$agent\"" } else { "" }
+	);
+	let mut ssh = Command::new("ssh")
+		.arg("--")
+		.arg(host)
+		.arg(script)
+		.stdin(if upload { Stdio::piped() } else { Stdio::null() })
+		.stdout(File::create(out)?)
+		.spawn()?;
+	let sender = ssh.stdin.take().map(|mut stdin| {
+		thread::spawn(move || -> io::Result<()> {
+			stdin.write_all(&fs::read(env::current_exe()?)?)
+		})
+	});
+	let status = ssh.wait()?;
+	let sent = sender.map_or(Ok(()), |sender| sender.join().expect("sending the agent doesn't panic"));
+	match status.success() {
+		true => sent,
+		false => Err(io::Error::other(format!("the agent on {} failed ({})", host, status))),
+	}
+}
@@ -0,0 +1,92 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use super::{HashingReport, WalkOptions, create_hashes, create_hashes_for_files};
+use crate::Algorithm;
+
+/// Create hashes for several directories into one mapping, storing the
+/// entries of each directory under its label.
+///
+/// `roots` pairs each directory with its label. `report` is filled like
+/// `create_hashes()` does.
+pub fn create_hashes_for_roots(
+	roots: &[(PathBuf, String)],
+	algo: Algorithm,
+	options: &WalkOptions,
+	report: &mut HashingReport,
+) -> BTreeMap<PathBuf, String> {
+	let mut hashes = BTreeMap::new();
+	for (path, label) in roots {
+		let mut root_report = HashingReport::default();
+		let root_hashes = match &options.recorded {
+			Some(recorded) => {
+				let options = WalkOptions { recorded: Some(recorded.under(Path::new(label))), ..options.clone() };
+				create_hashes(path, algo, &options, &mut root_report)
+			}
+			None => create_hashes(path, algo, options, &mut root_report),
+		};
+		let labelled = |file: PathBuf| Path::new(label).join(file);
+		hashes.extend(root_hashes.into_iter().map(|(file, hash)| (labelled(file), hash)));
+		report.notes.extend(root_report.notes.into_iter().map(|(file, note)| (labelled(file), note)));
+		report.metadata.extend(root_report.metadata.into_iter().map(|(file, metadata)| (labelled(file), metadata)));
+		report.assumed.extend(root_report.assumed.into_iter().map(labelled));
+		report.drifted.extend(root_report.drifted.into_iter().map(|(file, drift)| (labelled(file), drift)));
+		report.metadata_changed.extend(
+			root_report.metadata_changed.into_iter().map(|(file, change)| (labelled(file), change)),
+		);
+		report.attributes_changed.extend(
+			root_report.attributes_changed.into_iter().map(|(file, change)| (labelled(file), change)),
+		);
+		report.retargeted.extend(root_report.retargeted.into_iter().map(|(file, targets)| (labelled(file), targets)));
+		report.warnings.extend(root_report.warnings.into_iter().map(|warning| format!("{}: {}", label, warning)));
+		report.errors.extend(root_report.errors.into_iter().map(|error| format!("{}: {}", label, error)));
+		report.files_scanned += root_report.files_scanned;
+		report.bytes_hashed += root_report.bytes_hashed;
+		report.unhashed.extend(root_report.unhashed.into_iter().map(labelled));
+	}
+	hashes
+}
+
+/// Create hash mappings for files named like `create_hashes_for_roots()`
+/// does, looking each one up in the directory of its label.
+///
+/// Files not under any of the labels are skipped.
+pub fn create_hashes_for_labelled_files(
+	roots: &[(PathBuf, String)],
+	files: Vec<PathBuf>,
+	algo: Algorithm,
+	options: &WalkOptions,
+	report: &mut HashingReport,
+) -> BTreeMap<PathBuf, String> {
+	let mut hashes = BTreeMap::new();
+	for (path, label) in roots {
+		let labelled = files
+			.iter()
+			.filter_map(|file| file.strip_prefix(label).ok())
+			.map(Path::to_owned)
+			.collect();
+		hashes.extend(
+			create_hashes_for_files(path, labelled, algo, options, report)
+				.into_iter()
+				.map(|(file, hash)| (Path::new(label).join(file), hash)),
+		);
+	}
+	hashes
+}
@@ -0,0 +1,152 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Running as a systemd service of `Type=notify`: telling systemd when runs
+//! are ready, pinging its watchdog while they hash, and stopping hashing on
+//! SIGTERM so what was hashed can still be written.
+
+// signal() has no safe wrapper in std
+#![cfg_attr(unix, allow(unsafe_code))]
+
+use std::{
+	env, io,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{self, RecvTimeoutError, Sender},
+	},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+/// Exit code of runs stopped by SIGTERM, as if killed by it.
+pub const STOPPED_EXIT_CODE: i32 = 128 + 15;
+
+/// Whether SIGTERM was received since `stop_on_sigterm()`.
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Whether hashing should stop, for SIGTERM having been received.
+pub fn stopping() -> bool {
+	STOPPING.load(Ordering::Relaxed)
+}
+
+/// Stop hashing on SIGTERM instead of exiting straight away. Files being
+/// read are still hashed, others are left out.
+pub fn stop_on_sigterm() {
+	imp::handle_sigterm();
+}
+
+/// File the hashes made before stopping are written to, next to the hash
+/// file, so a stopped run doesn't pass for a whole one.
+pub fn partial_path(hash_file: &Path) -> PathBuf {
+	let mut path = hash_file.as_os_str().to_owned();
+	path.push(".partial");
+	PathBuf::from(path)
+}
+
+/// Send `state`, like `READY=1`, to the service manager, if it's listening.
+pub fn sd_notify(state: &str) -> io::Result<()> {
+	match env::var_os("NOTIFY_SOCKET") {
+		Some(socket) => imp::send(&socket, state),
+		None => Ok(()),
+	}
+}
+
+/// Thread pinging the watchdog of the service manager, if it set one for
+/// this process, until dropped.
+#[derive(Debug)]
+pub struct Watchdog {
+	stop: Sender<()>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+	/// Start pinging the watchdog twice as often as it expects, if it's
+	/// enabled for this process.
+	pub fn start() -> Option<Self> {
+		let timeout: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+		if env::var("WATCHDOG_PID").is_ok_and(|pid| pid != std::process::id().to_string()) || timeout == 0 {
+			return None;
+		}
+		let interval = Duration::from_micros(timeout / 2);
+		let (stop, stopped) = mpsc::channel();
+		let thread = thread::spawn(move || {
+			while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+				// A missed ping gets the service restarted, nothing to do here
+				let _ = sd_notify("WATCHDOG=1");
+			}
+		});
+		Some(Watchdog { stop, thread: Some(thread) })
+	}
+}
+
+impl Drop for Watchdog {
+	fn drop(&mut self) {
+		let _ = self.stop.send(());
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+#[cfg(unix)]
+mod imp {
+	use std::{
+		ffi::OsStr,
+		io,
+		os::unix::{ffi::OsStrExt, net::UnixDatagram},
+		sync::atomic::Ordering,
+	};
+
+	use super::STOPPING;
+
+	extern "C" fn on_sigterm(_: libc::c_int) {
+		STOPPING.store(true, Ordering::Relaxed);
+	}
+
+	pub(super) fn handle_sigterm() {
+		// SAFETY: the handler only stores to an atomic, which is
+		// async-signal-safe
+		unsafe { libc::signal(libc::SIGTERM, on_sigterm as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+	}
+
+	pub(super) fn send(socket: &OsStr, state: &str) -> io::Result<()> {
+		let datagram = UnixDatagram::unbound()?;
+		match socket.as_bytes().strip_prefix(b"@") {
+			// Abstract sockets, on Linux
+			#[cfg(target_os = "linux")]
+			Some(name) => {
+				use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+				datagram.send_to_addr(state.as_bytes(), &SocketAddr::from_abstract_name(name)?)?;
+			}
+			_ => {
+				datagram.send_to(state.as_bytes(), socket)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// systemd only runs on Unix.
+#[cfg(not(unix))]
+mod imp {
+	use std::{ffi::OsStr, io};
+
+	pub(super) fn handle_sigterm() {}
+
+	pub(super) fn send(_: &OsStr, _: &str) -> io::Result<()> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "there's no service manager to notify on this platform"))
+	}
+}
@@ -0,0 +1,168 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{BufRead, BufReader, Write},
+	path::{Component, Path, PathBuf},
+};
+
+use clap::ValueEnum;
+
+use super::{
+	BOM, CompareOutcome, CompareResult, ReadOptions, WriteOptions, comment_text, compare_hashes, read_hashes,
+	write_hashes,
+};
+use crate::{Error, utilities::long_path};
+
+/// Comment on the first line of a shard index file.
+pub static SHARD_INDEX_HEADER: &str = "quickdash shard index";
+
+/// How to split a manifest into shards.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum ShardBy {
+	/// One shard per top-level directory, plus one with an empty key for
+	/// top-level files, which no directory name can take.
+	TopLevelDir,
+	/// One shard per first two hex digits of the hash.
+	HashPrefix,
+}
+
+impl ShardBy {
+	fn shard_key(&self, file: &Path, hash: &str) -> String {
+		match *self {
+			ShardBy::TopLevelDir => {
+				let mut components = file.components().filter(|c| matches!(c, Component::Normal(_)));
+				match (components.next(), components.next()) {
+					(Some(dir), Some(_)) => dir.as_os_str().to_string_lossy().into_owned(),
+					_ => String::new(),
+				}
+			}
+			ShardBy::HashPrefix => hash.chars().take(2).collect(),
+		}
+	}
+}
+
+/// Split the specified hashes into shards written next to `out_file`, then
+/// write an index listing them to `out_file` itself.
+///
+/// Shards are named `<out_file stem>.<key>.hash`, so top-level files sharded
+/// by directory go in `<out_file stem>..hash`. Shards listed in the index
+/// being replaced that aren't in the new one are removed.
+pub fn write_sharded_hashes(
+	out_file: &Path,
+	hashes: BTreeMap<PathBuf, String>,
+	shard_by: ShardBy,
+	options: &WriteOptions,
+) -> i32 {
+	let mut shards: BTreeMap<String, BTreeMap<PathBuf, String>> = BTreeMap::new();
+	for (file, hash) in hashes {
+		shards.entry(shard_by.shard_key(&file, &hash)).or_default().insert(file, hash);
+	}
+
+	let stem = out_file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+	let previous = read_shard_index(out_file).ok().flatten().unwrap_or_default();
+	let mut written = Vec::new();
+	let mut index = File::create(long_path(out_file)).unwrap();
+	options.comment_style.write(&mut index, SHARD_INDEX_HEADER);
+	for (key, shard) in shards {
+		let shard_name = format!("{}.{}.hash", stem, key);
+		let rval = write_hashes(&out_file.with_file_name(&shard_name), shard, options);
+		if rval != 0 {
+			return rval;
+		}
+		writeln!(index, "{}", shard_name).unwrap();
+		written.push(out_file.with_file_name(shard_name));
+	}
+
+	index.flush().expect("Failed to flush output file");
+	// Only what this would have written, not anything an edited index lists
+	let stale = previous.into_iter().filter(|shard| {
+		let name = shard.file_name().unwrap_or_default().to_string_lossy();
+		shard != out_file
+			&& shard.parent() == out_file.parent()
+			&& name.starts_with(&format!("{}.", stem))
+			&& name.ends_with(".hash")
+			&& !written.contains(shard)
+	});
+	for shard in stale {
+		if let Err(err) = fs::remove_file(long_path(&shard)) {
+			eprintln!("Failed to remove the stale shard {:?}: {}", shard, err);
+		}
+	}
+	0
+}
+
+/// Get the shard files listed in the specified file, or `None` if it's a
+/// regular hashes file.
+pub fn read_shard_index(file: &Path) -> Result<Option<Vec<PathBuf>>, Error> {
+	let file_handle = File::open(long_path(file)).map_err(|err| Error::HashesFileParsingFailure(err.to_string()))?;
+	let mut reader = BufReader::new(file_handle);
+	// Regular hashes files may be in any encoding, so don't insist on UTF-8 yet
+	let mut first = Vec::new();
+	reader
+		.read_until(b'\n', &mut first)
+		.map_err(|err| Error::HashesFileParsingFailure(err.to_string()))?;
+	if comment_text(String::from_utf8_lossy(&first).trim_start_matches(BOM)) != Some(SHARD_INDEX_HEADER) {
+		return Ok(None);
+	}
+
+	let base = file.parent().unwrap_or(Path::new(""));
+	let mut shards = Vec::new();
+	for line in reader.lines() {
+		let line = line.map_err(|err| Error::HashesFileParsingFailure(err.to_string()))?;
+		let line = line.trim();
+		if line.is_empty() || comment_text(line).is_some() {
+			continue;
+		}
+		shards.push(base.join(line));
+	}
+
+	Ok(Some(shards))
+}
+
+/// Compare current hashes against a sharded manifest, loading one shard at a
+/// time.
+///
+/// Current hashes not present in any shard are reported as added.
+pub fn compare_sharded_hashes(
+	mut current_hashes: BTreeMap<PathBuf, String>,
+	shards: &[PathBuf],
+	options: &ReadOptions,
+) -> Result<CompareOutcome, Error> {
+	let mut compare_results = Vec::new();
+	let mut file_compare_results = Vec::new();
+
+	for shard in shards {
+		let loaded_hashes = read_hashes(shard, options)?;
+		let current_subset = loaded_hashes
+			.keys()
+			.filter_map(|key| current_hashes.remove_entry(key))
+			.collect();
+
+		match compare_hashes(current_subset, loaded_hashes) {
+			Ok((results, file_results)) => {
+				compare_results.extend(results);
+				file_compare_results.extend(file_results);
+			}
+			Err(err) => return Ok(Err(err)),
+		}
+	}
+
+	compare_results.extend(current_hashes.into_keys().map(CompareResult::FileAdded));
+
+	Ok(Ok((compare_results, file_compare_results)))
+}
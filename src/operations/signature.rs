@@ -0,0 +1,161 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! librsync signatures of hashed files, so `rdiff delta` and other librsync
+//! users can work out deltas against the archived copies without reading
+//! them.
+//!
+//! A signature starts with the magic number of BLAKE2 signatures, the block
+//! length and the strong sum length, as 4 big endian bytes each, followed by
+//! the rolling checksum of each block, as 4 big endian bytes, and its
+//! BLAKE2b-256 hash.
+
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{self, BufWriter, Read, Write},
+	path::{Path, PathBuf},
+};
+
+use blake2::{Blake2b, Digest, digest::consts::U32};
+
+use crate::{HashOptions, hashing::read_file_chunks, utilities::long_path};
+
+/// librsync's `RS_BLAKE2_SIG_MAGIC`.
+const BLAKE2_SIG_MAGIC: u32 = 0x7273_0137;
+
+/// Bytes of the BLAKE2b hash of each block.
+const STRONG_LEN: u32 = 32;
+
+/// Added to each byte by the rolling checksum, as in rsync.
+const CHAR_OFFSET: u32 = 31;
+
+/// Directory the signatures of the files listed in `hash_file` are kept in,
+/// next to it.
+pub fn signature_dir(hash_file: &Path) -> PathBuf {
+	let mut dir = hash_file.as_os_str().to_owned();
+	dir.push(".rsig");
+	PathBuf::from(dir)
+}
+
+/// Signature of the file stored as `name`, in `dir`.
+pub fn signature_path(dir: &Path, name: &Path) -> PathBuf {
+	let mut path = dir.join(name).into_os_string();
+	path.push(".sig");
+	PathBuf::from(path)
+}
+
+/// Write the signatures of the regular files in `hashes` bigger than a
+/// block, found under `base`, to `dir`, in blocks of `block_len` bytes.
+///
+/// Signatures of the right size that are newer than their file are kept.
+/// Files that can't be read, or that changed while read, get a warning
+/// instead.
+pub fn write_signatures(
+	base: &Path,
+	hashes: &BTreeMap<PathBuf, String>,
+	notes: &BTreeMap<PathBuf, String>,
+	dir: &Path,
+	block_len: u32,
+	options: &HashOptions,
+	warnings: &mut Vec<String>,
+) {
+	for name in hashes.keys() {
+		if notes.contains_key(name) {
+			continue;
+		}
+		let path = base.join(name);
+		let signature = signature_path(dir, name);
+		let written = fs::metadata(long_path(&path)).and_then(|metadata| match metadata.len() <= block_len as u64 {
+			true => Ok(true),
+			false if is_current(&signature, &metadata, block_len) => Ok(true),
+			false => write_signature(&path, &signature, block_len, options),
+		});
+		match written {
+			Ok(true) => {}
+			Ok(false) => warnings.push(format!("Changed while read, no signature written: {:?}", name)),
+			Err(err) => warnings.push(format!("Failed to write the signature of {:?}: {}", name, err)),
+		}
+	}
+}
+
+/// Write the signature of the file at `path` to `signature`, telling
+/// whether the file stayed the same while read.
+fn write_signature(path: &Path, signature: &Path, block_len: u32, options: &HashOptions) -> io::Result<bool> {
+	if let Some(parent) = signature.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	let mut out = BufWriter::new(File::create(signature)?);
+	for field in [BLAKE2_SIG_MAGIC, block_len, STRONG_LEN] {
+		out.write_all(&field.to_be_bytes())?;
+	}
+	let mut block = Vec::with_capacity(block_len as usize);
+	let mut written = Ok(());
+	let stable = read_file_chunks(path, options, |chunk| {
+		let mut data = &chunk[..];
+		while !data.is_empty() {
+			let take = (block_len as usize - block.len()).min(data.len());
+			block.extend_from_slice(&data[..take]);
+			data = &data[take..];
+			if block.len() == block_len as usize {
+				written = write_block(&mut out, &block);
+				block.clear();
+			}
+		}
+		written.is_ok()
+	})?;
+	written?;
+	if !block.is_empty() {
+		write_block(&mut out, &block)?;
+	}
+	out.flush()?;
+	if !stable {
+		drop(out);
+		let _ = fs::remove_file(signature);
+	}
+	Ok(stable)
+}
+
+fn write_block<W: Write>(out: &mut W, block: &[u8]) -> io::Result<()> {
+	out.write_all(&rollsum(block).to_be_bytes())?;
+	out.write_all(&Blake2b::<U32>::digest(block))
+}
+
+/// rsync's rolling checksum of a block.
+fn rollsum(block: &[u8]) -> u32 {
+	let (mut s1, mut s2) = (0u32, 0u32);
+	for &byte in block {
+		s1 = s1.wrapping_add(byte as u32 + CHAR_OFFSET);
+		s2 = s2.wrapping_add(s1);
+	}
+	(s2 << 16) | (s1 & 0xffff)
+}
+
+/// Whether `signature` is that of the file of `metadata` in blocks of
+/// `block_len` bytes, going by its size and header and being newer.
+fn is_current(signature: &Path, metadata: &fs::Metadata, block_len: u32) -> bool {
+	let blocks = metadata.len().div_ceil(block_len as u64);
+	let mut header = [0; 12];
+	let read = File::open(signature).and_then(|mut file| {
+		file.read_exact(&mut header)?;
+		file.metadata()
+	});
+	let Ok(current) = read else {
+		return false;
+	};
+	let expected: Vec<u8> = [BLAKE2_SIG_MAGIC, block_len, STRONG_LEN].iter().flat_map(|field| field.to_be_bytes()).collect();
+	current.len() == 12 + blocks * (4 + STRONG_LEN as u64)
+		&& header[..] == expected[..]
+		&& matches!((current.modified(), metadata.modified()), (Ok(signed), Ok(modified)) if signed >= modified)
+}
@@ -0,0 +1,218 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Files alike in content, like re-encodes or edited documents, which exact
+//! hashes tell apart, by how alike their ssdeep digests are.
+
+use std::{
+	collections::{BTreeMap, HashSet},
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::{
+		Mutex,
+		atomic::{AtomicUsize, Ordering},
+	},
+	thread,
+	time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use super::{HashingReport, SPINNER_STRINGS, WalkOptions, walk_files};
+use crate::hashing::{MIN_BLOCK_SIZE, SPAMSUM_LENGTH, try_fuzzy_hash_file};
+
+/// Length of the substring digests must have in common to be alike at all.
+const COMMON_LENGTH: usize = 7;
+
+/// A parsed ssdeep digest, `block size:digest:digest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyHash {
+	block_size: u64,
+	/// Digests at the block size and twice it, with runs of more than three
+	/// of a character cut short, as they tell little.
+	first: Vec<u8>,
+	second: Vec<u8>,
+}
+
+impl FromStr for FuzzyHash {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.splitn(3, ':');
+		match (parts.next().map(str::parse), parts.next(), parts.next()) {
+			(Some(Ok(block_size)), Some(first), Some(second)) if block_size >= MIN_BLOCK_SIZE => Ok(FuzzyHash {
+				block_size,
+				first: eliminate_sequences(first.as_bytes()),
+				second: eliminate_sequences(second.as_bytes()),
+			}),
+			_ => Err(format!("{:?} is not an ssdeep digest", s)),
+		}
+	}
+}
+
+impl FuzzyHash {
+	/// How alike the files of two digests are, from 0 for nothing in common
+	/// to 100, scored the way ssdeep does.
+	pub fn similarity(&self, other: &FuzzyHash) -> u32 {
+		let (a, b) = (self, other);
+		if a.block_size == b.block_size && a.first == b.first {
+			return 100;
+		}
+		if a.block_size == b.block_size {
+			score(&a.first, &b.first, a.block_size).max(score(&a.second, &b.second, a.block_size * 2))
+		} else if a.block_size * 2 == b.block_size {
+			score(&a.second, &b.first, b.block_size)
+		} else if a.block_size == b.block_size * 2 {
+			score(&a.first, &b.second, a.block_size)
+		} else {
+			0
+		}
+	}
+}
+
+/// Cut runs of more than three of a character down to three.
+fn eliminate_sequences(digest: &[u8]) -> Vec<u8> {
+	let mut cut = Vec::with_capacity(digest.len());
+	for &c in digest {
+		if !cut.ends_with(&[c, c, c]) {
+			cut.push(c);
+		}
+	}
+	cut
+}
+
+/// Score of two digests at the same block size.
+fn score(a: &[u8], b: &[u8], block_size: u64) -> u32 {
+	if a.len() > SPAMSUM_LENGTH || b.len() > SPAMSUM_LENGTH || !has_common_substring(a, b) {
+		return 0;
+	}
+	let distance = edit_distance(a, b) as u64;
+	let scaled = distance * SPAMSUM_LENGTH as u64 / (a.len() + b.len()) as u64 * 100 / SPAMSUM_LENGTH as u64;
+	if scaled >= 100 {
+		return 0;
+	}
+	let score = 100 - scaled;
+	// Digests of small block sizes can't tell much
+	let cap = match block_size >= (99 + COMMON_LENGTH as u64) / COMMON_LENGTH as u64 * MIN_BLOCK_SIZE {
+		true => score,
+		false => block_size / MIN_BLOCK_SIZE * a.len().min(b.len()) as u64,
+	};
+	score.min(cap) as u32
+}
+
+fn has_common_substring(a: &[u8], b: &[u8]) -> bool {
+	let windows: HashSet<&[u8]> = a.windows(COMMON_LENGTH).collect();
+	b.windows(COMMON_LENGTH).any(|window| windows.contains(window))
+}
+
+/// Edit distance, inserts and deletes costing 1 and changes 2.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+	let mut row: Vec<usize> = (0..=b.len()).collect();
+	for (i, &ca) in a.iter().enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, &cb) in b.iter().enumerate() {
+			let change = diagonal + if ca == cb { 0 } else { 2 };
+			diagonal = row[j + 1];
+			row[j + 1] = change.min(row[j] + 1).min(diagonal + 1);
+		}
+	}
+	row[b.len()]
+}
+
+/// ssdeep digests of the non-empty regular files under `path`, walked and
+/// read as set by `options`, named the way `create_hashes()` names them.
+///
+/// Files that can't be read get a warning in `report`.
+pub fn fuzzy_hashes(path: &Path, options: &WalkOptions, report: &mut HashingReport) -> BTreeMap<PathBuf, String> {
+	let pb_style = ProgressStyle::default_bar()
+		.template("{prefix:.bold.dim} {spinner} {wide_bar} {pos:>7}/{len:7} ETA: {eta} - {msg}")
+		.unwrap()
+		.tick_strings(&SPINNER_STRINGS);
+	let pb = ProgressBar::new_spinner();
+	pb.set_style(pb_style);
+	pb.enable_steady_tick(Duration::from_millis(80));
+	pb.set_message("Finding files to hash...");
+	let files: Vec<PathBuf> = walk_files(path, options, report)
+		.filter(|record| record.entry.file_type().is_file() && record.len > 0)
+		.map(|record| record.path().to_owned())
+		.collect();
+
+	pb.reset();
+	pb.set_length(files.len() as u64);
+	pb.set_message("Hashing files...");
+	let next = AtomicUsize::new(0);
+	let hashed = Mutex::new((BTreeMap::new(), Vec::new()));
+	thread::scope(|scope| {
+		for _ in 0..options.hash_threads.max(1) {
+			scope.spawn(|| {
+				while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+					let name = options.name(path, file);
+					let digest = try_fuzzy_hash_file(file, &options.hash_options);
+					let (digests, warnings) = &mut *hashed.lock().unwrap();
+					match digest {
+						Ok(digest) => {
+							digests.insert(name, digest);
+						}
+						Err(err) => warnings.push(format!("Failed to read {:?}: {}", name, err)),
+					}
+					pb.inc(1);
+				}
+			});
+		}
+	});
+	pb.finish_and_clear();
+	let (digests, warnings) = hashed.into_inner().unwrap();
+	report.warnings.extend(warnings);
+	digests
+}
+
+/// Clusters of files whose digests are at least `threshold` alike, directly
+/// or through other files of the cluster, each file with the best score it
+/// has with another. Files alike to no other are left out.
+///
+/// Digests that don't parse are passed over.
+pub fn similar_files(digests: &BTreeMap<PathBuf, String>, threshold: u32) -> Vec<Vec<(PathBuf, u32)>> {
+	let parsed: Vec<(&PathBuf, FuzzyHash)> =
+		digests.iter().filter_map(|(file, digest)| Some((file, digest.parse().ok()?))).collect();
+	// Union-find of the files, by index
+	let mut parent: Vec<usize> = (0..parsed.len()).collect();
+	let mut best = vec![0; parsed.len()];
+	fn root(parent: &mut [usize], mut i: usize) -> usize {
+		while parent[i] != i {
+			parent[i] = parent[parent[i]];
+			i = parent[i];
+		}
+		i
+	}
+	for i in 0..parsed.len() {
+		for j in i + 1..parsed.len() {
+			let score = parsed[i].1.similarity(&parsed[j].1);
+			if score >= threshold.max(1) {
+				best[i] = best[i].max(score);
+				best[j] = best[j].max(score);
+				let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+				parent[a.max(b)] = a.min(b);
+			}
+		}
+	}
+	let mut clusters: BTreeMap<usize, Vec<(PathBuf, u32)>> = BTreeMap::new();
+	for i in 0..parsed.len() {
+		if best[i] > 0 {
+			let cluster = root(&mut parent, i);
+			clusters.entry(cluster).or_default().push((parsed[i].0.clone(), best[i]));
+		}
+	}
+	clusters.into_values().collect()
+}
@@ -0,0 +1,331 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Point-in-time snapshots of the volume hashed files are on, so files being
+//! written to or kept locked, like mailboxes and databases, are read as they
+//! were at one instant.
+//!
+//! Snapshots are taken with the tools of the volume manager or filesystem:
+//! PowerShell for Volume Shadow Copies, and `btrfs`, `zfs` or `lvcreate` on
+//! Linux.
+
+use std::{
+	io,
+	path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+
+/// Kinds of snapshots files can be hashed from.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum SnapshotKind {
+	/// A Volume Shadow Copy of the drive, on Windows. Needs administrator
+	/// rights.
+	Vss,
+	/// A read-only snapshot of the btrfs subvolume, made inside it.
+	Btrfs,
+	/// A snapshot of the ZFS dataset, read under its `.zfs/snapshot`.
+	Zfs,
+	/// A snapshot of the LVM logical volume, taking a tenth of its size from
+	/// the free space of its volume group, mounted read-only.
+	Lvm,
+}
+
+/// A snapshot taken by `snapshot()`, removed when dropped.
+#[derive(Debug)]
+pub struct Snapshot {
+	kind: SnapshotKind,
+	/// What the snapshot is known by to remove it.
+	id: String,
+	/// Where the snapshot is, as a whole.
+	#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+	root: PathBuf,
+	path: PathBuf,
+}
+
+impl Snapshot {
+	/// Where the snapshotted path is in the snapshot.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for Snapshot {
+	fn drop(&mut self) {
+		let removed = match self.kind {
+			SnapshotKind::Vss => vss::remove(&self.id),
+			SnapshotKind::Btrfs | SnapshotKind::Zfs | SnapshotKind::Lvm => linux::remove(self),
+		};
+		if let Err(err) = removed {
+			eprintln!("Failed to remove the snapshot {}: {}", self.id, err);
+		}
+	}
+}
+
+/// Take a snapshot of the volume `path` is on, of `kind`.
+pub fn snapshot(kind: SnapshotKind, path: &Path) -> io::Result<Snapshot> {
+	match kind {
+		SnapshotKind::Vss => vss::create(path),
+		SnapshotKind::Btrfs => linux::btrfs(path),
+		SnapshotKind::Zfs => linux::zfs(path),
+		SnapshotKind::Lvm => linux::lvm(path),
+	}
+}
+
+/// Run `command`, returning what it printed, failing if it fails.
+#[cfg(any(windows, target_os = "linux"))]
+fn run(command: &mut std::process::Command) -> io::Result<String> {
+	let output = command.output()?;
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		let program = command.get_program().to_string_lossy();
+		return Err(io::Error::other(format!("{} failed ({}): {}", program, output.status, stderr.trim())));
+	}
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(windows)]
+mod vss {
+	use std::{
+		io,
+		path::{Component, Path, PathBuf, Prefix},
+		process::Command,
+	};
+
+	use super::{Snapshot, SnapshotKind, run};
+
+	/// Shadow copy the drive `path` is on with `Win32_ShadowCopy`.
+	pub(super) fn create(path: &Path) -> io::Result<Snapshot> {
+		let path = path.canonicalize()?;
+		let mut components = path.components();
+		let drive = match components.next() {
+			Some(Component::Prefix(prefix)) => match prefix.kind() {
+				Prefix::VerbatimDisk(drive) | Prefix::Disk(drive) => drive as char,
+				_ => return Err(io::Error::new(io::ErrorKind::Unsupported, "only local drives can be shadow copied")),
+			},
+			_ => return Err(io::Error::other(format!("{:?} isn't on a drive", path))),
+		};
+		let relative: PathBuf = components.filter(|component| !matches!(component, Component::RootDir)).collect();
+		let script = format!(
+			"$created = Invoke-CimMethod -ClassName Win32_ShadowCopy -MethodName Create -Arguments @{{ Volume = '{}:\\'; Context = 'ClientAccessible' }}; \
+			if ($created.ReturnValue -ne 0) {{ throw \"Win32_ShadowCopy.Create returned $($created.ReturnValue)\" }}; \
+			$copy = Get-CimInstance -ClassName Win32_ShadowCopy -Filter \"ID = '$($created.ShadowID)'\"; \
+			$copy.ID; $copy.DeviceObject",
+			drive
+		);
+		let output = powershell(&script)?;
+		let mut lines = output.lines().map(str::trim).filter(|line| !line.is_empty());
+		let (Some(id), Some(device)) = (lines.next(), lines.next()) else {
+			return Err(io::Error::other(format!("unexpected output of Win32_ShadowCopy: {:?}", output)));
+		};
+		// The device is a volume, its root needs the trailing separator
+		let root = PathBuf::from(format!("{}\\", device));
+		Ok(Snapshot { kind: SnapshotKind::Vss, id: id.to_owned(), path: root.join(relative), root })
+	}
+
+	/// Remove the shadow copy `id`.
+	pub(super) fn remove(id: &str) -> io::Result<()> {
+		let script = format!("Get-CimInstance -ClassName Win32_ShadowCopy -Filter \"ID = '{}'\" | Remove-CimInstance", id.replace('\'', "''"));
+		powershell(&script).map(drop)
+	}
+
+	/// Run `script` with PowerShell, stopping at the first error, returning
+	/// what it printed.
+	fn powershell(script: &str) -> io::Result<String> {
+		run(Command::new("powershell")
+			.args(["-NoProfile", "-NonInteractive", "-Command"])
+			.arg(format!("$ErrorActionPreference = 'Stop'; {}", script)))
+	}
+}
+
+/// Volume Shadow Copies are only on Windows.
+#[cfg(not(windows))]
+mod vss {
+	use std::{io, path::Path};
+
+	use super::Snapshot;
+
+	pub(super) fn create(_: &Path) -> io::Result<Snapshot> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "Volume Shadow Copies are only on Windows"))
+	}
+
+	pub(super) fn remove(_: &str) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+	use std::{
+		env,
+		ffi::OsString,
+		fs, io,
+		os::unix::{ffi::OsStringExt, fs::MetadataExt},
+		path::{Path, PathBuf},
+		process::{self, Command},
+	};
+
+	use super::{Snapshot, SnapshotKind, run};
+
+	/// Inode number of the root directory of btrfs subvolumes.
+	const BTRFS_SUBVOLUME_INO: u64 = 256;
+
+	/// The filesystem a path is on, as `findmnt` lists it.
+	struct Mount {
+		source: String,
+		target: PathBuf,
+		fstype: String,
+	}
+
+	/// Name of the snapshots of this run.
+	fn snapshot_name() -> String {
+		format!("quickdash-{}", process::id())
+	}
+
+	/// The filesystem `path` is on, requiring it to be a `fstype` one if
+	/// given.
+	fn mount_of(path: &Path, fstype: Option<&str>) -> io::Result<Mount> {
+		let output = run(Command::new("findmnt").args(["--noheadings", "--raw", "--output", "SOURCE,TARGET,FSTYPE", "--target"]).arg(path))?;
+		let fields: Vec<Vec<u8>> = output.split_whitespace().map(unescape).collect();
+		let [source, target, found] = <[Vec<u8>; 3]>::try_from(fields)
+			.map_err(|_| io::Error::other(format!("unexpected output of findmnt: {:?}", output)))?;
+		let mount = Mount {
+			source: String::from_utf8_lossy(&source).into_owned(),
+			target: PathBuf::from(OsString::from_vec(target)),
+			fstype: String::from_utf8_lossy(&found).into_owned(),
+		};
+		match fstype {
+			Some(fstype) if mount.fstype != fstype => Err(io::Error::other(format!("{:?} is on {}, not {}", path, mount.fstype, fstype))),
+			_ => Ok(mount),
+		}
+	}
+
+	/// A field of `findmnt --raw`, with its `\xNN` escapes undone.
+	fn unescape(field: &str) -> Vec<u8> {
+		let bytes = field.as_bytes();
+		let mut unescaped = Vec::with_capacity(bytes.len());
+		let mut i = 0;
+		while i < bytes.len() {
+			match field.get(i..i + 4).filter(|escape| escape.starts_with("\\x")).and_then(|escape| u8::from_str_radix(&escape[2..], 16).ok()) {
+				Some(byte) => {
+					unescaped.push(byte);
+					i += 4;
+				}
+				None => {
+					unescaped.push(bytes[i]);
+					i += 1;
+				}
+			}
+		}
+		unescaped
+	}
+
+	/// Read-only snapshot of the btrfs subvolume `path` is in, made in its
+	/// root.
+	pub(super) fn btrfs(path: &Path) -> io::Result<Snapshot> {
+		let path = path.canonicalize()?;
+		let mount = mount_of(&path, Some("btrfs"))?;
+		let subvolume = path
+			.ancestors()
+			.take_while(|dir| dir.starts_with(&mount.target))
+			.find(|dir| fs::metadata(dir).is_ok_and(|metadata| metadata.ino() == BTRFS_SUBVOLUME_INO))
+			.unwrap_or(mount.target.as_path());
+		let root = subvolume.join(format!(".{}", snapshot_name()));
+		run(Command::new("btrfs").args(["subvolume", "snapshot", "-r"]).arg(subvolume).arg(&root))?;
+		let path = root.join(path.strip_prefix(subvolume).expect("A path is in the subvolume it's in"));
+		Ok(Snapshot { kind: SnapshotKind::Btrfs, id: root.display().to_string(), root, path })
+	}
+
+	/// Snapshot of the ZFS dataset `path` is in.
+	pub(super) fn zfs(path: &Path) -> io::Result<Snapshot> {
+		let path = path.canonicalize()?;
+		let mount = mount_of(&path, Some("zfs"))?;
+		let name = snapshot_name();
+		let id = format!("{}@{}", mount.source, name);
+		run(Command::new("zfs").arg("snapshot").arg(&id))?;
+		let root = mount.target.join(".zfs").join("snapshot").join(name);
+		let path = root.join(path.strip_prefix(&mount.target).expect("A path is in the filesystem it's on"));
+		Ok(Snapshot { kind: SnapshotKind::Zfs, id, root, path })
+	}
+
+	/// Snapshot of the LVM logical volume `path` is on, mounted read-only in
+	/// the temporary directory.
+	pub(super) fn lvm(path: &Path) -> io::Result<Snapshot> {
+		let path = path.canonicalize()?;
+		let mount = mount_of(&path, None)?;
+		let output = run(Command::new("lvs").args(["--noheadings", "--options", "vg_name,lv_name"]).arg(&mount.source))?;
+		let mut names = output.split_whitespace();
+		let (Some(group), Some(volume)) = (names.next(), names.next()) else {
+			return Err(io::Error::other(format!("{} isn't an LVM logical volume", mount.source)));
+		};
+		let name = snapshot_name();
+		let id = format!("{}/{}", group, name);
+		run(Command::new("lvcreate")
+			.args(["--snapshot", "--permission", "r", "--extents", "10%ORIGIN", "--name", &name])
+			.arg(format!("{}/{}", group, volume)))?;
+		let root = env::temp_dir().join(&name);
+		// XFS refuses to mount a filesystem with the same UUID as a mounted one
+		let options = match mount.fstype.as_str() {
+			"xfs" => "ro,nouuid",
+			_ => "ro",
+		};
+		let mounted = fs::create_dir(&root)
+			.and_then(|_| run(Command::new("mount").args(["-o", options]).arg(Path::new("/dev").join(&id)).arg(&root)));
+		if let Err(err) = mounted {
+			let _ = fs::remove_dir(&root);
+			let _ = run(Command::new("lvremove").arg("--force").arg(&id));
+			return Err(err);
+		}
+		let path = root.join(path.strip_prefix(&mount.target).expect("A path is in the filesystem it's on"));
+		Ok(Snapshot { kind: SnapshotKind::Lvm, id, root, path })
+	}
+
+	/// Remove `snapshot`, unmounting it first if it's mounted.
+	pub(super) fn remove(snapshot: &Snapshot) -> io::Result<()> {
+		match snapshot.kind {
+			SnapshotKind::Btrfs => run(Command::new("btrfs").args(["subvolume", "delete"]).arg(&snapshot.root)).map(drop),
+			SnapshotKind::Zfs => run(Command::new("zfs").arg("destroy").arg(&snapshot.id)).map(drop),
+			SnapshotKind::Lvm => {
+				run(Command::new("umount").arg(&snapshot.root))?;
+				let _ = fs::remove_dir(&snapshot.root);
+				run(Command::new("lvremove").arg("--force").arg(&snapshot.id)).map(drop)
+			}
+			SnapshotKind::Vss => unreachable!("Volume Shadow Copies are removed with PowerShell"),
+		}
+	}
+}
+
+/// btrfs, ZFS and LVM snapshots are only taken on Linux.
+#[cfg(not(target_os = "linux"))]
+mod linux {
+	use std::{io, path::Path};
+
+	use super::Snapshot;
+
+	pub(super) fn btrfs(_: &Path) -> io::Result<Snapshot> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "btrfs snapshots are only taken on Linux"))
+	}
+
+	pub(super) fn zfs(_: &Path) -> io::Result<Snapshot> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "ZFS snapshots are only taken on Linux"))
+	}
+
+	pub(super) fn lvm(_: &Path) -> io::Result<Snapshot> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "LVM snapshots are only taken on Linux"))
+	}
+
+	pub(super) fn remove(_: &Snapshot) -> io::Result<()> {
+		Ok(())
+	}
+}
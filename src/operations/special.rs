@@ -0,0 +1,60 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::FileType;
+
+use clap::ValueEnum;
+
+/// What to do with FIFOs, sockets and device nodes found while walking.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum SpecialFiles {
+	/// Leave them out of the hashes.
+	#[default]
+	Skip,
+	/// Record them with a placeholder hash, without opening them.
+	Record,
+	/// Stop with an error.
+	Error,
+}
+
+/// Kind of special file, or `None` for regular files, directories and
+/// symlinks.
+#[cfg(unix)]
+pub(super) fn special_kind(file_type: FileType) -> Option<&'static str> {
+	use std::os::unix::fs::FileTypeExt;
+
+	if file_type.is_fifo() {
+		Some("FIFO")
+	} else if file_type.is_socket() {
+		Some("socket")
+	} else if file_type.is_block_device() {
+		Some("block device")
+	} else if file_type.is_char_device() {
+		Some("character device")
+	} else {
+		None
+	}
+}
+
+/// Kind of special file, or `None` for regular files, directories and
+/// symlinks.
+#[cfg(not(unix))]
+pub(super) fn special_kind(file_type: FileType) -> Option<&'static str> {
+	if file_type.is_file() || file_type.is_dir() || file_type.is_symlink() {
+		None
+	} else {
+		Some("special file")
+	}
+}
@@ -0,0 +1,59 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+	num::NonZero,
+	path::Path,
+	thread::available_parallelism,
+};
+
+/// Amount of threads to read files under `path` with when not told: one on
+/// spinning disks, where concurrent reads thrash the heads, and one per CPU
+/// thread otherwise.
+pub fn default_io_threads(path: &Path) -> usize {
+	match is_rotational(path) {
+		Some(true) => 1,
+		_ => cpu_threads(),
+	}
+}
+
+/// Amount of CPU threads, 1 if unknown.
+pub fn cpu_threads() -> usize {
+	available_parallelism().map_or(1, NonZero::get)
+}
+
+/// Whether `path` is on a spinning disk, going by the `rotational` flag of
+/// its block device in sysfs. `None` if unknown, e.g. on network filesystems.
+#[cfg(target_os = "linux")]
+pub fn is_rotational(path: &Path) -> Option<bool> {
+	use std::{fs::read_to_string, os::unix::fs::MetadataExt};
+
+	let dev = path.metadata().ok()?.dev();
+	// glibc's encoding of device numbers
+	let major = ((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0xfff);
+	let minor = ((dev >> 12) & 0xffff_ff00) | (dev & 0xff);
+	let device = Path::new("/sys/dev/block").join(format!("{}:{}", major, minor));
+	// Partitions don't have a queue of their own, their disk does
+	[device.join("queue/rotational"), device.join("../queue/rotational")]
+		.iter()
+		.find_map(|flag| read_to_string(flag).ok())
+		.map(|flag| flag.trim() == "1")
+}
+
+/// Whether `path` is on a spinning disk. Always unknown on this platform.
+#[cfg(not(target_os = "linux"))]
+pub fn is_rotational(_path: &Path) -> Option<bool> {
+	None
+}
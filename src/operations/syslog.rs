@@ -0,0 +1,230 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Records of verification runs and of each file that failed, logged to
+//! syslog or the systemd journal with structured fields, so log pipelines can
+//! gather integrity events from many hosts.
+//!
+//! The journal gets the fields as they are. Syslog gets them as an RFC 5424
+//! structured data element at the end of the message.
+
+use std::{io, path::Path};
+
+use clap::ValueEnum;
+
+use super::{CompareFileResult, CompareOutcome, CompareResult, EntryMetadata, Ownership, PathStyle, WindowsAttributes, audit::run_error};
+use crate::Error;
+
+/// Where records are logged.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, ValueEnum)]
+pub enum LogTarget {
+	/// The local syslog daemon, through `/dev/log`.
+	Syslog,
+	/// The systemd journal, through its native socket.
+	Journald,
+}
+
+/// `MESSAGE_ID` of records of runs.
+pub static RUN_MESSAGE_ID: &str = "3f1d6c0a9b2e4e57a4c8d1e26f0b9a53";
+
+/// `MESSAGE_ID` of records of files that failed.
+pub static FAILURE_MESSAGE_ID: &str = "b8e4a2f17c6d4d0f9e35a0c2d7f14e68";
+
+/// Name records are logged under.
+static IDENTIFIER: &str = "quickdash";
+
+/// Syslog severity of runs that passed.
+const INFO: u8 = 6;
+
+/// Syslog severity of runs that failed, and of the files that did.
+const ERR: u8 = 3;
+
+/// Structured data ID of syslog records.
+static SD_ID: &str = "quickdash@32473";
+
+/// A record to log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+	/// Syslog severity.
+	pub priority: u8,
+	pub message: String,
+	/// Fields besides the message and priority, named as in the journal.
+	pub fields: Vec<(&'static str, String)>,
+}
+
+impl LogRecord {
+	/// The record as a journal entry, in its native protocol.
+	pub fn journal_entry(&self) -> Vec<u8> {
+		let mut entry = Vec::new();
+		let fields = [("MESSAGE", self.message.clone()), ("PRIORITY", self.priority.to_string()), ("SYSLOG_IDENTIFIER", IDENTIFIER.to_owned())];
+		for (name, value) in fields.iter().chain(&self.fields) {
+			entry.extend_from_slice(name.as_bytes());
+			// Values spanning lines are given by length
+			match value.contains('\n') {
+				true => {
+					entry.push(b'\n');
+					entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+				}
+				false => entry.push(b'='),
+			}
+			entry.extend_from_slice(value.as_bytes());
+			entry.push(b'\n');
+		}
+		entry
+	}
+
+	/// The record as a syslog message of the user facility, as the local
+	/// daemon takes them.
+	pub fn syslog_message(&self, pid: u32) -> String {
+		let params: Vec<String> =
+			self.fields.iter().map(|(name, value)| format!("{}=\"{}\"", name, sd_param_value(value))).collect();
+		format!("<{}>{}[{}]: {} [{} {}]", 8 + self.priority, IDENTIFIER, pid, self.message.replace('\n', " "), SD_ID, params.join(" "))
+	}
+}
+
+/// Records of `command` verifying `manifest`: one of the run, and one for
+/// each file that failed.
+pub fn log_records(command: &str, manifest: &Path, result: Result<&CompareOutcome, &Error>) -> Vec<LogRecord> {
+	let run_fields = || vec![("COMMAND", command.to_owned()), ("MANIFEST", manifest.to_string_lossy().into_owned())];
+	let failure = |file: &Path, what: &str, expected: Option<String>, actual: Option<String>| {
+		let file = PathStyle::Unix.format(file);
+		let mut fields = run_fields();
+		fields.extend([("MESSAGE_ID", FAILURE_MESSAGE_ID.to_owned()), ("FILE", file.clone())]);
+		fields.extend(expected.map(|expected| ("EXPECTED", expected)));
+		fields.extend(actual.map(|actual| ("ACTUAL", actual)));
+		LogRecord { priority: ERR, message: format!("{} of {:?}: {:?} {}", command, manifest, file, what), fields }
+	};
+
+	let mut records = Vec::new();
+	let (mut matched, mut added, mut removed) = (0, 0, 0);
+	if let Ok(Ok((compare_results, file_compare_results))) = result {
+		for res in compare_results {
+			match res {
+				CompareResult::FileAdded(_) => added += 1,
+				CompareResult::FileRemoved(_) => removed += 1,
+				CompareResult::FileIgnored(_) => {}
+				CompareResult::MetadataChanged { file, was, new } => {
+					let ownership = |ownership: &Ownership| format!("uid {}, gid {}, mode {:o}", ownership.uid, ownership.gid, ownership.mode);
+					records.push(failure(file, "owner, group or mode changed", Some(ownership(was)), Some(ownership(new))));
+				}
+				CompareResult::AttributesChanged { file, was, new } => {
+					let attributes = |attributes: &WindowsAttributes| {
+						format!("{}, created {}.{:09}", attributes.flags(), attributes.created.as_secs(), attributes.created.subsec_nanos())
+					};
+					records.push(failure(file, "attributes changed", Some(attributes(was)), Some(attributes(new))));
+				}
+			}
+		}
+		for fres in file_compare_results {
+			match fres {
+				CompareFileResult::FileMatches(_) | CompareFileResult::FileAssumedOk(_) | CompareFileResult::FileDrifted { matches: Some(true), .. } => {
+					matched += 1
+				}
+				CompareFileResult::FileDiffers { file, was_hash, new_hash } | CompareFileResult::FilePiecesDiffer { file, was_hash, new_hash, .. } => {
+					records.push(failure(file, "doesn't match", Some(was_hash.clone()), Some(new_hash.clone())));
+				}
+				CompareFileResult::PiecesFail { file, pieces } => {
+					let pieces: Vec<String> = pieces.iter().map(usize::to_string).collect();
+					records.push(failure(file, "doesn't match", None, Some(format!("pieces {} fail", pieces.join(",")))));
+				}
+				CompareFileResult::FileDrifted { file, was, new, .. } => {
+					let metadata = |metadata: &EntryMetadata| {
+						format!("size {}, mtime {}.{:09}", metadata.size, metadata.mtime.as_secs(), metadata.mtime.subsec_nanos())
+					};
+					records.push(failure(file, "changed since hashed", Some(metadata(was)), Some(metadata(new))));
+				}
+				CompareFileResult::SymlinkRetargeted { file, was_target, new_target } => {
+					records.push(failure(file, "retargeted", Some(was_target.clone()), Some(new_target.clone())));
+				}
+			}
+		}
+	}
+
+	let error = run_error(result);
+	let priority = match records.is_empty() && error.is_none() {
+		true => INFO,
+		false => ERR,
+	};
+	let mut fields = run_fields();
+	fields.extend([
+		("MESSAGE_ID", RUN_MESSAGE_ID.to_owned()),
+		("MATCHED", matched.to_string()),
+		("FAILED", records.len().to_string()),
+		("ADDED", added.to_string()),
+		("REMOVED", removed.to_string()),
+	]);
+	let message = match &error {
+		Some(error) => format!("{} of {:?} couldn't compare: {}", command, manifest, error),
+		None => format!("{} of {:?}: {} matched, {} failed, {} added, {} removed", command, manifest, matched, records.len(), added, removed),
+	};
+	fields.extend(error.map(|error| ("ERROR", error)));
+	records.insert(0, LogRecord { priority, message, fields });
+	records
+}
+
+/// Log `records` to `target`.
+pub fn log_to(target: LogTarget, records: &[LogRecord]) -> io::Result<()> {
+	imp::send(target, records)
+}
+
+/// `value` escaped as a structured data parameter value.
+fn sd_param_value(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]").replace('\n', " ")
+}
+
+#[cfg(unix)]
+mod imp {
+	use std::{io, os::unix::net::UnixDatagram, path::Path, process};
+
+	use super::{LogRecord, LogTarget};
+
+	/// Sockets of the local syslog daemon, on Linux and elsewhere.
+	const SYSLOG_SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+
+	/// Native socket of the journal.
+	const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+	pub(super) fn send(target: LogTarget, records: &[LogRecord]) -> io::Result<()> {
+		let socket = UnixDatagram::unbound()?;
+		match target {
+			LogTarget::Syslog => {
+				let path = SYSLOG_SOCKETS
+					.into_iter()
+					.find(|path| Path::new(path).exists())
+					.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no syslog socket found"))?;
+				for record in records {
+					socket.send_to(record.syslog_message(process::id()).as_bytes(), path)?;
+				}
+			}
+			LogTarget::Journald => {
+				for record in records {
+					socket.send_to(&record.journal_entry(), JOURNAL_SOCKET)?;
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Syslog and the journal are reached through Unix sockets.
+#[cfg(not(unix))]
+mod imp {
+	use std::io;
+
+	use super::{LogRecord, LogTarget};
+
+	pub(super) fn send(_: LogTarget, _: &[LogRecord]) -> io::Result<()> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "syslog and the journal are only reached on Unix"))
+	}
+}
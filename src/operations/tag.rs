@@ -0,0 +1,147 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Hashes in filenames, like `Episode 01 [89ABCDEF].mkv`, as files are
+//! often distributed with their CRC32 in brackets: renaming files to add
+//! them, or to remove them.
+
+use std::{
+	collections::BTreeMap,
+	fs, io,
+	path::{Path, PathBuf},
+	sync::LazyLock,
+	time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+use regex::Regex;
+
+use super::{ERROR_NOTE_PREFIX, HashingReport, SPINNER_STRINGS, WalkOptions, hard_links::HardLinks, hash_entry, note_warning, parallel, walk_files};
+use crate::{Algorithm, utilities::long_path};
+
+/// Hex digits of the hash put in names, those of a CRC32.
+pub const TAG_LEN: usize = 8;
+
+/// Regex matching a hash in brackets in a name, with the space before it.
+static TAG_RGX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[ _]?\[[[:xdigit:]]{8}\]").unwrap());
+
+/// A file renamed to add or remove the hash in its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+	pub from: PathBuf,
+	pub to: PathBuf,
+}
+
+/// `name` with the first `TAG_LEN` digits of `hash` in brackets before its
+/// extension.
+pub fn tag_name(name: &str, hash: &str) -> String {
+	let tag = &hash[..hash.len().min(TAG_LEN)];
+	match name.rfind('.').filter(|&dot| dot > 0) {
+		Some(dot) => format!("{} [{}]{}", &name[..dot], tag, &name[dot..]),
+		None => format!("{} [{}]", name, tag),
+	}
+}
+
+/// `name` without the last hash in brackets in it, if it has one.
+pub fn strip_tag(name: &str) -> Option<String> {
+	let tag = TAG_RGX.find_iter(name).last()?;
+	Some(format!("{}{}", &name[..tag.start()], &name[tag.end()..]))
+}
+
+/// The renames adding the hash to the names of the files under `path`
+/// without one, hashing them with `algo` (default: CRC32), or with `strip`
+/// removing it from those with one. Files are walked and read as set by
+/// `options`.
+///
+/// Renames onto a file that exists, or onto the same name as another, are
+/// left out with a warning in `report`, as are files that fail to be read.
+pub fn tag_renames(path: &Path, algo: Algorithm, options: &WalkOptions, strip: bool, report: &mut HashingReport) -> Vec<Rename> {
+	let algo = match algo {
+		Algorithm::UNSPECIFIED => Algorithm::CRC32,
+		algo => algo,
+	};
+	let files = walk_files(path, options, report)
+		.filter(|record| record.entry.file_type().is_file())
+		.filter(|record| {
+			let name = record.entry.file_name().to_string_lossy();
+			TAG_RGX.is_match(&name) == strip
+		})
+		.collect::<Vec<_>>();
+
+	let mut renames = Vec::new();
+	if strip {
+		for record in &files {
+			let name = record.entry.file_name().to_string_lossy();
+			if let Some(stripped) = strip_tag(&name) {
+				renames.push(Rename { from: record.path().to_path_buf(), to: record.path().with_file_name(stripped) });
+			}
+		}
+	} else {
+		let pb_style = ProgressStyle::default_bar()
+			.template("{prefix:.bold.dim} {spinner} {wide_bar} {pos:>7}/{len:7} ETA: {eta} - {msg}")
+			.unwrap()
+			.tick_strings(&SPINNER_STRINGS);
+		let pb = ProgressBar::new(files.len() as u64);
+		pb.set_style(pb_style);
+		pb.enable_steady_tick(Duration::from_millis(80));
+		pb.set_message("Hashing files...");
+		let results = match options.io_threads.max(options.hash_threads) > 1 {
+			true => parallel::hash_entries(&files, vec![None; files.len()], algo, options, &pb),
+			false => {
+				let mut hard_links = HardLinks::default();
+				files.iter().progress_with(pb).map(|record| hash_entry(record, algo, options, &mut hard_links)).collect()
+			}
+		};
+		for (record, (hash, note)) in files.iter().zip(results) {
+			match note {
+				// A tag of a file that changed or wasn't read would be wrong
+				Some(note) => {
+					if note.starts_with(ERROR_NOTE_PREFIX) {
+						report.warnings.extend(note_warning(record.path(), &Some(note)));
+					}
+				}
+				None => {
+					let name = tag_name(&record.entry.file_name().to_string_lossy(), &hash);
+					renames.push(Rename { from: record.path().to_path_buf(), to: record.path().with_file_name(name) });
+				}
+			}
+		}
+	}
+
+	let mut targets = BTreeMap::<PathBuf, usize>::new();
+	for rename in &renames {
+		*targets.entry(rename.to.clone()).or_default() += 1;
+	}
+	renames.retain(|rename| {
+		if targets[&rename.to] > 1 {
+			report.warnings.push(format!("Not renaming {:?}, other files would get the same name {:?}", rename.from, rename.to));
+			false
+		} else if fs::symlink_metadata(long_path(&rename.to)).is_ok() {
+			report.warnings.push(format!("Not renaming {:?}, {:?} already exists", rename.from, rename.to));
+			false
+		} else {
+			true
+		}
+	});
+	renames
+}
+
+/// Do `rename`, failing if a file got its new name since it was planned.
+pub fn apply_rename(rename: &Rename) -> io::Result<()> {
+	let to = long_path(&rename.to);
+	if fs::symlink_metadata(&to).is_ok() {
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} already exists", rename.to)));
+	}
+	fs::rename(long_path(&rename.from), to)
+}
@@ -0,0 +1,262 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! RFC 3161 trusted timestamps of hash files: a SHA-256 of the hash file
+//! sent to a time-stamping authority, and the token it signs kept next to the
+//! hash file, proving the hash file existed as it is at that time.
+
+use std::{
+	fs,
+	hash::{BuildHasher, RandomState},
+	io::{self, Read, Write},
+	net::{TcpStream, ToSocketAddrs},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+
+use sha2::{Digest, Sha256};
+
+/// DER of the OID of SHA-256, 2.16.840.1.101.3.4.2.1.
+static SHA256_OID: &[u8] = &[0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const GENERALIZED_TIME: u8 = 0x18;
+/// `[0]`, wrapping the content of a CMS ContentInfo.
+const EXPLICIT_0: u8 = 0xa0;
+
+/// How long to wait on the time-stamping authority.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Result of checking a hash file against its timestamp token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampCheck {
+	/// The hash file is as it was at `time`.
+	Matches { time: String },
+	/// The hash file changed since it was timestamped at `time`.
+	Differs { time: String },
+}
+
+/// Timestamp token of `hash_file`, next to it.
+pub fn timestamp_path(hash_file: &Path) -> PathBuf {
+	let mut path = hash_file.as_os_str().to_owned();
+	path.push(".tsr");
+	PathBuf::from(path)
+}
+
+/// Have `hash_file` timestamped by the time-stamping authority at `url`,
+/// writing its response next to it, and get the time it vouches for.
+///
+/// Only `http://` URLs are supported. Tokens are signed, so they don't need
+/// a secure channel, and time-stamping authorities all take plain HTTP.
+pub fn request_timestamp(hash_file: &Path, url: &str) -> io::Result<String> {
+	let imprint = Sha256::digest(fs::read(hash_file)?);
+	let nonce = RandomState::new().hash_one(SystemTime::now()).to_be_bytes();
+
+	let mut request = der(INTEGER, &[1]);
+	request.extend(der(SEQUENCE, &[der(SEQUENCE, &[SHA256_OID, &[0x05, 0x00]].concat()), der(OCTET_STRING, &imprint)].concat()));
+	request.extend(der(INTEGER, &positive(&nonce)));
+	// Ask for the certificate of the authority to be included
+	request.extend([0x01, 0x01, 0xff]);
+	let response = post(url, &der(SEQUENCE, &request))?;
+
+	let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{} in the response of {}", what, url));
+	let token = parse_response(&response).ok_or_else(|| invalid("no granted timestamp"))?;
+	if token.imprint != imprint.as_slice() {
+		return Err(invalid("a timestamp of something else"));
+	}
+	if token.nonce != Some(positive(&nonce)) {
+		return Err(invalid("another nonce"));
+	}
+	fs::write(timestamp_path(hash_file), &response)?;
+	Ok(token.time)
+}
+
+/// Check `hash_file` against the timestamp token next to it, if any.
+///
+/// This checks what the token vouches for, not that the authority signed it,
+/// which is for `openssl ts -verify` and the certificate of the authority.
+pub fn check_timestamp(hash_file: &Path) -> io::Result<Option<TimestampCheck>> {
+	let response = match fs::read(timestamp_path(hash_file)) {
+		Ok(response) => response,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(err) => return Err(err),
+	};
+	let token =
+		parse_response(&response).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a granted timestamp"))?;
+	let imprint = Sha256::digest(fs::read(hash_file)?);
+	Ok(Some(match token.imprint == imprint.as_slice() {
+		true => TimestampCheck::Matches { time: token.time },
+		false => TimestampCheck::Differs { time: token.time },
+	}))
+}
+
+/// What a timestamp token vouches for.
+struct Token {
+	/// SHA-256 of what was timestamped.
+	imprint: Vec<u8>,
+	/// When, as `YYYY-MM-DD HH:MM:SS UTC`.
+	time: String,
+	nonce: Option<Vec<u8>>,
+}
+
+/// The token of a TimeStampResp, if it was granted and is of a SHA-256.
+fn parse_response(response: &[u8]) -> Option<Token> {
+	let (_, response, _) = tlv(response).filter(|(tag, _, _)| *tag == SEQUENCE)?;
+	let (_, status, token) = tlv(response)?;
+	// granted or grantedWithMods
+	let (_, status, _) = tlv(status).filter(|(tag, _, _)| *tag == INTEGER)?;
+	if !matches!(status, [0] | [1]) {
+		return None;
+	}
+	// ContentInfo, then SignedData, then its encapsulated TSTInfo
+	let (_, content_info, _) = tlv(token)?;
+	let (_, signed_data, _) = tlv(children(content_info).nth(1)?).filter(|(tag, _, _)| *tag == EXPLICIT_0)?;
+	let (_, signed_data, _) = tlv(signed_data)?;
+	let (_, encapsulated, _) = tlv(children(signed_data).nth(2)?)?;
+	let (_, content, _) = tlv(children(encapsulated).nth(1)?).filter(|(tag, _, _)| *tag == EXPLICIT_0)?;
+	let (_, tst_info, _) = tlv(content).filter(|(tag, _, _)| *tag == OCTET_STRING)?;
+	let (_, tst_info, _) = tlv(tst_info)?;
+
+	let mut fields = children(tst_info).skip(2);
+	let (_, imprint, _) = tlv(fields.next()?)?;
+	let mut imprint = children(imprint);
+	let (_, algorithm, _) = tlv(imprint.next()?)?;
+	if !algorithm.starts_with(SHA256_OID) {
+		return None;
+	}
+	let (_, hashed, _) = tlv(imprint.next()?).filter(|(tag, _, _)| *tag == OCTET_STRING)?;
+	let (_, time, _) = tlv(fields.nth(1)?).filter(|(tag, _, _)| *tag == GENERALIZED_TIME)?;
+	// After the optional accuracy and ordering
+	let nonce = fields.map_while(tlv).find(|(tag, _, _)| *tag == INTEGER).map(|(_, nonce, _)| nonce.to_vec());
+	Some(Token {
+		imprint: hashed.to_vec(),
+		time: format_time(str::from_utf8(time).ok()?)?,
+		nonce,
+	})
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC` of a GeneralizedTime, keeping fractions of a
+/// second.
+fn format_time(time: &str) -> Option<String> {
+	let time = time.strip_suffix('Z')?;
+	let (date, fraction) = time.split_once('.').map_or((time, ""), |(date, fraction)| (date, fraction));
+	if date.len() != 14 || !date.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+	let fraction = match fraction {
+		"" => String::new(),
+		fraction => format!(".{}", fraction),
+	};
+	Some(format!(
+		"{}-{}-{} {}:{}:{}{} UTC",
+		&date[..4],
+		&date[4..6],
+		&date[6..8],
+		&date[8..10],
+		&date[10..12],
+		&date[12..],
+		fraction
+	))
+}
+
+/// POST a timestamp query to `url`, and get the body of the response.
+fn post(url: &str, query: &[u8]) -> io::Result<Vec<u8>> {
+	let unsupported = || io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} isn't an http:// URL", url));
+	let rest = url.strip_prefix("http://").ok_or_else(unsupported)?;
+	let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+	let path = if path.is_empty() { "/" } else { path };
+	let address = match authority.contains(':') {
+		true => authority.to_owned(),
+		false => format!("{}:80", authority),
+	};
+	let address = address.to_socket_addrs()?.next().ok_or_else(unsupported)?;
+
+	let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+	stream.set_read_timeout(Some(TIMEOUT))?;
+	stream.set_write_timeout(Some(TIMEOUT))?;
+	// HTTP/1.0, so the response isn't chunked and ends with the connection
+	write!(
+		stream,
+		"POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/timestamp-query\r\nAccept: application/timestamp-reply\r\nContent-Length: {}\r\n\r\n",
+		path,
+		authority,
+		query.len()
+	)?;
+	stream.write_all(query)?;
+	let mut response = Vec::new();
+	stream.read_to_end(&mut response)?;
+
+	let end = response.windows(4).position(|window| window == b"\r\n\r\n").ok_or_else(|| {
+		io::Error::new(io::ErrorKind::InvalidData, format!("no HTTP response from {}", url))
+	})?;
+	let status = String::from_utf8_lossy(response[..end].split(|&b| b == b'\r').next().unwrap_or_default()).into_owned();
+	match status.split_whitespace().nth(1) {
+		Some("200") => Ok(response.split_off(end + 4)),
+		_ => Err(io::Error::other(format!("{} answered {:?}", url, status))),
+	}
+}
+
+/// Tag, contents and what follows of the DER element `bytes` start with.
+fn tlv(bytes: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+	let (&tag, rest) = bytes.split_first()?;
+	let (&first, mut rest) = rest.split_first()?;
+	let len = match first {
+		0..=0x7f => usize::from(first),
+		0x81..=0x84 => {
+			let (len, after) = rest.split_at_checked(usize::from(first & 0x7f))?;
+			rest = after;
+			len.iter().fold(0, |len, &byte| len << 8 | usize::from(byte))
+		}
+		_ => return None,
+	};
+	let (contents, rest) = rest.split_at_checked(len)?;
+	Some((tag, contents, rest))
+}
+
+/// The DER elements laid one after the other in `bytes`, whole.
+fn children(mut bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+	std::iter::from_fn(move || {
+		let (_, _, rest) = tlv(bytes)?;
+		let element = &bytes[..bytes.len() - rest.len()];
+		bytes = rest;
+		Some(element)
+	})
+}
+
+/// A DER element of `contents`.
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+	let len = contents.len().to_be_bytes();
+	let len = &len[len.iter().take_while(|&&byte| byte == 0).count()..];
+	let mut element = vec![tag];
+	match contents.len() {
+		0..=0x7f => element.push(contents.len() as u8),
+		_ => {
+			element.push(0x80 | len.len() as u8);
+			element.extend_from_slice(len);
+		}
+	}
+	element.extend_from_slice(contents);
+	element
+}
+
+/// The contents of a DER INTEGER of the unsigned big-endian `bytes`.
+fn positive(bytes: &[u8]) -> Vec<u8> {
+	let bytes = &bytes[bytes.iter().take_while(|&&byte| byte == 0).count().min(bytes.len() - 1)..];
+	match bytes[0] & 0x80 {
+		0 => bytes.to_vec(),
+		_ => [&[0], bytes].concat(),
+	}
+}
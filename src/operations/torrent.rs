@@ -0,0 +1,421 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Verifying data against the piece hashes of `.torrent` files: the SHA-1
+//! hashes of the pieces of all files laid end to end of BitTorrent v1, and
+//! the per-file SHA-256 merkle trees over 16 KiB blocks of BitTorrent v2.
+
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{self, BufReader, Read, Seek, SeekFrom},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::{CompareFileResult, CompareOutcome, CompareResult, SPINNER_STRINGS, bao::decode_hash, bencode::Value};
+use crate::{
+	Error,
+	hashing::{BLOCK_LEN, merkle_root},
+	utilities::long_path,
+};
+
+/// Pieces and files of a `.torrent` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Torrent {
+	piece_length: u64,
+	files: Vec<TorrentFile>,
+	/// SHA-1 hashes of the pieces of the files laid end to end, for v1.
+	pieces: Vec<[u8; 20]>,
+	/// Whether files are verified by their merkle trees, as in v2.
+	v2: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TorrentFile {
+	/// Where the file goes in the directory the torrent is downloaded to.
+	path: PathBuf,
+	length: u64,
+	/// Padding between files of v1 torrents, made of zeros and not stored.
+	pad: bool,
+	/// Hashes of the pieces of files larger than a piece, for v2. The last
+	/// ones are those of pieces past the end of the file, if any.
+	layer: Vec<[u8; 32]>,
+	/// Root of the merkle tree of the file, for v2.
+	root: [u8; 32],
+}
+
+impl Torrent {
+	/// Load the pieces and files of a `.torrent` file, using the v2 merkle
+	/// trees of hybrid torrents.
+	pub fn load(file: &Path) -> Result<Self, Error> {
+		let data = fs::read(file).map_err(|err| Error::HashesFileParsingFailure(err.to_string()))?;
+		Self::parse(&data).map_err(Error::HashesFileParsingFailure)
+	}
+
+	/// Amount of files, without the padding between them.
+	pub fn file_count(&self) -> usize {
+		self.files.iter().filter(|file| !file.pad).count()
+	}
+
+	/// Bytes of the files, without the padding between them.
+	pub fn total_length(&self) -> u64 {
+		self.files.iter().filter(|file| !file.pad).map(|file| file.length).sum()
+	}
+
+	fn parse(data: &[u8]) -> Result<Self, String> {
+		let torrent = Value::parse(data)?;
+		let info = torrent.get("info").ok_or("no info dictionary")?;
+		let name = path_element(info.get("name").and_then(Value::as_bytes).ok_or("no name")?)?;
+		let piece_length = info
+			.get("piece length")
+			.and_then(Value::as_int)
+			.and_then(|len| u64::try_from(len).ok())
+			.filter(|&len| len > 0)
+			.ok_or("no piece length")?;
+
+		if let Some(tree) = info.get("file tree") {
+			if !piece_length.is_power_of_two() || piece_length < BLOCK_LEN as u64 {
+				return Err(format!("invalid piece length {} for v2", piece_length));
+			}
+			let mut files = Vec::new();
+			file_tree(tree, PathBuf::new(), &mut files)?;
+			// Lone files aren't put in a directory named after the torrent
+			if !matches!(&files[..], [file] if file.path.components().count() == 1) {
+				files.iter_mut().for_each(|file| file.path = name.join(&file.path));
+			}
+			let layers = torrent.get("piece layers").and_then(Value::as_dict);
+			let zero_piece = merkle_root(&[], piece_length as usize / BLOCK_LEN, [0; 32]);
+			for file in files.iter_mut().filter(|file| file.length > piece_length) {
+				let layer: Vec<[u8; 32]> = layers
+					.and_then(|layers| layers.get(&file.root[..]))
+					.and_then(Value::as_bytes)
+					.filter(|layer| layer.len() as u64 == file.length.div_ceil(piece_length) * 32)
+					.ok_or_else(|| format!("no piece layer for {:?}", file.path))?
+					.chunks(32)
+					.map(|hash| hash.try_into().expect("Chunks of 32 bytes"))
+					.collect();
+				if merkle_root(&layer, layer.len().next_power_of_two(), zero_piece) != file.root {
+					return Err(format!("piece layer of {:?} doesn't match its root", file.path));
+				}
+				file.layer = layer;
+			}
+			return Ok(Torrent { piece_length, files, pieces: Vec::new(), v2: true });
+		}
+
+		let files = match info.get("files").and_then(Value::as_list) {
+			Some(list) => list
+				.iter()
+				.map(|file| {
+					let mut path = name.clone();
+					for element in file.get("path").and_then(Value::as_list).ok_or("file without a path")? {
+						path.push(path_element(element.as_bytes().ok_or("invalid path")?)?);
+					}
+					Ok(TorrentFile {
+						path,
+						length: length(file)?,
+						pad: file.get("attr").and_then(Value::as_bytes).is_some_and(|attr| attr.contains(&b'p')),
+						layer: Vec::new(),
+						root: [0; 32],
+					})
+				})
+				.collect::<Result<Vec<_>, String>>()?,
+			None => vec![TorrentFile { path: name, length: length(info)?, pad: false, layer: Vec::new(), root: [0; 32] }],
+		};
+		let pieces = info.get("pieces").and_then(Value::as_bytes).ok_or("no pieces")?;
+		let total: u64 = files.iter().map(|file| file.length).sum();
+		if pieces.len() as u64 != total.div_ceil(piece_length) * 20 {
+			return Err("amount of pieces doesn't match the length of the files".to_owned());
+		}
+		let pieces = pieces.chunks(20).map(|hash| hash.try_into().expect("Chunks of 20 bytes")).collect();
+		Ok(Torrent { piece_length, files, pieces, v2: false })
+	}
+}
+
+/// Collect the files of a v2 file tree, under `path`.
+fn file_tree(tree: &Value, path: PathBuf, files: &mut Vec<TorrentFile>) -> Result<(), String> {
+	for (name, entry) in tree.as_dict().ok_or("invalid file tree")? {
+		if name.is_empty() {
+			let length = length(entry)?;
+			let root = match entry.get("pieces root").and_then(Value::as_bytes) {
+				Some(root) => root.try_into().map_err(|_| format!("invalid pieces root for {:?}", path))?,
+				None if length == 0 => [0; 32],
+				None => return Err(format!("no pieces root for {:?}", path)),
+			};
+			files.push(TorrentFile { path: path.clone(), length, pad: false, layer: Vec::new(), root });
+		} else {
+			file_tree(entry, path.join(path_element(name)?), files)?;
+		}
+	}
+	Ok(())
+}
+
+fn length(file: &Value) -> Result<u64, String> {
+	file.get("length")
+		.and_then(Value::as_int)
+		.and_then(|len| u64::try_from(len).ok())
+		.ok_or_else(|| "file without a length".to_owned())
+}
+
+/// A file or directory name of a torrent, refusing ones that would lead
+/// outside the directory it's downloaded to.
+fn path_element(name: &[u8]) -> Result<PathBuf, String> {
+	let name = String::from_utf8_lossy(name);
+	match name.as_ref() {
+		"" | "." | ".." => Err(format!("invalid file name {:?}", name)),
+		_ if name.contains(['/', '\\', '\0']) => Err(format!("invalid file name {:?}", name)),
+		_ => Ok(PathBuf::from(name.as_ref())),
+	}
+}
+
+/// Write a BitTorrent v2 `.torrent` of the regular files in `hashes`, found
+/// under `base`, to `out`, given their pieces roots, with pieces of
+/// `piece_length` bytes and no trackers.
+///
+/// Files larger than a piece are read again for their piece layer. Those
+/// whose pieces root changed since they were hashed, or that can't be read,
+/// are left out with a warning.
+pub fn write_torrent(
+	base: &Path,
+	hashes: &BTreeMap<PathBuf, String>,
+	notes: &BTreeMap<PathBuf, String>,
+	piece_length: u64,
+	out: &Path,
+	warnings: &mut Vec<String>,
+) -> io::Result<()> {
+	let zero_piece = merkle_root(&[], piece_length as usize / BLOCK_LEN, [0; 32]);
+	let mut tree = BTreeMap::new();
+	let mut layers = BTreeMap::new();
+	for (name, hash) in hashes.iter().filter(|(name, _)| !notes.contains_key(*name)) {
+		let path = base.join(name);
+		let Some(root) = decode_hash(hash) else {
+			warnings.push(format!("Not a pieces root, left out of the torrent: {:?}", name));
+			continue;
+		};
+		let layer = fs::metadata(long_path(&path)).and_then(|metadata| match metadata.len() > piece_length {
+			true => piece_layer(&path, metadata.len(), piece_length).map(|layer| (metadata.len(), Some(layer))),
+			false => Ok((metadata.len(), None)),
+		});
+		let (length, layer) = match layer {
+			Ok(layer) => layer,
+			Err(err) => {
+				warnings.push(format!("Failed to read {:?}, left out of the torrent: {}", name, err));
+				continue;
+			}
+		};
+		let mut entry = BTreeMap::from([(b"length".to_vec(), Value::Int(length as i64))]);
+		if length > 0 {
+			entry.insert(b"pieces root".to_vec(), Value::Bytes(root.to_vec()));
+		}
+		if let Some(layer) = layer {
+			if merkle_root(&layer, layer.len().next_power_of_two(), zero_piece) != root {
+				warnings.push(format!("Changed since hashed, left out of the torrent: {:?}", name));
+				continue;
+			}
+			layers.insert(root.to_vec(), Value::Bytes(layer.concat()));
+		}
+
+		let mut dir = &mut tree;
+		for component in name.components() {
+			let key = component.as_os_str().to_string_lossy().into_owned().into_bytes();
+			let Value::Dict(next) = dir.entry(key).or_insert_with(|| Value::Dict(BTreeMap::new())) else {
+				unreachable!("Directories of the file tree are dictionaries");
+			};
+			dir = next;
+		}
+		dir.insert(Vec::new(), Value::Dict(entry));
+	}
+
+	// A lone file is named after itself, others after the directory
+	let name = match &tree.iter().collect::<Vec<_>>()[..] {
+		[(name, file)] if file.get("").is_some() => name.to_vec(),
+		_ => fs::canonicalize(base)?.file_name().unwrap_or(base.as_os_str()).to_string_lossy().into_owned().into_bytes(),
+	};
+	let info = BTreeMap::from([
+		(b"file tree".to_vec(), Value::Dict(tree)),
+		(b"meta version".to_vec(), Value::Int(2)),
+		(b"name".to_vec(), Value::Bytes(name)),
+		(b"piece length".to_vec(), Value::Int(piece_length as i64)),
+	]);
+	let torrent = Value::Dict(BTreeMap::from([
+		(b"created by".to_vec(), Value::Bytes(format!("quickdash {}", env!("CARGO_PKG_VERSION")).into_bytes())),
+		(b"info".to_vec(), Value::Dict(info)),
+		(b"piece layers".to_vec(), Value::Dict(layers)),
+	]));
+	let mut data = Vec::new();
+	torrent.encode(&mut data);
+	fs::write(out, data)
+}
+
+/// Roots of the pieces of `piece_length` bytes of the file at `path`, of
+/// `len` bytes.
+fn piece_layer(path: &Path, len: u64, piece_length: u64) -> io::Result<Vec<[u8; 32]>> {
+	let mut file = BufReader::with_capacity(1 << 20, File::open(long_path(path))?);
+	let mut piece = vec![0; piece_length as usize];
+	let mut layer = Vec::new();
+	for start in (0..len).step_by(piece_length as usize) {
+		let piece = &mut piece[..(len - start).min(piece_length) as usize];
+		file.read_exact(piece)?;
+		layer.push(merkle_root(&block_hashes(piece), piece_length as usize / BLOCK_LEN, [0; 32]));
+	}
+	Ok(layer)
+}
+
+/// SHA-256 hashes of the 16 KiB blocks of `data`.
+fn block_hashes(data: &[u8]) -> Vec<[u8; 32]> {
+	data.chunks(BLOCK_LEN).map(|block| Sha256::digest(block).into()).collect()
+}
+
+/// Verify the files of `torrent`, downloaded to `path`, against its piece
+/// hashes.
+///
+/// Missing files are reported as removed, and files with failed pieces with
+/// the pieces that fail. Files of the wrong size fail all their pieces.
+pub fn check_torrent(torrent: &Torrent, path: &Path) -> CompareOutcome {
+	let pb_style = ProgressStyle::default_bar()
+		.template("{prefix:.bold.dim} {spinner} {wide_bar} {bytes:>7}/{total_bytes:7} ETA: {eta} - {msg}")
+		.unwrap()
+		.tick_strings(&SPINNER_STRINGS);
+	let pb = ProgressBar::new(torrent.files.iter().map(|file| file.length).sum());
+	pb.set_style(pb_style);
+	pb.enable_steady_tick(Duration::from_millis(80));
+	pb.set_message("Checking pieces...");
+
+	let mut checker = Checker { torrent, path, pb: &pb, open: None };
+	let failed = match torrent.v2 {
+		true => (0..torrent.files.len()).map(|index| checker.check_file(index)).collect(),
+		false => checker.check_pieces(),
+	};
+	pb.finish_and_clear();
+
+	let (mut compare_results, mut file_compare_results) = (Vec::new(), Vec::new());
+	for (file, pieces) in torrent.files.iter().zip(failed) {
+		if file.pad {
+			continue;
+		}
+		if !long_path(&path.join(&file.path)).is_file() {
+			compare_results.push(CompareResult::FileRemoved(file.path.clone()));
+		} else if pieces.is_empty() {
+			file_compare_results.push(CompareFileResult::FileMatches(file.path.clone()));
+		} else {
+			file_compare_results.push(CompareFileResult::PiecesFail { file: file.path.clone(), pieces });
+		}
+	}
+	Ok((compare_results, file_compare_results))
+}
+
+/// Reader of the files of a torrent.
+struct Checker<'a> {
+	torrent: &'a Torrent,
+	path: &'a Path,
+	pb: &'a ProgressBar,
+	/// Last file read, by index in the torrent.
+	open: Option<(usize, File)>,
+}
+
+impl Checker<'_> {
+	/// Failed v1 pieces of each file, the files being laid end to end.
+	fn check_pieces(&mut self) -> Vec<Vec<usize>> {
+		let files = &self.torrent.files;
+		let mut failed = vec![Vec::new(); files.len()];
+		let sound: Vec<bool> = files.iter().map(|file| file.pad || self.has_length(file)).collect();
+		let starts: Vec<u64> = files.iter().scan(0, |start, file| Some(std::mem::replace(start, *start + file.length))).collect();
+		let total = starts.last().zip(files.last()).map_or(0, |(start, file)| start + file.length);
+
+		let mut piece = Vec::new();
+		let mut first = 0;
+		for (index, hash) in self.torrent.pieces.iter().enumerate() {
+			let start = index as u64 * self.torrent.piece_length;
+			let end = (start + self.torrent.piece_length).min(total);
+			while starts[first] + files[first].length <= start && first + 1 < files.len() {
+				first += 1;
+			}
+			let overlapping: Vec<usize> =
+				(first..files.len()).take_while(|&i| starts[i] < end).filter(|&i| files[i].length > 0).collect();
+
+			piece.clear();
+			let mut fails = false;
+			for &i in &overlapping {
+				let (from, to) = (start.max(starts[i]) - starts[i], end.min(starts[i] + files[i].length) - starts[i]);
+				if files[i].pad {
+					piece.resize(piece.len() + (to - from) as usize, 0);
+				} else if !sound[i] || self.read(i, from, to, &mut piece).is_err() {
+					fails = true;
+					break;
+				}
+			}
+			if fails || Sha1::digest(&piece)[..] != hash[..] {
+				overlapping.iter().for_each(|&i| failed[i].push(index));
+			}
+			self.pb.inc(end - start);
+		}
+		failed
+	}
+
+	/// Failed v2 pieces of the file at `index`.
+	fn check_file(&mut self, index: usize) -> Vec<usize> {
+		let (file, piece_length) = (&self.torrent.files[index], self.torrent.piece_length);
+		let pieces = file.length.div_ceil(piece_length) as usize;
+		if !self.has_length(file) {
+			self.pb.inc(file.length);
+			return (0..pieces).collect();
+		}
+
+		let mut failed = Vec::new();
+		let mut piece = Vec::new();
+		for i in 0..pieces {
+			let start = i as u64 * piece_length;
+			let end = (start + piece_length).min(file.length);
+			piece.clear();
+			let matches = self.read(index, start, end, &mut piece).is_ok() && {
+				let blocks = block_hashes(&piece);
+				match file.layer.get(i) {
+					Some(hash) => merkle_root(&blocks, piece_length as usize / BLOCK_LEN, [0; 32]) == *hash,
+					// Files of up to a piece have a tree only as wide as they need
+					None => merkle_root(&blocks, blocks.len().next_power_of_two(), [0; 32]) == file.root,
+				}
+			};
+			if !matches {
+				failed.push(i);
+			}
+			self.pb.inc(end - start);
+		}
+		failed
+	}
+
+	/// Whether the file is there with the size it has in the torrent.
+	fn has_length(&self, file: &TorrentFile) -> bool {
+		fs::metadata(long_path(&self.path.join(&file.path))).is_ok_and(|metadata| metadata.is_file() && metadata.len() == file.length)
+	}
+
+	/// Append the bytes from `from` to `to` of the file at `index` to `out`.
+	fn read(&mut self, index: usize, from: u64, to: u64, out: &mut Vec<u8>) -> io::Result<()> {
+		let file = match &mut self.open {
+			Some((open, file)) if *open == index => file,
+			open => {
+				let file = File::open(long_path(&self.path.join(&self.torrent.files[index].path)))?;
+				&mut open.insert((index, file)).1
+			}
+		};
+		file.seek(SeekFrom::Start(from))?;
+		let len = out.len();
+		out.resize(len + (to - from) as usize, 0);
+		file.read_exact(&mut out[len..])
+	}
+}
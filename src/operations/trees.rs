@@ -0,0 +1,177 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Two directory trees compared directly, without a hash file: the files
+//! only in either, and those that differ. Only files in both of the same
+//! size are read, all of them through the same workers.
+
+use std::{
+	collections::BTreeMap,
+	fs::File,
+	io::{self, BufReader, Read, Seek, SeekFrom},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+
+use super::{
+	ERROR_NOTE_PREFIX, HashingReport, SPINNER_STRINGS, WalkOptions, hard_links::HardLinks, hash_entry, note_warning, parallel,
+	record::FileRecord, walk_files,
+};
+use crate::{Algorithm, utilities::long_path};
+
+/// Bytes shown around the first difference of two files, before it and from
+/// it on.
+const CONTEXT_LEN: u64 = 8;
+
+/// How two trees differ, by the names of their files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeComparison {
+	pub only_in_a: Vec<PathBuf>,
+	pub only_in_b: Vec<PathBuf>,
+	/// In both, with different sizes or content.
+	pub differing: Vec<PathBuf>,
+	/// In both, but failing to be read in either.
+	pub unreadable: Vec<PathBuf>,
+	/// In both, the same.
+	pub identical: usize,
+}
+
+impl TreeComparison {
+	pub fn is_identical(&self) -> bool {
+		self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.differing.is_empty() && self.unreadable.is_empty()
+	}
+}
+
+/// Compare the trees at `a` and `b`, walked and read as set by `options`,
+/// hashing with `algo`. Warnings of either go to `report`.
+pub fn compare_trees(a: &Path, b: &Path, algo: Algorithm, options: &WalkOptions, report: &mut HashingReport) -> TreeComparison {
+	let pb_style = ProgressStyle::default_bar()
+		.template("{prefix:.bold.dim} {spinner} {wide_bar} {pos:>7}/{len:7} ETA: {eta} - {msg}")
+		.unwrap()
+		.tick_strings(&SPINNER_STRINGS);
+	let pb = ProgressBar::new_spinner();
+	pb.set_style(pb_style);
+	pb.enable_steady_tick(Duration::from_millis(80));
+	pb.set_message("Finding files to compare...");
+
+	let mut walk = |root: &Path| -> BTreeMap<PathBuf, FileRecord> {
+		walk_files(root, options, report).map(|record| (options.name(root, record.path()), record)).collect()
+	};
+	let (in_a, mut in_b) = (walk(a), walk(b));
+	let mut comparison = TreeComparison::default();
+	// Pairs to hash, as the index of their A side in `files`, B's following
+	let (mut files, mut pairs) = (Vec::new(), Vec::new());
+	for (name, a) in in_a {
+		let Some(b) = in_b.remove(&name) else {
+			comparison.only_in_a.push(name);
+			continue;
+		};
+		// Regular files of different sizes differ whatever they hold
+		if a.entry.file_type().is_file() && b.entry.file_type().is_file() && a.len != b.len {
+			comparison.differing.push(name);
+			continue;
+		}
+		pairs.push((name, files.len()));
+		files.push(a);
+		files.push(b);
+	}
+
+	comparison.only_in_b = in_b.into_keys().collect();
+
+	pb.reset();
+	pb.set_length(files.len() as u64);
+	pb.set_message("Hashing files...");
+	let results = match options.io_threads.max(options.hash_threads) > 1 {
+		true => parallel::hash_entries(&files, vec![None; files.len()], algo, options, &pb),
+		false => {
+			let mut hard_links = HardLinks::default();
+			files.iter().progress_with(pb).map(|record| hash_entry(record, algo, options, &mut hard_links)).collect()
+		}
+	};
+	for (name, at) in pairs {
+		let ((hash_a, note_a), (hash_b, note_b)) = (&results[at], &results[at + 1]);
+		let failed = |note: &Option<String>| note.as_deref().is_some_and(|note| note.starts_with(ERROR_NOTE_PREFIX));
+		if failed(note_a) || failed(note_b) {
+			report.warnings.extend(note_warning(&a.join(&name), note_a));
+			report.warnings.extend(note_warning(&b.join(&name), note_b));
+			comparison.unreadable.push(name);
+		} else if hash_a != hash_b || note_a != note_b {
+			comparison.differing.push(name);
+		} else {
+			comparison.identical += 1;
+		}
+	}
+	comparison.differing.sort();
+	comparison
+}
+
+/// Where two files first differ, with the bytes of each around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+	/// Offset of the first byte that differs, or the length of the shorter
+	/// file if it's the start of the longer one.
+	pub offset: u64,
+	/// Offset the context starts at.
+	pub start: u64,
+	pub context_a: Vec<u8>,
+	pub context_b: Vec<u8>,
+}
+
+/// Where the files `a` and `b` first differ, reading both up to there.
+/// `None` if they're the same.
+pub fn first_difference(a: &Path, b: &Path) -> io::Result<Option<Difference>> {
+	let (mut file_a, mut file_b) = (File::open(long_path(a))?, File::open(long_path(b))?);
+	let (mut reader_a, mut reader_b) = (BufReader::new(&mut file_a), BufReader::new(&mut file_b));
+	let (mut chunk_a, mut chunk_b) = (vec![0; 65536], vec![0; 65536]);
+	let mut offset = 0;
+	let offset = loop {
+		let read_a = read_full(&mut reader_a, &mut chunk_a)?;
+		let read_b = read_full(&mut reader_b, &mut chunk_b)?;
+		let same = read_a.min(read_b);
+		if let Some(at) = chunk_a[..same].iter().zip(&chunk_b[..same]).position(|(a, b)| a != b) {
+			break offset + at as u64;
+		}
+		if read_a != read_b {
+			break offset + same as u64;
+		}
+		if read_a == 0 {
+			return Ok(None);
+		}
+		offset += same as u64;
+	};
+	let start = offset.saturating_sub(CONTEXT_LEN);
+	let context = |file: &mut File| -> io::Result<Vec<u8>> {
+		let mut context = Vec::new();
+		file.seek(SeekFrom::Start(start))?;
+		file.take(offset - start + CONTEXT_LEN).read_to_end(&mut context)?;
+		Ok(context)
+	};
+	Ok(Some(Difference { offset, start, context_a: context(&mut file_a)?, context_b: context(&mut file_b)? }))
+}
+
+/// Fill `buf` from `reader`, short only at its end.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+	let mut filled = 0;
+	while filled < buf.len() {
+		match reader.read(&mut buf[filled..]) {
+			Ok(0) => break,
+			Ok(read) => filled += read,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+			Err(err) => return Err(err),
+		}
+	}
+	Ok(filled)
+}
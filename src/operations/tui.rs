@@ -0,0 +1,661 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Interactive terminal front end to verification: a live tree of results,
+//! what each worker is reading, the throughput over time, and the failures,
+//! which can be hashed again, accepted or quarantined as they come in.
+
+use std::{
+	collections::{BTreeMap, VecDeque},
+	fs::File,
+	io::{self, Read},
+	path::{Path, PathBuf},
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+		mpsc::{self, RecvTimeoutError},
+	},
+	thread::{self, JoinHandle},
+	time::{Duration, Instant},
+};
+
+use console::{Alignment, Key, Term, pad_str};
+use indicatif::HumanBytes;
+
+use super::{
+	CompareFileResult, CompareOutcome, CompareResult, Fix, HashingReport, Mismatch, ReadOptions, WalkOptions, compare::CompareError,
+	hard_links::HardLinks, hash_entry, placeholder_hash, quarantine, read_hashes, record::FileRecord, skip_note, walk_files,
+};
+use crate::{Algorithm, Error, hashing::try_hash_reader_with, utilities::long_path};
+
+/// How long idle workers wait before looking for files to hash again.
+const IDLE_WAIT: Duration = Duration::from_millis(20);
+
+/// How often the screen is drawn again without a key being pressed.
+const FRAME: Duration = Duration::from_millis(200);
+
+static SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// What became of an entry or a file in a live verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+	/// Not hashed yet.
+	Pending,
+	Matches,
+	/// The content differs, hashing to `new` instead of `was`.
+	Differs { was: String, new: String },
+	/// The file is gone.
+	Missing,
+	/// A file without an entry, hashing to this.
+	Added(String),
+	/// The file couldn't be read, for this reason.
+	Unreadable(String),
+	/// The change was accepted, to be written to the hash file.
+	Accepted,
+	/// The file was moved or linked to the quarantine, its content having
+	/// hashed to `new` instead of `was`.
+	Quarantined { was: String, new: String },
+}
+
+impl Verdict {
+	pub(super) fn label(&self) -> &'static str {
+		match self {
+			Verdict::Pending => "pending",
+			Verdict::Matches => "matches",
+			Verdict::Differs { .. } => "differs",
+			Verdict::Missing => "missing",
+			Verdict::Added(_) => "added",
+			Verdict::Unreadable(_) => "unreadable",
+			Verdict::Accepted => "accepted",
+			Verdict::Quarantined { .. } => "quarantined",
+		}
+	}
+
+	/// Whether it's something to look into.
+	fn fails(&self) -> bool {
+		!matches!(self, Verdict::Pending | Verdict::Matches)
+	}
+}
+
+/// How far a live verification got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+	/// Files hashed so far, not counting those queued again.
+	pub hashed: usize,
+	/// Files found.
+	pub files: usize,
+	/// Bytes read.
+	pub read: u64,
+}
+
+/// Verification of a directory against its hash file, hashed by worker
+/// threads in the background while its progress and verdicts are looked at.
+///
+/// The workers stop when it's dropped.
+pub struct LiveVerification {
+	state: Arc<State>,
+	workers: Vec<JoinHandle<()>>,
+}
+
+struct State {
+	algo: Algorithm,
+	/// Directory the names are relative to.
+	base: PathBuf,
+	options: WalkOptions,
+	loaded: BTreeMap<PathBuf, String>,
+	/// Files found, by name.
+	records: Vec<(PathBuf, FileRecord)>,
+	verdicts: Mutex<BTreeMap<PathBuf, Verdict>>,
+	/// Indices in `records` of the files to hash.
+	queue: Mutex<VecDeque<usize>>,
+	/// Files queued or being hashed.
+	pending: AtomicUsize,
+	/// What each worker is doing.
+	busy: Vec<Busy>,
+	read: AtomicU64,
+	fixes: Mutex<BTreeMap<PathBuf, Fix>>,
+	warnings: Vec<String>,
+	stop: AtomicBool,
+}
+
+impl LiveVerification {
+	/// Start verifying the files under `path` against the hash file `file`,
+	/// found and hashed as set by `options` on `options.hash_threads`
+	/// workers.
+	pub fn start(
+		path: &Path,
+		file: &Path,
+		algo: Algorithm,
+		options: &WalkOptions,
+		read_options: &ReadOptions,
+	) -> Result<LiveVerification, Error> {
+		Ok(LiveVerification::start_against(path, read_hashes(file, read_options)?, algo, options))
+	}
+
+	/// Like `start()`, against the hashes `loaded` instead of those of a hash
+	/// file. Against none, every file comes out added, with its hash.
+	pub fn start_against(
+		path: &Path,
+		loaded: BTreeMap<PathBuf, String>,
+		algo: Algorithm,
+		options: &WalkOptions,
+	) -> LiveVerification {
+		let mut walked = HashingReport::default();
+		let mut records: Vec<(PathBuf, FileRecord)> =
+			walk_files(path, options, &mut walked).map(|record| (options.name(path, record.path()), record)).collect();
+		// Nothing fails the live view, errors are shown along with warnings
+		let warnings: Vec<String> = walked.warnings.into_iter().chain(walked.errors).collect();
+		records.sort_by(|(a, _), (b, _)| a.cmp(b));
+		let mut verdicts: BTreeMap<PathBuf, Verdict> = loaded.keys().map(|name| (name.clone(), Verdict::Missing)).collect();
+		for (name, _) in &records {
+			verdicts.insert(name.clone(), Verdict::Pending);
+		}
+		let workers = options.hash_threads.max(1);
+		let state = Arc::new(State {
+			algo,
+			base: options.base(path).to_owned(),
+			options: options.clone(),
+			loaded,
+			queue: Mutex::new((0..records.len()).collect()),
+			pending: AtomicUsize::new(records.len()),
+			records,
+			verdicts: Mutex::new(verdicts),
+			busy: (0..workers).map(|_| Busy::default()).collect(),
+			read: AtomicU64::new(0),
+			fixes: Mutex::new(BTreeMap::new()),
+			warnings,
+			stop: AtomicBool::new(false),
+		});
+		let workers = (0..workers)
+			.map(|worker| {
+				let state = Arc::clone(&state);
+				thread::spawn(move || state.work(worker))
+			})
+			.collect();
+		LiveVerification { state, workers }
+	}
+
+	/// Wait until every queued file is hashed.
+	pub fn wait(&self) {
+		while !self.is_done() {
+			thread::sleep(IDLE_WAIT);
+		}
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.state.pending.load(Ordering::Acquire) == 0
+	}
+
+	pub fn progress(&self) -> Progress {
+		let files = self.state.records.len();
+		Progress {
+			hashed: files - self.state.pending.load(Ordering::Relaxed).min(files),
+			files,
+			read: self.state.read.load(Ordering::Relaxed),
+		}
+	}
+
+	/// Verdicts so far, by name.
+	pub fn verdicts(&self) -> BTreeMap<PathBuf, Verdict> {
+		self.state.verdicts.lock().unwrap().clone()
+	}
+
+	/// Hash `file` again, if it was found. Returns whether it's queued.
+	pub fn rehash(&self, file: &Path) -> bool {
+		let Ok(index) = self.state.records.binary_search_by(|(name, _)| name.as_path().cmp(file)) else {
+			return false;
+		};
+		self.state.fixes.lock().unwrap().remove(file);
+		self.state.verdicts.lock().unwrap().insert(file.to_owned(), Verdict::Pending);
+		self.state.pending.fetch_add(1, Ordering::AcqRel);
+		self.state.queue.lock().unwrap().push_back(index);
+		true
+	}
+
+	/// Accept `file` as it is now, to update, delete or add its entry.
+	/// Returns whether there was a change to accept.
+	pub fn accept(&self, file: &Path) -> bool {
+		let mut verdicts = self.state.verdicts.lock().unwrap();
+		let mismatch = match verdicts.get(file) {
+			Some(Verdict::Differs { new, .. }) => Mismatch::Differs { file: file.to_owned(), new_hash: new.clone() },
+			Some(Verdict::Missing) => Mismatch::Removed(file.to_owned()),
+			Some(Verdict::Added(hash)) => Mismatch::Added { file: file.to_owned(), hash: hash.clone() },
+			_ => return false,
+		};
+		self.state.fixes.lock().unwrap().insert(file.to_owned(), mismatch.accept());
+		verdicts.insert(file.to_owned(), Verdict::Accepted);
+		true
+	}
+
+	/// Move `file` to `dir`, or hard-link it there with `link`, if its
+	/// content differs, as `verify --quarantine` does. Returns the line
+	/// telling what was done, if anything.
+	pub fn quarantine(&self, file: &Path, dir: &Path, link: bool) -> Option<String> {
+		let mut verdicts = self.state.verdicts.lock().unwrap();
+		let Some(Verdict::Differs { was, new }) = verdicts.get(file).cloned() else {
+			return None;
+		};
+		let result = CompareFileResult::FileDiffers { file: file.to_owned(), was_hash: was.clone(), new_hash: new.clone() };
+		let line = quarantine(dir, &[result], link, |file| Some(self.state.base.join(file))).pop()?;
+		if !link && !self.state.base.join(file).exists() {
+			verdicts.insert(file.to_owned(), Verdict::Quarantined { was, new });
+		}
+		Some(line)
+	}
+
+	/// Changes accepted so far, to make to the hash file with
+	/// `fix_hashes()`.
+	pub fn fixes(&self) -> BTreeMap<PathBuf, Fix> {
+		self.state.fixes.lock().unwrap().clone()
+	}
+
+	/// The verification as it stands, like `compare_hashes()` makes it.
+	/// Accepted changes count as made, and files not hashed yet are left
+	/// out.
+	pub fn outcome(&self) -> CompareOutcome {
+		let placeholder = placeholder_hash(self.state.algo);
+		if let Some(loaded) = self.state.loaded.values().next()
+			&& loaded.len() != placeholder.len()
+		{
+			return Err(CompareError::HashLengthDiffers { previous_len: loaded.len(), current_len: placeholder.len() });
+		}
+		let fixes = self.fixes();
+		let (mut compare_results, mut file_compare_results) = (Vec::new(), Vec::new());
+		for (file, verdict) in self.verdicts() {
+			let was = self.state.loaded.get(&file);
+			match (verdict, was) {
+				(Verdict::Pending, _) => {}
+				(Verdict::Matches, _) => file_compare_results.push(CompareFileResult::FileMatches(file)),
+				(Verdict::Differs { was, new } | Verdict::Quarantined { was, new }, _) => {
+					file_compare_results.push(CompareFileResult::FileDiffers { file, was_hash: was, new_hash: new })
+				}
+				(Verdict::Missing, _) => compare_results.push(CompareResult::FileRemoved(file)),
+				(Verdict::Added(_), _) | (Verdict::Unreadable(_), None) => compare_results.push(CompareResult::FileAdded(file)),
+				(Verdict::Unreadable(_), Some(was)) => file_compare_results.push(CompareFileResult::FileDiffers {
+					file,
+					was_hash: was.clone(),
+					new_hash: placeholder.clone(),
+				}),
+				(Verdict::Accepted, _) => {
+					if let Some(Fix::Update(_)) = fixes.get(&file) {
+						file_compare_results.push(CompareFileResult::FileMatches(file));
+					}
+				}
+			}
+		}
+		Ok((compare_results, file_compare_results))
+	}
+
+	/// Problems found along the way: files that couldn't be read, and how
+	/// many weren't hashed yet.
+	pub fn warnings(&self) -> Vec<String> {
+		let mut warnings = self.state.warnings.clone();
+		let mut unverified = 0;
+		for (file, verdict) in self.verdicts() {
+			match verdict {
+				Verdict::Unreadable(err) => warnings.push(format!("Failed to read {:?}: {}", file, err)),
+				Verdict::Pending => unverified += 1,
+				_ => {}
+			}
+		}
+		if unverified > 0 {
+			warnings.push(format!("{} files weren't verified, left before they were hashed", unverified));
+		}
+		warnings
+	}
+}
+
+impl Drop for LiveVerification {
+	fn drop(&mut self) {
+		self.state.stop.store(true, Ordering::Release);
+		for worker in self.workers.drain(..) {
+			let _ = worker.join();
+		}
+	}
+}
+
+impl State {
+	/// Hash queued files on worker `worker` until stopped.
+	fn work(&self, worker: usize) {
+		let mut hard_links = HardLinks::default();
+		while !self.stop.load(Ordering::Acquire) {
+			let Some(index) = self.queue.lock().unwrap().pop_front() else {
+				thread::sleep(IDLE_WAIT);
+				continue;
+			};
+			let (name, record) = &self.records[index];
+			let Busy { file, read } = &self.busy[worker];
+			*file.lock().unwrap() = Some((name.clone(), record.len));
+			read.store(0, Ordering::Relaxed);
+			// Regular files are read here, to follow how far each worker got
+			let hash = match record.entry.file_type().is_file() && skip_note(record, &self.options).is_none() {
+				true => self.hash(record.path(), read).map_err(|err| err.to_string()),
+				false => Ok(hash_entry(record, self.algo, &self.options, &mut hard_links).0),
+			};
+			let verdict = match (hash, self.loaded.get(name)) {
+				(Err(err), _) => Verdict::Unreadable(err),
+				(Ok(hash), Some(was)) if hash == *was => Verdict::Matches,
+				(Ok(new), Some(was)) => Verdict::Differs { was: was.clone(), new },
+				(Ok(hash), None) => Verdict::Added(hash),
+			};
+			self.verdicts.lock().unwrap().insert(name.clone(), verdict);
+			*file.lock().unwrap() = None;
+			self.pending.fetch_sub(1, Ordering::AcqRel);
+		}
+	}
+
+	/// Hash the regular file at `path`, counting what's read in `read` and
+	/// the total.
+	fn hash(&self, path: &Path, read: &AtomicU64) -> io::Result<String> {
+		let mut reader = Counted { inner: File::open(long_path(path))?, read, total: &self.read };
+		try_hash_reader_with(self.algo, &mut reader, &self.options.hash_options)
+	}
+}
+
+/// File a worker is hashing and its size, if any, and how much of it it
+/// read.
+#[derive(Default)]
+struct Busy {
+	file: Mutex<Option<(PathBuf, u64)>>,
+	read: AtomicU64,
+}
+
+/// Reader adding up how much was read from it.
+struct Counted<'a, R> {
+	inner: R,
+	read: &'a AtomicU64,
+	total: &'a AtomicU64,
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		self.read.fetch_add(n as u64, Ordering::Relaxed);
+		self.total.fetch_add(n as u64, Ordering::Relaxed);
+		Ok(n)
+	}
+}
+
+/// Show `verification` on the terminal, titled `title`, until the user
+/// leaves, letting them hash failures again, accept them or quarantine
+/// them to `quarantine`, hard-linking with its flag, if given.
+///
+/// Fails if standard output isn't a terminal.
+pub fn run_tui(verification: &LiveVerification, title: &str, quarantine: Option<(&Path, bool)>) -> io::Result<()> {
+	let term = Term::stdout();
+	if !term.is_term() {
+		return Err(io::Error::other("the TUI needs a terminal"));
+	}
+	// Keys are read in raw mode, which is only left between keys, so the
+	// reader waits to be told to go on before reading the next one
+	let (keys, key_rx) = mpsc::channel();
+	let (go_on, go_on_rx) = mpsc::channel();
+	let input = term.clone();
+	thread::spawn(move || {
+		while let Ok(key) = input.read_key_raw() {
+			if keys.send(key).is_err() || go_on_rx.recv() != Ok(true) {
+				break;
+			}
+		}
+	});
+	// Alternate screen, so the shell's is back as it was when leaving
+	term.write_str("\x1b[?1049h")?;
+	term.hide_cursor()?;
+	let mut view = View { quarantine, ..View::default() };
+	let result = loop {
+		view.sample(verification);
+		if let Err(err) = view.draw(&term, verification, title) {
+			break Err(err);
+		}
+		match key_rx.recv_timeout(FRAME) {
+			Ok(key) => {
+				let keep_on = view.press(key, verification);
+				let _ = go_on.send(keep_on);
+				if !keep_on {
+					break Ok(());
+				}
+			}
+			Err(RecvTimeoutError::Timeout) => {}
+			Err(RecvTimeoutError::Disconnected) => break Ok(()),
+		}
+	};
+	term.show_cursor()?;
+	term.write_str("\x1b[?1049l")?;
+	result
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Tab {
+	#[default]
+	Tree,
+	Failures,
+}
+
+#[derive(Default)]
+struct View<'a> {
+	quarantine: Option<(&'a Path, bool)>,
+	tab: Tab,
+	selected: usize,
+	scroll: usize,
+	filter: String,
+	/// Whether keys are typed into the filter.
+	filtering: bool,
+	message: String,
+	/// Bytes read in each second so far, the latest last.
+	throughput: VecDeque<u64>,
+	/// Total read and when, at the latest sample.
+	sampled: Option<(u64, Instant)>,
+	/// Names of the rows of the failures tab, as last drawn.
+	rows: Vec<PathBuf>,
+}
+
+impl View<'_> {
+	/// Note how much was read since the last sample, once a second.
+	fn sample(&mut self, verification: &LiveVerification) {
+		let read = verification.state.read.load(Ordering::Relaxed);
+		match self.sampled {
+			Some((last, at)) if at.elapsed() >= Duration::from_secs(1) => {
+				self.throughput.push_back(read - last);
+				if self.throughput.len() > 512 {
+					self.throughput.pop_front();
+				}
+				self.sampled = Some((read, Instant::now()));
+			}
+			Some(_) => {}
+			None => self.sampled = Some((read, Instant::now())),
+		}
+	}
+
+	/// Handle a key press. Returns whether to go on.
+	fn press(&mut self, key: Key, verification: &LiveVerification) -> bool {
+		if self.filtering {
+			match key {
+				Key::Char(c) => self.filter.push(c),
+				Key::Backspace => {
+					self.filter.pop();
+				}
+				Key::Enter => self.filtering = false,
+				Key::Escape => {
+					self.filter.clear();
+					self.filtering = false;
+				}
+				Key::CtrlC => return false,
+				_ => {}
+			}
+			self.selected = 0;
+			return true;
+		}
+		let page = (Term::stdout().size().0 as usize / 2).max(1);
+		match key {
+			Key::Char('q') | Key::Escape | Key::CtrlC => return false,
+			Key::Tab | Key::BackTab => {
+				self.tab = match self.tab {
+					Tab::Tree => Tab::Failures,
+					Tab::Failures => Tab::Tree,
+				};
+				(self.selected, self.scroll) = (0, 0);
+			}
+			Key::Char('/') => {
+				self.tab = Tab::Failures;
+				self.filtering = true;
+			}
+			Key::ArrowUp | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+			Key::ArrowDown | Key::Char('j') => self.selected += 1,
+			Key::PageUp => self.selected = self.selected.saturating_sub(page),
+			Key::PageDown => self.selected += page,
+			Key::Home => self.selected = 0,
+			Key::End => self.selected = usize::MAX,
+			Key::Char(action @ ('r' | 'a' | 'x')) if self.tab == Tab::Failures => {
+				let Some(file) = self.rows.get(self.selected).cloned() else {
+					return true;
+				};
+				self.message = match action {
+					'r' if verification.rehash(&file) => format!("Hashing {:?} again", file),
+					'r' => format!("{:?} isn't there to hash", file),
+					'a' if verification.accept(&file) => format!("Accepted {:?}, written to the hash file when leaving", file),
+					'a' => format!("Nothing to accept for {:?}", file),
+					_ => match self.quarantine {
+						Some((dir, link)) => verification
+							.quarantine(&file, dir, link)
+							.unwrap_or_else(|| format!("Only files whose content differs are quarantined, not {:?}", file)),
+						None => "Use --quarantine to name where to quarantine files".to_owned(),
+					},
+				};
+			}
+			_ => {}
+		}
+		true
+	}
+
+	fn draw(&mut self, term: &Term, verification: &LiveVerification, title: &str) -> io::Result<()> {
+		let (height, width) = term.size();
+		let (height, width) = (height as usize, width as usize);
+		let state = &verification.state;
+		let verdicts = state.verdicts.lock().unwrap();
+		let progress = verification.progress();
+		let failures = verdicts.values().filter(|verdict| verdict.fails()).count();
+		let mut lines = vec![
+			format!("{}  {}/{} files hashed, {} to look into", title, progress.hashed, progress.files, failures),
+			format!(
+				"Read {} at {}/s",
+				HumanBytes(progress.read),
+				HumanBytes(self.throughput.back().copied().unwrap_or(0))
+			),
+			format!("Throughput {}", self.sparkline(width.saturating_sub(11))),
+		];
+		for (i, Busy { file, read }) in state.busy.iter().enumerate() {
+			lines.push(match &*file.lock().unwrap() {
+				Some((name, len)) => {
+					let read = read.load(Ordering::Relaxed).min(*len);
+					let percent = (read * 100).checked_div(*len).unwrap_or(100) as usize;
+					let bar = format!("{}{}", "█".repeat(percent / 5), "░".repeat(20 - percent / 5));
+					format!("{:>3} {} {:>3}% {}", i + 1, bar, percent, name.display())
+				}
+				None => format!("{:>3} idle", i + 1),
+			});
+		}
+		lines.push(String::new());
+		lines.push(match self.tab {
+			Tab::Tree => "[Tree]  Failures".to_owned(),
+			Tab::Failures if self.filter.is_empty() => " Tree  [Failures]".to_owned(),
+			Tab::Failures => format!(" Tree  [Failures] matching {:?}", self.filter),
+		});
+		let rows: Vec<String> = match self.tab {
+			Tab::Tree => tree_rows(&verdicts),
+			Tab::Failures => {
+				let filter = self.filter.to_lowercase();
+				self.rows.clear();
+				let mut rows = Vec::new();
+				for (file, verdict) in verdicts.iter().filter(|(_, verdict)| verdict.fails()) {
+					let row = format!("{:<11} {}", verdict.label(), file.display());
+					if row.to_lowercase().contains(&filter) {
+						self.rows.push(file.clone());
+						rows.push(row);
+					}
+				}
+				rows
+			}
+		};
+		drop(verdicts);
+		let shown = height.saturating_sub(lines.len() + 1).max(1);
+		self.selected = self.selected.min(rows.len().saturating_sub(1));
+		if self.selected < self.scroll {
+			self.scroll = self.selected;
+		} else if self.selected >= self.scroll + shown {
+			self.scroll = self.selected + 1 - shown;
+		}
+		let highlighted = (!rows.is_empty()).then(|| lines.len() + self.selected - self.scroll);
+		lines.extend(rows.into_iter().skip(self.scroll).take(shown));
+		while lines.len() < height.saturating_sub(1) {
+			lines.push(String::new());
+		}
+		lines.truncate(height.saturating_sub(1));
+		lines.push(match (self.filtering, self.message.is_empty(), self.tab) {
+			(true, _, _) => format!("/{}", self.filter),
+			(false, false, _) => self.message.clone(),
+			(false, true, Tab::Tree) => "Tab failures  ↑↓ move  / filter  q quit".to_owned(),
+			(false, true, Tab::Failures) => "r hash again  a accept  x quarantine  / filter  Tab tree  q quit".to_owned(),
+		});
+		let screen: Vec<String> = lines
+			.iter()
+			.enumerate()
+			.map(|(i, line)| {
+				let line = pad_str(line, width, Alignment::Left, Some("…"));
+				match Some(i) == highlighted {
+					true => format!("\x1b[7m{}\x1b[0m", line),
+					false => line.into_owned(),
+				}
+			})
+			.collect();
+		term.write_str(&format!("\x1b[H{}", screen.join("\r\n")))?;
+		term.flush()
+	}
+
+	/// Bars of the last `width` seconds of throughput, scaled to the
+	/// busiest.
+	fn sparkline(&self, width: usize) -> String {
+		let samples: Vec<u64> = self.throughput.iter().rev().take(width).rev().copied().collect();
+		let max = samples.iter().copied().max().unwrap_or(0).max(1);
+		samples.iter().map(|&sample| SPARKS[(sample * (SPARKS.len() as u64 - 1) / max) as usize]).collect()
+	}
+}
+
+/// Rows of the tree of directories, each with how many files under it were
+/// hashed and how many are to look into.
+fn tree_rows(verdicts: &BTreeMap<PathBuf, Verdict>) -> Vec<String> {
+	// Files hashed, files in all and failures, by directory
+	let mut dirs: BTreeMap<&Path, (usize, usize, usize)> = BTreeMap::new();
+	for (file, verdict) in verdicts {
+		for dir in file.ancestors().skip(1) {
+			let counts = dirs.entry(dir).or_default();
+			counts.0 += usize::from(*verdict != Verdict::Pending);
+			counts.1 += 1;
+			counts.2 += usize::from(verdict.fails());
+		}
+	}
+	dirs.into_iter()
+		.map(|(dir, (done, files, failures))| {
+			let depth = dir.components().count();
+			let name = match dir.file_name() {
+				Some(name) => format!("{}/", name.to_string_lossy()),
+				None => "./".to_owned(),
+			};
+			let status = match failures {
+				0 => format!("{}/{}", done, files),
+				failures => format!("{}/{}  {} to look into", done, files, failures),
+			};
+			format!("{}{:<30} {}", "  ".repeat(depth), name, status)
+		})
+		.collect()
+}
@@ -0,0 +1,111 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Updating a hash file without reading the files that didn't change since
+//! it was written, as told by a change journal.
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	ffi::OsString,
+	path::{Path, PathBuf},
+};
+
+use super::{EntryMetadata, FileRecord, HashingReport, ReadOptions, SYMLINK_NOTE_PREFIX, stream_hashes};
+use crate::Error;
+
+/// What changed since a hash file was written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Changed {
+	/// Names of the files that changed, wherever they are. Everything is
+	/// walked, and files of these names read.
+	Names(BTreeSet<OsString>),
+	/// Paths under which something changed, relative to the walked path.
+	/// Only these are walked and read, and the rest kept as it was.
+	Paths(BTreeSet<PathBuf>),
+}
+
+/// Hashes of an earlier run, kept for the files that haven't changed since.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unchanged {
+	hashes: BTreeMap<PathBuf, String>,
+	notes: BTreeMap<PathBuf, String>,
+	metadata: BTreeMap<PathBuf, EntryMetadata>,
+	changed: Changed,
+}
+
+impl Unchanged {
+	/// The `hashes` of a hash file, kept for the files that aren't `changed`.
+	pub fn new(hashes: BTreeMap<PathBuf, String>, changed: Changed) -> Self {
+		Unchanged { hashes, notes: BTreeMap::new(), metadata: BTreeMap::new(), changed }
+	}
+
+	/// The entries of the specified hash file, with their metadata and
+	/// symlink targets, kept for the files that aren't `changed`. Shard
+	/// indices aren't expanded.
+	pub fn load(file: &Path, options: &ReadOptions, changed: Changed) -> Result<Self, Error> {
+		let mut unchanged = Unchanged::new(BTreeMap::new(), changed);
+		let mut reader = stream_hashes(file, options)?;
+		while let Some(entry) = reader.next() {
+			let (name, hash) = entry?;
+			if let Some(target) = reader.symlink_target() {
+				unchanged.notes.insert(name.clone(), format!("{}{}", SYMLINK_NOTE_PREFIX, target));
+			} else if let Some(metadata) = reader.metadata() {
+				unchanged.metadata.insert(name.clone(), metadata);
+			}
+			unchanged.hashes.insert(name, hash);
+		}
+		Ok(unchanged)
+	}
+
+	/// Earlier hash of the regular file stored as `name`, if it hasn't
+	/// changed. Files not hashed before, and placeholder hashes, are read.
+	pub(super) fn get(&self, name: &Path, record: &FileRecord) -> Option<String> {
+		match &self.changed {
+			Changed::Names(names) if record.entry.file_type().is_file() && !names.contains(record.entry.file_name()) => {
+				self.hashes.get(name).filter(|hash| !hash.starts_with('-')).cloned()
+			}
+			// Files walked are the ones under changed paths
+			_ => None,
+		}
+	}
+
+	/// Whether the entry stored as `name`, a directory if `is_dir`, isn't
+	/// walked, as nothing changed in it.
+	pub(super) fn skips(&self, name: &Path, is_dir: bool) -> bool {
+		let Changed::Paths(paths) = &self.changed else {
+			return false;
+		};
+		!paths.iter().any(|path| name.starts_with(path) || (is_dir && path.starts_with(name)))
+	}
+
+	/// Add the entries that weren't walked to `hashes`, with their notes and
+	/// metadata to `report`.
+	pub(super) fn keep(&self, hashes: &mut BTreeMap<PathBuf, String>, report: &mut HashingReport) {
+		let Changed::Paths(paths) = &self.changed else {
+			return;
+		};
+		for (name, hash) in &self.hashes {
+			if paths.iter().any(|path| name.starts_with(path)) || hashes.contains_key(name) {
+				continue;
+			}
+			hashes.insert(name.clone(), hash.clone());
+			if let Some(note) = self.notes.get(name) {
+				report.notes.insert(name.clone(), note.clone());
+			}
+			if let Some(metadata) = self.metadata.get(name) {
+				report.metadata.insert(name.clone(), *metadata);
+			}
+		}
+	}
+}
@@ -0,0 +1,164 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! The NTFS change journal, the USN journal, read with `fsutil` to tell
+//! which files changed since a hash file was last updated.
+//!
+//! Journal records name the files that changed but not their directories,
+//! so every file of a changed name is taken to have changed.
+
+use std::{collections::BTreeSet, ffi::OsString, io, path::Path};
+
+/// Start of the hash file header line recording the journal position it was
+/// last updated at.
+pub static USN_HEADER_PREFIX: &str = "usn journal: ";
+
+/// A position in the USN journal of a volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsnPosition {
+	/// ID of the journal, which changes when it's deleted and made again.
+	pub journal: u64,
+	/// Update sequence number of the next change.
+	pub usn: u64,
+}
+
+impl UsnPosition {
+	/// The position recorded by a hash file header line, if it is one.
+	pub fn from_header(line: &str) -> Option<Self> {
+		let (journal, usn) = line.strip_prefix(USN_HEADER_PREFIX)?.split_once(' ')?;
+		Some(UsnPosition { journal: parse_hex(journal)?, usn: parse_hex(usn)? })
+	}
+
+	/// Hash file header line recording the position.
+	pub fn header(&self) -> String {
+		format!("{}{:#018x} {:#018x}", USN_HEADER_PREFIX, self.journal, self.usn)
+	}
+}
+
+/// A number as `fsutil` prints it, like `0x00000000004a1f28`.
+fn parse_hex(text: &str) -> Option<u64> {
+	u64::from_str_radix(text.trim().strip_prefix("0x")?, 16).ok()
+}
+
+/// The current position in the USN journal of the volume `path` is on.
+pub fn usn_position(path: &Path) -> io::Result<UsnPosition> {
+	imp::query(path).map(|(position, _)| position)
+}
+
+/// Names of the files of the volume `path` is on changed since `since`, or
+/// none if the journal doesn't reach back that far, having been made again
+/// or having dropped its oldest records.
+pub fn usn_changed_names(path: &Path, since: UsnPosition) -> io::Result<Option<BTreeSet<OsString>>> {
+	let (now, first) = imp::query(path)?;
+	if now.journal != since.journal || first > since.usn {
+		return Ok(None);
+	}
+	let records = imp::read(path, since.usn)?;
+	let mut lines = records.lines().skip_while(|line| !line.starts_with("Usn,"));
+	let Some(column) = lines.next().and_then(|header| csv_fields(header).iter().position(|field| field == "File name")) else {
+		return Err(io::Error::other("fsutil listed no file names"));
+	};
+	Ok(Some(lines.filter_map(|line| csv_fields(line).into_iter().nth(column)).map(OsString::from).collect()))
+}
+
+/// The fields of a line of CSV, with quotes undone.
+fn csv_fields(line: &str) -> Vec<String> {
+	let mut fields = vec![String::new()];
+	let (mut quoted, mut chars) = (false, line.trim_end().chars().peekable());
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if quoted && chars.peek() == Some(&'"') => {
+				chars.next();
+				fields.last_mut().unwrap().push('"');
+			}
+			'"' => quoted = !quoted,
+			',' if !quoted => fields.push(String::new()),
+			c => fields.last_mut().unwrap().push(c),
+		}
+	}
+	fields
+}
+
+#[cfg(windows)]
+mod imp {
+	use std::{
+		io,
+		path::{Component, Path, Prefix},
+		process::Command,
+	};
+
+	use super::{UsnPosition, parse_hex};
+
+	/// The drive `path` is on, like `C:`.
+	fn volume(path: &Path) -> io::Result<String> {
+		let path = path.canonicalize()?;
+		match path.components().next() {
+			Some(Component::Prefix(prefix)) => match prefix.kind() {
+				Prefix::VerbatimDisk(drive) | Prefix::Disk(drive) => Ok(format!("{}:", drive as char)),
+				_ => Err(io::Error::new(io::ErrorKind::Unsupported, "only local drives have a USN journal")),
+			},
+			_ => Err(io::Error::other(format!("{:?} isn't on a drive", path))),
+		}
+	}
+
+	fn fsutil(args: &[&str]) -> io::Result<String> {
+		let output = Command::new("fsutil").args(args).output()?;
+		// fsutil prints its errors to stdout
+		if !output.status.success() {
+			let stdout = String::from_utf8_lossy(&output.stdout);
+			return Err(io::Error::other(format!("fsutil failed ({}): {}", output.status, stdout.trim())));
+		}
+		Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+	}
+
+	/// The current position in the journal of the volume `path` is on, and
+	/// the oldest change it still has.
+	pub(super) fn query(path: &Path) -> io::Result<(UsnPosition, u64)> {
+		let output = fsutil(&["usn", "queryjournal", &volume(path)?])?;
+		let field = |name: &str| {
+			output
+				.lines()
+				.filter_map(|line| line.split_once(':'))
+				.find(|(key, _)| key.trim() == name)
+				.and_then(|(_, value)| parse_hex(value))
+				.ok_or_else(|| io::Error::other(format!("fsutil printed no {}", name)))
+		};
+		Ok((UsnPosition { journal: field("Usn Journal ID")?, usn: field("Next Usn")? }, field("First Usn")?))
+	}
+
+	/// The records of the journal from `usn` on, as CSV.
+	pub(super) fn read(path: &Path, usn: u64) -> io::Result<String> {
+		fsutil(&["usn", "readjournal", &volume(path)?, &format!("startusn={}", usn), "csv"])
+	}
+}
+
+/// The USN journal is only on Windows.
+#[cfg(not(windows))]
+mod imp {
+	use std::{io, path::Path};
+
+	use super::UsnPosition;
+
+	fn unsupported<T>() -> io::Result<T> {
+		Err(io::Error::new(io::ErrorKind::Unsupported, "the USN journal is only on Windows"))
+	}
+
+	pub(super) fn query(_: &Path) -> io::Result<(UsnPosition, u64)> {
+		unsupported()
+	}
+
+	pub(super) fn read(_: &Path, _: u64) -> io::Result<String> {
+		unsupported()
+	}
+}
@@ -0,0 +1,149 @@
+/* Copyright [2025] [Cerda]
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *    http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! When each file of a hash file last verified, kept next to it, to tell
+//! which files haven't been read in a while.
+
+use std::{
+	collections::BTreeMap,
+	fs::{File, read_to_string, rename},
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{CompareFileResult, CompareResult};
+use crate::utilities::{escape_filename, unescape_filename};
+
+/// Comment on the first line of last-verified files.
+static VERIFIED_HEADER: &str = "# quickdash last verified 1";
+
+/// File the last-verified times of the files listed in `hash_file` are kept
+/// in, next to it.
+pub fn verified_path(hash_file: &Path) -> PathBuf {
+	let mut path = hash_file.as_os_str().to_owned();
+	path.push(".verified");
+	PathBuf::from(path)
+}
+
+/// When each file listed in a hash file last verified, `None` for never.
+///
+/// Stored as a tab-separated text file, one file per line: `seconds name`,
+/// seconds since the Unix epoch or `-`, and the name escaped like in hash
+/// files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastVerified {
+	times: BTreeMap<PathBuf, Option<u64>>,
+}
+
+impl LastVerified {
+	/// Load the last-verified times of the files of `hash_file`. Having none
+	/// kept yet is having none at all.
+	pub fn load(hash_file: &Path) -> io::Result<Self> {
+		let text = match read_to_string(verified_path(hash_file)) {
+			Ok(text) => text,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(LastVerified::default()),
+			Err(err) => return Err(err),
+		};
+		let mut times = BTreeMap::new();
+		for line in text.lines().skip(1) {
+			let parsed = line.split_once('\t').and_then(|(secs, name)| {
+				let secs = match secs {
+					"-" => None,
+					secs => Some(secs.parse().ok()?),
+				};
+				Some((PathBuf::from(unescape_filename(name)?), secs))
+			});
+			match parsed {
+				Some((name, secs)) => times.insert(name, secs),
+				None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("malformed line {:?}", line))),
+			};
+		}
+		Ok(LastVerified { times })
+	}
+
+	/// Note the files that matched in a comparison against the hash file as
+	/// verified `now`. Files the comparison doesn't mention are no longer in
+	/// the hash file, and are forgotten.
+	pub fn record(&mut self, compare_results: &[CompareResult], file_compare_results: &[CompareFileResult], now: SystemTime) {
+		let now = now.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+		let mut times = BTreeMap::new();
+		for res in compare_results {
+			if let CompareResult::FileRemoved(file) | CompareResult::FileIgnored(file) = res {
+				times.insert(file.clone(), self.times.get(file).copied().flatten());
+			}
+		}
+		for fres in file_compare_results {
+			let (file, verified) = match fres {
+				CompareFileResult::FileMatches(file) | CompareFileResult::FileDrifted { file, matches: Some(true), .. } => {
+					(file, true)
+				}
+				CompareFileResult::FileAssumedOk(file)
+				| CompareFileResult::FileDiffers { file, .. }
+				| CompareFileResult::FilePiecesDiffer { file, .. }
+				| CompareFileResult::PiecesFail { file, .. }
+				| CompareFileResult::FileDrifted { file, .. }
+				| CompareFileResult::SymlinkRetargeted { file, .. } => (file, false),
+			};
+			let time = match verified {
+				true => Some(now),
+				false => self.times.get(file).copied().flatten(),
+			};
+			times.insert(file.clone(), time);
+		}
+		self.times = times;
+	}
+
+	/// When `file` last verified, if it ever did.
+	pub fn get(&self, file: &Path) -> Option<SystemTime> {
+		self.times.get(file).copied().flatten().map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+	}
+
+	/// Files that never verified.
+	pub fn never_verified(&self) -> Vec<&Path> {
+		self.times.iter().filter(|(_, time)| time.is_none()).map(|(file, _)| file.as_path()).collect()
+	}
+
+	/// Files that last verified before `time`, with when they did.
+	pub fn verified_before(&self, time: SystemTime) -> Vec<(&Path, SystemTime)> {
+		// Times are kept to the second
+		let time = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+		self.times
+			.iter()
+			.filter_map(|(file, secs)| Some((file, (*secs)?)))
+			.filter(|(_, secs)| *secs < time)
+			.map(|(file, secs)| (file.as_path(), UNIX_EPOCH + Duration::from_secs(secs)))
+			.collect()
+	}
+
+	/// Write the last-verified times of the files of `hash_file` next to it,
+	/// replacing the previous ones in one go.
+	pub fn save(&self, hash_file: &Path) -> io::Result<()> {
+		let path = verified_path(hash_file);
+		let mut temporary = path.clone().into_os_string();
+		temporary.push(".tmp");
+		let mut out = BufWriter::new(File::create(&temporary)?);
+		writeln!(out, "{}", VERIFIED_HEADER)?;
+		for (file, secs) in &self.times {
+			let name = file.to_string_lossy();
+			let name = escape_filename(&name, false).unwrap_or_else(|| name.into_owned());
+			match secs {
+				Some(secs) => writeln!(out, "{}\t{}", secs, name)?,
+				None => writeln!(out, "-\t{}", name)?,
+			}
+		}
+		out.into_inner()?.sync_all()?;
+		rename(temporary, path)
+	}
+}
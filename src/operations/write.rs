@@ -15,15 +15,16 @@
 
 use std::{io::Write, path::PathBuf, str::FromStr};
 
-use super::{CompareError, CompareFileResult, CompareResult};
+use super::{CompareError, EntryMetadata, Ownership, WindowsAttributes, CompareFileResult, CompareOutcome, CompareResult};
 use crate::{Error, utilities::mul_str};
 
 /// Write hash comparison results to the output streams in a human-consumable
-/// format
+/// format, followed by the warnings raised while hashing, if any
 pub fn write_hash_comparison_results<Wo: Write, We: Write>(
 	output: &mut Wo,
 	error: &mut We,
-	results: Result<(Vec<CompareResult>, Vec<CompareFileResult>), CompareError>,
+	results: CompareOutcome,
+	warnings: &[String],
 ) -> Error {
 	let result = match results {
 		Ok((mut compare_results, mut file_compare_results)) => {
@@ -41,6 +42,12 @@ pub fn write_hash_comparison_results<Wo: Write, We: Write>(
 					CompareResult::FileIgnored(ref file) => {
 						write_compare_result(output, "File ignored, skipping: ", file)
 					}
+					CompareResult::MetadataChanged { ref file, ref was, ref new } => {
+						write_metadata_change(output, file, was, new)
+					}
+					CompareResult::AttributesChanged { ref file, ref was, ref new } => {
+						write_attributes_change(output, file, was, new)
+					}
 				}
 			}
 
@@ -55,12 +62,18 @@ pub fn write_hash_comparison_results<Wo: Write, We: Write>(
 					writeln!(output).unwrap();
 				}
 
-				let mut differed_n = 0;
+				let mut differed_n = compare_results
+					.iter()
+					.filter(|res| matches!(res, CompareResult::MetadataChanged { .. } | CompareResult::AttributesChanged { .. }))
+					.count() as i32;
 				for fres in &file_compare_results {
 					match *fres {
 						CompareFileResult::FileMatches(ref file) => {
 							write_file_result_match(output, file)
 						}
+						CompareFileResult::FileAssumedOk(ref file) => {
+							write_file_result_assumed(output, file)
+						}
 						CompareFileResult::FileDiffers {
 							ref file,
 							ref was_hash,
@@ -69,6 +82,40 @@ pub fn write_hash_comparison_results<Wo: Write, We: Write>(
 							write_file_result_diff(output, file, was_hash, new_hash);
 							differed_n += 1;
 						}
+						CompareFileResult::FilePiecesDiffer {
+							ref file,
+							ref was_hash,
+							ref new_hash,
+							piece_size,
+							ref ranges,
+						} => {
+							write_file_result_diff(output, file, was_hash, new_hash);
+							write_differing_pieces(output, piece_size, ranges);
+							differed_n += 1;
+						}
+						CompareFileResult::PiecesFail { ref file, ref pieces } => {
+							write_failed_pieces(output, file, pieces);
+							differed_n += 1;
+						}
+						CompareFileResult::FileDrifted {
+							ref file,
+							ref was,
+							ref new,
+							matches,
+						} => {
+							write_file_result_drift(output, file, was, new, matches);
+							if matches != Some(true) {
+								differed_n += 1;
+							}
+						}
+						CompareFileResult::SymlinkRetargeted {
+							ref file,
+							ref was_target,
+							ref new_target,
+						} => {
+							write_symlink_retargeted(output, file, was_target, new_target);
+							differed_n += 1;
+						}
 					}
 				}
 
@@ -104,8 +151,25 @@ pub fn write_hash_comparison_results<Wo: Write, We: Write>(
 
 			Error::HashLengthDiffers
 		}
+		Err(CompareError::PayloadOxumDiffers { recorded, found }) => {
+			writeln!(error, "Payload-Oxum doesn't match; recorded: {}.{}, found: {}.{}", recorded.0, recorded.1, found.0, found.1)
+				.unwrap();
+			Error::NFilesDiffer(1)
+		}
+		Err(CompareError::BadSignature) => {
+			writeln!(error, "Signature doesn't verify").unwrap();
+			Error::NFilesDiffer(1)
+		}
 	};
 
+	if !warnings.is_empty() {
+		writeln!(output).unwrap();
+		writeln!(output, "Warnings:").unwrap();
+		for warning in warnings {
+			writeln!(output, "  {}", warning).unwrap();
+		}
+	}
+
 	output.flush().unwrap();
 	error.flush().unwrap();
 
@@ -139,6 +203,37 @@ fn write_result<W: Write>(out: &mut W, pre: &str, fname: &PathBuf, fname_indent:
 	}
 }
 
+fn write_metadata_change<W: Write>(out: &mut W, fname: &PathBuf, was: &Ownership, new: &Ownership) {
+	write_compare_result(out, "Metadata changed: ", fname);
+	if was.uid != new.uid {
+		writeln!(out, "  Owner: {} -> {}", was.uid, new.uid).unwrap();
+	}
+	if was.gid != new.gid {
+		writeln!(out, "  Group: {} -> {}", was.gid, new.gid).unwrap();
+	}
+	if was.mode != new.mode {
+		writeln!(out, "  Mode : {:o} -> {:o}", was.mode, new.mode).unwrap();
+	}
+}
+
+fn write_attributes_change<W: Write>(out: &mut W, fname: &PathBuf, was: &WindowsAttributes, new: &WindowsAttributes) {
+	write_compare_result(out, "Attributes changed: ", fname);
+	if (was.readonly, was.hidden, was.system) != (new.readonly, new.hidden, new.system) {
+		writeln!(out, "  Attributes: {} -> {}", was.flags(), new.flags()).unwrap();
+	}
+	if was.created != new.created {
+		writeln!(
+			out,
+			"  Created   : {}.{:09} -> {}.{:09}",
+			was.created.as_secs(),
+			was.created.subsec_nanos(),
+			new.created.as_secs(),
+			new.created.subsec_nanos()
+		)
+		.unwrap();
+	}
+}
+
 fn write_file_result_match<W: Write>(out: &mut W, fname: &PathBuf) {
 	if 15 + fname.to_str().unwrap().len() <= 80 {
 		writeln!(out, "File \"{}\" matches", fname.to_str().unwrap()).unwrap();
@@ -147,6 +242,90 @@ fn write_file_result_match<W: Write>(out: &mut W, fname: &PathBuf) {
 	}
 }
 
+fn write_file_result_assumed<W: Write>(out: &mut W, fname: &PathBuf) {
+	if 35 + fname.to_str().unwrap().len() <= 80 {
+		writeln!(out, "File \"{}\" assumed OK (metadata match)", fname.to_str().unwrap()).unwrap();
+	} else {
+		write_compare_result(out, "File assumed OK (metadata match): ", fname);
+	}
+}
+
+fn write_file_result_drift<W: Write>(
+	out: &mut W,
+	fname: &PathBuf,
+	was: &EntryMetadata,
+	new: &EntryMetadata,
+	matches: Option<bool>,
+) {
+	if 26 + fname.to_str().unwrap().len() <= 80 {
+		writeln!(out, "File \"{}\" changed since hashed", fname.to_str().unwrap()).unwrap();
+	} else {
+		write_result(out, "File changed since hashed: ", fname, 4, true);
+	}
+
+	if was.size != new.size {
+		writeln!(out, "  Size    : {} -> {}", was.size, new.size).unwrap();
+	}
+	if was.mtime != new.mtime {
+		writeln!(
+			out,
+			"  Modified: {}.{:09} -> {}.{:09}",
+			was.mtime.as_secs(),
+			was.mtime.subsec_nanos(),
+			new.mtime.as_secs(),
+			new.mtime.subsec_nanos()
+		)
+		.unwrap();
+	}
+	let content = match matches {
+		Some(true) => "matches",
+		Some(false) => "differs",
+		None => "not read, as the size differs",
+	};
+	writeln!(out, "  Content : {}", content).unwrap();
+}
+
+fn write_symlink_retargeted<W: Write>(out: &mut W, fname: &PathBuf, was_target: &str, new_target: &str) {
+	if 21 + fname.to_str().unwrap().len() <= 80 {
+		writeln!(out, "Symlink \"{}\" retargeted", fname.to_str().unwrap()).unwrap();
+	} else {
+		write_result(out, "Symlink retargeted: ", fname, 4, true);
+	}
+
+	write_result(out, "  Was: ", &PathBuf::from(was_target), 4, false);
+	write_result(out, "  Is : ", &PathBuf::from(new_target), 4, false);
+}
+
+fn write_differing_pieces<W: Write>(out: &mut W, piece_size: u64, ranges: &[(u64, u64)]) {
+	for &(start, end) in ranges {
+		let (first, last) = (start / piece_size, (end - 1) / piece_size);
+		match first == last {
+			true => writeln!(out, "  Piece {}: bytes {}..{}", first, start, end).unwrap(),
+			false => writeln!(out, "  Pieces {}-{}: bytes {}..{}", first, last, start, end).unwrap(),
+		}
+	}
+}
+
+fn write_failed_pieces<W: Write>(out: &mut W, fname: &PathBuf, pieces: &[usize]) {
+	if 21 + fname.to_str().unwrap().len() <= 80 {
+		writeln!(out, "File \"{}\" doesn't match", fname.to_str().unwrap()).unwrap();
+	} else {
+		write_result(out, "File doesn't match: ", fname, 4, true);
+	}
+
+	let mut pieces = pieces.iter().copied().peekable();
+	while let Some(first) = pieces.next() {
+		let mut last = first;
+		while pieces.next_if_eq(&(last + 1)).is_some() {
+			last += 1;
+		}
+		match first == last {
+			true => writeln!(out, "  Piece {} fails", first).unwrap(),
+			false => writeln!(out, "  Pieces {}-{} fail", first, last).unwrap(),
+		}
+	}
+}
+
 fn write_file_result_diff<W: Write>(out: &mut W, fname: &PathBuf, lhash: &str, chash: &str) {
 	if 21 + fname.to_str().unwrap().len() <= 80 {
 		writeln!(out, "File \"{}\" doesn't match", fname.to_str().unwrap()).unwrap();
@@ -13,11 +13,15 @@
  * limitations under the License.
  */
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
-use crate::Algorithm;
+use crate::{
+	Algorithm,
+	utilities::{parse_duration, parse_percent, parse_size},
+	operations::{ArchiveKind, CommentStyle, DedupeAction, FileOrder, HashEncoding, Keep, LogTarget, ManifestEncoding, MergePolicy, PathStyle, PublishTarget, ShardBy, SnapshotKind, SpecialFiles, UnicodeForm},
+};
 
 #[derive(Parser)]
 #[command(
@@ -27,6 +31,15 @@ use crate::Algorithm;
 	long_about = "A modern alternative to QuickSFV using Rust. Made with <3 by Cerda."
 )]
 pub struct Commands {
+	#[command(flatten)]
+	pub options: GlobalOptions,
+	/// Whether to verify or create hashes. Default: Verify
+	#[command(subcommand)]
+	pub command: Mode,
+}
+
+#[derive(Args)]
+pub struct GlobalOptions {
 	/// Hashing algorithm to use.
 	#[arg(value_enum, short, long, default_value = "unspecified")]
 	pub algorithm: Algorithm,
@@ -36,43 +49,771 @@ pub struct Commands {
 	/// Whether to recurse down symlinks. Default: `true`
 	#[arg(long)]
 	pub follow_symlinks: bool,
-	/// Files/directories to ignore. Default: none
+	/// Record symlinks by a hash of their target instead of skipping them
+	#[arg(long, conflicts_with = "follow_symlinks")]
+	pub record_symlinks: bool,
+	/// Record empty directories with a placeholder hash instead of skipping
+	/// them
+	#[arg(long)]
+	pub record_empty_dirs: bool,
+	/// Hash the layout of the tree instead of the contents of its files,
+	/// without reading them
+	#[arg(long, global = true)]
+	pub names_only: bool,
+	/// With `--names-only`, tell files apart by their size too
+	#[arg(long, global = true, requires = "names_only")]
+	pub with_sizes: bool,
+	/// What to do with FIFOs, sockets and device nodes. Default: skip
+	#[arg(value_enum, long, global = true, default_value = "skip")]
+	pub special_files: SpecialFiles,
+	/// Record files larger than this with a placeholder instead of hashing
+	/// them, e.g. `500M` or `2T`. Default: none
+	#[arg(long, global = true, value_parser = parse_size)]
+	pub skip_larger_than: Option<u64>,
+	/// Hash every hard link to a file instead of reusing the hash of the
+	/// first one. Default: each file is hashed once
+	#[arg(long, global = true)]
+	pub hash_each_hard_link: bool,
+	/// Skip hidden files and directories. Default: hidden files are hashed
+	#[arg(long, global = true)]
+	pub skip_hidden: bool,
+	/// Only hash the files git tracks, as listed by `git ls-files`, leaving
+	/// out untracked build output. Hash files made this way are verified
+	/// against those files only
+	#[arg(long, global = true)]
+	pub git_tracked: bool,
+	/// Let hashing update access times. Default: files are opened with
+	/// `O_NOATIME` where permitted
+	#[arg(long, global = true)]
+	pub update_atime: bool,
+	/// Evict files from the page cache once they're hashed. Default: off
+	#[arg(long, global = true)]
+	pub drop_caches: bool,
+	/// Hash files by mapping them into memory instead of reading them.
+	/// Default: off
+	#[arg(long, global = true)]
+	pub mmap: bool,
+	/// Read files through io_uring, keeping several reads in flight.
+	/// Default: off
+	#[cfg(feature = "io-uring")]
+	#[arg(long, global = true, conflicts_with = "mmap")]
+	pub io_uring: bool,
+	/// Part size of the multipart uploads S3 ETags are made for, with
+	/// `-a s3-etag`. Default: 8M, as the AWS CLI
+	#[arg(long, global = true, value_parser = parse_size, default_value = "8M")]
+	pub part_size: u64,
+	/// Hash files as text, reading CRLF line endings as LF, so files only
+	/// converted between Windows and Unix line endings verify. Noted in the
+	/// hash file, which is then always verified this way
+	#[arg(long, global = true)]
+	pub text_mode: bool,
+	/// Threads reading and hashing files. 0 for 255. Default: # of CPU
+	/// threads, reading with 1 thread on spinning disks. A value must follow `=`
+	#[arg(short, long, global = true, num_args = 0..=1, require_equals = true)]
+	pub jobs: Option<Option<usize>>,
+	/// Threads reading files ahead of the hashing threads. Default: `--jobs`
+	#[arg(long, global = true)]
+	pub io_threads: Option<usize>,
+	/// Threads hashing the files read. Default: `--jobs`
+	#[arg(long, global = true)]
+	pub hash_threads: Option<usize>,
+	/// Order files are hashed in. Default: inode
+	#[arg(value_enum, long, global = true, default_value = "inode")]
+	pub order: FileOrder,
+	/// How many more times to read a file that failed to be read, before
+	/// recording it as errored. Default: 0
+	#[arg(long, global = true, default_value_t = 0)]
+	pub retries: u32,
+	/// How long to wait before reading a file again, e.g. `500ms` or `5s`.
+	/// Default: 1s
+	#[arg(long, global = true, value_parser = parse_duration, default_value = "1s")]
+	pub retry_delay: Duration,
+	/// Read every file when creating, instead of reusing hashes of files
+	/// unchanged since an earlier run.
+	#[arg(long, global = true)]
+	pub no_cache: bool,
+	/// Record the owner, group and mode of each file when creating, or on
+	/// Windows its attributes and creation time, and report changes to them
+	/// when verifying or checking
+	#[arg(long, global = true)]
+	pub check_metadata: bool,
+	/// Hash cache file to use when creating. Default: `quickdash/hashes.cache`
+	/// in the user's cache directory
+	#[arg(long, global = true, conflicts_with = "no_cache")]
+	pub cache_path: Option<PathBuf>,
+	/// Files/directories to ignore, relative to the directory. Globs are
+	/// allowed, matching at any depth unless they start with `/`, and a
+	/// trailing `/` matches directories only. Default: none
 	#[arg(short, long)]
 	pub ignored_files: Vec<String>,
-	/// Whether to verify or create hashes. Default: Verify
-	#[command(subcommand)]
-	pub command: Mode,
+	/// Also hash the members of these kinds of archives, stored as
+	/// `archive.zip!member/path`. Default: none
+	#[arg(long, global = true, value_delimiter = ',')]
+	pub look_inside: Vec<ArchiveKind>,
+	/// Also hash resource forks, Finder info and quarantine attributes, on
+	/// macOS. Default: off
+	#[arg(long, global = true)]
+	pub mac_metadata: bool,
+	/// Hash files from a snapshot of their volume, taken for the run and
+	/// removed after it. Default: none
+	#[arg(value_enum, long, global = true)]
+	pub snapshot: Option<SnapshotKind>,
+	/// File of patterns of files/directories to ignore, one per line. Default: none
+	#[arg(long, global = true)]
+	pub exclude_from: Option<PathBuf>,
+	/// Path separator used in written hash files. Default: native
+	#[arg(value_enum, long, global = true, default_value = "native")]
+	pub path_style: PathStyle,
+	/// Bring paths to a common Unicode normalization form before comparing them.
+	/// Default: off, NFC if given without a value. A value must follow `=`
+	#[arg(value_enum, long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "nfc")]
+	pub normalize_unicode: Option<UnicodeForm>,
+	/// Character starting comment lines in written hash files. Default: semicolon
+	#[arg(value_enum, long, global = true, default_value = "semicolon")]
+	pub comment_style: CommentStyle,
+	/// How hashes are written in hash files. Multihashes, CIDs and OCI
+	/// digests are always accepted when reading, and name the algorithm.
+	/// Default: hex
+	#[arg(value_enum, long, global = true, default_value = "hex")]
+	pub hash_encoding: HashEncoding,
+	/// Write hash files exactly as `rclone hashsum` and `rclone md5sum` print
+	/// them, and read them by their rules
+	#[arg(long, global = true)]
+	pub rclone: bool,
+	/// Write hash files as BSD mtree specs, listing the type, size, mode
+	/// and owner of each file with its checksum, and read them as such.
+	/// SHA-256 unless `-a` says otherwise
+	#[arg(long, global = true, conflicts_with = "rclone")]
+	pub mtree: bool,
+	/// Text encoding of hash files being read. Default: auto
+	#[arg(value_enum, long, global = true, default_value = "auto")]
+	pub manifest_encoding: ManifestEncoding,
+	/// Encrypt written hash files to this recipient, an age public key or
+	/// a gpg key. May be repeated. Encrypted hash files are decrypted on
+	/// read whether or not it's given
+	#[arg(long, global = true, conflicts_with = "rclone")]
+	pub encrypt_to: Vec<String>,
+	/// age identity file to decrypt age-encrypted hash files with. May be
+	/// repeated
+	#[arg(long, global = true)]
+	pub identity: Vec<PathBuf>,
+	/// Append a hash-chained record of each verification run to this file
+	#[arg(long, global = true)]
+	pub audit_log: Option<PathBuf>,
+	/// Write the metrics of each verification run to this file, for
+	/// node_exporter's textfile collector
+	#[arg(long, global = true)]
+	pub metrics_textfile: Option<PathBuf>,
+	/// POST a JSON summary of each failed verification run to this URL
+	#[arg(long, global = true)]
+	pub notify_webhook: Option<String>,
+	/// Email a summary of each failed verification run to this address.
+	/// May be repeated
+	#[arg(long, global = true, requires = "smtp_url")]
+	pub notify_email: Vec<String>,
+	/// SMTP server to send emails through, like `smtps://mail.example.com`,
+	/// logged into with `~/.netrc`
+	#[arg(long, global = true, requires = "notify_email")]
+	pub smtp_url: Option<String>,
+	/// Sender of the emails. Default: `quickdash@` the host name
+	#[arg(long, global = true, requires = "notify_email")]
+	pub smtp_from: Option<String>,
+	/// Log a record of each verification run, and one of each file that
+	/// failed, to syslog or the systemd journal
+	#[arg(value_enum, long, global = true)]
+	pub log_to: Option<LogTarget>,
+	/// Run as a systemd service of `Type=notify`: tell systemd when the run
+	/// is ready, ping its watchdog, and on SIGTERM stop hashing and write
+	/// out what was hashed
+	#[arg(long, global = true)]
+	pub service: bool,
+	/// How long to wait for another run using the same hash file to finish
+	/// before exiting with 75
+	#[arg(long, global = true, value_parser = parse_duration, default_value = "0s")]
+	pub lock_wait: Duration,
+	/// Note each `create` and `verify` run, with the status and hash of
+	/// each file, in a central inventory, by piping SQL to this shell
+	/// command, like `sqlite3 inventory.db` or `psql inventory`
+	#[arg(long, global = true)]
+	pub inventory: Option<String>,
+	/// Name of this host in the inventory. Default: the host name
+	#[arg(long, global = true, requires = "inventory")]
+	pub host: Option<String>,
+	/// File of the hashes of known good files, like an NSRL RDS, to tell the
+	/// unknown files of a `create` or `verify` run by. May be repeated
+	#[arg(long, global = true)]
+	pub known_good: Vec<PathBuf>,
+	/// File of the hashes of known bad files, to flag the files of a
+	/// `create` or `verify` run by. May be repeated
+	#[arg(long, global = true)]
+	pub known_bad: Vec<PathBuf>,
+	/// Directory stored paths are relative to. Default: the hashed directory
+	#[arg(long, global = true)]
+	pub relative_to: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 pub enum Mode {
 	/// Create a hash file
-	Create {
+	Create(CreateArgs),
+	/// Verify a hash file
+	Verify(VerifyArgs),
+	/// Check a hash file
+	Check {
+		/// Directories to verify. Default: current directory
+		#[arg(default_value = ".")]
+		paths: Vec<PathBuf>,
+		/// Name the entries of each directory are stored under, in order.
+		/// Default: the directory names when several are given
+		#[arg(long)]
+		label: Vec<String>,
+		/// Input filename, or an HTTP(S) URL to fetch it from. Default:
+		/// `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+		/// Detached gpg signature of the hash file, a path or an HTTP(S)
+		/// URL, checked before anything's compared
+		#[arg(long)]
+		signature: Option<PathBuf>,
+		/// Keep when each file last verified next to the hash file, and list
+		/// the files that never did
+		#[arg(long)]
+		record_verified: bool,
+		/// With `--record-verified`, also list the files that haven't
+		/// verified in this many days
+		#[arg(long, requires = "record_verified")]
+		stale_after: Option<u64>,
+	},
+	/// Update a hash file, reading only the files that changed since it was
+	/// last updated, as recorded by `record-changes` or, on Windows, the
+	/// NTFS change journal
+	Update {
+		/// Directory the hash file is of. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// Hash file. Default: `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+	/// Record which paths change under a directory until stopped, on Linux,
+	/// so `update` walks only those
+	RecordChanges {
+		/// Directory to watch. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// Hash file, next to which changes are recorded. Default:
+		/// `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+	/// Print a single Merkle-style root hash of a directory
+	TreeHash {
 		/// Directory to hash. Default: current directory
 		#[arg(default_value = ".")]
 		path: PathBuf,
-		/// Output filename. Default: `directory_name.hash"`
+		/// Root hash the tree should have, in hex, as an OCI digest like
+		/// `sha256:...`, a multihash or a CID. The latter name the algorithm
 		#[arg(long)]
+		expect: Option<String>,
+	},
+	/// Hash a remote directory over SSH, with quickdash run there, and
+	/// verify it against a local hash file
+	Remote {
+		/// The directory, as `[user@]host:path`
+		target: String,
+		/// Hash file to verify the remote directory against. Default: print
+		/// its hash file instead
+		#[arg(short, long)]
 		file: Option<PathBuf>,
+		/// Also keep the remote directory's hash file here
+		#[arg(long)]
+		output: Option<PathBuf>,
+		/// Command running quickdash on the remote host
+		#[arg(long, default_value = "quickdash", conflicts_with = "upload")]
+		agent: String,
+		/// Copy this quickdash binary to the remote host for the run, for
+		/// hosts without it, of the same platform
+		#[arg(long)]
+		upload: bool,
+	},
+	/// Compare two directories, telling the files only in either and those
+	/// that differ, without a hash file
+	Cmp {
+		/// The two directories
+		#[arg(num_args = 2, required = true, value_names = ["A", "B"])]
+		paths: Vec<PathBuf>,
+		/// Re-read differing files to show the offset of their first
+		/// differing byte, with the bytes around it in hex
+		#[arg(long)]
+		first_difference: bool,
+	},
+	/// Hash a file at an HTTP(S) URL as it's downloaded, without saving it
+	Sum {
+		/// `http://` or `https://` URL of the file
+		url: String,
+		/// Hash the file should have, in hex, as an OCI digest like
+		/// `sha256:...`, a multihash or a CID. The latter name the algorithm
+		#[arg(long)]
+		expect: Option<String>,
+	},
+	/// Verify a byte range of a file using its bao outboard
+	VerifyRange {
+		/// File to verify, as named in the hash file
+		name: PathBuf,
+		/// First byte to verify. Default: 0
+		#[arg(long, value_parser = parse_size, default_value = "0")]
+		offset: u64,
+		/// Amount of bytes to verify. Default: up to the end of the file
+		#[arg(long, value_parser = parse_size)]
+		length: Option<u64>,
+		/// Directory the hash file is of. Default: current directory
+		#[arg(long, default_value = ".")]
+		path: PathBuf,
+		/// Input filename. Default: `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+	/// Verify downloaded data against the piece hashes of a .torrent file
+	CheckTorrent {
+		/// The .torrent file, v1, v2 or hybrid
+		torrent: PathBuf,
+		/// Directory the torrent was downloaded to. Default: current
+		/// directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+	},
+	/// Create or validate BagIt bags
+	Bagit {
+		#[command(subcommand)]
+		action: BagitAction,
+	},
+	/// Create or verify gpg-signed SHA256SUMS files of releases
+	Release {
+		#[command(subcommand)]
+		action: ReleaseAction,
+	},
+	/// Merge several hash files into one
+	Merge {
+		/// Hash files to merge
+		#[arg(required = true)]
+		files: Vec<PathBuf>,
+		/// Output filename
+		#[arg(short, long)]
+		output: PathBuf,
+		/// What to do when hash files disagree. Default: error
+		#[arg(value_enum, long, default_value = "error")]
+		policy: MergePolicy,
+		/// Comment to write at the top of the hash file. May be repeated
+		#[arg(long)]
+		comment: Vec<String>,
 		#[arg(short, long)]
 		force: bool,
 	},
-	/// Verify a hash file
-	Verify {
-		/// Directory to verify. Default: current directory
+	/// Look files up by hash or by name, in hash files or the inventory kept
+	/// with `--inventory`
+	Query {
+		/// Hash to look for, in hexadecimal
+		#[arg(long, required_unless_present_any = ["name", "same_as"], conflicts_with_all = ["name", "same_as"])]
+		hash: Option<String>,
+		/// Name of a file, as stored, to get the hash of and the other files
+		/// with it
+		#[arg(long = "path", conflicts_with = "same_as")]
+		name: Option<PathBuf>,
+		/// File to look for copies of, hashing it
+		#[arg(long)]
+		same_as: Option<PathBuf>,
+		/// Hash files to look in. May be repeated. Default: the inventory,
+		/// with `--inventory`
+		#[arg(short, long)]
+		file: Vec<PathBuf>,
+	},
+	/// Repair corrupt or missing files from the PAR2 recovery files made
+	/// with `create --par2`, then check them
+	Repair {
+		/// Directory the hash file is of. Default: current directory
 		#[arg(default_value = ".")]
 		path: PathBuf,
 		/// Input filename. Default: `directory_name.hash`
 		#[arg(short, long)]
 		file: Option<PathBuf>,
 	},
-	/// Check a hash file
-	Check {
+	/// Find near-duplicate files, like re-encodes or edited documents, by
+	/// how alike their ssdeep digests are
+	Similar {
+		/// Directory to look in. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// How alike files must be to be listed together, from 1 to 100
+		#[arg(long, default_value = "50", value_parser = clap::value_parser!(u32).range(1..=100))]
+		threshold: u32,
+		/// Print the ssdeep digest of each file instead
+		#[arg(long)]
+		digests: bool,
+	},
+	/// Find files with the same content, and hard-link, reflink or delete
+	/// all but one of each set
+	Dedupe {
+		/// Directory to look in. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// What to do with the duplicates of each kept file. Default: only
+		/// list them
+		#[arg(long)]
+		action: Option<DedupeAction>,
+		/// Which file of each set to keep
+		#[arg(long, default_value = "first")]
+		keep: Keep,
+		/// Do what `--action` says, instead of only printing it
+		#[arg(long, requires = "action")]
+		apply: bool,
+		/// Log of what's done, to undo it by. Default:
+		/// `directory_name.dedupe.log`
+		#[arg(long)]
+		log: Option<PathBuf>,
+		/// Undo what the log says was done, copying the kept files back
+		#[arg(long, conflicts_with_all = ["action", "apply"])]
+		undo: bool,
+	},
+	/// Rename files to add their CRC32 in brackets before the extension,
+	/// like `name [89ABCDEF].ext`, or to remove it
+	Tag {
+		/// Directory to rename the files in. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// Remove the hash from the names of the files with one instead
+		#[arg(long)]
+		strip: bool,
+		/// Only print the renames
+		#[arg(long)]
+		dry_run: bool,
+	},
+	/// Verify interactively, watching the results come in and hashing
+	/// again, accepting or quarantining the files that fail
+	Tui {
 		/// Directory to verify. Default: current directory
 		#[arg(default_value = ".")]
 		path: PathBuf,
 		/// Input filename. Default: `directory_name.hash`
 		#[arg(short, long)]
 		file: Option<PathBuf>,
+		/// Directory to quarantine files to, under the same relative paths,
+		/// logging them in `quarantine.log` there
+		#[arg(long)]
+		quarantine: Option<PathBuf>,
+		/// Hard-link the files to quarantine instead of moving them
+		#[arg(long, requires = "quarantine")]
+		quarantine_link: bool,
+	},
+	/// Take create and verify jobs as JSON-RPC requests on a Unix socket,
+	/// for front ends and schedulers to drive
+	Daemon {
+		/// Socket to listen on. Default: `quickdash.sock` in
+		/// `$XDG_RUNTIME_DIR`, or the temporary directory
+		#[arg(long)]
+		socket: Option<PathBuf>,
 	},
+	/// Serve an HTTP API to verify directories, fetch their hash files and
+	/// get the latest results as JSON, for dashboards
+	Serve {
+		/// Directories to serve, each with its `directory_name.hash`, found
+		/// by its name
+		#[arg(required = true)]
+		paths: Vec<PathBuf>,
+		/// Address to listen on
+		#[arg(long, default_value = "127.0.0.1:7878")]
+		listen: String,
+		/// File holding a token requests must carry as
+		/// `Authorization: Bearer <token>`
+		#[arg(long)]
+		token_file: Option<PathBuf>,
+	},
+	/// List, compare or prune the generations of a hash file kept with
+	/// `create --keep-generation`
+	Generations {
+		#[command(subcommand)]
+		action: GenerationsAction,
+	},
+	/// Check that the records of an audit log still chain, unedited
+	VerifyAuditLog {
+		/// The audit log written with `--audit-log`
+		log: PathBuf,
+	},
+}
+
+#[derive(Args)]
+pub struct CreateArgs {
+	/// Directories to hash. Default: current directory
+	#[arg(default_value = ".")]
+	pub paths: Vec<PathBuf>,
+	/// Name to store the entries of each directory under, in order.
+	/// Default: the directory names when several are given
+	#[arg(long)]
+	pub label: Vec<String>,
+	/// Output filename. Default: `directory_name.hash"`
+	#[arg(long)]
+	pub file: Option<PathBuf>,
+	#[arg(short, long)]
+	pub force: bool,
+	/// Split the output into shards listed by an index file. Default: none
+	#[arg(value_enum, long)]
+	pub shard_by: Option<ShardBy>,
+	/// Write hashes as they're computed instead of collecting them first,
+	/// keeping memory use flat for enormous trees
+	#[arg(long, conflicts_with = "shard_by")]
+	pub low_memory: bool,
+	/// With `--low-memory`, don't sort the output
+	#[arg(long, requires = "low_memory")]
+	pub unsorted: bool,
+	/// Reuse the hashes checkpointed by an interrupted run making the same
+	/// hash file, reading only the files it hadn't, or that changed since
+	#[arg(long, conflicts_with = "low_memory")]
+	pub resume: bool,
+	/// Store absolute canonical paths instead of relative ones
+	#[arg(long, conflicts_with = "relative_to")]
+	pub absolute_paths: bool,
+	/// Comment to write at the top of the hash file. May be repeated
+	#[arg(long)]
+	pub comment: Vec<String>,
+	/// Record the size and modification time of each file, for
+	/// `verify --quick`
+	#[arg(long)]
+	pub record_metadata: bool,
+	/// Write the tree hash of the directory at the top of the hash file
+	#[arg(long)]
+	pub tree_hash: bool,
+	/// Write bao outboards of the files next to the hash file, for
+	/// `verify-range`. BLAKE3 only
+	#[arg(long)]
+	pub bao_outboard: bool,
+	/// Write librsync signatures of the files next to the hash file, to
+	/// work out deltas against them with `rdiff delta`
+	#[arg(long)]
+	pub rsync_signatures: bool,
+	/// Bytes in each block of the librsync signatures
+	#[arg(long, value_parser = parse_size, default_value = "2048", requires = "rsync_signatures")]
+	pub signature_block_len: u64,
+	/// Also make PAR2 recovery files able to restore this percentage of
+	/// the files, like `5%`, with `par2`, for `repair`
+	#[arg(long, value_parser = parse_percent)]
+	pub par2: Option<u32>,
+	/// Also record a hash of each piece of this size of larger files, so
+	/// `verify` can tell which byte ranges differ. Default: none
+	#[arg(long, value_parser = parse_size)]
+	pub piece_size: Option<u64>,
+	/// Also write a BitTorrent v2 .torrent of the files, without
+	/// trackers, from their pieces roots. `-a btv2` only
+	#[arg(long)]
+	pub torrent: Option<PathBuf>,
+	/// Bytes in each piece of the .torrent, a power of two of at least
+	/// 16K
+	#[arg(long, value_parser = parse_size, default_value = "256K", requires = "torrent")]
+	pub torrent_piece_length: u64,
+	/// Also write an in-toto statement of the files as JSON, with SLSA
+	/// provenance, for publishing as a supply-chain attestation
+	#[arg(long)]
+	pub in_toto: Option<PathBuf>,
+	/// Sign the in-toto statement with this shell command, which reads
+	/// what to sign and prints a raw signature, writing a DSSE envelope
+	#[arg(long, requires = "in_toto")]
+	pub sign_with: Option<String>,
+	/// Have the hash file timestamped by the RFC 3161 time-stamping
+	/// authority at this http:// URL, keeping its token next to it
+	#[arg(long, conflicts_with = "shard_by")]
+	pub timestamp_url: Option<String>,
+	/// Also keep a copy of the hash file as a new generation, in the
+	/// `.history` directory next to it
+	#[arg(long, conflicts_with = "shard_by")]
+	pub keep_generation: bool,
+	/// Upload the hash file, and its shards, under this `s3://`,
+	/// WebDAV `https://` or `sftp://` URL once written. May be repeated
+	#[arg(long)]
+	pub publish: Vec<PublishTarget>,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+	/// Directories to verify. Default: current directory
+	#[arg(default_value = ".")]
+	pub paths: Vec<PathBuf>,
+	/// Name the entries of each directory are stored under, in order.
+	/// Default: the directory names when several are given
+	#[arg(long)]
+	pub label: Vec<String>,
+	/// Input filename, or an HTTP(S) URL to fetch it from. Default:
+	/// `directory_name.hash`
+	#[arg(short, long)]
+	pub file: Option<PathBuf>,
+	/// Detached gpg signature of the hash file, a path or an HTTP(S)
+	/// URL, checked before anything's compared
+	#[arg(long)]
+	pub signature: Option<PathBuf>,
+	/// Only read files whose size or modification time differ from the
+	/// recorded ones
+	#[arg(long)]
+	pub quick: bool,
+	/// With `--quick`, read this percentage of the other files anyway,
+	/// picked at random. Default: 0
+	#[arg(long, requires = "quick", default_value_t = 0.0)]
+	pub sample: f64,
+	/// Only read this percentage of the files' bytes, the files verified
+	/// least recently, assuming the others unchanged if their metadata
+	/// matches. Implies `--record-verified`
+	#[arg(long, conflicts_with_all = ["quick", "scrub_bytes"])]
+	pub scrub_percent: Option<f64>,
+	/// Only read this many bytes of the files verified least recently,
+	/// assuming the others unchanged if their metadata matches. Implies
+	/// `--record-verified`
+	#[arg(long, value_parser = parse_size, conflicts_with = "quick")]
+	pub scrub_bytes: Option<u64>,
+	/// Keep when each file last verified next to the hash file, and list
+	/// the files that never did
+	#[arg(long)]
+	pub record_verified: bool,
+	/// With `--record-verified`, also list the files that haven't
+	/// verified in this many days
+	#[arg(long, requires = "record_verified")]
+	pub stale_after: Option<u64>,
+	/// Move the files whose content differs to this directory, under
+	/// the same relative paths, logging them in `quarantine.log` there
+	#[arg(long)]
+	pub quarantine: Option<PathBuf>,
+	/// Hard-link the files to quarantine instead of moving them
+	#[arg(long, requires = "quarantine")]
+	pub quarantine_link: bool,
+	/// Ask for each file that differs, was removed or was added whether
+	/// to update its entry in the hash file, keep it as is, or delete it
+	#[arg(long, conflicts_with = "quarantine")]
+	pub fix: bool,
+	/// With `--fix`, accept every change without asking
+	#[arg(long, requires = "fix")]
+	pub yes: bool,
+	/// Reuse what an interrupted run verifying the same hash file read,
+	/// reading only the files it hadn't, or that changed since
+	#[arg(long)]
+	pub resume: bool,
+}
+
+#[derive(Subcommand)]
+pub enum BagitAction {
+	/// Turn a directory into a bag in place, moving what it holds into its
+	/// `data` directory. Checksums are SHA-512 unless `-a` says otherwise
+	Create {
+		/// Directory to bag. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// `Label: value` line to add to bag-info.txt. May be repeated
+		#[arg(long)]
+		info: Vec<String>,
+	},
+	/// Check a bag's payload against its manifests
+	Validate {
+		/// Bag to validate. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// Only check the size and amount of payload files against the
+		/// Payload-Oxum of bag-info.txt
+		#[arg(long)]
+		fast: bool,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum ReleaseAction {
+	/// Write the checksums of a directory's files as coreutils does, and sign
+	/// them with gpg. Checksums are SHA-256 unless `-a` says otherwise
+	Create {
+		/// Directory of the release. Default: current directory
+		#[arg(default_value = ".")]
+		path: PathBuf,
+		/// Output filename. Default: `SHA256SUMS`, or that of `-a`, in the
+		/// directory
+		#[arg(long)]
+		file: Option<PathBuf>,
+		/// Sign into a clearsigned `.asc` holding the checksums, instead of a
+		/// detached one
+		#[arg(long)]
+		clearsign: bool,
+		/// Key to sign with. Default: gpg's default key
+		#[arg(short = 'u', long)]
+		local_user: Option<String>,
+		#[arg(short, long)]
+		force: bool,
+	},
+	/// Check the signature of a checksum file, then the files it lists
+	Verify {
+		/// The signature, detached or clearsigned, like `SHA256SUMS.asc`
+		signature: PathBuf,
+		/// Directory of the files. Default: that of the signature
+		#[arg(long)]
+		path: Option<PathBuf>,
+		/// Checksum file a detached signature is of. Default: the signature
+		/// without its `.asc` or `.sig`
+		#[arg(long)]
+		file: Option<PathBuf>,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum GenerationsAction {
+	/// List the generations, oldest first, numbered from 1
+	List {
+		/// Directory the hash file is of. Default: current directory
+		#[arg(long, default_value = ".")]
+		path: PathBuf,
+		/// Hash file. Default: `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+	/// List the files added, removed or changed between two generations
+	Diff {
+		/// Older generation, by number or name
+		from: String,
+		/// Newer generation, by number or name. Default: the newest
+		to: Option<String>,
+		/// Directory the hash file is of. Default: current directory
+		#[arg(long, default_value = ".")]
+		path: PathBuf,
+		/// Hash file. Default: `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+	/// List the generations in which a file changed, with its hash
+	Log {
+		/// File, as named in the hash file
+		name: PathBuf,
+		/// Directory the hash file is of. Default: current directory
+		#[arg(long, default_value = ".")]
+		path: PathBuf,
+		/// Hash file. Default: `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+	/// Remove all but the newest generations
+	Prune {
+		/// Generations to keep
+		#[arg(long)]
+		keep: usize,
+		/// Directory the hash file is of. Default: current directory
+		#[arg(long, default_value = ".")]
+		path: PathBuf,
+		/// Hash file. Default: `directory_name.hash`
+		#[arg(short, long)]
+		file: Option<PathBuf>,
+	},
+}
+
+impl Mode {
+	/// Directories walked, none for `merge`.
+	pub fn paths(&self) -> &[PathBuf] {
+		match self {
+			Mode::Create(CreateArgs { paths, .. }) | Mode::Verify(VerifyArgs { paths, .. }) | Mode::Check { paths, .. } | Mode::Serve { paths, .. } | Mode::Cmp { paths, .. } => paths,
+			Mode::TreeHash { path, .. } | Mode::VerifyRange { path, .. } | Mode::CheckTorrent { path, .. } | Mode::Repair { path, .. } | Mode::Similar { path, .. } | Mode::Dedupe { path, .. } | Mode::Tag { path, .. } | Mode::Tui { path, .. } | Mode::Update { path, .. } | Mode::RecordChanges { path, .. } => {
+				std::slice::from_ref(path)
+			}
+			Mode::Bagit { action: BagitAction::Create { path, .. } | BagitAction::Validate { path, .. } } => std::slice::from_ref(path),
+			Mode::Release { action: ReleaseAction::Create { path, .. } } => std::slice::from_ref(path),
+			Mode::Release { action: ReleaseAction::Verify { path, .. } } => path.as_slice(),
+			Mode::Remote { .. } | Mode::Sum { .. } | Mode::Merge { .. } | Mode::Query { .. } | Mode::Daemon { .. } | Mode::Generations { .. } | Mode::VerifyAuditLog { .. } => &[],
+		}
+	}
 }
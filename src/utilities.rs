@@ -15,7 +15,14 @@
 
 //! Module containing various utility functions
 
-use std::path::Path;
+use std::{
+	borrow::Cow,
+	io::{self, Write},
+	path::Path,
+	process::{Command, Output, Stdio},
+	thread,
+	time::Duration,
+};
 
 /// Merges two `Vec`s.
 ///
@@ -51,7 +58,7 @@ pub fn mul_str(what: &str, n: usize) -> String {
 /// # Examples
 ///
 /// ```
-/// # use std::path::Path;
+/// # use std::{borrow::Cow, path::Path, time::Duration};
 /// assert_eq!(
 /// 	quickdash::utilities::relative_name(Path::new("/usr"), Path::new("/usr/bin/quickdash")),
 /// 	"bin/quickdash".to_string()
@@ -61,3 +68,250 @@ pub fn relative_name<'a>(prefix: &'a Path, what: &'a Path) -> &'a Path {
 	what.strip_prefix(prefix)
 		.unwrap()
 }
+
+
+/// Turn a path into its `\\?\` extended-length form on Windows, so that it
+/// can be opened or walked even when longer than 260 characters. Relative
+/// paths are made absolute first. Does nothing on other platforms.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+	use std::{
+		ffi::OsString,
+		path::{Component, Prefix},
+	};
+
+	let Ok(absolute) = std::path::absolute(path) else {
+		return Cow::Borrowed(path);
+	};
+	let mut long = match absolute.components().next() {
+		Some(Component::Prefix(prefix)) => match prefix.kind() {
+			Prefix::Disk(_) => OsString::from(r"\\?\"),
+			// `\\server\share` becomes `\\?\UNC\server\share`
+			Prefix::UNC(..) => {
+				let mut long = OsString::from(r"\\?\UNC");
+				long.push(&absolute.as_os_str().to_string_lossy()[1..]);
+				return Cow::Owned(long.into());
+			}
+			// Already verbatim or a device path
+			_ => return Cow::Owned(absolute),
+		},
+		_ => return Cow::Owned(absolute),
+	};
+	long.push(absolute.as_os_str());
+	Cow::Owned(long.into())
+}
+
+/// Turn a path into its `\\?\` extended-length form on Windows, so that it
+/// can be opened or walked even when longer than 260 characters. Relative
+/// paths are made absolute first. Does nothing on other platforms.
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+	Cow::Borrowed(path)
+}
+
+/// Strip the `\\?\` prefix added by `long_path()`, for displaying or storing
+/// a path. Does nothing on other platforms.
+#[cfg(windows)]
+pub fn short_path(path: &Path) -> Cow<'_, Path> {
+	let raw = path.as_os_str().to_string_lossy();
+	if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+		Cow::Owned(format!(r"\\{}", unc).into())
+	} else if let Some(disk) = raw.strip_prefix(r"\\?\") {
+		Cow::Owned(disk.into())
+	} else {
+		Cow::Borrowed(path)
+	}
+}
+
+/// Strip the `\\?\` prefix added by `long_path()`, for displaying or storing
+/// a path. Does nothing on other platforms.
+#[cfg(not(windows))]
+pub fn short_path(path: &Path) -> Cow<'_, Path> {
+	Cow::Borrowed(path)
+}
+
+/// Run `command` through the shell, `sh -c` or `cmd /C` on Windows, with
+/// `input` on its standard input, and wait for it. What it prints is in the
+/// returned output if `capture` is set, and goes to ours otherwise.
+pub fn run_shell(command: &str, input: Vec<u8>, capture: bool) -> io::Result<Output> {
+	let (shell, flag) = match cfg!(windows) {
+		true => ("cmd", "/C"),
+		false => ("sh", "-c"),
+	};
+	let mut child = Command::new(shell)
+		.args([flag, command])
+		.stdin(Stdio::piped())
+		.stdout(if capture { Stdio::piped() } else { Stdio::inherit() })
+		.spawn()?;
+	// Written from another thread, so a command printing before it has read
+	// all of its input can't fill its stdout and stall. Dropping stdin once
+	// written closes it, so the command sees the end of the input
+	let mut stdin = child.stdin.take().expect("stdin is piped");
+	let sender = thread::spawn(move || stdin.write_all(&input));
+	let output = child.wait_with_output()?;
+	sender.join().expect("writing to the command doesn't panic")?;
+	Ok(output)
+}
+
+/// Parse a size in bytes, optionally suffixed with a binary unit.
+///
+/// # Examples
+///
+/// ```
+/// # use quickdash::utilities::parse_size;
+/// assert_eq!(parse_size("512"), Ok(512));
+/// assert_eq!(parse_size("4K"), Ok(4096));
+/// assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+/// assert!(parse_size("big").is_err());
+/// ```
+pub fn parse_size(size: &str) -> Result<u64, String> {
+	let size = size.trim();
+	let number_len = size.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(size.len());
+	let (number, unit) = size.split_at(number_len);
+	let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+		"" => 1,
+		"K" => 1 << 10,
+		"M" => 1 << 20,
+		"G" => 1 << 30,
+		"T" => 1 << 40,
+		"P" => 1 << 50,
+		_ => return Err(format!("unknown size unit {:?}", unit)),
+	};
+	let number: f64 = number.parse().map_err(|_| format!("invalid size {:?}", size))?;
+	Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a duration in seconds, optionally suffixed with `ms`, `s`, `m` or
+/// `h`.
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// # use quickdash::utilities::parse_duration;
+/// assert_eq!(parse_duration("5"), Ok(Duration::from_secs(5)));
+/// assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+/// assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+	let duration = duration.trim();
+	let number_len = duration.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(duration.len());
+	let (number, unit) = duration.split_at(number_len);
+	let seconds: f64 = match unit.trim() {
+		"ms" => 0.001,
+		"" | "s" => 1.0,
+		"m" => 60.0,
+		"h" => 3600.0,
+		_ => return Err(format!("unknown duration unit {:?}", unit)),
+	};
+	let number: f64 = number.parse().map_err(|_| format!("invalid duration {:?}", duration))?;
+	Ok(Duration::from_secs_f64(number * seconds))
+}
+
+/// Parse a whole percentage from 1 to 100, optionally suffixed with `%`.
+///
+/// # Examples
+///
+/// ```
+/// # use quickdash::utilities::parse_percent;
+/// assert_eq!(parse_percent("5%"), Ok(5));
+/// assert_eq!(parse_percent("10"), Ok(10));
+/// assert!(parse_percent("0%").is_err());
+/// ```
+pub fn parse_percent(percent: &str) -> Result<u32, String> {
+	match percent.trim().trim_end_matches('%').trim().parse() {
+		Ok(percent @ 1..=100) => Ok(percent),
+		_ => Err(format!("invalid percentage {:?}, expected 1% to 100%", percent)),
+	}
+}
+
+/// Escape a filename for a hash file line, coreutils-style, if it contains
+/// characters that would otherwise break the line apart: newlines, tabs,
+/// leading/trailing whitespace or, unless it's a separator, a backslash.
+///
+/// Whitespace at either end is written as `\xNN` if it's ASCII, `\u{N}`
+/// otherwise, so reading the line can't trim it off.
+///
+/// Returns `None` if the filename can be written as-is.
+///
+/// # Examples
+///
+/// ```
+/// # use quickdash::utilities::escape_filename;
+/// assert_eq!(escape_filename("plain name", false), None);
+/// assert_eq!(escape_filename("new\nline", false), Some("new\\nline".to_string()));
+/// assert_eq!(escape_filename(" padded\t", false), Some("\\x20padded\\t".to_string()));
+/// assert_eq!(escape_filename("\u{a0}nbsp\u{b}\u{c}", false), Some("\\u{a0}nbsp\\x0b\\x0c".to_string()));
+/// assert_eq!(escape_filename("  two  ", false), Some("\\x20\\x20two\\x20\\x20".to_string()));
+/// assert_eq!(escape_filename("back\\slash", false), Some("back\\\\slash".to_string()));
+/// assert_eq!(escape_filename("dir\\file", true), None);
+/// ```
+pub fn escape_filename(name: &str, backslash_is_separator: bool) -> Option<String> {
+	let needs_escape = name.contains(['\n', '\r', '\t'])
+		|| name.starts_with(char::is_whitespace)
+		|| name.ends_with(char::is_whitespace)
+		|| (!backslash_is_separator && name.contains('\\'));
+	if !needs_escape {
+		return None;
+	}
+
+	let len = name.chars().count();
+	let leading = name.chars().take_while(|c| c.is_whitespace()).count();
+	let trailing = len - name.chars().rev().take_while(|c| c.is_whitespace()).count();
+	let mut escaped = String::with_capacity(name.len() + 8);
+	for (i, c) in name.chars().enumerate() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (i < leading || i >= trailing) && c.is_ascii() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+			c if i < leading || i >= trailing => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	Some(escaped)
+}
+
+/// Undo `escape_filename()`. Returns `None` on a malformed escape sequence.
+///
+/// # Examples
+///
+/// ```
+/// # use quickdash::utilities::unescape_filename;
+/// assert_eq!(unescape_filename("new\\nline"), Some("new\nline".to_string()));
+/// assert_eq!(unescape_filename("\\x20padded\\t"), Some(" padded\t".to_string()));
+/// assert_eq!(unescape_filename("\\u{a0}nbsp\\x0b"), Some("\u{a0}nbsp\u{b}".to_string()));
+/// assert_eq!(unescape_filename("dangling\\"), None);
+/// ```
+pub fn unescape_filename(escaped: &str) -> Option<String> {
+	let mut name = String::with_capacity(escaped.len());
+	let mut chars = escaped.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			name.push(c);
+			continue;
+		}
+		match chars.next()? {
+			'\\' => name.push('\\'),
+			'n' => name.push('\n'),
+			'r' => name.push('\r'),
+			't' => name.push('\t'),
+			'x' => {
+				let hex: String = chars.by_ref().take(2).collect();
+				let byte = u8::from_str_radix(&hex, 16).ok().filter(u8::is_ascii)?;
+				name.push(byte as char);
+			}
+			'u' => {
+				if chars.next()? != '{' {
+					return None;
+				}
+				let hex: String = chars.by_ref().take_while(|&c| c != '}').collect();
+				name.push(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)?);
+			}
+			_ => return None,
+		}
+	}
+	Some(name)
+}
@@ -1,6 +1,9 @@
-use std::str::FromStr;
+use std::{path::Path, str::FromStr};
 
-use quickdash::Algorithm;
+use quickdash::{
+	Algorithm,
+	operations::{sums_algorithm, sums_name},
+};
 
 #[test]
 fn from_str() {
@@ -27,3 +30,13 @@ fn from_str() {
 		assert_eq!(Algorithm::from_str(a.0).unwrap(), a.1);
 	}
 }
+
+#[test]
+fn sums_names() {
+	assert_eq!(sums_name(Algorithm::SHA2256), "SHA256SUMS");
+	assert_eq!(sums_name(Algorithm::SHA3256), "SHA3256SUMS");
+	assert_eq!(sums_algorithm(Path::new("downloads/SHA512SUMS.asc")), Some(Algorithm::SHA2512));
+	assert_eq!(sums_algorithm(Path::new("SHA3256SUMS")), Some(Algorithm::SHA3256));
+	assert_eq!(sums_algorithm(Path::new("SHA256SUMS.txt.asc")), Some(Algorithm::SHA2256));
+	assert_eq!(sums_algorithm(Path::new("checksums.txt")), None);
+}
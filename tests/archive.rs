@@ -0,0 +1,60 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{ArchiveKind, HashingReport, WalkOptions, create_hashes},
+};
+
+/// A zip of `dir/`, `dir/hello.txt` deflated and `stored`, as Python's
+/// zipfile writes it.
+static ZIP: &str = "504b0304140000000800e424505d000000000200000000000000040000006469722f0300504b0304140000000800e424505d83892fc612000000e00100000d0000006469722f68656c6c6f2e747874cb48cdc9c957c84027b9304446c587a43800504b0304140000000000000021000bf9435606000000060000000600000073746f72656473746f726564504b01021403140000000800e424505d000000000200000000000000040000000000000000001000fd41000000006469722f504b01021403140000000800e424505d83892fc612000000e00100000d00000000000000000000008001240000006469722f68656c6c6f2e747874504b01021403140000000000000021000bf94356060000000600000006000000000000000000000080016100000073746f726564504b05060000000003000300a10000008b0000000000";
+
+/// A tar member header of a regular file.
+fn tar_header(name: &str, size: usize) -> [u8; 512] {
+	let mut header = [0u8; 512];
+	header[..name.len()].copy_from_slice(name.as_bytes());
+	header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+	header[156] = b'0';
+	header[257..263].copy_from_slice(b"ustar\0");
+	header[148..156].fill(b' ');
+	let sum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+	header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+	header
+}
+
+#[test]
+fn members_of_archives() {
+	let dir = temp_dir().join("quickdash-archive");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	let zip: Vec<u8> = (0..ZIP.len()).step_by(2).map(|i| u8::from_str_radix(&ZIP[i..i + 2], 16).unwrap()).collect();
+	write(dir.join("a.zip"), zip).unwrap();
+	let mut tar = tar_header("./b/c.txt", 3).to_vec();
+	tar.extend_from_slice(b"abc");
+	tar.resize(1024 + 1024, 0);
+	write(dir.join("b.tar"), tar).unwrap();
+	write(dir.join("damaged.zip"), "not a zip").unwrap();
+
+	let options = WalkOptions { look_inside: vec![ArchiveKind::Zip, ArchiveKind::Tar], ..Default::default() };
+	let mut report = HashingReport::default();
+	let hashes = create_hashes(&dir, Algorithm::SHA1, &options, &mut report);
+	let members: Vec<(&str, &str)> = hashes
+		.iter()
+		.filter_map(|(name, hash)| Some((name.to_str()?, hash.as_str())))
+		.filter(|(name, _)| name.contains('!'))
+		.collect();
+	assert_eq!(members, [
+		("a.zip!dir/hello.txt", "F8832D56ACDB3BD733C3C994982A03F597F74E03"),
+		("a.zip!stored", "AB514B9A49FBA12B7F30C6780F4398FE0100A38D"),
+		("b.tar!b/c.txt", "A9993E364706816ABA3E25717850C26C9CD0D89D"),
+	]);
+	assert!(hashes.contains_key(&PathBuf::from("a.zip")));
+	assert_eq!(report.warnings.len(), 1);
+	assert!(report.warnings[0].contains("damaged.zip"));
+
+	remove_dir_all(&dir).unwrap();
+}
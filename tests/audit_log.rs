@@ -0,0 +1,41 @@
+use std::{
+	env::temp_dir,
+	fs::{read_to_string, remove_file, write},
+	path::{Path, PathBuf},
+	process,
+};
+
+use quickdash::{
+	Error,
+	operations::{CompareFileResult, CompareResult, append_audit_record, verify_audit_log},
+};
+
+#[test]
+fn records_chain() {
+	let log = temp_dir().join(format!("quickdash-audit-{}.jsonl", process::id()));
+	let outcome = Ok((
+		vec![CompareResult::FileAdded(PathBuf::from("new"))],
+		vec![
+			CompareFileResult::FileMatches(PathBuf::from("same")),
+			CompareFileResult::FileDiffers {
+				file: PathBuf::from("rotten"),
+				was_hash: "AA".to_string(),
+				new_hash: "BB".to_string(),
+			},
+		],
+	));
+	append_audit_record(&log, "verify", Path::new("missing.hash"), Ok(&outcome)).unwrap();
+	let failure = Error::HashesFileParsingFailure("unreadable".to_string());
+	append_audit_record(&log, "check", Path::new("missing.hash"), Err(&failure)).unwrap();
+	let chained = verify_audit_log(&log).unwrap();
+	let text = read_to_string(&log).unwrap();
+	write(&log, text.replacen("\"failed\":1", "\"failed\":0", 1)).unwrap();
+	let tampered = verify_audit_log(&log).unwrap();
+	let _ = remove_file(&log);
+
+	assert_eq!(chained, Ok(2));
+	assert_eq!(tampered, Err(1));
+	let first = text.lines().next().unwrap();
+	assert!(first.contains(r#""matched":1,"assumed_ok":0,"added":1,"removed":0,"ignored":0,"failed":1,"failures":["rotten"],"error":null"#));
+	assert!(text.lines().nth(1).unwrap().contains(r#""error":"unreadable""#));
+}
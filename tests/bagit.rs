@@ -0,0 +1,37 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, read_to_string, remove_dir_all, write},
+	path::Path,
+	process,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{CompareError, CompareFileResult, HashingReport, WalkOptions, create_bag, validate_bag},
+};
+
+#[test]
+fn bag_round_trip() {
+	let bag = temp_dir().join(format!("quickdash-bag-{}", process::id()));
+	create_dir_all(bag.join("data")).unwrap();
+	write(bag.join("100%.txt"), "percent").unwrap();
+	write(bag.join("data/nested.txt"), "nested").unwrap();
+
+	let options = WalkOptions::default();
+	create_bag(&bag, Algorithm::SHA2256, &[], &options, &mut HashingReport::default()).unwrap();
+	let manifest = read_to_string(bag.join("manifest-sha256.txt")).unwrap();
+	let bag_info = read_to_string(bag.join("bag-info.txt")).unwrap();
+	let valid = validate_bag(&bag, false, &options, &mut HashingReport::default()).unwrap();
+	write(bag.join("data/100%.txt"), "changed").unwrap();
+	let changed = validate_bag(&bag, false, &options, &mut HashingReport::default()).unwrap();
+	write(bag.join("data/100%.txt"), "changed again").unwrap();
+	let resized = validate_bag(&bag, true, &options, &mut HashingReport::default()).unwrap();
+	let _ = remove_dir_all(&bag);
+
+	assert!(manifest.contains("  data/100%25.txt\n") && manifest.contains("  data/data/nested.txt\n"));
+	assert!(bag_info.contains("Payload-Oxum: 13.2\n"));
+	assert!(valid.unwrap().1.iter().all(|result| matches!(result, CompareFileResult::FileMatches(_))));
+	assert!(changed.unwrap().1.iter().any(|result| matches!(result,
+		CompareFileResult::FileDiffers { file, .. } if file == Path::new("data/100%.txt"))));
+	assert_eq!(resized, Err(CompareError::PayloadOxumDiffers { recorded: (13, 2), found: (19, 2) }));
+}
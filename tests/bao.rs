@@ -0,0 +1,53 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{create_dir_all, metadata, remove_dir_all, write},
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm, hash_file,
+	operations::{RangeCheck, outboard_path, verify_range, write_outboards},
+};
+
+#[test]
+fn outboards_verify_ranges_of_the_files_they_were_written_for() {
+	let dir = temp_dir().join("quickdash-bao");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("files")).unwrap();
+	// Seven groups of 16 KiB, the last one partial
+	let mut contents: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+	let (file, small) = (dir.join("files/large.bin"), dir.join("files/small.bin"));
+	write(&file, &contents).unwrap();
+	write(&small, "small").unwrap();
+	let hashes: BTreeMap<PathBuf, String> = ["large.bin", "small.bin"]
+		.iter()
+		.map(|name| (PathBuf::from(name), hash_file(Algorithm::BLAKE3, &dir.join("files").join(name))))
+		.collect();
+	let hash = &hashes[&PathBuf::from("large.bin")];
+
+	let outboards = dir.join("files.hash.bao");
+	let mut warnings = Vec::new();
+	write_outboards(&dir.join("files"), &hashes, &BTreeMap::new(), &outboards, &mut warnings);
+	assert!(warnings.is_empty());
+	let outboard = outboard_path(&outboards, &PathBuf::from("large.bin"));
+	// The length, then the six parent nodes of seven groups
+	assert_eq!(metadata(&outboard).unwrap().len(), 8 + 6 * 64);
+	assert!(!outboard_path(&outboards, &PathBuf::from("small.bin")).exists());
+	assert_eq!(verify_range(&file, &outboard, hash, 0, 100_000).unwrap(), RangeCheck::Matches);
+	assert_eq!(verify_range(&file, &outboard, hash, 40_000, 41_000).unwrap(), RangeCheck::Matches);
+	assert_eq!(verify_range(&small, &outboard, &hashes[&PathBuf::from("small.bin")], 0, 5).unwrap(), RangeCheck::Matches);
+
+	// Only ranges covering the rotten byte fail
+	contents[50_000] ^= 1;
+	write(&file, &contents).unwrap();
+	assert_eq!(verify_range(&file, &outboard, hash, 0, 16_384).unwrap(), RangeCheck::Matches);
+	let check = verify_range(&file, &outboard, hash, 49_000, 51_000).unwrap();
+	assert!(matches!(check, RangeCheck::Differs { start, end } if start <= 50_000 && 50_000 < end));
+
+	contents.push(0);
+	write(&file, &contents).unwrap();
+	assert_eq!(verify_range(&file, &outboard, hash, 0, 16_384).unwrap(), RangeCheck::Resized { was: 100_000, is: 100_001 });
+
+	remove_dir_all(&dir).unwrap();
+}
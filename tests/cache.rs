@@ -0,0 +1,48 @@
+#![cfg(unix)]
+
+use std::{
+	env::temp_dir,
+	fs::{File, create_dir_all, read_dir, read_to_string, remove_dir_all, write},
+	path::Path,
+	process,
+	time::{Duration, UNIX_EPOCH},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, WalkOptions, create_hashes},
+};
+
+/// Write `contents` to `path`, modified long enough ago to be cached.
+fn write_settled(path: &Path, contents: &str) {
+	write(path, contents).unwrap();
+	let settled = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+	File::options().write(true).open(path).unwrap().set_modified(settled).unwrap();
+}
+
+#[test]
+fn changed_files_leave_the_cache() {
+	let dir = temp_dir().join(format!("quickdash-cache-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("tree")).unwrap();
+	let cache = dir.join("hashes.cache");
+	write_settled(&dir.join("tree/kept.txt"), "kept");
+	write_settled(&dir.join("tree/changed.txt"), "changed");
+	let options = WalkOptions { cache: Some(cache.clone()), ..Default::default() };
+
+	create_hashes(&dir.join("tree"), Algorithm::SHA2256, &options, &mut HashingReport::default());
+	assert_eq!(read_to_string(&cache).unwrap().lines().count(), 3);
+
+	// Changed too recently to be cached again
+	write(dir.join("tree/changed.txt"), "changed since").unwrap();
+	let mut report = HashingReport::default();
+	create_hashes(&dir.join("tree"), Algorithm::SHA2256, &options, &mut report);
+	assert_eq!(report.bytes_hashed, 13);
+	assert_eq!(read_to_string(&cache).unwrap().lines().count(), 2);
+
+	// No temporary files are left behind
+	let names: Vec<_> = read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+	assert!(names.iter().all(|name| !name.to_string_lossy().ends_with(".tmp")));
+
+	remove_dir_all(&dir).unwrap();
+}
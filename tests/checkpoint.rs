@@ -0,0 +1,96 @@
+use std::{
+	env::temp_dir,
+	fs::{File, create_dir_all, read_to_string, remove_dir_all, remove_file, write},
+	path::{Path, PathBuf},
+	time::{Duration, UNIX_EPOCH},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{CompareFileResult, HashingReport, WalkOptions, checkpoint_path, compare_hashes, create_hashes},
+};
+
+/// Write `contents` to `path`, modified long enough ago to be checkpointed,
+/// always at the same time.
+fn write_settled(path: &Path, contents: &str) {
+	write(path, contents).unwrap();
+	let settled = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+	File::options().write(true).open(path).unwrap().set_modified(settled).unwrap();
+}
+
+#[test]
+fn resumed_runs_reuse_checkpointed_hashes() {
+	let dir = temp_dir().join("quickdash-checkpoint");
+	let checkpoint = checkpoint_path(&temp_dir().join("quickdash-checkpoint.hash"));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	write_settled(&dir.join("kept.txt"), "kept");
+	write_settled(&dir.join("changed.txt"), "changed");
+	assert_eq!(checkpoint_path(Path::new("photos.hash")), Path::new("photos.hash.checkpoint"));
+
+	let mut options = WalkOptions { checkpoint: Some(checkpoint.clone()), ..Default::default() };
+	let fresh = create_hashes(&dir, Algorithm::SHA2256, &options, &mut HashingReport::default());
+	let written = read_to_string(&checkpoint).unwrap();
+	assert!(written.starts_with("# quickdash checkpoint 1 SHA2256 binary 0\n"));
+	assert_eq!(written.lines().count(), 3);
+
+	// Told apart from a hash that was read, and followed by a line the interruption cut short
+	let kept = &fresh[&PathBuf::from("kept.txt")];
+	write(&checkpoint, written.replace(kept.as_str(), "checkpointed") + "4\t1.0\tabc").unwrap();
+	write_settled(&dir.join("changed.txt"), "changed since");
+	options.resume = true;
+	let resumed = create_hashes(&dir, Algorithm::SHA2256, &options, &mut HashingReport::default());
+	let unchecked = create_hashes(&dir, Algorithm::SHA2256, &WalkOptions::default(), &mut HashingReport::default());
+	assert_eq!(resumed[&PathBuf::from("kept.txt")], "checkpointed");
+	assert_eq!(resumed[&PathBuf::from("changed.txt")], unchecked[&PathBuf::from("changed.txt")]);
+
+	// What was appended after the cut line is read back
+	write_settled(&dir.join("changed.txt"), "changed again");
+	let resumed_again = create_hashes(&dir, Algorithm::SHA2256, &options, &mut HashingReport::default());
+	assert_eq!(resumed_again[&PathBuf::from("kept.txt")], "checkpointed");
+
+	// Checkpoints of other algorithms are started over
+	let other = create_hashes(&dir, Algorithm::SHA1, &options, &mut HashingReport::default());
+	assert_ne!(other[&PathBuf::from("kept.txt")], "checkpointed");
+	assert!(read_to_string(&checkpoint).unwrap().starts_with("# quickdash checkpoint 1 SHA1 binary 0\n"));
+
+	remove_dir_all(&dir).unwrap();
+	remove_file(&checkpoint).unwrap();
+}
+
+#[test]
+fn resumed_verification_reports_on_every_file() {
+	let dir = temp_dir().join("quickdash-checkpoint-verify");
+	let checkpoint = checkpoint_path(&temp_dir().join("quickdash-checkpoint-verify.hash"));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	for name in ["a.txt", "b.txt", "c.txt"] {
+		write_settled(&dir.join(name), name);
+	}
+	let manifest = create_hashes(&dir, Algorithm::SHA2256, &WalkOptions::default(), &mut HashingReport::default());
+	// Rotted without its size or modification time changing
+	write_settled(&dir.join("b.txt"), "B.txt");
+
+	// Interrupted once a.txt was read, the checkpoint holding only that
+	let options = WalkOptions { checkpoint: Some(checkpoint.clone()), resume: true, ..Default::default() };
+	create_hashes(&dir, Algorithm::SHA2256, &options, &mut HashingReport::default());
+	let read_before: String = read_to_string(&checkpoint)
+		.unwrap()
+		.lines()
+		.filter(|line| !line.ends_with("b.txt") && !line.ends_with("c.txt"))
+		.map(|line| format!("{}\n", line))
+		.collect();
+	write(&checkpoint, read_before).unwrap();
+
+	let mut report = HashingReport::default();
+	let resumed = create_hashes(&dir, Algorithm::SHA2256, &options, &mut report);
+	assert_eq!(report.bytes_hashed, 10);
+	let (_, file_compare_results) = compare_hashes(resumed, manifest).unwrap();
+	assert_eq!(file_compare_results.len(), 3);
+	assert!(file_compare_results.contains(&CompareFileResult::FileMatches(PathBuf::from("a.txt"))));
+	assert!(file_compare_results.iter().any(|res| matches!(res, CompareFileResult::FileDiffers { file, .. } if file == Path::new("b.txt"))));
+	assert!(file_compare_results.contains(&CompareFileResult::FileMatches(PathBuf::from("c.txt"))));
+
+	remove_dir_all(&dir).unwrap();
+	remove_file(&checkpoint).unwrap();
+}
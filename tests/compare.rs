@@ -0,0 +1,204 @@
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	env::temp_dir,
+	fs::{create_dir_all, read_to_string, remove_dir_all, write},
+	path::{Path, PathBuf},
+	process,
+	time::{Duration, UNIX_EPOCH},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{
+		CompareFileResult, CompareResult, Difference, Fix, HashingReport, LastVerified, Mismatch, ReadOptions, RecordedMetadata, ScrubBudget,
+		TreeComparison, WalkOptions, WriteOptions, compare_hashes, compare_sorted_hashes, compare_trees, create_hashes, first_difference, fix_hashes, quarantine, quarantine_log,
+		read_hashes, take_placeholders, write_hashes,
+	},
+};
+
+fn hashes(entries: &[(&str, &str)]) -> BTreeMap<PathBuf, String> {
+	entries
+		.iter()
+		.map(|(file, hash)| (PathBuf::from(file), hash.to_string()))
+		.collect()
+}
+
+#[test]
+fn placeholders_are_ignored() {
+	let mut loaded = hashes(&[("huge.img", "----"), ("small.txt", "00")]);
+	assert_eq!(take_placeholders(&mut loaded), [CompareResult::FileIgnored(PathBuf::from("huge.img"))]);
+	assert_eq!(loaded, hashes(&[("small.txt", "00")]));
+}
+
+#[test]
+fn sorted_matches_unsorted() {
+	let current = hashes(&[("a", "00"), ("b/c", "11"), ("d", "22"), ("f", "33")]);
+	let loaded = hashes(&[("a", "00"), ("b/c", "FF"), ("e", "44"), ("f", "33")]);
+
+	let (mut results, mut file_results) = compare_hashes(current.clone(), loaded.clone()).unwrap();
+	let (mut sorted_results, mut sorted_file_results) =
+		compare_sorted_hashes(&current, loaded.into_iter().map(Ok))
+			.unwrap()
+			.unwrap()
+			.unwrap();
+
+	results.sort();
+	file_results.sort();
+	sorted_results.sort();
+	sorted_file_results.sort();
+	assert_eq!(results, sorted_results);
+	assert_eq!(file_results, sorted_file_results);
+}
+
+#[test]
+fn unsorted_is_rejected() {
+	let current = hashes(&[("a", "00"), ("b", "11")]);
+	let loaded = vec![
+		Ok((PathBuf::from("b"), "11".to_string())),
+		Ok((PathBuf::from("a"), "00".to_string())),
+	];
+
+	assert_eq!(compare_sorted_hashes(&current, loaded).unwrap(), None);
+}
+
+#[test]
+fn last_verified_times() {
+	let dir = temp_dir().join(format!("quickdash-verified-{}", process::id()));
+	create_dir_all(&dir).unwrap();
+	let file = dir.join("dir.hash");
+	let (earlier, later) = (UNIX_EPOCH + Duration::from_secs(1000), UNIX_EPOCH + Duration::from_secs(2000));
+
+	let mut verified = LastVerified::load(&file).unwrap();
+	verified.record(&[CompareResult::FileRemoved(PathBuf::from("gone"))], &[
+		CompareFileResult::FileMatches(PathBuf::from("a\tb")),
+		CompareFileResult::FileAssumedOk(PathBuf::from("c")),
+	], earlier);
+	verified.record(&[], &[
+		CompareFileResult::FileAssumedOk(PathBuf::from("a\tb")),
+		CompareFileResult::FileMatches(PathBuf::from("c")),
+	], later);
+	verified.save(&file).unwrap();
+	let loaded = LastVerified::load(&file);
+	let _ = remove_dir_all(&dir);
+
+	let loaded = loaded.unwrap();
+	assert_eq!(loaded, verified);
+	assert_eq!(loaded.get(Path::new("a\tb")), Some(earlier));
+	assert_eq!(loaded.get(Path::new("gone")), None);
+	assert!(loaded.never_verified().is_empty());
+	assert_eq!(loaded.verified_before(later), [(Path::new("a\tb"), earlier)]);
+}
+
+#[test]
+fn scrub_reads_least_recently_verified() {
+	let dir = temp_dir().join(format!("quickdash-scrub-{}", process::id()));
+	let files = dir.join("files");
+	create_dir_all(&files).unwrap();
+	for name in ["a", "b", "c", "d"] {
+		write(files.join(name), name.repeat(10)).unwrap();
+	}
+	let file = dir.join("files.hash");
+	let mut options = WalkOptions { record_metadata: true, ..Default::default() };
+	let mut report = HashingReport::default();
+	let hashes = create_hashes(&files, Algorithm::SHA2256, &options, &mut report);
+	write_hashes(&file, hashes, &WriteOptions { metadata: report.metadata, ..Default::default() });
+
+	let mut verified = LastVerified::default();
+	let (a, b) = (PathBuf::from("a"), PathBuf::from("b"));
+	verified.record(&[], &[CompareFileResult::FileMatches(b.clone())], UNIX_EPOCH + Duration::from_secs(1000));
+	verified.record(
+		&[],
+		&[CompareFileResult::FileMatches(a.clone()), CompareFileResult::FileAssumedOk(b.clone())],
+		UNIX_EPOCH + Duration::from_secs(2000),
+	);
+	let recorded = RecordedMetadata::load(&file, &ReadOptions::default());
+	options.recorded = Some(recorded.unwrap().scrub(&verified, ScrubBudget::Percent(60.0)));
+	let mut report = HashingReport::default();
+	create_hashes(&files, Algorithm::SHA2256, &options, &mut report);
+	let _ = remove_dir_all(&dir);
+
+	// c and d never verified, then b verified before a
+	assert_eq!(report.assumed, BTreeSet::from([a]));
+}
+
+#[test]
+fn quarantine_differing_files() {
+	let dir = temp_dir().join(format!("quickdash-quarantine-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("data/sub")).unwrap();
+	write(dir.join("data/same"), "same").unwrap();
+	write(dir.join("data/sub/rotten"), "rotten").unwrap();
+	let results = [
+		CompareFileResult::FileMatches(PathBuf::from("same")),
+		CompareFileResult::FileDiffers { file: PathBuf::from("sub/rotten"), was_hash: "AA".to_string(), new_hash: "BB".to_string() },
+	];
+	let quarantine_dir = dir.join("quarantine");
+	let lines = quarantine(&quarantine_dir, &results, false, |file| Some(dir.join("data").join(file)));
+
+	assert_eq!(lines.len(), 1);
+	assert!(dir.join("data/same").exists());
+	assert!(!dir.join("data/sub/rotten").exists());
+	assert_eq!(read_to_string(quarantine_dir.join("sub/rotten")).unwrap(), "rotten");
+	let log = read_to_string(quarantine_log(&quarantine_dir)).unwrap();
+	assert!(log.ends_with("\tmoved\tAA\tBB\t\"sub/rotten\"\n"));
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fix_accepted_mismatches() {
+	let dir = temp_dir().join("quickdash-fix");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	let file = dir.join("fix.hash");
+	let options = WriteOptions { algorithm: Algorithm::SHA1, ..Default::default() };
+	write_hashes(&file, hashes(&[("changed", "AA"), ("kept", "BB"), ("removed", "CC")]), &options);
+
+	let compare_results = [CompareResult::FileRemoved(PathBuf::from("removed")), CompareResult::FileAdded(PathBuf::from("added"))];
+	let file_compare_results = [
+		CompareFileResult::FileDiffers { file: PathBuf::from("changed"), was_hash: "AA".to_string(), new_hash: "DD".to_string() },
+		CompareFileResult::FileMatches(PathBuf::from("kept")),
+	];
+	let current = hashes(&[("added", "EE"), ("changed", "DD"), ("kept", "BB")]);
+	let mismatches = Mismatch::of(&compare_results, &file_compare_results, |file| current.get(file).cloned());
+	assert_eq!(mismatches.len(), 3);
+	let fixes: BTreeMap<PathBuf, Fix> = mismatches.iter().map(|mismatch| (mismatch.file().to_path_buf(), mismatch.accept())).collect();
+
+	let fixed = fix_hashes(&file, &fixes, &BTreeMap::new(), &ReadOptions::default(), WriteOptions::default()).unwrap();
+	assert_eq!(fixed, 0);
+	assert_eq!(read_hashes(&file, &ReadOptions::default()).unwrap(), current);
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compare_two_trees() {
+	let dir = temp_dir().join("quickdash-trees");
+	let _ = remove_dir_all(&dir);
+	for (file, contents) in [("a/same", "1"), ("b/same", "1"), ("a/sub/content", "xy"), ("b/sub/content", "yx"), ("a/size", "1"), ("b/size", "12"), ("a/old", ""), ("b/new", "")] {
+		create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+		write(dir.join(file), contents).unwrap();
+	}
+	let comparison = compare_trees(&dir.join("a"), &dir.join("b"), Algorithm::SHA1, &WalkOptions::default(), &mut HashingReport::default());
+	assert_eq!(comparison, TreeComparison {
+		only_in_a: vec![PathBuf::from("old")],
+		only_in_b: vec![PathBuf::from("new")],
+		differing: vec![PathBuf::from("size"), PathBuf::from("sub/content")],
+		unreadable: Vec::new(),
+		identical: 1,
+	});
+	assert!(!comparison.is_identical());
+
+	let (a, b) = (dir.join("a/long"), dir.join("b/long"));
+	let mut contents = vec![7; 100_000];
+	write(&a, &contents).unwrap();
+	contents[70_000] = 8;
+	write(&b, &contents).unwrap();
+	let context_b = [[7; 8], [8, 7, 7, 7, 7, 7, 7, 7]].concat();
+	assert_eq!(first_difference(&a, &b).unwrap(), Some(Difference { offset: 70_000, start: 69_992, context_a: vec![7; 16], context_b }));
+	write(&b, &contents[..4]).unwrap();
+	assert_eq!(first_difference(&a, &b).unwrap(), Some(Difference { offset: 4, start: 0, context_a: vec![7; 12], context_b: vec![7; 4] }));
+	assert_eq!(first_difference(&a, &a).unwrap(), None);
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,82 @@
+#![cfg(unix)]
+
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, metadata, remove_dir_all, write},
+	io::{BufRead, BufReader, Write},
+	os::unix::fs::PermissionsExt,
+	thread,
+	time::Duration,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{JobOptions, Json, connect_daemon, serve},
+};
+
+#[test]
+fn json_round_trip() {
+	let text = r#"{"a":[1,2.5,-3,true,null],"b":"tab\tquote\" é 😀","c":{}}"#;
+	let json: Json = text.parse().unwrap();
+	assert_eq!(json.get("b").and_then(Json::as_str), Some("tab\tquote\" é 😀"));
+	assert_eq!(json.to_string().parse::<Json>().unwrap(), json);
+	assert!("{\"a\":}".parse::<Json>().is_err());
+}
+
+#[test]
+fn create_and_verify_jobs() {
+	let dir = temp_dir().join("quickdash-daemon");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("data")).unwrap();
+	write(dir.join("data/a"), "a").unwrap();
+	write(dir.join("data/b"), "b").unwrap();
+	let socket = dir.join("quickdash.sock");
+	let options = JobOptions { algorithm: Algorithm::SHA1, ..Default::default() };
+	let server = {
+		let socket = socket.clone();
+		thread::spawn(move || serve(&socket, options))
+	};
+	while !socket.exists() {
+		thread::sleep(Duration::from_millis(10));
+	}
+
+	assert_eq!(metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+	let stream = connect_daemon(&socket).unwrap();
+	let mut out = stream.try_clone().unwrap();
+	let mut lines = BufReader::new(stream).lines();
+	let mut call = |request: &str| {
+		writeln!(out, "{}", request).unwrap();
+		let mut notifications = 0;
+		loop {
+			let message: Json = lines.next().unwrap().unwrap().parse().unwrap();
+			match message.get("method") {
+				Some(_) => notifications += 1,
+				None => return (message, notifications),
+			}
+		}
+	};
+	let data = dir.join("data");
+	let file = dir.join("data.hash");
+	let params = format!(r#""path":{:?},"file":{:?}"#, data, file);
+
+	let (response, _) = call(&format!(r#"{{"jsonrpc":"2.0","id":1,"method":"create","params":{{{}}}}}"#, params));
+	assert_eq!(response.get("result").and_then(Json::as_u64), Some(1));
+	let (response, notifications) = call(r#"{"jsonrpc":"2.0","id":2,"method":"subscribe","params":{"job":1}}"#);
+	assert!(notifications >= 1);
+	assert_eq!(response.get("result").and_then(|result| result.get("files")).and_then(Json::as_u64), Some(2));
+
+	write(dir.join("data/b"), "changed").unwrap();
+	call(&format!(r#"{{"jsonrpc":"2.0","id":3,"method":"verify","params":{{{}}}}}"#, params));
+	let (response, _) = call(r#"{"jsonrpc":"2.0","id":4,"method":"subscribe","params":{"job":2}}"#);
+	let result = response.get("result").unwrap();
+	assert_eq!(result.get("matched").and_then(Json::as_u64), Some(1));
+	assert_eq!(result.get("differs"), Some(&Json::Array(vec!["b".into()])));
+
+	let (response, _) = call(r#"{"jsonrpc":"2.0","id":5,"method":"status","params":{"job":3}}"#);
+	assert_eq!(response.get("error").and_then(|error| error.get("code")), Some(&Json::Number(-32001.0)));
+	call(r#"{"jsonrpc":"2.0","id":6,"method":"shutdown"}"#);
+	server.join().unwrap().unwrap();
+	assert!(!socket.exists());
+
+	remove_dir_all(&dir).unwrap();
+}
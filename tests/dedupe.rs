@@ -0,0 +1,74 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, read_to_string, remove_dir_all, write},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{DedupeAction, DuplicateSet, HashingReport, Keep, WalkOptions, consolidate, find_duplicates, log_consolidation, undo_consolidations},
+};
+
+#[test]
+fn dedupe_and_undo() {
+	let dir = temp_dir().join("quickdash-dedupe");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("sub")).unwrap();
+	for (file, contents) in [("a", "same"), ("sub/b", "same"), ("c", "else"), ("d", ""), ("e", "")] {
+		write(dir.join(file), contents).unwrap();
+	}
+	let sets = find_duplicates(&dir, Algorithm::SHA1, &WalkOptions::default(), Keep::First, &mut HashingReport::default());
+	assert_eq!(sets, vec![DuplicateSet { len: 4, kept: dir.join("a"), duplicates: vec![dir.join("sub/b")], shared: vec![] }]);
+
+	let log = dir.join("dedupe.log");
+	for action in [DedupeAction::Delete, DedupeAction::Hardlink] {
+		consolidate(&dir.join("a"), &dir.join("sub/b"), action).unwrap();
+		log_consolidation(&log, action, &dir.join("a"), &dir.join("sub/b")).unwrap();
+		assert_eq!(dir.join("sub/b").exists(), action == DedupeAction::Hardlink);
+		undo_consolidations(&log).unwrap();
+		assert_eq!(read_to_string(dir.join("sub/b")).unwrap(), "same");
+		assert!(!log.exists());
+	}
+	// Files that changed since are left alone
+	assert!(consolidate(&dir.join("a"), &dir.join("c"), DedupeAction::Delete).is_err());
+	assert!(dir.join("c").exists());
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn replaced_duplicates_keep_their_mode_and_time() {
+	use std::{
+		fs::{File, Permissions, metadata, set_permissions},
+		os::unix::fs::PermissionsExt,
+		time::{Duration, UNIX_EPOCH},
+	};
+
+	let dir = temp_dir().join(format!("quickdash-dedupe-mode-{}", std::process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	let (kept, duplicate) = (dir.join("kept"), dir.join("duplicate"));
+	write(&kept, "same").unwrap();
+	write(&duplicate, "same").unwrap();
+	set_permissions(&kept, Permissions::from_mode(0o644)).unwrap();
+	set_permissions(&duplicate, Permissions::from_mode(0o600)).unwrap();
+	let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+	File::options().write(true).open(&duplicate).unwrap().set_modified(modified).unwrap();
+	let mode = |path| metadata(path).unwrap().permissions().mode() & 0o7777;
+
+	// Where the filesystem can reflink
+	if consolidate(&kept, &duplicate, DedupeAction::Reflink).is_ok() {
+		assert_eq!(mode(&duplicate), 0o600);
+		assert_eq!(metadata(&duplicate).unwrap().modified().unwrap(), modified);
+	}
+
+	// Undoing copies the kept file, but keeps the mode and time of the one it replaces
+	let log = dir.join("dedupe.log");
+	log_consolidation(&log, DedupeAction::Reflink, &kept, &duplicate).unwrap();
+	undo_consolidations(&log).unwrap();
+	assert_eq!(mode(&duplicate), 0o600);
+	assert_eq!(metadata(&duplicate).unwrap().modified().unwrap(), modified);
+	assert_eq!(mode(&kept), 0o644);
+
+	remove_dir_all(&dir).unwrap();
+}
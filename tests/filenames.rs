@@ -0,0 +1,55 @@
+use std::{collections::BTreeMap, env::temp_dir, fs::remove_file, path::PathBuf, process};
+
+use quickdash::operations::{PathStyle, ReadOptions, WriteOptions, read_hashes, write_hashes};
+
+#[test]
+fn hostile_filenames_round_trip() {
+	let hashes: BTreeMap<PathBuf, String> = [
+		"plain",
+		"with space",
+		" leading space",
+		"trailing space ",
+		"tab\there",
+		"new\nline",
+		"carriage\rreturn",
+		"back\\slash",
+		"dir/nested\nname",
+		"  AABB  looks like a line",
+		"\u{a0}no-break space\u{a0}",
+		"\x0bvertical tab",
+		"form feed\x0c",
+	]
+	.iter()
+	.enumerate()
+	.map(|(i, name)| (PathBuf::from(name), format!("{:08X}", i)))
+	.collect();
+
+	let file = temp_dir().join(format!("quickdash-filenames-{}.hash", process::id()));
+	let options = WriteOptions { path_style: PathStyle::Unix, ..Default::default() };
+	write_hashes(&file, hashes.clone(), &options);
+	let loaded = read_hashes(&file, &ReadOptions::default());
+	let _ = remove_file(&file);
+
+	assert_eq!(loaded.unwrap(), hashes);
+}
+
+#[cfg(unix)]
+#[test]
+fn mixed_hash_files_name_each_file_like_its_entry() {
+	use std::path::Path;
+
+	use quickdash::operations::{WalkOptions, relative_entries};
+
+	let hashes: BTreeMap<PathBuf, String> =
+		[("/srv/absolute", "0000AAAA"), ("relative", "1111BBBB")].iter().map(|(name, hash)| (PathBuf::from(name), hash.to_string())).collect();
+	let file = temp_dir().join(format!("quickdash-mixed-{}.hash", process::id()));
+	let options = WriteOptions { path_style: PathStyle::Unix, ..Default::default() };
+	write_hashes(&file, hashes, &options);
+	let relative = relative_entries(&file, &ReadOptions::default());
+	let _ = remove_file(&file);
+
+	let walk_options = WalkOptions { absolute_paths: true, relative_names: relative.unwrap().unwrap(), ..Default::default() };
+	let root = Path::new("/srv");
+	assert_eq!(walk_options.name(root, &root.join("relative")), Path::new("relative"));
+	assert_eq!(walk_options.name(root, &root.join("absolute")), Path::new("/srv/absolute"));
+}
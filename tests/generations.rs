@@ -0,0 +1,62 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all},
+	path::{Path, PathBuf},
+	process,
+};
+
+use quickdash::operations::{
+	GenerationChange, ReadOptions, WriteOptions, diff_generations, entry_history, find_generation, list_generations,
+	prune_generations, store_generation, write_hashes,
+};
+
+fn hashes(entries: &[(&str, &str)]) -> BTreeMap<PathBuf, String> {
+	entries
+		.iter()
+		.map(|(file, hash)| (PathBuf::from(file), hash.to_string()))
+		.collect()
+}
+
+#[test]
+fn generations_history() {
+	let dir = temp_dir().join(format!("quickdash-generations-{}", process::id()));
+	create_dir_all(&dir).unwrap();
+	let file = dir.join("dir.hash");
+	let options = ReadOptions::default();
+
+	write_hashes(&file, hashes(&[("a", "00"), ("b", "11")]), &WriteOptions::default());
+	let first = store_generation(&file).unwrap();
+	write_hashes(&file, hashes(&[("a", "00"), ("b", "22"), ("c", "33")]), &WriteOptions::default());
+	let second = store_generation(&file).unwrap();
+	write_hashes(&file, hashes(&[("a", "00"), ("c", "33")]), &WriteOptions::default());
+	let third = store_generation(&file).unwrap();
+	let generations = list_generations(&file).unwrap();
+	let changes = diff_generations(&first, &second, &options).unwrap();
+	let history: Vec<(String, Option<String>)> = entry_history(&generations, Path::new("b"), &options)
+		.unwrap()
+		.into_iter()
+		.map(|(generation, hash)| (generation.id.clone(), hash))
+		.collect();
+	let removed = prune_generations(&file, 1).unwrap();
+	let left = list_generations(&file).unwrap();
+	let _ = remove_dir_all(&dir);
+
+	assert_eq!(generations, [first.clone(), second.clone(), third.clone()]);
+	assert_eq!(find_generation(&generations, "2"), Some(&second));
+	assert_eq!(find_generation(&generations, &third.id), Some(&third));
+	assert_eq!(find_generation(&generations, "0"), None);
+	assert_eq!(
+		changes.into_iter().collect::<Vec<_>>(),
+		[
+			(PathBuf::from("b"), GenerationChange::Changed { was: "11".to_string(), new: "22".to_string() }),
+			(PathBuf::from("c"), GenerationChange::Added("33".to_string())),
+		]
+	);
+	assert_eq!(
+		history,
+		[(first.id.clone(), Some("11".to_string())), (second.id.clone(), Some("22".to_string())), (third.id.clone(), None)]
+	);
+	assert_eq!(removed, [first, second]);
+	assert_eq!(left, [third]);
+}
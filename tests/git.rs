@@ -0,0 +1,35 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	path::PathBuf,
+	process::Command,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, WalkOptions, create_hashes},
+};
+
+#[test]
+fn git_tracked_files_only() {
+	let dir = temp_dir().join("quickdash-git-tracked");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("src")).unwrap();
+	create_dir_all(dir.join("target")).unwrap();
+	for file in ["README", "src/lib.rs", "target/junk", "untracked"] {
+		write(dir.join(file), file).unwrap();
+	}
+	let git = |args: &[&str]| assert!(Command::new("git").arg("-C").arg(&dir).args(args).status().unwrap().success());
+	git(&["init", "-q"]);
+	git(&["add", "README", "src/lib.rs"]);
+
+	let mut options = WalkOptions::default();
+	options.track_git(&dir).unwrap();
+	let hashes = create_hashes(&dir, Algorithm::SHA1, &options, &mut HashingReport::default());
+	assert_eq!(hashes.into_keys().collect::<Vec<_>>(), [PathBuf::from("README"), PathBuf::from("src/lib.rs")]);
+
+	// Outside a checkout
+	assert!(WalkOptions::default().track_git(&temp_dir().join("quickdash-not-a-checkout-at-all")).is_err());
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,119 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	io::{Read, Write},
+	net::{TcpListener, TcpStream},
+	thread,
+	time::Duration,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, JobOptions, Json, PublishTarget, ServedRoot, WalkOptions, WriteOptions, create_hashes, fetch, is_url, publish, read_hashes, serve_http, write_hashes},
+};
+
+/// Status and body of the response to a request.
+fn request(port: u16, method: &str, target: &str, token: Option<&str>) -> (u16, String) {
+	let mut stream = loop {
+		match TcpStream::connect(("127.0.0.1", port)) {
+			Ok(stream) => break stream,
+			Err(_) => thread::sleep(Duration::from_millis(10)),
+		}
+	};
+	let authorization = token.map(|token| format!("Authorization: Bearer {}\r\n", token)).unwrap_or_default();
+	write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n", method, target, authorization).unwrap();
+	let mut response = String::new();
+	stream.read_to_string(&mut response).unwrap();
+	let status = response[9..12].parse().unwrap();
+	let body = response.split_once("\r\n\r\n").unwrap().1.to_owned();
+	(status, body)
+}
+
+#[test]
+fn verify_and_fetch_results() {
+	let dir = temp_dir().join("quickdash-http");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("data")).unwrap();
+	write(dir.join("data/a"), "a").unwrap();
+	let file = dir.join("data.hash");
+	let hashes = create_hashes(&dir.join("data"), Algorithm::SHA1, &WalkOptions::default(), &mut HashingReport::default());
+	write_hashes(&file, hashes, &WriteOptions { algorithm: Algorithm::SHA1, ..Default::default() });
+
+	let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+	let roots = vec![ServedRoot { name: "data".to_owned(), path: dir.join("data"), file: file.clone() }];
+	let options = JobOptions { algorithm: Algorithm::SHA1, ..Default::default() };
+	thread::spawn(move || serve_http(("127.0.0.1", port), roots, Some("secret".to_owned()), options));
+
+	assert_eq!(request(port, "GET", "/roots", None).0, 401);
+	assert_eq!(request(port, "GET", "/roots/data/results", Some("secret")).0, 404);
+	assert_eq!(request(port, "GET", "/roots/other/results", Some("secret")).0, 404);
+	assert_eq!(request(port, "POST", "/roots/data/verify", Some("secret")).0, 202);
+	let result = loop {
+		let (status, body) = request(port, "GET", "/roots/data/results", Some("secret"));
+		if status == 200 {
+			break body.parse::<Json>().unwrap();
+		}
+		thread::sleep(Duration::from_millis(20));
+	};
+	assert_eq!(result.get("result").and_then(|result| result.get("matched")).and_then(Json::as_u64), Some(1));
+	let (status, manifest) = request(port, "GET", "/roots/data/manifest", Some("secret"));
+	assert_eq!((status, manifest.ends_with("  a\n")), (200, true));
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fetch_hash_file() {
+	assert!(is_url("https://example.com/SHA256SUMS".as_ref()));
+	assert!(!is_url("SHA256SUMS".as_ref()));
+
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut request = [0; 1024];
+		let _ = stream.read(&mut request).unwrap();
+		let body = format!("{}  a\n", "86f7e437faa5a7fce15d1ddcb9eaeaea377667b8");
+		write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+	});
+
+	let fetched = fetch(&format!("http://127.0.0.1:{}/release/SHA1SUMS?x=1", port)).unwrap();
+	assert_eq!(fetched.path().file_name().unwrap(), "SHA1SUMS");
+	assert_eq!(read_hashes(fetched.path(), &Default::default()).unwrap().len(), 1);
+	let path = fetched.path().to_owned();
+	drop(fetched);
+	assert!(!path.exists());
+	assert!(fetch(&format!("http://127.0.0.1:{}/gone", port)).is_err());
+}
+
+#[test]
+fn publish_over_webdav() {
+	assert_eq!("davs://host/dav/".parse(), Ok(PublishTarget::WebDav("https://host/dav/".to_owned())));
+	assert!("ftp://host/".parse::<PublishTarget>().is_err());
+	let target: PublishTarget = "s3://bucket/manifests/".parse().unwrap();
+	assert_eq!(target.url("a.hash"), "s3://bucket/manifests/a.hash");
+
+	let dir = temp_dir().join("quickdash-publish");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	write(dir.join("a.hash"), "manifest").unwrap();
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let server = thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut request = Vec::new();
+		let mut buffer = [0; 1024];
+		while !request.ends_with(b"manifest") {
+			let read = stream.read(&mut buffer).unwrap();
+			request.extend_from_slice(&buffer[..read]);
+		}
+		stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+		String::from_utf8(request).unwrap()
+	});
+
+	let target = format!("http://127.0.0.1:{}/dav", port).parse().unwrap();
+	assert_eq!(publish(&dir.join("a.hash"), &target).unwrap(), format!("http://127.0.0.1:{}/dav/a.hash", port));
+	assert!(server.join().unwrap().starts_with("PUT /dav/a.hash "));
+
+	remove_dir_all(&dir).unwrap();
+}
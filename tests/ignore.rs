@@ -0,0 +1,37 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	path::PathBuf,
+	process,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, WalkOptions, create_hashes, parse_ignore_patterns},
+};
+
+#[test]
+fn ignore_and_exclude_files_read_patterns_alike() {
+	let dir = temp_dir().join(format!("quickdash-ignore-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("sub/cache")).unwrap();
+	for file in ["top.tmp", "kept", "sub/deep.tmp", "sub/cache/entry"] {
+		write(dir.join(file), file).unwrap();
+	}
+
+	for (pattern, left) in [
+		("*.tmp", &["kept", "sub/cache/entry"][..]),
+		("/*.tmp", &["kept", "sub/cache/entry", "sub/deep.tmp"][..]),
+		("cache/", &["kept", "sub/deep.tmp", "top.tmp"][..]),
+	] {
+		let mut ignoring = WalkOptions::default();
+		ignoring.ignore(pattern).unwrap();
+		let excluding = WalkOptions { ignored_patterns: parse_ignore_patterns(pattern).unwrap(), ..Default::default() };
+		for options in [ignoring, excluding] {
+			let hashes = create_hashes(&dir, Algorithm::SHA1, &options, &mut HashingReport::default());
+			assert_eq!(hashes.into_keys().collect::<Vec<_>>(), left.iter().map(PathBuf::from).collect::<Vec<_>>(), "{}", pattern);
+		}
+	}
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,43 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{read_to_string, remove_file},
+	path::{Path, PathBuf},
+	process,
+};
+
+use quickdash::{Algorithm, operations::write_in_toto};
+
+#[test]
+fn statement_subjects() {
+	let out = temp_dir().join(format!("quickdash-in-toto-{}.json", process::id()));
+	let hashes = BTreeMap::from([
+		(PathBuf::from("dir/\"quoted\""), "AABBCCDD".repeat(8)),
+		(PathBuf::from("link"), "11223344".repeat(8)),
+		(PathBuf::from("unread"), "-".repeat(64)),
+	]);
+	let notes = BTreeMap::from([(PathBuf::from("link"), "symlink -> dir".to_string())]);
+	write_in_toto(Path::new("dist"), &hashes, &notes, Algorithm::SHA3256, &out, None).unwrap();
+	let statement = read_to_string(&out);
+	let _ = remove_file(&out);
+
+	let statement = statement.unwrap();
+	assert!(statement.starts_with(r#"{"_type":"https://in-toto.io/Statement/v1","subject":[{"name":"dir/\"quoted\"","digest":{"sha3_256":"aabbccdd"#));
+	assert!(statement.contains(r#"}}],"predicateType":"https://slsa.dev/provenance/v1""#));
+}
+
+#[cfg(unix)]
+#[test]
+fn signed_envelope() {
+	let out = temp_dir().join(format!("quickdash-dsse-{}.json", process::id()));
+	let hashes = BTreeMap::from([(PathBuf::from("file"), "AABBCCDD".repeat(8))]);
+	// `head` signs with the start of what it's given, the pre-authentication encoding
+	write_in_toto(Path::new("dist"), &hashes, &BTreeMap::new(), Algorithm::SHA2256, &out, Some("head -c 36")).unwrap();
+	let envelope = read_to_string(&out);
+	let _ = remove_file(&out);
+
+	// "DSSEv1 28 application/vnd.in-toto+json" cut to 36 bytes
+	let envelope = envelope.unwrap();
+	assert!(envelope.starts_with(r#"{"payloadType":"application/vnd.in-toto+json","payload":"eyJfdHlwZSI6"#));
+	assert!(envelope.ends_with("\"signatures\":[{\"sig\":\"RFNTRXYxIDI4IGFwcGxpY2F0aW9uL3ZuZC5pbi10b3RvK2pz\"}]}\n"));
+}
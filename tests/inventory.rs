@@ -0,0 +1,61 @@
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{CompareFileResult, CompareResult, InventoryRun, Query, inventory_sql, query_hashes, query_sql},
+};
+
+#[test]
+fn verify_run_rows() {
+	let run = InventoryRun {
+		host: "nas",
+		root: Path::new("/srv/o'brien"),
+		manifest: Path::new("/srv/o'brien/o'brien.hash"),
+		command: "verify",
+		algorithm: Algorithm::UNSPECIFIED,
+	};
+	let hashes = BTreeMap::from([
+		(PathBuf::from("same"), "AA".to_string()),
+		(PathBuf::from("rotten"), "BB".to_string()),
+		(PathBuf::from("unread"), "--".to_string()),
+	]);
+	let compare_results = [CompareResult::FileRemoved(PathBuf::from("gone"))];
+	let file_compare_results = [
+		CompareFileResult::FileMatches(PathBuf::from("same")),
+		CompareFileResult::FileDiffers { file: PathBuf::from("rotten"), was_hash: "CC".to_string(), new_hash: "BB".to_string() },
+	];
+	let sql = inventory_sql(&run, &hashes, Some((&compare_results, &file_compare_results)));
+
+	assert!(sql.starts_with("CREATE TABLE IF NOT EXISTS runs ("));
+	assert!(sql.contains("VALUES ('nas-"));
+	assert!(sql.contains(", 'nas', '/srv/o''brien', '/srv/o''brien/o''brien.hash', 'verify', 'blake3', '"));
+	let files: Vec<&str> = sql.lines().filter(|line| line.starts_with("INSERT INTO files")).collect();
+	assert_eq!(files.len(), 4);
+	assert!(files[0].ends_with(", 'gone', NULL, 'removed');"));
+	assert!(files[1].ends_with(", 'rotten', 'BB', 'differs');"));
+	assert!(files[2].ends_with(", 'same', 'AA', 'matches');"));
+	assert!(files[3].ends_with(", 'unread', NULL, 'created');"));
+	assert!(sql.ends_with("COMMIT;\n"));
+}
+
+#[test]
+fn query_by_hash_and_name() {
+	let hashes = BTreeMap::from([
+		(PathBuf::from("a"), "AA".to_string()),
+		(PathBuf::from("b"), "BB".to_string()),
+		(PathBuf::from("copy of b"), "BB".to_string()),
+		(PathBuf::from("unread"), "--".to_string()),
+	]);
+
+	let by_hash = query_hashes(&hashes, &Query::Hash("bb".to_string()));
+	assert_eq!(by_hash, [(Path::new("b"), "BB"), (Path::new("copy of b"), "BB")]);
+	let by_name = query_hashes(&hashes, &Query::Name(PathBuf::from("copy of b")));
+	assert_eq!(by_name, [(Path::new("copy of b"), "BB"), (Path::new("b"), "BB")]);
+	assert!(query_hashes(&hashes, &Query::Name(PathBuf::from("unread"))).is_empty());
+
+	let sql = query_sql(&Query::Name(PathBuf::from("o'brien")), None);
+	assert!(sql.contains("SELECT hash FROM files WHERE name = 'o''brien'"));
+}
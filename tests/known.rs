@@ -0,0 +1,47 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{Known, KnownHashes},
+};
+
+#[test]
+fn triage_by_known_hashes() {
+	let dir = std::env::temp_dir().join("quickdash-known-hashes");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	let good = dir.join("good.txt");
+	fs::write(&good, "# known good\naa11  a\n\\bb22  b\\\\name\nnot-a-hash\n").unwrap();
+	let bad = dir.join("NSRLFile.txt");
+	fs::write(
+		&bad,
+		"\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\"\n\"BB22\",\"CC33\",\"0\",\"b\"\n\"DD44\",\"EE55\",\"0\",\"d\"\n",
+	)
+	.unwrap();
+
+	let known = KnownHashes::load(&[good], std::slice::from_ref(&bad), Algorithm::SHA1).unwrap();
+	assert_eq!(known.classify("AA11"), Known::Good);
+	assert_eq!(known.classify("bb22"), Known::Flagged);
+	assert_eq!(known.classify("CC33"), Known::Unknown);
+
+	let hashes = BTreeMap::from([
+		(PathBuf::from("a"), "AA11".to_string()),
+		(PathBuf::from("b"), "BB22".to_string()),
+		(PathBuf::from("c"), "FF66".to_string()),
+		(PathBuf::from("unread"), "--".to_string()),
+	]);
+	let files = known.classify_files(&hashes);
+	assert_eq!(files.known, 1);
+	assert_eq!(files.flagged, [PathBuf::from("b")]);
+	assert_eq!(files.unknown, [PathBuf::from("c")]);
+
+	let by_md5 = KnownHashes::load(&[], std::slice::from_ref(&bad), Algorithm::MD5).unwrap();
+	assert_eq!(by_md5.classify("CC33"), Known::Flagged);
+	assert!(KnownHashes::load(&[], &[bad], Algorithm::BLAKE3).is_err());
+
+	fs::remove_dir_all(&dir).unwrap();
+}
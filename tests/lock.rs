@@ -0,0 +1,56 @@
+use std::{
+	env::temp_dir,
+	fs::remove_file,
+	process,
+	time::{Duration, Instant},
+};
+
+use quickdash::operations::{lock_path, lock_run};
+
+#[test]
+fn runs_wait_for_the_lock() {
+	let file = temp_dir().join(format!("quickdash-lock-{}.hash", process::id()));
+	let lock = lock_run(&file, Duration::ZERO, false).unwrap();
+	assert!(lock.as_ref().is_some_and(|lock| lock.held()));
+	assert!(lock_run(&file, Duration::ZERO, false).unwrap().is_none());
+	assert!(lock_run(&file, Duration::ZERO, true).unwrap().is_none());
+	let started = Instant::now();
+	assert!(lock_run(&file, Duration::from_millis(300), false).unwrap().is_none());
+	assert!(started.elapsed() >= Duration::from_millis(300));
+	drop(lock);
+	assert!(lock_run(&file, Duration::ZERO, false).unwrap().is_some());
+	let _ = remove_file(lock_path(&file));
+}
+
+#[test]
+fn reading_runs_share_the_lock() {
+	let file = temp_dir().join(format!("quickdash-lock-shared-{}.hash", process::id()));
+	let first = lock_run(&file, Duration::ZERO, true).unwrap();
+	let second = lock_run(&file, Duration::ZERO, true).unwrap();
+	assert!(first.is_some() && second.is_some());
+	assert!(lock_run(&file, Duration::ZERO, false).unwrap().is_none());
+	drop((first, second));
+	assert!(lock_run(&file, Duration::ZERO, false).unwrap().is_some());
+	let _ = remove_file(lock_path(&file));
+}
+
+#[cfg(unix)]
+#[test]
+fn hash_files_that_cant_be_locked_are_used_unlocked() {
+	use std::{
+		fs::{self, Permissions, create_dir_all, remove_dir_all},
+		os::unix::fs::PermissionsExt,
+	};
+
+	let dir = temp_dir().join(format!("quickdash-lock-read-only-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	fs::set_permissions(&dir, Permissions::from_mode(0o555)).unwrap();
+	// Root writes whatever the permissions say
+	let writable = fs::write(dir.join("probe"), "").is_ok();
+	let lock = lock_run(&dir.join("photos.hash"), Duration::ZERO, true).unwrap().unwrap();
+	assert_eq!(lock.held(), writable);
+	drop(lock);
+	fs::set_permissions(&dir, Permissions::from_mode(0o755)).unwrap();
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,43 @@
+use std::{
+	env::temp_dir,
+	fs::{read_to_string, remove_file},
+	path::{Path, PathBuf},
+	process,
+};
+
+use quickdash::{
+	Error,
+	operations::{CompareFileResult, RunMetrics, write_metrics_textfile},
+};
+
+#[test]
+fn runs_replace_their_metrics() {
+	let textfile = temp_dir().join(format!("quickdash-metrics-{}.prom", process::id()));
+	let run = RunMetrics { files_scanned: 2, bytes_hashed: 10 };
+	let matching = Ok((Vec::new(), vec![CompareFileResult::FileMatches(PathBuf::from("same"))]));
+	write_metrics_textfile(&textfile, "verify", Path::new("photos.hash"), &run, Ok(&matching)).unwrap();
+	write_metrics_textfile(&textfile, "verify", Path::new("music.hash"), &run, Ok(&matching)).unwrap();
+	let rotten = Ok((
+		Vec::new(),
+		vec![CompareFileResult::FileDiffers { file: PathBuf::from("rotten"), was_hash: "AA".to_string(), new_hash: "BB".to_string() }],
+	));
+	write_metrics_textfile(&textfile, "verify", Path::new("photos.hash"), &RunMetrics::default(), Ok(&rotten)).unwrap();
+	let failure = Error::HashesFileParsingFailure("unreadable".to_string());
+	write_metrics_textfile(&textfile, "check", Path::new("photos.hash"), &RunMetrics::default(), Err(&failure)).unwrap();
+	let text = read_to_string(&textfile).unwrap();
+	let _ = remove_file(&textfile);
+
+	let photos = r#"{command="verify",manifest="photos.hash"}"#;
+	let music = r#"{command="verify",manifest="music.hash"}"#;
+	let checked = r#"{command="check",manifest="photos.hash"}"#;
+	assert_eq!(text.matches("# TYPE quickdash_failures gauge").count(), 1);
+	assert!(text.contains(&format!("quickdash_failures{} 1\n", photos)));
+	assert!(text.contains(&format!("quickdash_last_run_success{} 0\n", photos)));
+	assert!(text.contains(&format!("quickdash_files_scanned{} 0\n", photos)));
+	assert!(text.contains(&format!("quickdash_last_success_timestamp_seconds{} ", photos)));
+	assert!(text.contains(&format!("quickdash_bytes_hashed{} 10\n", music)));
+	assert!(text.contains(&format!("quickdash_last_run_success{} 1\n", music)));
+	assert!(text.contains(&format!("quickdash_last_run_success{} 0\n", checked)));
+	assert!(!text.contains(&format!("quickdash_last_success_timestamp_seconds{}", checked)));
+	assert_eq!(text.matches(&format!("quickdash_failures{}", photos)).count(), 1);
+}
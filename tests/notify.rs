@@ -0,0 +1,29 @@
+use std::path::{Path, PathBuf};
+
+use quickdash::{
+	Error,
+	operations::{CompareFileResult, CompareResult, FailureSummary},
+};
+
+#[test]
+fn summarises_failed_runs() {
+	let matching = Ok((vec![CompareResult::FileAdded(PathBuf::from("new"))], vec![CompareFileResult::FileMatches(PathBuf::from("same"))]));
+	assert_eq!(FailureSummary::of("nas", "verify", Path::new("photos.hash"), Ok(&matching)), None);
+
+	let rotten = Ok((
+		vec![CompareResult::FileRemoved(PathBuf::from("gone"))],
+		vec![CompareFileResult::FileDiffers { file: PathBuf::from("rotten \"one\""), was_hash: "AA".to_string(), new_hash: "BB".to_string() }],
+	));
+	let summary = FailureSummary::of("nas", "verify", Path::new("photos.hash"), Ok(&rotten)).unwrap();
+	let json = summary.json();
+	assert!(json.contains(r#""host":"nas","command":"verify","manifest":"photos.hash","failed":1,"failures":["rotten \"one\""],"removed":1,"error":null}"#));
+	let email = summary.email("quickdash@nas", &["ops@example.com".to_string(), "me@example.com".to_string()]);
+	assert!(email.starts_with("From: quickdash@nas\r\nTo: ops@example.com, me@example.com\r\nSubject: quickdash verify of \"photos.hash\" failed on nas\r\n"));
+	assert!(email.contains("\r\n1 files failed:\r\n  rotten \"one\"\r\n"));
+
+	let failure = Error::HashesFileParsingFailure("unreadable".to_string());
+	let summary = FailureSummary::of("nas", "check", Path::new("photos.hash"), Err(&failure)).unwrap();
+	assert!(summary.json().ends_with(r#""failed":0,"failures":[],"removed":0,"error":"unreadable"}"#));
+	let summary = FailureSummary::of("nas", "check", Path::new("fotos/\u{e9}t\u{e9}.hash"), Err(&failure)).unwrap();
+	assert!(summary.email("quickdash@nas", &[]).contains("\r\nSubject: =?utf-8?b?"));
+}
@@ -0,0 +1,32 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, ReadOptions, WalkOptions, WriteOptions, create_hashes_bounded, read_hashes},
+};
+
+#[test]
+fn low_memory_runs_leave_their_hash_file_out() {
+	let dir = temp_dir().join("quickdash-pipeline");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	write(dir.join("a.txt"), "a").unwrap();
+	let file = dir.join("inside.hash");
+
+	let mut report = HashingReport::default();
+	let rval = create_hashes_bounded(&dir, Algorithm::SHA2256, &WalkOptions::default(), &file, true, &WriteOptions::default(), &mut report);
+	assert_eq!(rval, 0);
+	let hashes = read_hashes(&file, &ReadOptions::default()).unwrap();
+	assert_eq!(hashes.keys().collect::<Vec<_>>(), [&PathBuf::from("a.txt")]);
+
+	// A hash file that can't be created fails the run
+	let unwritable = dir.join("missing").join("out.hash");
+	let rval = create_hashes_bounded(&dir, Algorithm::SHA2256, &WalkOptions::default(), &unwritable, true, &WriteOptions::default(), &mut report);
+	assert_eq!(rval, 1);
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,36 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	path::PathBuf,
+	process,
+};
+
+use quickdash::operations::{CompareFileResult, quarantine};
+
+#[test]
+fn names_stay_inside_the_quarantine() {
+	let dir = temp_dir().join(format!("quickdash-quarantine-names-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	let (tree, held) = (dir.join("tree"), dir.join("held"));
+	create_dir_all(tree.join("sub")).unwrap();
+	write(tree.join("sub/rotten"), "rotten").unwrap();
+	write(dir.join("escaped"), "escaped").unwrap();
+	let differs = |file: &str| CompareFileResult::FileDiffers {
+		file: PathBuf::from(file),
+		was_hash: "AA".to_string(),
+		new_hash: "BB".to_string(),
+	};
+
+	// Without the check, "../elsewhere" would be moved to the quarantine's parent
+	let lines = quarantine(&held, &[differs("/sub/rotten"), differs("../elsewhere")], false, |file| match file.starts_with("..") {
+		true => Some(dir.join("escaped")),
+		false => Some(tree.join(file.strip_prefix("/").unwrap())),
+	});
+	assert!(held.join("sub/rotten").exists());
+	assert!(!tree.join("sub/rotten").exists());
+	assert!(lines[1].starts_with("Failed to quarantine \"../elsewhere\""));
+	assert!(dir.join("escaped").exists());
+	assert!(!dir.join("elsewhere").exists());
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,62 @@
+use std::{
+	collections::BTreeSet,
+	env::temp_dir,
+	fs::{File, create_dir_all, remove_dir_all, remove_file, write},
+	path::{Path, PathBuf},
+	time::{Duration, UNIX_EPOCH},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{
+		CompareFileResult, HashingReport, ReadOptions, RecordedMetadata, WalkOptions, WriteOptions, apply_recorded_metadata, compare_hashes,
+		create_hashes, read_hashes, write_hashes,
+	},
+};
+
+/// Write `contents` to `path`, modified `secs` seconds after a fixed time.
+fn write_at(path: &Path, contents: &str, secs: u64) {
+	write(path, contents).unwrap();
+	let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs);
+	File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+}
+
+#[test]
+fn quick_verification_reads_only_files_whose_metadata_changed() {
+	let dir = temp_dir().join("quickdash-quick");
+	let manifest = temp_dir().join("quickdash-quick.hash");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	write_at(&dir.join("kept.txt"), "kept", 0);
+	write_at(&dir.join("grown.txt"), "grown", 0);
+	write_at(&dir.join("touched.txt"), "touched", 0);
+
+	let options = WalkOptions { record_metadata: true, ..Default::default() };
+	let mut report = HashingReport::default();
+	let hashes = create_hashes(&dir, Algorithm::SHA2256, &options, &mut report);
+	assert_eq!(write_hashes(&manifest, hashes, &WriteOptions { metadata: report.metadata, ..Default::default() }), 0);
+
+	// Grown files differ whatever they hold, and only touched ones are read
+	write_at(&dir.join("grown.txt"), "grown more", 1);
+	write_at(&dir.join("touched.txt"), "touched", 1);
+	let recorded = RecordedMetadata::load(&manifest, &ReadOptions::default()).unwrap().quick(0.0);
+	let options = WalkOptions { recorded: Some(recorded), ..Default::default() };
+	let mut report = HashingReport::default();
+	let hashes = create_hashes(&dir, Algorithm::SHA2256, &options, &mut report);
+	assert_eq!(report.bytes_hashed, 7);
+	assert_eq!(report.assumed, BTreeSet::from([PathBuf::from("kept.txt")]));
+	assert_eq!(report.drifted.keys().collect::<Vec<_>>(), [Path::new("grown.txt"), Path::new("touched.txt")]);
+
+	let loaded = read_hashes(&manifest, &ReadOptions::default()).unwrap();
+	let (_, file_compare_results) = apply_recorded_metadata(compare_hashes(hashes, loaded), &report).unwrap();
+	assert!(file_compare_results.contains(&CompareFileResult::FileAssumedOk(PathBuf::from("kept.txt"))));
+	assert!(file_compare_results.iter().any(|res| matches!(res, CompareFileResult::FileDrifted { file, matches: None, .. } if file == Path::new("grown.txt"))));
+	assert!(
+		file_compare_results
+			.iter()
+			.any(|res| matches!(res, CompareFileResult::FileDrifted { file, matches: Some(true), .. } if file == Path::new("touched.txt")))
+	);
+
+	remove_dir_all(&dir).unwrap();
+	remove_file(&manifest).unwrap();
+}
@@ -0,0 +1,262 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{remove_file, write},
+	path::PathBuf,
+	process,
+	time::Duration,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{
+		CommentStyle, Encryption, EntryMetadata, HashEncoding, Ownership, Pieces, ReadOptions, WriteOptions, read_header, read_hashes, stream_hashes,
+		write_hashes,
+	},
+};
+
+fn read_bytes(name: &str, contents: &[u8]) -> BTreeMap<PathBuf, String> {
+	let file = temp_dir().join(format!("quickdash-{}-{}.hash", name, process::id()));
+	write(&file, contents).unwrap();
+	let hashes = read_hashes(&file, &ReadOptions::default());
+	let _ = remove_file(&file);
+	hashes.unwrap()
+}
+
+fn expected() -> BTreeMap<PathBuf, String> {
+	BTreeMap::from([
+		(PathBuf::from("first file"), "AABBCCDD".to_string()),
+		(PathBuf::from("second"), "11223344".to_string()),
+	])
+}
+
+#[test]
+fn bom_and_crlf() {
+	let contents = "\u{feff}aabbccdd  first file\r\n11223344  second\r\n";
+	assert_eq!(read_bytes("crlf", contents.as_bytes()), expected());
+}
+
+#[test]
+fn trailing_whitespace() {
+	let contents = "aabbccdd  first file \t\nsecond\t11223344  \n\n";
+	assert_eq!(read_bytes("trailing", contents.as_bytes()), expected());
+}
+
+#[test]
+fn crlf_round_trip() {
+	let file = temp_dir().join(format!("quickdash-round-trip-{}.hash", process::id()));
+	write_hashes(&file, expected(), &WriteOptions::default());
+	let crlf = std::fs::read_to_string(&file).unwrap().replace('\n', "\r\n");
+	let _ = remove_file(&file);
+
+	assert_eq!(read_bytes("round-trip", format!("\u{feff}{}", crlf).as_bytes()), expected());
+}
+
+#[test]
+fn legacy_encodings() {
+	let name = |hashes: BTreeMap<PathBuf, String>| hashes.into_keys().next().unwrap();
+
+	// "café.txt" in CP1252 and CP850
+	assert_eq!(name(read_bytes("cp1252", b"AABBCCDD  caf\xE9.txt\n")), PathBuf::from("café.txt"));
+	assert_eq!(name(read_bytes("cp850", b"AABBCCDD  caf\x82.txt\n")), PathBuf::from("café.txt"));
+}
+
+#[test]
+fn utf16() {
+	let contents = "aabbccdd  first file\r\n11223344  second\r\n";
+	let le: Vec<u8> = [0xFF, 0xFE]
+		.into_iter()
+		.chain(contents.encode_utf16().flat_map(u16::to_le_bytes))
+		.collect();
+	let be: Vec<u8> = [0xFE, 0xFF]
+		.into_iter()
+		.chain(contents.encode_utf16().flat_map(u16::to_be_bytes))
+		.collect();
+
+	assert_eq!(read_bytes("utf16le", &le), expected());
+	assert_eq!(read_bytes("utf16be", &be), expected());
+}
+
+#[test]
+fn comments() {
+	let contents = "; generated by QuickSFV\n# sha256\naabbccdd  first file\n# not a header\n11223344  second\n";
+	assert_eq!(read_bytes("comments", contents.as_bytes()), expected());
+}
+
+#[test]
+fn hash_signs_starting_names() {
+	let contents = "# filename first\n#notes.txt  aabbccdd\nsecond  11223344\n";
+	let expected = BTreeMap::from([
+		(PathBuf::from("#notes.txt"), "AABBCCDD".to_string()),
+		(PathBuf::from("second"), "11223344".to_string()),
+	]);
+	assert_eq!(read_bytes("hash-signs", contents.as_bytes()), expected);
+}
+
+#[test]
+fn header_round_trip() {
+	let file = temp_dir().join(format!("quickdash-header-{}.hash", process::id()));
+	let options = WriteOptions {
+		comment_style: CommentStyle::Hash,
+		header: vec!["created by quickdash".to_string(), "SHA256".to_string()],
+		..Default::default()
+	};
+	write_hashes(&file, expected(), &options);
+	let contents = std::fs::read_to_string(&file).unwrap();
+	let header = read_header(&file, &ReadOptions::default());
+	let hashes = read_hashes(&file, &ReadOptions::default());
+	let _ = remove_file(&file);
+
+	assert!(contents.starts_with("# created by quickdash\n# SHA256\n"));
+	assert_eq!(header.unwrap(), options.header);
+	assert_eq!(hashes.unwrap(), expected());
+}
+
+#[test]
+fn metadata_round_trip() {
+	let file = temp_dir().join(format!("quickdash-metadata-{}.hash", process::id()));
+	let metadata = EntryMetadata {
+		size: 1234,
+		mtime: Duration::new(1_700_000_000, 5),
+		ownership: Some(Ownership { uid: 0, gid: 10, mode: 0o644 }),
+		attributes: None,
+	};
+	let options = WriteOptions {
+		header: vec!["SHA256".to_string()],
+		metadata: BTreeMap::from([(PathBuf::from("second"), metadata)]),
+		..Default::default()
+	};
+	write_hashes(&file, expected(), &options);
+	let header = read_header(&file, &ReadOptions::default());
+	let mut reader = stream_hashes(&file, &ReadOptions::default()).unwrap();
+	let first = (reader.next().unwrap().unwrap(), reader.metadata());
+	let second = (reader.next().unwrap().unwrap(), reader.metadata());
+	let _ = remove_file(&file);
+
+	assert_eq!(header.unwrap(), options.header);
+	assert_eq!(first, ((PathBuf::from("first file"), "AABBCCDD".to_string()), None));
+	assert_eq!(second, ((PathBuf::from("second"), "11223344".to_string()), Some(metadata)));
+}
+
+#[test]
+fn pieces_round_trip() {
+	let file = temp_dir().join(format!("quickdash-pieces-{}.hash", process::id()));
+	let pieces = Pieces { size: 4, hashes: vec!["AA".to_string(), "BB".to_string()] };
+	let options = WriteOptions {
+		header: vec!["SHA256".to_string()],
+		pieces: BTreeMap::from([(PathBuf::from("first file"), pieces.clone())]),
+		..Default::default()
+	};
+	write_hashes(&file, expected(), &options);
+	let header = read_header(&file, &ReadOptions::default());
+	let mut reader = stream_hashes(&file, &ReadOptions::default()).unwrap();
+	let first = (reader.next().unwrap().unwrap(), reader.pieces().cloned());
+	let second = (reader.next().unwrap().unwrap(), reader.pieces().cloned());
+	let _ = remove_file(&file);
+
+	assert_eq!(header.unwrap(), options.header);
+	assert_eq!(first, ((PathBuf::from("first file"), "AABBCCDD".to_string()), Some(pieces)));
+	assert_eq!(second, ((PathBuf::from("second"), "11223344".to_string()), None));
+}
+
+#[test]
+fn multihash_round_trip() {
+	for hash_encoding in [HashEncoding::Multihash, HashEncoding::Cid, HashEncoding::Oci] {
+		let file = temp_dir().join(format!("quickdash-multihash-{}.hash", process::id()));
+		write_hashes(&file, expected(), &WriteOptions { hash_encoding, algorithm: Algorithm::CRC32, ..Default::default() });
+		let mut reader = stream_hashes(&file, &ReadOptions::default()).unwrap();
+		let first = (reader.next().unwrap().unwrap(), reader.algorithm());
+		let _ = remove_file(&file);
+
+		assert_eq!(first, ((PathBuf::from("first file"), "AABBCCDD".to_string()), Some(Algorithm::CRC32)));
+	}
+
+	// SHA-256 of "hello\n", as a CIDv1 and a CIDv0
+	let sha256 = "5891B5B522D5DF086D0FF0B110FBD9D21BB4FC7163AF34D08286A2E846F6BE03".to_string();
+	let contents = "bafkreicysg23kiwv34eg2d7qweipxwosdo2py4ldv42nbauguluen5v6am  first file\nQmUJPTFZnR2CPGAzmfdYPghgrFtYFB6pf1BqMvqfiPDam8  second\n";
+	assert_eq!(
+		read_bytes("cid", contents.as_bytes()),
+		BTreeMap::from([(PathBuf::from("first file"), sha256.clone()), (PathBuf::from("second"), sha256.clone())])
+	);
+
+	let contents = format!("sha256:{}  first file\n", sha256.to_lowercase());
+	assert_eq!(read_bytes("oci", contents.as_bytes()), BTreeMap::from([(PathBuf::from("first file"), sha256)]));
+}
+
+#[test]
+fn rclone_round_trip() {
+	let file = temp_dir().join(format!("quickdash-rclone-{}.hash", process::id()));
+	let hashes = BTreeMap::from([
+		(PathBuf::from(" padded\tname "), "AABBCCDD".to_string()),
+		(PathBuf::from("dir/file"), "11223344".to_string()),
+	]);
+	let options = WriteOptions { header: vec!["not written".to_string()], rclone: true, ..Default::default() };
+	write_hashes(&file, hashes.clone(), &options);
+	let written = std::fs::read_to_string(&file).unwrap();
+	let _ = remove_file(&file);
+	assert_eq!(written, "aabbccdd   padded\tname \n11223344  dir/file\n");
+
+	let rclone = ReadOptions { rclone: true, ..Default::default() };
+	write(&file, format!("                     UNSUPPORTED  unhashed\n{}", written)).unwrap();
+	let read = read_hashes(&file, &rclone);
+	let _ = remove_file(&file);
+	assert_eq!(read.unwrap(), hashes);
+}
+
+#[test]
+fn mtree_round_trip() {
+	let file = temp_dir().join(format!("quickdash-mtree-{}.hash", process::id()));
+	let hashes = BTreeMap::from([
+		(PathBuf::from("a b#1"), "AABBCCDD".repeat(4)),
+		(PathBuf::from("dir/file"), "11223344".repeat(4)),
+	]);
+	let metadata = EntryMetadata {
+		size: 5,
+		mtime: Duration::new(1_700_000_000, 5),
+		ownership: Some(Ownership { uid: 0, gid: 10, mode: 0o644 }),
+		attributes: None,
+	};
+	let options = WriteOptions {
+		metadata: BTreeMap::from([(PathBuf::from("dir/file"), metadata)]),
+		algorithm: Algorithm::MD5,
+		mtree: true,
+		..Default::default()
+	};
+	write_hashes(&file, hashes.clone(), &options);
+	let written = std::fs::read_to_string(&file).unwrap();
+	assert_eq!(
+		written,
+		"#mtree\n./a\\040b\\0431 type=file md5digest=aabbccddaabbccddaabbccddaabbccdd\n\
+		 ./dir/file type=file size=5 time=1700000000.000000005 mode=0644 uid=0 gid=10 md5digest=11223344112233441122334411223344\n"
+	);
+
+	let mtree = ReadOptions { mtree: true, ..Default::default() };
+	let mut reader = stream_hashes(&file, &mtree).unwrap();
+	assert_eq!(reader.next().unwrap().unwrap(), (PathBuf::from("a b#1"), hashes[&PathBuf::from("a b#1")].clone()));
+	assert_eq!(reader.algorithm(), Some(Algorithm::MD5));
+	reader.next().unwrap().unwrap();
+	assert_eq!(reader.metadata(), Some(metadata));
+	drop(reader);
+
+	// The hierarchical form, leaving out directories with something in them
+	write(
+		&file,
+		"/set type=file mode=0644\n. type=dir\ndir type=dir\n    file \\\n    md5digest=11223344112233441122334411223344\n..\na\\sb\\0431 md5=aabbccddaabbccddaabbccddaabbccdd\n",
+	)
+	.unwrap();
+	let read = read_hashes(&file, &mtree);
+	let _ = remove_file(&file);
+	assert_eq!(read.unwrap(), hashes);
+}
+
+#[test]
+fn encryption_detected() {
+	assert_eq!(Encryption::detect(b"age-encryption.org/v1\n-> X25519 "), Some(Encryption::Age));
+	assert_eq!(Encryption::detect(b"-----BEGIN AGE ENCRYPTED FILE-----\n"), Some(Encryption::Age));
+	assert_eq!(Encryption::detect(b"-----BEGIN PGP MESSAGE-----\n"), Some(Encryption::Gpg));
+	assert_eq!(Encryption::detect(&[0x85, 0x01, 0x0c]), Some(Encryption::Gpg));
+	assert_eq!(Encryption::detect(b"AABBCCDD  first file\n"), None);
+	assert_eq!(Encryption::detect("\u{e9}t\u{e9}.txt".as_bytes()), None);
+	assert_eq!(Encryption::of_recipient("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"), Encryption::Age);
+	assert_eq!(Encryption::of_recipient("alice@example.org"), Encryption::Gpg);
+}
@@ -0,0 +1,11 @@
+use quickdash::operations::parse_remote_target;
+
+#[test]
+fn remote_targets() {
+	assert_eq!(parse_remote_target("me@host:/srv/data"), Ok(("me@host".to_owned(), "/srv/data")));
+	assert_eq!(parse_remote_target("host:data:2024"), Ok(("host".to_owned(), "data:2024")));
+	assert_eq!(parse_remote_target("me@[::1]:/srv"), Ok(("me@::1".to_owned(), "/srv")));
+	assert!(parse_remote_target("host").is_err());
+	assert!(parse_remote_target("host:").is_err());
+	assert!(parse_remote_target(":/srv").is_err());
+}
@@ -0,0 +1,40 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	process,
+};
+
+use quickdash::{Algorithm, HashOptions, try_hash_file};
+
+/// The AWS CLI's smallest part size, as with `--multipart-chunksize 5MB`.
+const PART_SIZE: u64 = 5 << 20;
+
+#[test]
+fn etags_match_those_of_the_aws_cli() {
+	let dir = temp_dir().join(format!("quickdash-s3-etag-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	let options = HashOptions { part_size: PART_SIZE, ..Default::default() };
+
+	// Uploaded in one go below the part size, in parts from it on
+	for (len, etag) in [
+		(PART_SIZE - 1, "7c668eb59d6f0141a7863774100bfbcc"),
+		(PART_SIZE, "81485c0e873d222199469076b60f30e9-1"),
+		(PART_SIZE + 1, "92f3a08aa3b1d7eb318ab9c2fc4a6ec3-2"),
+	] {
+		let file = dir.join(format!("{}.bin", len));
+		write(&file, vec![0; len as usize]).unwrap();
+		assert_eq!(try_hash_file(Algorithm::S3ETag, &file, &options).unwrap(), etag);
+	}
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn multipart_etags_are_detected() {
+	assert_eq!(Algorithm::autodetect_from_hash("81485c0e873d222199469076b60f30e9-1"), Algorithm::S3ETag);
+	assert_eq!(Algorithm::autodetect_from_hash("92f3a08aa3b1d7eb318ab9c2fc4a6ec3-2"), Algorithm::S3ETag);
+	assert_eq!(Algorithm::autodetect_from_hash("7c668eb59d6f0141a7863774100bfbcc"), Algorithm::MD5);
+	assert_ne!(Algorithm::autodetect_from_hash("92f3a08aa3b1d7eb318ab9c2fc4a6ec3-"), Algorithm::S3ETag);
+	assert_ne!(Algorithm::autodetect_from_hash("92f3a08aa3b1d7eb-2"), Algorithm::S3ETag);
+}
@@ -0,0 +1,28 @@
+#![cfg(unix)]
+
+use std::{
+	env::{set_var, temp_dir},
+	fs::remove_file,
+	os::unix::net::UnixDatagram,
+	path::Path,
+	process,
+};
+
+use quickdash::operations::{partial_path, sd_notify, stopping};
+
+#[test]
+fn notifies_the_service_manager() {
+	let socket_path = temp_dir().join(format!("quickdash-notify-{}.sock", process::id()));
+	let socket = UnixDatagram::bind(&socket_path).unwrap();
+	// SAFETY: this is the only test of its binary, no other thread reads the
+	// environment
+	unsafe { set_var("NOTIFY_SOCKET", &socket_path) };
+	sd_notify("READY=1").unwrap();
+	let mut state = [0; 16];
+	let len = socket.recv(&mut state).unwrap();
+	let _ = remove_file(&socket_path);
+
+	assert_eq!(&state[..len], b"READY=1");
+	assert!(!stopping());
+	assert_eq!(partial_path(Path::new("photos.hash")), Path::new("photos.hash.partial"));
+}
@@ -0,0 +1,35 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all},
+	path::PathBuf,
+};
+
+use quickdash::operations::{ReadOptions, ShardBy, WriteOptions, read_hashes, read_shard_index, write_sharded_hashes};
+
+#[test]
+fn top_level_files_and_underscore_dirs_get_their_own_shards() {
+	let dir = temp_dir().join("quickdash-shard");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	let index = dir.join("tree.hash");
+	let hashes: BTreeMap<PathBuf, String> = [("top.txt", "0000AAAA"), ("_/under.txt", "1111BBBB"), ("photos/a.jpg", "2222CCCC")]
+		.iter()
+		.map(|(name, hash)| (PathBuf::from(name), hash.to_string()))
+		.collect();
+
+	assert_eq!(write_sharded_hashes(&index, hashes, ShardBy::TopLevelDir, &WriteOptions::default()), 0);
+	let shards = read_shard_index(&index).unwrap().unwrap();
+	assert_eq!(shards, [dir.join("tree..hash"), dir.join("tree._.hash"), dir.join("tree.photos.hash")]);
+	let top = read_hashes(&dir.join("tree..hash"), &ReadOptions::default()).unwrap();
+	assert_eq!(top.keys().collect::<Vec<_>>(), [&PathBuf::from("top.txt")]);
+
+	// Shards the new index doesn't list are removed
+	let hashes = [(PathBuf::from("photos/a.jpg"), "2222CCCC".to_string())].into();
+	assert_eq!(write_sharded_hashes(&index, hashes, ShardBy::TopLevelDir, &WriteOptions::default()), 0);
+	assert_eq!(read_shard_index(&index).unwrap().unwrap(), [dir.join("tree.photos.hash")]);
+	assert!(!dir.join("tree..hash").exists());
+	assert!(!dir.join("tree._.hash").exists());
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,30 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use quickdash::{
+	HashOptions,
+	operations::{signature_dir, signature_path, write_signatures},
+};
+
+#[test]
+fn librsync_signatures() {
+	let dir = std::env::temp_dir().join("quickdash-rsync-signatures");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	fs::write(dir.join("big"), [1u8; 5]).unwrap();
+	fs::write(dir.join("small"), [1u8; 4]).unwrap();
+	let hashes = BTreeMap::from([(PathBuf::from("big"), "AA".to_string()), (PathBuf::from("small"), "BB".to_string())]);
+	let signatures = signature_dir(&dir.join("dir.hash"));
+	let mut warnings = Vec::new();
+	write_signatures(&dir, &hashes, &BTreeMap::new(), &signatures, 4, &HashOptions::default(), &mut warnings);
+
+	assert!(warnings.is_empty());
+	assert!(!signature_path(&signatures, "small".as_ref()).exists());
+	let signature = fs::read(signature_path(&signatures, "big".as_ref())).unwrap();
+	assert_eq!(signature.len(), 12 + 2 * 36);
+	assert_eq!(signature[..12], [0x72, 0x73, 0x01, 0x37, 0, 0, 0, 4, 0, 0, 0, 32]);
+	// Rolling checksums of 4 and of 1 bytes of 1, each plus 31
+	assert_eq!(signature[12..16], [0x01, 0x40, 0x00, 0x80]);
+	assert_eq!(signature[48..52], [0x00, 0x20, 0x00, 0x20]);
+
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,51 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use quickdash::{
+	fuzzy_hash_bytes,
+	operations::{FuzzyHash, similar_files},
+};
+
+/// Text of `words` words picked by a simple generator from `seed`.
+fn text(seed: u64, words: usize) -> Vec<u8> {
+	let mut state = seed;
+	let mut text = Vec::new();
+	for _ in 0..words {
+		state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		let word = (state >> 33) % 400;
+		text.extend(format!("w{} ", word).bytes());
+	}
+	text
+}
+
+#[test]
+fn edited_files_are_alike() {
+	let original = text(1, 20000);
+	let mut edited = original.clone();
+	edited.splice(30000..30000, b"a paragraph added in the middle ".repeat(10));
+	edited.truncate(edited.len() - 5000);
+	let other = text(2, 20000);
+
+	let digests = [&original, &edited, &other].map(|data| fuzzy_hash_bytes(data));
+	assert_eq!(fuzzy_hash_bytes(&original), digests[0]);
+	let [original, edited, other] = digests.map(|digest| digest.parse::<FuzzyHash>().unwrap());
+	assert_eq!(original.similarity(&original), 100);
+	assert!(original.similarity(&edited) >= 50);
+	assert_eq!(original.similarity(&other), 0);
+	assert!("3:abc".parse::<FuzzyHash>().is_err());
+}
+
+#[test]
+fn clusters_of_similar_files() {
+	let digests = BTreeMap::from([
+		(PathBuf::from("a"), "96:AAAAAAAAbcdefghijklmnopqrstuv:AAAbcdefghijk".to_string()),
+		(PathBuf::from("b"), "96:AAAAbcdefghijklmnopqrstuvwxyz:AAAbcdefghijk".to_string()),
+		(PathBuf::from("c"), "192:AAAbcdefghijklmno:zyx".to_string()),
+		(PathBuf::from("d"), "96:0123456789+/ZYXWVUTSRQPONMLK:9876543210".to_string()),
+		(PathBuf::from("e"), "not a digest".to_string()),
+	]);
+	let clusters = similar_files(&digests, 50);
+	assert_eq!(clusters.len(), 1);
+	let names: Vec<&str> = clusters[0].iter().map(|(file, _)| file.to_str().unwrap()).collect();
+	assert_eq!(names, ["a", "b", "c"]);
+	assert!(clusters[0].iter().all(|&(_, score)| (50..=100).contains(&score)));
+}
@@ -0,0 +1,27 @@
+use std::{
+	env::temp_dir,
+	fs::{File, read, remove_file},
+	io::{Seek, SeekFrom, Write},
+	process,
+};
+
+use quickdash::{Algorithm, hash_file, hash_reader};
+
+#[test]
+fn sparse_file_hashes_like_plain_read() {
+	let path = temp_dir().join(format!("quickdash-sparse-{}", process::id()));
+	let mut file = File::create(&path).unwrap();
+	// hole, data, hole, data, trailing hole
+	file.seek(SeekFrom::Start(3 << 20)).unwrap();
+	file.write_all(b"data in the middle").unwrap();
+	file.seek(SeekFrom::Start(7 << 20)).unwrap();
+	file.write_all(&[0xAB; 5000]).unwrap();
+	file.set_len(9 << 20).unwrap();
+	drop(file);
+
+	let sparse = hash_file(Algorithm::SHA2256, &path);
+	let plain = hash_reader(Algorithm::SHA2256, &mut read(&path).unwrap().as_slice());
+	let _ = remove_file(&path);
+
+	assert_eq!(sparse, plain);
+}
@@ -0,0 +1,36 @@
+#![cfg(unix)]
+
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	os::unix::net::UnixListener,
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, SpecialFiles, WalkOptions, create_hashes},
+};
+
+#[test]
+fn special_files_that_are_an_error_fail_the_run() {
+	let dir = temp_dir().join("quickdash-special-files");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	write(dir.join("file.txt"), "file").unwrap();
+	let _socket = UnixListener::bind(dir.join("socket")).unwrap();
+
+	let options = WalkOptions { special_files: SpecialFiles::Error, ..Default::default() };
+	let mut report = HashingReport::default();
+	let hashes = create_hashes(&dir, Algorithm::SHA2256, &options, &mut report);
+	assert_eq!(hashes.keys().collect::<Vec<_>>(), [&PathBuf::from("file.txt")]);
+	assert_eq!(report.errors.len(), 1);
+	assert!(report.errors[0].contains("socket"));
+
+	let parallel = WalkOptions { io_threads: 2, ..options };
+	let mut report = HashingReport::default();
+	create_hashes(&dir, Algorithm::SHA2256, &parallel, &mut report);
+	assert_eq!(report.errors.len(), 1);
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+use quickdash::{
+	Error,
+	operations::{CompareFileResult, CompareResult, FAILURE_MESSAGE_ID, RUN_MESSAGE_ID, log_records},
+};
+
+#[test]
+fn records_runs_and_failures() {
+	let outcome = Ok((
+		vec![CompareResult::FileAdded(PathBuf::from("new"))],
+		vec![
+			CompareFileResult::FileMatches(PathBuf::from("same")),
+			CompareFileResult::FileDiffers { file: PathBuf::from("rotten"), was_hash: "AA".to_string(), new_hash: "BB".to_string() },
+		],
+	));
+	let records = log_records("verify", Path::new("photos.hash"), Ok(&outcome));
+	assert_eq!(records.len(), 2);
+	assert_eq!(records[0].priority, 3);
+	assert!(records[0].fields.contains(&("MESSAGE_ID", RUN_MESSAGE_ID.to_string())));
+	assert!(records[0].fields.contains(&("FAILED", "1".to_string())));
+	assert!(records[0].fields.contains(&("ADDED", "1".to_string())));
+	assert!(records[1].fields.contains(&("MESSAGE_ID", FAILURE_MESSAGE_ID.to_string())));
+	for field in [("FILE", "rotten"), ("EXPECTED", "AA"), ("ACTUAL", "BB")] {
+		assert!(records[1].fields.contains(&(field.0, field.1.to_string())));
+	}
+
+	let entry = String::from_utf8(records[1].journal_entry()).unwrap();
+	assert!(entry.starts_with("MESSAGE=verify of \"photos.hash\": \"rotten\" doesn't match\nPRIORITY=3\nSYSLOG_IDENTIFIER=quickdash\n"));
+	assert!(entry.ends_with("FILE=rotten\nEXPECTED=AA\nACTUAL=BB\n"));
+	let message = records[1].syslog_message(42);
+	assert!(message.starts_with("<11>quickdash[42]: verify of \"photos.hash\": \"rotten\" doesn't match [quickdash@32473 COMMAND=\"verify\""));
+	assert!(message.ends_with(" FILE=\"rotten\" EXPECTED=\"AA\" ACTUAL=\"BB\"]"));
+
+	let failure = Error::HashesFileParsingFailure("line 3\nis \"broken\"".to_string());
+	let records = log_records("check", Path::new("photos.hash"), Err(&failure));
+	assert_eq!(records.len(), 1);
+	assert!(records[0].journal_entry().windows(9).any(|bytes| bytes == b"\nERROR\n\x12\0"));
+	assert!(records[0].syslog_message(42).ends_with(r#" ERROR="line 3 is \"broken\""]"#));
+}
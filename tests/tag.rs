@@ -0,0 +1,42 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, read_to_string, remove_dir_all, write},
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{HashingReport, Rename, WalkOptions, apply_rename, strip_tag, tag_name, tag_renames},
+};
+
+#[test]
+fn tag_names() {
+	assert_eq!(tag_name("Episode 01.mkv", "89ABCDEF"), "Episode 01 [89ABCDEF].mkv");
+	assert_eq!(tag_name("README", "89ABCDEF0123"), "README [89ABCDEF]");
+	assert_eq!(tag_name(".hidden", "89ABCDEF"), ".hidden [89ABCDEF]");
+	assert_eq!(strip_tag("Episode 01 [89ABCDEF].mkv").as_deref(), Some("Episode 01.mkv"));
+	assert_eq!(strip_tag("[Group] Episode 01 [1080p][89abcdef].mkv").as_deref(), Some("[Group] Episode 01 [1080p].mkv"));
+	assert_eq!(strip_tag("Episode 01 [1080p].mkv"), None);
+}
+
+#[test]
+fn tag_and_strip() {
+	let dir = temp_dir().join("quickdash-tag");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	write(dir.join("a.txt"), "hello").unwrap();
+	write(dir.join("b [00000000].txt"), "else").unwrap();
+
+	let renames = tag_renames(&dir, Algorithm::UNSPECIFIED, &WalkOptions::default(), false, &mut HashingReport::default());
+	assert_eq!(renames, vec![Rename { from: dir.join("a.txt"), to: dir.join("a [3610A686].txt") }]);
+	apply_rename(&renames[0]).unwrap();
+	assert_eq!(read_to_string(dir.join("a [3610A686].txt")).unwrap(), "hello");
+
+	// Files aren't renamed onto one that exists
+	write(dir.join("a.txt"), "taken").unwrap();
+	let mut report = HashingReport::default();
+	let renames = tag_renames(&dir, Algorithm::UNSPECIFIED, &WalkOptions::default(), true, &mut report);
+	assert_eq!(renames, vec![Rename { from: dir.join("b [00000000].txt"), to: dir.join("b.txt") }]);
+	assert_eq!(report.warnings.len(), 1);
+
+	remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,26 @@
+use std::{
+	env::temp_dir,
+	fs::{remove_file, write},
+	process,
+};
+
+use quickdash::{Algorithm, HashOptions, hash_reader, try_hash_file};
+
+#[test]
+fn crlf_hashes_like_lf() {
+	let options = HashOptions { text_mode: true, ..HashOptions::default() };
+	let path = temp_dir().join(format!("quickdash-text-mode-{}", process::id()));
+	// Long enough for line endings to straddle reads
+	let lines = "a line of text\r\n".repeat(5000);
+	let hash = |contents: &str| {
+		write(&path, contents).unwrap();
+		try_hash_file(Algorithm::SHA2256, &path, &options).unwrap()
+	};
+	let (crlf, lone_cr, trailing_cr) = (hash(&lines), hash("lone\rcr\r\r\n"), hash("trailing\r"));
+	let _ = remove_file(&path);
+
+	let plain = |contents: &str| hash_reader(Algorithm::SHA2256, &mut contents.as_bytes());
+	assert_eq!(crlf, plain(&lines.replace("\r\n", "\n")));
+	assert_eq!(lone_cr, plain("lone\rcr\r\n"));
+	assert_eq!(trailing_cr, plain("trailing\r"));
+}
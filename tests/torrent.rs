@@ -0,0 +1,72 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, remove_file, write},
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm, hash_file,
+	operations::{CompareFileResult, Torrent, check_torrent, write_torrent},
+};
+use sha1::{Digest, Sha1};
+
+#[test]
+fn v1_pieces_spanning_files_fail_every_file_they_cover() {
+	let dir = temp_dir().join("quickdash-torrent-v1");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("set")).unwrap();
+	write(dir.join("set/a"), "hello").unwrap();
+	write(dir.join("set/b"), " world").unwrap();
+
+	// Pieces of 4 bytes: "hell", "o wo" across both files, and "rld"
+	let pieces: Vec<u8> = [&b"hell"[..], &b"o wo"[..], &b"rld"[..]].iter().flat_map(|piece| Sha1::digest(piece)).collect();
+	let mut data = b"d4:infod5:filesld6:lengthi5e4:pathl1:aeed6:lengthi6e4:pathl1:beee4:name3:set12:piece lengthi4e6:pieces60:".to_vec();
+	data.extend(pieces);
+	data.extend(b"ee");
+	let file = dir.join("set.torrent");
+	write(&file, data).unwrap();
+	let torrent = Torrent::load(&file).unwrap();
+
+	let (_, file_compare_results) = check_torrent(&torrent, &dir).unwrap();
+	assert_eq!(file_compare_results, [CompareFileResult::FileMatches(PathBuf::from("set/a")), CompareFileResult::FileMatches(PathBuf::from("set/b"))]);
+
+	write(dir.join("set/b"), " World").unwrap();
+	let (_, file_compare_results) = check_torrent(&torrent, &dir).unwrap();
+	assert_eq!(
+		file_compare_results,
+		[
+			CompareFileResult::PiecesFail { file: PathBuf::from("set/a"), pieces: vec![1] },
+			CompareFileResult::PiecesFail { file: PathBuf::from("set/b"), pieces: vec![1] },
+		]
+	);
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn v2_files_of_a_single_piece_are_checked_against_their_root() {
+	let dir = temp_dir().join("quickdash-torrent-v2");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(&dir).unwrap();
+	// Two blocks, in a piece of four
+	let mut contents = vec![b'a'; 20_000];
+	write(dir.join("one.bin"), &contents).unwrap();
+	let hashes = BTreeMap::from([(PathBuf::from("one.bin"), hash_file(Algorithm::BTv2, &dir.join("one.bin")))]);
+	let file = temp_dir().join("quickdash-torrent-v2.torrent");
+	let mut warnings = Vec::new();
+	write_torrent(&dir, &hashes, &BTreeMap::new(), 64 * 1024, &file, &mut warnings).unwrap();
+	assert!(warnings.is_empty());
+	let torrent = Torrent::load(&file).unwrap();
+
+	let (_, file_compare_results) = check_torrent(&torrent, &dir).unwrap();
+	assert_eq!(file_compare_results, [CompareFileResult::FileMatches(PathBuf::from("one.bin"))]);
+
+	contents[19_999] = b'b';
+	write(dir.join("one.bin"), &contents).unwrap();
+	let (_, file_compare_results) = check_torrent(&torrent, &dir).unwrap();
+	assert_eq!(file_compare_results, [CompareFileResult::PiecesFail { file: PathBuf::from("one.bin"), pieces: vec![0] }]);
+
+	remove_dir_all(&dir).unwrap();
+	remove_file(&file).unwrap();
+}
@@ -0,0 +1,37 @@
+use std::{
+	env::temp_dir,
+	fs::{create_dir_all, read_to_string, remove_dir_all, write},
+	process::{self, Command},
+};
+
+/// Run quickdash with `args`, getting whether it succeeded and what it printed.
+fn quickdash(args: &[&str]) -> (bool, String) {
+	let output = Command::new(env!("CARGO_BIN_EXE_quickdash")).args(args).output().unwrap();
+	(output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn tree_hash_matches_the_header_and_expect() {
+	let dir = temp_dir().join(format!("quickdash-tree-hash-{}", process::id()));
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("tree/sub")).unwrap();
+	write(dir.join("tree/a"), "a").unwrap();
+	write(dir.join("tree/sub/b"), "b").unwrap();
+	let (tree, file) = (dir.join("tree"), dir.join("tree.hash"));
+	let (tree, file) = (tree.to_str().unwrap(), file.to_str().unwrap());
+
+	assert!(quickdash(&["-a", "sha1", "create", tree, "--file", file, "--tree-hash"]).0);
+	let contents = read_to_string(file).unwrap();
+	let header = contents.lines().find_map(|line| line.split_once("tree hash: ")).unwrap().1.trim();
+	let (succeeded, printed) = quickdash(&["-a", "sha1", "tree-hash", tree]);
+	assert!(succeeded);
+	assert!(printed.trim().eq_ignore_ascii_case(header));
+
+	// Either case is expected alike
+	assert!(quickdash(&["-a", "sha1", "tree-hash", tree, "--expect", &header.to_lowercase()]).0);
+	assert!(quickdash(&["-a", "sha1", "tree-hash", tree, "--expect", &header.to_uppercase()]).0);
+	write(dir.join("tree/sub/b"), "changed").unwrap();
+	assert!(!quickdash(&["-a", "sha1", "tree-hash", tree, "--expect", header]).0);
+
+	remove_dir_all(&dir).unwrap();
+}
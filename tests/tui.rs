@@ -0,0 +1,54 @@
+use std::{
+	collections::BTreeMap,
+	env::temp_dir,
+	fs::{create_dir_all, remove_dir_all, write},
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{
+		Fix, HashingReport, LiveVerification, ReadOptions, Verdict, WalkOptions, WriteOptions, create_hashes, write_hashes,
+	},
+};
+
+#[test]
+fn live_verification_verdicts() {
+	let dir = temp_dir().join("quickdash-tui");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("data/sub")).unwrap();
+	write(dir.join("data/same"), "same").unwrap();
+	write(dir.join("data/sub/changed"), "before").unwrap();
+	write(dir.join("data/gone"), "gone").unwrap();
+	let options = WalkOptions { hash_threads: 2, ..Default::default() };
+	let hashes = create_hashes(&dir.join("data"), Algorithm::SHA1, &options, &mut HashingReport::default());
+	let file = dir.join("data.hash");
+	write_hashes(&file, hashes, &WriteOptions { algorithm: Algorithm::SHA1, ..Default::default() });
+	write(dir.join("data/sub/changed"), "after").unwrap();
+	write(dir.join("data/new"), "new").unwrap();
+	std::fs::remove_file(dir.join("data/gone")).unwrap();
+
+	let verification =
+		LiveVerification::start(&dir.join("data"), &file, Algorithm::SHA1, &options, &ReadOptions::default()).unwrap();
+	verification.wait();
+	let verdicts = verification.verdicts();
+	assert_eq!(verdicts[&PathBuf::from("same")], Verdict::Matches);
+	assert!(matches!(verdicts[&PathBuf::from("sub/changed")], Verdict::Differs { .. }));
+	assert_eq!(verdicts[&PathBuf::from("gone")], Verdict::Missing);
+	assert!(matches!(verdicts[&PathBuf::from("new")], Verdict::Added(_)));
+
+	assert!(verification.accept(&PathBuf::from("gone")));
+	assert!(!verification.accept(&PathBuf::from("same")));
+	assert_eq!(verification.fixes(), BTreeMap::from([(PathBuf::from("gone"), Fix::Delete)]));
+
+	write(dir.join("data/sub/changed"), "before").unwrap();
+	assert!(verification.rehash(&PathBuf::from("sub/changed")));
+	verification.wait();
+	assert_eq!(verification.verdicts()[&PathBuf::from("sub/changed")], Verdict::Matches);
+	let (compare_results, file_compare_results) = verification.outcome().unwrap();
+	assert_eq!(compare_results.len(), 1);
+	assert_eq!(file_compare_results.len(), 2);
+
+	drop(verification);
+	remove_dir_all(&dir).unwrap();
+}
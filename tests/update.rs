@@ -0,0 +1,93 @@
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	env::temp_dir,
+	ffi::OsString,
+	fs::{create_dir_all, remove_dir_all, remove_file, write},
+	path::PathBuf,
+};
+
+use quickdash::{
+	Algorithm,
+	operations::{Changed, ChangesPosition, HashingReport, Unchanged, UsnPosition, WalkOptions, changed_paths, create_hashes},
+};
+
+#[test]
+fn usn_header() {
+	let position = UsnPosition { journal: 0x01d2_d8e7_c8c3_a0b1, usn: 0x4a_1f28 };
+	assert_eq!(position.header(), "usn journal: 0x01d2d8e7c8c3a0b1 0x00000000004a1f28");
+	assert_eq!(UsnPosition::from_header(&position.header()), Some(position));
+	assert_eq!(UsnPosition::from_header("files: tracked by git"), None);
+}
+
+#[test]
+fn unchanged_files_keep_their_hash() {
+	let dir = temp_dir().join("quickdash-update");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("sub")).unwrap();
+	write(dir.join("kept.txt"), "kept").unwrap();
+	write(dir.join("sub").join("changed.txt"), "changed").unwrap();
+	write(dir.join("added.txt"), "added").unwrap();
+
+	let fresh = create_hashes(&dir, Algorithm::SHA2256, &WalkOptions::default(), &mut HashingReport::default());
+	let earlier = BTreeMap::from([
+		(PathBuf::from("kept.txt"), "earlier".to_owned()),
+		(PathBuf::from("sub").join("changed.txt"), "earlier".to_owned()),
+	]);
+	let changed = BTreeSet::from([OsString::from("changed.txt")]);
+	let options = WalkOptions { unchanged: Some(Unchanged::new(earlier, Changed::Names(changed))), ..Default::default() };
+	let hashes = create_hashes(&dir, Algorithm::SHA2256, &options, &mut HashingReport::default());
+	assert_eq!(hashes[&PathBuf::from("kept.txt")], "earlier");
+	assert_eq!(hashes[&PathBuf::from("sub").join("changed.txt")], fresh[&PathBuf::from("sub").join("changed.txt")]);
+	assert_eq!(hashes[&PathBuf::from("added.txt")], fresh[&PathBuf::from("added.txt")]);
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unchanged_paths_arent_walked() {
+	let dir = temp_dir().join("quickdash-update-paths");
+	let _ = remove_dir_all(&dir);
+	create_dir_all(dir.join("kept")).unwrap();
+	create_dir_all(dir.join("changed")).unwrap();
+	write(dir.join("kept").join("a.txt"), "kept").unwrap();
+	write(dir.join("changed").join("b.txt"), "changed").unwrap();
+
+	let fresh = create_hashes(&dir, Algorithm::SHA2256, &WalkOptions::default(), &mut HashingReport::default());
+	let earlier = BTreeMap::from([
+		(PathBuf::from("kept").join("a.txt"), "earlier".to_owned()),
+		(PathBuf::from("kept").join("gone.txt"), "earlier".to_owned()),
+		(PathBuf::from("changed").join("b.txt"), "earlier".to_owned()),
+		(PathBuf::from("changed").join("gone.txt"), "earlier".to_owned()),
+	]);
+	let changed = BTreeSet::from([PathBuf::from("changed")]);
+	let options = WalkOptions { unchanged: Some(Unchanged::new(earlier, Changed::Paths(changed))), ..Default::default() };
+	let hashes = create_hashes(&dir, Algorithm::SHA2256, &options, &mut HashingReport::default());
+	// Files outside changed paths are kept as they were, even if gone
+	assert_eq!(
+		hashes,
+		BTreeMap::from([
+			(PathBuf::from("kept").join("a.txt"), "earlier".to_owned()),
+			(PathBuf::from("kept").join("gone.txt"), "earlier".to_owned()),
+			(PathBuf::from("changed").join("b.txt"), fresh[&PathBuf::from("changed").join("b.txt")].clone()),
+		])
+	);
+
+	remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn recorded_changes() {
+	let position = ChangesPosition { started: 1_700_000_000_000_000_000, offset: 42 };
+	assert_eq!(position.header(), "changes journal: 1700000000000000000 42");
+	assert_eq!(ChangesPosition::from_header(&position.header()), Some(position));
+
+	let journal = temp_dir().join("quickdash-update.hash.changes");
+	let header = "# quickdash changes 1\n";
+	write(&journal, format!("{}a/b.txt\nc\\x20d\n*\n", header)).unwrap();
+	let at = |offset: usize| ChangesPosition { started: 1, offset: (header.len() + offset) as u64 };
+	assert_eq!(changed_paths(&journal, at(0), at(15)).unwrap(), Some(BTreeSet::from([PathBuf::from("a/b.txt"), PathBuf::from("c d")])));
+	// Lost changes, and other recorders, tell nothing
+	assert_eq!(changed_paths(&journal, at(0), at(17)).unwrap(), None);
+	assert_eq!(changed_paths(&journal, ChangesPosition { started: 2, ..at(0) }, at(16)).unwrap(), None);
+	remove_file(&journal).unwrap();
+}